@@ -0,0 +1,105 @@
+use anyhow::Result;
+use sqlparser::ast::{AssignmentTarget, Statement, TableFactor};
+use sqlparser::dialect::SQLiteDialect;
+use sqlparser::parser::Parser;
+
+use crate::database::quote_identifier;
+
+/// An UPDATE typed at the bulk edit prompt (`U`), previewed on the page before it is applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkUpdate {
+    pub assignments: Vec<(String, String)>, // column and the SQL expression of its new value
+    pub selection: Option<String>,          // the WHERE condition; every row without one
+}
+
+impl BulkUpdate {
+    /// Parse `column = expression[, ...] [WHERE condition]`, or a whole `UPDATE x SET ...`
+    /// of the current table, named `x` as at the query prompt or by its name
+    pub fn parse(input: &str, table: &str) -> Result<Self> {
+        let sql = if input.trim_start().get(..6).is_some_and(|word| word.eq_ignore_ascii_case("update")) {
+            input.to_string()
+        } else {
+            format!("UPDATE x SET {}", input)
+        };
+        let statements = Parser::parse_sql(&SQLiteDialect {}, &sql).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let [Statement::Update { table: target, assignments, from: None, selection, returning: None }] = statements.as_slice()
+        else {
+            return Err(anyhow::anyhow!("Expected column = expression [WHERE condition], or one UPDATE of this table"));
+        };
+
+        let named = match &target.relation {
+            TableFactor::Table { name, .. } => name.0.last().map(|ident| ident.value.clone()),
+            _ => None,
+        };
+        if !target.joins.is_empty() || !named.as_deref().is_some_and(|name| name == "x" || name == table) {
+            return Err(anyhow::anyhow!("The UPDATE has to be of this table ('x' or '{}')", table));
+        }
+
+        let assignments = assignments
+            .iter()
+            .map(|assignment| match &assignment.target {
+                AssignmentTarget::ColumnName(name) => match name.0.last() {
+                    Some(column) => Ok((column.value.clone(), assignment.value.to_string())),
+                    None => Err(anyhow::anyhow!("Missing a column name")),
+                },
+                AssignmentTarget::Tuple(_) => Err(anyhow::anyhow!("Assign the columns one by one")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { assignments, selection: selection.as_ref().map(|expr| expr.to_string()) })
+    }
+
+    /// The UPDATE over the whole of `table`, already quoted
+    pub fn to_sql(&self, table: &str) -> String {
+        let assignments: Vec<String> = self
+            .assignments
+            .iter()
+            .map(|(column, expr)| format!("{} = {}", quote_identifier(column), expr))
+            .collect();
+        let mut sql = format!("UPDATE {} SET {}", table, assignments.join(", "));
+        if let Some(selection) = &self.selection {
+            sql.push_str(&format!(" WHERE {}", selection));
+        }
+        sql
+    }
+
+    /// A SELECT of the rowid and new values of the rows of `table` with `rowids` that the
+    /// update changes, leaving the table as it is
+    pub fn preview_sql(&self, table: &str, rowids: &[i64]) -> String {
+        let values: Vec<&str> = self.assignments.iter().map(|(_, expr)| expr.as_str()).collect();
+        let rowids: Vec<String> = rowids.iter().map(|id| id.to_string()).collect();
+        let mut sql = format!("SELECT rowid, {} FROM {} WHERE rowid IN ({})", values.join(", "), table, rowids.join(", "));
+        if let Some(selection) = &self.selection {
+            sql.push_str(&format!(" AND ({})", selection));
+        }
+        sql
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bulk_update() {
+        let update = BulkUpdate::parse("price = price * 1.1, note = 'sale' WHERE qty > 5", "items").unwrap();
+        assert_eq!(
+            update.assignments,
+            vec![("price".to_string(), "price * 1.1".to_string()), ("note".to_string(), "'sale'".to_string())]
+        );
+        assert_eq!(update.selection.as_deref(), Some("qty > 5"));
+
+        let update = BulkUpdate::parse("update items set done = 1", "items").unwrap();
+        assert_eq!((update.assignments.len(), update.selection), (1, None));
+
+        assert!(BulkUpdate::parse("UPDATE other SET a = 1", "items").is_err());
+        assert!(BulkUpdate::parse("SELECT 1", "items").is_err());
+        assert!(BulkUpdate::parse("a = ", "items").is_err());
+    }
+
+    #[test]
+    fn test_bulk_update_sql() {
+        let update = BulkUpdate::parse("n = n + 1 WHERE n < 3", "t").unwrap();
+        assert_eq!(update.to_sql("\"t\""), "UPDATE \"t\" SET \"n\" = n + 1 WHERE n < 3");
+        assert_eq!(update.preview_sql("\"t\"", &[1, 2]), "SELECT rowid, n + 1 FROM \"t\" WHERE rowid IN (1, 2) AND (n < 3)");
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::Result;
+
+use crate::database::{quote_identifier, ColumnSchema, ForeignKey, IndexSchema, QueryResult, TableSchema};
+use crate::value::Value;
+
+/// A change to the columns of a table, made from the column manager (`C`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnEdit {
+    Add {
+        name: String,
+        column_type: String,     // as declared in SQLite; empty for none
+        default: Option<String>, // filling the column's cells
+    },
+    Rename { from: String, to: String },
+    Drop(String),
+}
+
+impl ColumnEdit {
+    /// Parse the add prompt, `name [type] [= default]`, e.g. `score INTEGER = 0`
+    pub fn parse_add(input: &str) -> Result<Self> {
+        let (definition, default) = match input.split_once('=') {
+            Some((definition, default)) => (definition, Some(unquote(default.trim()).to_string())),
+            None => (input, None),
+        };
+        let mut words = definition.split_whitespace();
+        let name = words.next().ok_or_else(|| anyhow::anyhow!("Missing the name of the new column"))?;
+        Ok(ColumnEdit::Add {
+            name: name.to_string(),
+            column_type: words.collect::<Vec<_>>().join(" "),
+            default,
+        })
+    }
+
+    /// What the change did, for the status line
+    pub fn describe(&self) -> String {
+        match self {
+            ColumnEdit::Add { name, .. } => format!("Added column '{}'", name),
+            ColumnEdit::Rename { from, to } => format!("Renamed column '{}' to '{}'", from, to),
+            ColumnEdit::Drop(name) => format!("Dropped column '{}'", name),
+        }
+    }
+
+    /// The name `column` has after the change; None once it's dropped
+    pub fn column_after(&self, column: &str) -> Option<String> {
+        match self {
+            ColumnEdit::Rename { from, to } if from == column => Some(to.clone()),
+            ColumnEdit::Drop(name) if name == column => None,
+            _ => Some(column.to_string()),
+        }
+    }
+
+    /// The ALTER TABLE statement making the change on `table`, already quoted
+    pub fn alter_sql(&self, table: &str) -> String {
+        match self {
+            ColumnEdit::Add { name, column_type, default } => {
+                let mut sql = format!("ALTER TABLE {} ADD COLUMN {}", table, quote_identifier(name));
+                if !column_type.is_empty() {
+                    sql.push_str(&format!(" {}", column_type));
+                }
+                if let Some(default) = default {
+                    sql.push_str(&format!(" DEFAULT {}", sql_literal(default)));
+                }
+                sql
+            }
+            ColumnEdit::Rename { from, to } => format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                table,
+                quote_identifier(from),
+                quote_identifier(to)
+            ),
+            ColumnEdit::Drop(name) => format!("ALTER TABLE {} DROP COLUMN {}", table, quote_identifier(name)),
+        }
+    }
+
+    /// Make the change on a table held in memory, as read from a file. New columns are
+    /// filled with the default read like a cell of the file, or left empty.
+    pub fn apply(&self, data: &mut QueryResult) -> Result<()> {
+        let position = |name: &str| {
+            data.columns
+                .iter()
+                .position(|c| c == name)
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", name))
+        };
+        match self {
+            ColumnEdit::Add { name, default, .. } => {
+                self.check_new_name(name, &data.columns)?;
+                let value = default.as_deref().map(Value::from).unwrap_or(Value::Null);
+                data.columns.push(name.clone());
+                for row in &mut data.rows {
+                    row.push(value.clone());
+                }
+            }
+            ColumnEdit::Rename { from, to } => {
+                let idx = position(from)?;
+                self.check_new_name(to, &data.columns)?;
+                data.columns[idx] = to.clone();
+            }
+            ColumnEdit::Drop(name) => {
+                let idx = position(name)?;
+                if data.columns.len() == 1 {
+                    return Err(anyhow::anyhow!("Can't drop the only column of the table"));
+                }
+                data.columns.remove(idx);
+                for row in &mut data.rows {
+                    if idx < row.len() {
+                        row.remove(idx);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_new_name(&self, name: &str, columns: &[String]) -> Result<()> {
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("The column needs a name"));
+        }
+        if columns.iter().any(|c| c.eq_ignore_ascii_case(name)) {
+            return Err(anyhow::anyhow!("There is already a column named '{}'", name));
+        }
+        Ok(())
+    }
+
+    /// The schema a table has after the change, for rebuilding it when ALTER TABLE can't
+    /// make it. Indexes and foreign keys on a dropped column go with it.
+    pub fn apply_to_schema(&self, schema: &TableSchema) -> TableSchema {
+        let rename = |columns: &[String]| -> Option<Vec<String>> {
+            columns.iter().map(|c| self.column_after(c)).collect()
+        };
+        let mut columns: Vec<ColumnSchema> = schema
+            .columns
+            .iter()
+            .filter_map(|column| {
+                self.column_after(&column.name).map(|name| ColumnSchema { name, ..column.clone() })
+            })
+            .collect();
+        if let ColumnEdit::Add { name, column_type, default } = self {
+            columns.push(ColumnSchema {
+                name: name.clone(),
+                column_type: column_type.clone(),
+                default: default.as_deref().map(sql_literal),
+                ..Default::default()
+            });
+        }
+        let indexes = schema
+            .indexes
+            .iter()
+            .filter_map(|index| Some(IndexSchema { columns: rename(&index.columns)?, ..index.clone() }))
+            .collect();
+        let foreign_keys = schema
+            .foreign_keys
+            .iter()
+            .filter_map(|key| Some(ForeignKey { columns: rename(&key.columns)?, ..key.clone() }))
+            .collect();
+        TableSchema { columns, indexes, foreign_keys, inferred: schema.inferred }
+    }
+}
+
+/// A default typed at the prompt as an SQL literal: numbers and NULL as they are, anything
+/// else quoted as text
+fn sql_literal(value: &str) -> String {
+    if value.eq_ignore_ascii_case("null") || value.parse::<f64>().is_ok_and(f64::is_finite) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('\'')
+        .and_then(|v| v.strip_suffix('\''))
+        .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    #[test]
+    fn test_parse_add() {
+        assert_eq!(
+            ColumnEdit::parse_add("score INTEGER = 0").unwrap(),
+            ColumnEdit::Add { name: "score".to_string(), column_type: "INTEGER".to_string(), default: Some("0".to_string()) }
+        );
+        assert_eq!(
+            ColumnEdit::parse_add("note = 'n/a'").unwrap(),
+            ColumnEdit::Add { name: "note".to_string(), column_type: String::new(), default: Some("n/a".to_string()) }
+        );
+        assert!(ColumnEdit::parse_add("  ").is_err());
+    }
+
+    #[test]
+    fn test_alter_sql() {
+        let add = ColumnEdit::parse_add("note TEXT = it's").unwrap();
+        assert_eq!(add.alter_sql("\"t\""), "ALTER TABLE \"t\" ADD COLUMN \"note\" TEXT DEFAULT 'it''s'");
+        let add = ColumnEdit::parse_add("n = 1.5").unwrap();
+        assert_eq!(add.alter_sql("\"t\""), "ALTER TABLE \"t\" ADD COLUMN \"n\" DEFAULT 1.5");
+        let rename = ColumnEdit::Rename { from: "a".to_string(), to: "b".to_string() };
+        assert_eq!(rename.alter_sql("\"t\""), "ALTER TABLE \"t\" RENAME COLUMN \"a\" TO \"b\"");
+        assert_eq!(ColumnEdit::Drop("a".to_string()).alter_sql("\"t\""), "ALTER TABLE \"t\" DROP COLUMN \"a\"");
+    }
+
+    #[test]
+    fn test_apply_to_rows() {
+        let mut data = QueryResult {
+            columns: vec!["a".to_string(), "b".to_string()],
+            rows: text_rows(vec![vec!["1", "x"], vec!["2", "y"]]),
+            total_rows: 2,
+        };
+        ColumnEdit::parse_add("c = 0").unwrap().apply(&mut data).unwrap();
+        assert_eq!(data.columns, vec!["a", "b", "c"]);
+        assert_eq!(data.rows[1], vec!["2", "y", "0"]);
+        assert!(ColumnEdit::parse_add("A").unwrap().apply(&mut data).is_err());
+
+        ColumnEdit::Rename { from: "b".to_string(), to: "name".to_string() }.apply(&mut data).unwrap();
+        ColumnEdit::Drop("a".to_string()).apply(&mut data).unwrap();
+        assert_eq!(data.columns, vec!["name", "c"]);
+        assert_eq!(data.rows[0], vec!["x", "0"]);
+        assert!(ColumnEdit::Drop("a".to_string()).apply(&mut data).is_err());
+    }
+
+    #[test]
+    fn test_apply_to_schema() {
+        let column = |name: &str| ColumnSchema { name: name.to_string(), column_type: "TEXT".to_string(), ..Default::default() };
+        let schema = TableSchema {
+            columns: vec![column("a"), column("b")],
+            indexes: vec![
+                IndexSchema { name: "ia".to_string(), columns: vec!["a".to_string()], unique: false, origin: "c".to_string() },
+                IndexSchema { name: "iab".to_string(), columns: vec!["a".to_string(), "b".to_string()], unique: true, origin: "u".to_string() },
+            ],
+            ..Default::default()
+        };
+        let dropped = ColumnEdit::Drop("b".to_string()).apply_to_schema(&schema);
+        assert_eq!(dropped.columns, vec![column("a")]);
+        assert_eq!(dropped.indexes.len(), 1);
+
+        let renamed = ColumnEdit::Rename { from: "a".to_string(), to: "z".to_string() }.apply_to_schema(&schema);
+        assert_eq!(renamed.columns[0].name, "z");
+        assert_eq!(renamed.indexes[1].columns, vec!["z", "b"]);
+    }
+}
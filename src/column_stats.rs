@@ -0,0 +1,338 @@
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::config::NumberLocale;
+use crate::format::format_number;
+use crate::value::Value;
+
+/// Most frequent values listed for text columns
+pub const TOP_VALUES: usize = 10;
+
+/// Bars of a histogram: numeric ranges, or the most frequent values of other columns
+pub const HISTOGRAM_BINS: usize = 20;
+
+/// Distinct values listed in the value counts popup, most frequent first
+pub const VALUE_COUNTS_LIMIT: usize = 1000;
+
+/// What to work out about a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SummaryKind {
+    Stats,     // the stats popup (`t`)
+    Histogram,   // the chart popup (`v`)
+    ValueCounts, // the value counts popup (`u`)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSummary {
+    Stats(ColumnStats),
+    Histogram(Histogram),
+    ValueCounts(ValueCounts),
+}
+
+/// Summary of one column, shown in the stats popup (`t`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    pub column: String,
+    pub rows: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub numeric: Option<NumericSummary>,  // when every non-null value is a number
+    pub top_values: Vec<(String, usize)>, // most frequent first, for other columns
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericSummary {
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64, // sample standard deviation; 0 for a single value
+}
+
+/// Counts of a column's values by range (numbers) or by value (anything else)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub column: String,
+    pub numeric: bool,
+    pub bars: Vec<(String, u64)>, // label and count, in range order or most frequent first
+}
+
+/// How often each value of a column occurs, NULL included
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueCounts {
+    pub column: String,
+    pub rows: usize,
+    pub distinct: usize,           // may be more than the values listed
+    pub values: Vec<(Value, u64)>, // most frequent first, at most VALUE_COUNTS_LIMIT
+}
+
+/// Equal-width ranges between a column's minimum and maximum. Integer columns with few
+/// distinct values get a bar per value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bins {
+    pub min: f64,
+    pub width: f64,
+    pub count: usize,
+    integers: bool,
+}
+
+impl Bins {
+    pub fn new(min: f64, max: f64, integers: bool) -> Self {
+        if integers && max - min < HISTOGRAM_BINS as f64 {
+            Bins { min, width: 1.0, count: (max - min) as usize + 1, integers }
+        } else if max > min {
+            Bins { min, width: (max - min) / HISTOGRAM_BINS as f64, count: HISTOGRAM_BINS, integers }
+        } else {
+            Bins { min, width: 1.0, count: 1, integers }
+        }
+    }
+
+    /// The bin a value falls in; the maximum goes in the last one
+    pub fn index(&self, value: f64) -> usize {
+        (((value - self.min) / self.width).max(0.0) as usize).min(self.count - 1)
+    }
+
+    fn label(&self, idx: usize) -> String {
+        let low = self.min + idx as f64 * self.width;
+        if self.integers && self.width == 1.0 {
+            return format_number(low);
+        }
+        // Enough decimals to tell neighbouring bins apart
+        let decimals = if self.width >= 1.0 { 0 } else { (-self.width.log10()).ceil() as usize };
+        format!("{:.*}..{:.*}", decimals, low, decimals, low + self.width)
+    }
+
+    /// Bars for counts indexed by bin
+    pub fn bars(&self, counts: &[u64]) -> Vec<(String, u64)> {
+        (0..self.count).map(|idx| (self.label(idx), counts.get(idx).copied().unwrap_or(0))).collect()
+    }
+}
+
+/// Whether a cell counts as missing: NULL, or an empty cell of a file
+pub fn is_missing(cell: &Value) -> bool {
+    match cell {
+        Value::Null => true,
+        Value::Text(text) => text.is_empty() || text == "NULL",
+        _ => false,
+    }
+}
+
+/// Stats of a column held in memory. Numbers stored as text are read in `locale`.
+pub fn column_stats<'a>(column: &str, cells: impl Iterator<Item = &'a Value>, locale: NumberLocale) -> ColumnStats {
+    let mut rows = 0;
+    let mut present: Vec<&Value> = Vec::new();
+    for cell in cells {
+        rows += 1;
+        if !is_missing(cell) {
+            present.push(cell);
+        }
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for cell in &present {
+        *counts.entry(cell.to_string()).or_insert(0) += 1;
+    }
+
+    let numbers: Option<Vec<(f64, &Value)>> =
+        present.iter().map(|cell| cell.as_number(locale).map(|n| (n, *cell))).collect();
+    let (min, max, numeric, top_values) = match numbers.filter(|numbers| !numbers.is_empty()) {
+        Some(mut numbers) => {
+            numbers.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+            let values: Vec<f64> = numbers.iter().map(|(n, _)| *n).collect();
+            (
+                numbers.first().map(|(_, cell)| cell.to_string()),
+                numbers.last().map(|(_, cell)| cell.to_string()),
+                Some(numeric_summary(&values)),
+                Vec::new(),
+            )
+        }
+        None => {
+            let min = present.iter().min_by(|a, b| a.compare(b)).map(|cell| cell.to_string());
+            let max = present.iter().max_by(|a, b| a.compare(b)).map(|cell| cell.to_string());
+            (min, max, None, top_counts(counts.clone(), TOP_VALUES))
+        }
+    };
+
+    ColumnStats {
+        column: column.to_string(),
+        rows,
+        nulls: rows - present.len(),
+        distinct: counts.len(),
+        min,
+        max,
+        numeric,
+        top_values,
+    }
+}
+
+/// Mean, median and standard deviation of values sorted in ascending order
+fn numeric_summary(sorted: &[f64]) -> NumericSummary {
+    let n = sorted.len();
+    let mean = sorted.iter().sum::<f64>() / n as f64;
+    let median = if n % 2 == 1 {
+        sorted[n / 2]
+    } else {
+        (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+    };
+    let stddev = if n > 1 {
+        (sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    NumericSummary { mean, median, stddev }
+}
+
+/// Histogram of a column held in memory; missing cells are left out
+pub fn histogram<'a>(column: &str, cells: impl Iterator<Item = &'a Value>, locale: NumberLocale) -> Histogram {
+    let present: Vec<&Value> = cells.filter(|cell| !is_missing(cell)).collect();
+    let numbers: Option<Vec<f64>> = present.iter().map(|cell| cell.as_number(locale)).collect();
+    if let Some(numbers) = numbers.filter(|numbers| !numbers.is_empty()) {
+        let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let bins = Bins::new(min, max, numbers.iter().all(|n| n.fract() == 0.0));
+        let mut counts = vec![0; bins.count];
+        for number in numbers {
+            counts[bins.index(number)] += 1;
+        }
+        return Histogram { column: column.to_string(), numeric: true, bars: bins.bars(&counts) };
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for cell in present {
+        *counts.entry(cell.to_string()).or_insert(0) += 1;
+    }
+    Histogram {
+        column: column.to_string(),
+        numeric: false,
+        bars: top_counts(counts, HISTOGRAM_BINS).into_iter().map(|(value, count)| (value, count as u64)).collect(),
+    }
+}
+
+/// Value counts of a column held in memory. Missing cells, empty ones included, count as
+/// NULL, like the `=NULL` filter.
+pub fn value_counts<'a>(column: &str, cells: impl Iterator<Item = &'a Value>) -> ValueCounts {
+    let mut rows = 0;
+    let mut counts: HashMap<Option<String>, usize> = HashMap::new();
+    for cell in cells {
+        rows += 1;
+        let key = (!is_missing(cell)).then(|| cell.to_string());
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    let distinct = counts.len();
+    let values = top_counts(counts, VALUE_COUNTS_LIMIT)
+        .into_iter()
+        .map(|(value, count)| (value.map_or(Value::Null, Value::Text), count as u64))
+        .collect();
+    ValueCounts { column: column.to_string(), rows, distinct, values }
+}
+
+/// Summary of a column held in memory
+pub fn summarize<'a>(
+    column: &str,
+    cells: impl Iterator<Item = &'a Value>,
+    kind: SummaryKind,
+    locale: NumberLocale,
+) -> ColumnSummary {
+    match kind {
+        SummaryKind::Stats => ColumnSummary::Stats(column_stats(column, cells, locale)),
+        SummaryKind::Histogram => ColumnSummary::Histogram(histogram(column, cells, locale)),
+        SummaryKind::ValueCounts => ColumnSummary::ValueCounts(value_counts(column, cells)),
+    }
+}
+
+/// `func` (sum, mean, count, min or max) over the numbers of a column held in memory, as a
+/// computed column's aggregate; cells that aren't numbers are left out, and 0 is given when
+/// none is
+pub fn aggregate<'a>(func: &str, cells: impl Iterator<Item = &'a Value>, locale: NumberLocale) -> Result<f64> {
+    let values: Vec<f64> = cells.filter_map(|cell| cell.as_number(locale)).collect();
+    if values.is_empty() {
+        return Ok(0.0);
+    }
+    Ok(match func {
+        "sum" => values.iter().sum::<f64>(),
+        "mean" => values.iter().sum::<f64>() / values.len() as f64,
+        "count" => values.len() as f64,
+        "min" => values.iter().fold(f64::INFINITY, |a, &b| a.min(b)),
+        "max" => values.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
+        _ => return Err(anyhow::anyhow!("Unknown function: {}", func)),
+    })
+}
+
+// Ties are listed in value order so the list doesn't change between runs
+fn top_counts<K: Ord>(counts: HashMap<K, usize>, limit: usize) -> Vec<(K, usize)> {
+    let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    #[test]
+    fn test_numeric_column_stats() {
+        let cells = [Value::Int(4), Value::Null, Value::Float(1.5), Value::Int(4), Value::from("10")];
+        let stats = column_stats("n", cells.iter(), NumberLocale::En);
+        assert_eq!((stats.rows, stats.nulls, stats.distinct), (5, 1, 3));
+        assert_eq!((stats.min.as_deref(), stats.max.as_deref()), (Some("1.5"), Some("10")));
+        let numeric = stats.numeric.unwrap();
+        assert_eq!((numeric.mean, numeric.median), (4.875, 4.0));
+        assert!((numeric.stddev - 3.6142).abs() < 1e-3);
+        assert!(stats.top_values.is_empty());
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let cells = [Value::Int(4), Value::Null, Value::Float(1.5), Value::from("x"), Value::from("10")];
+        let aggregate = |func: &str| aggregate(func, cells.iter(), NumberLocale::En).unwrap();
+        assert_eq!((aggregate("sum"), aggregate("mean"), aggregate("count")), (15.5, 15.5 / 3.0, 3.0));
+        assert_eq!((aggregate("min"), aggregate("max")), (1.5, 10.0));
+        assert_eq!(super::aggregate("sum", [Value::Null].iter(), NumberLocale::En).unwrap(), 0.0);
+        assert!(super::aggregate("median", cells.iter(), NumberLocale::En).is_err());
+    }
+
+    #[test]
+    fn test_text_column_stats() {
+        let rows = text_rows(vec![vec!["b"], vec!["a"], vec![""], vec!["b"], vec!["c"]]);
+        let stats = column_stats("s", rows.iter().map(|row| &row[0]), NumberLocale::En);
+        assert_eq!((stats.rows, stats.nulls, stats.distinct), (5, 1, 3));
+        assert_eq!((stats.min.as_deref(), stats.max.as_deref()), (Some("a"), Some("c")));
+        assert!(stats.numeric.is_none());
+        assert_eq!(stats.top_values[0], ("b".to_string(), 2));
+        assert_eq!(stats.top_values[1], ("a".to_string(), 1));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let cells: Vec<Value> = (1..=5).map(Value::Int).chain([Value::Int(5), Value::Null]).collect();
+        let chart = histogram("n", cells.iter(), NumberLocale::En);
+        assert!(chart.numeric);
+        assert_eq!(chart.bars.len(), 5);
+        assert_eq!(chart.bars[4], ("5".to_string(), 2));
+
+        let cells: Vec<Value> = (0..=100).map(|n| Value::Float(n as f64 / 10.0)).collect();
+        let chart = histogram("x", cells.iter(), NumberLocale::En);
+        assert_eq!(chart.bars.len(), HISTOGRAM_BINS);
+        assert_eq!(chart.bars[0].0, "0.0..0.5");
+        assert_eq!(chart.bars.iter().map(|(_, count)| count).sum::<u64>(), 101);
+
+        let cells = [Value::from("b"), Value::from("a"), Value::from("b")];
+        let chart = histogram("s", cells.iter(), NumberLocale::En);
+        assert!(!chart.numeric);
+        assert_eq!(chart.bars, vec![("b".to_string(), 2), ("a".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_value_counts() {
+        let cells = [Value::from("b"), Value::Null, Value::from("a"), Value::from(""), Value::Int(3), Value::from("b")];
+        let counts = value_counts("s", cells.iter());
+        assert_eq!((counts.rows, counts.distinct), (6, 4));
+        assert_eq!(
+            counts.values,
+            vec![(Value::Null, 2), (Value::from("b"), 2), (Value::from("3"), 1), (Value::from("a"), 1)]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::path::PathBuf;
+
+use crate::config::expand_home;
+use crate::database::quote_identifier;
+
+/// Table and column names completed with Tab in the query prompt
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    pub candidates: Vec<String>, // names matching the word being completed
+    pub selected: Option<usize>, // candidate currently inserted, once Tab cycles through them
+    pub start: usize,            // byte offset in the query where the completed word starts
+    pub quoted: bool,            // the word was started with a double quote
+}
+
+impl Completion {
+    /// Complete the word at the end of `input` from `names`. A single match is inserted
+    /// right away; with several, the common prefix is inserted and the matches are kept to
+    /// cycle through. Returns None when nothing matches.
+    pub fn start(input: &mut String, names: &[String]) -> Option<Self> {
+        let start = word_start(input);
+        let word = &input[start..];
+        let quoted = word.starts_with('"');
+        let typed = word.trim_start_matches('"').to_lowercase();
+
+        let mut candidates: Vec<String> = Vec::new();
+        for name in names {
+            if name.to_lowercase().starts_with(&typed) && !candidates.contains(name) {
+                candidates.push(name.clone());
+            }
+        }
+        match candidates.len() {
+            0 => None,
+            1 => {
+                input.truncate(start);
+                input.push_str(&insertion(&candidates[0], quoted));
+                None
+            }
+            _ => {
+                let prefix = common_prefix(&candidates);
+                // Extend only when the prefix adds to what was typed; quoting waits for a choice
+                if prefix.len() > typed.len() && is_plain_identifier(&prefix) {
+                    input.truncate(start);
+                    input.push_str(if quoted { "\"" } else { "" });
+                    input.push_str(&prefix);
+                }
+                Some(Self { candidates, selected: None, start, quoted })
+            }
+        }
+    }
+
+    /// Insert the next candidate in place of the previous one
+    pub fn cycle(&mut self, input: &mut String) {
+        let next = self.selected.map_or(0, |i| (i + 1) % self.candidates.len());
+        self.selected = Some(next);
+        input.truncate(self.start);
+        input.push_str(&insertion(&self.candidates[next], self.quoted));
+    }
+}
+
+/// Complete the file name at the end of `input`, a path typed in the export prompt, with
+/// the longest prefix shared by the entries of its directory that start with it; a
+/// directory gets a trailing `/`. Hidden entries are offered once a `.` is typed. Returns
+/// the matching names, to show when there are several.
+pub fn complete_path(input: &mut String) -> Vec<String> {
+    let (directory, typed) = match input.rfind('/') {
+        Some(idx) => (input[..=idx].to_string(), input[idx + 1..].to_string()),
+        None => (String::new(), input.clone()),
+    };
+    let listed = if directory.is_empty() { PathBuf::from(".") } else { expand_home(&directory) };
+    let Ok(entries) = std::fs::read_dir(listed) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let mut name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with(&typed) || (name.starts_with('.') && !typed.starts_with('.')) {
+                return None;
+            }
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            Some(name)
+        })
+        .collect();
+    matches.sort();
+
+    if let Some(first) = matches.first() {
+        let len = matches[1..].iter().fold(first.len(), |len, name| {
+            first
+                .char_indices()
+                .zip(name.chars())
+                .take_while(|((_, a), b)| a == b)
+                .map(|((i, a), _)| i + a.len_utf8())
+                .last()
+                .unwrap_or(0)
+                .min(len)
+        });
+        *input = format!("{}{}", directory, &first[..len]);
+    }
+    matches
+}
+
+/// Byte offset where the identifier being typed at the end of the input begins, including
+/// an opening double quote
+fn word_start(input: &str) -> usize {
+    let start = input
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || (*c == ' ' && in_quotes(input)))
+        .last()
+        .map_or(input.len(), |(i, _)| i);
+    if input[..start].ends_with('"') {
+        start - 1
+    } else {
+        start
+    }
+}
+
+/// Whether the input ends inside an open double-quoted identifier
+fn in_quotes(input: &str) -> bool {
+    input.matches('"').count() % 2 == 1
+}
+
+/// Longest case-insensitive common prefix, as spelled by the first name
+fn common_prefix(names: &[String]) -> String {
+    let first = &names[0];
+    let mut len = first.len();
+    for name in &names[1..] {
+        len = first
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|((_, a), b)| a.to_lowercase().eq(b.to_lowercase()))
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+fn is_plain_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Names that aren't plain identifiers, or were started with a quote, are inserted quoted
+fn insertion(name: &str, quoted: bool) -> String {
+    if quoted || !is_plain_identifier(name) {
+        quote_identifier(name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("reports")).unwrap();
+        std::fs::write(dir.path().join("report_2024.csv"), "").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        let base = format!("{}/", dir.path().display());
+
+        let mut input = format!("{}rep", base);
+        assert_eq!(complete_path(&mut input), names(&["report_2024.csv", "reports/"]));
+        assert_eq!(input, format!("{}report", base));
+
+        let mut input = format!("{}reports/", base);
+        assert!(complete_path(&mut input).is_empty());
+
+        let mut input = format!("{}report_", base);
+        complete_path(&mut input);
+        assert_eq!(input, format!("{}report_2024.csv", base));
+
+        let mut input = base.clone();
+        assert_eq!(complete_path(&mut input).len(), 2);
+        let mut input = format!("{}.h", base);
+        complete_path(&mut input);
+        assert_eq!(input, format!("{}.hidden", base));
+    }
+
+    #[test]
+    fn test_single_match_is_inserted() {
+        let mut input = "SELECT cust".to_string();
+        assert!(Completion::start(&mut input, &names(&["customers", "orders"])).is_none());
+        assert_eq!(input, "SELECT customers");
+
+        let mut input = "SELECT * FROM x WHERE first".to_string();
+        Completion::start(&mut input, &names(&["first name", "age"]));
+        assert_eq!(input, "SELECT * FROM x WHERE \"first name\"");
+
+        let mut input = "SELECT \"Ag".to_string();
+        Completion::start(&mut input, &names(&["age"]));
+        assert_eq!(input, "SELECT \"age\"");
+
+        let mut input = "SELECT zz".to_string();
+        assert!(Completion::start(&mut input, &names(&["age"])).is_none());
+        assert_eq!(input, "SELECT zz");
+    }
+
+    #[test]
+    fn test_several_matches_cycle() {
+        let mut input = "SELECT or".to_string();
+        let all = names(&["order_id", "order_date", "Order Items", "amount"]);
+        let mut completion = Completion::start(&mut input, &all).unwrap();
+        assert_eq!(completion.candidates, names(&["order_id", "order_date", "Order Items"]));
+        assert_eq!(input, "SELECT order");
+
+        completion.cycle(&mut input);
+        assert_eq!(input, "SELECT order_id");
+        completion.cycle(&mut input);
+        assert_eq!(input, "SELECT order_date");
+        completion.cycle(&mut input);
+        assert_eq!(input, "SELECT \"Order Items\"");
+        completion.cycle(&mut input);
+        assert_eq!(input, "SELECT order_id");
+    }
+
+    #[test]
+    fn test_word_start() {
+        assert_eq!(word_start("SELECT a"), 7);
+        assert_eq!(word_start("SELECT "), 7);
+        assert_eq!(word_start("x.col"), 2);
+        assert_eq!(word_start("WHERE \"first na"), 6);
+        assert_eq!(common_prefix(&names(&["Order", "ORDERS"])), "Order");
+    }
+}
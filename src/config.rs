@@ -1,11 +1,15 @@
 use anyhow::{Context, Result};
 use ratatui::style::Color;
+use ratatui::symbols::border;
+use std::borrow::Cow;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::keymap::KeymapPreset;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ColorConfig {
     pub border: String,
     pub text: String,
@@ -39,7 +43,268 @@ pub struct ColorConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub colors: ColorConfig,
+    #[serde(default)]
+    pub theme: ThemePreset,
+    #[serde(default)]
+    pub colors: ColorConfig, // colors changed from the defaults replace the theme's
+    #[serde(default)]
+    pub color_mode: ColorMode,
+    #[serde(default)]
+    pub ascii: bool, // plain ASCII borders and symbols for legacy terminals
+    #[serde(default)]
+    pub page_size: usize, // rows per page; 0 fits a page to the table area
+    #[serde(default)]
+    pub keymap: KeymapPreset,
+    #[serde(default)]
+    pub format: FormatConfig,
+    #[serde(default)]
+    pub csv: CsvSettings,
+    #[serde(default)]
+    pub xlsx: XlsxSettings,
+    #[serde(default)]
+    pub sqlite: SqliteSettings,
+    #[serde(default)]
+    pub parquet: ParquetSettings,
+    #[serde(default)]
+    pub export: ExportSettings,
+    #[serde(default = "default_external_tools")]
+    pub external_tools: Vec<ExternalTool>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: ThemePreset::default(),
+            colors: ColorConfig::default(),
+            color_mode: ColorMode::default(),
+            ascii: false,
+            page_size: 0,
+            keymap: KeymapPreset::default(),
+            format: FormatConfig::default(),
+            csv: CsvSettings::default(),
+            xlsx: XlsxSettings::default(),
+            sqlite: SqliteSettings::default(),
+            parquet: ParquetSettings::default(),
+            export: ExportSettings::default(),
+            external_tools: default_external_tools(),
+        }
+    }
+}
+
+/// A program the open file can be handed to, for things sqbrowser can't do itself.
+/// `{file}` and `{table}` in the command are replaced by the shell-quoted file path and table.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalTool {
+    pub name: String,
+    pub command: String,
+    pub file_types: Vec<String>, // "sqlite", "csv", "xlsx", "parquet", "json", "arrow", "dbf", "dta", "sav", "sas7bdat", "html", "duckdb", "libsql"; empty means any
+}
+
+impl ExternalTool {
+    pub fn applies_to(&self, file_type: &str) -> bool {
+        !self.command.trim().is_empty()
+            && (self.file_types.is_empty()
+                || self.file_types.iter().any(|t| t.eq_ignore_ascii_case(file_type)))
+    }
+}
+
+fn default_external_tools() -> Vec<ExternalTool> {
+    vec![
+        ExternalTool {
+            name: "sqlite3".to_string(),
+            command: "sqlite3 {file}".to_string(),
+            file_types: vec!["sqlite".to_string()],
+        },
+        ExternalTool {
+            name: "Editor".to_string(),
+            command: "${EDITOR:-vi} {file}".to_string(),
+            file_types: vec!["csv".to_string()],
+        },
+        ExternalTool {
+            name: "VisiData".to_string(),
+            command: "vd {file}".to_string(),
+            file_types: Vec::new(),
+        },
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvSettings {
+    pub delimiter: String, // single character, e.g. ";" or "\t"
+    pub has_header: bool,
+    pub stream_above_mb: u64, // larger files are read a page at a time rather than whole
+    pub keep_index: bool,     // the index of a streamed file is kept, so it opens at once next time
+}
+
+impl Default for CsvSettings {
+    fn default() -> Self {
+        Self {
+            delimiter: ",".to_string(),
+            has_header: true,
+            stream_above_mb: 64,
+            keep_index: true,
+        }
+    }
+}
+
+impl CsvSettings {
+    pub fn delimiter_byte(&self) -> u8 {
+        match self.delimiter.as_bytes() {
+            [byte] => *byte,
+            _ => b',',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct XlsxSettings {
+    pub header_row: usize, // 0-based row holding the column names; rows above it are skipped
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SqliteSettings {
+    pub readonly: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParquetSettings {
+    pub batch_size: usize, // rows decoded per record batch
+}
+
+impl Default for ParquetSettings {
+    fn default() -> Self {
+        Self { batch_size: 8192 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub directory: String, // where exports are written by default; empty means the working directory
+}
+
+impl ExportSettings {
+    /// Default path of an exported file named `name`
+    pub fn path_for(&self, name: &str) -> PathBuf {
+        match self.directory.trim() {
+            "" => PathBuf::from(name),
+            directory => expand_home(directory).join(name),
+        }
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(format!("{}{}", home, rest))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Display formatting for numeric and date cells
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    pub decimal_places: Option<usize>, // None shows numbers as stored
+    pub thousands_separator: String,
+    pub decimal_separator: String,
+    pub date_format: Option<String>,     // chrono format string, e.g. "%d/%m/%Y"
+    pub datetime_format: Option<String>, // chrono format string, e.g. "%d/%m/%Y %H:%M"
+    pub humanize: bool, // show epoch timestamps as ISO datetimes and UUID blobs as UUIDs
+    pub number_locale: NumberLocale, // how numbers in text cells are written, for parsing
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            decimal_places: None,
+            thousands_separator: String::new(),
+            decimal_separator: ".".to_string(),
+            date_format: None,
+            datetime_format: None,
+            humanize: false,
+            number_locale: NumberLocale::default(),
+        }
+    }
+}
+
+/// Separators used by numbers stored as text, e.g. `De` reads "1.234,56" as 1234.56
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberLocale {
+    #[default]
+    En, // 1,234.56
+    De, // 1.234,56
+    Fr, // 1 234,56
+    Ch, // 1'234.56
+}
+
+impl NumberLocale {
+    pub fn decimal_separator(self) -> char {
+        match self {
+            NumberLocale::En | NumberLocale::Ch => '.',
+            NumberLocale::De | NumberLocale::Fr => ',',
+        }
+    }
+
+    /// Characters accepted between digit groups (French uses plain or non-breaking spaces)
+    pub fn group_separators(self) -> &'static [char] {
+        match self {
+            NumberLocale::En => &[','],
+            NumberLocale::De => &['.'],
+            NumberLocale::Fr => &[' ', '\u{a0}', '\u{202f}'],
+            NumberLocale::Ch => &['\''],
+        }
+    }
+}
+
+/// How many colors the terminal can display. `Auto` inspects `COLORTERM`/`TERM`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Truecolor,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
+impl ColorMode {
+    /// Resolve `Auto` into a concrete mode based on the terminal environment
+    pub fn resolve(self) -> ColorMode {
+        match self {
+            ColorMode::Auto => detect_color_mode(
+                std::env::var("COLORTERM").ok().as_deref(),
+                std::env::var("TERM").ok().as_deref(),
+            ),
+            other => other,
+        }
+    }
+}
+
+fn detect_color_mode(colorterm: Option<&str>, term: Option<&str>) -> ColorMode {
+    let colorterm = colorterm.unwrap_or("").to_lowercase();
+    if colorterm.contains("truecolor") || colorterm.contains("24bit") {
+        return ColorMode::Truecolor;
+    }
+
+    let term = term.unwrap_or("").to_lowercase();
+    if term.contains("direct") || term.contains("truecolor") {
+        ColorMode::Truecolor
+    } else if term.contains("256") {
+        ColorMode::Ansi256
+    } else {
+        ColorMode::Ansi16
+    }
 }
 
 impl Default for ColorConfig {
@@ -77,10 +342,163 @@ impl Default for ColorConfig {
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            colors: ColorConfig::default(),
+impl ColorConfig {
+    /// The colors of a theme preset, with the ones set here in its place. A color still at
+    /// its default, as written to a new config file, is the preset's.
+    pub fn over(&self, preset: ThemePreset) -> ColorConfig {
+        let (Ok(serde_json::Value::Object(colors)), Ok(serde_json::Value::Object(defaults)), Ok(mut merged)) = (
+            serde_json::to_value(self),
+            serde_json::to_value(ColorConfig::default()),
+            serde_json::to_value(preset.colors()),
+        ) else {
+            return self.clone();
+        };
+        for (key, color) in colors {
+            let default = defaults.get(&key).and_then(|d| d.as_str());
+            if !default.zip(color.as_str()).is_some_and(|(d, c)| d.eq_ignore_ascii_case(c)) {
+                merged[key] = color;
+            }
+        }
+        serde_json::from_value(merged).unwrap_or_else(|_| self.clone())
+    }
+}
+
+/// A built-in set of colors, chosen with `"theme"` in the config or `--theme`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    Solarized,
+    Gruvbox,
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub fn colors(self) -> ColorConfig {
+        match self {
+            ThemePreset::Dark => ColorConfig::default(),
+            ThemePreset::Light => ColorConfig {
+                border: "#acb0beff".to_string(),
+                text: "#4c4f69ff".to_string(),
+                number: "#fe640bff".to_string(),
+                selected_border: "#8839efff".to_string(),
+                selected_text: "#dce0e8ff".to_string(),
+                selected_bg: "#8839efff".to_string(),
+                edit_border: "#1e66f5ff".to_string(),
+                edit_text: "#4c4f69ff".to_string(),
+                edit_bg: "#1e66f5ff".to_string(),
+                header: "#40a02bff".to_string(),
+                status: "#179299ff".to_string(),
+                error: "#d20f39ff".to_string(),
+                help: "#5c5f77ff".to_string(),
+                help_bg: "#e6e9efff".to_string(),
+                help_title: "#df8e1dff".to_string(),
+                help_section_header: "#ea76cbff".to_string(),
+                help_key: "#8839efff".to_string(),
+                help_description: "#4c4f69ff".to_string(),
+                column_header: "#dc8a78ff".to_string(),
+                query_bg: "#ccd0daff".to_string(),
+                query_text: "#4c4f69ff".to_string(),
+                query_border: "#1e66f5ff".to_string(),
+                edit_area_bg: "#bcc0ccff".to_string(),
+                detailed_view_bg: "#eff1f5ff".to_string(),
+                detailed_view_border: "#df8e1dff".to_string(),
+                detailed_view_title: "#df8e1dff".to_string(),
+                detailed_view_field: "#1e66f5ff".to_string(),
+                detailed_view_value: "#4c4f69ff".to_string(),
+            },
+            ThemePreset::Solarized => ColorConfig {
+                border: "#586e75ff".to_string(),
+                text: "#839496ff".to_string(),
+                number: "#2aa198ff".to_string(),
+                selected_border: "#b58900ff".to_string(),
+                selected_text: "#002b36ff".to_string(),
+                selected_bg: "#268bd2ff".to_string(),
+                edit_border: "#dc322fff".to_string(),
+                edit_text: "#002b36ff".to_string(),
+                edit_bg: "#b58900ff".to_string(),
+                header: "#859900ff".to_string(),
+                status: "#859900ff".to_string(),
+                error: "#dc322fff".to_string(),
+                help: "#6c71c4ff".to_string(),
+                help_bg: "#002b36ff".to_string(),
+                help_title: "#cb4b16ff".to_string(),
+                help_section_header: "#859900ff".to_string(),
+                help_key: "#268bd2ff".to_string(),
+                help_description: "#93a1a1ff".to_string(),
+                column_header: "#d33682ff".to_string(),
+                query_bg: "#073642ff".to_string(),
+                query_text: "#93a1a1ff".to_string(),
+                query_border: "#268bd2ff".to_string(),
+                edit_area_bg: "#eee8d5ff".to_string(),
+                detailed_view_bg: "#002b36ff".to_string(),
+                detailed_view_border: "#b58900ff".to_string(),
+                detailed_view_title: "#b58900ff".to_string(),
+                detailed_view_field: "#268bd2ff".to_string(),
+                detailed_view_value: "#93a1a1ff".to_string(),
+            },
+            ThemePreset::Gruvbox => ColorConfig {
+                border: "#665c54ff".to_string(),
+                text: "#ebdbb2ff".to_string(),
+                number: "#83a598ff".to_string(),
+                selected_border: "#fabd2fff".to_string(),
+                selected_text: "#282828ff".to_string(),
+                selected_bg: "#8ec07cff".to_string(),
+                edit_border: "#fb4934ff".to_string(),
+                edit_text: "#282828ff".to_string(),
+                edit_bg: "#fabd2fff".to_string(),
+                header: "#b8bb26ff".to_string(),
+                status: "#b8bb26ff".to_string(),
+                error: "#fb4934ff".to_string(),
+                help: "#d3869bff".to_string(),
+                help_bg: "#282828ff".to_string(),
+                help_title: "#fe8019ff".to_string(),
+                help_section_header: "#b8bb26ff".to_string(),
+                help_key: "#83a598ff".to_string(),
+                help_description: "#ebdbb2ff".to_string(),
+                column_header: "#d3869bff".to_string(),
+                query_bg: "#3c3836ff".to_string(),
+                query_text: "#ebdbb2ff".to_string(),
+                query_border: "#83a598ff".to_string(),
+                edit_area_bg: "#fbf1c7ff".to_string(),
+                detailed_view_bg: "#282828ff".to_string(),
+                detailed_view_border: "#fabd2fff".to_string(),
+                detailed_view_title: "#fabd2fff".to_string(),
+                detailed_view_field: "#83a598ff".to_string(),
+                detailed_view_value: "#ebdbb2ff".to_string(),
+            },
+            ThemePreset::HighContrast => ColorConfig {
+                border: "#ffffffff".to_string(),
+                text: "#ffffffff".to_string(),
+                number: "#00ffffff".to_string(),
+                selected_border: "#ffff00ff".to_string(),
+                selected_text: "#000000ff".to_string(),
+                selected_bg: "#ffff00ff".to_string(),
+                edit_border: "#ff0000ff".to_string(),
+                edit_text: "#000000ff".to_string(),
+                edit_bg: "#00ffffff".to_string(),
+                header: "#00ff00ff".to_string(),
+                status: "#00ff00ff".to_string(),
+                error: "#ff5555ff".to_string(),
+                help: "#ffffffff".to_string(),
+                help_bg: "#000000ff".to_string(),
+                help_title: "#ffff00ff".to_string(),
+                help_section_header: "#00ff00ff".to_string(),
+                help_key: "#00ffffff".to_string(),
+                help_description: "#ffffffff".to_string(),
+                column_header: "#ffff00ff".to_string(),
+                query_bg: "#000000ff".to_string(),
+                query_text: "#ffffffff".to_string(),
+                query_border: "#ffff00ff".to_string(),
+                edit_area_bg: "#ffffffff".to_string(),
+                detailed_view_bg: "#000000ff".to_string(),
+                detailed_view_border: "#ffff00ff".to_string(),
+                detailed_view_title: "#ffff00ff".to_string(),
+                detailed_view_field: "#00ffffff".to_string(),
+                detailed_view_value: "#ffffffff".to_string(),
+            },
         }
     }
 }
@@ -114,10 +532,62 @@ pub struct Theme {
     pub detailed_view_title: Color,
     pub detailed_view_field: Color,
     pub detailed_view_value: Color,
+    pub ascii: bool,
+}
+
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+impl Theme {
+    pub fn border_set(&self) -> border::Set {
+        if self.ascii {
+            ASCII_BORDER
+        } else {
+            border::PLAIN
+        }
+    }
+
+    /// Replace arrows and markers with ASCII equivalents when ASCII mode is on
+    pub fn text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.ascii || text.is_ascii() {
+            return Cow::Borrowed(text);
+        }
+        Cow::Owned(to_ascii_symbols(text))
+    }
 }
 
-impl From<&ColorConfig> for Theme {
-    fn from(config: &ColorConfig) -> Self {
+fn to_ascii_symbols(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '↑' => "^".to_string(),
+            '↓' => "v".to_string(),
+            '←' => "<".to_string(),
+            '→' | '▶' | '▸' => ">".to_string(),
+            '▾' => "v".to_string(),
+            '…' => "...".to_string(),
+            '│' => "|".to_string(),
+            '─' => "-".to_string(),
+            '✓' | '★' => "*".to_string(),
+            // Other characters, such as those of table names and cells, are kept
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+impl Theme {
+    /// Build a theme whose RGB colors are reduced to what the terminal supports
+    pub fn with_color_mode(config: &ColorConfig, mode: ColorMode) -> Self {
+        let mode = mode.resolve();
+        let parse_color = |hex: &str| parse_color(hex).map(|c| downsample_color(c, mode));
+
         Self {
             border: parse_color(&config.border).unwrap_or(Color::Cyan),
             text: parse_color(&config.text).unwrap_or(Color::White),
@@ -147,24 +617,172 @@ impl From<&ColorConfig> for Theme {
             detailed_view_title: parse_color(&config.detailed_view_title).unwrap_or(Color::Yellow),
             detailed_view_field: parse_color(&config.detailed_view_field).unwrap_or(Color::Blue),
             detailed_view_value: parse_color(&config.detailed_view_value).unwrap_or(Color::White),
+            ascii: false,
         }
     }
 }
 
-pub fn load_config() -> Result<Config> {
+/// A single problem found in the config file; the offending value is replaced by its default
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub field: String,
+    pub value: String,
+    pub expected: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` = {} (expected {})", self.field, self.value, self.expected)
+    }
+}
+
+/// Load the config, returning any invalid fields that were replaced by defaults
+pub fn load_config() -> Result<(Config, Vec<ConfigIssue>)> {
     let config_path = get_config_path()?;
     
     if config_path.exists() {
         let content = fs::read_to_string(&config_path)
             .context("Failed to read config file")?;
-        let config: Config = serde_json::from_str(&content)
-            .context("Failed to parse config file")?;
-        Ok(config)
+        parse_config(&content)
+            .with_context(|| format!("Failed to parse config file {}", config_path.display()))
     } else {
         // Create default config file
         let default_config = Config::default();
         create_config_file(&config_path, &default_config)?;
-        Ok(default_config)
+        Ok((default_config, Vec::new()))
+    }
+}
+
+fn parse_config(content: &str) -> Result<(Config, Vec<ConfigIssue>)> {
+    let mut value: serde_json::Value = serde_json::from_str(content)?;
+    let schema = serde_json::to_value(Config::default())?;
+    let mut issues = Vec::new();
+    validate_value(&mut value, &schema, "", &mut issues);
+    let config: Config = serde_json::from_value(value)?;
+    Ok((config, issues))
+}
+
+/// Check `value` against the shape of the default config, dropping unknown or invalid
+/// entries so deserialization falls back to their defaults
+fn validate_value(
+    value: &mut serde_json::Value,
+    schema: &serde_json::Value,
+    path: &str,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    use serde_json::Value;
+
+    let (Value::Object(map), Value::Object(schema_map)) = (value, schema) else {
+        return;
+    };
+
+    let mut invalid_keys = Vec::new();
+    for (key, entry) in map.iter_mut() {
+        let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        let Some(expected) = schema_map.get(key) else {
+            let mut known: Vec<&str> = schema_map.keys().map(|k| k.as_str()).collect();
+            known.sort();
+            issues.push(ConfigIssue {
+                field,
+                value: entry.to_string(),
+                expected: format!("one of the known keys: {}", known.join(", ")),
+            });
+            invalid_keys.push(key.clone());
+            continue;
+        };
+
+        if let Some(expected) = check_entry(entry, expected, &field, issues) {
+            issues.push(ConfigIssue {
+                field,
+                value: entry.to_string(),
+                expected,
+            });
+            invalid_keys.push(key.clone());
+        }
+    }
+
+    for key in invalid_keys {
+        map.remove(&key);
+    }
+}
+
+/// Validate one entry against its schema value, returning what was expected if it is invalid
+fn check_entry(
+    entry: &mut serde_json::Value,
+    expected: &serde_json::Value,
+    field: &str,
+    issues: &mut Vec<ConfigIssue>,
+) -> Option<String> {
+    use serde_json::Value;
+
+    match (&mut *entry, expected) {
+        (Value::Object(_), Value::Object(_)) => {
+            validate_value(entry, expected, field, issues);
+            None
+        }
+        (Value::Array(items), Value::Array(schema_items)) => {
+            // Every item is checked against the first item of the default list
+            if let Some(item_schema) = schema_items.first() {
+                let mut invalid_items = Vec::new();
+                for (idx, item) in items.iter_mut().enumerate() {
+                    let item_field = format!("{}[{}]", field, idx);
+                    if let Some(expected) = check_entry(item, item_schema, &item_field, issues) {
+                        issues.push(ConfigIssue {
+                            field: item_field,
+                            value: item.to_string(),
+                            expected,
+                        });
+                        invalid_items.push(idx);
+                    }
+                }
+                for idx in invalid_items.into_iter().rev() {
+                    items.remove(idx);
+                }
+            }
+            None
+        }
+        (Value::String(s), Value::String(_)) => validate_string_field(field, s),
+        (_, Value::Null) if field == "format.decimal_places" => {
+            (!entry.is_null() && !entry.is_u64()).then(|| "a non-negative integer or null".to_string())
+        }
+        (Value::String(_) | Value::Null, Value::Null) => None,
+        (Value::Number(n), Value::Number(_)) if n.is_u64() => None,
+        (Value::Bool(_), Value::Bool(_)) => None,
+        (_, Value::Object(_)) => Some("an object".to_string()),
+        (_, Value::Array(_)) => Some("a list".to_string()),
+        (_, Value::String(_)) => Some("a string".to_string()),
+        (_, Value::Null) => Some("a string or null".to_string()),
+        (_, Value::Number(_)) => Some("a non-negative integer".to_string()),
+        (_, Value::Bool(_)) => Some("true or false".to_string()),
+    }
+}
+
+fn validate_string_field(field: &str, value: &str) -> Option<String> {
+    if field.starts_with("colors.") {
+        parse_color(value)
+            .err()
+            .map(|_| "a hex color like \"#RRGGBB\" or \"#RRGGBBAA\"".to_string())
+    } else if field == "csv.delimiter" {
+        (value.len() != 1 || !value.is_ascii())
+            .then(|| "a single ASCII character such as \",\", \";\" or \"\\t\"".to_string())
+    } else if field == "color_mode" {
+        serde_json::from_value::<ColorMode>(serde_json::Value::String(value.to_string()))
+            .err()
+            .map(|_| "one of \"auto\", \"truecolor\", \"256\", \"16\"".to_string())
+    } else if field == "theme" {
+        serde_json::from_value::<ThemePreset>(serde_json::Value::String(value.to_string()))
+            .err()
+            .map(|_| "one of \"dark\", \"light\", \"solarized\", \"gruvbox\", \"high-contrast\"".to_string())
+    } else if field == "keymap" {
+        serde_json::from_value::<KeymapPreset>(serde_json::Value::String(value.to_string()))
+            .err()
+            .map(|_| "one of \"default\", \"vim\"".to_string())
+    } else if field == "format.number_locale" {
+        serde_json::from_value::<NumberLocale>(serde_json::Value::String(value.to_string()))
+            .err()
+            .map(|_| "one of \"en\", \"de\", \"fr\", \"ch\"".to_string())
+    } else {
+        None
     }
 }
 
@@ -215,6 +833,84 @@ pub fn parse_color(hex: &str) -> Result<Color> {
     Ok(Color::Rgb(r, g, b))
 }
 
+/// Map an RGB color to the closest color available in the given mode
+pub fn downsample_color(color: Color, mode: ColorMode) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+
+    match mode {
+        ColorMode::Auto | ColorMode::Truecolor => color,
+        ColorMode::Ansi256 => Color::Indexed(nearest_ansi256(r, g, b)),
+        ColorMode::Ansi16 => nearest_ansi16(r, g, b),
+    }
+}
+
+fn color_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+fn nearest_ansi16(r: u8, g: u8, b: u8) -> Color {
+    // Standard xterm palette values for the 16 base colors
+    const PALETTE: [(Color, (u8, u8, u8)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::Red, (205, 0, 0)),
+        (Color::Green, (0, 205, 0)),
+        (Color::Yellow, (205, 205, 0)),
+        (Color::Blue, (0, 0, 238)),
+        (Color::Magenta, (205, 0, 205)),
+        (Color::Cyan, (0, 205, 205)),
+        (Color::Gray, (229, 229, 229)),
+        (Color::DarkGray, (127, 127, 127)),
+        (Color::LightRed, (255, 0, 0)),
+        (Color::LightGreen, (0, 255, 0)),
+        (Color::LightYellow, (255, 255, 0)),
+        (Color::LightBlue, (92, 92, 255)),
+        (Color::LightMagenta, (255, 0, 255)),
+        (Color::LightCyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+
+    PALETTE
+        .iter()
+        .min_by_key(|(_, rgb)| color_distance(*rgb, (r, g, b)))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::White)
+}
+
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    // 6x6x6 color cube (indices 16-231)
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |v: u8| -> usize {
+        LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, l)| (**l as i32 - v as i32).abs())
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube_rgb = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_idx = 16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8;
+
+    // Grayscale ramp (indices 232-255)
+    let avg = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+    let gray_step = (avg.saturating_sub(8) as u32 / 10).min(23) as u8;
+    let gray_value = 8 + gray_step * 10;
+    let gray_idx = 232 + gray_step;
+
+    if color_distance((gray_value, gray_value, gray_value), (r, g, b))
+        < color_distance(cube_rgb, (r, g, b))
+    {
+        gray_idx
+    } else {
+        cube_idx
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,6 +923,145 @@ mod tests {
         assert!(parse_color("#invalid").is_err());
     }
 
+    #[test]
+    fn test_detect_color_mode() {
+        assert_eq!(detect_color_mode(Some("truecolor"), Some("xterm")), ColorMode::Truecolor);
+        assert_eq!(detect_color_mode(None, Some("xterm-256color")), ColorMode::Ansi256);
+        assert_eq!(detect_color_mode(None, Some("linux")), ColorMode::Ansi16);
+        assert_eq!(detect_color_mode(None, None), ColorMode::Ansi16);
+    }
+
+    #[test]
+    fn test_downsample_color() {
+        let red = Color::Rgb(200, 30, 30);
+        assert_eq!(downsample_color(red, ColorMode::Truecolor), red);
+        assert_eq!(downsample_color(red, ColorMode::Ansi16), Color::Red);
+        assert_eq!(downsample_color(Color::Rgb(0, 0, 0), ColorMode::Ansi256), Color::Indexed(16));
+        assert_eq!(downsample_color(Color::Rgb(128, 128, 128), ColorMode::Ansi256), Color::Indexed(244));
+        assert_eq!(downsample_color(Color::Cyan, ColorMode::Ansi16), Color::Cyan);
+    }
+
+    #[test]
+    fn test_color_mode_deserialize() {
+        let mode: ColorMode = serde_json::from_str(r#""256""#).unwrap();
+        assert_eq!(mode, ColorMode::Ansi256);
+        let mode: ColorMode = serde_json::from_str(r#""16""#).unwrap();
+        assert_eq!(mode, ColorMode::Ansi16);
+    }
+
+    #[test]
+    fn test_theme_presets() {
+        let (config, issues) = parse_config(r##"{"theme": "high-contrast", "colors": {"text": "#ff8800"}}"##).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(config.theme, ThemePreset::HighContrast);
+        let colors = config.colors.over(config.theme);
+        assert_eq!(colors.text, "#ff8800");
+        assert_eq!(colors.border, ThemePreset::HighContrast.colors().border);
+
+        // A config written with the default colors takes the preset's whole
+        let colors = ColorConfig::default().over(ThemePreset::Light);
+        assert_eq!(colors.help_bg, ThemePreset::Light.colors().help_bg);
+        assert_ne!(colors.help_bg, ColorConfig::default().help_bg);
+
+        let (config, issues) = parse_config(r#"{"theme": "neon"}"#).unwrap();
+        assert_eq!(issues[0].field, "theme");
+        assert_eq!(config.theme, ThemePreset::Dark);
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let content = r##"{
+            "colors": { "border": "#zz0000", "text": "#ffffff", "bordr": "#000000" },
+            "color_mode": "mono",
+            "format": { "decimal_places": "two" },
+            "extra": true
+        }"##;
+        let (config, issues) = parse_config(content).unwrap();
+        let fields: Vec<&str> = issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["color_mode", "colors.border", "colors.bordr", "extra", "format.decimal_places"]
+        );
+        assert!(issues[1].expected.contains("#RRGGBB"));
+        assert_eq!(issues[1].value, "\"#zz0000\"");
+
+        // Invalid values fall back to defaults, valid ones are kept
+        assert_eq!(config.colors.border, ColorConfig::default().border);
+        assert_eq!(config.colors.text, "#ffffff");
+        assert_eq!(config.color_mode, ColorMode::Auto);
+        assert_eq!(config.format.decimal_places, None);
+        let (config, issues) = parse_config(r#"{"format": {"decimal_places": 3}}"#).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(config.format.decimal_places, Some(3));
+
+        let (config, issues) = parse_config(r#"{"csv": {"delimiter": "||"}, "sqlite": {"readonly": true}}"#).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "csv.delimiter");
+        assert_eq!(config.csv.delimiter_byte(), b',');
+        assert!(config.sqlite.readonly);
+
+        let content = r#"{"external_tools": [
+            {"name": "jq", "command": "jq . {file}", "file_types": ["csv", 3]},
+            "vd {file}"
+        ]}"#;
+        let (config, issues) = parse_config(content).unwrap();
+        let fields: Vec<&str> = issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, vec!["external_tools[0].file_types[1]", "external_tools[1]"]);
+        assert_eq!(config.external_tools.len(), 1);
+        assert_eq!(config.external_tools[0].file_types, vec!["csv"]);
+
+        let (_, issues) = parse_config(r#"{"external_tools": "vd"}"#).unwrap();
+        assert_eq!(issues[0].expected, "a list");
+
+        let (config, issues) = parse_config(r#"{"format": {"number_locale": "xx"}}"#).unwrap();
+        assert_eq!(issues[0].field, "format.number_locale");
+        assert_eq!(config.format.number_locale, NumberLocale::En);
+        let (config, _) = parse_config(r#"{"format": {"number_locale": "de"}}"#).unwrap();
+        assert_eq!(config.format.number_locale, NumberLocale::De);
+
+        let (config, issues) = parse_config(r#"{"keymap": "emacs"}"#).unwrap();
+        assert_eq!(issues[0].field, "keymap");
+        assert_eq!(config.keymap, KeymapPreset::Default);
+        let (config, _) = parse_config(r#"{"keymap": "vim"}"#).unwrap();
+        assert_eq!(config.keymap, KeymapPreset::Vim);
+    }
+
+    #[test]
+    fn test_external_tool_applies_to() {
+        let tools = Config::default().external_tools;
+        let names = |file_type: &str| -> Vec<String> {
+            tools
+                .iter()
+                .filter(|t| t.applies_to(file_type))
+                .map(|t| t.name.clone())
+                .collect()
+        };
+        assert_eq!(names("sqlite"), vec!["sqlite3", "VisiData"]);
+        assert_eq!(names("csv"), vec!["Editor", "VisiData"]);
+        assert_eq!(names("parquet"), vec!["VisiData"]);
+    }
+
+    #[test]
+    fn test_export_path_for() {
+        let settings = ExportSettings::default();
+        assert_eq!(settings.path_for("t.csv"), PathBuf::from("t.csv"));
+        let settings = ExportSettings { directory: "/tmp/exports".to_string() };
+        assert_eq!(settings.path_for("t.csv"), PathBuf::from("/tmp/exports/t.csv"));
+        assert_eq!(expand_home("a/~b"), PathBuf::from("a/~b"));
+    }
+
+    #[test]
+    fn test_ascii_symbols() {
+        let mut theme = Theme::with_color_mode(&ColorConfig::default(), ColorMode::Truecolor);
+        assert_eq!(theme.text("↑↓←→ Navigate"), "↑↓←→ Navigate");
+        theme.ascii = true;
+        assert_eq!(theme.text("↑↓←→ Navigate"), "^v<> Navigate");
+        assert_eq!(theme.text("▶ users"), "> users");
+        assert_eq!(theme.text("▶ Müller → 日本"), "> Müller > 日本");
+        assert_eq!(theme.text("── Views ──"), "-- Views --");
+        assert_eq!(theme.border_set().top_left, "+");
+    }
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
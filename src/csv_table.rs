@@ -0,0 +1,434 @@
+use anyhow::{Context, Result};
+use csv::{ByteRecord, Reader, ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use crate::config::{CsvSettings, NumberLocale};
+use crate::database::QueryResult;
+use crate::file_reader::{detect_compression, open_input, paginate_data, read_csv_file};
+use crate::filter::{filter_rows, ColumnFilter, FilterOp};
+use crate::format::parse_number;
+use crate::persistence::csv_index_path;
+use crate::value::Value;
+
+/// Rows between the offsets kept in the index; a page is read from the last offset before it
+pub const CHUNK_ROWS: usize = 1024;
+
+/// Longest value kept as the smallest or largest of a chunk
+const MAX_RANGE_TEXT: usize = 64;
+
+/// Where every `CHUNK_ROWS`-th record of a CSV file starts, and the range of each column's
+/// values in every chunk, found in one pass over the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CsvIndex {
+    pub columns: Vec<String>,
+    pub offsets: Vec<u64>, // byte offsets of rows 0, CHUNK_ROWS, 2 * CHUNK_ROWS, ...
+    pub total_rows: usize,
+    #[serde(default)]
+    pub ranges: Vec<Vec<Option<ChunkRange>>>, // by chunk, then column; None when every value is empty
+}
+
+/// The smallest and largest values of a column in one chunk, leaving out empty and NULL
+/// cells as filters do; enough for a comparison filter to skip chunks none of whose rows pass
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkRange {
+    pub text: Option<(String, String)>, // compared as text; None once one is too long to keep
+    pub integers: Option<(i64, i64)>,   // while every value is a plain integer
+}
+
+/// An index as kept on disk, with what it was built from
+#[derive(Serialize, Deserialize)]
+struct StoredIndex {
+    stamp: String, // the file's size and modification time
+    delimiter: String,
+    has_header: bool,
+    index: CsvIndex,
+}
+
+impl CsvIndex {
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %path.display()), err)]
+    pub fn build(path: &Path, settings: &CsvSettings) -> Result<Self> {
+        let mut reader = csv_reader(settings, settings.has_header).from_reader(open_input(path)?);
+        // Without a header row the first record is only used to count the columns
+        let mut columns: Vec<String> = if settings.has_header {
+            reader.byte_headers()?.iter().map(|h| String::from_utf8_lossy(h).to_string()).collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut offsets = Vec::new();
+        let mut ranges: Vec<Vec<Option<ChunkRange>>> = Vec::new();
+        let mut total_rows = 0;
+        let mut record = ByteRecord::new();
+        loop {
+            let start = reader.position().byte();
+            if !reader.read_byte_record(&mut record)? {
+                break;
+            }
+            if total_rows % CHUNK_ROWS == 0 {
+                offsets.push(start);
+                ranges.push(Vec::new());
+            }
+            if total_rows == 0 && !settings.has_header {
+                columns = (1..=record.len()).map(|i| format!("Column{}", i)).collect();
+            }
+            let chunk = ranges.last_mut().expect("a chunk was just started");
+            if chunk.len() < record.len() {
+                chunk.resize(record.len(), None);
+            }
+            for (range, field) in chunk.iter_mut().zip(record.iter()) {
+                ChunkRange::add(range, &String::from_utf8_lossy(field));
+            }
+            total_rows += 1;
+        }
+        Ok(Self { columns, offsets, total_rows, ranges })
+    }
+
+    /// The index kept from an earlier run, while the file and settings are as they were, or
+    /// else built and kept for the next run
+    pub fn load_or_build(path: &Path, settings: &CsvSettings) -> Result<Self> {
+        let stored = csv_index_path(path);
+        if let Some(index) = stored.as_ref().ok().and_then(|stored| Self::load(stored, path, settings)) {
+            return Ok(index);
+        }
+        let index = Self::build(path, settings)?;
+        if let Err(e) = stored.and_then(|stored| index.save(&stored, path, settings)) {
+            tracing::warn!(error = %e, "failed to keep the CSV index");
+        }
+        Ok(index)
+    }
+
+    fn load(stored: &Path, path: &Path, settings: &CsvSettings) -> Option<Self> {
+        let stored: StoredIndex = serde_json::from_str(&std::fs::read_to_string(stored).ok()?).ok()?;
+        let current = (file_stamp(path).ok()?, settings.delimiter.as_str(), settings.has_header);
+        (current == (stored.stamp, stored.delimiter.as_str(), stored.has_header)).then_some(stored.index)
+    }
+
+    fn save(&self, stored: &Path, path: &Path, settings: &CsvSettings) -> Result<()> {
+        let kept = StoredIndex {
+            stamp: file_stamp(path)?,
+            delimiter: settings.delimiter.clone(),
+            has_header: settings.has_header,
+            index: self.clone(),
+        };
+        std::fs::write(stored, serde_json::to_string(&kept)?).context("Failed to write the CSV index")
+    }
+
+    /// Whether any row of chunk `chunk` can pass every filter, from the ranges of its
+    /// columns; filters are given with the index of their column
+    fn chunk_may_match(&self, chunk: usize, filters: &[(usize, &ColumnFilter)], locale: NumberLocale) -> bool {
+        let Some(ranges) = self.ranges.get(chunk) else {
+            return true;
+        };
+        filters.iter().all(|(idx, filter)| match ranges.get(*idx) {
+            Some(range) => ChunkRange::may_match(range.as_ref(), filter, locale),
+            None => true,
+        })
+    }
+}
+
+impl ChunkRange {
+    /// Take a cell into the range of its column
+    fn add(range: &mut Option<ChunkRange>, cell: &str) {
+        let value = cell.trim();
+        if value.is_empty() || value == "NULL" {
+            return;
+        }
+        let integer = is_plain_integer(value).then(|| value.parse::<i64>().ok()).flatten();
+        let Some(range) = range else {
+            let text = (value.len() <= MAX_RANGE_TEXT).then(|| (value.to_string(), value.to_string()));
+            *range = Some(ChunkRange { text, integers: integer.map(|n| (n, n)) });
+            return;
+        };
+        if let Some((min, max)) = &mut range.text {
+            let (smaller, larger) = (value < min.as_str(), value > max.as_str());
+            if (smaller || larger) && value.len() > MAX_RANGE_TEXT {
+                range.text = None;
+            } else if smaller {
+                *min = value.to_string();
+            } else if larger {
+                *max = value.to_string();
+            }
+        }
+        range.integers = match (range.integers, integer) {
+            (Some((min, max)), Some(n)) => Some((min.min(n), max.max(n))),
+            _ => None,
+        };
+    }
+
+    /// Whether a value in `range` can pass `filter`, comparing as filters do: as numbers
+    /// when both sides are numbers, otherwise as text. Only comparisons rule a range out;
+    /// a column of empty cells (`None`) passes none of them.
+    fn may_match(range: Option<&ChunkRange>, filter: &ColumnFilter, locale: NumberLocale) -> bool {
+        if !matches!(filter.op, FilterOp::Eq | FilterOp::Lt | FilterOp::Le | FilterOp::Gt | FilterOp::Ge) {
+            return true;
+        }
+        let Some(range) = range else {
+            return false;
+        };
+        // How the smallest and largest values compare with the filter's
+        let bounds = match parse_number(&filter.value, locale) {
+            Some(value) => range.integers.and_then(|(min, max)| {
+                Some(((min as f64).partial_cmp(&value)?, (max as f64).partial_cmp(&value)?))
+            }),
+            None => range
+                .text
+                .as_ref()
+                .map(|(min, max)| (min.as_str().cmp(filter.value.as_str()), max.as_str().cmp(filter.value.as_str()))),
+        };
+        let Some((min, max)) = bounds else {
+            return true;
+        };
+        match filter.op {
+            FilterOp::Eq => min != Ordering::Greater && max != Ordering::Less,
+            FilterOp::Lt => min == Ordering::Less,
+            FilterOp::Le => min != Ordering::Greater,
+            FilterOp::Gt => max == Ordering::Greater,
+            _ => max != Ordering::Less,
+        }
+    }
+}
+
+/// Digits with an optional minus sign, read the same in every number locale
+fn is_plain_integer(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    !digits.is_empty() && digits.len() <= 18 && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The size and modification time of a file, which change when it is written
+fn file_stamp(path: &Path) -> Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(SystemTime::UNIX_EPOCH)?;
+    Ok(format!("{}_{}", metadata.len(), modified.as_nanos()))
+}
+
+/// The rows of a CSV file. Small and compressed files are read whole; larger plain files are
+/// indexed and read a page at a time, until sorting, a query or an edit needs every row.
+pub struct CsvTable {
+    path: PathBuf,
+    settings: CsvSettings,
+    index: Option<CsvIndex>,
+    loaded: OnceLock<QueryResult>, // every row; once read, edits go here and pages come from it
+}
+
+impl CsvTable {
+    pub fn open(path: &Path, settings: &CsvSettings) -> Result<Self> {
+        let streamed = detect_compression(path).is_none()
+            && std::fs::metadata(path)?.len() > settings.stream_above_mb.saturating_mul(1024 * 1024);
+        let mut table = Self { path: path.to_path_buf(), settings: settings.clone(), index: None, loaded: OnceLock::new() };
+        if streamed && settings.keep_index {
+            table.index = Some(CsvIndex::load_or_build(path, settings)?);
+        } else if streamed {
+            table.index = Some(CsvIndex::build(path, settings)?);
+        } else {
+            let _ = table.loaded.set(read_csv_file(path, settings)?);
+        }
+        Ok(table)
+    }
+
+    pub fn columns(&self) -> &[String] {
+        match (self.loaded.get(), &self.index) {
+            (Some(data), _) => &data.columns,
+            (None, Some(index)) => &index.columns,
+            (None, None) => &[],
+        }
+    }
+
+    /// Every row, read from the file the first time it is needed
+    pub fn data(&self) -> Result<&QueryResult> {
+        if let Some(data) = self.loaded.get() {
+            return Ok(data);
+        }
+        let data = read_csv_file(&self.path, &self.settings)?;
+        Ok(self.loaded.get_or_init(|| data))
+    }
+
+    pub fn data_mut(&mut self) -> Result<&mut QueryResult> {
+        self.data()?;
+        Ok(self.loaded.get_mut().expect("rows were just read"))
+    }
+
+    /// `limit` rows from `offset`, with the number of rows in the table
+    pub fn page(&self, offset: usize, limit: usize) -> Result<QueryResult> {
+        let (Some(index), None) = (&self.index, self.loaded.get()) else {
+            return Ok(paginate_data(self.data()?, offset, limit));
+        };
+        let mut rows = Vec::new();
+        if offset < index.total_rows {
+            let mut reader = self.reader_at(index, offset / CHUNK_ROWS)?;
+            let mut record = StringRecord::new();
+            for _ in 0..offset % CHUNK_ROWS {
+                reader.read_record(&mut record)?;
+            }
+            while rows.len() < limit && reader.read_record(&mut record)? {
+                rows.push(record.iter().map(Value::from).collect());
+            }
+        }
+        Ok(QueryResult { columns: index.columns.clone(), rows, total_rows: index.total_rows })
+    }
+
+    /// A page of the rows passing `filters`: the file is read a chunk at a time, keeping only
+    /// the page, so a filter doesn't need every row in memory. Chunks whose column ranges
+    /// rule out a comparison filter are not read at all.
+    pub fn filtered_page(&self, filters: &[ColumnFilter], offset: usize, limit: usize, locale: NumberLocale) -> Result<QueryResult> {
+        let (Some(index), None) = (&self.index, self.loaded.get()) else {
+            return Ok(paginate_data(&filter_rows(self.data()?, filters, locale), offset, limit));
+        };
+        let columns: Vec<(usize, &ColumnFilter)> = filters
+            .iter()
+            .filter_map(|f| index.columns.iter().position(|c| *c == f.column).map(|idx| (idx, f)))
+            .collect();
+        let mut reader = None; // with the chunk it reads next
+        let mut record = StringRecord::new();
+        let mut rows = Vec::new();
+        let mut matches = 0;
+        let mut chunk = QueryResult { columns: index.columns.clone(), rows: Vec::new(), total_rows: 0 };
+        for chunk_idx in 0..index.offsets.len() {
+            if !index.chunk_may_match(chunk_idx, &columns, locale) {
+                continue;
+            }
+            let reader = match &mut reader {
+                Some((next, reader)) if *next == chunk_idx => reader,
+                _ => &mut reader.insert((chunk_idx, self.reader_at(index, chunk_idx)?)).1,
+            };
+            chunk.rows.clear();
+            while chunk.rows.len() < CHUNK_ROWS && reader.read_record(&mut record)? {
+                chunk.rows.push(record.iter().map(Value::from).collect());
+            }
+            for row in filter_rows(&chunk, filters, locale).rows {
+                if matches >= offset && rows.len() < limit {
+                    rows.push(row);
+                }
+                matches += 1;
+            }
+        }
+        Ok(QueryResult { columns: index.columns.clone(), rows, total_rows: matches })
+    }
+
+    /// A reader of the records from the start of chunk `chunk`
+    fn reader_at(&self, index: &CsvIndex, chunk: usize) -> Result<Reader<BufReader<File>>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(index.offsets.get(chunk).copied().unwrap_or_default()))?;
+        Ok(csv_reader(&self.settings, false).from_reader(BufReader::new(file)))
+    }
+}
+
+fn csv_reader(settings: &CsvSettings, has_headers: bool) -> ReaderBuilder {
+    let mut builder = ReaderBuilder::new();
+    builder.has_headers(has_headers).delimiter(settings.delimiter_byte());
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterOp;
+
+    fn streamed_csv(name: &str, rows: usize) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sqbrowser_{}_{}.csv", name, std::process::id()));
+        let mut text = "id,name\n".to_string();
+        for i in 0..rows {
+            // Quoted line breaks don't start a record
+            text.push_str(&format!("{},\"n{}\nx\"\n", i, i % 7));
+        }
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_streamed_pages() {
+        let path = streamed_csv("stream_pages", 3000);
+        let settings = CsvSettings { stream_above_mb: 0, keep_index: false, ..Default::default() };
+        let table = CsvTable::open(&path, &settings).unwrap();
+        assert!(table.loaded.get().is_none());
+        assert_eq!(table.columns(), ["id", "name"]);
+
+        let page = table.page(2040, 20).unwrap();
+        assert_eq!((page.total_rows, page.rows.len()), (3000, 20));
+        assert_eq!(page.rows[0], vec![Value::from("2040"), Value::from("n3\nx")]);
+        assert_eq!(table.page(2990, 50).unwrap().rows.len(), 10);
+        assert!(table.page(3000, 50).unwrap().rows.is_empty());
+        assert_eq!(table.page(0, 3000).unwrap().rows, read_csv_file(&path, &settings).unwrap().rows);
+        assert!(table.loaded.get().is_none());
+
+        // Small files are read whole
+        assert!(CsvTable::open(&path, &CsvSettings::default()).unwrap().loaded.get().is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_streamed_filter() {
+        let path = streamed_csv("stream_filter", 3000);
+        let settings = CsvSettings { stream_above_mb: 0, keep_index: false, ..Default::default() };
+        let mut table = CsvTable::open(&path, &settings).unwrap();
+        let filters = [ColumnFilter { column: "name".to_string(), op: FilterOp::Eq, value: "n0\nx".to_string() }];
+        let page = table.filtered_page(&filters, 400, 100, NumberLocale::En).unwrap();
+        // Every 7th row, from 0 to 2996
+        assert_eq!((page.total_rows, page.rows.len()), (429, 29));
+        assert_eq!(page.rows[0][0], Value::from("2800"));
+
+        table.data_mut().unwrap().rows.truncate(10);
+        assert!(table.loaded.get().is_some());
+        assert_eq!(table.filtered_page(&filters, 0, 100, NumberLocale::En).unwrap().total_rows, 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        let path = streamed_csv("chunk_ranges", 3000);
+        let settings = CsvSettings { stream_above_mb: 0, keep_index: false, ..Default::default() };
+        let table = CsvTable::open(&path, &settings).unwrap();
+        let index = table.index.as_ref().unwrap();
+        let range = index.ranges[1][0].as_ref().unwrap();
+        assert_eq!(range.integers, Some((1024, 2047)));
+        assert_eq!(range.text, Some(("1024".to_string(), "2047".to_string())));
+        assert_eq!(index.ranges[0][1].as_ref().unwrap().integers, None);
+
+        // Only the last chunk can have ids above 2900
+        let filters = [ColumnFilter::parse("id", ">2900").unwrap()];
+        let columns = [(0, &filters[0])];
+        let readable: Vec<bool> = (0..3).map(|chunk| index.chunk_may_match(chunk, &columns, NumberLocale::En)).collect();
+        assert_eq!(readable, vec![false, false, true]);
+        let page = table.filtered_page(&filters, 0, 10, NumberLocale::En).unwrap();
+        assert_eq!((page.total_rows, page.rows[0][0].clone()), (99, Value::from("2901")));
+        // Names run from "n0\nx" up, so none is at most "n0"
+        let names = [ColumnFilter::parse("name", "<=n0").unwrap(), ColumnFilter::parse("name", "<n1").unwrap()];
+        assert!(!index.chunk_may_match(0, &[(1, &names[0])], NumberLocale::En));
+        assert!(index.chunk_may_match(0, &[(1, &names[1])], NumberLocale::En));
+
+        let mut range = None;
+        for cell in ["5", " ", "NULL", "-12", "b"] {
+            ChunkRange::add(&mut range, cell);
+        }
+        let range = range.unwrap();
+        assert_eq!((range.integers, range.text.clone()), (None, Some(("-12".to_string(), "b".to_string()))));
+        let eq = |value: &str| ColumnFilter { column: "id".to_string(), op: FilterOp::Eq, value: value.to_string() };
+        assert!(ChunkRange::may_match(Some(&range), &eq("a"), NumberLocale::En));
+        assert!(!ChunkRange::may_match(Some(&range), &eq("c"), NumberLocale::En));
+        assert!(!ChunkRange::may_match(None, &eq("c"), NumberLocale::En));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_kept_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("kept.csv");
+        let stored = temp_dir.path().join("kept.json");
+        std::fs::write(&path, "id,name\n1,a\n2,b\n").unwrap();
+        let settings = CsvSettings::default();
+        let index = CsvIndex::build(&path, &settings).unwrap();
+        index.save(&stored, &path, &settings).unwrap();
+        assert_eq!(CsvIndex::load(&stored, &path, &settings), Some(index));
+
+        // Not once the file or how it is read changes
+        let other = CsvSettings { delimiter: ";".to_string(), ..Default::default() };
+        assert_eq!(CsvIndex::load(&stored, &path, &other), None);
+        std::fs::write(&path, "id,name\n1,a\n2,b\n3,c\n").unwrap();
+        assert_eq!(CsvIndex::load(&stored, &path, &settings), None);
+    }
+}
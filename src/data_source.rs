@@ -1,178 +1,837 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use calamine::Reader;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use sqlparser::dialect::{PostgreSqlDialect, SQLiteDialect};
+use tracing::instrument;
 
-use crate::database::{Database, QueryResult};
-use crate::file_reader::{detect_file_type, read_csv_file, read_xlsx_file, read_parquet_file, paginate_data, FileType};
+use crate::bulk_edit::BulkUpdate;
+use crate::column_edit::ColumnEdit;
+use crate::column_stats::{aggregate, summarize, ColumnSummary, SummaryKind};
+use crate::csv_table::CsvTable;
+use crate::page_cache::PageKey;
+use crate::parquet_table::ParquetTable;
+use crate::workbook::Workbook;
+use crate::config::{Config, CsvSettings, NumberLocale, ParquetSettings, XlsxSettings};
+#[cfg(feature = "duckdb")]
+use crate::duck_database::DuckDatabase;
+use crate::database::{
+    export_typed_table, make_snippet, query_parameters, syntax_error, ChangeSignature, Database, ForeignKey, QueryResult, RowChange,
+    SchemaObject, SearchMatch, StatementPlan, SyntaxError, TableSchema,
+};
+use crate::file_reader::{backup_path, converted_csv_path, decompressed_path, detect_compression, detect_file_type, infer_column_types, lowercase_extension, read_arrow_file, read_csv_file, read_dbf_file, read_input_bytes, read_json_file, read_xlsx_file, paginate_sorted_data, ColumnType, FileType, TYPE_SAMPLE_ROWS};
+use crate::filter::{filter_rows, ColumnFilter};
+use crate::persistence::{ReverseStatement, SortOrder};
+use crate::html_table::{is_web_url, read_html_tables};
+use crate::libsql_database::LibsqlDatabase;
+use crate::postgres_database::{redact_password, PostgresDatabase};
+use crate::statistical::{read_statistical_file, StatFormat};
+use crate::value::Value;
+use crate::window::WindowSpec;
 
 pub enum DataSource {
     Sqlite(Database),
-    Csv(QueryResult, PathBuf),  // Store original path for SQL queries
-    Xlsx(Vec<(String, QueryResult)>, PathBuf),  // Store original path
-    Parquet(QueryResult, PathBuf),  // Store original path for SQL queries
+    Csv(CsvTable, PathBuf, CsvSettings),  // large files are read a page at a time
+    Xlsx(Workbook, PathBuf, XlsxSettings, OnceLock<Database>),  // sheets are read as they are first shown, and loaded into SQLite on the first query
+    Parquet(ParquetTable, PathBuf, ParquetSettings),  // plain files are read a page at a time
+    Json(QueryResult, PathBuf),  // .json array or .jsonl/.ndjson lines of objects
+    Arrow(QueryResult, PathBuf),  // Arrow IPC file or stream, including Feather v2
+    Dbf(QueryResult, PathBuf),  // dBase table, e.g. shapefile attributes
+    Statistical(QueryResult, PathBuf, StatFormat),  // Stata, SPSS or SAS data set
+    Html(Vec<(String, QueryResult)>, PathBuf),  // the <table>s of an HTML file or web page
+    #[cfg(feature = "duckdb")]
+    DuckDb(DuckDatabase),
+    Postgres(PostgresDatabase),  // connected from a postgres:// connection string
+    Libsql(LibsqlDatabase),  // hosted SQLite (Turso) from a libsql:// URL
 }
 
 impl DataSource {
+    #[allow(dead_code)]
     pub fn open(path: PathBuf) -> Result<Self> {
+        Self::open_with_config(path, &Config::default())
+    }
+
+    /// Open a file, seeding its reader with the per-file-type settings from the config
+    #[instrument(level = "info", skip(config), fields(path = %redact_password(&path.to_string_lossy())), err)]
+    pub fn open_with_config(path: PathBuf, config: &Config) -> Result<Self> {
         let file_type = detect_file_type(&path)?;
         
         match file_type {
             FileType::Sqlite => {
-                let db = Database::open(&path)?;
+                let db = if config.sqlite.readonly {
+                    Database::open_readonly(&path)?
+                } else {
+                    Database::open(&path)?
+                };
                 Ok(DataSource::Sqlite(db))
             }
             FileType::Csv => {
-                let data = read_csv_file(&path)?;
-                Ok(DataSource::Csv(data, path))
+                let settings = config.csv.clone();
+                let table = CsvTable::open(&path, &settings)?;
+                Ok(DataSource::Csv(table, path, settings))
             }
             FileType::Xlsx => {
-                let sheets = read_xlsx_file(&path)?;
-                Ok(DataSource::Xlsx(sheets, path))
+                let settings = config.xlsx.clone();
+                let workbook = Workbook::open(&path, &settings)?;
+                Ok(DataSource::Xlsx(workbook, path, settings, OnceLock::new()))
             }
             FileType::Parquet => {
-                let data = read_parquet_file(&path)?;
-                Ok(DataSource::Parquet(data, path))
+                let settings = config.parquet.clone();
+                let table = ParquetTable::open(&path, &settings)?;
+                Ok(DataSource::Parquet(table, path, settings))
+            }
+            FileType::Json => {
+                let data = read_json_file(&path)?;
+                Ok(DataSource::Json(data, path))
+            }
+            FileType::Arrow => {
+                let data = read_arrow_file(&path)?;
+                Ok(DataSource::Arrow(data, path))
             }
+            FileType::Dbf => {
+                let data = read_dbf_file(&path)?;
+                Ok(DataSource::Dbf(data, path))
+            }
+            FileType::Statistical(format) => {
+                let data = read_statistical_file(&path, format)?;
+                Ok(DataSource::Statistical(data, path, format))
+            }
+            FileType::Html => {
+                let tables = read_html_tables(&path)?;
+                Ok(DataSource::Html(tables, path))
+            }
+            #[cfg(feature = "duckdb")]
+            FileType::DuckDb => Ok(DataSource::DuckDb(DuckDatabase::open(&path)?)),
+            #[cfg(not(feature = "duckdb"))]
+            FileType::DuckDb => Err(anyhow::anyhow!(
+                "This build has no DuckDB support; rebuild with --features duckdb"
+            )),
+            FileType::Postgres => Ok(DataSource::Postgres(PostgresDatabase::connect(&path.to_string_lossy())?)),
+            FileType::Libsql => Ok(DataSource::Libsql(LibsqlDatabase::connect(&path.to_string_lossy())?)),
+        }
+    }
+
+    /// Short name of the file type, as used by `file_types` in the config
+    pub fn file_type_name(&self) -> &'static str {
+        match self {
+            DataSource::Sqlite(_) => "sqlite",
+            DataSource::Csv(_, _, _) => "csv",
+            DataSource::Xlsx(_, _, _, _) => "xlsx",
+            DataSource::Parquet(_, _, _) => "parquet",
+            DataSource::Json(_, _) => "json",
+            DataSource::Arrow(_, _) => "arrow",
+            DataSource::Dbf(_, _) => "dbf",
+            DataSource::Statistical(_, _, format) => format.file_type(),
+            DataSource::Html(_, _) => "html",
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => "duckdb",
+            DataSource::Postgres(_) => "postgres",
+            DataSource::Libsql(_) => "libsql",
+        }
+    }
+
+    /// The SQLite database file, for opening a second connection to it
+    pub fn database_file(&self) -> Option<PathBuf> {
+        match self {
+            DataSource::Sqlite(db) => db.file_path(),
+            _ => None,
+        }
+    }
+
+    /// Signature of the files backing the data, for noticing changes made by other processes
+    pub fn change_signature(&self) -> Option<ChangeSignature> {
+        match self {
+            DataSource::Sqlite(db) => db.change_signature(),
+            _ => None,
         }
     }
 
+    #[instrument(level = "debug", skip(self), err)]
     pub fn get_tables(&self) -> Result<Vec<String>> {
         match self {
             DataSource::Sqlite(db) => db.get_tables(),
-            DataSource::Csv(_, _) => Ok(vec!["CSV Data".to_string()]),
-            DataSource::Xlsx(sheets, _) => Ok(sheets.iter().map(|(name, _)| name.clone()).collect()),
-            DataSource::Parquet(_, _) => Ok(vec!["Parquet Data".to_string()]),
+            DataSource::Csv(_, _, _) => Ok(vec!["CSV Data".to_string()]),
+            DataSource::Xlsx(workbook, _, _, _) => Ok(workbook.names()),
+            DataSource::Parquet(_, _, _) => Ok(vec!["Parquet Data".to_string()]),
+            DataSource::Json(_, _) => Ok(vec!["JSON Data".to_string()]),
+            DataSource::Arrow(_, _) => Ok(vec!["Arrow Data".to_string()]),
+            DataSource::Dbf(_, _) => Ok(vec!["DBF Data".to_string()]),
+            DataSource::Statistical(_, _, format) => Ok(vec![format!("{} Data", format.name())]),
+            DataSource::Html(tables, _) => Ok(tables.iter().map(|(name, _)| name.clone()).collect()),
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(db) => db.get_tables(),
+            DataSource::Postgres(db) => db.get_tables(),
+            DataSource::Libsql(db) => db.get_tables(),
         }
     }
 
-    pub fn get_table_data(&self, table_name: &str, offset: usize, limit: usize) -> Result<QueryResult> {
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn get_table_data(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+        locale: NumberLocale,
+    ) -> Result<QueryResult> {
+        let page = |data: &QueryResult| {
+            if filters.is_empty() {
+                paginate_sorted_data(data, sort, offset, limit, locale)
+            } else {
+                let filtered = filter_rows(data, filters, locale);
+                paginate_sorted_data(&filtered, sort, offset, limit, locale)
+            }
+        };
         match self {
-            DataSource::Sqlite(db) => db.get_table_data(table_name, offset, limit),
-            DataSource::Csv(data, _) => Ok(paginate_data(data, offset, limit)),
-            DataSource::Xlsx(sheets, _) => {
-                if let Some((_, sheet_data)) = sheets.iter().find(|(name, _)| name == table_name) {
-                    Ok(paginate_data(sheet_data, offset, limit))
-                } else {
-                    Err(anyhow::anyhow!("Sheet '{}' not found", table_name))
-                }
+            DataSource::Sqlite(db) => db.get_table_data(table_name, offset, limit, sort, filters),
+            DataSource::Csv(table, _, _) => match (sort, filters.is_empty()) {
+                (None, true) => table.page(offset, limit),
+                (None, false) => table.filtered_page(filters, offset, limit, locale),
+                // Sorting needs every row
+                (Some(_), _) => Ok(page(table.data()?)),
+            },
+            DataSource::Parquet(table, _, _) => match (sort, filters.is_empty()) {
+                (None, true) => table.page(offset, limit, None),
+                (None, false) => table.filtered_page(filters, offset, limit, locale),
+                (Some(_), _) => Ok(page(table.data()?)),
+            },
+            DataSource::Xlsx(workbook, _, _, _) => Ok(page(workbook.sheet(table_name)?)),
+            DataSource::Html(tables, _) => match tables.iter().find(|(name, _)| name == table_name) {
+                Some((_, table_data)) => Ok(page(table_data)),
+                None => Err(anyhow::anyhow!("Table '{}' not found", table_name)),
+            },
+            DataSource::Json(data, _)
+            | DataSource::Arrow(data, _)
+            | DataSource::Dbf(data, _)
+            | DataSource::Statistical(data, _, _) => Ok(page(data)),
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(db) => db.get_table_data(table_name, offset, limit, sort, filters),
+            DataSource::Postgres(db) => db.get_table_data(table_name, offset, limit, sort, filters),
+            DataSource::Libsql(db) => db.get_table_data(table_name, offset, limit, sort, filters),
+        }
+    }
+
+    /// Whether pages can be read decoding only some of the columns (`get_projected_page`):
+    /// Parquet files not read whole
+    pub fn projects_columns(&self) -> bool {
+        matches!(self, DataSource::Parquet(table, _, _) if !table.is_loaded())
+    }
+
+    /// `limit` rows of the table from `offset` with only `columns` (by index) decoded and the
+    /// others NULL; sources that can't skip columns read them all
+    pub fn get_projected_page(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        columns: &[usize],
+        locale: NumberLocale,
+    ) -> Result<QueryResult> {
+        match self {
+            DataSource::Parquet(table, _, _) => table.page(offset, limit, Some(columns)),
+            _ => self.get_table_data(table_name, offset, limit, None, &[], locale),
+        }
+    }
+
+    /// A page of a SQLite table without counting its rows, which the page cache counts in the
+    /// background; its `total_rows` is only the rows on the page. Other sources are counted
+    /// as the page is read.
+    pub fn get_uncounted_table_data(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+        locale: NumberLocale,
+    ) -> Result<QueryResult> {
+        match self {
+            DataSource::Sqlite(db) => db.get_table_page(table_name, offset, limit, sort, filters),
+            _ => self.get_table_data(table_name, offset, limit, sort, filters, locale),
+        }
+    }
+
+    /// Start reading a workbook sheet in the background; true until it has been read. Other
+    /// tables are always ready.
+    pub fn load_in_background(&mut self, table_name: &str) -> bool {
+        match self {
+            DataSource::Xlsx(workbook, _, _, _) => workbook.load_in_background(table_name),
+            _ => false,
+        }
+    }
+
+    /// The workbook sheets read in the background since the last call, with the error a
+    /// sheet failed with
+    pub fn finished_sheets(&mut self) -> Vec<(String, Result<(), String>)> {
+        match self {
+            DataSource::Xlsx(workbook, _, _, _) => workbook.finished(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Column names of a table without reading its rows; None for servers, whose columns are
+    /// only known from the data shown
+    pub fn column_names(&self, table_name: &str) -> Option<Vec<String>> {
+        match self {
+            DataSource::Sqlite(db) => db.get_table_info(table_name).ok().map(|info| info.columns),
+            DataSource::Postgres(_) | DataSource::Libsql(_) => None,
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => None,
+            DataSource::Csv(table, _, _) => Some(table.columns().to_vec()),
+            DataSource::Parquet(table, _, _) => Some(table.columns().to_vec()),
+            DataSource::Json(data, _)
+            | DataSource::Arrow(data, _)
+            | DataSource::Dbf(data, _)
+            | DataSource::Statistical(data, _, _) => Some(data.columns.clone()),
+            DataSource::Xlsx(workbook, _, _, _) => Some(workbook.sheet(table_name).ok()?.columns.clone()),
+            DataSource::Html(tables, _) => tables
+                .iter()
+                .find(|(name, _)| name == table_name)
+                .map(|(_, data)| data.columns.clone()),
+        }
+    }
+
+    /// Column types inferred from the values of a file table; database tables have their own
+    pub fn column_types(&self, table_name: &str, locale: NumberLocale) -> Option<Vec<ColumnType>> {
+        match self {
+            DataSource::Sqlite(_) | DataSource::Postgres(_) | DataSource::Libsql(_) => None,
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => None,
+            // The types are inferred from the first rows, so a streamed file is not read whole
+            DataSource::Csv(table, _, _) => Some(infer_column_types(&table.page(0, TYPE_SAMPLE_ROWS).ok()?, locale)),
+            DataSource::Parquet(table, _, _) => Some(infer_column_types(&table.page(0, TYPE_SAMPLE_ROWS, None).ok()?, locale)),
+            DataSource::Json(data, _)
+            | DataSource::Arrow(data, _)
+            | DataSource::Dbf(data, _)
+            | DataSource::Statistical(data, _, _) => Some(infer_column_types(data, locale)),
+            DataSource::Xlsx(workbook, _, _, _) => Some(infer_column_types(workbook.sheet(table_name).ok()?, locale)),
+            DataSource::Html(tables, _) => tables
+                .iter()
+                .find(|(name, _)| name == table_name)
+                .map(|(_, data)| infer_column_types(data, locale)),
+        }
+    }
+
+    /// Structure of a table for the schema panel: declared by SQLite databases, inferred from
+    /// the values of file tables. None for servers, whose schema is not read.
+    pub fn table_schema(&self, table_name: &str, locale: NumberLocale) -> Result<Option<TableSchema>> {
+        if let DataSource::Sqlite(db) = self {
+            return db.table_schema(table_name).map(Some);
+        }
+        match (self.column_names(table_name), self.column_types(table_name, locale)) {
+            (Some(columns), Some(types)) => Ok(Some(TableSchema::inferred(&columns, &types))),
+            _ => Ok(None),
+        }
+    }
+
+    /// CREATE statements of a SQLite table or view and of its indexes and triggers
+    pub fn table_ddl(&self, table_name: &str) -> Result<Vec<String>> {
+        match self {
+            DataSource::Sqlite(db) => db.table_ddl(table_name),
+            _ => Err(anyhow::anyhow!("Only SQLite databases keep the CREATE statements of their tables")),
+        }
+    }
+
+    /// Foreign keys of a SQLite table, with the parent columns each one refers to. Other
+    /// sources declare none.
+    pub fn foreign_keys(&self, table_name: &str) -> Result<Vec<(ForeignKey, Vec<String>)>> {
+        let DataSource::Sqlite(db) = self else {
+            return Ok(Vec::new());
+        };
+        db.foreign_keys(table_name)?
+            .into_iter()
+            .map(|key| {
+                let references = db.referenced_columns(&key)?;
+                Ok((key, references))
+            })
+            .collect()
+    }
+
+    /// Export a file table into a new SQLite database, with columns typed as inferred
+    #[instrument(level = "info", skip(self), err)]
+    pub fn export_table_to_sqlite(
+        &self,
+        table_name: &str,
+        filename: &str,
+        locale: NumberLocale,
+    ) -> Result<usize> {
+        let types = self.column_types(table_name, locale).ok_or_else(|| {
+            anyhow::anyhow!("Typed export is for CSV, XLSX, Parquet, JSON, Arrow, DBF and HTML files; database tables are already typed")
+        })?;
+        let data = self.get_all_table_data(table_name)?;
+        // Single-table files get a table named after the file rather than "CSV Data"
+        let export_name = match self {
+            DataSource::Csv(_, path, _)
+            | DataSource::Parquet(_, path, _)
+            | DataSource::Json(_, path)
+            | DataSource::Arrow(_, path)
+            | DataSource::Dbf(_, path)
+            | DataSource::Statistical(_, path, _) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| table_name.to_string()),
+            _ => table_name.to_string(),
+        };
+        export_typed_table(std::path::Path::new(filename), &export_name, &data, &types, locale)
+    }
+
+    /// Fetch every row of a table, for operations that need the whole table (e.g. diffs)
+    pub fn get_all_table_data(&self, table_name: &str) -> Result<QueryResult> {
+        // Unsorted, so the locale doesn't matter
+        self.get_table_data(table_name, 0, i64::MAX as usize, None, &[], NumberLocale::default())
+    }
+
+    /// Stats, histogram or value counts of a column over the rows passing `filters`: SQLite
+    /// works them out itself, other sources over every row in memory
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn column_summary(
+        &self,
+        table_name: &str,
+        column: &str,
+        filters: &[ColumnFilter],
+        kind: SummaryKind,
+        locale: NumberLocale,
+    ) -> Result<ColumnSummary> {
+        match (self, kind) {
+            (DataSource::Sqlite(db), SummaryKind::Stats) => {
+                Ok(ColumnSummary::Stats(db.column_stats(table_name, column, filters)?))
+            }
+            (DataSource::Sqlite(db), SummaryKind::Histogram) => {
+                Ok(ColumnSummary::Histogram(db.column_histogram(table_name, column, filters)?))
+            }
+            (DataSource::Sqlite(db), SummaryKind::ValueCounts) => {
+                Ok(ColumnSummary::ValueCounts(db.column_value_counts(table_name, column, filters)?))
+            }
+            // Only the summarized column is decoded
+            (DataSource::Parquet(table, _, _), _) if filters.is_empty() => {
+                summarize_rows(&table.column(column)?, column, kind, locale)
+            }
+            _ => {
+                let data = self.get_table_data(table_name, 0, i64::MAX as usize, None, filters, locale)?;
+                summarize_rows(&data, column, kind, locale)
             }
-            DataSource::Parquet(data, _) => Ok(paginate_data(data, offset, limit)),
         }
     }
 
-    pub fn execute_custom_query(&self, query: &str, table_name: &str, offset: usize, limit: usize) -> Result<QueryResult> {
+    /// `func` (sum, mean, count, min or max) of a column over every row passing `filters`, for
+    /// a computed column: SQLite works it out itself, other sources over every row in memory
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn column_aggregate(
+        &self,
+        table_name: &str,
+        column: &str,
+        func: &str,
+        filters: &[ColumnFilter],
+        locale: NumberLocale,
+    ) -> Result<f64> {
+        let data = match self {
+            DataSource::Sqlite(db) => return db.column_aggregate(table_name, column, func, filters),
+            // Only the aggregated column is decoded
+            DataSource::Parquet(table, _, _) if filters.is_empty() => table.column(column)?,
+            _ => self.get_table_data(table_name, 0, i64::MAX as usize, None, filters, locale)?,
+        };
+        let idx = data
+            .columns
+            .iter()
+            .position(|c| c == column)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", column))?;
+        aggregate(func, data.rows.iter().filter_map(|row| row.get(idx)), locale)
+    }
+
+    /// The values of a window computed column, such as `cumsum(Amount)`, for a page of the
+    /// table sorted and filtered as shown: SQLite works them out itself, other sources over
+    /// every row in memory
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn window_column(&self, page: &PageKey, window: &WindowSpec, locale: NumberLocale) -> Result<Vec<Value>> {
+        let sort = page.sort.as_ref();
+        if let DataSource::Sqlite(db) = self {
+            return db.window_column(&page.table, window, sort, &page.filters, page.offset, page.limit);
+        }
+        let data = self.get_table_data(&page.table, 0, i64::MAX as usize, sort, &page.filters, locale)?;
+        let idx = data
+            .columns
+            .iter()
+            .position(|c| *c == window.column)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", window.column))?;
+        let values = window.compute(data.rows.iter().map(|row| row.get(idx).unwrap_or(&Value::Null)), locale);
+        Ok(values.into_iter().skip(page.offset).take(page.limit).collect())
+    }
+
+    /// Attach another SQLite file for cross-database queries; returns its schema name
+    #[instrument(level = "info", skip(self), err)]
+    pub fn attach_database(&self, path: &std::path::Path) -> Result<String> {
+        match self {
+            DataSource::Sqlite(db) => db.attach(path),
+            _ => Err(anyhow::anyhow!("Only SQLite databases can attach other databases")),
+        }
+    }
+
+    /// Schema names of the attached SQLite databases
+    pub fn attached_databases(&self) -> Vec<String> {
+        match self {
+            DataSource::Sqlite(db) => db.attached_databases().unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Indexes and triggers; files have none
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn schema_objects(&self) -> Result<Vec<SchemaObject>> {
+        match self {
+            DataSource::Sqlite(db) => db.get_schema_objects(),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Views, virtual tables, indexes and triggers listed in the sidebar sections; files have none
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn sidebar_objects(&self) -> Result<Vec<SchemaObject>> {
+        match self {
+            DataSource::Sqlite(db) => db.get_sidebar_objects(),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    #[instrument(level = "info", skip(self), err)]
+    pub fn search_all_tables(&self, term: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+        if let DataSource::Sqlite(db) = self {
+            return db.search_all_tables(term, limit);
+        }
+        match self.in_memory_tables() {
+            Some(tables) => Ok(search_tables(&tables, term, limit)),
+            None => Err(anyhow::anyhow!("Global search is only available for SQLite databases and files")),
+        }
+    }
+
+    /// 0-based position of a row (by rowid) in the table's unsorted order
+    pub fn row_position(&self, table_name: &str, rowid: i64) -> Result<usize> {
+        match self {
+            DataSource::Sqlite(db) => db.row_position(table_name, rowid),
+            // Search matches in files carry the row's number as their rowid
+            _ if self.in_memory_tables().is_some() => Ok((rowid - 1).max(0) as usize),
+            _ => Err(anyhow::anyhow!("Row lookup by rowid is only available for SQLite databases")),
+        }
+    }
+
+    /// The tables of a file, which are read into memory whole; None for databases
+    fn in_memory_tables(&self) -> Option<Vec<(String, &QueryResult)>> {
         match self {
-            DataSource::Sqlite(db) => db.execute_custom_query(query, table_name, offset, limit),
-            DataSource::Csv(data, path) => {
+            DataSource::Xlsx(workbook, _, _, _) => workbook.sheets().ok(),
+            DataSource::Html(tables, _) => Some(tables.iter().map(|(name, data)| (name.clone(), data)).collect()),
+            DataSource::Csv(table, _, _) => Some(vec![(self.get_tables().ok()?.first()?.clone(), table.data().ok()?)]),
+            DataSource::Parquet(table, _, _) => Some(vec![(self.get_tables().ok()?.first()?.clone(), table.data().ok()?)]),
+            DataSource::Json(data, _)
+            | DataSource::Arrow(data, _)
+            | DataSource::Dbf(data, _)
+            | DataSource::Statistical(data, _, _) => Some(vec![(self.get_tables().ok()?.first()?.clone(), data)]),
+            _ => None,
+        }
+    }
+
+    /// Run a query and return one page of its result. `params` are the values of its
+    /// `:name` placeholders, which only SQLite databases bind.
+    #[instrument(level = "info", skip(self, params), err)]
+    pub fn execute_custom_query(
+        &self,
+        query: &str,
+        params: &[(String, String)],
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<QueryResult> {
+        if !params.is_empty() && !matches!(self, DataSource::Sqlite(_) | DataSource::Xlsx(..)) {
+            return Err(anyhow::anyhow!("Query parameters are only supported for SQLite databases and Excel files"));
+        }
+        match self {
+            DataSource::Sqlite(db) => db.execute_custom_query(query, params, table_name, offset, limit),
+            DataSource::Csv(table, _, _) => {
                 // For now, use a simple implementation that will be enhanced with DataFusion
                 // This allows basic SQL-like filtering
                 if query.to_uppercase().contains("SELECT") {
                     // Replace 'x' with table name (basic implementation)
-                    let processed_query = self.replace_table_alias(query, table_name);
+                    let _processed_query = self.replace_table_alias(query, table_name);
                     
                     // For demonstration, return the original data with pagination
                     // TODO: Implement actual SQL execution with DataFusion
-                    Ok(paginate_data(data, offset, limit))
+                    table.page(offset, limit)
                 } else {
                     Err(anyhow::anyhow!("Only SELECT queries are supported for CSV files"))
                 }
             }
-            DataSource::Xlsx(sheets, _) => {
-                if let Some((_, sheet_data)) = sheets.iter().find(|(name, _)| name == table_name) {
-                    // Similar limitation for XLSX - DataFusion doesn't support Excel directly
-                    if query.to_uppercase().contains("SELECT") {
-                        Ok(paginate_data(sheet_data, offset, limit))
-                    } else {
-                        Err(anyhow::anyhow!("Custom queries not supported for XLSX files"))
-                    }
-                } else {
-                    Err(anyhow::anyhow!("Sheet '{}' not found", table_name))
-                }
+            DataSource::Xlsx(workbook, _, _, cache) => {
+                sheet_database(workbook, cache)?.execute_custom_query(query, params, table_name, offset, limit)
             }
-            DataSource::Parquet(data, path) => {
+            DataSource::Parquet(table, _, _) => {
                 // For now, use a simple implementation that will be enhanced with DataFusion
                 if query.to_uppercase().contains("SELECT") {
                     // Replace 'x' with table name (basic implementation)
-                    let processed_query = self.replace_table_alias(query, table_name);
+                    let _processed_query = self.replace_table_alias(query, table_name);
                     
                     // For demonstration, return the original data with pagination
                     // TODO: Implement actual SQL execution with DataFusion
-                    Ok(paginate_data(data, offset, limit))
+                    table.page(offset, limit, None)
                 } else {
                     Err(anyhow::anyhow!("Only SELECT queries are supported for Parquet files"))
                 }
             }
+            DataSource::Json(_, _) => Err(anyhow::anyhow!("Custom queries not supported for JSON files")),
+            DataSource::Arrow(_, _) => Err(anyhow::anyhow!("Custom queries not supported for Arrow files")),
+            DataSource::Dbf(_, _) => Err(anyhow::anyhow!("Custom queries not supported for DBF files")),
+            DataSource::Statistical(_, _, format) => {
+                Err(anyhow::anyhow!("Custom queries not supported for {} files", format.name()))
+            }
+            DataSource::Html(_, _) => Err(anyhow::anyhow!("Custom queries not supported for HTML tables")),
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(db) => {
+                db.execute_custom_query(&self.replace_table_alias(query, table_name), offset, limit)
+            }
+            DataSource::Postgres(db) => {
+                db.execute_custom_query(&self.replace_table_alias(query, table_name), offset, limit)
+            }
+            DataSource::Libsql(db) => {
+                db.execute_custom_query(&self.replace_table_alias(query, table_name), offset, limit)
+            }
         }
     }
 
-    pub fn export_table_to_csv(&self, table_name: &str, filename: &str) -> Result<usize> {
+    #[instrument(level = "info", skip(self, data), fields(rows = data.rows.len()), err)]
+    pub fn save_table_data(&self, table_name: &str, data: &QueryResult) -> Result<()> {
         match self {
-            DataSource::Sqlite(db) => db.export_table_to_csv(table_name, filename),
-            DataSource::Csv(data, _) => {
-                self.write_csv_data(data, filename)?;
-                Ok(data.total_rows)
+            DataSource::Sqlite(_) => {
+                Err(anyhow::anyhow!("SQLite tables are saved row by row with apply_row_changes"))
             }
-            DataSource::Xlsx(sheets, _) => {
-                if let Some((_, sheet_data)) = sheets.iter().find(|(name, _)| name == table_name) {
-                    self.write_csv_data(sheet_data, filename)?;
-                    Ok(sheet_data.total_rows)
-                } else {
-                    Err(anyhow::anyhow!("Sheet '{}' not found", table_name))
-                }
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => Err(anyhow::anyhow!("DuckDB databases are opened read-only")),
+            DataSource::Postgres(_) => Err(anyhow::anyhow!("PostgreSQL databases are browsed read-only")),
+            DataSource::Libsql(_) => Err(anyhow::anyhow!("libSQL databases are browsed read-only")),
+            DataSource::Csv(_, path, settings) => {
+                // Compressed CSV is saved decompressed next to it (`data.csv.gz` -> `data.csv`)
+                replace_file(&decompressed_path(path), |tmp| write_delimited(data, &tmp.to_string_lossy(), settings))
+            }
+            DataSource::Html(_, path) if is_web_url(path) => {
+                Err(anyhow::anyhow!("Web pages are browsed read-only; export the table to CSV instead"))
+            }
+            DataSource::Xlsx(_, path, settings, _) if writable_workbook(path) => {
+                replace_file(&decompressed_path(path), |tmp| write_workbook_sheet(path, tmp, table_name, data, settings))
             }
-            DataSource::Parquet(data, _) => {
-                self.write_csv_data(data, filename)?;
-                Ok(data.total_rows)
+            DataSource::Xlsx(_, path, _, _) | DataSource::Html(_, path) => {
+                // Convert original Excel/HTML file path to CSV
+                let csv_path = converted_csv_path(path);
+                self.write_csv_data(data, &csv_path.to_string_lossy())?;
+                Ok(())
+            }
+            DataSource::Parquet(_, path, _)
+            | DataSource::Json(_, path)
+            | DataSource::Arrow(_, path)
+            | DataSource::Dbf(_, path)
+            | DataSource::Statistical(_, path, _) => {
+                // Convert original Parquet/JSON/Arrow/DBF/statistical file path to CSV
+                let csv_path = converted_csv_path(path);
+                self.write_csv_data(data, &csv_path.to_string_lossy())?;
+                Ok(())
             }
         }
     }
 
-    pub fn export_query_to_csv(&self, query: &str, filename: &str) -> Result<usize> {
+    /// New values an update (`U`) gives the rows of `page`, by their index on the page, without
+    /// changing anything. SQLite works them out on the table itself; file pages are loaded into
+    /// an in-memory SQLite table, so the expressions are SQLite's for files too.
+    pub fn preview_update(&self, table_name: &str, update: &BulkUpdate, page: &QueryResult, locale: NumberLocale) -> Result<Vec<(usize, Vec<Value>)>> {
         match self {
-            DataSource::Sqlite(db) => db.export_query_to_csv(query, filename),
-            DataSource::Csv(data, _) => {
-                self.write_csv_data(data, filename)?;
-                Ok(data.total_rows)
-            }
-            DataSource::Xlsx(_, _) => {
-                Err(anyhow::anyhow!("Query export not supported for XLSX files"))
+            DataSource::Sqlite(db) => {
+                if page.columns.first().is_none_or(|c| c != "rowid") {
+                    return Err(anyhow::anyhow!("'{}' has no rowid to tell its rows apart", table_name));
+                }
+                // Rows added to the page have no rowid yet
+                let rowids: Vec<(usize, i64)> = page
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, row)| match row.first() {
+                        Some(Value::Int(rowid)) => Some((idx, *rowid)),
+                        _ => None,
+                    })
+                    .collect();
+                let ids: Vec<i64> = rowids.iter().map(|(_, id)| *id).collect();
+                Ok(db
+                    .preview_update(table_name, update, &ids)?
+                    .into_iter()
+                    .filter_map(|(rowid, values)| {
+                        rowids.iter().find(|(_, id)| *id == rowid).map(|(idx, _)| (*idx, values))
+                    })
+                    .collect())
             }
-            DataSource::Parquet(data, _) => {
-                self.write_csv_data(data, filename)?;
-                Ok(data.total_rows)
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => Err(anyhow::anyhow!("DuckDB databases are opened read-only")),
+            DataSource::Postgres(_) => Err(anyhow::anyhow!("PostgreSQL databases are browsed read-only")),
+            DataSource::Libsql(_) => Err(anyhow::anyhow!("libSQL databases are browsed read-only")),
+            _ => {
+                // The page's rows get rowids 1, 2, ... in order
+                let db = Database::from_tables(&[(table_name.to_string(), page)], locale)?;
+                let rowids: Vec<i64> = (1..=page.rows.len() as i64).collect();
+                Ok(db
+                    .preview_update(table_name, update, &rowids)?
+                    .into_iter()
+                    .map(|(rowid, values)| (rowid as usize - 1, values))
+                    .collect())
             }
         }
     }
 
-    pub fn save_table_data(&self, table_name: &str, data: &QueryResult) -> Result<()> {
+    /// Add, rename or drop a column. SQLite tables are altered at once; file tables are
+    /// changed in memory and written with the next save.
+    pub fn alter_column(&mut self, table_name: &str, edit: &ColumnEdit) -> Result<()> {
         match self {
-            DataSource::Sqlite(_) => {
-                Err(anyhow::anyhow!("Direct SQLite table saving not implemented yet"))
-            }
-            DataSource::Csv(_, path) => {
-                self.write_csv_data(data, &path.to_string_lossy())?;
+            DataSource::Sqlite(db) => db.alter_column(table_name, edit),
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => Err(anyhow::anyhow!("DuckDB databases are opened read-only")),
+            DataSource::Postgres(_) => Err(anyhow::anyhow!("PostgreSQL databases are browsed read-only")),
+            DataSource::Libsql(_) => Err(anyhow::anyhow!("libSQL databases are browsed read-only")),
+            DataSource::Csv(table, _, _) => edit.apply(table.data_mut()?),
+            DataSource::Parquet(table, _, _) => edit.apply(table.data_mut()?),
+            DataSource::Json(data, _)
+            | DataSource::Arrow(data, _)
+            | DataSource::Dbf(data, _)
+            | DataSource::Statistical(data, _, _) => edit.apply(data),
+            DataSource::Xlsx(workbook, _, _, cache) => {
+                edit.apply(workbook.sheet_mut(table_name)?)?;
+                // Queries see the sheet as it is now
+                cache.take();
                 Ok(())
             }
-            DataSource::Xlsx(_, path) => {
-                // Convert original Excel file path to CSV
-                let csv_path = path.with_extension("csv");
-                self.write_csv_data(data, &csv_path.to_string_lossy())?;
-                Ok(())
+            DataSource::Html(tables, _) => {
+                let (_, data) = tables
+                    .iter_mut()
+                    .find(|(name, _)| name == table_name)
+                    .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", table_name))?;
+                edit.apply(data)
             }
-            DataSource::Parquet(_, path) => {
-                // Convert original Parquet file path to CSV
-                let csv_path = path.with_extension("csv");
-                self.write_csv_data(data, &csv_path.to_string_lossy())?;
-                Ok(())
+        }
+    }
+
+    /// The file a save overwrites, for sources saved in place rather than converted to CSV:
+    /// CSV files and `.xlsx`/`.xlsm` workbooks
+    pub fn saved_in_place(&self) -> Option<PathBuf> {
+        match self {
+            DataSource::Csv(_, path, _) => Some(decompressed_path(path)),
+            DataSource::Xlsx(_, path, _, _) if writable_workbook(path) => Some(decompressed_path(path)),
+            _ => None,
+        }
+    }
+
+    /// Handle for cancelling a query running on the worker thread; only SQLite statements
+    /// can be interrupted
+    pub fn interrupt_handle(&self) -> Option<rusqlite::InterruptHandle> {
+        match self {
+            DataSource::Sqlite(db) => Some(db.interrupt_handle()),
+            DataSource::Xlsx(_, _, _, cache) => cache.get().map(|db| db.interrupt_handle()),
+            _ => None,
+        }
+    }
+
+    /// Make the next page of a custom query run it again rather than read the stored result
+    pub fn discard_query_results(&self) {
+        match self {
+            DataSource::Sqlite(db) => db.discard_query_results(),
+            DataSource::Xlsx(_, _, _, cache) => {
+                if let Some(db) = cache.get() {
+                    db.discard_query_results();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Check a data-modifying statement from the query prompt before running it; None when
+    /// the query only reads. Only SQLite databases are written to.
+    pub fn plan_statement(
+        &self,
+        query: &str,
+        params: &[(String, String)],
+        table_name: &str,
+    ) -> Result<Option<StatementPlan>> {
+        match self {
+            DataSource::Sqlite(db) => db.plan_statement(query, params, table_name),
+            DataSource::Xlsx(workbook, _, _, cache) => {
+                match sheet_database(workbook, cache)?.plan_statement(query, params, table_name)? {
+                    Some(_) => Err(anyhow::anyhow!("Excel sheets can only be queried, not changed")),
+                    None => Ok(None),
+                }
             }
+            _ => Ok(None),
+        }
+    }
+
+    /// A syntax error to show in the query prompt before the query runs; None when it parses
+    /// or the file is not queried with a SQL engine
+    pub fn syntax_error(&self, query: &str) -> Option<SyntaxError> {
+        match self {
+            DataSource::Sqlite(db) => db.syntax_error(query),
+            DataSource::Xlsx(workbook, _, _, cache) => sheet_database(workbook, cache).ok()?.syntax_error(query),
+            DataSource::Libsql(_) => syntax_error(query, &SQLiteDialect {}),
+            DataSource::Postgres(_) => syntax_error(query, &PostgreSqlDialect {}),
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => syntax_error(query, &sqlparser::dialect::DuckDbDialect {}),
+            _ => None,
+        }
+    }
+
+    /// The `:name` placeholders of a query, to ask for their values before it runs
+    pub fn query_parameters(&self, query: &str) -> Vec<String> {
+        match self {
+            DataSource::Sqlite(_) | DataSource::Xlsx(..) => query_parameters(query),
+            _ => Vec::new(),
         }
     }
 
+    /// Run a statement checked with `plan_statement`, returning the number of rows changed
+    #[instrument(level = "info", skip(self, plan), fields(sql = %plan.sql), err)]
+    pub fn execute_statement(&self, plan: &StatementPlan) -> Result<u64> {
+        match self {
+            DataSource::Sqlite(db) => db.execute_statement(plan),
+            _ => Err(anyhow::anyhow!("Only SQLite databases can be changed from the query prompt")),
+        }
+    }
+
+    /// Write edited and new rows back to a SQLite table, in one transaction, returning the
+    /// statements undoing them
+    #[instrument(level = "info", skip(self, changes), fields(changes = changes.len()), err)]
+    pub fn apply_row_changes(&self, table_name: &str, changes: &[RowChange]) -> Result<Vec<ReverseStatement>> {
+        match self {
+            DataSource::Sqlite(db) => db.apply_row_changes(table_name, changes),
+            _ => Err(anyhow::anyhow!("Only SQLite tables are saved row by row")),
+        }
+    }
+
+    /// Undo a save to a SQLite table with the statements `apply_row_changes` returned
+    #[instrument(level = "info", skip(self, statements), fields(statements = statements.len()), err)]
+    pub fn revert_changes(&self, statements: &[ReverseStatement]) -> Result<()> {
+        match self {
+            DataSource::Sqlite(db) => db.revert_changes(statements),
+            _ => Err(anyhow::anyhow!("Only saves to SQLite tables can be reverted")),
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn get_original_file_path(&self) -> Option<PathBuf> {
         match self {
-            DataSource::Sqlite(db) => None, // Database doesn't have a simple file path in this context
-            DataSource::Csv(_, path) => Some(path.clone()),
-            DataSource::Xlsx(_, path) => Some(path.clone()),
-            DataSource::Parquet(_, path) => Some(path.clone()),
+            DataSource::Sqlite(_) => None, // Database doesn't have a simple file path in this context
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => None,
+            DataSource::Postgres(_) => None,
+            DataSource::Libsql(_) => None,
+            DataSource::Csv(_, path, _) => Some(path.clone()),
+            DataSource::Xlsx(_, path, _, _) => Some(path.clone()),
+            DataSource::Parquet(_, path, _) => Some(path.clone()),
+            DataSource::Json(_, path) => Some(path.clone()),
+            DataSource::Arrow(_, path) => Some(path.clone()),
+            DataSource::Dbf(_, path) => Some(path.clone()),
+            DataSource::Statistical(_, path, _) => Some(path.clone()),
+            DataSource::Html(_, path) => Some(path.clone()),
         }
     }
 
@@ -180,20 +839,38 @@ impl DataSource {
     pub fn get_effective_save_path(&self) -> Option<PathBuf> {
         match self {
             DataSource::Sqlite(_) => None, // SQLite doesn't save to files directly
-            DataSource::Csv(_, path) => Some(path.clone()),
-            DataSource::Xlsx(_, path) => Some(path.with_extension("csv")), // Excel saves as CSV
-            DataSource::Parquet(_, path) => Some(path.with_extension("csv")), // Parquet saves as CSV
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => None, // read-only
+            DataSource::Postgres(_) => None, // read-only
+            DataSource::Libsql(_) => None, // read-only
+            DataSource::Csv(_, path, _) => Some(decompressed_path(path)),
+            DataSource::Xlsx(_, path, _, _) => Some(converted_csv_path(path)), // Excel saves as CSV
+            DataSource::Parquet(_, path, _) => Some(converted_csv_path(path)), // Parquet saves as CSV
+            DataSource::Json(_, path) => Some(converted_csv_path(path)), // JSON saves as CSV
+            DataSource::Arrow(_, path) => Some(converted_csv_path(path)), // Arrow saves as CSV
+            DataSource::Dbf(_, path) => Some(converted_csv_path(path)), // DBF saves as CSV
+            DataSource::Statistical(_, path, _) => Some(converted_csv_path(path)), // so do .dta/.sav/.sas7bdat
+            DataSource::Html(_, path) if is_web_url(path) => None, // read-only
+            DataSource::Html(_, path) => Some(converted_csv_path(path)), // and HTML
         }
     }
 
     /// Reload the data from the current file (to reflect saved changes)
+    #[instrument(level = "info", skip(self), err)]
     pub fn reload_data(&mut self) -> Result<()> {
         match self {
             DataSource::Sqlite(_) => {
                 // SQLite doesn't need reloading since it reads from the database directly
                 Ok(())
             }
-            DataSource::Csv(data, path) => {
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => Ok(()),
+            DataSource::Postgres(_) => Ok(()),
+            DataSource::Libsql(_) => Ok(()),
+            DataSource::Csv(table, path, settings) => {
+                if detect_compression(&*path).is_some() && decompressed_path(&*path).exists() {
+                    *path = decompressed_path(&*path);
+                }
                 // Check if the file was converted to CSV (original was Excel/Parquet)
                 let effective_path = path.clone();
                 if effective_path.extension().and_then(|s| s.to_str()) != Some("csv") {
@@ -201,44 +878,94 @@ impl DataSource {
                     let csv_path = effective_path.with_extension("csv");
                     if csv_path.exists() {
                         // Load from the converted CSV file
-                        *data = read_csv_file(&csv_path)?;
+                        *table = CsvTable::open(&csv_path, &CsvSettings::default())?;
                         // Update the path to point to the CSV file for future operations
                         *path = csv_path;
                     } else {
                         // Reload original CSV
-                        *data = read_csv_file(path)?;
+                        *table = CsvTable::open(path, settings)?;
                     }
                 } else {
                     // Reload original CSV
-                    *data = read_csv_file(path)?;
+                    *table = CsvTable::open(path, settings)?;
                 }
                 Ok(())
             }
-            DataSource::Xlsx(sheets, path) => {
+            DataSource::Xlsx(workbook, path, settings, cache) => {
+                cache.take();
                 // Check if a CSV version was created
-                let csv_path = path.with_extension("csv");
+                let csv_path = converted_csv_path(&*path);
                 if csv_path.exists() {
                     // Convert to CSV DataSource since the file was saved as CSV
-                    let csv_data = read_csv_file(&csv_path)?;
+                    let csv_data = read_csv_file(&csv_path, &CsvSettings::default())?;
                     // This is a bit tricky - we need to replace ourselves with a CSV DataSource
                     // For now, we'll update the sheets to contain the CSV data
-                    sheets.clear();
-                    sheets.push(("CSV Data".to_string(), csv_data));
+                    *workbook = Workbook::with_sheets(path, settings, vec![("CSV Data".to_string(), csv_data)]);
                 } else {
                     // Reload original Excel file
-                    *sheets = read_xlsx_file(path)?;
+                    *workbook = Workbook::open(path, settings)?;
                 }
                 Ok(())
             }
-            DataSource::Parquet(data, path) => {
+            DataSource::Parquet(table, path, settings) => {
                 // Check if a CSV version was created
-                let csv_path = path.with_extension("csv");
+                let csv_path = converted_csv_path(&*path);
                 if csv_path.exists() {
                     // Load from the converted CSV file
-                    *data = read_csv_file(&csv_path)?;
+                    *table = ParquetTable::with_rows(path, settings, read_csv_file(&csv_path, &CsvSettings::default())?);
                 } else {
                     // Reload original Parquet file
-                    *data = read_parquet_file(path)?;
+                    *table = ParquetTable::open(path, settings)?;
+                }
+                Ok(())
+            }
+            DataSource::Json(data, path) => {
+                let csv_path = converted_csv_path(&*path);
+                if csv_path.exists() {
+                    *data = read_csv_file(&csv_path, &CsvSettings::default())?;
+                } else {
+                    *data = read_json_file(path)?;
+                }
+                Ok(())
+            }
+            DataSource::Arrow(data, path) => {
+                let csv_path = converted_csv_path(&*path);
+                if csv_path.exists() {
+                    *data = read_csv_file(&csv_path, &CsvSettings::default())?;
+                } else {
+                    *data = read_arrow_file(path)?;
+                }
+                Ok(())
+            }
+            DataSource::Dbf(data, path) => {
+                let csv_path = converted_csv_path(&*path);
+                if csv_path.exists() {
+                    *data = read_csv_file(&csv_path, &CsvSettings::default())?;
+                } else {
+                    *data = read_dbf_file(path)?;
+                }
+                Ok(())
+            }
+            DataSource::Statistical(data, path, format) => {
+                let csv_path = converted_csv_path(&*path);
+                if csv_path.exists() {
+                    *data = read_csv_file(&csv_path, &CsvSettings::default())?;
+                } else {
+                    *data = read_statistical_file(path, *format)?;
+                }
+                Ok(())
+            }
+            // Web pages aren't saved, so there is nothing new to fetch
+            DataSource::Html(_, path) if is_web_url(path) => Ok(()),
+            DataSource::Html(tables, path) => {
+                // Like workbooks, a saved table replaces the tables of the page
+                let csv_path = converted_csv_path(&*path);
+                if csv_path.exists() {
+                    let csv_data = read_csv_file(&csv_path, &CsvSettings::default())?;
+                    tables.clear();
+                    tables.push(("CSV Data".to_string(), csv_data));
+                } else {
+                    *tables = read_html_tables(path)?;
                 }
                 Ok(())
             }
@@ -253,7 +980,7 @@ impl DataSource {
         
         // Write data rows
         for row in &data.rows {
-            writer.write_record(row)?;
+            writer.write_record(row.iter().map(|cell| cell.to_string()))?;
         }
         
         writer.flush()?;
@@ -261,7 +988,14 @@ impl DataSource {
     }
 
     pub fn supports_custom_queries(&self) -> bool {
-        matches!(self, DataSource::Sqlite(_) | DataSource::Csv(_, _) | DataSource::Parquet(_, _))
+        match self {
+            DataSource::Sqlite(_) | DataSource::Csv(_, _, _) | DataSource::Parquet(_, _, _) | DataSource::Postgres(_) => true,
+            DataSource::Xlsx(..) => true,
+            DataSource::Libsql(_) => true,
+            #[cfg(feature = "duckdb")]
+            DataSource::DuckDb(_) => true,
+            _ => false,
+        }
     }
 
     // Helper function to execute DataFusion queries (TODO: implement)
@@ -269,6 +1003,9 @@ impl DataSource {
 
     // Helper function to replace 'x' with table name (similar to SQLite implementation)
     fn replace_table_alias(&self, query: &str, table_name: &str) -> String {
+        if table_name.is_empty() {
+            return query.to_string();
+        }
         let words: Vec<&str> = query.split_whitespace().collect();
         let mut replaced_words = Vec::new();
         
@@ -298,10 +1035,197 @@ impl DataSource {
     // TODO: Add DataFusion integration here when build complexity is resolved
 }
 
+/// Case-insensitive substring search over every cell of tables held in memory, up to `limit`
+/// matches. A match's `rowid` is the 1-based number of its row.
+fn search_tables(tables: &[(String, &QueryResult)], term: &str, limit: usize) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for (table, data) in tables {
+        for (position, row) in data.rows.iter().enumerate() {
+            for (column, value) in data.columns.iter().zip(row) {
+                if matches.len() >= limit {
+                    return matches;
+                }
+                if let Some(snippet) = make_snippet(&value.as_text(), term) {
+                    matches.push(SearchMatch {
+                        table: table.clone(),
+                        rowid: position as i64 + 1,
+                        column: column.clone(),
+                        snippet,
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// Stats, histogram or value counts of a column of rows already fetched, such as a query's
+pub fn summarize_rows(data: &QueryResult, column: &str, kind: SummaryKind, locale: NumberLocale) -> Result<ColumnSummary> {
+    let idx = data
+        .columns
+        .iter()
+        .position(|c| c == column)
+        .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", column))?;
+    Ok(summarize(column, data.rows.iter().filter_map(|row| row.get(idx)), kind, locale))
+}
+
+/// Write data back using the file's own delimiter and header settings
+fn write_delimited(data: &QueryResult, filename: &str, settings: &CsvSettings) -> Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(settings.delimiter_byte())
+        .from_path(filename)?;
+
+    if settings.has_header {
+        writer.write_record(&data.columns)?;
+    }
+    for row in &data.rows {
+        writer.write_record(row.iter().map(|cell| cell.to_string()))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Workbooks whose sheets are written back in place; legacy `.xls`, `.xlsb` and `.ods` are
+/// saved to a CSV next to them
+fn writable_workbook(path: &Path) -> bool {
+    matches!(lowercase_extension(&decompressed_path(path)).as_str(), "xlsx" | "xlsm")
+}
+
+/// Write the edited cells of one sheet of the workbook at `source` to `target`, keeping the
+/// other sheets and the formatting; only cells whose value changed lose their formula.
+/// Cells are compared with the sheet as saved, so added, renamed and dropped columns are
+/// written too, and cells left past the end of the table are cleared.
+fn write_workbook_sheet(source: &Path, target: &Path, sheet: &str, data: &QueryResult, settings: &XlsxSettings) -> Result<()> {
+    let saved = read_xlsx_file(source, settings)?
+        .into_iter()
+        .find(|(name, _)| name == sheet)
+        .map(|(_, data)| data)
+        .ok_or_else(|| anyhow::anyhow!("Sheet '{}' not found", sheet))?;
+    let bytes = read_input_bytes(source)?;
+    // Rows and columns were read relative to the sheet's first used cell
+    let (origin_row, origin_col) = calamine::open_workbook_auto_from_rs(Cursor::new(bytes.clone()))?
+        .worksheet_range(sheet)?
+        .start()
+        .unwrap_or((0, 0));
+    let mut book = umya_spreadsheet::reader::xlsx::read_reader(Cursor::new(bytes), true)
+        .map_err(|e| anyhow::anyhow!("Failed to read the workbook: {}", e))?;
+    let worksheet = book
+        .sheet_by_name_mut(sheet)
+        .map_err(|e| anyhow::anyhow!("Sheet '{}' not found: {}", sheet, e))?;
+
+    let header_row = origin_row + settings.header_row as u32 + 1; // 1-based
+    let position = |row: u32, col: usize| (origin_col + col as u32 + 1, row);
+    for (col_idx, column) in data.columns.iter().enumerate() {
+        if saved.columns.get(col_idx) != Some(column) {
+            worksheet.cell_mut(position(header_row, col_idx)).set_value_string(column.clone());
+        }
+    }
+    for (row_idx, row) in data.rows.iter().enumerate() {
+        for (col_idx, value) in row.iter().enumerate() {
+            if saved.rows.get(row_idx).and_then(|row| row.get(col_idx)) == Some(value) {
+                continue;
+            }
+            let cell = worksheet.cell_mut(position(header_row + 1 + row_idx as u32, col_idx));
+            match value {
+                Value::Null => cell.set_blank(),
+                Value::Text(text) if text.is_empty() => cell.set_blank(),
+                Value::Int(i) => cell.set_value_number(*i as f64),
+                Value::Float(f) => cell.set_value_number(*f),
+                Value::Bool(b) => cell.set_value_bool(*b),
+                Value::Date(date) => {
+                    if cell.style().number_format().is_none_or(|format| format.format_code() == "General") {
+                        cell.style_mut().number_format_mut().set_format_code(if value.is_date_only() {
+                            "yyyy-mm-dd"
+                        } else {
+                            "yyyy-mm-dd hh:mm:ss"
+                        });
+                    }
+                    cell.set_value_number(excel_serial(date))
+                }
+                Value::Text(_) | Value::Blob(_) => cell.set_value_string(value.to_string()),
+            };
+        }
+    }
+
+    // Columns the table no longer reaches, e.g. after dropping one
+    for col_idx in data.columns.len()..saved.columns.len() {
+        for row in header_row..=header_row + saved.rows.len() as u32 {
+            worksheet.cell_mut(position(row, col_idx)).set_blank();
+        }
+    }
+    // and rows, after deleting some
+    for row_idx in data.rows.len()..saved.rows.len() {
+        for col_idx in 0..saved.columns.len().max(data.columns.len()) {
+            worksheet.cell_mut(position(header_row + 1 + row_idx as u32, col_idx)).set_blank();
+        }
+    }
+
+    umya_spreadsheet::writer::xlsx::write(&book, target).map_err(|e| anyhow::anyhow!("Failed to write the workbook: {}", e))
+}
+
+/// Days since Excel's epoch, with the time of day as the fraction
+fn excel_serial(date: &NaiveDateTime) -> f64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default().and_time(NaiveTime::MIN);
+    (*date - epoch).num_milliseconds() as f64 / 86_400_000.0
+}
+
+/// Write `path` through a temporary file next to it, renamed over it once complete, so a
+/// failed save leaves the file as it was. The previous version is copied to its backup path.
+fn replace_file(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Not a file path: {}", path.display()))?;
+    let tmp = path.with_file_name(format!(".{}.sqbrowser-tmp", name.to_string_lossy()));
+    if let Err(e) = write(&tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    if path.exists() {
+        if let Ok(metadata) = fs::metadata(path) {
+            let _ = fs::set_permissions(&tmp, metadata.permissions());
+        }
+        fs::copy(path, backup_path(path)).context("Failed to back up the file")?;
+    }
+    fs::rename(&tmp, path).context("Failed to replace the file")?;
+    Ok(())
+}
+
+/// The workbook's sheets as tables of an in-memory SQLite database, loaded on the first query
+fn sheet_database<'a>(workbook: &Workbook, cache: &'a OnceLock<Database>) -> Result<&'a Database> {
+    if let Some(db) = cache.get() {
+        return Ok(db);
+    }
+    let db = Database::from_tables(&workbook.sheets()?, NumberLocale::default())?;
+    Ok(cache.get_or_init(|| db))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::Path;
+    use crate::value::{text_rows, Value};
+
+    #[test]
+    fn test_search_in_memory_tables() {
+        let data = QueryResult {
+            columns: vec!["name".to_string(), "city".to_string()],
+            rows: text_rows(vec![vec!["Alice", "Paris"], vec!["Bob", "Lisbon"]]),
+            total_rows: 2,
+        };
+        let tables = vec![("People".to_string(), &data)];
+
+        let matches = search_tables(&tables, "LIS", 10);
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].rowid, matches[0].column.as_str()), (2, "city"));
+        assert_eq!(search_tables(&tables, "i", 2).len(), 2);
+
+        let test_file = std::env::temp_dir().join("sqbrowser_search.csv");
+        std::fs::write(&test_file, "name,city\nAlice,Paris\nBob,Lisbon").unwrap();
+        let data_source = DataSource::open(test_file).unwrap();
+        let found = data_source.search_all_tables("bob", 10).unwrap();
+        assert_eq!(found[0].table, "CSV Data");
+        assert_eq!(data_source.row_position(&found[0].table, found[0].rowid).unwrap(), 1);
+    }
 
     #[test]
     fn test_csv_query_support() {
@@ -318,7 +1242,8 @@ mod tests {
         
         // Test executing a basic query
         let result = data_source.execute_custom_query(
-            "SELECT * FROM x", 
+            "SELECT * FROM x",
+            &[],
             "CSV Data", 
             0, 
             10
@@ -355,7 +1280,7 @@ mod tests {
         ];
 
         for query in test_queries {
-            let result = data_source.execute_custom_query(query, "CSV Data", 0, 10);
+            let result = data_source.execute_custom_query(query, &[], "CSV Data", 0, 10);
             match result {
                 Ok(_) => println!("✓ Query '{}' executed successfully", query),
                 Err(e) => println!("✗ Query '{}' failed: {}", query, e),
@@ -366,6 +1291,127 @@ mod tests {
         std::fs::remove_file(test_file).ok();
     }
 
+    #[test]
+    fn test_csv_settings_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_file = temp_dir.path().join("semicolon.csv");
+        std::fs::write(&test_file, "Alice;30\nBob;25\n").unwrap();
+
+        let mut config = Config::default();
+        config.csv.delimiter = ";".to_string();
+        config.csv.has_header = false;
+
+        let mut data_source = DataSource::open_with_config(test_file.clone(), &config).unwrap();
+        let mut data = data_source.get_table_data("CSV Data", 0, 10, None, &[], NumberLocale::En).unwrap();
+        assert_eq!(data.columns, vec!["Column1", "Column2"]);
+        assert_eq!(data.rows.len(), 2);
+        assert_eq!(data.rows[1], vec!["Bob", "25"]);
+
+        data.rows[1][1] = Value::from("26");
+        data_source.save_table_data("CSV Data", &data).unwrap();
+        assert_eq!(std::fs::read_to_string(&test_file).unwrap(), "Alice;30\nBob;26\n");
+        assert_eq!(std::fs::read_to_string(backup_path(&test_file)).unwrap(), "Alice;30\nBob;25\n");
+
+        data_source.reload_data().unwrap();
+        let reloaded = data_source.get_table_data("CSV Data", 0, 10, None, &[], NumberLocale::En).unwrap();
+        assert_eq!(reloaded.rows[1], vec!["Bob", "26"]);
+    }
+
+    #[test]
+    fn test_legacy_and_binary_workbooks_save_as_csv() {
+        for name in ["people.xls", "people.xlsb"] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let path = temp_dir.path().join(name);
+            std::fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name), &path).unwrap();
+            let original = std::fs::read(&path).unwrap();
+            assert!(!writable_workbook(&path));
+
+            let data_source = DataSource::open(path.clone()).unwrap();
+            let mut data = data_source.get_all_table_data("People").unwrap();
+            data.rows[0][0] = Value::from("Alicia");
+            data_source.save_table_data("People", &data).unwrap();
+            assert_eq!(data_source.saved_in_place(), None);
+            assert_eq!(data_source.get_effective_save_path(), Some(temp_dir.path().join("people.csv")));
+
+            let csv = std::fs::read_to_string(temp_dir.path().join("people.csv")).unwrap();
+            assert_eq!(csv.lines().collect::<Vec<_>>(), vec!["name,age", "Alicia,30", "Bob,25"], "{}", name);
+            assert_eq!(std::fs::read(&path).unwrap(), original, "{} is left as it was", name);
+        }
+    }
+
+    #[test]
+    fn test_xlsx_save_keeps_the_other_sheets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("book.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.sheet_by_name_mut("Sheet1").unwrap();
+        // The table starts at B2, so rows and columns are offset when written back
+        sheet.cell_mut("B2").set_value_string("name");
+        sheet.cell_mut("C2").set_value_string("n");
+        sheet.cell_mut("D2").set_value_string("double");
+        sheet.cell_mut("B3").set_value_string("Ann");
+        sheet.cell_mut("C3").set_value_number(1);
+        sheet.cell_mut("D3").set_formula("C3*2");
+        sheet.cell_mut("B4").set_value_string("Bob");
+        sheet.cell_mut("C4").set_value_number(2);
+        book.new_sheet("Notes").unwrap().cell_mut("A1").set_value_string("keep");
+        umya_spreadsheet::writer::xlsx::write(&book, &path).unwrap();
+
+        let data_source = DataSource::open(path.clone()).unwrap();
+        let mut data = data_source.get_table_data("Sheet1", 0, 10, None, &[], NumberLocale::En).unwrap();
+        assert_eq!(data.columns, vec!["name", "n", "double"]);
+        data.rows[1][1] = Value::Int(5);
+        data.rows.push(vec![Value::from("Cy"), Value::Int(3), Value::from("")]);
+        data_source.save_table_data("Sheet1", &data).unwrap();
+        assert_eq!(data_source.saved_in_place(), Some(path.clone()));
+        assert!(backup_path(&path).exists());
+
+        let book = umya_spreadsheet::reader::xlsx::read(&path).unwrap();
+        let sheet = book.sheet_by_name("Sheet1").unwrap();
+        assert_eq!(sheet.value("C4"), "5");
+        assert_eq!((sheet.value("B5"), sheet.value("C5")), ("Cy".to_string(), "3".to_string()));
+        assert_eq!(sheet.cell("D3").unwrap().formula(), "C3*2");
+        assert_eq!(book.sheet_by_name("Notes").unwrap().value("A1"), "keep");
+
+        // Column changes made in memory are written with the next save
+        let mut data_source = DataSource::open(path.clone()).unwrap();
+        data_source.alter_column("Sheet1", &ColumnEdit::Drop("n".to_string())).unwrap();
+        data_source.alter_column("Sheet1", &ColumnEdit::parse_add("note = hi").unwrap()).unwrap();
+        let data = data_source.get_all_table_data("Sheet1").unwrap();
+        data_source.save_table_data("Sheet1", &data).unwrap();
+        let book = umya_spreadsheet::reader::xlsx::read(&path).unwrap();
+        let sheet = book.sheet_by_name("Sheet1").unwrap();
+        assert_eq!((sheet.value("C2"), sheet.value("D2"), sheet.value("E2")), ("double".to_string(), "note".to_string(), String::new()));
+        assert_eq!((sheet.value("C5"), sheet.value("D5"), sheet.value("E5")), (String::new(), "hi".to_string(), String::new()));
+
+        // Deleted rows leave the sheet shorter
+        let mut data = data_source.get_all_table_data("Sheet1").unwrap();
+        data.rows.remove(0);
+        data_source.save_table_data("Sheet1", &data).unwrap();
+        let data = DataSource::open(path.clone()).unwrap().get_all_table_data("Sheet1").unwrap();
+        assert_eq!(data.rows.len(), 2);
+        assert_eq!(data.rows[0][0], "Bob");
+    }
+
+    #[test]
+    fn test_replace_file_keeps_the_file_on_failure() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("data.csv");
+        std::fs::write(&path, "old").unwrap();
+
+        let failed = replace_file(&path, |tmp| {
+            std::fs::write(tmp, "partial")?;
+            Err(anyhow::anyhow!("disk full"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        replace_file(&path, |tmp| Ok(std::fs::write(tmp, "new")?)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), "old");
+    }
+
     #[test] 
     fn test_parquet_query_support() {
         let parquet_file = "customer_features_2024-03.parquet";
@@ -380,7 +1426,8 @@ mod tests {
                     
                     // Test executing a basic query
                     let result = ds.execute_custom_query(
-                        "SELECT * FROM x", 
+                        "SELECT * FROM x",
+                        &[],
                         "Parquet Data", 
                         0, 
                         5
@@ -404,4 +1451,44 @@ mod tests {
             println!("⚠ Parquet test file not found, skipping test");
         }
     }
+
+    #[test]
+    fn test_xlsx_sheets_are_queried_with_sql() {
+        let sheet = |columns: &[&str], rows: &[&[&str]]| QueryResult {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: rows.iter().map(|r| r.iter().map(|v| Value::from(*v)).collect()).collect(),
+            total_rows: rows.len(),
+        };
+        let sheets = vec![
+            (
+                "Orders".to_string(),
+                sheet(&["id", "customer", "amount"], &[&["1", "A", "10.5"], &["2", "B", "4"], &["3", "A", "20"]]),
+            ),
+            ("Customers".to_string(), sheet(&["name", "name"], &[&["A", "Alice"], &["B", "Bob"]])),
+        ];
+        let workbook = Workbook::with_sheets(Path::new("book.xlsx"), &XlsxSettings::default(), sheets);
+        let data_source = DataSource::Xlsx(workbook, PathBuf::from("book.xlsx"), XlsxSettings::default(), OnceLock::new());
+        assert!(data_source.supports_custom_queries());
+
+        let result = data_source
+            .execute_custom_query(
+                "SELECT customer, SUM(amount) FROM x WHERE amount > 5 GROUP BY customer",
+                &[],
+                "Orders",
+                0,
+                10,
+            )
+            .unwrap();
+        assert_eq!(result.rows, vec![vec!["A".to_string(), "30.5".to_string()]]);
+
+        // Repeated headers get numbered so the sheet still loads
+        let params = vec![("min".to_string(), "5".to_string())];
+        let query = "SELECT c.\"name (2)\", o.amount FROM Orders o JOIN Customers c ON c.name = o.customer \
+                     WHERE o.amount < :min";
+        assert_eq!(data_source.query_parameters(query), vec!["min"]);
+        let result = data_source.execute_custom_query(query, &params, "Orders", 0, 10).unwrap();
+        assert_eq!(result.rows, vec![vec!["Bob".to_string(), "4".to_string()]]);
+
+        assert!(data_source.plan_statement("DELETE FROM x", &[], "Orders").is_err());
+    }
 }
\ No newline at end of file
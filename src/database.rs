@@ -1,7 +1,35 @@
 use anyhow::{Context, Result};
-use rusqlite::{Connection, Row};
-use std::path::Path;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use sqlparser::ast::{
+    Expr, Ident, ObjectName, Query, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins, Visit,
+    VisitMut, Visitor, VisitorMut,
+};
+use sqlparser::dialect::{Dialect, SQLiteDialect};
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+use crate::bulk_edit::BulkUpdate;
+use crate::column_edit::ColumnEdit;
+use crate::column_stats::{
+    Bins, ColumnStats, Histogram, NumericSummary, ValueCounts, HISTOGRAM_BINS, TOP_VALUES, VALUE_COUNTS_LIMIT,
+};
+use crate::config::NumberLocale;
+use crate::file_reader::{infer_column_types, parse_bool, parse_date, ColumnType};
+use crate::filter::{where_clause, ColumnFilter, SqlFlavor};
+use crate::format::{parse_integer, parse_number};
+use crate::persistence::{ReverseStatement, SortOrder, SqlValue};
+use crate::value::Value;
+use crate::window::WindowSpec;
+
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct TableInfo {
     pub name: String,
@@ -9,228 +37,2627 @@ pub struct TableInfo {
     pub total_rows: usize,
 }
 
+/// A single cell matching a global search
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub table: String,
+    pub rowid: i64, // the row number in files, which have no rowid
+    pub column: String,
+    pub snippet: String,
+}
+
+/// An index or trigger, shown read-only in the schema objects view
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaObject {
+    pub kind: String, // "index" or "trigger"; the sidebar also lists "view" and "virtual table"
+    pub name: String,
+    pub table: String,
+    pub sql: Option<String>, // None for indexes SQLite creates for UNIQUE and PRIMARY KEY
+}
+
+/// Size and row count of a table, for the overview next to the table list
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    pub rows: usize,
+    pub columns: usize,
+    pub size_bytes: Option<u64>,    // pages of the table and its indexes; None without the dbstat table
+    pub analyzed_rows: Option<u64>, // row estimate stored by the last ANALYZE; None if never analyzed
+}
+
+/// The structure of one table, shown in the schema panel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableSchema {
+    pub columns: Vec<ColumnSchema>,
+    pub indexes: Vec<IndexSchema>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub inferred: bool, // column types guessed from the values, for files without a schema
+}
+
+impl TableSchema {
+    /// Columns typed as inferred from their values, for tables without a declared schema
+    pub fn inferred(columns: &[String], types: &[ColumnType]) -> Self {
+        let columns = columns
+            .iter()
+            .zip(types)
+            .map(|(name, ty)| ColumnSchema {
+                name: name.clone(),
+                column_type: ty.label().to_string(),
+                ..Default::default()
+            })
+            .collect();
+        Self { columns, inferred: true, ..Default::default() }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub column_type: String, // as declared; may be empty in SQLite
+    pub primary_key: bool,
+    pub not_null: bool,
+    pub default: Option<String>, // default expression as written
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSchema {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub origin: String, // "c" for CREATE INDEX, "u" for UNIQUE and "pk" for PRIMARY KEY constraints
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub table: String,
+    pub references: Vec<String>, // empty when the parent table's primary key is meant
+    pub on_update: String,
+    pub on_delete: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct QueryResult {
     pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+    pub rows: Vec<Vec<Value>>,
     pub total_rows: usize,
 }
 
-pub struct Database {
-    conn: Connection,
-}
+/// A data-modifying statement from the query prompt, checked before it runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementPlan {
+    pub sql: String,                  // with the `x` shorthand resolved
+    pub params: Vec<(String, String)>, // values bound to its `:name` placeholders
+    pub affected_rows: Option<u64>,   // counted in a rolled-back dry run; None for schema changes and the like
+}
+
+/// Why the SQL parser rejects a query typed at the query prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub position: Option<usize>, // character offset in the query, when the parser reports one
+}
+
+/// An edited, new or deleted row of a page of table data, as it is written back to the database
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    Update { rowid: i64, values: Vec<(String, String)> }, // changed columns only
+    Insert { values: Vec<(String, String)> },             // filled-in columns only
+    Delete { rowid: i64 },
+}
+
+/// Compare an edited page with the page as it was loaded (`rowid` first). Only the
+/// loaded columns are compared, so computed columns appended by the UI are never written.
+/// Loaded rows missing from the edited page are deleted.
+pub fn row_changes(original: &QueryResult, edited: &QueryResult) -> Result<Vec<RowChange>> {
+    if original.columns.first().map(|c| c.as_str()) != Some("rowid") {
+        return Err(anyhow::anyhow!("Only table data loaded with its rowid can be saved"));
+    }
+    if edited.columns.get(..original.columns.len()) != Some(&original.columns[..]) {
+        return Err(anyhow::anyhow!("The columns changed since the data was loaded; reload the table"));
+    }
+
+    let columns = &original.columns[1..];
+    let mut changes = Vec::new();
+    for row in &edited.rows {
+        let cell = |idx: usize| row.get(idx + 1).map(|v| v.as_text()).unwrap_or_default();
+        if row[0].is_blank() {
+            let values: Vec<(String, String)> = columns
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| !cell(*idx).is_empty())
+                .map(|(idx, column)| (column.clone(), cell(idx).to_string()))
+                .collect();
+            changes.push(RowChange::Insert { values });
+            continue;
+        }
+
+        let original_row = original
+            .rows
+            .iter()
+            .find(|r| r[0] == row[0])
+            .ok_or_else(|| anyhow::anyhow!("Row with rowid {} is not part of the loaded page", row[0]))?;
+        let values: Vec<(String, String)> = columns
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| original_row.get(idx + 1).map(|v| v.as_text()).unwrap_or_default() != cell(*idx))
+            .map(|(idx, column)| (column.clone(), cell(idx).to_string()))
+            .collect();
+        if !values.is_empty() {
+            let rowid = match &row[0] {
+                Value::Int(rowid) => *rowid,
+                other => other
+                    .as_text()
+                    .parse()
+                    .with_context(|| format!("Invalid rowid '{}'", other))?,
+            };
+            changes.push(RowChange::Update { rowid, values });
+        }
+    }
+    for row in &original.rows {
+        if let Value::Int(rowid) = row[0] {
+            if !edited.rows.iter().any(|r| r[0] == row[0]) {
+                changes.push(RowChange::Delete { rowid });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Size and modification time of the database file and its WAL, used to notice
+/// commits made by other processes without querying the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeSignature {
+    files: Vec<Option<(SystemTime, u64)>>,
+}
+
+/// Temp table holding the rows of the last custom query
+const QUERY_RESULTS_TABLE: &str = "sqbrowser_query_results";
+
+/// The last custom query, whose rows are stored in `QUERY_RESULTS_TABLE`
+struct QueryResults {
+    key: (String, Vec<(String, String)>, String), // query as typed, its parameter values and the table it was run on
+    columns: Vec<String>,  // as the query names them; the temp table's are c0, c1, ...
+    total_rows: usize,
+}
+
+pub struct Database {
+    conn: Connection,
+    query_results: RefCell<Option<QueryResults>>,
+}
+
+impl Database {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let conn = Connection::open(path)
+            .context("Failed to open database")?;
+        register_regexp(&conn)?;
+        Ok(Self { conn, query_results: RefCell::new(None) })
+    }
+
+    /// An in-memory database holding `tables` (e.g. the sheets of a workbook), each column
+    /// typed as inferred from its values, so they can be queried with SQL
+    pub fn from_tables(tables: &[(String, &QueryResult)], locale: NumberLocale) -> Result<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        register_regexp(&conn)?;
+        for (name, data) in tables {
+            write_typed_table(&mut conn, name, data, &infer_column_types(data, locale), locale)
+                .with_context(|| format!("Failed to load '{}' for querying", name))?;
+        }
+        Ok(Self { conn, query_results: RefCell::new(None) })
+    }
+
+    pub fn open_readonly<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Failed to open database in read-only mode")?;
+        register_regexp(&conn)?;
+        Ok(Self { conn, query_results: RefCell::new(None) })
+    }
+
+    /// The database file; None for in-memory databases
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.conn.path().filter(|p| !p.is_empty()).map(PathBuf::from)
+    }
+
+    /// None for in-memory databases, which no other process can change
+    pub fn change_signature(&self) -> Option<ChangeSignature> {
+        let path = self.file_path()?;
+        let mut wal_path = path.clone().into_os_string();
+        wal_path.push("-wal");
+
+        let stat = |path: &Path| {
+            let metadata = std::fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        };
+        Some(ChangeSignature {
+            files: vec![stat(&path), stat(Path::new(&wal_path))],
+        })
+    }
+
+    pub fn get_tables(&self) -> Result<Vec<String>> {
+        // Views follow the tables; both can be browsed
+        let mut stmt = self.conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') ORDER BY type, name"
+        )?;
+        
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut tables = Vec::new();
+        for row in rows {
+            tables.push(row?);
+        }
+
+        // Tables of attached databases follow, qualified with their schema
+        for schema in self.attached_databases()? {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT name FROM {}.sqlite_master WHERE type='table' ORDER BY name",
+                quote_identifier(&schema)
+            ))?;
+            let names = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            tables.extend(names.into_iter().map(|name| format!("{}.{}", schema, name)));
+        }
+
+        Ok(tables)
+    }
+
+    /// Attach another SQLite file under a schema named after it (`sales.db` -> `sales`),
+    /// so queries can join across both. Returns the schema name.
+    pub fn attach(&self, path: &Path) -> Result<String> {
+        // ATTACH would silently create a missing file
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("File '{}' not found", path.display()));
+        }
+
+        let stem: String = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let stem = if stem.is_empty() { "db".to_string() } else { stem };
+        let taken = |name: &str| -> Result<bool> {
+            Ok(["main", "temp"].contains(&name) || self.attached_databases()?.iter().any(|s| s == name))
+        };
+        let mut schema = stem.clone();
+        let mut suffix = 2;
+        while taken(&schema)? {
+            schema = format!("{}_{}", stem, suffix);
+            suffix += 1;
+        }
+
+        self.conn
+            .execute(
+                &format!("ATTACH DATABASE ?1 AS {}", quote_identifier(&schema)),
+                [path.to_string_lossy()],
+            )
+            .with_context(|| format!("Failed to attach '{}'", path.display()))?;
+        // A file that isn't a database only fails once it is read
+        let check = format!("SELECT COUNT(*) FROM {}.sqlite_master", quote_identifier(&schema));
+        if let Err(e) = self.conn.query_row(&check, [], |row| row.get::<_, i64>(0)) {
+            self.conn.execute(&format!("DETACH DATABASE {}", quote_identifier(&schema)), [])?;
+            return Err(anyhow::anyhow!("'{}' is not a SQLite database: {}", path.display(), e));
+        }
+        Ok(schema)
+    }
+
+    /// Schema names of the attached databases, in attach order
+    pub fn attached_databases(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA database_list")?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(names.into_iter().filter(|name| name != "main" && name != "temp").collect())
+    }
+
+    /// Quote a table name from the sidebar, where tables of attached databases are
+    /// listed as `schema.table`
+    fn quote_table(&self, table_name: &str) -> Result<String> {
+        let parts: Vec<String> = self.table_name_parts(table_name)?.iter().map(|part| quote_identifier(part)).collect();
+        Ok(parts.join("."))
+    }
+
+    /// A sidebar table name as schema and table, or just the table for the main database
+    fn table_name_parts(&self, table_name: &str) -> Result<Vec<String>> {
+        if let Some((schema, table)) = table_name.split_once('.') {
+            if self.attached_databases()?.iter().any(|s| s == schema) {
+                return Ok(vec![schema.to_string(), table.to_string()]);
+            }
+        }
+        Ok(vec![table_name.to_string()])
+    }
+
+    /// Write edited and new rows in a single transaction; nothing is written if any fails.
+    /// Cells reading "NULL" are stored as NULL, other values as text converted by column affinity.
+    /// Returns the statements undoing the changes, last change first, for `revert_changes`. Each
+    /// finds its row by the rowid it has after the save and only if the row still holds what the
+    /// save wrote, so a row changed since is not overwritten.
+    pub fn apply_row_changes(&self, table_name: &str, changes: &[RowChange]) -> Result<Vec<ReverseStatement>> {
+        use rusqlite::types::Value;
+        use rusqlite::OptionalExtension;
+
+        self.discard_query_results();
+        let to_value = |value: &str| match value {
+            "NULL" => Value::Null,
+            _ => Value::Text(value.to_string()),
+        };
+        let table = self.quote_table(table_name)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut reverse = Vec::new();
+        for change in changes {
+            match change {
+                RowChange::Update { rowid, values } => {
+                    let columns: Vec<String> = values.iter().map(|(column, _)| quote_identifier(column)).collect();
+                    let assignments: Vec<String> = columns.iter().map(|column| format!("{} = ?", column)).collect();
+                    let before = tx
+                        .query_row(&format!("SELECT {} FROM {} WHERE rowid = ?", columns.join(", "), table), [rowid], |row| {
+                            sql_values(row, 0..columns.len())
+                        })
+                        .optional()
+                        .with_context(|| format!("Failed to update row {}", rowid))?;
+                    let mut params: Vec<Value> = values.iter().map(|(_, v)| to_value(v)).collect();
+                    params.push(Value::Integer(*rowid));
+                    // The rowid and values as stored: an update of an INTEGER PRIMARY KEY moves the row
+                    let after = tx
+                        .query_row(
+                            &format!(
+                                "UPDATE {} SET {} WHERE rowid = ? RETURNING rowid, {}",
+                                table,
+                                assignments.join(", "),
+                                columns.join(", ")
+                            ),
+                            rusqlite::params_from_iter(params),
+                            |row| Ok((row.get::<_, i64>(0)?, sql_values(row, 1..columns.len() + 1)?)),
+                        )
+                        .optional()
+                        .with_context(|| format!("Failed to update row {}", rowid))?;
+                    // The values before the update are written back to the row where it is now
+                    if let (Some(before), Some((rowid, after))) = (before, after) {
+                        reverse.push(ReverseStatement {
+                            sql: format!(
+                                "UPDATE {} SET {} WHERE rowid = ?{}",
+                                table,
+                                assignments.join(", "),
+                                unchanged_since(&columns)
+                            ),
+                            params: before.into_iter().chain(std::iter::once(SqlValue::Integer(rowid))).chain(after).collect(),
+                        });
+                    }
+                }
+                RowChange::Delete { rowid } => {
+                    // The row is inserted again, under its rowid
+                    let mut select = tx.prepare(&format!("SELECT * FROM {} WHERE rowid = ?", table))?;
+                    let columns: Vec<String> = select.column_names().iter().map(|name| quote_identifier(name)).collect();
+                    let before = select
+                        .query_row([rowid], |row| sql_values(row, 0..columns.len()))
+                        .optional()
+                        .with_context(|| format!("Failed to delete row {}", rowid))?;
+                    tx.execute(&format!("DELETE FROM {} WHERE rowid = ?", table), [rowid])
+                        .with_context(|| format!("Failed to delete row {}", rowid))?;
+                    if let Some(values) = before {
+                        reverse.push(ReverseStatement {
+                            sql: format!(
+                                "INSERT INTO {} (rowid, {}) VALUES (?, {})",
+                                table,
+                                columns.join(", "),
+                                vec!["?"; columns.len()].join(", ")
+                            ),
+                            params: std::iter::once(SqlValue::Integer(*rowid)).chain(values).collect(),
+                        });
+                    }
+                }
+                RowChange::Insert { values } => {
+                    let sql = if values.is_empty() {
+                        format!("INSERT INTO {} DEFAULT VALUES RETURNING rowid, *", table)
+                    } else {
+                        let columns: Vec<String> =
+                            values.iter().map(|(column, _)| quote_identifier(column)).collect();
+                        let placeholders = vec!["?"; values.len()].join(", ");
+                        format!(
+                            "INSERT INTO {} ({}) VALUES ({}) RETURNING rowid, *",
+                            table,
+                            columns.join(", "),
+                            placeholders
+                        )
+                    };
+                    // A new row is deleted again, with all its values as stored
+                    let mut insert = tx.prepare(&sql)?;
+                    let columns: Vec<String> =
+                        insert.column_names().iter().skip(1).map(|name| quote_identifier(name)).collect();
+                    let after = insert
+                        .query_row(rusqlite::params_from_iter(values.iter().map(|(_, v)| to_value(v))), |row| {
+                            sql_values(row, 0..columns.len() + 1)
+                        })
+                        .context("Failed to insert row")?;
+                    reverse.push(ReverseStatement {
+                        sql: format!("DELETE FROM {} WHERE rowid = ?{}", table, unchanged_since(&columns)),
+                        params: after,
+                    });
+                }
+            }
+        }
+        tx.commit()?;
+        reverse.reverse();
+        Ok(reverse)
+    }
+
+    /// Undo a save with the statements `apply_row_changes` returned for it, in a single
+    /// transaction. Each statement must find its row as the save left it, so a save whose rows
+    /// were changed, deleted or taken by other rows since is not reverted at all.
+    pub fn revert_changes(&self, statements: &[ReverseStatement]) -> Result<()> {
+        self.discard_query_results();
+        let tx = self.conn.unchecked_transaction()?;
+        for statement in statements {
+            let changed = tx
+                .execute(&statement.sql, rusqlite::params_from_iter(&statement.params))
+                .context("Failed to revert a row of the save")?;
+            if changed == 0 {
+                return Err(anyhow::anyhow!("A row of the save was changed or deleted since"));
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// New values an update gives the rows with `rowids`, leaving the table as it is: the
+    /// rowid and assigned values of each row its condition selects
+    pub fn preview_update(&self, table_name: &str, update: &BulkUpdate, rowids: &[i64]) -> Result<Vec<(i64, Vec<Value>)>> {
+        if rowids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let result = self.execute_query(&update.preview_sql(&self.quote_table(table_name)?, rowids))?;
+        Ok(result
+            .rows
+            .into_iter()
+            .filter_map(|mut row| match row.first() {
+                Some(Value::Int(rowid)) => {
+                    let rowid = *rowid;
+                    row.remove(0);
+                    Some((rowid, row))
+                }
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Add, rename or drop a column with ALTER TABLE. Where that fails, as it does for
+    /// columns in an index or key and on SQLite versions without RENAME/DROP COLUMN, the
+    /// table is rebuilt with the new columns instead.
+    pub fn alter_column(&self, table_name: &str, edit: &ColumnEdit) -> Result<()> {
+        self.discard_query_results();
+        let table = self.quote_table(table_name)?;
+        match self.conn.execute_batch(&edit.alter_sql(&table)) {
+            Ok(()) => Ok(()),
+            Err(e) if matches!(edit, ColumnEdit::Add { .. }) => Err(e.into()),
+            Err(e) => self
+                .rebuild_table(table_name, edit)
+                .with_context(|| format!("ALTER TABLE failed ({}) and so did rebuilding the table", e)),
+        }
+    }
+
+    /// Copy the table into a new one with the edited columns and put it in its place, the way
+    /// SQLite documents for schema changes ALTER TABLE can't make. Indexes, keys and defaults
+    /// are recreated; triggers and CHECK constraints are not.
+    fn rebuild_table(&self, table_name: &str, edit: &ColumnEdit) -> Result<()> {
+        let parts = self.table_name_parts(table_name)?;
+        let (schema_prefix, name) = match parts.as_slice() {
+            [schema, table] => (format!("{}.", quote_identifier(schema)), table.clone()),
+            _ => (String::new(), table_name.to_string()),
+        };
+        let old = self.table_schema(table_name)?;
+        if let ColumnEdit::Rename { from: column, .. } | ColumnEdit::Drop(column) = edit {
+            if !old.columns.iter().any(|c| c.name == *column) {
+                return Err(anyhow::anyhow!("Column '{}' not found", column));
+            }
+        }
+        let new = edit.apply_to_schema(&old);
+        let rebuilt = format!("{}_sqbrowser_rebuild", name);
+
+        let mut definitions: Vec<String> = new
+            .columns
+            .iter()
+            .map(|column| {
+                let mut definition = quote_identifier(&column.name);
+                if !column.column_type.is_empty() {
+                    definition.push_str(&format!(" {}", column.column_type));
+                }
+                if column.not_null {
+                    definition.push_str(" NOT NULL");
+                }
+                if let Some(default) = &column.default {
+                    definition.push_str(&format!(" DEFAULT {}", default));
+                }
+                definition
+            })
+            .collect();
+        let quoted = |columns: &[String]| columns.iter().map(|c| quote_identifier(c)).collect::<Vec<_>>().join(", ");
+        let primary_key: Vec<String> = new.columns.iter().filter(|c| c.primary_key).map(|c| c.name.clone()).collect();
+        if !primary_key.is_empty() {
+            definitions.push(format!("PRIMARY KEY ({})", quoted(&primary_key)));
+        }
+        for index in new.indexes.iter().filter(|index| index.origin == "u") {
+            definitions.push(format!("UNIQUE ({})", quoted(&index.columns)));
+        }
+        for key in &new.foreign_keys {
+            let parent = key.table.rsplit('.').next().unwrap_or(&key.table);
+            let references = if key.references.is_empty() { String::new() } else { format!(" ({})", quoted(&key.references)) };
+            definitions.push(format!(
+                "FOREIGN KEY ({}) REFERENCES {}{} ON UPDATE {} ON DELETE {}",
+                quoted(&key.columns),
+                quote_identifier(parent),
+                references,
+                key.on_update,
+                key.on_delete
+            ));
+        }
+
+        // Columns copied over, under their new names
+        let copied: Vec<(String, String)> = old
+            .columns
+            .iter()
+            .filter_map(|column| edit.column_after(&column.name).map(|after| (column.name.clone(), after)))
+            .collect();
+        let from: Vec<String> = copied.iter().map(|(before, _)| before.clone()).collect();
+        let to: Vec<String> = copied.iter().map(|(_, after)| after.clone()).collect();
+
+        let mut sql = format!(
+            "CREATE TABLE {0}{1} ({2});\nINSERT INTO {0}{1} ({3}) SELECT {4} FROM {0}{5};\nDROP TABLE {0}{5};\nALTER TABLE {0}{1} RENAME TO {5};\n",
+            schema_prefix,
+            quote_identifier(&rebuilt),
+            definitions.join(", "),
+            quoted(&to),
+            quoted(&from),
+            quote_identifier(&name),
+        );
+        for index in new.indexes.iter().filter(|index| index.origin == "c" && !index.columns.iter().any(|c| c == "<expression>")) {
+            sql.push_str(&format!(
+                "CREATE {}INDEX {}{} ON {} ({});\n",
+                if index.unique { "UNIQUE " } else { "" },
+                schema_prefix,
+                quote_identifier(&index.name),
+                quote_identifier(&name),
+                quoted(&index.columns)
+            ));
+        }
+
+        // Foreign keys pointing at the table would otherwise stop the drop
+        let foreign_keys: bool = self.conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        self.conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+        let tx = self.conn.unchecked_transaction()?;
+        let result = tx.execute_batch(&sql).and_then(|()| tx.commit());
+        if foreign_keys {
+            self.conn.execute_batch("PRAGMA foreign_keys = ON")?;
+        }
+        Ok(result?)
+    }
+
+    /// Indexes and triggers of every table, grouped by table
+    pub fn get_schema_objects(&self) -> Result<Vec<SchemaObject>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT type, name, tbl_name, sql FROM sqlite_master \
+             WHERE type IN ('index', 'trigger') ORDER BY tbl_name, type, name",
+        )?;
+        let objects = stmt
+            .query_map([], |row| {
+                Ok(SchemaObject {
+                    kind: row.get(0)?,
+                    name: row.get(1)?,
+                    table: row.get(2)?,
+                    sql: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(objects)
+    }
+
+    /// Views, virtual tables, indexes and triggers of the main database, in the order of
+    /// their sidebar sections
+    pub fn get_sidebar_objects(&self) -> Result<Vec<SchemaObject>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT CASE type WHEN 'table' THEN 'virtual table' ELSE type END, name, tbl_name, sql \
+             FROM sqlite_master \
+             WHERE type IN ('view', 'index', 'trigger') OR (type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%') \
+             ORDER BY CASE type WHEN 'view' THEN 0 WHEN 'table' THEN 1 WHEN 'index' THEN 2 ELSE 3 END, name",
+        )?;
+        let objects = stmt
+            .query_map([], |row| {
+                Ok(SchemaObject {
+                    kind: row.get(0)?,
+                    name: row.get(1)?,
+                    table: row.get(2)?,
+                    sql: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(objects)
+    }
+
+    /// Views have no rowid to load for editing
+    fn is_view(&self, table_name: &str) -> Result<bool> {
+        let (master, name) = match self.table_name_parts(table_name)?.as_slice() {
+            [schema, table] => (format!("{}.sqlite_master", quote_identifier(schema)), table.clone()),
+            _ => ("sqlite_master".to_string(), table_name.to_string()),
+        };
+        let query = format!("SELECT COUNT(*) FROM {} WHERE type = 'view' AND name = ?1", master);
+        Ok(self.conn.query_row(&query, [name], |row| row.get::<_, i64>(0))? > 0)
+    }
+
+    /// Row and column count of a table, and what dbstat and ANALYZE know about it. SQLite
+    /// doesn't record when ANALYZE ran, only the row estimate it stored.
+    pub fn table_stats(&self, table_name: &str) -> Result<TableStats> {
+        let info = self.get_table_info(table_name)?;
+        let (schema, name) = match self.table_name_parts(table_name)?.as_slice() {
+            [schema, table] => (format!("{}.", quote_identifier(schema)), table.clone()),
+            _ => (String::new(), table_name.to_string()),
+        };
+
+        // dbstat is optional in SQLite builds; its absence only leaves the size unknown
+        let size_query = format!(
+            "SELECT SUM(pgsize) FROM {0}dbstat WHERE name IN (SELECT name FROM {0}sqlite_master WHERE tbl_name = ?1)",
+            schema
+        );
+        let size_bytes = self
+            .conn
+            .query_row(&size_query, [&name], |row| row.get::<_, Option<i64>>(0))
+            .ok()
+            .flatten()
+            .map(|size| size as u64);
+
+        // sqlite_stat1 only exists once ANALYZE has run; its stat column starts with the row count
+        let stat_query = format!("SELECT stat FROM {}sqlite_stat1 WHERE tbl = ?1 LIMIT 1", schema);
+        let analyzed_rows = self
+            .conn
+            .query_row(&stat_query, [&name], |row| row.get::<_, String>(0))
+            .ok()
+            .and_then(|stat| stat.split_whitespace().next()?.parse().ok());
+
+        Ok(TableStats {
+            rows: info.total_rows,
+            columns: info.columns.len(),
+            size_bytes,
+            analyzed_rows,
+        })
+    }
+
+    /// Stats of one column over the rows passing `filters`, computed by SQLite
+    pub fn column_stats(&self, table_name: &str, column: &str, filters: &[ColumnFilter]) -> Result<ColumnStats> {
+        let col = quote_identifier(column);
+        let where_clause = where_clause(filters, SqlFlavor::Sqlite);
+        let present = if where_clause.is_empty() {
+            format!(" WHERE {} IS NOT NULL", col)
+        } else {
+            format!("{} AND {} IS NOT NULL", where_clause, col)
+        };
+
+        let summary_query = format!(
+            "SELECT COUNT(*), COUNT({0}), COUNT(DISTINCT {0}), MIN({0}), MAX({0}), \
+             TOTAL(typeof({0}) IN ('integer', 'real')), AVG({0}), AVG({0} * {0}) FROM {1}{2}",
+            col, table_name, where_clause
+        );
+        let (rows, non_null, distinct, min, max, numbers, mean, mean_square) =
+            self.conn.query_row(&summary_query, [], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, i64>(1)? as usize,
+                    row.get::<_, i64>(2)? as usize,
+                    row.get::<_, rusqlite::types::Value>(3)?,
+                    row.get::<_, rusqlite::types::Value>(4)?,
+                    row.get::<_, f64>(5)? as usize,
+                    row.get::<_, Option<f64>>(6)?,
+                    row.get::<_, Option<f64>>(7)?,
+                ))
+            })?;
+        let shown = |value: rusqlite::types::Value| match value {
+            rusqlite::types::Value::Null => None,
+            value => Some(sqlite_value(value).to_string()),
+        };
+
+        let mut stats = ColumnStats {
+            column: column.to_string(),
+            rows,
+            nulls: rows - non_null,
+            distinct,
+            min: shown(min),
+            max: shown(max),
+            numeric: None,
+            top_values: Vec::new(),
+        };
+
+        if let (true, Some(mean), Some(mean_square)) = (numbers == non_null && non_null > 0, mean, mean_square) {
+            // The middle value, or the two around the middle for an even count
+            let median_query = format!(
+                "SELECT {0} FROM {1}{2} ORDER BY {0} LIMIT {3} OFFSET {4}",
+                col,
+                table_name,
+                present,
+                2 - non_null % 2,
+                (non_null - 1) / 2
+            );
+            let mut stmt = self.conn.prepare(&median_query)?;
+            let middle: Vec<f64> = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+            let n = non_null as f64;
+            let variance = if non_null > 1 { (mean_square - mean * mean).max(0.0) * n / (n - 1.0) } else { 0.0 };
+            stats.numeric = Some(NumericSummary {
+                mean,
+                median: middle.iter().sum::<f64>() / middle.len().max(1) as f64,
+                stddev: variance.sqrt(),
+            });
+        } else {
+            let top_query = format!(
+                "SELECT {0}, COUNT(*) FROM {1}{2} GROUP BY {0} ORDER BY COUNT(*) DESC, {0} LIMIT {3}",
+                col, table_name, present, TOP_VALUES
+            );
+            let mut stmt = self.conn.prepare(&top_query)?;
+            stats.top_values = stmt
+                .query_map([], |row| {
+                    Ok((sqlite_value(row.get(0)?).to_string(), row.get::<_, i64>(1)? as usize))
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+        }
+        Ok(stats)
+    }
+
+    /// Histogram of one column over the rows passing `filters`, counted by SQLite
+    pub fn column_histogram(&self, table_name: &str, column: &str, filters: &[ColumnFilter]) -> Result<Histogram> {
+        let col = quote_identifier(column);
+        let where_clause = where_clause(filters, SqlFlavor::Sqlite);
+        let present = if where_clause.is_empty() {
+            format!(" WHERE {} IS NOT NULL", col)
+        } else {
+            format!("{} AND {} IS NOT NULL", where_clause, col)
+        };
+
+        let range_query = format!(
+            "SELECT COUNT({0}), TOTAL(typeof({0}) IN ('integer', 'real')), TOTAL(typeof({0}) = 'integer'), \
+             MIN({0}), MAX({0}) FROM {1}{2}",
+            col, table_name, where_clause
+        );
+        let (non_null, numbers, integers, min, max) = self.conn.query_row(&range_query, [], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get::<_, f64>(1)? as usize,
+                row.get::<_, f64>(2)? as usize,
+                row.get::<_, rusqlite::types::Value>(3)?,
+                row.get::<_, rusqlite::types::Value>(4)?,
+            ))
+        })?;
+
+        let range = match (min, max) {
+            (rusqlite::types::Value::Integer(min), rusqlite::types::Value::Integer(max)) => Some((min as f64, max as f64)),
+            (rusqlite::types::Value::Integer(min), rusqlite::types::Value::Real(max)) => Some((min as f64, max)),
+            (rusqlite::types::Value::Real(min), rusqlite::types::Value::Integer(max)) => Some((min, max as f64)),
+            (rusqlite::types::Value::Real(min), rusqlite::types::Value::Real(max)) => Some((min, max)),
+            _ => None,
+        };
+        if let (true, Some((min, max))) = (numbers == non_null && non_null > 0, range) {
+            let bins = Bins::new(min, max, integers == non_null);
+            let bin_query = format!(
+                "SELECT MIN(CAST(({0} - ?1) / ?2 AS INTEGER), ?3), COUNT(*) FROM {1}{2} GROUP BY 1",
+                col, table_name, present
+            );
+            let mut counts = vec![0; bins.count];
+            let mut stmt = self.conn.prepare(&bin_query)?;
+            let rows = stmt.query_map(rusqlite::params![bins.min, bins.width, bins.count as i64 - 1], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })?;
+            for row in rows {
+                let (bin, count) = row?;
+                if let Some(slot) = counts.get_mut(bin.max(0) as usize) {
+                    *slot += count as u64;
+                }
+            }
+            return Ok(Histogram { column: column.to_string(), numeric: true, bars: bins.bars(&counts) });
+        }
+
+        let top_query = format!(
+            "SELECT {0}, COUNT(*) FROM {1}{2} GROUP BY {0} ORDER BY COUNT(*) DESC, {0} LIMIT {3}",
+            col, table_name, present, HISTOGRAM_BINS
+        );
+        let mut stmt = self.conn.prepare(&top_query)?;
+        let bars = stmt
+            .query_map([], |row| Ok((sqlite_value(row.get(0)?).to_string(), row.get::<_, i64>(1)? as u64)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Histogram { column: column.to_string(), numeric: false, bars })
+    }
+
+    /// How often each value of a column occurs among the rows passing `filters`
+    pub fn column_value_counts(&self, table_name: &str, column: &str, filters: &[ColumnFilter]) -> Result<ValueCounts> {
+        let col = quote_identifier(column);
+        let where_clause = where_clause(filters, SqlFlavor::Sqlite);
+        // COUNT(DISTINCT) leaves NULL out, GROUP BY doesn't
+        let totals_query = format!(
+            "SELECT COUNT(*), COUNT(DISTINCT {0}) + (COUNT(*) > COUNT({0})) FROM {1}{2}",
+            col, table_name, where_clause
+        );
+        let (rows, distinct) = self.conn.query_row(&totals_query, [], |row| {
+            Ok((row.get::<_, i64>(0)? as usize, row.get::<_, i64>(1)? as usize))
+        })?;
+
+        let counts_query = format!(
+            "SELECT {0}, COUNT(*) FROM {1}{2} GROUP BY {0} ORDER BY COUNT(*) DESC, {0} LIMIT {3}",
+            col, table_name, where_clause, VALUE_COUNTS_LIMIT
+        );
+        let mut stmt = self.conn.prepare(&counts_query)?;
+        let values = stmt
+            .query_map([], |row| Ok((sqlite_value(row.get(0)?), row.get::<_, i64>(1)? as u64)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ValueCounts { column: column.to_string(), rows, distinct, values })
+    }
+
+    /// CREATE statements of a table or view followed by those of its indexes and triggers,
+    /// like `.schema` in the sqlite3 shell
+    pub fn table_ddl(&self, table_name: &str) -> Result<Vec<String>> {
+        let (master, name) = match self.table_name_parts(table_name)?.as_slice() {
+            [schema, table] => (format!("{}.sqlite_master", quote_identifier(schema)), table.clone()),
+            _ => ("sqlite_master".to_string(), table_name.to_string()),
+        };
+        let query = format!(
+            "SELECT sql FROM {} WHERE tbl_name = ?1 AND sql IS NOT NULL \
+             ORDER BY CASE type WHEN 'index' THEN 1 WHEN 'trigger' THEN 2 ELSE 0 END, name",
+            master
+        );
+        let mut stmt = self.conn.prepare(&query)?;
+        let statements = stmt
+            .query_map([name], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if statements.is_empty() {
+            return Err(anyhow::anyhow!("Table '{}' not found", table_name));
+        }
+        Ok(statements)
+    }
+
+    /// Columns, indexes and foreign keys of a table, from its PRAGMAs
+    pub fn table_schema(&self, table_name: &str) -> Result<TableSchema> {
+        let (schema, table) = match self.table_name_parts(table_name)?.as_slice() {
+            [schema, table] => (format!("{}.", quote_identifier(schema)), table.clone()),
+            _ => (String::new(), table_name.to_string()),
+        };
+        let pragma = |name: &str, argument: &str| format!("PRAGMA {}{}({})", schema, name, quote_identifier(argument));
+
+        let mut stmt = self.conn.prepare(&pragma("table_info", &table))?;
+        let columns = stmt
+            .query_map([], |row| {
+                Ok(ColumnSchema {
+                    name: row.get(1)?,
+                    column_type: row.get(2)?,
+                    not_null: row.get(3)?,
+                    default: row.get(4)?,
+                    primary_key: row.get::<_, i64>(5)? > 0,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("Table '{}' not found", table_name));
+        }
+
+        let mut stmt = self.conn.prepare(&pragma("index_list", &table))?;
+        let index_list = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, bool>(2)?, row.get::<_, String>(3)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut indexes = Vec::new();
+        for (name, unique, origin) in index_list {
+            let mut stmt = self.conn.prepare(&pragma("index_info", &name))?;
+            // Expression indexes have no column name
+            let columns = stmt
+                .query_map([], |row| row.get::<_, Option<String>>(2))?
+                .map(|column| column.map(|c| c.unwrap_or_else(|| "<expression>".to_string())))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            indexes.push(IndexSchema { name, columns, unique, origin });
+        }
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(TableSchema {
+            columns,
+            indexes,
+            foreign_keys: self.foreign_keys(table_name)?,
+            inferred: false,
+        })
+    }
+
+    /// Foreign keys of a table. Parents of attached tables are named `schema.table`, like
+    /// the tables in the sidebar.
+    pub fn foreign_keys(&self, table_name: &str) -> Result<Vec<ForeignKey>> {
+        let (query, schema) = match self.table_name_parts(table_name)?.as_slice() {
+            [schema, table] => (
+                format!("PRAGMA {}.foreign_key_list({})", quote_identifier(schema), quote_identifier(table)),
+                Some(schema.clone()),
+            ),
+            _ => (format!("PRAGMA foreign_key_list({})", quote_identifier(table_name)), None),
+        };
+
+        // One row per column of each key, numbered by the key's id
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        let mut foreign_keys: Vec<(i64, ForeignKey)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let from: String = row.get(3)?;
+            let to: Option<String> = row.get(4)?;
+            match foreign_keys.last_mut() {
+                Some((last_id, key)) if *last_id == id => {
+                    key.columns.push(from);
+                    key.references.extend(to);
+                }
+                _ => {
+                    let parent: String = row.get(2)?;
+                    foreign_keys.push((
+                        id,
+                        ForeignKey {
+                            columns: vec![from],
+                            table: match &schema {
+                                Some(schema) => format!("{}.{}", schema, parent),
+                                None => parent,
+                            },
+                            references: to.into_iter().collect(),
+                            on_update: row.get(5)?,
+                            on_delete: row.get(6)?,
+                        },
+                    ))
+                }
+            }
+        }
+        Ok(foreign_keys.into_iter().map(|(_, key)| key).collect())
+    }
+
+    /// Columns of the parent table a foreign key points at: those it names, or else the
+    /// parent's primary key
+    pub fn referenced_columns(&self, key: &ForeignKey) -> Result<Vec<String>> {
+        if !key.references.is_empty() {
+            return Ok(key.references.clone());
+        }
+        let query = match self.table_name_parts(&key.table)?.as_slice() {
+            [schema, table] => format!("PRAGMA {}.table_info({})", quote_identifier(schema), quote_identifier(table)),
+            _ => format!("PRAGMA table_info({})", quote_identifier(&key.table)),
+        };
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut key_columns = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(5)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        key_columns.retain(|(position, _)| *position > 0);
+        key_columns.sort();
+        if key_columns.is_empty() {
+            return Err(anyhow::anyhow!("'{}' has no primary key for the foreign key to refer to", key.table));
+        }
+        Ok(key_columns.into_iter().map(|(_, column)| column).collect())
+    }
+
+    pub fn get_table_info(&self, table_name: &str) -> Result<TableInfo> {
+        // Get column information
+        let mut stmt = match self.table_name_parts(table_name)?.as_slice() {
+            [schema, table] => self.conn.prepare(&format!(
+                "PRAGMA {}.table_info({})",
+                quote_identifier(schema),
+                quote_identifier(table)
+            ))?,
+            _ => self.conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(table_name)))?,
+        };
+        let rows = stmt.query_map([], |row| {
+            row.get::<_, String>(1) // Column name is at index 1
+        })?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            columns.push(row?);
+        }
+
+        // Get total row count
+        let mut stmt = self.conn.prepare(&format!("SELECT COUNT(*) FROM {}", self.quote_table(table_name)?))?;
+        let total_rows: i64 = stmt.query_row([], |row| row.get(0))?;
+
+        Ok(TableInfo {
+            name: table_name.to_string(),
+            columns,
+            total_rows: total_rows as usize,
+        })
+    }
+
+    pub fn get_table_data(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+    ) -> Result<QueryResult> {
+        let mut result = self.get_table_page(table_name, offset, limit, sort, filters)?;
+        // Count every matching row, not just this page
+        result.total_rows = self.count_rows(table_name, filters)?;
+        Ok(result)
+    }
+
+    /// A page of a table without counting its rows, which can take long on large tables;
+    /// its `total_rows` is only the rows on the page
+    pub fn get_table_page(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+    ) -> Result<QueryResult> {
+        let where_clause = where_clause(filters, SqlFlavor::Sqlite);
+        let order_by = match sort {
+            Some(sort) => format!(
+                " ORDER BY {} {}",
+                quote_identifier(&sort.column),
+                if sort.descending { "DESC" } else { "ASC" }
+            ),
+            None => String::new(),
+        };
+
+        // Include rowid for update operations. The alias keeps the column named "rowid"
+        // even when an INTEGER PRIMARY KEY column would otherwise lend it its name.
+        // Views have none and are shown read-only.
+        let rowid = if self.is_view(table_name)? { "" } else { "rowid AS rowid, " };
+        let query = format!(
+            "SELECT {}* FROM {}{}{} LIMIT {} OFFSET {}",
+            rowid, table_name, where_clause, order_by, limit, offset
+        );
+        self.execute_query(&query)
+    }
+
+    /// `func` (sum, mean, count, min or max) over the numbers of a column in the rows passing
+    /// `filters`, computed by SQLite for a computed column; 0 when there are none
+    pub fn column_aggregate(&self, table_name: &str, column: &str, func: &str, filters: &[ColumnFilter]) -> Result<f64> {
+        let sql_func = match func {
+            "sum" => "TOTAL",
+            "mean" => "AVG",
+            "count" => "COUNT",
+            "min" => "MIN",
+            "max" => "MAX",
+            _ => return Err(anyhow::anyhow!("Unknown function: {}", func)),
+        };
+        let col = quote_identifier(column);
+        let where_clause = where_clause(filters, SqlFlavor::Sqlite);
+        let numbers = if where_clause.is_empty() {
+            format!(" WHERE typeof({}) IN ('integer', 'real')", col)
+        } else {
+            format!("{} AND typeof({}) IN ('integer', 'real')", where_clause, col)
+        };
+        let query = format!("SELECT {}({}) FROM {}{}", sql_func, col, table_name, numbers);
+        let value: Option<f64> = self.conn.query_row(&query, [], |row| row.get(0))?;
+        Ok(value.unwrap_or(0.0))
+    }
+
+    /// The values of a window computed column for the page of `limit` rows from `offset`,
+    /// worked out by SQLite over every row passing `filters` in `sort` order
+    pub fn window_column(
+        &self,
+        table_name: &str,
+        window: &WindowSpec,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Value>> {
+        // Rows are numbered in the order the table is read, as its pages are, which breaks
+        // ties of the sort
+        let order = match sort {
+            Some(sort) => format!(
+                "{} {}, sqbrowser_position",
+                quote_identifier(&sort.column),
+                if sort.descending { "DESC" } else { "ASC" }
+            ),
+            None => "sqbrowser_position".to_string(),
+        };
+        let query = format!(
+            "SELECT {} FROM (SELECT *, ROW_NUMBER() OVER () AS sqbrowser_position FROM {}{}) ORDER BY {} LIMIT {} OFFSET {}",
+            window.sql(&order),
+            table_name,
+            where_clause(filters, SqlFlavor::Sqlite),
+            order,
+            limit,
+            offset
+        );
+        Ok(self.execute_query(&query)?.rows.into_iter().map(|row| row.into_iter().next().unwrap_or(Value::Null)).collect())
+    }
+
+    /// Rows of a table passing `filters`
+    pub fn count_rows(&self, table_name: &str, filters: &[ColumnFilter]) -> Result<usize> {
+        let count_query = format!("SELECT COUNT(*) FROM {}{}", table_name, where_clause(filters, SqlFlavor::Sqlite));
+        let total_rows: i64 = self.conn.query_row(&count_query, [], |row| row.get(0))?;
+        Ok(total_rows as usize)
+    }
+
+    pub fn execute_query(&self, query: &str) -> Result<QueryResult> {
+        let mut stmt = self.conn.prepare(query)?;
+        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        
+        let rows = stmt.query_map([], |row| {
+            let mut values = Vec::new();
+            for i in 0..column_names.len() {
+                let value: rusqlite::types::Value = row.get(i)?;
+                values.push(sqlite_value(value));
+            }
+            Ok(values)
+        })?;
+
+        let mut result_rows = Vec::new();
+        for row in rows {
+            result_rows.push(row?);
+        }
+
+        // Try to get total count for the query (simplified approach)
+        let total_rows = result_rows.len();
+
+        Ok(QueryResult {
+            columns: column_names,
+            rows: result_rows,
+            total_rows,
+        })
+    }
+
+    pub fn execute_custom_query(
+        &self,
+        query: &str,
+        params: &[(String, String)],
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<QueryResult> {
+        let key = (query.to_string(), params.to_vec(), table_name.to_string());
+        if self.query_results.borrow().as_ref().is_none_or(|results| results.key != key) {
+            self.discard_query_results();
+            let (final_query, with_rowid) = self.rewrite_custom_query(query, table_name)?;
+            // Rows of a single table keep their rowid for editing; views and WITHOUT ROWID
+            // tables have none, so those run as written
+            let (columns, total_rows) = match with_rowid.map(|query| self.materialize_query(&query, params)) {
+                Some(Ok(materialized)) => materialized,
+                _ => self.materialize_query(&final_query, params)?,
+            };
+            *self.query_results.borrow_mut() = Some(QueryResults { key, columns, total_rows });
+        }
+
+        let results = self.query_results.borrow();
+        let results = results.as_ref().expect("query results were just stored");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM temp.{} ORDER BY rowid LIMIT {} OFFSET {}",
+            QUERY_RESULTS_TABLE, limit, offset
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let mut values = Vec::new();
+            for i in 0..results.columns.len() {
+                let value: rusqlite::types::Value = row.get(i)?;
+                values.push(sqlite_value(value));
+            }
+            Ok(values)
+        })?;
+
+        let mut result_rows = Vec::new();
+        for row in rows {
+            result_rows.push(row?);
+        }
+
+        Ok(QueryResult {
+            columns: results.columns.clone(),
+            rows: result_rows,
+            total_rows: results.total_rows,
+        })
+    }
+
+    /// Run a query once and keep all of its rows in a temp table, so pages of the result
+    /// come from the same run, however the query orders (or doesn't order) its rows.
+    /// Returns the column names and the number of rows.
+    fn materialize_query(&self, sql: &str, params: &[(String, String)]) -> Result<(Vec<String>, usize)> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("The query returns no columns"));
+        }
+
+        // Untyped columns keep every value exactly as the query returned it
+        let definitions: Vec<String> = (0..columns.len()).map(|i| format!("c{}", i)).collect();
+        self.conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS temp.{table}; CREATE TEMP TABLE {table} ({columns}); SAVEPOINT sqbrowser_results;",
+            table = QUERY_RESULTS_TABLE,
+            columns = definitions.join(", ")
+        ))?;
+        let mut copy = || -> Result<usize> {
+            let mut insert = self.conn.prepare(&format!(
+                "INSERT INTO temp.{} VALUES ({})",
+                QUERY_RESULTS_TABLE,
+                vec!["?"; columns.len()].join(", ")
+            ))?;
+            let values = parameter_values(params);
+            let mut rows = stmt.query(&*named_params(&values))?;
+            let mut count = 0;
+            while let Some(row) = rows.next()? {
+                let values = (0..columns.len())
+                    .map(|i| row.get::<_, rusqlite::types::Value>(i))
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                insert.execute(rusqlite::params_from_iter(values))?;
+                count += 1;
+            }
+            Ok(count)
+        };
+        match copy() {
+            Ok(count) => {
+                self.conn.execute_batch("RELEASE sqbrowser_results")?;
+                Ok((columns, count))
+            }
+            Err(e) => {
+                // An interrupted statement rolls back the whole transaction, savepoint included
+                if !self.conn.is_autocommit() {
+                    self.conn.execute_batch("ROLLBACK TO sqbrowser_results; RELEASE sqbrowser_results")?;
+                }
+                self.conn.execute_batch(&format!("DROP TABLE IF EXISTS temp.{}", QUERY_RESULTS_TABLE))?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Handle for interrupting a statement running on another thread
+    pub fn interrupt_handle(&self) -> rusqlite::InterruptHandle {
+        self.conn.get_interrupt_handle()
+    }
+
+    /// Forget the stored result of the last custom query, so the next page runs it again.
+    /// Called when a query is (re)entered and whenever the data may have changed.
+    pub fn discard_query_results(&self) {
+        if self.query_results.borrow_mut().take().is_some() {
+            // A leftover table is dropped before the next query is stored anyway
+            if let Err(e) = self.conn.execute_batch(&format!("DROP TABLE IF EXISTS temp.{}", QUERY_RESULTS_TABLE)) {
+                tracing::warn!(error = %e, "failed to drop query results");
+            }
+        }
+    }
+
+    /// Resolve the `x` shorthand of a custom query: `x` (and `x.column`) stands for the current
+    /// table unless the query or the database defines an `x` of its own, so other tables can be
+    /// joined by name. A SELECT of columns without FROM reads the current table. Also returns
+    /// the query with `rowid` added when it is `SELECT *` from a single table. Queries over
+    /// the whole database (empty `table_name`) run as written.
+    fn rewrite_custom_query(&self, query: &str, table_name: &str) -> Result<(String, Option<String>)> {
+        let query = query.trim().trim_end_matches(';').trim();
+        if table_name.is_empty() {
+            return Ok((query.to_string(), None));
+        }
+        // Syntax the parser doesn't know is left for SQLite to run or reject
+        let mut statements = match Parser::parse_sql(&SQLiteDialect {}, query) {
+            Ok(statements) if statements.len() == 1 => statements,
+            _ => return Ok((query.to_string(), None)),
+        };
+        let Statement::Query(parsed) = &mut statements[0] else {
+            return Ok((query.to_string(), None));
+        };
+
+        let table = self.table_object_name(table_name)?;
+        let has_table_x = self.has_table_named_x()?;
+        let mut defines = DefinesX(false);
+        let _ = Visit::visit(&**parsed, &mut defines);
+        if !has_table_x && !defines.0 {
+            let _ = VisitMut::visit(&mut **parsed, &mut ReplaceX::new(&table));
+        }
+
+        if let SetExpr::Select(select) = parsed.body.as_mut() {
+            let mut references_column = false;
+            let _ = sqlparser::ast::visit_expressions(&select.projection, |expr| {
+                references_column |= matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_));
+                ControlFlow::<()>::Continue(())
+            });
+            if select.from.is_empty() && references_column {
+                select.from.push(TableWithJoins {
+                    relation: TableFactor::Table {
+                        name: table.clone(),
+                        alias: None,
+                        args: None,
+                        with_hints: Vec::new(),
+                        version: None,
+                        with_ordinality: false,
+                        partitions: Vec::new(),
+                    },
+                    joins: Vec::new(),
+                });
+            }
+        }
+
+        let rewritten = parsed.to_string();
+        let with_rowid = match parsed.body.as_mut() {
+            SetExpr::Select(select)
+                if matches!(select.projection.as_slice(), [SelectItem::Wildcard(_)])
+                    && matches!(
+                        select.from.as_slice(),
+                        [TableWithJoins { relation: TableFactor::Table { .. }, joins }] if joins.is_empty()
+                    ) =>
+            {
+                select.projection.insert(0, SelectItem::UnnamedExpr(Expr::Identifier(Ident::new("rowid"))));
+                Some(parsed.to_string())
+            }
+            _ => None,
+        };
+        Ok((rewritten, with_rowid))
+    }
+
+    fn table_object_name(&self, table_name: &str) -> Result<ObjectName> {
+        Ok(ObjectName(
+            self.table_name_parts(table_name)?
+                .into_iter()
+                .map(|part| Ident::with_quote('"', part))
+                .collect(),
+        ))
+    }
+
+    /// A real table or view named `x` takes precedence over the shorthand
+    fn has_table_named_x(&self) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type IN ('table', 'view') AND name = 'x' COLLATE NOCASE",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// A syntax error to point out in the query prompt before the query runs. The parser
+    /// doesn't know all of SQLite's syntax, so an error is only reported when SQLite can't
+    /// prepare the query either.
+    pub fn syntax_error(&self, query: &str) -> Option<SyntaxError> {
+        let error = syntax_error(query, &SQLiteDialect {})?;
+        self.conn.prepare(query).is_err().then_some(error)
+    }
+
+    /// Check a query prompt entry that changes the database (INSERT, UPDATE, DELETE, DDL,
+    /// or a script of several statements). Returns None for queries that only read, which
+    /// run through `execute_custom_query`. INSERT, UPDATE and DELETE are run once in a
+    /// savepoint that is rolled back, to count the rows they would change.
+    pub fn plan_statement(
+        &self,
+        query: &str,
+        params: &[(String, String)],
+        table_name: &str,
+    ) -> Result<Option<StatementPlan>> {
+        let query = query.trim().trim_end_matches(';').trim();
+        let (sql, rows_changed, single) = match Parser::parse_sql(&SQLiteDialect {}, query) {
+            Ok(mut statements) if !statements.is_empty() => {
+                if statements.iter().all(|s| matches!(s, Statement::Query(_))) {
+                    return Ok(None);
+                }
+                let table = self.table_object_name(table_name)?;
+                let mut defines = DefinesX(false);
+                let _ = Visit::visit(&statements, &mut defines);
+                let mut replace = ReplaceX::new(&table);
+                if !table_name.is_empty() && !self.has_table_named_x()? && !defines.0 {
+                    let _ = VisitMut::visit(&mut statements, &mut replace);
+                }
+                // Printed from the syntax tree only when needed, to run the user's text as typed
+                let sql = if replace.replaced {
+                    statements.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(";\n")
+                } else {
+                    query.to_string()
+                };
+                let rows_changed = statements
+                    .iter()
+                    .all(|s| matches!(s, Statement::Insert(_) | Statement::Update { .. } | Statement::Delete(_)));
+                (sql, rows_changed, statements.len() == 1)
+            }
+            // Syntax the parser doesn't know: SQLite tells whether the first statement writes
+            _ => match self.conn.prepare(query) {
+                Ok(stmt) if !stmt.readonly() => (query.to_string(), false, false),
+                _ => return Ok(None),
+            },
+        };
+        // Reads such as PRAGMA table_info are not worth a confirmation
+        if single && self.conn.prepare(&sql).is_ok_and(|stmt| stmt.readonly()) {
+            return Ok(None);
+        }
+        // A bound statement is run on its own, so a script would silently lose the rest
+        if !params.is_empty() && !single {
+            return Err(anyhow::anyhow!("Parameters can only be used in a single statement"));
+        }
+
+        let affected_rows = if rows_changed { Some(self.run_in_savepoint(&sql, params, false)?) } else { None };
+        Ok(Some(StatementPlan { sql, params: params.to_vec(), affected_rows }))
+    }
+
+    /// Run a planned statement, returning the number of rows it changed. Row changes are
+    /// made all at once; schema changes and settings run as a plain script, since some of
+    /// them (VACUUM, explicit transactions) can't run inside a savepoint.
+    pub fn execute_statement(&self, plan: &StatementPlan) -> Result<u64> {
+        self.discard_query_results();
+        if plan.affected_rows.is_some() {
+            return self.run_in_savepoint(&plan.sql, &plan.params, true);
+        }
+        let before = self.conn.total_changes();
+        self.run_sql(&plan.sql, &plan.params)?;
+        Ok(self.conn.total_changes() - before)
+    }
+
+    /// Run a script, or a single statement with its parameters bound
+    fn run_sql(&self, sql: &str, params: &[(String, String)]) -> rusqlite::Result<()> {
+        if params.is_empty() {
+            self.conn.execute_batch(sql)
+        } else {
+            let values = parameter_values(params);
+            self.conn.execute(sql, &*named_params(&values)).map(|_| ())
+        }
+    }
+
+    /// Run `sql` inside a savepoint, keeping or rolling back its changes, and count the rows changed
+    fn run_in_savepoint(&self, sql: &str, params: &[(String, String)], keep: bool) -> Result<u64> {
+        self.conn.execute_batch("SAVEPOINT sqbrowser_statement")?;
+        let before = self.conn.total_changes();
+        let result = self.run_sql(sql, params);
+        let changed = self.conn.total_changes() - before;
+        // An interrupted statement rolls back the whole transaction, savepoint included
+        if !self.conn.is_autocommit() {
+            if result.is_err() || !keep {
+                self.conn.execute_batch("ROLLBACK TO sqbrowser_statement")?;
+            }
+            self.conn.execute_batch("RELEASE sqbrowser_statement")?;
+        }
+        result?;
+        Ok(changed)
+    }
+
+    /// Search every table for `term`: FTS tables use MATCH, other tables LIKE over text columns.
+    /// At most `limit` matches are returned.
+    pub fn search_all_tables(&self, term: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, COALESCE(sql, '') FROM sqlite_master WHERE type='table' ORDER BY name",
+        )?;
+        let tables: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let fts_tables: Vec<&str> = tables
+            .iter()
+            .filter(|(_, sql)| sql.to_uppercase().contains("USING FTS"))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let is_fts_shadow = |name: &str| {
+            fts_tables
+                .iter()
+                .any(|fts| name.len() > fts.len() && name.starts_with(fts) && name[fts.len()..].starts_with('_'))
+        };
+
+        let mut matches = Vec::new();
+        for (table, _) in &tables {
+            if matches.len() >= limit {
+                break;
+            }
+            if is_fts_shadow(table) {
+                continue;
+            }
+            let remaining = limit - matches.len();
+            let found = if fts_tables.contains(&table.as_str()) {
+                self.search_fts_table(table, term, remaining)
+            } else {
+                self.search_table_like(table, term, remaining)
+            };
+            // Tables without rowid or with unusual schemas are skipped rather than failing the search
+            if let Ok(found) = found {
+                matches.extend(found);
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn text_columns(&self, table_name: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("PRAGMA table_info({})", quote_identifier(table_name)))?;
+        let columns = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Untyped columns may hold text too, so only clearly numeric/binary ones are skipped
+        Ok(columns
+            .into_iter()
+            .filter(|(_, declared_type)| {
+                let declared_type = declared_type.to_uppercase();
+                !["INT", "REAL", "FLOA", "DOUB", "NUMERIC", "DECIMAL", "BOOL", "BLOB"]
+                    .iter()
+                    .any(|t| declared_type.contains(t))
+            })
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    fn search_table_like(&self, table_name: &str, term: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+        let columns = self.text_columns(table_name)?;
+        if columns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let select_list: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+        let conditions: Vec<String> = columns
+            .iter()
+            .map(|c| format!("{} LIKE ?1 ESCAPE '\\'", quote_identifier(c)))
+            .collect();
+        let query = format!(
+            "SELECT rowid, {} FROM {} WHERE {} LIMIT {}",
+            select_list.join(", "),
+            quote_identifier(table_name),
+            conditions.join(" OR "),
+            limit
+        );
+        let pattern = format!(
+            "%{}%",
+            term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        self.collect_matches(table_name, &query, &pattern, &columns, term, limit)
+    }
+
+    fn search_fts_table(&self, table_name: &str, term: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+        let columns = self.text_columns(table_name)?;
+        let select_list: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+        let query = format!(
+            "SELECT rowid, {} FROM {} WHERE {} MATCH ?1 LIMIT {}",
+            select_list.join(", "),
+            quote_identifier(table_name),
+            quote_identifier(table_name),
+            limit
+        );
+        // Quote the term so FTS treats it as a phrase instead of query syntax
+        let phrase = format!("\"{}\"", term.replace('"', "\"\""));
+        self.collect_matches(table_name, &query, &phrase, &columns, term, limit)
+    }
+
+    fn collect_matches(
+        &self,
+        table_name: &str,
+        query: &str,
+        parameter: &str,
+        columns: &[String],
+        term: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchMatch>> {
+        let mut stmt = self.conn.prepare(query)?;
+        let rows = stmt.query_map([parameter], |row| {
+            let rowid: i64 = row.get(0)?;
+            let mut values = Vec::new();
+            for i in 0..columns.len() {
+                let value: rusqlite::types::Value = row.get(i + 1)?;
+                values.push(sqlite_value(value));
+            }
+            Ok((rowid, values))
+        })?;
+
+        let mut matches = Vec::new();
+        for row in rows {
+            let (rowid, values) = row?;
+            let mut matched_any = false;
+            for (column, value) in columns.iter().zip(&values) {
+                if let Some(snippet) = make_snippet(&value.as_text(), term) {
+                    matched_any = true;
+                    matches.push(SearchMatch {
+                        table: table_name.to_string(),
+                        rowid,
+                        column: column.clone(),
+                        snippet,
+                    });
+                }
+            }
+            // FTS can match on stemmed/tokenized forms; still report the row
+            if !matched_any {
+                if let (Some(column), Some(value)) = (columns.first(), values.first()) {
+                    matches.push(SearchMatch {
+                        table: table_name.to_string(),
+                        rowid,
+                        column: column.clone(),
+                        snippet: value.as_text().chars().take(60).collect(),
+                    });
+                }
+            }
+            if matches.len() >= limit {
+                matches.truncate(limit);
+                break;
+            }
+        }
+        Ok(matches)
+    }
+
+    /// 0-based position of a row in the table's natural (rowid) order
+    pub fn row_position(&self, table_name: &str, rowid: i64) -> Result<usize> {
+        let query = format!("SELECT COUNT(*) FROM {} WHERE rowid < ?1", self.quote_table(table_name)?);
+        let position: i64 = self.conn.query_row(&query, [rowid], |row| row.get(0))?;
+        Ok(position as usize)
+    }
+}
+
+/// Case-insensitive excerpt of `value` around the first occurrence of `term`
+pub fn make_snippet(value: &str, term: &str) -> Option<String> {
+    let lower_value: Vec<char> = value.to_lowercase().chars().collect();
+    let lower_term: Vec<char> = term.to_lowercase().chars().collect();
+    if lower_term.is_empty() || lower_value.len() != value.chars().count() {
+        // Lowercasing changed the length (rare unicode cases); fall back to a plain check
+        return value
+            .to_lowercase()
+            .contains(&term.to_lowercase())
+            .then(|| value.chars().take(60).collect());
+    }
+
+    let start = lower_value
+        .windows(lower_term.len())
+        .position(|window| window == lower_term.as_slice())?;
+
+    let chars: Vec<char> = value.chars().collect();
+    let from = start.saturating_sub(20);
+    let to = (start + lower_term.len() + 20).min(chars.len());
+    let mut snippet: String = chars[from..to].iter().collect();
+    if from > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if to < chars.len() {
+        snippet.push_str("...");
+    }
+    Some(snippet.replace('\n', " "))
+}
+
+/// Write `data` into a new table of the SQLite database at `path`, declaring each
+/// column with its inferred type and storing values as integers, reals or text accordingly
+pub fn export_typed_table(
+    path: &Path,
+    table_name: &str,
+    data: &QueryResult,
+    types: &[ColumnType],
+    locale: NumberLocale,
+) -> Result<usize> {
+    let mut conn = Connection::open(path).context("Failed to create export database")?;
+    write_typed_table(&mut conn, table_name, data, types, locale)?;
+    Ok(data.rows.len())
+}
+
+fn write_typed_table(
+    conn: &mut Connection,
+    table_name: &str,
+    data: &QueryResult,
+    types: &[ColumnType],
+    locale: NumberLocale,
+) -> Result<()> {
+    use rusqlite::types::Value;
+
+    let declarations: Vec<String> = unique_column_names(&data.columns)
+        .iter()
+        .zip(types)
+        .map(|(column, ty)| {
+            let declared = match ty {
+                ColumnType::Int => "INTEGER",
+                ColumnType::Float => "REAL",
+                ColumnType::Bool => "BOOLEAN",
+                ColumnType::Date => "DATE",
+                ColumnType::Text => "TEXT",
+            };
+            format!("{} {}", quote_identifier(column), declared)
+        })
+        .collect();
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        &format!("CREATE TABLE {} ({})", quote_identifier(table_name), declarations.join(", ")),
+        [],
+    )?;
+    {
+        let placeholders = vec!["?"; data.columns.len()].join(", ");
+        let mut stmt = tx.prepare(&format!(
+            "INSERT INTO {} VALUES ({})",
+            quote_identifier(table_name),
+            placeholders
+        ))?;
+        for row in &data.rows {
+            let values: Vec<Value> = types
+                .iter()
+                .enumerate()
+                .map(|(idx, ty)| {
+                    row.get(idx).map_or(Value::Null, |cell| cell_sql_value(cell, *ty, locale))
+                })
+                .collect();
+            stmt.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// SQLite rejects repeated column names (case-insensitively), which spreadsheets allow
+fn unique_column_names(columns: &[String]) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    columns
+        .iter()
+        .map(|column| {
+            let mut name = column.clone();
+            let mut n = 2;
+            while !seen.insert(name.to_lowercase()) {
+                name = format!("{} ({})", column, n);
+                n += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+// Values that don't fit the column type (rows past the inference sample) are kept as text
+pub fn typed_value(value: &str, column_type: ColumnType, locale: NumberLocale) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+
+    let trimmed = value.trim();
+    if trimmed.is_empty() || trimmed == "NULL" {
+        return Value::Null;
+    }
+    let typed = match column_type {
+        ColumnType::Int => parse_integer(trimmed, locale).map(Value::Integer),
+        ColumnType::Float => parse_number(trimmed, locale).map(Value::Real),
+        ColumnType::Bool => parse_bool(trimmed).map(|b| Value::Integer(b as i64)),
+        ColumnType::Date => parse_date(trimmed).map(|dt| {
+            if trimmed.len() == 10 {
+                Value::Text(dt.format("%Y-%m-%d").to_string())
+            } else {
+                Value::Text(dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            }
+        }),
+        ColumnType::Text => None,
+    };
+    typed.unwrap_or_else(|| Value::Text(value.to_string()))
+}
+
+/// A value read from SQLite, as kept in a reverse statement
+fn sql_value(value: rusqlite::types::ValueRef) -> SqlValue {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => SqlValue::Null,
+        ValueRef::Integer(i) => SqlValue::Integer(i),
+        ValueRef::Real(x) => SqlValue::Real(x),
+        ValueRef::Text(text) => SqlValue::Text(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => SqlValue::Blob(blob.to_vec()),
+    }
+}
+
+/// The values of `columns` of a row read back, in order
+fn sql_values(row: &rusqlite::Row, columns: std::ops::Range<usize>) -> rusqlite::Result<Vec<SqlValue>> {
+    columns.map(|i| row.get_ref(i).map(sql_value)).collect()
+}
+
+/// Conditions of a reverse statement that its row still holds the values written, bound after
+/// the rowid
+fn unchanged_since(columns: &[String]) -> String {
+    columns.iter().map(|column| format!(" AND {} IS ?", column)).collect()
+}
+
+impl rusqlite::ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        use rusqlite::types::ValueRef;
+        Ok(rusqlite::types::ToSqlOutput::Borrowed(match self {
+            SqlValue::Null => ValueRef::Null,
+            SqlValue::Integer(i) => ValueRef::Integer(*i),
+            SqlValue::Real(x) => ValueRef::Real(*x),
+            SqlValue::Text(text) => ValueRef::Text(text.as_bytes()),
+            SqlValue::Blob(blob) => ValueRef::Blob(blob),
+        }))
+    }
+}
+
+/// A cell as stored by SQLite: typed cells keep their type, text is typed as its column
+pub fn cell_sql_value(cell: &Value, column_type: ColumnType, locale: NumberLocale) -> rusqlite::types::Value {
+    use rusqlite::types::Value as SqlValue;
+
+    match cell {
+        Value::Null => SqlValue::Null,
+        Value::Int(i) => SqlValue::Integer(*i),
+        Value::Float(f) => SqlValue::Real(*f),
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Date(_) => SqlValue::Text(cell.to_string()),
+        Value::Blob(bytes) => SqlValue::Blob(bytes.clone()),
+        Value::Text(text) => typed_value(text, column_type, locale),
+    }
+}
+
+/// Parse a query in the given dialect, returning the parser's complaint with its position
+pub fn syntax_error(query: &str, dialect: &dyn Dialect) -> Option<SyntaxError> {
+    let error = match Parser::parse_sql(dialect, query) {
+        Ok(_) => return None,
+        Err(ParserError::TokenizerError(message) | ParserError::ParserError(message)) => message,
+        Err(e) => e.to_string(),
+    };
+    // Running out of query has no location of its own
+    if error.ends_with("found: EOF") {
+        return Some(SyntaxError { message: error, position: Some(query.chars().count()) });
+    }
+    // Most messages end with " at Line: 2, Column: 7" (1-based)
+    let Some((message, location)) = error.rsplit_once(" at Line: ") else {
+        return Some(SyntaxError { message: error, position: None });
+    };
+    let position = location.split_once(", Column: ").and_then(|(line, column)| {
+        let line: usize = line.trim().parse().ok()?;
+        let column: usize = column.trim().parse().ok()?;
+        let line_start: usize = query.split('\n').take(line.checked_sub(1)?).map(|l| l.chars().count() + 1).sum();
+        Some(line_start + column.checked_sub(1)?)
+    });
+    Some(SyntaxError { message: message.to_string(), position })
+}
+
+/// What a piece of SQL is, for syntax highlighting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SqlTokenKind {
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Other, // identifiers, punctuation and whitespace
+}
+
+/// Split SQL into highlighted pieces that concatenate back to the original text. SQL the
+/// tokenizer rejects comes back as a single plain piece.
+pub fn highlight_sql(sql: &str) -> Vec<(SqlTokenKind, String)> {
+    use sqlparser::keywords::Keyword;
+    use sqlparser::tokenizer::Whitespace;
+
+    let Ok(tokens) = Tokenizer::new(&SQLiteDialect {}, sql).with_unescape(false).tokenize() else {
+        return vec![(SqlTokenKind::Other, sql.to_string())];
+    };
+    tokens
+        .into_iter()
+        .map(|token| {
+            let kind = match &token {
+                Token::Word(word) if word.quote_style.is_none() && word.keyword != Keyword::NoKeyword => {
+                    SqlTokenKind::Keyword
+                }
+                Token::SingleQuotedString(_) => SqlTokenKind::String,
+                Token::Number(_, _) => SqlTokenKind::Number,
+                Token::Whitespace(Whitespace::SingleLineComment { .. } | Whitespace::MultiLineComment(_)) => {
+                    SqlTokenKind::Comment
+                }
+                _ => SqlTokenKind::Other,
+            };
+            (kind, token.to_string())
+        })
+        .collect()
+}
+
+/// Names of the `:name` placeholders of a query, in order of first use. Placeholders in
+/// string literals and comments don't count, nor do `::` casts.
+pub fn query_parameters(query: &str) -> Vec<String> {
+    let Ok(tokens) = Tokenizer::new(&SQLiteDialect {}, query).tokenize() else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = Vec::new();
+    for pair in tokens.windows(2) {
+        if let [Token::Colon, Token::Word(word)] = pair {
+            if word.quote_style.is_none() && !names.contains(&word.value) {
+                names.push(word.value.clone());
+            }
+        }
+    }
+    names
+}
+
+/// Parameter values keyed by placeholder. Like the sqlite3 shell's `.parameter set`, numbers
+/// are bound as numbers (untyped columns don't compare 1 with '1') unless single-quoted, and
+/// "NULL" as NULL, like edited cells.
+fn parameter_values(params: &[(String, String)]) -> Vec<(String, rusqlite::types::Value)> {
+    use rusqlite::types::Value;
+
+    params
+        .iter()
+        .map(|(name, value)| {
+            let quoted = value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'');
+            let value = if quoted {
+                Value::Text(value[1..value.len() - 1].replace("''", "'"))
+            } else if value == "NULL" {
+                Value::Null
+            } else if let Ok(int) = value.trim().parse::<i64>() {
+                Value::Integer(int)
+            } else if let Some(float) = value.trim().parse::<f64>().ok().filter(|f| f.is_finite()) {
+                Value::Real(float)
+            } else {
+                Value::Text(value.clone())
+            };
+            (format!(":{}", name), value)
+        })
+        .collect()
+}
+
+/// The form rusqlite binds named parameters from
+fn named_params(values: &[(String, rusqlite::types::Value)]) -> Vec<(&str, &dyn rusqlite::ToSql)> {
+    values.iter().map(|(name, value)| (name.as_str(), value as &dyn rusqlite::ToSql)).collect()
+}
+
+/// The unquoted `x` of custom queries
+fn is_table_shorthand(ident: &Ident) -> bool {
+    ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("x")
+}
+
+/// Finds a CTE or table alias named `x`, which then means that rather than the current table
+struct DefinesX(bool);
+
+impl Visitor for DefinesX {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        if let Some(with) = &query.with {
+            self.0 |= with.cte_tables.iter().any(|cte| is_table_shorthand(&cte.alias.name));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Table { alias: Some(alias), .. } | TableFactor::Derived { alias: Some(alias), .. } = factor {
+            self.0 |= is_table_shorthand(&alias.name);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Replaces `x` in table references, `x.column` and `x.*` with the current table
+struct ReplaceX<'a> {
+    table: &'a ObjectName,
+    replaced: bool,
+}
+
+impl<'a> ReplaceX<'a> {
+    fn new(table: &'a ObjectName) -> Self {
+        Self { table, replaced: false }
+    }
+
+    fn replace_wildcards(&mut self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    if let SelectItem::QualifiedWildcard(name, _) = item {
+                        if matches!(name.0.as_slice(), [ident] if is_table_shorthand(ident)) {
+                            *name = self.table.clone();
+                            self.replaced = true;
+                        }
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.replace_wildcards(left);
+                self.replace_wildcards(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for ReplaceX<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.replace_wildcards(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        if matches!(relation.0.as_slice(), [ident] if is_table_shorthand(ident)) {
+            *relation = self.table.clone();
+            self.replaced = true;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::CompoundIdentifier(idents) = expr {
+            if idents.len() > 1 && is_table_shorthand(&idents[0]) {
+                idents.splice(0..1, self.table.0.iter().cloned());
+                self.replaced = true;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// SQLite parses `x REGEXP y` but ships no function behind it; this one makes it (and
+/// `re:` filters) work. NULL values give NULL, numbers are matched as text.
+fn register_regexp(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            // Compiled once per statement rather than once per row
+            let regex = ctx.get_or_create_aux(0, |pattern| -> Result<regex::Regex, BoxError> {
+                Ok(regex::Regex::new(pattern.as_str()?)?)
+            })?;
+            let text = match ctx.get_raw(1) {
+                ValueRef::Null => return Ok(None),
+                ValueRef::Integer(i) => i.to_string(),
+                ValueRef::Real(f) => f.to_string(),
+                ValueRef::Text(bytes) | ValueRef::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            };
+            Ok(Some(regex.is_match(&text)))
+        },
+    )
+    .context("Failed to register the regexp function")
+}
+
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn sqlite_value(value: rusqlite::types::Value) -> Value {
+    match value {
+        rusqlite::types::Value::Null => Value::Null,
+        rusqlite::types::Value::Integer(i) => Value::Int(i),
+        rusqlite::types::Value::Real(f) => Value::Float(f),
+        rusqlite::types::Value::Text(s) => Value::Text(s),
+        rusqlite::types::Value::Blob(b) => Value::Blob(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    #[test]
+    fn test_export_typed_table() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("typed.db");
+        let data = QueryResult {
+            columns: vec!["id".to_string(), "price".to_string(), "active".to_string()],
+            rows: text_rows(vec![vec!["1", "9.5", "yes"], vec!["2", "", "no"]]),
+            total_rows: 2,
+        };
+        let types = [ColumnType::Int, ColumnType::Float, ColumnType::Bool];
+        assert_eq!(export_typed_table(&path, "sales data", &data, &types, NumberLocale::En).unwrap(), 2);
+
+        let db = Database::open(&path).unwrap();
+        let result = db
+            .execute_query("SELECT typeof(id), typeof(price), active FROM \"sales data\" ORDER BY id")
+            .unwrap();
+        assert_eq!(result.rows[0], vec!["integer", "real", "1"]);
+        assert_eq!(result.rows[1], vec!["integer", "null", "0"]);
+    }
+
+    #[test]
+    fn test_column_stats() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (n REAL, s TEXT);
+                 INSERT INTO t VALUES (4, 'b'), (NULL, 'a'), (1.5, NULL), (4, 'b'), (10, 'c');",
+            )
+            .unwrap();
+
+        let stats = db.column_stats("t", "n", &[]).unwrap();
+        assert_eq!((stats.rows, stats.nulls, stats.distinct), (5, 1, 3));
+        assert_eq!((stats.min.as_deref(), stats.max.as_deref()), (Some("1.5"), Some("10")));
+        let numeric = stats.numeric.unwrap();
+        assert_eq!((numeric.mean, numeric.median), (4.875, 4.0));
+        assert!((numeric.stddev - 3.6142).abs() < 1e-3);
+
+        let stats = db.column_stats("t", "s", &[]).unwrap();
+        assert!(stats.numeric.is_none());
+        assert_eq!(stats.top_values[0], ("b".to_string(), 2));
+
+        let filters = [ColumnFilter::parse("s", "b").unwrap()];
+        let stats = db.column_stats("t", "n", &filters).unwrap();
+        assert_eq!((stats.rows, stats.distinct), (2, 1));
+        assert_eq!(stats.numeric.unwrap().stddev, 0.0);
+    }
+
+    #[test]
+    fn test_column_aggregate() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (n, s TEXT);
+                 INSERT INTO t VALUES (4, 'b'), (NULL, 'a'), (1.5, 'a'), ('x', 'b'), (10, 'c');",
+            )
+            .unwrap();
+
+        let aggregate = |func: &str, filters: &[ColumnFilter]| db.column_aggregate("t", "n", func, filters).unwrap();
+        assert_eq!(aggregate("sum", &[]), 15.5);
+        assert_eq!(aggregate("count", &[]), 3.0);
+        assert_eq!((aggregate("min", &[]), aggregate("max", &[])), (1.5, 10.0));
+        let filters = [ColumnFilter::parse("s", "b").unwrap()];
+        assert_eq!(aggregate("mean", &filters), 4.0);
+        let filters = [ColumnFilter::parse("s", "z").unwrap()];
+        assert_eq!(aggregate("max", &filters), 0.0);
+        assert!(db.column_aggregate("t", "n", "median", &[]).is_err());
+    }
+
+    #[test]
+    fn test_window_column() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch("CREATE TABLE t (n, s TEXT); INSERT INTO t VALUES (4, 'b'), (NULL, 'a'), (1.5, 'a'), (10, 'c'), (2, 'b');")
+            .unwrap();
+        let window = |expression: &str| WindowSpec::parse(expression).unwrap().unwrap();
+
+        // Running on from the rows before the page
+        let values = db.window_column("t", &window("cumsum(n)"), None, &[], 2, 2).unwrap();
+        assert_eq!(values, vec![Value::Float(5.5), Value::Float(15.5)]);
+        let sort = SortOrder { column: "n".to_string(), descending: true };
+        let values = db.window_column("t", &window("lag(n)"), Some(&sort), &[], 0, 3).unwrap();
+        assert_eq!(values, vec![Value::Null, Value::Int(10), Value::Int(4)]);
+        let values = db.window_column("t", &window("rank(n)"), None, &[], 0, 5).unwrap();
+        assert_eq!(values, vec![Value::Int(2), Value::Null, Value::Int(4), Value::Int(1), Value::Int(3)]);
+
+        let filters = [ColumnFilter::parse("s", "!=a").unwrap()];
+        let values = db.window_column("t", &window("rolling_mean(n, 2)"), None, &filters, 0, 5).unwrap();
+        assert_eq!(values, vec![Value::Float(4.0), Value::Float(7.0), Value::Float(6.0)]);
+    }
+
+    #[test]
+    fn test_column_histogram() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (n INTEGER, x REAL, s TEXT);
+                 WITH RECURSIVE seq(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM seq WHERE i < 100)
+                 INSERT INTO t SELECT i % 4, i / 10.0, CASE WHEN i % 3 = 0 THEN 'a' ELSE 'b' END FROM seq;
+                 INSERT INTO t VALUES (NULL, NULL, NULL);",
+            )
+            .unwrap();
+
+        let chart = db.column_histogram("t", "n", &[]).unwrap();
+        assert!(chart.numeric);
+        assert_eq!(chart.bars.iter().map(|(label, _)| label.as_str()).collect::<Vec<_>>(), vec!["0", "1", "2", "3"]);
+        assert_eq!(chart.bars[0].1, 26);
+
+        let chart = db.column_histogram("t", "x", &[]).unwrap();
+        assert_eq!(chart.bars.len(), HISTOGRAM_BINS);
+        assert_eq!(chart.bars.iter().map(|(_, count)| count).sum::<u64>(), 101);
+        assert_eq!(chart.bars[HISTOGRAM_BINS - 1].1, 6);
+
+        let chart = db.column_histogram("t", "s", &[]).unwrap();
+        assert_eq!(chart.bars, vec![("b".to_string(), 67), ("a".to_string(), 34)]);
+    }
+
+    #[test]
+    fn test_column_value_counts() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (s TEXT, n INTEGER);
+                 INSERT INTO t VALUES ('b', 1), ('a', 2), (NULL, 3), ('b', 4), (NULL, 5), ('b', 6);",
+            )
+            .unwrap();
+
+        let counts = db.column_value_counts("t", "s", &[]).unwrap();
+        assert_eq!((counts.rows, counts.distinct), (6, 3));
+        assert_eq!(counts.values, vec![(Value::from("b"), 3), (Value::Null, 2), (Value::from("a"), 1)]);
+
+        let filters = [ColumnFilter::parse("n", "<4").unwrap()];
+        let counts = db.column_value_counts("t", "s", &filters).unwrap();
+        assert_eq!((counts.rows, counts.distinct), (3, 3));
+    }
 
-impl Database {
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let conn = Connection::open(path)
-            .context("Failed to open database")?;
-        Ok(Self { conn })
+    #[test]
+    fn test_sqlite_values() {
+        use rusqlite::types::Value as SqlValue;
+        assert_eq!(
+            sqlite_value(SqlValue::Blob((0u8..16).collect())).to_string(),
+            "x'000102030405060708090a0b0c0d0e0f'"
+        );
+        assert_eq!(sqlite_value(SqlValue::Blob(vec![1, 2, 3])).to_string(), "[BLOB 3 bytes]");
+        assert_eq!(sqlite_value(SqlValue::Integer(7)), Value::Int(7));
+        assert_eq!(sqlite_value(SqlValue::Null), Value::Null);
     }
 
-    pub fn get_tables(&self) -> Result<Vec<String>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT name FROM sqlite_master WHERE type='table' ORDER BY name"
-        )?;
-        
-        let rows = stmt.query_map([], |row| {
-            Ok(row.get::<_, String>(0)?)
-        })?;
+    #[test]
+    fn test_row_changes_written_back() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER);
+                 INSERT INTO users (name, age) VALUES ('Alice', 30), ('Bob', 25);",
+            )
+            .unwrap();
 
-        let mut tables = Vec::new();
-        for row in rows {
-            tables.push(row?);
+        let original = db.get_table_data("users", 0, 10, None, &[]).unwrap();
+        assert_eq!(original.columns, vec!["rowid", "id", "name", "age"]);
+
+        // Edit a cell, add a row and append a computed column, as the UI does
+        let mut edited = original.clone();
+        edited.columns.push("double_age".to_string());
+        for row in &mut edited.rows {
+            row.push(Value::from("0"));
         }
-        
-        Ok(tables)
+        edited.rows[0][2] = Value::from("Alicia");
+        edited.rows.push(vec!["".into(), "".into(), "Carol".into(), "41".into(), "82".into()]);
+        let mut with_deleted = edited.clone();
+        with_deleted.rows.remove(1);
+        assert_eq!(row_changes(&original, &with_deleted).unwrap().last(), Some(&RowChange::Delete { rowid: 2 }));
+
+        let changes = row_changes(&original, &edited).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                RowChange::Update { rowid: 1, values: vec![("name".into(), "Alicia".into())] },
+                RowChange::Insert {
+                    values: vec![("name".into(), "Carol".into()), ("age".into(), "41".into())]
+                },
+            ]
+        );
+        db.apply_row_changes("users", &changes).unwrap();
+
+        let result = db
+            .execute_query("SELECT id, name, typeof(age) FROM users ORDER BY id")
+            .unwrap();
+        assert_eq!(result.rows[0], vec!["1", "Alicia", "integer"]);
+        assert_eq!(result.rows[2], vec!["3", "Carol", "integer"]);
+        db.apply_row_changes("users", &[RowChange::Delete { rowid: 3 }]).unwrap();
+        assert_eq!(db.execute_query("SELECT count(*) FROM users").unwrap().rows[0], vec!["2"]);
+
+        // A failing statement rolls back the whole batch
+        let bad = vec![
+            RowChange::Update { rowid: 2, values: vec![("name".into(), "Robert".into())] },
+            RowChange::Update { rowid: 2, values: vec![("missing".into(), "x".into())] },
+        ];
+        assert!(db.apply_row_changes("users", &bad).is_err());
+        let result = db.execute_query("SELECT name FROM users WHERE id = 2").unwrap();
+        assert_eq!(result.rows[0], vec!["Bob"]);
     }
 
-    pub fn get_table_info(&self, table_name: &str) -> Result<TableInfo> {
-        // Get column information
-        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table_name))?;
-        let rows = stmt.query_map([], |row| {
-            Ok(row.get::<_, String>(1)?) // Column name is at index 1
-        })?;
+    #[test]
+    fn test_revert_row_changes() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, score REAL, data BLOB);
+                 INSERT INTO t VALUES (1, 'a', 1.5, x'00ff'), (2, NULL, 2, NULL), (3, 'c', 3, NULL);",
+            )
+            .unwrap();
+        let dump = |db: &Database| db.execute_query("SELECT rowid, id, name, typeof(score), hex(data) FROM t ORDER BY rowid").unwrap().rows;
+        let before = dump(&db);
 
-        let mut columns = Vec::new();
-        for row in rows {
-            columns.push(row?);
-        }
+        let changes = vec![
+            RowChange::Update { rowid: 2, values: vec![("name".into(), "b".into()), ("score".into(), "NULL".into())] },
+            RowChange::Insert { values: vec![("name".into(), "d".into())] },
+            RowChange::Delete { rowid: 1 },
+        ];
+        let reverse = db.apply_row_changes("t", &changes).unwrap();
+        assert_eq!(reverse.len(), 3);
+        assert!(reverse[0].sql.starts_with("INSERT INTO \"t\" (rowid, \"id\""));
+        assert_ne!(dump(&db), before);
 
-        // Get total row count
-        let mut stmt = self.conn.prepare(&format!("SELECT COUNT(*) FROM {}", table_name))?;
-        let total_rows: i64 = stmt.query_row([], |row| row.get(0))?;
+        db.revert_changes(&reverse).unwrap();
+        assert_eq!(dump(&db), before);
 
-        Ok(TableInfo {
-            name: table_name.to_string(),
-            columns,
-            total_rows: total_rows as usize,
-        })
+        // A save whose rows are gone is not reverted, not even in part
+        let reverse = db
+            .apply_row_changes("t", &[RowChange::Update { rowid: 3, values: vec![("name".into(), "z".into())] }, RowChange::Delete { rowid: 2 }])
+            .unwrap();
+        db.conn.execute_batch("DELETE FROM t WHERE rowid = 3; INSERT INTO t (id, name) VALUES (2, 'taken');").unwrap();
+        let changed = dump(&db);
+        assert!(db.revert_changes(&reverse).is_err());
+        assert_eq!(dump(&db), changed);
     }
 
-    pub fn get_table_data(
-        &self,
-        table_name: &str,
-        offset: usize,
-        limit: usize,
-    ) -> Result<QueryResult> {
-        // Include rowid for update operations
-        let query = format!("SELECT rowid, * FROM {} LIMIT {} OFFSET {}", table_name, limit, offset);
-        self.execute_query(&query)
+    #[test]
+    fn test_revert_finds_rows_as_saved() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, n INTEGER); INSERT INTO t VALUES (1, 'a', 1), (2, 'b', 2);")
+            .unwrap();
+        let dump = |db: &Database| db.execute_query("SELECT rowid, id, name, typeof(n) FROM t ORDER BY rowid").unwrap().rows;
+        let before = dump(&db);
+
+        // A changed INTEGER PRIMARY KEY moves the row to a new rowid
+        let reverse = db
+            .apply_row_changes("t", &[RowChange::Update { rowid: 1, values: vec![("id".into(), "10".into()), ("n".into(), "5".into())] }])
+            .unwrap();
+        assert_eq!(dump(&db)[1][0], "10");
+        db.revert_changes(&reverse).unwrap();
+        assert_eq!(dump(&db), before);
+
+        // Rows changed since the save are left alone, as is the rest of it
+        let reverse = db
+            .apply_row_changes(
+                "t",
+                &[
+                    RowChange::Update { rowid: 1, values: vec![("name".into(), "x".into())] },
+                    RowChange::Insert { values: vec![("name".into(), "c".into())] },
+                ],
+            )
+            .unwrap();
+        db.conn.execute_batch("UPDATE t SET name = 'y' WHERE id = 1;").unwrap();
+        let changed = dump(&db);
+        assert!(db.revert_changes(&reverse).is_err());
+        assert_eq!(dump(&db), changed);
+
+        db.conn.execute_batch("UPDATE t SET name = 'x' WHERE id = 1; UPDATE t SET n = 7 WHERE id = 3;").unwrap();
+        assert!(db.revert_changes(&reverse).is_err());
+        db.conn.execute_batch("UPDATE t SET n = NULL WHERE id = 3;").unwrap();
+        db.revert_changes(&reverse).unwrap();
+        assert_eq!(dump(&db), before);
     }
 
-    pub fn execute_query(&self, query: &str) -> Result<QueryResult> {
-        let mut stmt = self.conn.prepare(query)?;
-        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-        
-        let rows = stmt.query_map([], |row| {
-            let mut values = Vec::new();
-            for i in 0..column_names.len() {
-                let value: rusqlite::types::Value = row.get(i)?;
-                values.push(format_value(value));
-            }
-            Ok(values)
-        })?;
+    #[test]
+    fn test_preview_update() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch("CREATE TABLE t (n INTEGER, s TEXT); INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c');")
+            .unwrap();
+        let update = BulkUpdate::parse("n = n * 10, s = upper(s) WHERE n >= 2", "t").unwrap();
+        let preview = db.preview_update("t", &update, &[1, 2]).unwrap();
+        assert_eq!(preview, vec![(2, vec![Value::Int(20), Value::Text("B".to_string())])]);
+        // Nothing was written
+        assert_eq!(db.execute_query("SELECT n FROM t WHERE rowid = 2").unwrap().rows[0], vec![Value::Int(2)]);
+    }
 
-        let mut result_rows = Vec::new();
-        for row in rows {
-            result_rows.push(row?);
-        }
+    #[test]
+    fn test_alter_column() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL, code TEXT UNIQUE);
+                 CREATE INDEX t_name ON t (name);
+                 INSERT INTO t VALUES (1, 'a', 'x'), (2, 'b', 'y');",
+            )
+            .unwrap();
 
-        // Try to get total count for the query (simplified approach)
-        let total_rows = result_rows.len();
+        db.alter_column("t", &ColumnEdit::parse_add("score INTEGER = 0").unwrap()).unwrap();
+        db.alter_column("t", &ColumnEdit::Rename { from: "name".to_string(), to: "label".to_string() }).unwrap();
+        let result = db.execute_query("SELECT label, score FROM t ORDER BY id").unwrap();
+        assert_eq!(result.rows[1], vec![Value::Text("b".to_string()), Value::Int(0)]);
 
-        Ok(QueryResult {
-            columns: column_names,
-            rows: result_rows,
-            total_rows,
-        })
+        // A UNIQUE column can't be dropped by ALTER TABLE, so the table is rebuilt
+        db.alter_column("t", &ColumnEdit::Drop("code".to_string())).unwrap();
+        let schema = db.table_schema("t").unwrap();
+        let columns: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(columns, vec!["id", "label", "score"]);
+        assert!(schema.columns[0].primary_key && schema.columns[1].not_null);
+        assert_eq!(schema.indexes.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), vec!["t_name"]);
+        assert_eq!(db.execute_query("SELECT count(*) FROM t").unwrap().rows[0], vec!["2"]);
+        assert!(db.alter_column("t", &ColumnEdit::Drop("missing".to_string())).is_err());
     }
 
-    pub fn execute_custom_query(
-        &self,
-        query: &str,
-        table_name: &str,
-        offset: usize,
-        limit: usize,
-    ) -> Result<QueryResult> {
-        // Replace 'x' with the actual table name (case insensitive, word boundary)
-        let mut processed_query = query.to_string();
-        
-        // Use regex-like replacement for word boundaries
-        // Replace 'x' when it's a standalone word (not part of another word)
-        let words: Vec<&str> = processed_query.split_whitespace().collect();
-        let mut replaced_words = Vec::new();
-        
-        for word in words {
-            // Check if word is exactly 'x' (case insensitive) or 'x' followed by punctuation
-            if word.to_lowercase() == "x" {
-                replaced_words.push(table_name.to_string());
-            } else if word.to_lowercase().starts_with("x") && 
-                     word.len() > 1 && 
-                     !word.chars().nth(1).unwrap().is_alphanumeric() {
-                // Handle cases like "x," "x;" "x)" etc.
-                let rest = &word[1..];
-                replaced_words.push(format!("{}{}", table_name, rest));
-            } else {
-                replaced_words.push(word.to_string());
-            }
-        }
-        processed_query = replaced_words.join(" ");
+    #[test]
+    fn test_filtered_table_data() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (name TEXT, age INTEGER);
+                 INSERT INTO t VALUES ('Alice', 30), ('Bob', 25), ('Carol', 41), ('alan', NULL);",
+            )
+            .unwrap();
 
-        // Add table context if FROM is missing
-        let mut final_query = if !processed_query.to_uppercase().contains("FROM") {
-            format!("{} FROM {}", processed_query, table_name)
-        } else {
-            processed_query
-        };
+        let filters = vec![
+            ColumnFilter::parse("age", ">=30").unwrap(),
+            ColumnFilter::parse("name", "contains:a").unwrap(),
+        ];
+        let result = db.get_table_data("t", 0, 1, None, &filters).unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.total_rows, 2);
 
-        // Ensure rowid is included for update operations (only if SELECT * is used)
-        if final_query.to_uppercase().contains("SELECT *") {
-            final_query = final_query.replace("SELECT *", "SELECT rowid, *");
-        }
+        let missing = vec![ColumnFilter::parse("age", "=NULL").unwrap()];
+        let result = db.get_table_data("t", 0, 10, None, &missing).unwrap();
+        assert_eq!(result.rows[0][1], "alan");
+    }
 
-        // Add pagination
-        let paginated_query = format!("{} LIMIT {} OFFSET {}", final_query, limit, offset);
-        
-        let mut stmt = self.conn.prepare(&paginated_query)?;
-        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-        
-        let rows = stmt.query_map([], |row| {
-            let mut values = Vec::new();
-            for i in 0..column_names.len() {
-                let value: rusqlite::types::Value = row.get(i)?;
-                values.push(format_value(value));
-            }
-            Ok(values)
-        })?;
+    #[test]
+    fn test_attach_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sales_path = temp_dir.path().join("Sales 2024.db");
+        Connection::open(&sales_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE orders (customer_id INTEGER, total REAL);
+                 INSERT INTO orders VALUES (1, 9.5), (1, 20.0), (2, 3.0);",
+            )
+            .unwrap();
 
-        let mut result_rows = Vec::new();
-        for row in rows {
-            result_rows.push(row?);
-        }
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch("CREATE TABLE customers (id INTEGER, name TEXT); INSERT INTO customers VALUES (1, 'Alice'), (2, 'Bob');")
+            .unwrap();
+        assert_eq!(db.attach(&sales_path).unwrap(), "sales_2024");
+        assert_eq!(db.attach(&sales_path).unwrap(), "sales_2024_2");
+        assert_eq!(
+            db.get_tables().unwrap(),
+            vec!["customers", "sales_2024.orders", "sales_2024_2.orders"]
+        );
 
-        // Try to get total count for the custom query
-        let count_query = format!("SELECT COUNT(*) FROM ({})", final_query);
-        let total_rows = match self.conn.prepare(&count_query) {
-            Ok(mut stmt) => {
-                match stmt.query_row([], |row| row.get::<_, i64>(0)) {
-                    Ok(count) => count as usize,
-                    Err(_) => result_rows.len(), // Fallback to current result count
-                }
-            }
-            Err(_) => result_rows.len(), // Fallback to current result count
-        };
+        let joined = db
+            .execute_query(
+                "SELECT c.name, SUM(o.total) FROM customers c JOIN sales_2024.orders o ON o.customer_id = c.id GROUP BY c.name",
+            )
+            .unwrap();
+        assert_eq!(joined.rows, vec![vec!["Alice", "29.5"], vec!["Bob", "3"]]);
 
-        Ok(QueryResult {
-            columns: column_names,
-            rows: result_rows,
-            total_rows,
-        })
+        let orders = db.get_table_data("sales_2024.orders", 0, 10, None, &[]).unwrap();
+        assert_eq!(orders.total_rows, 3);
+        let update = RowChange::Update { rowid: 3, values: vec![("total".to_string(), "4.5".to_string())] };
+        db.apply_row_changes("sales_2024.orders", &[update]).unwrap();
+        assert_eq!(db.row_position("sales_2024.orders", 3).unwrap(), 2);
+
+        let not_a_database = temp_dir.path().join("notes.db");
+        std::fs::write(&not_a_database, "just some text, long enough to not be a header").unwrap();
+        assert!(db.attach(&not_a_database).is_err());
+        assert!(db.attach(&temp_dir.path().join("missing.db")).is_err());
+        assert_eq!(db.attached_databases().unwrap(), vec!["sales_2024", "sales_2024_2"]);
     }
 
-    pub fn export_table_to_csv(&self, table_name: &str, filename: &str) -> Result<usize> {
-        let query = format!("SELECT * FROM {}", table_name);
-        let result = self.execute_query(&query)?;
-        self.write_csv(&result, filename)?;
-        Ok(result.rows.len())
+    #[test]
+    fn test_get_schema_objects() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE, name TEXT);
+                 CREATE INDEX idx_users_name ON users (name);
+                 CREATE TABLE log (msg TEXT);
+                 CREATE TRIGGER users_log AFTER INSERT ON users BEGIN INSERT INTO log VALUES (new.name); END;",
+            )
+            .unwrap();
+
+        let objects = db.get_schema_objects().unwrap();
+        let names: Vec<(&str, &str)> = objects
+            .iter()
+            .map(|o| (o.kind.as_str(), o.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("index", "idx_users_name"),
+                ("index", "sqlite_autoindex_users_1"),
+                ("trigger", "users_log")
+            ]
+        );
+        assert!(objects.iter().all(|o| o.table == "users"));
+        assert!(objects[0].sql.as_deref().unwrap().starts_with("CREATE INDEX"));
+        assert!(objects[1].sql.is_none());
     }
 
-    pub fn export_query_to_csv(&self, query: &str, filename: &str) -> Result<usize> {
-        let result = self.execute_query(query)?;
-        self.write_csv(&result, filename)?;
-        Ok(result.rows.len())
+    #[test]
+    fn test_views_and_sidebar_objects() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT UNIQUE);
+                 INSERT INTO users (name) VALUES ('Alice'), ('Bob');
+                 CREATE VIEW named_b AS SELECT name FROM users WHERE name LIKE 'B%';
+                 CREATE INDEX idx_users_id ON users (id);
+                 CREATE TRIGGER users_noop AFTER INSERT ON users BEGIN SELECT 1; END;
+                 CREATE VIRTUAL TABLE docs USING fts5(body);",
+            )
+            .unwrap();
+
+        let tables = db.get_tables().unwrap();
+        assert_eq!(tables.last().map(|t| t.as_str()), Some("named_b"));
+        assert!(tables.iter().any(|t| t == "docs"));
+        let page = db.get_table_data("named_b", 0, 10, None, &[]).unwrap();
+        assert_eq!(page.columns, vec!["name"]);
+        assert_eq!(page.rows, vec![vec!["Bob"]]);
+        assert_eq!(page.total_rows, 1);
+
+        let objects = db.get_sidebar_objects().unwrap();
+        let names: Vec<(&str, &str)> = objects.iter().map(|o| (o.kind.as_str(), o.name.as_str())).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("view", "named_b"),
+                ("virtual table", "docs"),
+                ("index", "idx_users_id"),
+                ("index", "sqlite_autoindex_users_1"),
+                ("trigger", "users_noop")
+            ]
+        );
+        assert!(objects[0].sql.as_deref().unwrap().starts_with("CREATE VIEW"));
     }
 
-    fn write_csv(&self, result: &QueryResult, filename: &str) -> Result<()> {
-        let mut writer = csv::Writer::from_path(filename)?;
-        
-        // Write header
-        writer.write_record(&result.columns)?;
-        
-        // Write data rows
-        for row in &result.rows {
-            writer.write_record(row)?;
-        }
-        
-        writer.flush()?;
-        Ok(())
+    #[test]
+    fn test_table_schema() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE);
+                 CREATE TABLE orders (
+                     id INTEGER PRIMARY KEY,
+                     user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                     status TEXT DEFAULT 'new',
+                     note
+                 );
+                 CREATE INDEX idx_orders_user ON orders (user_id, status);",
+            )
+            .unwrap();
+
+        let schema = db.table_schema("orders").unwrap();
+        assert!(!schema.inferred);
+        let id = &schema.columns[0];
+        assert_eq!((id.name.as_str(), id.column_type.as_str(), id.primary_key), ("id", "INTEGER", true));
+        assert!(schema.columns[1].not_null);
+        assert_eq!(schema.columns[2].default.as_deref(), Some("'new'"));
+        assert_eq!(schema.columns[3].column_type, "");
+
+        assert_eq!(schema.indexes.len(), 1);
+        assert_eq!(schema.indexes[0].columns, vec!["user_id", "status"]);
+        assert!(!schema.indexes[0].unique);
+
+        assert_eq!(schema.foreign_keys.len(), 1);
+        let key = &schema.foreign_keys[0];
+        assert_eq!(key.table, "users");
+        assert_eq!((key.columns.clone(), key.references.clone()), (vec!["user_id".to_string()], vec!["id".to_string()]));
+        assert_eq!(key.on_delete, "CASCADE");
+
+        let users = db.table_schema("users").unwrap();
+        assert_eq!(users.indexes[0].origin, "u");
+        assert!(users.indexes[0].unique);
+        assert!(db.table_schema("missing").is_err());
     }
 
-}
+    #[test]
+    fn test_table_stats() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT);
+                 CREATE INDEX idx_t_name ON t (name);
+                 INSERT INTO t (name) VALUES ('a'), ('b'), ('c');",
+            )
+            .unwrap();
 
-fn format_value(value: rusqlite::types::Value) -> String {
-    match value {
-        rusqlite::types::Value::Null => "NULL".to_string(),
-        rusqlite::types::Value::Integer(i) => i.to_string(),
-        rusqlite::types::Value::Real(f) => f.to_string(),
-        rusqlite::types::Value::Text(s) => s,
-        rusqlite::types::Value::Blob(b) => format!("[BLOB {} bytes]", b.len()),
+        let stats = db.table_stats("t").unwrap();
+        assert_eq!((stats.rows, stats.columns), (3, 2));
+        assert!(stats.size_bytes.unwrap() > 0);
+        assert_eq!(stats.analyzed_rows, None);
+
+        db.conn.execute_batch("ANALYZE").unwrap();
+        assert_eq!(db.table_stats("t").unwrap().analyzed_rows, Some(3));
+        assert!(db.table_stats("missing").is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_table_ddl() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE);
+                 CREATE INDEX idx_users_email ON users (email);
+                 CREATE TRIGGER users_noop AFTER INSERT ON users BEGIN SELECT 1; END;",
+            )
+            .unwrap();
+
+        let ddl = db.table_ddl("users").unwrap();
+        assert_eq!(ddl.len(), 3);
+        assert!(ddl[0].starts_with("CREATE TABLE users"));
+        assert!(ddl[1].starts_with("CREATE INDEX idx_users_email"));
+        assert!(ddl[2].starts_with("CREATE TRIGGER users_noop"));
+        assert!(db.table_ddl("missing").is_err());
+    }
+
+    #[test]
+    fn test_highlight_sql() {
+        let sql = "CREATE TABLE \"t\" (n INTEGER DEFAULT 0, s TEXT CHECK (s <> 'x')) -- note";
+        let pieces = highlight_sql(sql);
+        assert_eq!(pieces.iter().map(|(_, text)| text.as_str()).collect::<String>(), sql);
+        let kind_of = |text: &str| pieces.iter().find(|(_, t)| t == text).map(|(kind, _)| *kind);
+        assert_eq!(kind_of("CREATE"), Some(SqlTokenKind::Keyword));
+        assert_eq!(kind_of("\"t\""), Some(SqlTokenKind::Other));
+        assert_eq!(kind_of("0"), Some(SqlTokenKind::Number));
+        assert_eq!(kind_of("'x'"), Some(SqlTokenKind::String));
+        assert_eq!(pieces.last().map(|(kind, _)| *kind), Some(SqlTokenKind::Comment));
+    }
+
+    #[test]
+    fn test_referenced_columns() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE);
+                 CREATE TABLE orders (user_id INTEGER REFERENCES users, email TEXT REFERENCES users(email));",
+            )
+            .unwrap();
+
+        let keys = db.foreign_keys("orders").unwrap();
+        let targets: Vec<Vec<String>> = keys.iter().map(|key| db.referenced_columns(key).unwrap()).collect();
+        assert!(targets.contains(&vec!["id".to_string()]));
+        assert!(targets.contains(&vec!["email".to_string()]));
+        assert!(db.foreign_keys("users").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_change_signature() {
+        assert!(Database::open(":memory:").unwrap().change_signature().is_none());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("watched.db");
+        let db = Database::open(&path).unwrap();
+        db.conn.execute("CREATE TABLE t (v TEXT)", []).unwrap();
+        let before = db.change_signature().unwrap();
+        assert_eq!(db.change_signature().unwrap(), before);
+
+        // A commit from another process shows up in the WAL
+        let other = Connection::open(&path).unwrap();
+        other.pragma_update(None, "journal_mode", "WAL").unwrap();
+        other.execute("INSERT INTO t VALUES ('changed')", []).unwrap();
+        assert_ne!(db.change_signature().unwrap(), before);
+    }
 
     #[test]
     fn test_table_alias_replacement() {
@@ -267,7 +2694,7 @@ mod tests {
             println!("Testing query: {} -> Expected: {}", input_query, expected_processed);
             
             // The actual processed query will have LIMIT and OFFSET added, so we need to check the processing logic
-            let result = db.execute_custom_query(input_query, "users", 0, 10);
+            let result = db.execute_custom_query(input_query, &[], "users", 0, 10);
             
             // If query executes without error, the alias replacement worked
             match result {
@@ -277,6 +2704,312 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_custom_query_rewrite() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO users (name) VALUES ('Alice'), ('x');
+             CREATE TABLE orders (user_id INTEGER, amount INTEGER);
+             INSERT INTO orders VALUES (1, 10), (1, 5), (2, 7);",
+        ).unwrap();
+        let rewrite = |query: &str| db.rewrite_custom_query(query, "users").unwrap().0;
+
+        // Other tables are referenced by name, with or without the alias
+        assert_eq!(
+            rewrite("SELECT x.name, SUM(o.amount) FROM x JOIN orders o ON o.user_id = x.id GROUP BY x.name"),
+            r#"SELECT "users".name, SUM(o.amount) FROM "users" JOIN orders AS o ON o.user_id = "users".id GROUP BY "users".name"#
+        );
+        let result = db
+            .execute_custom_query("SELECT name, amount FROM users JOIN orders ON user_id = id ORDER BY amount", &[], "users", 0, 10)
+            .unwrap();
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.rows[0], vec!["Alice".to_string(), "5".to_string()]);
+
+        // String literals and longer words are left alone
+        assert_eq!(rewrite("SELECT * FROM x WHERE name = 'x'"), r#"SELECT * FROM "users" WHERE name = 'x'"#);
+        assert_eq!(rewrite("SELECT max(id) AS xx FROM x;"), r#"SELECT max(id) AS xx FROM "users""#);
+
+        // An `x` defined by the query is not the current table
+        assert_eq!(rewrite("SELECT x.amount FROM orders x"), "SELECT x.amount FROM orders AS x");
+        assert_eq!(
+            rewrite("WITH x AS (SELECT 1 AS n) SELECT n FROM x"),
+            "WITH x AS (SELECT 1 AS n) SELECT n FROM x"
+        );
+
+        // rowid is only added to a plain SELECT * of one table
+        let (query, with_rowid) = db.rewrite_custom_query("SELECT * FROM x", "users").unwrap();
+        assert_eq!(query, r#"SELECT * FROM "users""#);
+        assert_eq!(with_rowid.as_deref(), Some(r#"SELECT rowid, * FROM "users""#));
+        assert!(db.rewrite_custom_query("SELECT * FROM x JOIN orders", "users").unwrap().1.is_none());
+
+        // Queries over the whole database have no current table for `x` to stand for
+        assert_eq!(
+            db.rewrite_custom_query("SELECT x.amount FROM orders x;", "").unwrap(),
+            ("SELECT x.amount FROM orders x".to_string(), None)
+        );
+
+        // A real table named x wins over the shorthand
+        db.conn.execute_batch("CREATE TABLE x (n INTEGER); INSERT INTO x VALUES (42);").unwrap();
+        let result = db.execute_custom_query("SELECT n FROM x", &[], "users", 0, 10).unwrap();
+        assert_eq!(result.rows, vec![vec!["42".to_string()]]);
+    }
+
+    #[test]
+    fn test_plan_and_execute_statements() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, active INTEGER);
+             INSERT INTO users (name, active) VALUES ('Alice', 1), ('Bob', 0), ('Carol', 0);",
+        ).unwrap();
+        let count = |sql: &str| -> i64 { db.conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+
+        // Queries that only read run as usual
+        assert!(db.plan_statement("SELECT * FROM x", &[], "users").unwrap().is_none());
+        assert!(db.plan_statement("PRAGMA table_info(users)", &[], "users").unwrap().is_none());
+
+        // Row changes are counted without being made
+        let plan = db.plan_statement("UPDATE x SET active = 1 WHERE active = 0;", &[], "users").unwrap().unwrap();
+        assert_eq!(plan.sql, r#"UPDATE "users" SET active = 1 WHERE active = 0"#);
+        assert_eq!(plan.affected_rows, Some(2));
+        assert_eq!(count("SELECT COUNT(*) FROM users WHERE active = 0"), 2);
+        assert_eq!(db.execute_statement(&plan).unwrap(), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM users WHERE active = 0"), 0);
+
+        // Text without the shorthand runs as typed
+        let plan = db.plan_statement("delete from users where name = 'x'", &[], "users").unwrap().unwrap();
+        assert_eq!(plan.sql, "delete from users where name = 'x'");
+        assert_eq!(plan.affected_rows, Some(0));
+
+        let plan = db.plan_statement("INSERT INTO x (name) VALUES ('Dan'), ('Eve')", &[], "users").unwrap().unwrap();
+        assert_eq!(plan.affected_rows, Some(2));
+
+        // Schema changes aren't counted beforehand
+        let plan = db.plan_statement("CREATE TABLE archive AS SELECT * FROM users", &[], "users").unwrap().unwrap();
+        assert_eq!(plan.affected_rows, None);
+        db.execute_statement(&plan).unwrap();
+        assert!(db.get_tables().unwrap().contains(&"archive".to_string()));
+
+        // A statement that fails is reported before asking, and changes nothing
+        assert!(db.plan_statement("UPDATE users SET missing = 1", &[], "users").is_err());
+        let plan = StatementPlan {
+            sql: "INSERT INTO users (name) VALUES ('Fay'); INSERT INTO nowhere VALUES (1)".to_string(),
+            params: Vec::new(),
+            affected_rows: Some(1),
+        };
+        assert!(db.execute_statement(&plan).is_err());
+        assert_eq!(count("SELECT COUNT(*) FROM users"), 3);
+    }
+
+    #[test]
+    fn test_query_results_are_paged_from_one_run() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("paged.db");
+        let db = Database::open(&path).unwrap();
+        db.conn.execute_batch(
+            "CREATE TABLE t (n INTEGER);
+             WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 100)
+             INSERT INTO t SELECT n FROM seq;",
+        ).unwrap();
+
+        // Pages of a shuffled result come from the same shuffle
+        let query = "SELECT n, n AS n FROM x ORDER BY random()";
+        let mut seen = Vec::new();
+        for offset in (0..100).step_by(30) {
+            let page = db.execute_custom_query(query, &[], "t", offset, 30).unwrap();
+            assert_eq!(page.columns, vec!["n", "n"]);
+            assert_eq!(page.total_rows, 100);
+            seen.extend(page.rows.into_iter().map(|row| match row[0] {
+                Value::Int(n) => n,
+                ref other => panic!("expected an integer, got {:?}", other),
+            }));
+        }
+        seen.sort();
+        assert_eq!(seen, (1..=100).collect::<Vec<_>>());
+
+        // Values keep their type, and SELECT * keeps the rowid
+        let page = db.execute_custom_query("SELECT * FROM x WHERE n = 7", &[], "t", 0, 10).unwrap();
+        assert_eq!(page.columns, vec!["rowid", "n"]);
+        assert_eq!(page.rows, vec![vec!["7".to_string(), "7".to_string()]]);
+
+        // Changes made through the browser are picked up by the next page
+        let plan = db.plan_statement("DELETE FROM t WHERE n > 10", &[], "t").unwrap().unwrap();
+        db.execute_statement(&plan).unwrap();
+        assert_eq!(db.execute_custom_query(query, &[], "t", 0, 30).unwrap().total_rows, 10);
+
+        // Read-only databases store results in the temp schema too
+        drop(db);
+        let db = Database::open_readonly(&path).unwrap();
+        assert_eq!(db.execute_custom_query("SELECT n FROM x", &[], "t", 5, 30).unwrap().rows.len(), 5);
+        assert!(db.execute_custom_query("SELECT nothing FROM x", &[], "t", 0, 30).is_err());
+    }
+
+    #[test]
+    fn test_interrupt_running_query() {
+        let mut db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1);").unwrap();
+        let interrupt = db.interrupt_handle();
+
+        let endless = "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq) SELECT n FROM seq";
+        let result = std::thread::scope(|scope| {
+            let db = &mut db;
+            let worker = scope.spawn(move || db.execute_custom_query(endless, &[], "t", 0, 10));
+            // An interrupt before the statement starts is a no-op, so repeat until it stops
+            while !worker.is_finished() {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                interrupt.interrupt();
+            }
+            worker.join().unwrap()
+        });
+        let error = result.unwrap_err();
+        assert!(error.to_string().contains("interrupt"), "{:#}", error);
+
+        // The connection is usable afterwards, with nothing left half-stored
+        let page = db.execute_custom_query("SELECT n FROM x", &[], "t", 0, 10).unwrap();
+        assert_eq!(page.rows, vec![vec!["1".to_string()]]);
+        db.discard_query_results();
+        let leftovers: i64 = db.conn.query_row("SELECT COUNT(*) FROM sqlite_temp_master", [], |row| row.get(0)).unwrap();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_query_parameters() {
+        use rusqlite::types::Value;
+
+        assert_eq!(
+            query_parameters("SELECT * FROM x WHERE age > :min_age AND name = :name OR age < :min_age"),
+            vec!["min_age", "name"]
+        );
+        // Not in strings, comments or casts
+        assert!(query_parameters("SELECT ':a', CAST(1 AS INT)::text -- :b\nFROM x /* :c */").is_empty());
+
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch(
+            "CREATE TABLE users (name TEXT, age INTEGER, team TEXT);
+             INSERT INTO users VALUES ('Alice', 30, 'a'), ('Bob', 25, NULL), ('Carol', 41, 'a');",
+        ).unwrap();
+        let params = |values: &[(&str, &str)]| -> Vec<(String, String)> {
+            values.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+        };
+
+        // Values are bound, so quotes in them are just text; numbers are bound as numbers
+        let query = "SELECT name FROM x WHERE age >= :min_age OR name = :name ORDER BY name";
+        let injected = params(&[("min_age", "30"), ("name", "x' OR '1'='1")]);
+        let page = db.execute_custom_query(query, &injected, "users", 0, 10).unwrap();
+        assert_eq!(page.rows, vec![vec!["Alice".to_string()], vec!["Carol".to_string()]]);
+        // Other values run the query again rather than reading the stored result
+        let bob = params(&[("min_age", "99"), ("name", "Bob")]);
+        let page = db.execute_custom_query(query, &bob, "users", 0, 10).unwrap();
+        assert_eq!(page.rows, vec![vec!["Bob".to_string()]]);
+
+        // Runs as typed where the parser gives up (`IS :team`), still with the value bound
+        let team = params(&[("team", "NULL")]);
+        let page = db.execute_custom_query("SELECT name FROM users WHERE team IS :team", &team, "users", 0, 10).unwrap();
+        assert_eq!(page.rows, vec![vec!["Bob".to_string()]]);
+
+        let values = parameter_values(&params(&[("a", "'42'"), ("b", "42"), ("c", "1.5"), ("d", "NaN"), ("e", "'it''s'")]));
+        let values: Vec<&Value> = values.iter().map(|(_, v)| v).collect();
+        assert_eq!(
+            values,
+            vec![
+                &Value::Text("42".to_string()),
+                &Value::Integer(42),
+                &Value::Real(1.5),
+                &Value::Text("NaN".to_string()),
+                &Value::Text("it's".to_string()),
+            ]
+        );
+
+        let plan = db
+            .plan_statement("UPDATE x SET team = :team WHERE age < :age", &params(&[("team", "b"), ("age", "35")]), "users")
+            .unwrap()
+            .unwrap();
+        assert_eq!(plan.affected_rows, Some(2));
+        assert_eq!(db.execute_statement(&plan).unwrap(), 2);
+        let teams: i64 = db.conn.query_row("SELECT COUNT(*) FROM users WHERE team = 'b'", [], |row| row.get(0)).unwrap();
+        assert_eq!(teams, 2);
+
+        assert!(db
+            .plan_statement("DELETE FROM users WHERE age = :a; DELETE FROM users", &params(&[("a", "1")]), "users")
+            .is_err());
+    }
+
+    #[test]
+    fn test_sorted_table_data() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute("CREATE TABLE t (name TEXT, age INTEGER)", []).unwrap();
+        db.conn.execute(
+            "INSERT INTO t VALUES ('Alice', 30), ('Bob', 25), ('Carol', 41)",
+            [],
+        ).unwrap();
+
+        let sort = SortOrder { column: "age".to_string(), descending: true };
+        let result = db.get_table_data("t", 0, 10, Some(&sort), &[]).unwrap();
+        let names: Vec<String> = result.rows.iter().map(|r| r[1].to_string()).collect();
+        assert_eq!(names, vec!["Carol", "Alice", "Bob"]);
+    }
+
+    #[test]
+    fn test_regexp_function() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch(
+            "CREATE TABLE t (code TEXT, n INTEGER); \
+             INSERT INTO t VALUES ('A12', 7), ('B3', 120), ('a9', NULL), (NULL, 15);",
+        ).unwrap();
+
+        let count = |condition: &str| -> i64 {
+            db.conn
+                .query_row(&format!("SELECT COUNT(*) FROM t WHERE {}", condition), [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(count("code REGEXP '^[A-Z]\\d+$'"), 2);
+        assert_eq!(count("n REGEXP '^1'"), 2);
+        assert!(db.conn.query_row("SELECT 'x' REGEXP '('", [], |r| r.get::<_, bool>(0)).is_err());
+
+        let filter = ColumnFilter::parse("code", "re:(?i)^a").unwrap();
+        let result = db.get_table_data("t", 0, 10, None, &[filter]).unwrap();
+        assert_eq!(result.total_rows, 2);
+    }
+
+    #[test]
+    fn test_search_all_tables() {
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch(
+            "CREATE TABLE users (name TEXT, age INTEGER);
+             INSERT INTO users VALUES ('Alice Smith', 30), ('Bob', 25), ('100% real', 1);
+             CREATE TABLE notes (body TEXT);
+             INSERT INTO notes VALUES ('call alice tomorrow'), ('nothing here');
+             CREATE VIRTUAL TABLE docs USING fts5(content);
+             INSERT INTO docs VALUES ('Alice in wonderland');",
+        ).unwrap();
+
+        let matches = db.search_all_tables("alice", 100).unwrap();
+        let found: Vec<(&str, i64, &str)> = matches
+            .iter()
+            .map(|m| (m.table.as_str(), m.rowid, m.column.as_str()))
+            .collect();
+        assert_eq!(found, vec![("docs", 1, "content"), ("notes", 1, "body"), ("users", 1, "name")]);
+
+        // LIKE wildcards in the term are matched literally
+        let matches = db.search_all_tables("0%", 100).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].snippet, "100% real");
+
+        assert_eq!(db.row_position("users", 3).unwrap(), 2);
+        assert_eq!(db.search_all_tables("alice", 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_make_snippet() {
+        assert_eq!(make_snippet("Hello World", "world").unwrap(), "Hello World");
+        let long = format!("{}needle{}", "a".repeat(30), "b".repeat(30));
+        assert_eq!(
+            make_snippet(&long, "NEEDLE").unwrap(),
+            format!("...{}needle{}...", "a".repeat(20), "b".repeat(20))
+        );
+        assert!(make_snippet("haystack", "needle").is_none());
+    }
+
     #[test]
     fn test_table_alias_edge_cases() {
         let db = Database::open(":memory:").unwrap();
@@ -312,7 +3045,7 @@ mod tests {
         ];
 
         for (query, should_succeed) in edge_cases {
-            let result = db.execute_custom_query(query, "my_table", 0, 10);
+            let result = db.execute_custom_query(query, &[], "my_table", 0, 10);
             match (result.is_ok(), should_succeed) {
                 (true, true) => println!("✓ Edge case passed: {}", query),
                 (false, false) => println!("✓ Edge case correctly failed: {}", query),
@@ -321,4 +3054,23 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_syntax_error_positions() {
+        let error = syntax_error("SELECT * FRM users", &SQLiteDialect {}).unwrap();
+        assert_eq!(error.message, "Expected: end of statement, found: FRM");
+        assert_eq!(error.position, Some(9));
+        // Positions count characters across lines
+        let error = syntax_error("SELECT naïve\nFROM users WHERE )", &SQLiteDialect {}).unwrap();
+        assert_eq!(error.position, Some(30));
+        assert_eq!(syntax_error("SELECT 'abc", &SQLiteDialect {}).unwrap().position, Some(7));
+        assert_eq!(syntax_error("SELECT name FROM", &SQLiteDialect {}).unwrap().position, Some(16));
+        assert_eq!(syntax_error("SELECT name FROM x WHERE id = :id", &SQLiteDialect {}), None);
+
+        // Syntax only the parser rejects is left for SQLite to run
+        let db = Database::open(":memory:").unwrap();
+        db.conn.execute_batch("CREATE TABLE users (id INTEGER, team TEXT);").unwrap();
+        assert!(db.syntax_error("SELECT * FROM users WHERE team IS :team").is_none());
+        assert!(db.syntax_error("SELECT * FRM users").is_some());
+    }
+}
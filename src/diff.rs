@@ -0,0 +1,253 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::database::QueryResult;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffRow {
+    pub kind: DiffKind,
+    pub values: Vec<String>,             // new values (old values for removed rows)
+    pub old_values: Option<Vec<String>>, // previous values for changed rows
+    pub changed_columns: Vec<usize>,     // indices into DiffResult::columns
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffResult {
+    pub key_column: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<DiffRow>,
+    pub unchanged: usize,
+}
+
+impl DiffResult {
+    pub fn count(&self, kind: DiffKind) -> usize {
+        self.rows.iter().filter(|row| row.kind == kind).count()
+    }
+}
+
+/// Compare two tables row by row, matching rows on `key_column`.
+/// `old` is the baseline (e.g. last month's export) and `new` the table compared against it.
+pub fn diff_tables(old: &QueryResult, new: &QueryResult, key_column: &str) -> Result<DiffResult> {
+    let old_key = old
+        .columns
+        .iter()
+        .position(|c| c == key_column)
+        .ok_or_else(|| anyhow::anyhow!("Key column '{}' not found in the baseline table", key_column))?;
+    let new_key = new
+        .columns
+        .iter()
+        .position(|c| c == key_column)
+        .ok_or_else(|| anyhow::anyhow!("Key column '{}' not found in the compared table", key_column))?;
+
+    // Union of columns: the new table's order first, then columns that were dropped.
+    // SQLite's implicit rowid differs between tables and is never compared.
+    let mut columns: Vec<String> = new.columns.iter().filter(|c| *c != "rowid").cloned().collect();
+    for column in &old.columns {
+        if column != "rowid" && !columns.contains(column) {
+            columns.push(column.clone());
+        }
+    }
+
+    // Cells are compared as shown, so a CSV's "1" matches a database's integer 1
+    let project = |data: &QueryResult, row: &[Value]| -> Vec<String> {
+        columns
+            .iter()
+            .map(|column| {
+                data.columns
+                    .iter()
+                    .position(|c| c == column)
+                    .and_then(|idx| row.get(idx).map(|cell| cell.to_string()))
+                    .unwrap_or_default()
+            })
+            .collect()
+    };
+
+    let old_rows = index_rows(old, old_key);
+    let new_rows = index_rows(new, new_key);
+
+    let old_lookup: HashMap<&(String, usize), &Vec<Value>> = old_rows.iter().map(|(key, row)| (key, *row)).collect();
+
+    let mut rows = Vec::new();
+    let mut unchanged = 0;
+
+    for (key, new_row) in &new_rows {
+        let new_values = project(new, new_row);
+        match old_lookup.get(key) {
+            Some(old_row) => {
+                let old_values = project(old, old_row);
+                let changed_columns: Vec<usize> = (0..columns.len())
+                    .filter(|&i| old_values[i] != new_values[i])
+                    .collect();
+                if changed_columns.is_empty() {
+                    unchanged += 1;
+                } else {
+                    rows.push(DiffRow {
+                        kind: DiffKind::Changed,
+                        values: new_values,
+                        old_values: Some(old_values),
+                        changed_columns,
+                    });
+                }
+            }
+            None => rows.push(DiffRow {
+                kind: DiffKind::Added,
+                values: new_values,
+                old_values: None,
+                changed_columns: Vec::new(),
+            }),
+        }
+    }
+
+    let new_keys: HashSet<&(String, usize)> = new_rows.iter().map(|(key, _)| key).collect();
+    for (key, old_row) in &old_rows {
+        if !new_keys.contains(key) {
+            rows.push(DiffRow {
+                kind: DiffKind::Removed,
+                values: project(old, old_row),
+                old_values: None,
+                changed_columns: Vec::new(),
+            });
+        }
+    }
+
+    Ok(DiffResult {
+        key_column: key_column.to_string(),
+        columns,
+        rows,
+        unchanged,
+    })
+}
+
+/// Write the differing rows to a CSV file, after a `change` column saying what happened to
+/// each: `added`, `removed`, or for changed rows a `changed from` row with the old values
+/// followed by a `changed to` row with the new ones
+pub fn write_diff_csv(diff: &DiffResult, path: &Path) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(std::iter::once("change").chain(diff.columns.iter().map(|c| c.as_str())))?;
+    for row in &diff.rows {
+        let mut write = |change: &str, values: &[String]| {
+            writer.write_record(std::iter::once(change).chain(values.iter().map(|v| v.as_str())))
+        };
+        match (row.kind, &row.old_values) {
+            (DiffKind::Added, _) => write("added", &row.values)?,
+            (DiffKind::Removed, _) => write("removed", &row.values)?,
+            (DiffKind::Changed, old_values) => {
+                write("changed from", old_values.as_deref().unwrap_or_default())?;
+                write("changed to", &row.values)?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Rows are keyed by their key and its occurrence, so duplicate keys match up in order and
+// every row gets compared
+fn index_rows(data: &QueryResult, key_idx: usize) -> Vec<((String, usize), &Vec<Value>)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    data.rows
+        .iter()
+        .map(|row| {
+            let key = row.get(key_idx).map(|cell| cell.to_string()).unwrap_or_default();
+            let occurrence = seen.entry(key.clone()).or_insert(0);
+            *occurrence += 1;
+            ((key, *occurrence), row)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(columns: &[&str], rows: &[&[&str]]) -> QueryResult {
+        QueryResult {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|r| r.iter().map(|v| Value::from(*v)).collect())
+                .collect(),
+            total_rows: rows.len(),
+        }
+    }
+
+    #[test]
+    fn test_diff_tables() {
+        let old = table(
+            &["id", "name", "amount"],
+            &[&["1", "Alice", "10"], &["2", "Bob", "20"], &["3", "Carol", "30"]],
+        );
+        let new = table(
+            &["id", "name", "amount"],
+            &[&["1", "Alice", "10"], &["2", "Bob", "25"], &["4", "Dave", "40"]],
+        );
+
+        let diff = diff_tables(&old, &new, "id").unwrap();
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.count(DiffKind::Added), 1);
+        assert_eq!(diff.count(DiffKind::Removed), 1);
+        assert_eq!(diff.count(DiffKind::Changed), 1);
+
+        let changed = diff.rows.iter().find(|r| r.kind == DiffKind::Changed).unwrap();
+        assert_eq!(changed.values[0], "2");
+        assert_eq!(changed.changed_columns, vec![2]);
+        assert_eq!(changed.old_values.as_ref().unwrap()[2], "20");
+
+        let removed = diff.rows.iter().find(|r| r.kind == DiffKind::Removed).unwrap();
+        assert_eq!(removed.values, vec!["3", "Carol", "30"]);
+    }
+
+    #[test]
+    fn test_diff_tables_column_changes() {
+        let old = table(&["rowid", "id", "legacy"], &[&["1", "a", "x"]]);
+        let new = table(&["rowid", "id", "extra"], &[&["7", "a", "y"]]);
+
+        let diff = diff_tables(&old, &new, "id").unwrap();
+        assert_eq!(diff.columns, vec!["id", "extra", "legacy"]);
+        assert_eq!(diff.rows[0].changed_columns, vec![1, 2]);
+        assert!(diff_tables(&old, &new, "missing").is_err());
+    }
+
+    #[test]
+    fn test_diff_tables_duplicate_keys() {
+        // A second "a" must not be taken for the key "a #2"
+        let old = table(&["id", "amount"], &[&["a", "1"], &["a", "2"], &["a #2", "3"]]);
+        let new = table(&["id", "amount"], &[&["a", "1"], &["a", "5"], &["a #2", "3"]]);
+
+        let diff = diff_tables(&old, &new, "id").unwrap();
+        assert_eq!(diff.unchanged, 2);
+        assert_eq!(diff.rows.len(), 1);
+        assert_eq!(diff.rows[0].kind, DiffKind::Changed);
+        assert_eq!(diff.rows[0].values, vec!["a", "5"]);
+        assert_eq!(diff.rows[0].old_values.as_ref().unwrap(), &vec!["a", "2"]);
+
+        let new = table(&["id", "amount"], &[&["a", "1"], &["a #2", "3"]]);
+        let diff = diff_tables(&old, &new, "id").unwrap();
+        assert_eq!((diff.unchanged, diff.count(DiffKind::Removed)), (2, 1));
+        assert_eq!(diff.rows[0].values, vec!["a", "2"]);
+    }
+
+    #[test]
+    fn test_write_diff_csv() {
+        let old = table(&["id", "amount"], &[&["1", "10"], &["2", "20"]]);
+        let new = table(&["id", "amount"], &[&["1", "15"], &["3", "30"]]);
+        let diff = diff_tables(&old, &new, "id").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("diff.csv");
+        write_diff_csv(&diff, &path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "change,id,amount\nchanged from,1,10\nchanged to,1,15\nadded,3,30\nremoved,2,20\n"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::file_reader::{detect_file_type, FileType};
+use crate::html_table::is_web_url;
+
+/// Validators of a cached download, sent back so an unchanged file isn't fetched again
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// The file name at the end of a URL's path, without query string or fragment
+pub fn url_file_name(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let after_scheme = path.split_once("://").map_or(path, |(_, rest)| rest);
+    let (_, path) = after_scheme.split_once('/')?;
+    let name = path.rsplit('/').next()?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Whether a URL names a data file (`.../data.parquet`) to download and open with the usual
+/// readers, rather than a web page whose tables are read in place
+pub fn downloads_data_file(path: &Path) -> bool {
+    if !is_web_url(path) {
+        return false;
+    }
+    let Some(name) = url_file_name(&path.to_string_lossy()) else {
+        return false;
+    };
+    Path::new(&name).extension().is_some()
+        && !matches!(detect_file_type(&name), Ok(FileType::Html) | Err(_))
+}
+
+/// "1.5 MB", for download progress
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Files downloaded from URLs, one directory per URL holding the file and its validators
+pub struct DownloadCache {
+    dir: PathBuf,
+}
+
+impl DownloadCache {
+    pub fn new() -> Result<Self> {
+        let home_dir = std::env::var("HOME").context("HOME environment variable not set")?;
+        Ok(Self::at(PathBuf::from(home_dir).join(".cache").join("sqbrowser").join("downloads")))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Download `url` into the cache, reporting bytes received and the total when known.
+    /// A cached copy is revalidated with its ETag or Last-Modified date and reused when the
+    /// server answers 304 Not Modified, or when the server can't be reached at all.
+    pub fn fetch(
+        &self,
+        url: &str,
+        mut progress: impl FnMut(u64, Option<u64>) -> Result<()>,
+    ) -> Result<PathBuf> {
+        let entry_dir = self.dir.join(safe_name(url));
+        let file_name = url_file_name(url).unwrap_or_else(|| "download".to_string());
+        let file = entry_dir.join(&file_name);
+        let meta_file = entry_dir.join("entry.json");
+        let cached: Option<CacheEntry> = file
+            .exists()
+            .then(|| fs::read_to_string(&meta_file).ok())
+            .flatten()
+            .and_then(|content| serde_json::from_str(&content).ok());
+
+        let agent = ureq::AgentBuilder::new().timeout_connect(Duration::from_secs(30)).build();
+        let mut request = agent.get(url);
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.set("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.set("If-Modified-Since", last_modified);
+            }
+        }
+
+        let response = match request.call() {
+            Ok(response) if response.status() == 304 && cached.is_some() => {
+                tracing::debug!(url, "cached download is current");
+                return Ok(file);
+            }
+            Ok(response) => response,
+            Err(ureq::Error::Transport(e)) if cached.is_some() => {
+                tracing::warn!(url, error = %e, "server unreachable, using cached download");
+                return Ok(file);
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                return Err(anyhow::anyhow!("Download of {} failed: server returned {}", url, status));
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to download {}", url)),
+        };
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            etag: response.header("ETag").map(str::to_string),
+            last_modified: response.header("Last-Modified").map(str::to_string),
+        };
+        let total = response.header("Content-Length").and_then(|len| len.parse().ok());
+
+        fs::create_dir_all(&entry_dir).context("Failed to create download cache directory")?;
+        // Written aside and renamed, so an interrupted download never passes for a cached one
+        let partial = entry_dir.join(format!("{}.part", file_name));
+        let mut output = fs::File::create(&partial).context("Failed to create download file")?;
+        let mut reader = response.into_reader();
+        let mut copy = || -> Result<u64> {
+            let mut buffer = vec![0; 64 * 1024];
+            let mut received = 0;
+            progress(received, total)?;
+            loop {
+                let read = reader
+                    .read(&mut buffer)
+                    .with_context(|| format!("Download of {} was interrupted", url))?;
+                if read == 0 {
+                    break;
+                }
+                output.write_all(&buffer[..read])?;
+                received += read as u64;
+                progress(received, total)?;
+            }
+            output.flush()?;
+            Ok(received)
+        };
+        let received = match copy() {
+            Ok(received) => received,
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(e);
+            }
+        };
+        fs::rename(&partial, &file).context("Failed to store download")?;
+        fs::write(&meta_file, serde_json::to_string_pretty(&entry)?)
+            .context("Failed to write download cache entry")?;
+        tracing::info!(url, bytes = received, "downloaded");
+        Ok(file)
+    }
+}
+
+fn safe_name(url: &str) -> String {
+    url.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|', '#', '&', '='], "_")
+        .replace(' ', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Serve `body` with an ETag, answering 304 to requests that already have it; returns
+    /// the base URL and the status codes sent
+    fn serve(body: &'static str, requests: usize) -> (String, Arc<Mutex<Vec<u16>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let sent = statuses.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut revalidated = false;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    revalidated |= line.to_ascii_lowercase().starts_with("if-none-match: \"v1\"");
+                }
+                let response = if revalidated {
+                    sent.lock().unwrap().push(304);
+                    "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    sent.lock().unwrap().push(200);
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        (url, statuses)
+    }
+
+    #[test]
+    fn test_fetch_caches_by_etag() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::at(dir.path().to_path_buf());
+        let (base, statuses) = serve("id,name\n1,Alice\n", 2);
+        let url = format!("{}/exports/people.csv?day=1", base);
+
+        let mut reported = Vec::new();
+        let file = cache
+            .fetch(&url, |received, total| {
+                reported.push((received, total));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(file.file_name().unwrap(), "people.csv");
+        assert_eq!(fs::read_to_string(&file).unwrap(), "id,name\n1,Alice\n");
+        assert_eq!(reported.last(), Some(&(16, Some(16))));
+
+        // The second open only revalidates
+        let mut called = false;
+        let again = cache
+            .fetch(&url, |_, _| {
+                called = true;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(again, file);
+        assert!(!called);
+        assert_eq!(*statuses.lock().unwrap(), vec![200, 304]);
+    }
+
+    #[test]
+    fn test_cancelled_download_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::at(dir.path().to_path_buf());
+        let (base, _) = serve("a,b\n1,2\n", 1);
+        let url = format!("{}/data.csv", base);
+
+        let result = cache.fetch(&url, |_, _| Err(anyhow::anyhow!("Download cancelled")));
+        assert!(result.is_err());
+        let entry_dir = dir.path().join(safe_name(&url));
+        assert!(!entry_dir.join("data.csv").exists());
+        assert!(!entry_dir.join("data.csv.part").exists());
+    }
+
+    #[test]
+    fn test_data_file_urls() {
+        assert_eq!(url_file_name("https://example.com/a/data.parquet?x=1").as_deref(), Some("data.parquet"));
+        assert_eq!(url_file_name("https://example.com/"), None);
+        assert_eq!(url_file_name("https://example.com"), None);
+
+        assert!(downloads_data_file(Path::new("https://example.com/data.parquet")));
+        assert!(downloads_data_file(Path::new("https://example.com/data.csv.gz")));
+        assert!(!downloads_data_file(Path::new("https://example.com/report.html")));
+        assert!(!downloads_data_file(Path::new("https://example.com/wiki/Page")));
+        assert!(!downloads_data_file(Path::new("data.parquet")));
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536 * 1024), "1.5 MB");
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use duckdb::{AccessMode, Config, Connection};
+use std::path::Path;
+
+use crate::database::{quote_identifier, QueryResult};
+use crate::filter::{where_clause, ColumnFilter, SqlFlavor};
+use crate::persistence::SortOrder;
+use crate::value::Value;
+
+/// A DuckDB database file, opened read-only
+pub struct DuckDatabase {
+    conn: Connection,
+}
+
+impl DuckDatabase {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let config = Config::default().access_mode(AccessMode::ReadOnly)?;
+        let conn = Connection::open_with_flags(path, config)
+            .with_context(|| format!("Failed to open DuckDB database {}", path.display()))?;
+        Ok(Self { conn })
+    }
+
+    /// Tables and views of the main schema
+    pub fn get_tables(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT table_name FROM information_schema.tables \
+             WHERE table_schema = 'main' ORDER BY table_name",
+        )?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(tables)
+    }
+
+    pub fn get_table_data(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+    ) -> Result<QueryResult> {
+        let table = quote_identifier(table_name);
+        let where_clause = where_clause(filters, SqlFlavor::DuckDb);
+        let order_by = match sort {
+            Some(sort) => format!(
+                " ORDER BY {} {}",
+                quote_identifier(&sort.column),
+                if sort.descending { "DESC" } else { "ASC" }
+            ),
+            None => String::new(),
+        };
+
+        let query = format!(
+            "SELECT * FROM {}{}{} LIMIT {} OFFSET {}",
+            table, where_clause, order_by, limit, offset
+        );
+        let mut result = self.execute_query(&query)?;
+
+        let count_query = format!("SELECT COUNT(*) FROM {}{}", table, where_clause);
+        let total_rows: i64 = self.conn.query_row(&count_query, [], |row| row.get(0))?;
+        result.total_rows = total_rows as usize;
+        Ok(result)
+    }
+
+    /// Run a query whose table alias has already been replaced, one page at a time
+    pub fn execute_custom_query(&self, query: &str, offset: usize, limit: usize) -> Result<QueryResult> {
+        let query = query.trim().trim_end_matches(';');
+        let mut result = self.execute_query(&format!("{} LIMIT {} OFFSET {}", query, limit, offset))?;
+
+        let count_query = format!("SELECT COUNT(*) FROM ({})", query);
+        if let Ok(count) = self.conn.query_row(&count_query, [], |row| row.get::<_, i64>(0)) {
+            result.total_rows = count as usize;
+        }
+        Ok(result)
+    }
+
+    /// Every value is cast to VARCHAR by DuckDB itself, so dates, decimals, lists and
+    /// structs read the way DuckDB prints them; their types are inferred like a CSV's
+    fn execute_query(&self, query: &str) -> Result<QueryResult> {
+        let cast_query = format!("SELECT COLUMNS(*)::VARCHAR FROM ({}) AS q", query);
+        let mut stmt = self.conn.prepare(&cast_query)?;
+        let mut rows = stmt.query([])?;
+        let columns: Vec<String> = rows
+            .as_ref()
+            .map(|stmt| stmt.column_names())
+            .unwrap_or_default();
+
+        let mut result_rows = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut values = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                let value: Option<String> = row.get(i)?;
+                values.push(value.map_or(Value::Null, Value::Text));
+            }
+            result_rows.push(values);
+        }
+
+        Ok(QueryResult {
+            columns,
+            total_rows: result_rows.len(),
+            rows: result_rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::SortOrder;
+
+    fn sample_database(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("sample.duckdb");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE people (name VARCHAR, age INTEGER, joined DATE);
+             INSERT INTO people VALUES ('Alice', 30, DATE '2024-01-15'), ('Bob', 25, NULL), ('Carol', 41, DATE '2023-06-01');
+             CREATE VIEW adults AS SELECT name FROM people WHERE age > 26;",
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_duckdb_tables_and_pages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = DuckDatabase::open(sample_database(temp_dir.path())).unwrap();
+        assert_eq!(db.get_tables().unwrap(), vec!["adults", "people"]);
+
+        let sort = SortOrder { column: "age".to_string(), descending: true };
+        let page = db.get_table_data("people", 1, 1, Some(&sort), &[]).unwrap();
+        assert_eq!(page.columns, vec!["name", "age", "joined"]);
+        assert_eq!(page.rows, vec![vec!["Alice", "30", "2024-01-15"]]);
+        assert_eq!(page.total_rows, 3);
+
+        let filter = ColumnFilter::parse("joined", "=NULL").unwrap();
+        let filtered = db.get_table_data("people", 0, 10, None, &[filter]).unwrap();
+        assert_eq!(filtered.rows, vec![vec!["Bob", "25", "NULL"]]);
+        assert_eq!(filtered.total_rows, 1);
+
+        let query = db.execute_custom_query("SELECT name FROM people ORDER BY name;", 0, 2).unwrap();
+        assert_eq!(query.rows, vec![vec!["Alice"], vec!["Bob"]]);
+        assert_eq!(query.total_rows, 3);
+    }
+}
@@ -0,0 +1,414 @@
+use anyhow::{Context, Result};
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray};
+use arrow::datatypes::{Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use rusqlite::types::Value;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::NumberLocale;
+use crate::database::{cell_sql_value, QueryResult};
+use crate::file_reader::{infer_column_types, ColumnType};
+use crate::value::Value as Cell;
+
+/// File formats the full result of a query can be written to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+    Json,
+    JsonLines, // an object per line
+    Markdown,  // a GitHub-flavored table
+    Html,      // a minimal table
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Csv,
+        ExportFormat::Parquet,
+        ExportFormat::Json,
+        ExportFormat::JsonLines,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Parquet => "Parquet",
+            ExportFormat::Json => "JSON",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Html => "HTML",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Json => "json",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    pub fn is_json(self) -> bool {
+        matches!(self, ExportFormat::Json | ExportFormat::JsonLines)
+    }
+}
+
+/// Rows of the view the export popup writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportScope {
+    All,      // every row, filtered and sorted as shown
+    Page,     // the page on screen
+    Selected, // the selected rows
+}
+
+impl ExportScope {
+    pub fn next(self) -> Self {
+        match self {
+            ExportScope::All => ExportScope::Page,
+            ExportScope::Page => ExportScope::Selected,
+            ExportScope::Selected => ExportScope::All,
+        }
+    }
+}
+
+/// Write `data` to `path`. CSV, Markdown and HTML keep the values as shown; Parquet stores
+/// numbers and booleans typed as inferred from the values, with NULL as null. JSON does too
+/// when `typed`, and otherwise writes every value as the text shown.
+pub fn write_results(
+    data: &QueryResult,
+    path: &Path,
+    format: ExportFormat,
+    typed: bool,
+    locale: NumberLocale,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(&data.columns)?;
+            for row in &data.rows {
+                writer.write_record(row.iter().map(|cell| cell.to_string()))?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Parquet => write_parquet(data, path, locale)?,
+        ExportFormat::Markdown | ExportFormat::Html => {
+            let rows: Vec<Vec<String>> =
+                data.rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect();
+            let table = match format {
+                ExportFormat::Markdown => markdown_table(&data.columns, &rows),
+                _ => html_table(&data.columns, &rows),
+            };
+            std::fs::write(path, table).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        ExportFormat::Json | ExportFormat::JsonLines => {
+            let types = infer_column_types(data, locale);
+            let rows = data.rows.iter().map(|row| JsonRow {
+                columns: &data.columns,
+                values: types
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, ty)| {
+                        let cell = row.get(idx).unwrap_or(&Cell::Null);
+                        if typed {
+                            json_value(cell, *ty, locale)
+                        } else {
+                            serde_json::Value::String(cell.to_string())
+                        }
+                    })
+                    .collect(),
+            });
+            let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+            let mut writer = BufWriter::new(file);
+            if format == ExportFormat::JsonLines {
+                for row in rows {
+                    serde_json::to_writer(&mut writer, &row)?;
+                    writer.write_all(b"\n")?;
+                }
+            } else {
+                serde_json::to_writer_pretty(&mut writer, &rows.collect::<Vec<_>>())?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+// An object with the columns in query order; serde_json's own maps sort their keys
+struct JsonRow<'a> {
+    columns: &'a [String],
+    values: Vec<serde_json::Value>,
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for (column, value) in self.columns.iter().zip(&self.values) {
+            map.serialize_entry(column, value)?;
+        }
+        map.end()
+    }
+}
+
+fn json_value(cell: &Cell, column_type: ColumnType, locale: NumberLocale) -> serde_json::Value {
+    if let (ColumnType::Text, Cell::Text(text)) = (column_type, cell) {
+        return match text.as_str() {
+            "NULL" => serde_json::Value::Null,
+            _ => serde_json::Value::String(text.clone()),
+        };
+    }
+    match (cell_sql_value(cell, column_type, locale), column_type) {
+        (Value::Null, _) => serde_json::Value::Null,
+        (Value::Integer(i), ColumnType::Bool) => serde_json::Value::Bool(i != 0),
+        (Value::Integer(i), _) => i.into(),
+        (Value::Real(f), _) => serde_json::Number::from_f64(f).map_or(serde_json::Value::Null, Into::into),
+        (Value::Text(s), _) => serde_json::Value::String(s),
+        (Value::Blob(_), _) => serde_json::Value::String(cell.to_string()),
+    }
+}
+
+/// A GitHub-flavored Markdown table. Pipes are escaped and line breaks become `<br>`, so every
+/// row stays on one line.
+pub fn markdown_table(columns: &[String], rows: &[Vec<String>]) -> String {
+    let escape = |text: &str| text.replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>");
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut table = line(columns.iter().map(|c| escape(c)).collect());
+    table.push_str(&line(columns.iter().map(|_| "---".to_string()).collect()));
+    for row in rows {
+        table.push_str(&line(row.iter().map(|cell| escape(cell)).collect()));
+    }
+    table
+}
+
+/// A minimal HTML table, without styling, for pasting into reports
+pub fn html_table(columns: &[String], rows: &[Vec<String>]) -> String {
+    let mut table = String::from("<table>\n  <thead>\n    <tr>");
+    for column in columns {
+        table.push_str(&format!("<th>{}</th>", html_escape(column)));
+    }
+    table.push_str("</tr>\n  </thead>\n  <tbody>\n");
+    for row in rows {
+        table.push_str("    <tr>");
+        for cell in row {
+            table.push_str(&format!("<td>{}</td>", html_escape(cell)));
+        }
+        table.push_str("</tr>\n");
+    }
+    table.push_str("  </tbody>\n</table>\n");
+    table
+}
+
+/// Tab-separated text as spreadsheets paste it: a line per row, with cells holding tabs,
+/// line breaks or quotes quoted and their quotes doubled
+pub fn tsv(rows: &[Vec<String>]) -> String {
+    let cell = |text: &String| {
+        if text.contains(['\t', '\n', '\r', '"']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.clone()
+        }
+    };
+    rows.iter()
+        .map(|row| row.iter().map(cell).collect::<Vec<_>>().join("\t"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rows of tab-separated text as spreadsheets copy it, the reverse of `tsv`: quoted cells
+/// may hold tabs, line breaks and doubled quotes. A trailing line break adds no row.
+pub fn parse_tsv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut chars = text.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.is_empty() => quoted = true,
+            '\t' if !quoted => row.push(std::mem::take(&mut cell)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    rows
+}
+
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// A column whose values don't all fit its inferred type (rows past the inference sample)
+// is written as text
+fn write_parquet(data: &QueryResult, path: &Path, locale: NumberLocale) -> Result<()> {
+    let types = infer_column_types(data, locale);
+    let mut fields = Vec::new();
+    let mut arrays: Vec<ArrayRef> = Vec::new();
+    for (idx, (column, ty)) in data.columns.iter().zip(&types).enumerate() {
+        let cells: Vec<&Cell> = data.rows.iter().map(|row| row.get(idx).unwrap_or(&Cell::Null)).collect();
+        let values: Vec<Value> = cells.iter().map(|cell| cell_sql_value(cell, *ty, locale)).collect();
+        let integers = || values.iter().all(|v| matches!(v, Value::Null | Value::Integer(_)));
+
+        let array: ArrayRef = match ty {
+            ColumnType::Int if integers() => Arc::new(Int64Array::from_iter(
+                values.iter().map(|v| if let Value::Integer(i) = v { Some(*i) } else { None }),
+            )),
+            ColumnType::Bool if integers() => Arc::new(BooleanArray::from_iter(
+                values.iter().map(|v| if let Value::Integer(i) = v { Some(*i != 0) } else { None }),
+            )),
+            ColumnType::Float if values.iter().all(|v| matches!(v, Value::Null | Value::Integer(_) | Value::Real(_))) => {
+                Arc::new(Float64Array::from_iter(values.iter().map(|v| match v {
+                    Value::Integer(i) => Some(*i as f64),
+                    Value::Real(f) => Some(*f),
+                    _ => None,
+                })))
+            }
+            _ => Arc::new(StringArray::from_iter(
+                cells.iter().map(|cell| match cell {
+                    Cell::Null => None,
+                    Cell::Text(text) if text == "NULL" => None,
+                    cell => Some(cell.to_string()),
+                }),
+            )),
+        };
+        fields.push(Field::new(column, array.data_type().clone(), true));
+        arrays.push(array);
+    }
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParquetSettings;
+    use crate::file_reader::read_parquet_file;
+    use crate::value::text_rows;
+
+    fn sample() -> QueryResult {
+        let rows = text_rows(vec![vec!["1", "Ann", "2.5", "true"], vec!["2", "NULL", "NULL", "false"]]);
+        QueryResult {
+            columns: vec!["id".to_string(), "name".to_string(), "score".to_string(), "active".to_string()],
+            total_rows: rows.len(),
+            rows,
+        }
+    }
+
+    #[test]
+    fn test_write_json_is_typed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.json");
+        write_results(&sample(), &path, ExportFormat::Json, true, NumberLocale::En).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.find("\"id\"").unwrap() < text.find("\"active\"").unwrap());
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"id": 1, "name": "Ann", "score": 2.5, "active": true},
+                {"id": 2, "name": null, "score": null, "active": false}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_write_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        write_results(&sample(), &path, ExportFormat::JsonLines, true, NumberLocale::En).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], r#"{"id":1,"name":"Ann","score":2.5,"active":true}"#);
+
+        write_results(&sample(), &path, ExportFormat::JsonLines, false, NumberLocale::En).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().nth(1), Some(r#"{"id":"2","name":"NULL","score":"NULL","active":"false"}"#));
+    }
+
+    #[test]
+    fn test_markdown_and_html_tables() {
+        let columns = vec!["name".to_string(), "note".to_string()];
+        let rows = vec![vec!["a|b".to_string(), "x\ny".to_string()], vec!["<c>".to_string(), "&".to_string()]];
+        assert_eq!(
+            markdown_table(&columns, &rows),
+            "| name | note |\n| --- | --- |\n| a\\|b | x<br>y |\n| <c> | & |\n"
+        );
+        let html = html_table(&columns, &rows);
+        assert!(html.contains("<tr><th>name</th><th>note</th></tr>"));
+        assert!(html.contains("<tr><td>&lt;c&gt;</td><td>&amp;</td></tr>"));
+    }
+
+    #[test]
+    fn test_tsv() {
+        let rows = vec![
+            vec!["name".to_string(), "note".to_string()],
+            vec!["Ann".to_string(), "a\tb".to_string()],
+            vec!["say \"hi\"".to_string(), "x\ny".to_string()],
+        ];
+        assert_eq!(tsv(&rows), "name\tnote\nAnn\t\"a\tb\"\n\"say \"\"hi\"\"\"\t\"x\ny\"");
+        assert_eq!(parse_tsv(&tsv(&rows)), rows);
+    }
+
+    #[test]
+    fn test_parse_tsv() {
+        assert_eq!(parse_tsv("1\t2\r\n3\t\r\n"), vec![vec!["1", "2"], vec!["3", ""]]);
+        assert_eq!(parse_tsv("single"), vec![vec!["single"]]);
+        assert!(parse_tsv("").is_empty());
+    }
+
+    #[test]
+    fn test_write_markdown_and_html() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.md");
+        write_results(&sample(), &path, ExportFormat::Markdown, true, NumberLocale::En).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().nth(2), Some("| 1 | Ann | 2.5 | true |"));
+
+        let path = dir.path().join("results.html");
+        write_results(&sample(), &path, ExportFormat::Html, true, NumberLocale::En).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("<table>"));
+        assert!(text.contains("<td>2</td><td>NULL</td>"));
+    }
+
+    #[test]
+    fn test_write_parquet_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.parquet");
+        write_results(&sample(), &path, ExportFormat::Parquet, true, NumberLocale::En).unwrap();
+
+        let data = read_parquet_file(&path, &ParquetSettings::default()).unwrap();
+        assert_eq!(data.columns, sample().columns);
+        assert_eq!(data.rows.len(), 2);
+        assert_eq!(data.rows[0][0], "1");
+        assert_eq!(data.rows[0][2], "2.5");
+    }
+}
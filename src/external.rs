@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, ExitStatus, Stdio};
+
+use crate::value::Value;
+
+/// A shell command waiting to be run by the main loop, which suspends the TUI around it
+#[derive(Debug, Clone)]
+pub struct PipeRequest {
+    pub command: String,
+    pub input: String, // CSV sent to the command's stdin
+    pub label: String, // what was piped, shown above the output
+}
+
+/// An external tool to launch with the terminal handed over to it
+#[derive(Debug, Clone)]
+pub struct ToolLaunch {
+    pub name: String,
+    pub command: String, // placeholders already expanded
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub status: Option<i32>, // None when the process was killed by a signal
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+/// Build a command run through the platform shell, so pipes and quoting work as typed
+pub fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Run `command` with `input` on its stdin and capture what it prints
+pub fn run_piped(command: &str, input: &str) -> Result<CommandOutput> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start '{}'", command))?;
+
+    // Write from a separate thread so a command that prints before reading all of
+    // its input cannot deadlock against us
+    let mut stdin = child.stdin.take().context("Failed to open the command's stdin")?;
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || {
+        // A command that exits without reading everything (e.g. `head`) closes the pipe early
+        let _ = stdin.write_all(input.as_bytes());
+    });
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    let _ = writer.join();
+
+    Ok(CommandOutput {
+        status: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    })
+}
+
+/// Run `command` attached to the terminal (for interactive tools like `sqlite3` or an editor)
+pub fn run_interactive(command: &str) -> Result<ExitStatus> {
+    shell_command(command)
+        .status()
+        .with_context(|| format!("Failed to start '{}'", command))
+}
+
+/// Replace `{file}` and `{table}` in a tool's command line with shell-quoted values
+pub fn expand_tool_command(template: &str, file: &str, table: &str) -> String {
+    template
+        .replace("{file}", &shell_quote(file))
+        .replace("{table}", &shell_quote(table))
+}
+
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Serialize rows as CSV with a header line
+pub fn to_csv(columns: &[String], rows: &[Vec<Value>]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns)?;
+    for row in rows {
+        writer.write_record(row.iter().map(|cell| cell.to_string()))?;
+    }
+    let bytes = writer.into_inner().map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_csv() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let rows = vec![
+            vec![Value::Int(1), Value::from("Smith, John")],
+            vec![Value::Int(2), Value::from("Ann")],
+        ];
+        assert_eq!(
+            to_csv(&columns, &rows).unwrap(),
+            "id,name\n1,\"Smith, John\"\n2,Ann\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_expand_tool_command() {
+        assert_eq!(
+            expand_tool_command("sqlite3 {file} .schema {table}", "my db.sqlite", "users"),
+            "sqlite3 'my db.sqlite' .schema 'users'"
+        );
+        assert_eq!(expand_tool_command("vd {file}", "it's.csv", ""), "vd 'it'\\''s.csv'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_piped() {
+        let output = run_piped("tail -n +2 | wc -l", "a\n1\n2\n").unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout.trim(), "2");
+
+        let failed = run_piped("echo oops >&2; exit 3", "").unwrap();
+        assert_eq!(failed.status, Some(3));
+        assert_eq!(failed.stderr.trim(), "oops");
+    }
+}
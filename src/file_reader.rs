@@ -1,12 +1,32 @@
-use anyhow::Result;
-use calamine::{open_workbook, Data, Reader, Xlsx};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use calamine::{open_workbook_auto, open_workbook_auto_from_rs, Data, Reader, Sheets};
 use csv::ReaderBuilder;
-use std::path::Path;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::fs::File;
-use parquet::file::reader::{FileReader, SerializedFileReader};
-use parquet::record::RowAccessor;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use bytes::Bytes;
+use flate2::read::MultiGzDecoder;
+use arrow::array::{make_array, Array, ArrayRef};
+use arrow::datatypes::DataType;
+use arrow::error::ArrowError;
+use arrow::ipc::reader::{FileReader, StreamReader};
+use arrow::record_batch::{RecordBatch, RecordBatchReader};
+use arrow::util::display::array_value_to_string;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 
+use crate::config::{CsvSettings, NumberLocale, ParquetSettings, XlsxSettings};
 use crate::database::QueryResult;
+use crate::format::{parse_integer, parse_number};
+use crate::persistence::SortOrder;
+use crate::html_table::is_web_url;
+use crate::libsql_database::is_libsql_url;
+use crate::postgres_database::is_connection_string;
+use crate::statistical::StatFormat;
+use crate::value::Value;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FileType {
@@ -14,21 +34,178 @@ pub enum FileType {
     Csv,
     Xlsx,
     Parquet,
+    Json,
+    Arrow,
+    Dbf,
+    Statistical(StatFormat),
+    Html,
+    DuckDb,
+    Postgres,
+    Libsql,
 }
 
-pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<FileType> {
+/// Compression wrapped around a data file, named by its last extension (`data.csv.gz`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+pub fn detect_compression<P: AsRef<Path>>(path: P) -> Option<Compression> {
+    match lowercase_extension(path.as_ref()).as_str() {
+        "gz" | "gzip" => Some(Compression::Gzip),
+        "zst" | "zstd" => Some(Compression::Zstd),
+        _ => None,
+    }
+}
+
+/// The path without its compression extension (`data.csv.gz` -> `data.csv`)
+pub fn decompressed_path<P: AsRef<Path>>(path: P) -> PathBuf {
     let path = path.as_ref();
-    let extension = path
-        .extension()
+    match detect_compression(path) {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Where the previous version of a file overwritten by a save is kept (`data.csv` -> `data.csv.bak`)
+pub fn backup_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut backup = path.as_ref().as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Where edits to a file that isn't plain CSV are saved (`data.parquet.gz` -> `data.csv`)
+pub fn converted_csv_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    decompressed_path(path).with_extension("csv")
+}
+
+pub fn lowercase_extension(path: &Path) -> String {
+    path.extension()
         .and_then(|ext| ext.to_str())
         .unwrap_or("")
-        .to_lowercase();
+        .to_lowercase()
+}
+
+/// How far a file being opened on a worker thread has been read, and whether the user gave
+/// up waiting for it
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    read: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    /// Bytes of the file read so far, before decompression
+    pub fn bytes_read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    /// Stop reading at the next chunk; the open then fails
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn check(&self) -> std::io::Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(std::io::Error::other("loading was cancelled"));
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static LOAD_PROGRESS: RefCell<Option<Arc<LoadProgress>>> = const { RefCell::new(None) };
+}
+
+/// Report reads of the files opened on this thread to `progress`
+pub fn track_load_progress(progress: Arc<LoadProgress>) {
+    LOAD_PROGRESS.with(|tracked| *tracked.borrow_mut() = Some(progress));
+}
+
+fn load_progress() -> Option<Arc<LoadProgress>> {
+    LOAD_PROGRESS.with(|tracked| tracked.borrow().clone())
+}
+
+/// Fail once loading is cancelled, between batches of formats not read as a stream
+fn check_cancelled() -> Result<()> {
+    match load_progress() {
+        Some(progress) => Ok(progress.check()?),
+        None => Ok(()),
+    }
+}
+
+struct ProgressReader<R> {
+    inner: R,
+    progress: Arc<LoadProgress>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.progress.check()?;
+        let read = self.inner.read(buf)?;
+        self.progress.read.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Open a file for reading, decompressing it on the fly when it is compressed
+pub fn open_input<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read>> {
+    let path = path.as_ref();
+    let file: Box<dyn Read> = match load_progress() {
+        Some(progress) => Box::new(ProgressReader { inner: File::open(path)?, progress }),
+        None => Box::new(File::open(path)?),
+    };
+    let file = BufReader::new(file);
+    Ok(match detect_compression(path) {
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(file)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::with_buffer(file)?),
+        None => Box::new(file),
+    })
+}
+
+/// The whole decompressed file, for formats that need to seek (Parquet, Arrow, workbooks)
+pub fn read_input_bytes(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_input(path)?
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to decompress {}", path.display()))?;
+    Ok(bytes)
+}
+
+/// File type from the extension, looking through a compression layer (`data.jsonl.zst`)
+pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<FileType> {
+    let path = path.as_ref();
+    if is_connection_string(path) {
+        return Ok(FileType::Postgres);
+    }
+    if is_libsql_url(path) {
+        return Ok(FileType::Libsql);
+    }
+    if is_web_url(path) {
+        return Ok(FileType::Html);
+    }
+    let compressed = detect_compression(path).is_some();
+    let extension = lowercase_extension(&decompressed_path(path));
 
     match extension.as_str() {
+        "db" | "sqlite" | "sqlite3" | "duckdb" if compressed => Err(anyhow::anyhow!(
+            "Compressed databases can't be opened in place; decompress {} first",
+            path.display()
+        )),
         "db" | "sqlite" | "sqlite3" => Ok(FileType::Sqlite),
         "csv" => Ok(FileType::Csv),
-        "xlsx" | "xls" => Ok(FileType::Xlsx),
+        "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => Ok(FileType::Xlsx),
         "parquet" => Ok(FileType::Parquet),
+        "json" | "jsonl" | "ndjson" => Ok(FileType::Json),
+        "arrow" | "arrows" | "feather" | "ipc" => Ok(FileType::Arrow),
+        "dbf" => Ok(FileType::Dbf),
+        "dta" => Ok(FileType::Statistical(StatFormat::Stata)),
+        "sav" | "zsav" => Ok(FileType::Statistical(StatFormat::Spss)),
+        "sas7bdat" => Ok(FileType::Statistical(StatFormat::Sas)),
+        "html" | "htm" => Ok(FileType::Html),
+        "duckdb" => Ok(FileType::DuckDb),
+        _ if compressed => Ok(FileType::Csv),
         _ => {
             // Try to detect by content for files without clear extensions
             if is_sqlite_file(path)? {
@@ -57,18 +234,25 @@ fn is_sqlite_file<P: AsRef<Path>>(path: P) -> Result<bool> {
     }
 }
 
-pub fn read_csv_file<P: AsRef<Path>>(path: P) -> Result<QueryResult> {
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_csv_file<P: AsRef<Path>>(path: P, settings: &CsvSettings) -> Result<QueryResult> {
     let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .from_path(path)?;
+        .has_headers(settings.has_header)
+        .delimiter(settings.delimiter_byte())
+        .from_reader(open_input(path)?);
 
+    // Without a header row the first record is only used to count the columns
     let headers = reader.headers()?.clone();
-    let columns: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let columns: Vec<String> = if settings.has_header {
+        headers.iter().map(|h| h.to_string()).collect()
+    } else {
+        (1..=headers.len()).map(|i| format!("Column{}", i)).collect()
+    };
 
     let mut rows = Vec::new();
     for result in reader.records() {
         let record = result?;
-        let row: Vec<String> = record.iter().map(|field| field.to_string()).collect();
+        let row: Vec<Value> = record.iter().map(Value::from).collect();
         rows.push(row);
     }
 
@@ -81,143 +265,483 @@ pub fn read_csv_file<P: AsRef<Path>>(path: P) -> Result<QueryResult> {
     })
 }
 
-pub fn read_xlsx_file<P: AsRef<Path>>(path: P) -> Result<Vec<(String, QueryResult)>> {
-    let mut workbook: Xlsx<_> = open_workbook(path)?;
-    let mut sheets = Vec::new();
-
-    for sheet_name in workbook.sheet_names() {
-        let sheet_name = sheet_name.to_string();
-        
-        if let Ok(range) = workbook.worksheet_range(&sheet_name) {
-            let mut columns = Vec::new();
-            let mut rows = Vec::new();
-
-            // Get dimensions
-            let (height, width) = range.get_size();
-            
-            if height == 0 || width == 0 {
-                // Empty sheet
-                sheets.push((sheet_name, QueryResult {
-                    columns: vec!["Column1".to_string()],
-                    rows: Vec::new(),
-                    total_rows: 0,
-                }));
-                continue;
-            }
+/// Read every sheet of a spreadsheet; the format (XLSX, XLSB, legacy XLS or ODS) follows the extension,
+/// or is recognised from the content once decompressed
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_xlsx_file<P: AsRef<Path>>(
+    path: P,
+    settings: &XlsxSettings,
+) -> Result<Vec<(String, QueryResult)>> {
+    let path = path.as_ref();
+    if detect_compression(path).is_some() {
+        let workbook = open_workbook_auto_from_rs(Cursor::new(read_input_bytes(path)?))?;
+        Ok(read_workbook_sheets(workbook, settings))
+    } else {
+        Ok(read_workbook_sheets(open_workbook_auto(path)?, settings))
+    }
+}
 
-            // Extract headers from first row
-            for col in 0..width {
-                let cell_value = range.get((0, col));
-                let header = match cell_value {
-                    Some(Data::String(s)) => s.clone(),
-                    Some(Data::Float(f)) => f.to_string(),
-                    Some(Data::Int(i)) => i.to_string(),
-                    Some(Data::Bool(b)) => b.to_string(),
-                    Some(Data::DateTime(dt)) => dt.to_string(),
-                    Some(Data::DateTimeIso(dt)) => dt.clone(),
-                    Some(Data::DurationIso(d)) => d.clone(),
-                    Some(Data::Error(e)) => format!("Error: {:?}", e),
-                    None | Some(Data::Empty) => format!("Column{}", col + 1),
-                };
-                columns.push(header);
-            }
+fn read_workbook_sheets<RS: Read + Seek>(
+    mut workbook: Sheets<RS>,
+    settings: &XlsxSettings,
+) -> Vec<(String, QueryResult)> {
+    workbook
+        .sheet_names()
+        .into_iter()
+        .filter_map(|sheet_name| {
+            let sheet = read_sheet(&mut workbook, &sheet_name, settings).ok()?;
+            Some((sheet_name, sheet))
+        })
+        .collect()
+}
 
-            // Extract data rows (skip header row)
-            for row_idx in 1..height {
-                let mut row_data = Vec::new();
-                for col_idx in 0..width {
-                    let cell_value = range.get((row_idx, col_idx));
-                    let cell_string = match cell_value {
-                        Some(Data::String(s)) => s.clone(),
-                        Some(Data::Float(f)) => {
-                            // Format floats nicely
-                            if f.fract() == 0.0 {
-                                format!("{:.0}", f)
-                            } else {
-                                f.to_string()
-                            }
-                        },
-                        Some(Data::Int(i)) => i.to_string(),
-                        Some(Data::Bool(b)) => b.to_string(),
-                        Some(Data::DateTime(dt)) => dt.to_string(),
-                        Some(Data::DateTimeIso(dt)) => dt.clone(),
-                        Some(Data::DurationIso(d)) => d.clone(),
-                        Some(Data::Error(e)) => format!("Error: {:?}", e),
-                        None | Some(Data::Empty) => String::new(),
-                    };
-                    row_data.push(cell_string);
-                }
-                rows.push(row_data);
-            }
+/// Read one sheet of an open workbook, with its header on the configured row
+pub fn read_sheet<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    sheet_name: &str,
+    settings: &XlsxSettings,
+) -> Result<QueryResult> {
+    let range = workbook.worksheet_range(sheet_name)?;
+    let mut columns = Vec::new();
+    let mut rows = Vec::new();
 
-            let total_rows = rows.len();
-            sheets.push((sheet_name, QueryResult {
-                columns,
-                rows,
-                total_rows,
-            }));
+    // Get dimensions
+    let (height, width) = range.get_size();
+    let header_row = settings.header_row;
+
+    if height <= header_row || width == 0 {
+        // Empty sheet
+        return Ok(QueryResult {
+            columns: vec!["Column1".to_string()],
+            rows: Vec::new(),
+            total_rows: 0,
+        });
+    }
+
+    // Extract headers from the configured header row
+    for col in 0..width {
+        let cell_value = range.get((header_row, col));
+        let header = match cell_value {
+            Some(Data::String(s)) => s.clone(),
+            Some(Data::Float(f)) => f.to_string(),
+            Some(Data::Int(i)) => i.to_string(),
+            Some(Data::Bool(b)) => b.to_string(),
+            Some(Data::DateTime(dt)) => dt.to_string(),
+            Some(Data::DateTimeIso(dt)) => dt.clone(),
+            Some(Data::DurationIso(d)) => d.clone(),
+            Some(Data::Error(e)) => format!("Error: {:?}", e),
+            None | Some(Data::Empty) => format!("Column{}", col + 1),
+        };
+        columns.push(header);
+    }
+
+    // Extract data rows (skip header row and anything above it)
+    for row_idx in header_row + 1..height {
+        let mut row_data = Vec::new();
+        for col_idx in 0..width {
+            let cell_value = range.get((row_idx, col_idx));
+            let cell = match cell_value {
+                Some(Data::String(s)) => Value::Text(s.clone()),
+                // Whole numbers are stored as floats
+                Some(Data::Float(f)) if f.fract() == 0.0 && f.abs() < 1e15 => Value::Int(*f as i64),
+                Some(Data::Float(f)) => Value::Float(*f),
+                Some(Data::Int(i)) => Value::Int(*i),
+                Some(Data::Bool(b)) => Value::Bool(*b),
+                Some(Data::DateTime(dt)) => match dt.as_datetime() {
+                    Some(datetime) if dt.is_datetime() => Value::Date(datetime),
+                    _ => Value::Text(dt.to_string()),
+                },
+                Some(Data::DateTimeIso(dt)) => parse_date(dt).map_or_else(|| Value::Text(dt.clone()), Value::Date),
+                Some(Data::DurationIso(d)) => Value::Text(d.clone()),
+                Some(Data::Error(e)) => Value::Text(format!("Error: {:?}", e)),
+                None | Some(Data::Empty) => Value::Text(String::new()),
+            };
+            row_data.push(cell);
         }
+        rows.push(row_data);
     }
 
-    Ok(sheets)
+    let total_rows = rows.len();
+    Ok(QueryResult {
+        columns,
+        rows,
+        total_rows,
+    })
 }
 
-pub fn read_parquet_file<P: AsRef<Path>>(path: P) -> Result<QueryResult> {
-    let file = File::open(path)?;
-    let reader = SerializedFileReader::new(file)?;
-    let metadata = reader.metadata();
-    
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display(), batch_size = settings.batch_size), err)]
+pub fn read_parquet_file<P: AsRef<Path>>(path: P, settings: &ParquetSettings) -> Result<QueryResult> {
+    let path = path.as_ref();
+    let batch_size = settings.batch_size.max(1);
+    let reader = if detect_compression(path).is_some() {
+        ParquetRecordBatchReaderBuilder::try_new(Bytes::from(read_input_bytes(path)?))?
+            .with_batch_size(batch_size)
+            .build()?
+    } else {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+            .with_batch_size(batch_size)
+            .build()?
+    };
+
     // Get column names from schema
-    let schema = metadata.file_metadata().schema_descr();
-    let mut columns = Vec::new();
-    for i in 0..schema.num_columns() {
-        let column = schema.column(i);
-        columns.push(column.name().to_string());
+    let columns: Vec<String> = reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+
+    let rows = record_batch_rows(reader)?;
+    let total_rows = rows.len();
+
+    Ok(QueryResult {
+        columns,
+        rows,
+        total_rows,
+    })
+}
+
+/// Read an Arrow IPC file (`.arrow`, Feather v2) or stream (`.arrows`)
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_arrow_file<P: AsRef<Path>>(path: P) -> Result<QueryResult> {
+    let path = path.as_ref();
+    if detect_compression(path).is_some() {
+        read_arrow_ipc(Cursor::new(read_input_bytes(path)?))
+    } else {
+        read_arrow_ipc(File::open(path)?)
     }
-    
-    // Read all row groups
+}
+
+fn read_arrow_ipc<R: Read + Seek>(mut input: R) -> Result<QueryResult> {
+    let mut magic = [0u8; 6];
+    let is_file_format = input.read_exact(&mut magic).is_ok() && &magic == b"ARROW1";
+    if magic.starts_with(b"FEA1") {
+        return Err(anyhow::anyhow!(
+            "Feather v1 files are not supported; save them again as Feather v2 (Arrow IPC)"
+        ));
+    }
+
+    input.seek(SeekFrom::Start(0))?;
+    let (schema, rows) = if is_file_format {
+        let reader = FileReader::try_new(input, None)?;
+        (reader.schema(), record_batch_rows(reader)?)
+    } else {
+        let reader = StreamReader::try_new(input, None)?;
+        (reader.schema(), record_batch_rows(reader)?)
+    };
+
+    let columns: Vec<String> = schema.fields().iter().map(|field| field.name().to_string()).collect();
+    let total_rows = rows.len();
+    Ok(QueryResult {
+        columns,
+        rows,
+        total_rows,
+    })
+}
+
+/// Decode record batches into typed cells
+pub fn record_batch_rows(
+    batches: impl Iterator<Item = std::result::Result<RecordBatch, ArrowError>>,
+) -> Result<Vec<Vec<Value>>> {
     let mut rows = Vec::new();
-    
-    for row_group_idx in 0..metadata.num_row_groups() {
-        let row_group_reader = reader.get_row_group(row_group_idx)?;
-        let mut row_iter = row_group_reader.get_row_iter(None)?;
-        
-        while let Some(row_result) = row_iter.next() {
-            let row = row_result?;
-            let mut row_data = Vec::new();
-            
-            for col_idx in 0..columns.len() {
-                let cell_value = match row.get_string(col_idx) {
-                    Ok(val) => val.clone(),
-                    Err(_) => {
-                        // Try other types if string fails
-                        match row.get_long(col_idx) {
-                            Ok(val) => val.to_string(),
-                            Err(_) => match row.get_double(col_idx) {
-                                Ok(val) => val.to_string(),
-                                Err(_) => match row.get_bool(col_idx) {
-                                    Ok(val) => val.to_string(),
-                                    Err(_) => "NULL".to_string(),
-                                }
-                            }
-                        }
-                    }
-                };
-                row_data.push(cell_value);
+    for batch in batches {
+        check_cancelled()?;
+        let batch = batch?;
+        let columns: Vec<(ArrayRef, &str)> = batch
+            .columns()
+            .iter()
+            .map(|column| displayable_column(column))
+            .collect::<Result<_>>()?;
+        for row_idx in 0..batch.num_rows() {
+            let mut row_data = Vec::with_capacity(columns.len());
+            for (column, suffix) in &columns {
+                row_data.push(arrow_cell(column, row_idx, suffix)?);
             }
             rows.push(row_data);
         }
     }
-    
+    Ok(rows)
+}
+
+/// Numbers, booleans, binary values and dates keep their type; anything else (decimals,
+/// lists, structs, zoned timestamps) is shown as Arrow formats it
+fn arrow_cell(column: &ArrayRef, row: usize, suffix: &str) -> Result<Value> {
+    use arrow::array::AsArray;
+    use arrow::datatypes::{
+        Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type,
+        UInt8Type,
+    };
+
+    if column.is_null(row) {
+        return Ok(Value::Null);
+    }
+    let text = || -> Result<Value> { Ok(Value::Text(format!("{}{}", array_value_to_string(column, row)?, suffix))) };
+    Ok(match column.data_type() {
+        DataType::Int8 => Value::Int(column.as_primitive::<Int8Type>().value(row).into()),
+        DataType::Int16 => Value::Int(column.as_primitive::<Int16Type>().value(row).into()),
+        DataType::Int32 => Value::Int(column.as_primitive::<Int32Type>().value(row).into()),
+        DataType::Int64 => Value::Int(column.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt8 => Value::Int(column.as_primitive::<UInt8Type>().value(row).into()),
+        DataType::UInt16 => Value::Int(column.as_primitive::<UInt16Type>().value(row).into()),
+        DataType::UInt32 => Value::Int(column.as_primitive::<UInt32Type>().value(row).into()),
+        DataType::UInt64 => match i64::try_from(column.as_primitive::<UInt64Type>().value(row)) {
+            Ok(int) => Value::Int(int),
+            Err(_) => text()?,
+        },
+        // Widened through their shortest text so 0.1f32 stays 0.1
+        DataType::Float32 => {
+            let float = column.as_primitive::<Float32Type>().value(row);
+            Value::Float(float.to_string().parse().unwrap_or(float.into()))
+        }
+        DataType::Float64 => Value::Float(column.as_primitive::<Float64Type>().value(row)),
+        DataType::Boolean => Value::Bool(column.as_boolean().value(row)),
+        DataType::Binary => Value::Blob(column.as_binary::<i32>().value(row).to_vec()),
+        DataType::LargeBinary => Value::Blob(column.as_binary::<i64>().value(row).to_vec()),
+        DataType::Date32 | DataType::Date64 | DataType::Timestamp(_, None) if suffix.is_empty() => {
+            match parse_date(&array_value_to_string(column, row)?) {
+                Some(date) => Value::Date(date),
+                None => text()?,
+            }
+        }
+        _ => text()?,
+    })
+}
+
+/// Timestamps with a time zone hold UTC instants, but named zones (e.g. "Europe/Paris")
+/// can't be resolved without a time zone database. They are shown in UTC with a `Z`.
+fn displayable_column(column: &ArrayRef) -> Result<(ArrayRef, &'static str)> {
+    match column.data_type() {
+        DataType::Timestamp(unit, Some(_)) => {
+            let utc = column
+                .to_data()
+                .into_builder()
+                .data_type(DataType::Timestamp(*unit, None))
+                .build()?;
+            Ok((make_array(utc), "Z"))
+        }
+        _ => Ok((column.clone(), "")),
+    }
+}
+
+/// Read a JSON array of objects (`.json`) or one object per line (`.jsonl`, `.ndjson`).
+/// Top-level keys become columns in the order they first appear.
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_json_file<P: AsRef<Path>>(path: P) -> Result<QueryResult> {
+    let path = path.as_ref();
+    let mut content = String::new();
+    open_input(path)?.read_to_string(&mut content)?;
+    let json_lines = matches!(lowercase_extension(&decompressed_path(path)).as_str(), "jsonl" | "ndjson");
+
+    let objects: Vec<JsonObject> = if json_lines {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Line {} is not a JSON object", idx + 1))
+            })
+            .collect::<Result<_>>()?
+    } else {
+        serde_json::from_str(&content).context("Expected a JSON array of objects")?
+    };
+
+    Ok(flatten_json_objects(objects))
+}
+
+/// A JSON object with its keys in file order (serde_json's own map sorts them)
+struct JsonObject(Vec<(String, serde_json::Value)>);
+
+impl<'de> serde::Deserialize<'de> for JsonObject {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ObjectVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ObjectVisitor {
+            type Value = JsonObject;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<JsonObject, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(JsonObject(entries))
+            }
+        }
+
+        deserializer.deserialize_map(ObjectVisitor)
+    }
+}
+
+// Missing keys are left empty; nested objects and arrays are kept as compact JSON
+fn flatten_json_objects(objects: Vec<JsonObject>) -> QueryResult {
+    let mut columns: Vec<String> = Vec::new();
+    for JsonObject(entries) in &objects {
+        for (key, _) in entries {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+
+    let rows: Vec<Vec<Value>> = objects
+        .into_iter()
+        .map(|JsonObject(entries)| {
+            let mut row = vec![Value::Text(String::new()); columns.len()];
+            for (key, value) in entries {
+                if let Some(idx) = columns.iter().position(|c| *c == key) {
+                    row[idx] = json_cell(value);
+                }
+            }
+            row
+        })
+        .collect();
+
+    QueryResult {
+        columns,
+        total_rows: rows.len(),
+        rows,
+    }
+}
+
+fn json_cell(value: serde_json::Value) -> Value {
+    use serde_json::Value as Json;
+
+    match value {
+        Json::Null => Value::Null,
+        Json::String(s) => Value::Text(s),
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(int) => Value::Int(int),
+            None => n.as_f64().map_or_else(|| Value::Text(n.to_string()), Value::Float),
+        },
+        other => Value::Text(other.to_string()),
+    }
+}
+
+/// Read a dBase table (`.dbf`, e.g. the attributes of a shapefile). Deleted records are
+/// skipped; blank numbers, dates and logicals read as NULL. Memo fields live in a separate
+/// `.dbt`/`.fpt` file and are shown as their block number.
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_dbf_file<P: AsRef<Path>>(path: P) -> Result<QueryResult> {
+    let bytes = read_input_bytes(path.as_ref())?;
+    if bytes.len() < 32 {
+        return Err(anyhow::anyhow!("Not a dBase file: header is truncated"));
+    }
+    let record_count = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let record_len = u16::from_le_bytes([bytes[10], bytes[11]]) as usize;
+
+    // Field descriptors are 32 bytes each, up to a 0x0D terminator
+    let mut fields = Vec::new();
+    let mut offset = 32;
+    while offset + 32 <= header_len.min(bytes.len()) && bytes[offset] != 0x0D {
+        let descriptor = &bytes[offset..offset + 32];
+        let name_end = descriptor[..11].iter().position(|&b| b == 0).unwrap_or(11);
+        fields.push(DbfField {
+            name: decode_text(&descriptor[..name_end]),
+            kind: descriptor[11],
+            length: descriptor[16] as usize,
+        });
+        offset += 32;
+    }
+    if fields.is_empty() {
+        return Err(anyhow::anyhow!("Not a dBase file: no field descriptors"));
+    }
+
+    let mut rows = Vec::with_capacity(record_count);
+    for record_idx in 0..record_count {
+        let start = header_len + record_idx * record_len;
+        let Some(record) = bytes.get(start..start + record_len) else {
+            break; // truncated file: keep the complete records
+        };
+        if record[0] == b'*' {
+            continue;
+        }
+        let mut position = 1;
+        let mut row = Vec::with_capacity(fields.len());
+        for field in &fields {
+            let raw = record.get(position..position + field.length).unwrap_or_default();
+            row.push(dbf_cell(field.kind, raw));
+            position += field.length;
+        }
+        rows.push(row);
+    }
+
     let total_rows = rows.len();
-    
     Ok(QueryResult {
-        columns,
+        columns: fields.into_iter().map(|field| field.name).collect(),
         rows,
         total_rows,
     })
 }
 
+struct DbfField {
+    name: String,
+    kind: u8,
+    length: usize,
+}
+
+fn dbf_cell(kind: u8, raw: &[u8]) -> Value {
+    let text = decode_text(raw);
+    let trimmed = text.trim();
+    let null = || Value::Null;
+    match kind {
+        b'C' => Value::Text(text.trim_end_matches([' ', '\0']).to_string()),
+        b'N' | b'F' => {
+            // Overflowing numbers are filled with asterisks
+            if trimmed.is_empty() || trimmed.starts_with('*') {
+                null()
+            } else if let Ok(int) = trimmed.parse() {
+                Value::Int(int)
+            } else {
+                trimmed.parse().map_or_else(|_| Value::Text(trimmed.to_string()), Value::Float)
+            }
+        }
+        b'D' => NaiveDate::parse_from_str(trimmed, "%Y%m%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map_or_else(null, Value::Date),
+        b'L' => match trimmed.chars().next() {
+            Some('T' | 't' | 'Y' | 'y') => Value::Bool(true),
+            Some('F' | 'f' | 'N' | 'n') => Value::Bool(false),
+            _ => null(),
+        },
+        // Visual FoxPro binary types
+        b'I' if raw.len() == 4 => Value::Int(i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]).into()),
+        b'B' | b'O' if raw.len() == 8 => Value::Float(f64::from_le_bytes(raw.try_into().unwrap_or_default())),
+        // Currency keeps its four decimals
+        b'Y' if raw.len() == 8 => {
+            let cents = i64::from_le_bytes(raw.try_into().unwrap_or_default());
+            Value::Text(format!("{:.4}", cents as f64 / 10_000.0))
+        }
+        b'T' if raw.len() == 8 => {
+            let day = i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+            let millis = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]);
+            // Julian day number; 2440588 is 1970-01-01
+            let seconds = (day as i64 - 2_440_588) * 86_400 + (millis / 1000) as i64;
+            if day == 0 {
+                null()
+            } else {
+                DateTime::from_timestamp(seconds, 0).map_or_else(null, |dt| Value::Date(dt.naive_utc()))
+            }
+        }
+        _ => Value::Text(trimmed.to_string()),
+    }
+}
+
+/// Text of binary formats (dBase, Stata, SPSS, SAS) is in the file's code page; UTF-8 is
+/// used when valid, Latin-1 otherwise
+pub fn decode_text(raw: &[u8]) -> String {
+    match std::str::from_utf8(raw) {
+        Ok(text) => text.to_string(),
+        Err(_) => raw.iter().map(|&b| b as char).collect(),
+    }
+}
+
 pub fn paginate_data(data: &QueryResult, offset: usize, limit: usize) -> QueryResult {
     let end = (offset + limit).min(data.rows.len());
     let paginated_rows = if offset < data.rows.len() {
@@ -231,4 +755,523 @@ pub fn paginate_data(data: &QueryResult, offset: usize, limit: usize) -> QueryRe
         rows: paginated_rows,
         total_rows: data.total_rows,
     }
-}
\ No newline at end of file
+}
+
+/// Type of a file column, inferred from a sample of its values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    Date,
+    Text,
+}
+
+/// Rows looked at when inferring column types
+pub const TYPE_SAMPLE_ROWS: usize = 1000;
+
+impl ColumnType {
+    pub fn label(self) -> &'static str {
+        match self {
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+            ColumnType::Date => "date",
+            ColumnType::Text => "text",
+        }
+    }
+
+    pub fn is_numeric(self) -> bool {
+        matches!(self, ColumnType::Int | ColumnType::Float)
+    }
+
+    /// Whether `value` fits this type; empty and NULL values fit every type
+    pub fn accepts(self, value: &str, locale: NumberLocale) -> bool {
+        let value = value.trim();
+        if is_missing(value) {
+            return true;
+        }
+        match self {
+            ColumnType::Int => parse_integer(value, locale).is_some(),
+            ColumnType::Float => parse_number(value, locale).is_some_and(|f| f.is_finite()),
+            ColumnType::Bool => parse_bool(value).is_some(),
+            ColumnType::Date => parse_date(value).is_some(),
+            ColumnType::Text => true,
+        }
+    }
+}
+
+/// Infer the type of every column from the first rows of the data. Columns of typed
+/// cells take their cells' type; text is inferred from what it reads as.
+pub fn infer_column_types(data: &QueryResult, locale: NumberLocale) -> Vec<ColumnType> {
+    (0..data.columns.len())
+        .map(|col_idx| infer_cells_type(data, col_idx, locale))
+        .collect()
+}
+
+fn infer_cells_type(data: &QueryResult, col_idx: usize, locale: NumberLocale) -> ColumnType {
+    let sample = data.rows.iter().take(TYPE_SAMPLE_ROWS).filter_map(|row| row.get(col_idx));
+    typed_column_type(sample.clone()).unwrap_or_else(|| {
+        let texts: Vec<_> = sample.map(|v| v.as_text()).collect();
+        infer_column_type(texts.iter().map(|v| v.as_ref()), locale)
+    })
+}
+
+/// The type shared by typed cells (integers and floats make floats), None when there is
+/// text among them or no typed cell at all
+fn typed_column_type<'a>(values: impl Iterator<Item = &'a Value>) -> Option<ColumnType> {
+    let mut column_type = None;
+    for value in values {
+        let value_type = match value {
+            Value::Null => continue,
+            Value::Int(_) => ColumnType::Int,
+            Value::Float(_) => ColumnType::Float,
+            Value::Bool(_) => ColumnType::Bool,
+            Value::Date(_) => ColumnType::Date,
+            Value::Text(_) | Value::Blob(_) => return None,
+        };
+        column_type = match (column_type, value_type) {
+            (None, ty) => Some(ty),
+            (Some(a), b) if a == b => Some(a),
+            (Some(ColumnType::Int | ColumnType::Float), ColumnType::Int | ColumnType::Float) => Some(ColumnType::Float),
+            _ => return None,
+        };
+    }
+    column_type
+}
+
+/// The narrowest type that accepts every non-empty value (text when all are empty)
+pub fn infer_column_type<'a>(
+    values: impl Iterator<Item = &'a str> + Clone,
+    locale: NumberLocale,
+) -> ColumnType {
+    let mut present = values.filter(|v| !is_missing(v.trim())).peekable();
+    if present.peek().is_none() {
+        return ColumnType::Text;
+    }
+    [ColumnType::Int, ColumnType::Float, ColumnType::Bool, ColumnType::Date]
+        .into_iter()
+        .find(|ty| present.clone().all(|v| ty.accepts(v, locale)))
+        .unwrap_or(ColumnType::Text)
+}
+
+fn is_missing(value: &str) -> bool {
+    value.is_empty() || value == "NULL"
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|pattern| NaiveDateTime::parse_from_str(value, pattern).ok())
+        // With an offset or `Z`, compared as UTC
+        .or_else(|| DateTime::parse_from_rfc3339(value).ok().map(|date| date.naive_utc()))
+}
+
+/// Paginate after ordering rows by a column, compared according to its inferred type
+pub fn paginate_sorted_data(
+    data: &QueryResult,
+    sort: Option<&SortOrder>,
+    offset: usize,
+    limit: usize,
+    locale: NumberLocale,
+) -> QueryResult {
+    let Some(sort) = sort else {
+        return paginate_data(data, offset, limit);
+    };
+    let Some(col_idx) = data.columns.iter().position(|c| c == &sort.column) else {
+        return paginate_data(data, offset, limit);
+    };
+
+    let null = Value::Null;
+    let value = |row: usize| data.rows[row].get(col_idx).unwrap_or(&null);
+    let column_type = infer_cells_type(data, col_idx, locale);
+
+    let mut order: Vec<usize> = (0..data.rows.len()).collect();
+    order.sort_by(|&a, &b| {
+        // Text is compared as the type it reads as, typed cells by their values
+        let ordering = match (value(a), value(b)) {
+            (Value::Text(a), Value::Text(b)) => compare_values(a, b, column_type, locale),
+            (a, b) => a.compare(b),
+        };
+        if sort.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    QueryResult {
+        columns: data.columns.clone(),
+        rows: order
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|i| data.rows[i].clone())
+            .collect(),
+        total_rows: data.total_rows,
+    }
+}
+
+/// Compare two cells as `column_type`. Values that don't parse (empty, NULL, or outside
+/// the sampled rows' type) sort before all others, like NULLs in SQLite.
+pub fn compare_values(
+    a: &str,
+    b: &str,
+    column_type: ColumnType,
+    locale: NumberLocale,
+) -> std::cmp::Ordering {
+    fn by_key<T: PartialOrd>(a: Option<T>, b: Option<T>) -> std::cmp::Ordering {
+        match (a, b) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        }
+    }
+
+    let (a, b) = (a.trim(), b.trim());
+    match column_type {
+        ColumnType::Int | ColumnType::Float => {
+            by_key(parse_number(a, locale), parse_number(b, locale))
+        }
+        ColumnType::Bool => by_key(parse_bool(a), parse_bool(b)),
+        ColumnType::Date => by_key(parse_date(a), parse_date(b)),
+        ColumnType::Text => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_read_parquet_in_batches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("people.parquet");
+
+        let names: ArrayRef = Arc::new(StringArray::from(vec![Some("Alice"), None, Some("Carol")]));
+        let ages: ArrayRef = Arc::new(Int64Array::from(vec![30, 25, 41]));
+        let batch = RecordBatch::try_from_iter(vec![("name", names), ("age", ages)]).unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let data = read_parquet_file(&path, &ParquetSettings { batch_size: 2 }).unwrap();
+        assert_eq!(data.columns, vec!["name", "age"]);
+        assert_eq!(data.total_rows, 3);
+        assert_eq!(data.rows[1], vec!["NULL", "25"]);
+        assert_eq!(data.rows[2], vec!["Carol", "41"]);
+    }
+
+    #[test]
+    fn test_load_progress() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("data.csv");
+        std::fs::write(&path, "name,age\nAlice,30\nBob,25\n").unwrap();
+
+        // Reads are counted on the thread the progress is tracked on
+        let progress = Arc::new(LoadProgress::default());
+        let tracked = progress.clone();
+        let read = std::thread::spawn(move || {
+            track_load_progress(tracked);
+            read_csv_file(&path, &CsvSettings::default())
+        });
+        assert_eq!(read.join().unwrap().unwrap().total_rows, 2);
+        assert_eq!(progress.bytes_read(), "name,age\nAlice,30\nBob,25\n".len() as u64);
+
+        progress.cancel();
+        track_load_progress(progress);
+        let path = temp_dir.path().join("data.csv");
+        assert!(read_csv_file(&path, &CsvSettings::default()).is_err());
+    }
+
+    #[test]
+    fn test_read_compressed_files() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let gzip = |name: &str, content: &[u8]| {
+            let path = temp_dir.path().join(name);
+            let mut encoder = GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::default());
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap();
+            path
+        };
+
+        let path = gzip("data.csv.gz", b"name,age\nAlice,30\nBob,25\n");
+        assert_eq!(detect_file_type(&path).unwrap(), FileType::Csv);
+        let data = read_csv_file(&path, &CsvSettings::default()).unwrap();
+        assert_eq!(data.rows[1], vec!["Bob", "25"]);
+        assert_eq!(converted_csv_path(&path), temp_dir.path().join("data.csv"));
+
+        let path = temp_dir.path().join("events.jsonl.zst");
+        std::fs::write(&path, zstd::encode_all(&b"{\"id\": 1}\n{\"id\": 2}\n"[..], 3).unwrap()).unwrap();
+        assert_eq!(detect_file_type(&path).unwrap(), FileType::Json);
+        assert_eq!(read_json_file(&path).unwrap().total_rows, 2);
+
+        let ids: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+        let batch = RecordBatch::try_from_iter(vec![("id", ids)]).unwrap();
+        let mut parquet = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut parquet, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let path = gzip("ids.parquet.gz", &parquet);
+        assert_eq!(detect_file_type(&path).unwrap(), FileType::Parquet);
+        let data = read_parquet_file(&path, &ParquetSettings::default()).unwrap();
+        assert_eq!(data.rows[2], vec!["3"]);
+
+        assert!(detect_file_type("app.db.gz").is_err());
+    }
+
+    #[test]
+    fn test_read_dbf_file() {
+        // name C(8), pop N(6), founded D, capital L, one live and one deleted record
+        let fields: [(&[u8], u8, u8); 4] = [(b"NAME", b'C', 8), (b"POP", b'N', 6), (b"FOUNDED", b'D', 8), (b"CAPITAL", b'L', 1)];
+        let record_len = 1 + 8 + 6 + 8 + 1;
+        let header_len = 32 + 32 * fields.len() + 1;
+        let mut dbf = vec![0x03, 124, 1, 1];
+        dbf.extend_from_slice(&3u32.to_le_bytes());
+        dbf.extend_from_slice(&(header_len as u16).to_le_bytes());
+        dbf.extend_from_slice(&(record_len as u16).to_le_bytes());
+        dbf.resize(32, 0);
+        for (name, kind, length) in fields {
+            let mut descriptor = [0u8; 32];
+            descriptor[..name.len()].copy_from_slice(name);
+            descriptor[11] = kind;
+            descriptor[16] = length;
+            dbf.extend_from_slice(&descriptor);
+        }
+        dbf.push(0x0D);
+        dbf.extend_from_slice(&[&b" "[..], b"Z\xfcrich  ", b"   421", b"19000101", b"T"].concat());
+        dbf.extend_from_slice(&[&b"*"[..], b"Deleted ", b"     1", b"        ", b"F"].concat());
+        dbf.extend_from_slice(&[&b" "[..], b"Bern    ", b"      ", b"        ", b"?"].concat());
+        dbf.push(0x1A);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("cities.dbf");
+        std::fs::write(&path, &dbf).unwrap();
+        assert_eq!(detect_file_type(&path).unwrap(), FileType::Dbf);
+
+        let data = read_dbf_file(&path).unwrap();
+        assert_eq!(data.columns, vec!["NAME", "POP", "FOUNDED", "CAPITAL"]);
+        assert_eq!(data.rows, vec![
+            vec!["Zürich", "421", "1900-01-01", "true"],
+            vec!["Bern", "NULL", "NULL", "NULL"],
+        ]);
+    }
+
+    #[test]
+    fn test_read_ods_sheets() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("budget.ods");
+        let sheet = |name: &str, rows: &[&[&str]]| {
+            let rows: String = rows
+                .iter()
+                .map(|cells| {
+                    let cells: String = cells
+                        .iter()
+                        .map(|c| format!("<table:table-cell office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>", c))
+                        .collect();
+                    format!("<table:table-row>{}</table:table-row>", cells)
+                })
+                .collect();
+            format!("<table:table table:name=\"{}\">{}</table:table>", name, rows)
+        };
+        let content = format!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+                "<office:document-content xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" ",
+                "xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" ",
+                "xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\">",
+                "<office:body><office:spreadsheet>{}{}</office:spreadsheet></office:body></office:document-content>"
+            ),
+            sheet("Income", &[&["month", "amount"], &["Jan", "100"]]),
+            sheet("Costs", &[&["item"], &["Rent"], &["Food"]]),
+        );
+
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("mimetype", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"application/vnd.oasis.opendocument.spreadsheet").unwrap();
+        zip.start_file("content.xml", SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+        zip.start_file("META-INF/manifest.xml", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\"/>")
+            .unwrap();
+        zip.finish().unwrap();
+
+        assert_eq!(detect_file_type(&path).unwrap(), FileType::Xlsx);
+        assert_eq!(detect_file_type("legacy.xls").unwrap(), FileType::Xlsx);
+        assert_eq!(detect_file_type("binary.xlsb").unwrap(), FileType::Xlsx);
+        let sheets = read_xlsx_file(&path, &XlsxSettings::default()).unwrap();
+        let names: Vec<&str> = sheets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Income", "Costs"]);
+        assert_eq!(sheets[0].1.columns, vec!["month", "amount"]);
+        assert_eq!(sheets[0].1.rows, vec![vec!["Jan", "100"]]);
+        assert_eq!(sheets[1].1.total_rows, 2);
+    }
+
+    #[test]
+    fn test_read_legacy_and_binary_workbooks() {
+        for name in ["people.xls", "people.xlsb"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+            let sheets = read_xlsx_file(&path, &XlsxSettings::default()).unwrap();
+            assert_eq!(sheets.len(), 1, "{}", name);
+            assert_eq!(sheets[0].0, "People");
+            assert_eq!(sheets[0].1.columns, vec!["name", "age"]);
+            assert_eq!(sheets[0].1.rows, vec![vec!["Alice", "30"], vec!["Bob", "25"]], "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_read_arrow_files() {
+        use arrow::array::TimestampSecondArray;
+        use arrow::ipc::writer::{FileWriter, StreamWriter};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let names: ArrayRef = Arc::new(StringArray::from(vec![Some("Alice"), None]));
+        let seen: ArrayRef = Arc::new(
+            TimestampSecondArray::from(vec![Some(1_705_314_600), None]).with_timezone("Europe/Paris"),
+        );
+        let batch = RecordBatch::try_from_iter(vec![("name", names), ("seen", seen)]).unwrap();
+
+        let path = temp_dir.path().join("people.feather");
+        let mut writer = FileWriter::try_new(File::create(&path).unwrap(), &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        let data = read_arrow_file(&path).unwrap();
+        assert_eq!(data.columns, vec!["name", "seen"]);
+        assert_eq!(data.rows[0], vec!["Alice", "2024-01-15T10:30:00Z"]);
+        assert_eq!(data.rows[1], vec!["NULL", "NULL"]);
+        assert_eq!(infer_column_types(&data, NumberLocale::En)[1], ColumnType::Date);
+
+        let path = temp_dir.path().join("people.arrows");
+        let mut writer = StreamWriter::try_new(File::create(&path).unwrap(), &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(read_arrow_file(&path).unwrap().total_rows, 2);
+        assert_eq!(detect_file_type("x.arrow").unwrap(), FileType::Arrow);
+    }
+
+    #[test]
+    fn test_read_json_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let path = temp_dir.path().join("people.json");
+        std::fs::write(
+            &path,
+            r#"[{"name": "Alice", "age": 30, "tags": ["a", "b"]},
+                {"name": "Bob", "address": {"city": "Oslo"}, "age": null}]"#,
+        )
+        .unwrap();
+        let data = read_json_file(&path).unwrap();
+        assert_eq!(data.columns, vec!["name", "age", "tags", "address"]);
+        assert_eq!(data.rows[0], vec!["Alice", "30", r#"["a","b"]"#, ""]);
+        assert_eq!(data.rows[1], vec!["Bob", "NULL", "", r#"{"city":"Oslo"}"#]);
+
+        let path = temp_dir.path().join("events.ndjson");
+        std::fs::write(&path, "{\"id\": 1, \"ok\": true}\n\n{\"id\": 2}\n").unwrap();
+        let data = read_json_file(&path).unwrap();
+        assert_eq!(data.columns, vec!["id", "ok"]);
+        assert_eq!(data.total_rows, 2);
+        assert_eq!(data.rows[1], vec!["2", ""]);
+
+        std::fs::write(&path, "{\"id\": 1}\n[1, 2]\n").unwrap();
+        let error = read_json_file(&path).unwrap_err();
+        assert!(error.to_string().contains("Line 2"));
+        assert_eq!(detect_file_type("x.jsonl").unwrap(), FileType::Json);
+        assert_eq!(detect_file_type("x.duckdb").unwrap(), FileType::DuckDb);
+        assert_eq!(detect_file_type("postgres://localhost/x.db").unwrap(), FileType::Postgres);
+        assert_eq!(detect_file_type("libsql://sales-acme.turso.io").unwrap(), FileType::Libsql);
+        assert_eq!(detect_file_type("report.htm").unwrap(), FileType::Html);
+        assert_eq!(detect_file_type("https://example.com/report").unwrap(), FileType::Html);
+    }
+
+    #[test]
+    fn test_paginate_sorted_data() {
+        let data = QueryResult {
+            columns: vec!["name".to_string(), "age".to_string()],
+            rows: text_rows(vec![vec!["Alice", "30"], vec!["Bob", "9"], vec!["Carol", "41"]]),
+            total_rows: 3,
+        };
+        let sort = SortOrder { column: "age".to_string(), descending: false };
+        let page = paginate_sorted_data(&data, Some(&sort), 1, 2, NumberLocale::En);
+        assert_eq!(page.rows[0][0], "Alice");
+        assert_eq!(page.rows[1][0], "Carol");
+    }
+
+    #[test]
+    fn test_sort_localized_numbers() {
+        let data = QueryResult {
+            columns: vec!["name".to_string(), "amount".to_string()],
+            rows: text_rows(vec![vec!["a", "1.234,5"], vec!["b", "99,9"], vec!["c", "-2,5"]]),
+            total_rows: 3,
+        };
+        assert_eq!(infer_column_types(&data, NumberLocale::De)[1], ColumnType::Float);
+        assert_eq!(infer_column_types(&data, NumberLocale::En)[1], ColumnType::Text);
+
+        let sort = SortOrder { column: "amount".to_string(), descending: false };
+        let page = paginate_sorted_data(&data, Some(&sort), 0, 3, NumberLocale::De);
+        let names: Vec<String> = page.rows.iter().map(|r| r[0].to_string()).collect();
+        assert_eq!(names, vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_infer_column_types() {
+        let data = QueryResult {
+            columns: ["id", "price", "active", "joined", "note", "empty"].map(String::from).to_vec(),
+            rows: text_rows(vec![
+                vec!["1", "9.5", "true", "2024-01-05", "a", ""],
+                vec!["2", "10", "No", "2024-02-10 08:00:00", "7", "NULL"],
+                vec!["", "NULL", "yes", "", "b", ""],
+            ]),
+            total_rows: 3,
+        };
+        assert_eq!(
+            infer_column_types(&data, NumberLocale::En),
+            vec![
+                ColumnType::Int,
+                ColumnType::Float,
+                ColumnType::Bool,
+                ColumnType::Date,
+                ColumnType::Text,
+                ColumnType::Text
+            ]
+        );
+        assert!(ColumnType::Int.accepts("42", NumberLocale::En));
+        assert!(ColumnType::Int.accepts("", NumberLocale::En));
+        assert!(!ColumnType::Int.accepts("4.2", NumberLocale::En));
+        assert!(!ColumnType::Date.accepts("2024-13-01", NumberLocale::En));
+    }
+
+    #[test]
+    fn test_compare_values_by_type() {
+        use std::cmp::Ordering;
+        assert_eq!(compare_values("9", "10", ColumnType::Int, NumberLocale::En), Ordering::Less);
+        assert_eq!(compare_values("9", "10", ColumnType::Text, NumberLocale::En), Ordering::Greater);
+        assert_eq!(compare_values("", "1", ColumnType::Int, NumberLocale::En), Ordering::Less);
+        assert_eq!(compare_values("false", "true", ColumnType::Bool, NumberLocale::En), Ordering::Less);
+        assert_eq!(
+            compare_values("2024-02-01", "2023-12-31 23:00:00", ColumnType::Date, NumberLocale::En),
+            Ordering::Greater
+        );
+    }
+}
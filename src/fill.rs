@@ -0,0 +1,133 @@
+use anyhow::Result;
+use chrono::{NaiveDate, TimeDelta};
+
+use crate::value::Value;
+
+/// Values for the `count` cells below `start` when filling down (`Ctrl+D`): copies of it, or
+/// for a series the values continuing from it. A series steps by the difference from
+/// `previous`, the cell above, when that is of the same kind, and by one otherwise; text
+/// keeps its form, so "2024-01-31" is followed by "2024-02-01" and "item09" by "item10".
+pub fn fill_values(start: &Value, previous: Option<&Value>, count: usize, series: bool) -> Result<Vec<Value>> {
+    if !series {
+        return Ok(vec![start.clone(); count]);
+    }
+    let steps = 1..=count as i64;
+    let values = match start {
+        Value::Int(n) => {
+            let step = match previous {
+                Some(Value::Int(p)) => n.saturating_sub(*p),
+                _ => 1,
+            };
+            steps.map(|k| Value::Int(n.saturating_add(step.saturating_mul(k)))).collect()
+        }
+        Value::Float(f) => {
+            let step = match previous {
+                Some(Value::Float(p)) => f - p,
+                Some(Value::Int(p)) => f - *p as f64,
+                _ => 1.0,
+            };
+            steps.map(|k| Value::Float(f + step * k as f64)).collect()
+        }
+        Value::Date(date) => {
+            let step = match previous {
+                Some(Value::Date(p)) => *date - *p,
+                _ => TimeDelta::days(1),
+            };
+            steps.map(|k| Value::Date(*date + step * k as i32)).collect()
+        }
+        Value::Text(text) => {
+            let previous = match previous {
+                Some(Value::Text(p)) => Some(p.as_str()),
+                _ => None,
+            };
+            text_series(text, previous, count)?.into_iter().map(Value::Text).collect()
+        }
+        _ => return Err(series_error()),
+    };
+    Ok(values)
+}
+
+fn series_error() -> anyhow::Error {
+    anyhow::anyhow!("A series continues a number, a date or text ending in a number")
+}
+
+/// A series of numbers, ISO dates or numbered text, as read from a text cell
+fn text_series(text: &str, previous: Option<&str>, count: usize) -> Result<Vec<String>> {
+    let steps = 1..=count as i64;
+    let trimmed = text.trim();
+
+    if let Ok(n) = trimmed.parse::<i64>() {
+        let step = previous.and_then(|p| p.trim().parse::<i64>().ok()).map_or(1, |p| n.saturating_sub(p));
+        return Ok(steps.map(|k| n.saturating_add(step.saturating_mul(k)).to_string()).collect());
+    }
+    if let Ok(f) = trimmed.parse::<f64>() {
+        let step = previous.and_then(|p| p.trim().parse::<f64>().ok()).map_or(1.0, |p| f - p);
+        // As many decimals as the cell shows, so 1.50 is followed by 2.50
+        let decimals = trimmed.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+        return Ok(steps.map(|k| format!("{:.*}", decimals, f + step * k as f64)).collect());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+        let step = previous
+            .and_then(|p| NaiveDate::parse_from_str(p.trim(), "%Y-%m-%d").ok())
+            .map_or(TimeDelta::days(1), |p| date - p);
+        return Ok(steps.map(|k| (date + step * k as i32).format("%Y-%m-%d").to_string()).collect());
+    }
+
+    let (prefix, digits) = split_trailing_number(text).ok_or_else(series_error)?;
+    let n: i64 = digits.parse().map_err(|_| series_error())?;
+    let step = previous
+        .and_then(split_trailing_number)
+        .filter(|(other_prefix, _)| *other_prefix == prefix)
+        .and_then(|(_, other)| other.parse::<i64>().ok())
+        .map_or(1, |p| n.saturating_sub(p));
+    Ok(steps
+        .map(|k| format!("{}{:0width$}", prefix, n.saturating_add(step.saturating_mul(k)), width = digits.len()))
+        .collect())
+}
+
+/// `item09` as `item` and `09`
+fn split_trailing_number(text: &str) -> Option<(&str, &str)> {
+    let start = text.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    (start < text.len()).then(|| text.split_at(start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(values: Vec<Value>) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fill_copies() {
+        let values = fill_values(&Value::from("done"), None, 3, false).unwrap();
+        assert_eq!(values, vec!["done", "done", "done"]);
+        assert!(fill_values(&Value::from("done"), None, 3, true).is_err());
+    }
+
+    #[test]
+    fn test_number_series() {
+        assert_eq!(fill_values(&Value::Int(5), None, 2, true).unwrap(), vec![Value::Int(6), Value::Int(7)]);
+        assert_eq!(fill_values(&Value::Int(10), Some(&Value::Int(5)), 2, true).unwrap(), vec![Value::Int(15), Value::Int(20)]);
+        assert_eq!(fill_values(&Value::Float(0.5), Some(&Value::Float(0.25)), 1, true).unwrap(), vec![Value::Float(0.75)]);
+        assert_eq!(text(fill_values(&Value::from("1.50"), None, 2, true).unwrap()), vec!["2.50", "3.50"]);
+        assert_eq!(text(fill_values(&Value::from("30"), Some(&Value::from("20")), 2, true).unwrap()), vec!["40", "50"]);
+    }
+
+    #[test]
+    fn test_date_and_text_series() {
+        let values = fill_values(&Value::from("2024-01-31"), None, 2, true).unwrap();
+        assert_eq!(text(values), vec!["2024-02-01", "2024-02-02"]);
+        let values = fill_values(&Value::from("2024-01-08"), Some(&Value::from("2024-01-01")), 1, true).unwrap();
+        assert_eq!(text(values), vec!["2024-01-15"]);
+
+        let start = NaiveDate::from_ymd_opt(2024, 2, 28).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let values = fill_values(&Value::Date(start), None, 2, true).unwrap();
+        assert_eq!(values[1], Value::Date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(0, 0, 0).unwrap()));
+
+        assert_eq!(text(fill_values(&Value::from("item09"), None, 2, true).unwrap()), vec!["item10", "item11"]);
+        let values = fill_values(&Value::from("row 4"), Some(&Value::from("row 2")), 1, true).unwrap();
+        assert_eq!(text(values), vec!["row 6"]);
+    }
+}
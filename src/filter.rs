@@ -0,0 +1,320 @@
+use anyhow::Result;
+use regex::Regex;
+
+use crate::config::NumberLocale;
+use crate::database::{quote_identifier, QueryResult};
+use crate::format::parse_number;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Regex,
+    IsNull,
+    NotNull,
+}
+
+/// A condition on one column of the data view, typed at the `w` prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnFilter {
+    pub column: String,
+    pub op: FilterOp,
+    pub value: String, // empty for the NULL tests
+}
+
+impl ColumnFilter {
+    /// Parse `>100`, `<=2024-01-01`, `!=done`, `contains:foo`, `re:^A\d+`, `=NULL`, `!=NULL`
+    /// or a plain value, which must match exactly
+    pub fn parse(column: &str, input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(anyhow::anyhow!("Empty filter"));
+        }
+
+        let (op, value) = if let Some(rest) = input.strip_prefix("contains:") {
+            (FilterOp::Contains, rest)
+        } else if let Some(rest) = input.strip_prefix("re:") {
+            (FilterOp::Regex, rest)
+        } else if let Some(rest) = input.strip_prefix(">=") {
+            (FilterOp::Ge, rest)
+        } else if let Some(rest) = input.strip_prefix("<=") {
+            (FilterOp::Le, rest)
+        } else if let Some(rest) = input.strip_prefix("!=") {
+            (FilterOp::Ne, rest)
+        } else if let Some(rest) = input.strip_prefix('>') {
+            (FilterOp::Gt, rest)
+        } else if let Some(rest) = input.strip_prefix('<') {
+            (FilterOp::Lt, rest)
+        } else if let Some(rest) = input.strip_prefix('=') {
+            (FilterOp::Eq, rest)
+        } else {
+            (FilterOp::Eq, input)
+        };
+
+        let value = value.trim();
+        if op == FilterOp::Regex {
+            Regex::new(value).map_err(|e| anyhow::anyhow!("Invalid regular expression: {}", e))?;
+        }
+        let op = match (op, value.eq_ignore_ascii_case("NULL")) {
+            (FilterOp::Eq, true) => FilterOp::IsNull,
+            (FilterOp::Ne, true) => FilterOp::NotNull,
+            (FilterOp::Regex, true) => FilterOp::Regex,
+            (_, true) => return Err(anyhow::anyhow!("NULL can only be tested with = or !=")),
+            (op, false) => op,
+        };
+        if value.is_empty() && op != FilterOp::Contains {
+            return Err(anyhow::anyhow!("Missing value after the operator in '{}'", input));
+        }
+
+        Ok(Self {
+            column: column.to_string(),
+            op,
+            value: match op {
+                FilterOp::IsNull | FilterOp::NotNull => String::new(),
+                _ => value.to_string(),
+            },
+        })
+    }
+
+    /// The filter as typed, e.g. `>100` (shown in the prompt and the data view title)
+    pub fn expression(&self) -> String {
+        match self.op {
+            FilterOp::Eq => format!("={}", self.value),
+            FilterOp::Ne => format!("!={}", self.value),
+            FilterOp::Lt => format!("<{}", self.value),
+            FilterOp::Le => format!("<={}", self.value),
+            FilterOp::Gt => format!(">{}", self.value),
+            FilterOp::Ge => format!(">={}", self.value),
+            FilterOp::Contains => format!("contains:{}", self.value),
+            FilterOp::Regex => format!("re:{}", self.value),
+            FilterOp::IsNull => "=NULL".to_string(),
+            FilterOp::NotNull => "!=NULL".to_string(),
+        }
+    }
+
+    /// SQL condition for a WHERE clause. Numbers are left unquoted so numeric columns
+    /// compare numerically; `!=` keeps NULLs, like the in-memory filter.
+    pub fn sql_condition(&self, flavor: SqlFlavor) -> String {
+        let column = quote_identifier(&self.column);
+        let literal = if self.value.parse::<f64>().is_ok() {
+            self.value.clone()
+        } else {
+            sql_string(&self.value)
+        };
+        match self.op {
+            FilterOp::Eq => format!("{} = {}", column, literal),
+            FilterOp::Ne => format!("{} IS DISTINCT FROM {}", column, literal),
+            FilterOp::Lt => format!("{} < {}", column, literal),
+            FilterOp::Le => format!("{} <= {}", column, literal),
+            FilterOp::Gt => format!("{} > {}", column, literal),
+            FilterOp::Ge => format!("{} >= {}", column, literal),
+            FilterOp::Contains => {
+                let pattern = self
+                    .value
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                format!("{} LIKE {} ESCAPE '\\'", column, sql_string(&format!("%{}%", pattern)))
+            }
+            FilterOp::Regex => {
+                let pattern = sql_string(&self.value);
+                match flavor {
+                    SqlFlavor::Sqlite => format!("{} REGEXP {}", column, pattern),
+                    SqlFlavor::Postgres => format!("{}::text ~ {}", column, pattern),
+                    #[cfg(feature = "duckdb")]
+                    SqlFlavor::DuckDb => format!("regexp_matches(CAST({} AS VARCHAR), {})", column, pattern),
+                }
+            }
+            FilterOp::IsNull => format!("{} IS NULL", column),
+            FilterOp::NotNull => format!("{} IS NOT NULL", column),
+        }
+    }
+
+    /// The compiled pattern of a `re:` filter
+    fn regex(&self) -> Option<Regex> {
+        (self.op == FilterOp::Regex).then(|| Regex::new(&self.value).ok()).flatten()
+    }
+
+    /// Whether a cell of an in-memory table passes. Values compare as numbers when both
+    /// sides are numbers in `locale`, otherwise as text; empty cells count as NULL. `regex`
+    /// is the filter's pattern, compiled once for all rows.
+    fn matches(&self, cell: &str, locale: NumberLocale, regex: Option<&Regex>) -> bool {
+        let value = cell.trim();
+        let is_null = value.is_empty() || value == "NULL";
+        let ordering = || match (parse_number(value, locale), parse_number(&self.value, locale)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => Some(value.cmp(self.value.as_str())),
+        };
+
+        match self.op {
+            FilterOp::IsNull => is_null,
+            FilterOp::NotNull => !is_null,
+            FilterOp::Contains => value.to_lowercase().contains(&self.value.to_lowercase()),
+            FilterOp::Regex => regex.is_some_and(|regex| regex.is_match(cell)),
+            FilterOp::Ne => ordering() != Some(std::cmp::Ordering::Equal),
+            _ if is_null => false,
+            FilterOp::Eq => ordering() == Some(std::cmp::Ordering::Equal),
+            FilterOp::Lt => ordering() == Some(std::cmp::Ordering::Less),
+            FilterOp::Le => ordering().is_some_and(|o| o.is_le()),
+            FilterOp::Gt => ordering() == Some(std::cmp::Ordering::Greater),
+            FilterOp::Ge => ordering().is_some_and(|o| o.is_ge()),
+        }
+    }
+}
+
+/// How the database a WHERE clause is written for spells a regular expression match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SqlFlavor {
+    Sqlite, // REGEXP, backed by the function registered on every connection
+    Postgres,
+    #[cfg(feature = "duckdb")]
+    DuckDb,
+}
+
+/// What the table search (`/`) looks for: plain text ignoring case, or a regular
+/// expression after `re:`
+#[derive(Debug, Clone)]
+pub enum SearchPattern {
+    Text(String), // lowercased
+    Regex(Regex),
+}
+
+impl SearchPattern {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.strip_prefix("re:") {
+            Some(pattern) => Regex::new(pattern)
+                .map(SearchPattern::Regex)
+                .map_err(|e| anyhow::anyhow!("Invalid regular expression: {}", e)),
+            None => Ok(SearchPattern::Text(input.to_lowercase())),
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            SearchPattern::Text(text) => value.to_lowercase().contains(text),
+            SearchPattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// WHERE clause (with its leading space) combining all filters, empty without filters
+pub fn where_clause(filters: &[ColumnFilter], flavor: SqlFlavor) -> String {
+    if filters.is_empty() {
+        return String::new();
+    }
+    let conditions: Vec<String> = filters.iter().map(|f| f.sql_condition(flavor)).collect();
+    format!(" WHERE {}", conditions.join(" AND "))
+}
+
+/// Rows of an in-memory table that pass every filter. Filters on missing columns are
+/// ignored rather than hiding every row.
+pub fn filter_rows(data: &QueryResult, filters: &[ColumnFilter], locale: NumberLocale) -> QueryResult {
+    let indexed: Vec<(usize, &ColumnFilter, Option<Regex>)> = filters
+        .iter()
+        .filter_map(|f| data.columns.iter().position(|c| *c == f.column).map(|idx| (idx, f, f.regex())))
+        .collect();
+    let rows: Vec<Vec<Value>> = data
+        .rows
+        .iter()
+        .filter(|row| {
+            indexed.iter().all(|(idx, f, regex)| {
+                f.matches(&row.get(*idx).map(|v| v.as_text()).unwrap_or_default(), locale, regex.as_ref())
+            })
+        })
+        .cloned()
+        .collect();
+
+    QueryResult {
+        columns: data.columns.clone(),
+        total_rows: rows.len(),
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = ColumnFilter::parse("age", ">100").unwrap();
+        assert_eq!((filter.op, filter.value.as_str()), (FilterOp::Gt, "100"));
+        assert_eq!(ColumnFilter::parse("age", ">=5").unwrap().op, FilterOp::Ge);
+        assert_eq!(ColumnFilter::parse("name", "contains:foo").unwrap().op, FilterOp::Contains);
+        assert_eq!(ColumnFilter::parse("name", "=NULL").unwrap().op, FilterOp::IsNull);
+        assert_eq!(ColumnFilter::parse("name", "!= null").unwrap().op, FilterOp::NotNull);
+        assert_eq!(ColumnFilter::parse("name", "Alice").unwrap().op, FilterOp::Eq);
+        assert!(ColumnFilter::parse("name", "").is_err());
+        assert!(ColumnFilter::parse("name", ">").is_err());
+        assert!(ColumnFilter::parse("name", ">NULL").is_err());
+        assert_eq!(ColumnFilter::parse("name", "re:^A\\d+").unwrap().op, FilterOp::Regex);
+        assert!(ColumnFilter::parse("name", "re:(").is_err());
+        assert_eq!(ColumnFilter::parse("age", " <= 3 ").unwrap().expression(), "<=3");
+    }
+
+    #[test]
+    fn test_sql_condition() {
+        let sql = |input: &str| ColumnFilter::parse("my col", input).unwrap().sql_condition(SqlFlavor::Sqlite);
+        assert_eq!(sql(">100"), "\"my col\" > 100");
+        assert_eq!(sql("O'Brien"), "\"my col\" = 'O''Brien'");
+        assert_eq!(sql("!=x"), "\"my col\" IS DISTINCT FROM 'x'");
+        assert_eq!(sql("contains:50%"), "\"my col\" LIKE '%50\\%%' ESCAPE '\\'");
+        assert_eq!(sql("=NULL"), "\"my col\" IS NULL");
+        assert_eq!(sql("re:^a'b"), "\"my col\" REGEXP '^a''b'");
+        let regex = ColumnFilter::parse("n", "re:x").unwrap();
+        assert_eq!(regex.sql_condition(SqlFlavor::Postgres), "\"n\"::text ~ 'x'");
+    }
+
+    #[test]
+    fn test_filter_rows() {
+        let data = QueryResult {
+            columns: vec!["name".to_string(), "amount".to_string()],
+            rows: text_rows(vec![
+                vec!["Alice", "150"],
+                vec!["Bob", "99.5"],
+                vec!["Carol", ""],
+                vec!["alicia", "1000"],
+            ]),
+            total_rows: 4,
+        };
+        let names = |filters: &[ColumnFilter]| -> Vec<String> {
+            filter_rows(&data, filters, NumberLocale::En)
+                .rows
+                .into_iter()
+                .map(|r| r[0].to_string())
+                .collect()
+        };
+
+        let over_100 = ColumnFilter::parse("amount", ">100").unwrap();
+        assert_eq!(names(std::slice::from_ref(&over_100)), vec!["Alice", "alicia"]);
+        let ali = ColumnFilter::parse("name", "contains:ALI").unwrap();
+        assert_eq!(names(&[over_100, ali.clone()]), vec!["Alice", "alicia"]);
+        let missing = ColumnFilter::parse("amount", "=NULL").unwrap();
+        assert_eq!(names(&[missing]), vec!["Carol"]);
+        let not_bob = ColumnFilter::parse("name", "!=Bob").unwrap();
+        assert_eq!(names(&[not_bob]).len(), 3);
+        assert_eq!(filter_rows(&data, &[ali], NumberLocale::En).total_rows, 2);
+        let regex = ColumnFilter::parse("name", "re:^[A-C]").unwrap();
+        assert_eq!(names(&[regex]), vec!["Alice", "Bob", "Carol"]);
+    }
+
+    #[test]
+    fn test_search_pattern() {
+        let text = SearchPattern::parse("ALI").unwrap();
+        assert!(text.matches("alicia") && !text.matches("Bob"));
+        let regex = SearchPattern::parse("re:^\\d{3}$").unwrap();
+        assert!(regex.matches("150") && !regex.matches("99.5"));
+        assert!(SearchPattern::parse("re:[").is_err());
+    }
+}
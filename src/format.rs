@@ -0,0 +1,350 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::{FormatConfig, NumberLocale};
+use crate::value::Value;
+
+/// Format a computed number without losing precision (integers have no decimals)
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{:.0}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parse a number written with the separators of `locale`, e.g. "1.234,56" in `De`
+pub fn parse_number(value: &str, locale: NumberLocale) -> Option<f64> {
+    normalize_number(value, locale)?.parse().ok()
+}
+
+/// Like `parse_number`, for whole numbers only ("1.234" is 1234 in `De`)
+pub fn parse_integer(value: &str, locale: NumberLocale) -> Option<i64> {
+    normalize_number(value, locale)?.parse().ok()
+}
+
+// Rewrite a localized number as a Rust literal. Digit groups must have three digits,
+// so "1,5" is not a number in `En` and "1.5" is not one in `De`.
+fn normalize_number(value: &str, locale: NumberLocale) -> Option<String> {
+    let value = value.trim();
+    let (sign, unsigned) = match value.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (int_part, frac_part) = match unsigned.rsplit_once(locale.decimal_separator()) {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (unsigned, None),
+    };
+
+    let groups: Vec<&str> = int_part
+        .split(|c| locale.group_separators().contains(&c))
+        .collect();
+    if groups.len() > 1 {
+        let all_digits = |group: &str| group.chars().all(|c| c.is_ascii_digit());
+        let valid = (1..=3).contains(&groups[0].len())
+            && all_digits(groups[0])
+            && groups[1..].iter().all(|g| g.len() == 3 && all_digits(g));
+        if !valid {
+            return None;
+        }
+    }
+
+    let mut normalized = format!("{}{}", sign, groups.concat());
+    if let Some(frac) = frac_part {
+        normalized.push('.');
+        normalized.push_str(frac);
+    }
+    Some(normalized)
+}
+
+/// Format a typed cell for the table view: floats get the configured decimals, if any, and
+/// dates the configured formats; other values are formatted from their text like untyped cells
+pub fn format_value(value: &Value, format: &FormatConfig) -> String {
+    match value {
+        Value::Float(float) if float.is_finite() => {
+            let fixed = match format.decimal_places {
+                Some(places) => format!("{:.*}", places, float),
+                None => float.to_string(),
+            };
+            localize_number(&fixed, format)
+        }
+        Value::Date(dt) if value.is_date_only() => match &format.date_format {
+            Some(date_format) => dt.format(date_format).to_string(),
+            None => value.to_string(),
+        },
+        Value::Date(dt) => match &format.datetime_format {
+            Some(datetime_format) => dt.format(datetime_format).to_string(),
+            None => value.to_string(),
+        },
+        other => format_cell(&other.as_text(), format),
+    }
+}
+
+/// Format a cell value for display according to the configured number/date settings.
+/// Values that are neither numbers nor dates are returned unchanged.
+pub fn format_cell(value: &str, format: &FormatConfig) -> String {
+    let trimmed = value.trim();
+
+    if format.humanize {
+        if let Some(friendly) = humanize_value(trimmed) {
+            return friendly;
+        }
+    }
+
+    if is_integer_literal(trimmed) {
+        if let Ok(int) = trimmed.parse::<i64>() {
+            return group_thousands(&int.to_string(), &format.thousands_separator);
+        }
+    }
+
+    if is_decimal_literal(trimmed) {
+        if let Ok(float) = trimmed.parse::<f64>() {
+            if float.is_finite() {
+                let fixed = match format.decimal_places {
+                    Some(places) => format!("{:.*}", places, float),
+                    None => trimmed.to_string(),
+                };
+                return localize_number(&fixed, format);
+            }
+        }
+    }
+
+    if let Some(date_format) = &format.datetime_format {
+        for pattern in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S%.f"] {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(trimmed, pattern) {
+                return dt.format(date_format).to_string();
+            }
+        }
+    }
+
+    if let Some(date_format) = &format.date_format {
+        if let Ok(date) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+            return date.format(date_format).to_string();
+        }
+    }
+
+    value.to_string()
+}
+
+/// Cut `text` to at most `width` terminal columns, ending in "..." when it is cut. Wide
+/// characters (CJK, emoji) take two columns, and a cut never splits a character.
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let keep = width.saturating_sub(3);
+    let mut used = 0;
+    let mut cut: String = text
+        .chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= keep
+        })
+        .collect();
+    cut.push_str(&"..."[..width.min(3)]);
+    cut
+}
+
+// Epoch values between 2000-01-01 and 2100-01-01 are treated as timestamps
+const EPOCH_SECONDS_MIN: i64 = 946_684_800;
+const EPOCH_SECONDS_MAX: i64 = 4_102_444_800;
+
+/// Render likely Unix timestamps (seconds or milliseconds) as ISO datetimes and
+/// 16-byte blobs (`x'...'`) as canonical UUIDs
+pub fn humanize_value(value: &str) -> Option<String> {
+    if let Some(uuid) = blob_to_uuid(value) {
+        return Some(uuid);
+    }
+
+    if !is_integer_literal(value) {
+        return None;
+    }
+    let number: i64 = value.parse().ok()?;
+    if (EPOCH_SECONDS_MIN..EPOCH_SECONDS_MAX).contains(&number) {
+        let datetime = DateTime::from_timestamp(number, 0)?;
+        return Some(datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    }
+    if (EPOCH_SECONDS_MIN * 1000..EPOCH_SECONDS_MAX * 1000).contains(&number) {
+        let datetime = DateTime::from_timestamp_millis(number)?;
+        return Some(datetime.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+    }
+    None
+}
+
+fn blob_to_uuid(value: &str) -> Option<String> {
+    let hex = value
+        .strip_prefix("x'")
+        .or_else(|| value.strip_prefix("X'"))?
+        .strip_suffix('\'')?;
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let hex = hex.to_ascii_lowercase();
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+// Leading zeros usually mean an identifier (zip code, phone), not a number
+fn is_integer_literal(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && (digits.len() == 1 || !digits.starts_with('0'))
+}
+
+fn is_decimal_literal(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    match digits.split_once('.') {
+        Some((int_part, frac_part)) => {
+            !int_part.is_empty()
+                && !frac_part.is_empty()
+                && int_part.chars().all(|c| c.is_ascii_digit())
+                && frac_part.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+fn localize_number(fixed: &str, format: &FormatConfig) -> String {
+    let (int_part, frac_part) = match fixed.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (fixed, None),
+    };
+
+    let mut result = group_thousands(int_part, &format.thousands_separator);
+    if let Some(frac) = frac_part {
+        result.push_str(&format.decimal_separator);
+        result.push_str(frac);
+    }
+    result
+}
+
+fn group_thousands(int_part: &str, separator: &str) -> String {
+    if separator.is_empty() {
+        return int_part.to_string();
+    }
+
+    let (sign, digits) = match int_part.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", int_part),
+    };
+
+    let mut grouped = String::new();
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(ch);
+    }
+
+    format!("{}{}", sign, grouped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(-12.0), "-12");
+        assert_eq!(format_number(1.0 / 3.0), (1.0f64 / 3.0).to_string());
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("abcdefghij", 8), "abcde...");
+        // Two columns per character, and no cut through one
+        assert_eq!(truncate_to_width("日本語のテキスト", 8), "日本...");
+        assert_eq!(truncate_to_width("日本語のテキスト", 9), "日本語...");
+        assert_eq!(truncate_to_width("ééééé", 5), "ééééé");
+        assert_eq!(truncate_to_width("abcdef", 2), "..");
+    }
+
+    #[test]
+    fn test_format_cell_numbers() {
+        let format = FormatConfig {
+            decimal_places: Some(2),
+            thousands_separator: ",".to_string(),
+            decimal_separator: ".".to_string(),
+            ..FormatConfig::default()
+        };
+        assert_eq!(format_cell("1234567", &format), "1,234,567");
+        assert_eq!(format_cell("-1234.5", &format), "-1,234.50");
+        assert_eq!(format_cell("0.126", &format), "0.13");
+        assert_eq!(format_cell("007", &format), "007");
+        assert_eq!(format_cell("hello", &format), "hello");
+
+        let european = FormatConfig {
+            decimal_places: Some(1),
+            thousands_separator: ".".to_string(),
+            decimal_separator: ",".to_string(),
+            ..FormatConfig::default()
+        };
+        assert_eq!(format_cell("9876.54", &european), "9.876,5");
+
+        // Without decimal places set, numbers are shown as stored
+        let format = FormatConfig::default();
+        assert_eq!(format_cell("0.000123", &format), "0.000123");
+        assert_eq!(format_cell("12.3456", &format), "12.3456");
+        assert_eq!(format_cell("-1234.5", &format), "-1234.5");
+        assert_eq!(format_value(&Value::Float(0.000123), &format), "0.000123");
+        assert_eq!(format_value(&Value::Float(12.3456), &format), "12.3456");
+    }
+
+    #[test]
+    fn test_humanize_value() {
+        assert_eq!(humanize_value("1710491400").unwrap(), "2024-03-15T08:30:00Z");
+        assert_eq!(humanize_value("1710491400123").unwrap(), "2024-03-15T08:30:00.123Z");
+        assert_eq!(
+            humanize_value("x'550E8400E29B41D4A716446655440000'").unwrap(),
+            "550e8400-e29b-41d4-a716-446655440000"
+        );
+        assert!(humanize_value("42").is_none());
+        assert!(humanize_value("x'0102'").is_none());
+        assert!(humanize_value("hello").is_none());
+
+        let mut format = FormatConfig::default();
+        assert_eq!(format_cell("1710491400", &format), "1710491400");
+        format.humanize = true;
+        assert_eq!(format_cell("1710491400", &format), "2024-03-15T08:30:00Z");
+    }
+
+    #[test]
+    fn test_parse_localized_numbers() {
+        assert_eq!(parse_number("1,234.56", NumberLocale::En), Some(1234.56));
+        assert_eq!(parse_number("-1.5e3", NumberLocale::En), Some(-1500.0));
+        assert_eq!(parse_number("1.234,56", NumberLocale::De), Some(1234.56));
+        assert_eq!(parse_number("1 234,5", NumberLocale::Fr), Some(1234.5));
+        assert_eq!(parse_number("1\u{202f}234", NumberLocale::Fr), Some(1234.0));
+        assert_eq!(parse_number("1'234.5", NumberLocale::Ch), Some(1234.5));
+        assert_eq!(parse_number("0,5", NumberLocale::De), Some(0.5));
+        assert_eq!(parse_number("1,5", NumberLocale::En), None);
+        assert_eq!(parse_number("1.5", NumberLocale::De), None);
+        assert_eq!(parse_number("12.34.5", NumberLocale::De), None);
+        assert_eq!(parse_number("abc", NumberLocale::En), None);
+
+        assert_eq!(parse_integer("1.234", NumberLocale::De), Some(1234));
+        assert_eq!(parse_integer("1,234", NumberLocale::De), None);
+        assert_eq!(parse_integer("1,234", NumberLocale::En), Some(1234));
+    }
+
+    #[test]
+    fn test_format_cell_dates() {
+        let format = FormatConfig {
+            date_format: Some("%d/%m/%Y".to_string()),
+            datetime_format: Some("%d/%m/%Y %H:%M".to_string()),
+            ..FormatConfig::default()
+        };
+        assert_eq!(format_cell("2024-03-15", &format), "15/03/2024");
+        assert_eq!(format_cell("2024-03-15 08:30:00", &format), "15/03/2024 08:30");
+        assert_eq!(format_cell("2024-13-45", &format), "2024-13-45");
+    }
+}
@@ -0,0 +1,412 @@
+use serde_json::Value;
+
+const GEOMETRY_TYPES: [&str; 7] = [
+    "Point",
+    "LineString",
+    "Polygon",
+    "MultiPoint",
+    "MultiLineString",
+    "MultiPolygon",
+    "GeometryCollection",
+];
+
+/// A run of coordinates: a line or ring when `connected`, otherwise loose points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub points: Vec<(f64, f64)>,
+    pub connected: bool,
+}
+
+/// What a WKT or GeoJSON cell contains, for the preview in the detailed view
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoSummary {
+    pub geometry_type: String,
+    pub shapes: Vec<Shape>,
+}
+
+impl GeoSummary {
+    pub fn point_count(&self) -> usize {
+        self.shapes.iter().map(|shape| shape.points.len()).sum()
+    }
+
+    /// (min_x, min_y, max_x, max_y), or None for empty geometries
+    pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let mut points = self.shapes.iter().flat_map(|shape| shape.points.iter());
+        let &(x, y) = points.next()?;
+        Some(points.fold((x, y, x, y), |(min_x, min_y, max_x, max_y), &(x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        }))
+    }
+}
+
+/// Recognize a WKT (optionally with an EWKT `SRID=...;` prefix) or GeoJSON value
+pub fn parse_geometry(value: &str) -> Option<GeoSummary> {
+    let value = value.trim();
+    if value.starts_with('{') {
+        let json: Value = serde_json::from_str(value).ok()?;
+        let mut shapes = Vec::new();
+        let geometry_type = geojson_shapes(&json, &mut shapes)?;
+        Some(GeoSummary { geometry_type, shapes })
+    } else {
+        parse_wkt(value)
+    }
+}
+
+/// Plot the geometry onto a `width` x `height` character grid, north up.
+/// Lines and rings are drawn with '#', loose points with 'o'.
+pub fn ascii_map(summary: &GeoSummary, width: usize, height: usize) -> Vec<String> {
+    let mut grid = vec![vec!['.'; width]; height];
+    let Some((min_x, min_y, max_x, max_y)) = summary.bounds() else {
+        return Vec::new();
+    };
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    // Degenerate extents (a single point, a horizontal line) are drawn in the middle
+    let to_cell = |x: f64, y: f64| -> (f64, f64) {
+        let col = if max_x > min_x {
+            (x - min_x) / (max_x - min_x) * (width - 1) as f64
+        } else {
+            (width - 1) as f64 / 2.0
+        };
+        let row = if max_y > min_y {
+            (max_y - y) / (max_y - min_y) * (height - 1) as f64
+        } else {
+            (height - 1) as f64 / 2.0
+        };
+        (col, row)
+    };
+    let mut plot = |col: f64, row: f64, mark: char| {
+        let (col, row) = (col.round() as usize, row.round() as usize);
+        if let Some(cell) = grid.get_mut(row).and_then(|r| r.get_mut(col)) {
+            // Points stay visible on top of lines
+            if *cell != 'o' {
+                *cell = mark;
+            }
+        }
+    };
+
+    for shape in &summary.shapes {
+        if !shape.connected || shape.points.len() == 1 {
+            for &(x, y) in &shape.points {
+                let (col, row) = to_cell(x, y);
+                plot(col, row, 'o');
+            }
+            continue;
+        }
+        for segment in shape.points.windows(2) {
+            let (x0, y0) = to_cell(segment[0].0, segment[0].1);
+            let (x1, y1) = to_cell(segment[1].0, segment[1].1);
+            let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f64 / steps as f64;
+                plot(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t, '#');
+            }
+        }
+    }
+
+    grid.into_iter().map(|row| row.into_iter().collect()).collect()
+}
+
+fn canonical_type(name: &str) -> Option<&'static str> {
+    GEOMETRY_TYPES
+        .iter()
+        .copied()
+        .find(|t| t.eq_ignore_ascii_case(name))
+}
+
+// GeoJSON: geometries, Features and FeatureCollections
+
+fn geojson_shapes(json: &Value, shapes: &mut Vec<Shape>) -> Option<String> {
+    let kind = json.get("type")?.as_str()?;
+    match kind {
+        "FeatureCollection" => {
+            let features = json.get("features")?.as_array()?;
+            for feature in features {
+                geojson_shapes(feature, shapes)?;
+            }
+            Some(format!("FeatureCollection ({} features)", features.len()))
+        }
+        "Feature" => geojson_shapes(json.get("geometry")?, shapes),
+        "GeometryCollection" => {
+            for geometry in json.get("geometries")?.as_array()? {
+                geojson_shapes(geometry, shapes)?;
+            }
+            Some(kind.to_string())
+        }
+        _ => {
+            let kind = GEOMETRY_TYPES.iter().find(|t| **t == kind)?;
+            let connected = !matches!(*kind, "Point" | "MultiPoint");
+            geojson_coordinates(json.get("coordinates")?, connected, shapes)?;
+            Some(kind.to_string())
+        }
+    }
+}
+
+fn geojson_coordinates(value: &Value, connected: bool, shapes: &mut Vec<Shape>) -> Option<()> {
+    if let Some(position) = geojson_position(value) {
+        shapes.push(Shape {
+            points: vec![position],
+            connected: false,
+        });
+        return Some(());
+    }
+
+    let items = value.as_array()?;
+    if items.first().is_some_and(|item| geojson_position(item).is_some()) {
+        let points = items.iter().map(geojson_position).collect::<Option<Vec<_>>>()?;
+        shapes.push(Shape { points, connected });
+        return Some(());
+    }
+    for item in items {
+        geojson_coordinates(item, connected, shapes)?;
+    }
+    Some(())
+}
+
+fn geojson_position(value: &Value) -> Option<(f64, f64)> {
+    let items = value.as_array()?;
+    if items.len() < 2 {
+        return None;
+    }
+    Some((items[0].as_f64()?, items[1].as_f64()?))
+}
+
+// WKT
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word.to_ascii_uppercase()));
+            }
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let mut number = String::new();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+                {
+                    number.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Number(number.parse().ok()?));
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+fn parse_wkt(text: &str) -> Option<GeoSummary> {
+    // EWKT as written by PostGIS: "SRID=4326;POINT(1 2)"
+    let text = match text.split_once(';') {
+        Some((srid, rest)) if srid.trim().to_ascii_uppercase().starts_with("SRID=") => rest,
+        _ => text,
+    };
+
+    let mut parser = WktParser {
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    let mut shapes = Vec::new();
+    let geometry_type = parser.geometry(&mut shapes)?;
+    (parser.pos == parser.tokens.len()).then_some(GeoSummary {
+        geometry_type,
+        shapes,
+    })
+}
+
+struct WktParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl WktParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        (self.next()? == token).then_some(())
+    }
+
+    fn skip_word(&mut self, words: &[&str]) -> bool {
+        if let Some(Token::Word(word)) = self.peek() {
+            if words.contains(&word.as_str()) {
+                self.pos += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn geometry(&mut self, shapes: &mut Vec<Shape>) -> Option<String> {
+        let Token::Word(name) = self.next()? else {
+            return None;
+        };
+        let kind = canonical_type(&name)?;
+        self.skip_word(&["Z", "M", "ZM"]);
+        if self.skip_word(&["EMPTY"]) {
+            return Some(kind.to_string());
+        }
+
+        if kind == "GeometryCollection" {
+            self.expect(Token::Open)?;
+            loop {
+                self.geometry(shapes)?;
+                match self.next()? {
+                    Token::Comma => continue,
+                    Token::Close => break,
+                    _ => return None,
+                }
+            }
+        } else {
+            let connected = !matches!(kind, "Point" | "MultiPoint");
+            self.coordinates(shapes, connected)?;
+        }
+        Some(kind.to_string())
+    }
+
+    fn coordinates(&mut self, shapes: &mut Vec<Shape>, connected: bool) -> Option<()> {
+        self.expect(Token::Open)?;
+
+        if self.peek() == Some(&Token::Open) {
+            loop {
+                self.coordinates(shapes, connected)?;
+                match self.next()? {
+                    Token::Comma => continue,
+                    Token::Close => return Some(()),
+                    _ => return None,
+                }
+            }
+        }
+
+        let mut points = Vec::new();
+        loop {
+            let (Token::Number(x), Token::Number(y)) = (self.next()?, self.next()?) else {
+                return None;
+            };
+            // Z and M values are not needed for the preview
+            while matches!(self.peek(), Some(Token::Number(_))) {
+                self.pos += 1;
+            }
+            points.push((x, y));
+            match self.next()? {
+                Token::Comma => continue,
+                Token::Close => break,
+                _ => return None,
+            }
+        }
+        shapes.push(Shape { points, connected });
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wkt() {
+        let point = parse_geometry("POINT (30 10)").unwrap();
+        assert_eq!(point.geometry_type, "Point");
+        assert_eq!(point.bounds(), Some((30.0, 10.0, 30.0, 10.0)));
+
+        let polygon =
+            parse_geometry("SRID=4326;POLYGON Z ((0 0 1, 4 0 1, 4 3 1, 0 3 1, 0 0 1), (1 1 0, 2 1 0, 1 2 0, 1 1 0))")
+                .unwrap();
+        assert_eq!(polygon.geometry_type, "Polygon");
+        assert_eq!(polygon.shapes.len(), 2);
+        assert_eq!(polygon.point_count(), 9);
+        assert_eq!(polygon.bounds(), Some((0.0, 0.0, 4.0, 3.0)));
+
+        let collection =
+            parse_geometry("GEOMETRYCOLLECTION (POINT (4 6), LINESTRING (4 6, 7 10))").unwrap();
+        assert_eq!(collection.geometry_type, "GeometryCollection");
+        assert_eq!(collection.point_count(), 3);
+
+        let multipoint = parse_geometry("multipoint ((10 40), (40 30))").unwrap();
+        assert!(multipoint.shapes.iter().all(|s| !s.connected));
+        assert_eq!(parse_geometry("POINT EMPTY").unwrap().bounds(), None);
+
+        assert!(parse_geometry("POINT (1)").is_none());
+        assert!(parse_geometry("Point of no return").is_none());
+        assert!(parse_geometry("hello (1 2)").is_none());
+        assert!(parse_geometry("42").is_none());
+    }
+
+    #[test]
+    fn test_parse_geojson() {
+        let line = parse_geometry(r#"{"type": "LineString", "coordinates": [[0, 0], [2, 1.5]]}"#).unwrap();
+        assert_eq!(line.geometry_type, "LineString");
+        assert_eq!(line.bounds(), Some((0.0, 0.0, 2.0, 1.5)));
+
+        let collection = parse_geometry(
+            r#"{"type": "FeatureCollection", "features": [
+                {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+                {"type": "Feature", "geometry": {"type": "MultiPolygon",
+                    "coordinates": [[[[0, 0], [1, 0], [1, 1], [0, 0]]], [[[5, 5], [6, 5], [6, 6], [5, 5]]]]}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(collection.geometry_type, "FeatureCollection (2 features)");
+        assert_eq!(collection.point_count(), 9);
+        assert_eq!(collection.bounds(), Some((0.0, 0.0, 6.0, 6.0)));
+
+        assert!(parse_geometry(r#"{"type": "Person", "coordinates": [1, 2]}"#).is_none());
+        assert!(parse_geometry(r#"{"name": "not geo"}"#).is_none());
+    }
+
+    #[test]
+    fn test_ascii_map() {
+        let square = parse_geometry("POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0))").unwrap();
+        assert_eq!(
+            ascii_map(&square, 5, 3),
+            vec!["#####", "#...#", "#####"]
+        );
+
+        let points = parse_geometry("MULTIPOINT (0 0, 10 10)").unwrap();
+        assert_eq!(ascii_map(&points, 3, 2), vec!["..o", "o.."]);
+
+        let single = parse_geometry("POINT (3 3)").unwrap();
+        assert_eq!(ascii_map(&single, 3, 3), vec!["...", ".o.", "..."]);
+    }
+}
@@ -0,0 +1,442 @@
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::Path;
+
+use crate::database::QueryResult;
+use crate::file_reader::{decode_text, read_input_bytes};
+use crate::value::text_rows;
+
+/// Whether the command-line argument is a web page address rather than a file
+pub fn is_web_url(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Read every `<table>` of an HTML file or web page, named by its caption or id
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_html_tables<P: AsRef<Path>>(path: P) -> Result<Vec<(String, QueryResult)>> {
+    let path = path.as_ref();
+    let html = if is_web_url(path) {
+        let mut bytes = Vec::new();
+        ureq::get(&path.to_string_lossy())
+            .call()
+            .with_context(|| format!("Failed to fetch {}", path.display()))?
+            .into_reader()
+            .read_to_end(&mut bytes)?;
+        decode_text(&bytes)
+    } else {
+        decode_text(&read_input_bytes(path)?)
+    };
+    let tables = parse_html_tables(&html);
+    if tables.is_empty() {
+        return Err(anyhow::anyhow!("No <table> found in {}", path.display()));
+    }
+    Ok(tables)
+}
+
+struct Cell {
+    text: String,
+    header: bool,
+    colspan: usize,
+    rowspan: usize,
+}
+
+struct Row {
+    cells: Vec<Cell>,
+    in_head: bool,
+}
+
+/// A table being read; tables nested in a cell get their own
+#[derive(Default)]
+struct TableBuilder {
+    caption: Option<String>,
+    id: Option<String>,
+    in_caption: bool,
+    in_head: bool,
+    rows: Vec<Row>,
+    row: Option<Vec<Cell>>,
+    cell: Option<Cell>,
+}
+
+impl TableBuilder {
+    fn close_cell(&mut self) {
+        if let Some(mut cell) = self.cell.take() {
+            cell.text = cell.text.trim().to_string();
+            self.row.get_or_insert_with(Vec::new).push(cell);
+        }
+    }
+
+    fn close_row(&mut self) {
+        self.close_cell();
+        if let Some(cells) = self.row.take() {
+            self.rows.push(Row { cells, in_head: self.in_head });
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(cell) = &mut self.cell {
+            push_collapsed(&mut cell.text, text);
+        } else if self.in_caption {
+            push_collapsed(self.caption.get_or_insert_with(String::new), text);
+        }
+    }
+
+    /// Lay the cells out on a grid, repeating spanned cells into every slot they cover.
+    /// Rows of `<thead>`, or a leading row of `<th>` cells, name the columns.
+    fn finish(mut self) -> Option<QueryResult> {
+        self.close_row();
+        let mut grid: Vec<Vec<Option<String>>> = Vec::new();
+        for (row_idx, row) in self.rows.iter().enumerate() {
+            if grid.len() <= row_idx {
+                grid.resize(row_idx + 1, Vec::new());
+            }
+            let mut col = 0;
+            for cell in &row.cells {
+                while grid[row_idx].get(col).is_some_and(|slot| slot.is_some()) {
+                    col += 1;
+                }
+                for r in row_idx..row_idx + cell.rowspan {
+                    if grid.len() <= r {
+                        grid.resize(r + 1, Vec::new());
+                    }
+                    for c in col..col + cell.colspan {
+                        if grid[r].len() <= c {
+                            grid[r].resize(c + 1, None);
+                        }
+                        grid[r][c] = Some(cell.text.clone());
+                    }
+                }
+                col += cell.colspan;
+            }
+        }
+        let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+        if width == 0 {
+            return None;
+        }
+        let mut rows: Vec<Vec<String>> = grid
+            .into_iter()
+            .map(|row| {
+                let mut row: Vec<String> = row.into_iter().map(Option::unwrap_or_default).collect();
+                row.resize(width, String::new());
+                row
+            })
+            .collect();
+
+        let head_rows = self.rows.iter().take_while(|row| row.in_head).count();
+        let head_rows = if head_rows > 0 {
+            head_rows
+        } else {
+            usize::from(self.rows.first().is_some_and(|row| row.cells.iter().all(|cell| cell.header)))
+        };
+        let head: Vec<Vec<String>> = rows.drain(..head_rows.min(rows.len())).collect();
+        let columns = (0..width)
+            .map(|c| {
+                // Stacked header rows are joined ("2023 Q1"), skipping repeats of a spanned cell
+                let mut parts: Vec<&str> = Vec::new();
+                for row in &head {
+                    let part = row[c].as_str();
+                    if !part.is_empty() && parts.last() != Some(&part) {
+                        parts.push(part);
+                    }
+                }
+                if parts.is_empty() {
+                    format!("Column {}", c + 1)
+                } else {
+                    parts.join(" ")
+                }
+            })
+            .collect();
+
+        Some(QueryResult {
+            columns,
+            total_rows: rows.len(),
+            rows: text_rows(rows),
+        })
+    }
+}
+
+/// Extract the tables of an HTML document, in document order. Tables are named by their
+/// `<caption>`, else their `id`, else "Table N"; repeated names get a " (2)" suffix.
+pub fn parse_html_tables(html: &str) -> Vec<(String, QueryResult)> {
+    let mut open: Vec<(usize, TableBuilder)> = Vec::new();
+    let mut finished: Vec<(usize, String, QueryResult)> = Vec::new();
+    let mut started = 0;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            if let Some((_, table)) = open.last_mut() {
+                table.push_text(&decode_entities(rest));
+            }
+            break;
+        };
+        if lt > 0 {
+            if let Some((_, table)) = open.last_mut() {
+                table.push_text(&decode_entities(&rest[..lt]));
+            }
+        }
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/');
+        let name_end = tag.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        let attributes = &tag[name_end..];
+
+        // Script and style contents are not text
+        if !closing && (name == "script" || name == "style") {
+            let end = format!("</{}", name);
+            rest = find_ignore_case(rest, &end).map_or("", |at| &rest[at..]);
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("table", false) => {
+                started += 1;
+                let table = TableBuilder {
+                    id: attribute(attributes, "id"),
+                    ..TableBuilder::default()
+                };
+                open.push((started, table));
+            }
+            ("table", true) => {
+                if let Some((order, table)) = open.pop() {
+                    let name = table
+                        .caption
+                        .clone()
+                        .map(|caption| caption.trim().to_string())
+                        .filter(|caption| !caption.is_empty())
+                        .or_else(|| table.id.clone())
+                        .unwrap_or_else(|| format!("Table {}", order));
+                    if let Some(data) = table.finish() {
+                        finished.push((order, name, data));
+                    }
+                }
+            }
+            _ => {
+                let Some((_, table)) = open.last_mut() else {
+                    continue;
+                };
+                match (name.as_str(), closing) {
+                    ("caption", false) => table.in_caption = true,
+                    ("caption", true) => table.in_caption = false,
+                    ("thead", false) => {
+                        table.close_row();
+                        table.in_head = true;
+                    }
+                    ("thead", true) | ("tbody", _) | ("tfoot", _) => {
+                        table.close_row();
+                        table.in_head = false;
+                    }
+                    ("tr", false) => {
+                        table.close_row();
+                        table.row = Some(Vec::new());
+                    }
+                    ("tr", true) => table.close_row(),
+                    ("td", false) | ("th", false) => {
+                        table.close_cell();
+                        let span = |name| {
+                            attribute(attributes, name)
+                                .and_then(|value| value.parse::<usize>().ok())
+                                .unwrap_or(1)
+                                .clamp(1, 1000)
+                        };
+                        table.cell = Some(Cell {
+                            text: String::new(),
+                            header: name == "th",
+                            colspan: span("colspan"),
+                            rowspan: span("rowspan"),
+                        });
+                    }
+                    ("td", true) | ("th", true) => table.close_cell(),
+                    ("br", _) | ("p", _) | ("div", _) | ("li", _) => table.push_text(" "),
+                    _ => {}
+                }
+            }
+        }
+    }
+    // An unclosed table at the end of the document still counts
+    while let Some((order, table)) = open.pop() {
+        let name = table.id.clone().unwrap_or_else(|| format!("Table {}", order));
+        if let Some(data) = table.finish() {
+            finished.push((order, name, data));
+        }
+    }
+
+    finished.sort_by_key(|(order, _, _)| *order);
+    let mut tables: Vec<(String, QueryResult)> = Vec::new();
+    for (_, name, data) in finished {
+        let mut unique = name.clone();
+        let mut n = 2;
+        while tables.iter().any(|(existing, _)| *existing == unique) {
+            unique = format!("{} ({})", name, n);
+            n += 1;
+        }
+        tables.push((unique, data));
+    }
+    tables
+}
+
+/// Append text with runs of whitespace collapsed to one space
+fn push_collapsed(target: &mut String, text: &str) {
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !target.is_empty() && !target.ends_with(' ') {
+                target.push(' ');
+            }
+        } else {
+            target.push(c);
+        }
+    }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Value of a tag attribute, quoted or not
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(at) = find_ignore_case(rest, name) {
+        let before = rest[..at].chars().last();
+        let after = rest[at + name.len()..].trim_start();
+        rest = &rest[at + name.len()..];
+        if before.is_some_and(|c| !c.is_whitespace()) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Replace the character references that show up in table text
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let reference = rest[1..].find(';').filter(|&end| end <= 10).map(|end| &rest[1..end + 1]);
+        let character = reference.and_then(|reference| match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = reference.strip_prefix('#')?;
+                let code = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (reference, character) {
+            (Some(reference), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_html_tables() {
+        let html = r#"<html><head><style>td { color: red }</style></head><body>
+            <table id="sales">
+              <caption>Quarterly <b>sales</b></caption>
+              <thead><tr><th>Region</th><th colspan="2">2023</th></tr>
+                     <tr><th></th><th>Q1</th><th>Q2</th></tr></thead>
+              <tbody>
+                <tr><td rowspan=2>North</td><td>1,200</td><td>1&nbsp;300</td></tr>
+                <tr><td>900<td>&lt;1000&gt;
+              </tbody>
+            </table>
+            <!-- <table><tr><td>commented out</td></tr></table> -->
+            <table id='people'><tr><td>Alice &amp; Bob</td><td>Paris<br>France</td></tr></table>
+            <table><tr><th>x</th></tr><tr><td>1</td></tr></table>
+            <table><tr><th>y</th></tr></table>
+        </body></html>"#;
+
+        let tables = parse_html_tables(html);
+        let names: Vec<&str> = tables.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Quarterly sales", "people", "Table 3", "Table 4"]);
+
+        let sales = &tables[0].1;
+        assert_eq!(sales.columns, vec!["Region", "2023 Q1", "2023 Q2"]);
+        assert_eq!(
+            sales.rows,
+            vec![vec!["North", "1,200", "1 300"], vec!["North", "900", "<1000>"]]
+        );
+
+        let people = &tables[1].1;
+        assert_eq!(people.columns, vec!["Column 1", "Column 2"]);
+        assert_eq!(people.rows, vec![vec!["Alice & Bob", "Paris France"]]);
+
+        assert_eq!(tables[2].1.columns, vec!["x"]);
+        assert_eq!(tables[2].1.rows, vec![vec!["1"]]);
+        assert_eq!(tables[3].1.total_rows, 0);
+    }
+
+    #[test]
+    fn test_nested_tables_and_duplicate_names() {
+        let html = "<table id=t><tr><td>outer<table id=t><tr><td>inner</td></tr></table></td></tr></table>";
+        let tables = parse_html_tables(html);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].0, "t");
+        assert_eq!(tables[0].1.rows, vec![vec!["outer"]]);
+        assert_eq!(tables[1].0, "t (2)");
+        assert_eq!(tables[1].1.rows, vec![vec!["inner"]]);
+    }
+
+    #[test]
+    fn test_read_html_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.html");
+        std::fs::write(&path, "<table><tr><th>a</th><th>b</th></tr><tr><td>1</td><td>2</td></tr></table>").unwrap();
+        let tables = read_html_tables(&path).unwrap();
+        assert_eq!(tables[0].1.columns, vec!["a", "b"]);
+
+        std::fs::write(&path, "<p>no tables here</p>").unwrap();
+        assert!(read_html_tables(&path).is_err());
+
+        assert!(is_web_url(Path::new("https://example.com/report")));
+        assert!(!is_web_url(Path::new("report.html")));
+    }
+}
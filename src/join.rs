@@ -0,0 +1,193 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::database::QueryResult;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner, // only rows with a match
+    Left,  // every row of the current table, NULL where nothing matches
+}
+
+impl JoinKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            JoinKind::Inner => "inner",
+            JoinKind::Left => "left",
+        }
+    }
+}
+
+/// A join of the current table with a table of another file, typed at the `J` prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinSpec {
+    pub path: PathBuf,
+    pub table: Option<String>, // defaults to the table named like the current one, or the first
+    pub key: String,           // column of the current table
+    pub other_key: String,     // column of the other table
+    pub kind: JoinKind,
+}
+
+impl JoinSpec {
+    /// Parse `file key[=other_key] [left|inner] [table]`; the join is a left join unless
+    /// `inner` is given
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parts = input.split_whitespace();
+        let path = parts.next().ok_or_else(|| anyhow::anyhow!("Missing the file to join with"))?;
+        let keys = parts.next().ok_or_else(|| anyhow::anyhow!("Missing the key column to join on"))?;
+        let (key, other_key) = keys.split_once('=').unwrap_or((keys, keys));
+        if key.is_empty() || other_key.is_empty() {
+            return Err(anyhow::anyhow!("Missing a key column in '{}'", keys));
+        }
+
+        let mut kind = JoinKind::Left;
+        let mut table = None;
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "left" => kind = JoinKind::Left,
+                "inner" => kind = JoinKind::Inner,
+                _ if table.is_none() => table = Some(part.to_string()),
+                _ => return Err(anyhow::anyhow!("Unexpected '{}' after the table name", part)),
+            }
+        }
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            table,
+            key: key.to_string(),
+            other_key: other_key.to_string(),
+            kind,
+        })
+    }
+}
+
+/// Join `left` with `right` on the key columns, as SQL would: a row for every pair of rows
+/// with equal keys, and for a left join the unmatched rows of `left` with NULLs. Keys are
+/// compared as shown, so a CSV's "1" matches a database's integer 1; NULL keys match nothing.
+/// The right table's key column is left out, and its columns named like one of `left` get
+/// `right_name` as a prefix.
+pub fn join_tables(
+    left: &QueryResult,
+    right: &QueryResult,
+    key: &str,
+    other_key: &str,
+    kind: JoinKind,
+    right_name: &str,
+) -> Result<QueryResult> {
+    let position = |data: &QueryResult, column: &str, side: &str| {
+        data.columns
+            .iter()
+            .position(|c| c == column)
+            .ok_or_else(|| anyhow::anyhow!("Key column '{}' not found in the {} table", column, side))
+    };
+    let left_key = position(left, key, "current")?;
+    let right_key = position(right, other_key, "other")?;
+
+    // SQLite's implicit rowid means nothing outside its own table
+    let left_columns: Vec<usize> = (0..left.columns.len()).filter(|&idx| left.columns[idx] != "rowid").collect();
+    let right_columns: Vec<usize> = (0..right.columns.len())
+        .filter(|&idx| idx != right_key && right.columns[idx] != "rowid")
+        .collect();
+
+    let mut columns: Vec<String> = left_columns.iter().map(|&idx| left.columns[idx].clone()).collect();
+    for &idx in &right_columns {
+        let column = &right.columns[idx];
+        columns.push(if columns.contains(column) {
+            format!("{}.{}", right_name, column)
+        } else {
+            column.clone()
+        });
+    }
+
+    let mut matches: HashMap<String, Vec<&Vec<Value>>> = HashMap::new();
+    for row in &right.rows {
+        if let Some(key) = join_key(row.get(right_key)) {
+            matches.entry(key).or_default().push(row);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for row in &left.rows {
+        let left_values = || left_columns.iter().map(|&idx| row.get(idx).cloned().unwrap_or(Value::Null));
+        match join_key(row.get(left_key)).and_then(|key| matches.get(&key)) {
+            Some(others) => {
+                for other in others {
+                    let right_values = right_columns.iter().map(|&idx| other.get(idx).cloned().unwrap_or(Value::Null));
+                    rows.push(left_values().chain(right_values).collect());
+                }
+            }
+            None if kind == JoinKind::Left => {
+                rows.push(left_values().chain(right_columns.iter().map(|_| Value::Null)).collect());
+            }
+            None => {}
+        }
+    }
+
+    Ok(QueryResult { columns, total_rows: rows.len(), rows })
+}
+
+fn join_key(cell: Option<&Value>) -> Option<String> {
+    match cell {
+        None | Some(Value::Null) => None,
+        Some(cell) => Some(cell.to_string()).filter(|key| !key.is_empty() && key != "NULL"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    fn table(columns: &[&str], rows: Vec<Vec<&str>>) -> QueryResult {
+        QueryResult {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            total_rows: rows.len(),
+            rows: text_rows(rows),
+        }
+    }
+
+    #[test]
+    fn test_parse_join_spec() {
+        let spec = JoinSpec::parse("customers.csv customer_id=id").unwrap();
+        assert_eq!((spec.key.as_str(), spec.other_key.as_str()), ("customer_id", "id"));
+        assert_eq!((spec.kind, spec.table), (JoinKind::Left, None));
+
+        let spec = JoinSpec::parse("shop.db id inner customers").unwrap();
+        assert_eq!((spec.key.as_str(), spec.other_key.as_str()), ("id", "id"));
+        assert_eq!((spec.kind, spec.table.as_deref()), (JoinKind::Inner, Some("customers")));
+
+        assert!(JoinSpec::parse("shop.db").is_err());
+        assert!(JoinSpec::parse("shop.db id= left").is_err());
+    }
+
+    #[test]
+    fn test_join_tables() {
+        let orders = table(
+            &["rowid", "id", "customer", "total"],
+            vec![vec!["1", "10", "1", "5"], vec!["2", "11", "2", "7"], vec!["3", "12", "", "9"]],
+        );
+        let customers = table(&["id", "name"], vec![vec!["1", "Ann"], vec!["1", "Annie"], vec!["3", "Cy"]]);
+
+        let joined = join_tables(&orders, &customers, "customer", "id", JoinKind::Left, "customers").unwrap();
+        assert_eq!(joined.columns, vec!["id", "customer", "total", "name"]);
+        assert_eq!(joined.rows.len(), 4);
+        assert_eq!(joined.rows[0], vec!["10", "1", "5", "Ann"]);
+        assert_eq!(joined.rows[1][3], "Annie");
+        assert_eq!(joined.rows[2][3], Value::Null);
+
+        let joined = join_tables(&orders, &customers, "customer", "id", JoinKind::Inner, "customers").unwrap();
+        assert_eq!(joined.rows.len(), 2);
+        assert!(join_tables(&orders, &customers, "missing", "id", JoinKind::Inner, "customers").is_err());
+    }
+
+    #[test]
+    fn test_join_prefixes_clashing_columns() {
+        let left = table(&["id", "name"], vec![vec!["1", "a"]]);
+        let right = table(&["key", "name"], vec![vec!["1", "b"]]);
+        let joined = join_tables(&left, &right, "id", "key", JoinKind::Inner, "other").unwrap();
+        assert_eq!(joined.columns, vec!["id", "name", "other.name"]);
+        assert_eq!(joined.rows[0], vec!["1", "a", "b"]);
+    }
+}
@@ -0,0 +1,101 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// The keys of the table list and data view: sqbrowser's own, or Vim's on top of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Vim,
+}
+
+/// What a key of the preset does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Key(KeyCode),       // the same as this key of the default keymap
+    HalfPage(bool),     // move the cursor half a page down (true) or up
+    EdgeColumn(bool),   // go to the last (true) or first shown column
+    Pending,            // the first key of a sequence such as `gg`, waiting for the next
+}
+
+/// Turns the keys of a preset into actions, remembering the first key of a sequence
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    pub preset: KeymapPreset,
+    pending_g: bool,
+}
+
+impl Keymap {
+    pub fn new(preset: KeymapPreset) -> Self {
+        Self { preset, pending_g: false }
+    }
+
+    /// The action of a key; None when it keeps its default meaning. In the Vim preset
+    /// `hjkl` move, `gg`/`G` go to the first/last page, `Ctrl+D`/`Ctrl+U` scroll half a page,
+    /// `0`/`$` go to the first/last column, `:` opens the query prompt, `?` the help and
+    /// `Ctrl+G` the goto prompt, as `g` does otherwise.
+    pub fn action(&mut self, key: KeyEvent) -> Option<Action> {
+        if self.preset == KeymapPreset::Default {
+            return None;
+        }
+        let pending_g = std::mem::take(&mut self.pending_g);
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.code {
+                KeyCode::Char('d') => Some(Action::HalfPage(true)),
+                KeyCode::Char('u') => Some(Action::HalfPage(false)),
+                KeyCode::Char('g') => Some(Action::Key(KeyCode::Char('g'))),
+                _ => None,
+            };
+        }
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        Some(match c {
+            'g' if pending_g => Action::Key(KeyCode::Home),
+            'g' => {
+                self.pending_g = true;
+                Action::Pending
+            }
+            'G' => Action::Key(KeyCode::End),
+            'h' => Action::Key(KeyCode::Left),
+            'j' => Action::Key(KeyCode::Down),
+            'k' => Action::Key(KeyCode::Up),
+            'l' => Action::Key(KeyCode::Right),
+            '0' => Action::EdgeColumn(false),
+            '$' => Action::EdgeColumn(true),
+            ':' => Action::Key(KeyCode::Char('i')),
+            '?' => Action::Key(KeyCode::Char('h')),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_vim_keymap() {
+        let mut keymap = Keymap::new(KeymapPreset::Vim);
+        assert_eq!(keymap.action(key('j')), Some(Action::Key(KeyCode::Down)));
+        assert_eq!(keymap.action(key('$')), Some(Action::EdgeColumn(true)));
+        assert_eq!(keymap.action(key('x')), None);
+        assert_eq!(keymap.action(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL)), Some(Action::HalfPage(true)));
+        assert_eq!(keymap.action(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)), None);
+        assert_eq!(keymap.action(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL)), Some(Action::Key(KeyCode::Char('g'))));
+
+        // `gg` needs both keys in a row
+        assert_eq!(keymap.action(key('g')), Some(Action::Pending));
+        assert_eq!(keymap.action(key('g')), Some(Action::Key(KeyCode::Home)));
+        assert_eq!(keymap.action(key('g')), Some(Action::Pending));
+        assert_eq!(keymap.action(key('j')), Some(Action::Key(KeyCode::Down)));
+        assert_eq!(keymap.action(key('g')), Some(Action::Pending));
+
+        assert_eq!(Keymap::new(KeymapPreset::Default).action(key('j')), None);
+    }
+}
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::database::{quote_identifier, QueryResult};
+use crate::filter::{where_clause, ColumnFilter, SqlFlavor};
+use crate::persistence::SortOrder;
+use crate::value::Value as Cell;
+
+/// Environment variables checked, in order, for the database's auth token
+const AUTH_TOKEN_VARS: [&str; 2] = ["LIBSQL_AUTH_TOKEN", "TURSO_AUTH_TOKEN"];
+
+/// Whether the command-line argument is a libSQL (e.g. Turso) database URL rather than a file
+pub fn is_libsql_url(path: &Path) -> bool {
+    path.to_string_lossy().starts_with("libsql://")
+}
+
+/// The HTTP endpoint behind a `libsql://` URL: HTTPS, except for a local `sqld`
+fn http_url(url: &str) -> String {
+    let rest = url.trim_start_matches("libsql://").trim_end_matches('/');
+    let host = rest.split([':', '/']).next().unwrap_or_default();
+    let scheme = if matches!(host, "localhost" | "127.0.0.1" | "[::1]") { "http" } else { "https" };
+    format!("{}://{}", scheme, rest)
+}
+
+/// A hosted SQLite database spoken to over libSQL's HTTP protocol (Hrana), browsed read-only
+pub struct LibsqlDatabase {
+    pipeline_url: String,
+    auth_token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl LibsqlDatabase {
+    pub fn connect(url: &str) -> Result<Self> {
+        let auth_token = AUTH_TOKEN_VARS.iter().find_map(|var| std::env::var(var).ok());
+        let db = Self {
+            pipeline_url: format!("{}/v2/pipeline", http_url(url)),
+            auth_token,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(30)).build(),
+        };
+        db.execute_query("SELECT 1")
+            .with_context(|| format!("Failed to connect to {}", url))?;
+        Ok(db)
+    }
+
+    pub fn get_tables(&self) -> Result<Vec<String>> {
+        let result = self.execute_query(
+            "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') \
+             AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '_litestream_%' ORDER BY name",
+        )?;
+        Ok(result.rows.into_iter().filter_map(|row| row.into_iter().next()).map(|name| name.to_string()).collect())
+    }
+
+    pub fn get_table_data(
+        &self,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+        sort: Option<&SortOrder>,
+        filters: &[ColumnFilter],
+    ) -> Result<QueryResult> {
+        let table = quote_identifier(table_name);
+        let where_clause = where_clause(filters, SqlFlavor::Sqlite);
+        let order_by = match sort {
+            Some(sort) => format!(
+                " ORDER BY {} {}",
+                quote_identifier(&sort.column),
+                if sort.descending { "DESC" } else { "ASC" }
+            ),
+            None => String::new(),
+        };
+
+        let query = format!(
+            "SELECT * FROM {}{}{} LIMIT {} OFFSET {}",
+            table, where_clause, order_by, limit, offset
+        );
+        let mut result = self.execute_query(&query)?;
+        result.total_rows = self.count(&format!("SELECT COUNT(*) FROM {}{}", table, where_clause))?;
+        Ok(result)
+    }
+
+    /// Run a query whose table alias has already been replaced, one page at a time
+    pub fn execute_custom_query(&self, query: &str, offset: usize, limit: usize) -> Result<QueryResult> {
+        let query = query.trim().trim_end_matches(';');
+        let mut result = self.execute_query(&format!("{} LIMIT {} OFFSET {}", query, limit, offset))?;
+        if let Ok(count) = self.count(&format!("SELECT COUNT(*) FROM ({})", query)) {
+            result.total_rows = count;
+        }
+        Ok(result)
+    }
+
+    fn count(&self, query: &str) -> Result<usize> {
+        let result = self.execute_query(query)?;
+        let count = result
+            .rows
+            .first()
+            .and_then(|row| row.first())
+            .ok_or_else(|| anyhow::anyhow!("COUNT returned no rows"))?;
+        Ok(count.as_text().parse()?)
+    }
+
+    /// Send one statement in its own pipeline, closing the stream behind it
+    fn execute_query(&self, query: &str) -> Result<QueryResult> {
+        let body = json!({
+            "requests": [
+                { "type": "execute", "stmt": { "sql": query } },
+                { "type": "close" },
+            ]
+        });
+        let mut request = self.agent.post(&self.pipeline_url);
+        if let Some(token) = &self.auth_token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response: Value = match request.send_json(body) {
+            Ok(response) => response.into_json()?,
+            Err(ureq::Error::Status(status, response)) => {
+                let message = response.into_string().unwrap_or_default();
+                return Err(anyhow::anyhow!("libSQL server returned {}: {}", status, message.trim()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        parse_execute_result(&response)
+    }
+}
+
+/// Columns and rows of the first result of a pipeline response. Integers arrive as strings
+/// and are typed back; blobs arrive as base64 and are kept as that text.
+fn parse_execute_result(response: &Value) -> Result<QueryResult> {
+    let first = &response["results"][0];
+    if first["type"] == "error" {
+        let message = first["error"]["message"].as_str().unwrap_or("unknown error");
+        return Err(anyhow::anyhow!("{}", message));
+    }
+    let result = &first["response"]["result"];
+
+    let columns: Vec<String> = result["cols"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Malformed libSQL response: no columns"))?
+        .iter()
+        .map(|col| col["name"].as_str().unwrap_or_default().to_string())
+        .collect();
+    let rows: Vec<Vec<Cell>> = result["rows"]
+        .as_array()
+        .map(|rows| {
+            rows.iter()
+                .map(|row| {
+                    row.as_array()
+                        .map(|values| values.iter().map(libsql_value).collect())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(QueryResult {
+        columns,
+        total_rows: rows.len(),
+        rows,
+    })
+}
+
+fn libsql_value(value: &Value) -> Cell {
+    let text = || match &value["value"] {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    match value["type"].as_str() {
+        Some("null") | None => Cell::Null,
+        Some("blob") => Cell::Text(value["base64"].as_str().unwrap_or_default().to_string()),
+        Some("integer") => text().parse().map_or_else(|_| Cell::Text(text()), Cell::Int),
+        Some("float") => value["value"].as_f64().map_or_else(|| Cell::Text(text()), Cell::Float),
+        _ => Cell::Text(text()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_libsql_urls() {
+        assert!(is_libsql_url(Path::new("libsql://sales-acme.turso.io")));
+        assert!(!is_libsql_url(Path::new("sales.db")));
+
+        assert_eq!(http_url("libsql://sales-acme.turso.io"), "https://sales-acme.turso.io");
+        assert_eq!(http_url("libsql://localhost:8080/"), "http://localhost:8080");
+    }
+
+    #[test]
+    fn test_parse_execute_result() {
+        let response = json!({
+            "baton": null,
+            "results": [
+                { "type": "ok", "response": { "type": "execute", "result": {
+                    "cols": [{ "name": "id", "decltype": "INTEGER" }, { "name": "name" }, { "name": "score" }],
+                    "rows": [
+                        [{ "type": "integer", "value": "1" }, { "type": "text", "value": "Alice" }, { "type": "float", "value": 9.5 }],
+                        [{ "type": "integer", "value": "2" }, { "type": "null" }, { "type": "blob", "base64": "AQI=" }],
+                    ],
+                    "affected_row_count": 0,
+                }}},
+                { "type": "ok", "response": { "type": "close" } },
+            ]
+        });
+        let result = parse_execute_result(&response).unwrap();
+        assert_eq!(result.columns, vec!["id", "name", "score"]);
+        assert_eq!(result.rows, vec![vec!["1", "Alice", "9.5"], vec!["2", "NULL", "AQI="]]);
+        assert_eq!(result.rows[0][0], Cell::Int(1));
+        assert_eq!(result.rows[1][1], Cell::Null);
+
+        let error = json!({ "results": [{ "type": "error", "error": { "message": "no such table: nope" } }] });
+        assert_eq!(parse_execute_result(&error).unwrap_err().to_string(), "no such table: nope");
+    }
+}
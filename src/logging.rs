@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable overriding the log filter (e.g. `SQBROWSER_LOG=trace`)
+const LOG_FILTER_ENV: &str = "SQBROWSER_LOG";
+
+/// Send tracing output to `path` (appending). Without a log file nothing is recorded,
+/// since the terminal itself is owned by the TUI.
+pub fn init(path: Option<&Path>) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file '{}'", path.display()))?;
+
+    let filter = EnvFilter::try_from_env(LOG_FILTER_ENV)
+        .unwrap_or_else(|_| EnvFilter::new("sqbrowser=debug"));
+
+    // Closing a span logs its duration, which gives the timing of every instrumented operation
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("Failed to initialise logging: {}", e))
+}
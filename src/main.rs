@@ -1,14 +1,46 @@
 mod database;
+#[cfg(feature = "duckdb")]
+mod duck_database;
 mod file_reader;
 mod data_source;
 mod ui;
 mod config;
 mod persistence;
+mod format;
+mod diff;
+mod filter;
+mod external;
+mod export;
+mod geo;
+mod logging;
+mod postgres_database;
+mod libsql_database;
+mod html_table;
+mod download;
+mod completion;
+mod statistical;
+mod table_stats;
+mod column_stats;
+mod join;
+mod profile;
+mod value;
+mod undo;
+mod column_edit;
+mod fill;
+mod bulk_edit;
+mod row_form;
+mod text_edit;
+mod csv_table;
+mod parquet_table;
+mod page_cache;
+mod workbook;
+mod window;
+mod keymap;
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -18,54 +50,138 @@ use ratatui::{
 };
 use std::{
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
     time::{Duration, Instant},
 };
 
 use data_source::DataSource;
-use ui::{AppState, NavigationMode, render_ui};
-use config::{load_config, Theme};
+use file_reader::{track_load_progress, LoadProgress};
+use ui::{
+    page_rows, render_download_screen, render_loading_screen, render_start_screen, render_ui, AppState, NavigationMode, QueryJob, QueryOutcome, RunningQuery,
+    StartAction, StartScreen,
+};
+use config::{load_config, Theme, ThemePreset};
+use persistence::RecentFiles;
+use download::{downloads_data_file, DownloadCache};
+use html_table::is_web_url;
+use libsql_database::is_libsql_url;
+use postgres_database::{is_connection_string, redact_password};
+use table_stats::StatsWorker;
+use page_cache::PageCache;
 
 #[derive(Parser)]
 #[command(name = "sqbrowser")]
-#[command(about = "A file browser supporting SQLite databases, CSV, XLSX, and Parquet files")]
+#[command(about = "A file browser supporting SQLite, libSQL and PostgreSQL databases, CSV, XLSX, Parquet, JSON, Arrow, DBF, Stata, SPSS and SAS files")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Path to the file (SQLite database, CSV, XLSX, or Parquet)
-    file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Paths to files (SQLite database, CSV, XLSX, Parquet, or HTML), postgres:// connection
+    /// strings, libsql:// URLs or web addresses of pages and data files,
+    /// each opened in its own tab; without any, pick from the recently opened files
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// Refuse to start when the config file has invalid entries instead of using defaults
+    #[arg(long, global = true)]
+    strict_config: bool,
+
+    /// Draw borders and symbols with plain ASCII (for legacy terminals)
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Compare the file against this baseline file (e.g. last month's export)
+    #[arg(long, value_name = "FILE")]
+    diff: Option<PathBuf>,
+
+    /// Key column used to match rows in diff mode (defaults to the first column)
+    #[arg(long, value_name = "COLUMN", requires = "diff")]
+    key: Option<String>,
+
+    /// Colors to draw with, instead of the config's theme
+    #[arg(long, value_name = "NAME", global = true)]
+    theme: Option<ThemePreset>,
+
+    /// Rows per page, instead of as many as fit the terminal
+    #[arg(long, value_name = "ROWS", global = true)]
+    page_size: Option<usize>,
+
+    /// Open each file on its first table instead of where it was left last time
+    #[arg(long, global = true)]
+    fresh: bool,
+
+    /// Write a log of data-source operations, query timings and errors to this file
+    #[arg(long, value_name = "FILE", global = true)]
+    log_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the rows added, removed and changed between two files, e.g. two runs of an ETL job
+    Diff {
+        /// The baseline file
+        old: PathBuf,
+
+        /// The file compared against the baseline
+        new: PathBuf,
+
+        /// Key column used to match rows (defaults to the first column)
+        #[arg(long, value_name = "COLUMN")]
+        key: Option<String>,
+    },
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // `sqbrowser diff old new` is `sqbrowser new --diff old`
+    if let Some(Command::Diff { old, new, key }) = args.command.take() {
+        args.files = vec![new];
+        args.diff = Some(old);
+        args.key = key;
+    }
+    logging::init(args.log_file.as_deref())?;
 
     // Load configuration
-    let config = load_config().context("Failed to load configuration")?;
-    let theme = Theme::from(&config.colors);
-
-    // Verify file exists
-    if !args.file.exists() {
-        return Err(anyhow::anyhow!("File '{}' not found", args.file.display()));
+    let (mut config, config_issues) = load_config().context("Failed to load configuration")?;
+    if args.strict_config && !config_issues.is_empty() {
+        let details: Vec<String> = config_issues.iter().map(|issue| issue.to_string()).collect();
+        return Err(anyhow::anyhow!("Invalid configuration:\n  {}", details.join("\n  ")));
+    }
+    let colors = config.colors.over(args.theme.unwrap_or(config.theme));
+    let mut theme = Theme::with_color_mode(&colors, config.color_mode);
+    theme.ascii = config.ascii || args.ascii;
+    if let Some(page_size) = args.page_size {
+        config.page_size = page_size;
     }
 
-    // Open data source
-    let mut data_source = DataSource::open(args.file.clone())
-        .context("Failed to open file")?;
+    let files = if args.files.is_empty() {
+        match pick_recent_file(&theme)? {
+            Some(file) => vec![file],
+            None => return Ok(()),
+        }
+    } else {
+        args.files
+    };
+    tracing::info!(files = files.len(), "starting sqbrowser");
 
-    // Get tables/sheets
-    let tables = data_source.get_tables()
-        .context("Failed to get table/sheet list from file")?;
+    let mut sessions = files
+        .iter()
+        .map(|file| open_session(file, &config, &theme, args.fresh))
+        .collect::<Result<Vec<_>>>()?;
 
-    if tables.is_empty() {
-        return Err(anyhow::anyhow!("No tables/sheets found in file"));
+    let first = &mut sessions[0];
+    if !config_issues.is_empty() {
+        let details: Vec<String> = config_issues.iter().map(|issue| issue.to_string()).collect();
+        first.app.show_error(format!(
+            "Invalid config entries replaced by defaults:\n{}",
+            details.join("\n")
+        ));
+    }
+    if let Some(baseline_path) = &args.diff {
+        open_file_diff(&mut first.app, &first.data_source, baseline_path, args.key.as_deref(), &config)?;
     }
-
-    // Initialize app state
-    let mut app = AppState::new(
-        args.file.to_string_lossy().to_string(),
-        tables
-    )?;
-
-    // Load initial data
-    app.load_current_data(&mut data_source)?;
 
     // Setup terminal
     enable_raw_mode()?;
@@ -75,7 +191,10 @@ fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Run the application
-    let result = run_app(&mut terminal, &mut app, &mut data_source, &theme);
+    let result = run_app(&mut terminal, &mut sessions, &theme);
+    for session in &mut sessions {
+        session.app.persist_view_state(&session.data_source);
+    }
 
     // Restore terminal
     disable_raw_mode()?;
@@ -87,6 +206,7 @@ fn main() -> Result<()> {
     terminal.show_cursor()?;
 
     if let Err(err) = result {
+        tracing::error!(error = %err, "application error");
         eprintln!("Application error: {}", err);
         return Err(err);
     }
@@ -94,18 +214,384 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(
+/// An open file with its own cursor, query, filters and other view state
+struct Session {
+    app: AppState,
+    data_source: DataSource,
+}
+
+/// Open a file and, unless `fresh`, restore how it was last viewed
+fn open_session(file: &Path, config: &config::Config, theme: &Theme, fresh: bool) -> Result<Session> {
+    // Connection strings may carry a password, which stays out of logs, titles and saved state
+    let location = redact_password(&file.to_string_lossy());
+    tracing::info!(file = %location, "opening file");
+
+    // Verify file exists
+    if !is_remote(file) && !file.exists() {
+        return Err(anyhow::anyhow!("File '{}' not found", file.display()));
+    }
+
+    // Data files behind a URL are opened from their downloaded copy
+    let source = if downloads_data_file(file) {
+        download_with_progress(&file.to_string_lossy(), theme)?
+    } else {
+        file.to_path_buf()
+    };
+
+    // Open data source
+    let mut data_source = open_with_progress(source, config, &location, theme)
+        .with_context(|| format!("Failed to open '{}'", location))?;
+    remember_recent_file(file, &location);
+
+    // Get tables/sheets
+    let tables = data_source.get_tables()
+        .context("Failed to get table/sheet list from file")?;
+
+    if tables.is_empty() {
+        return Err(anyhow::anyhow!("No tables/sheets found in '{}'", location));
+    }
+
+    // Initialize app state
+    let mut app = AppState::new(location, tables)?;
+    app.format = config.format.clone();
+    app.reader_config = config.clone();
+    app.keymap = keymap::Keymap::new(config.keymap);
+    // A page fills the table area unless its size is set
+    app.page_size = match config.page_size {
+        0 => crossterm::terminal::size().map_or(app.page_size, |(_, height)| page_rows(&app, height)),
+        rows => rows,
+    };
+    app.external_tools = config
+        .external_tools
+        .iter()
+        .filter(|tool| tool.applies_to(data_source.file_type_name()))
+        .cloned()
+        .collect();
+
+    // Views, indexes and triggers get sections of their own in the sidebar
+    app.sidebar_objects = data_source.sidebar_objects()?;
+
+    // Row counts and sizes of SQLite tables are worked out in the background
+    app.stats_worker = data_source.database_file().map(StatsWorker::spawn);
+    app.page_cache = data_source.database_file().map(PageCache::spawn);
+
+    // Pin favorite tables before the first table is opened
+    app.load_favorite_tables(&data_source);
+
+    app.load_bookmarks(&data_source);
+    app.load_query_history(&data_source);
+    app.load_saved_queries(&data_source);
+
+    // Load initial data, on the table, page and cursor the file was left on
+    if fresh {
+        app.load_current_data(&mut data_source)?;
+    } else {
+        app.restore_session(&mut data_source)?;
+    }
+
+    Ok(Session { app, data_source })
+}
+
+/// The tab a key switches to: Tab and Shift+Tab cycle, Alt+1..9 jump. Only while browsing
+/// tables, data or query results, so Tab keeps its meaning in edit and input popups.
+fn switched_tab(key: KeyEvent, app: &AppState, active: usize, count: usize) -> Option<usize> {
+    if count < 2
+        || app.show_help
+        || !matches!(
+            app.navigation_mode,
+            NavigationMode::Table | NavigationMode::Data | NavigationMode::DatabaseResults
+        )
+    {
+        return None;
+    }
+    match key.code {
+        KeyCode::Tab => Some((active + 1) % count),
+        KeyCode::BackTab => Some((active + count - 1) % count),
+        KeyCode::Char(c @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+            let idx = c as usize - '1' as usize;
+            (idx < count).then_some(idx)
+        }
+        _ => None,
+    }
+}
+
+/// A database server or web page address rather than a local file
+fn is_remote(path: &Path) -> bool {
+    is_connection_string(path) || is_libsql_url(path) || is_web_url(path)
+}
+
+/// Show the start screen of recently opened files; None when the user quits without picking
+fn pick_recent_file(theme: &Theme) -> Result<Option<PathBuf>> {
+    let recent: Vec<_> = RecentFiles::new()?
+        .load()
+        .into_iter()
+        .filter(|file| is_remote(Path::new(&file.path)) || Path::new(&file.path).exists())
+        .collect();
+    if recent.is_empty() {
+        return Err(anyhow::anyhow!("No recently opened files; run sqbrowser <FILE>"));
+    }
+    let mut screen = StartScreen::new(recent);
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run_start_screen(&mut terminal, &mut screen, theme);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run_start_screen<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    screen: &mut StartScreen,
+    theme: &Theme,
+) -> Result<Option<PathBuf>> {
+    loop {
+        terminal.draw(|f| render_start_screen(f, screen, theme))?;
+        if let Event::Key(key) = event::read()? {
+            match screen.handle_key_event(key) {
+                StartAction::Stay => {}
+                StartAction::Open(path) => return Ok(Some(PathBuf::from(path))),
+                StartAction::Quit => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Fetch a URL into the download cache, showing progress while it downloads. The terminal
+/// is only taken over once a download actually starts, so cached files open without a flash.
+fn download_with_progress(url: &str, theme: &Theme) -> Result<PathBuf> {
+    let mut terminal: Option<Terminal<CrosstermBackend<io::Stdout>>> = None;
+    let mut last_draw: Option<Instant> = None;
+    let result = DownloadCache::new()?.fetch(url, |received, total| {
+        if terminal.is_none() {
+            enable_raw_mode()?;
+            let mut stdout = io::stdout();
+            execute!(stdout, EnterAlternateScreen)?;
+            terminal = Some(Terminal::new(CrosstermBackend::new(stdout))?);
+        }
+        // Raw mode swallows Ctrl+C, so look for it between chunks
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    return Err(anyhow::anyhow!("Download of {} cancelled", url));
+                }
+            }
+        }
+        let finished = total == Some(received);
+        if finished || last_draw.is_none_or(|at| at.elapsed() >= Duration::from_millis(100)) {
+            last_draw = Some(Instant::now());
+            if let Some(terminal) = &mut terminal {
+                terminal.draw(|f| render_download_screen(f, url, received, total, theme))?;
+            }
+        }
+        Ok(())
+    });
+
+    if let Some(mut terminal) = terminal {
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+    }
+    result
+}
+
+/// Open a file on a worker thread, showing how much of it has been read while it loads.
+/// As with downloads, the terminal is only taken over when loading takes a while; ESC or
+/// Ctrl+C gives up on it.
+fn open_with_progress(path: PathBuf, config: &config::Config, location: &str, theme: &Theme) -> Result<DataSource> {
+    let progress = Arc::new(LoadProgress::default());
+    // Compressed files are counted as read from disk, so the size on disk is the total
+    let total = std::fs::metadata(&path).ok().filter(|meta| meta.is_file()).map(|meta| meta.len());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn({
+        let progress = progress.clone();
+        let config = config.clone();
+        move || {
+            track_load_progress(progress);
+            let _ = sender.send(DataSource::open_with_config(path, &config));
+        }
+    });
+
+    let started = Instant::now();
+    let mut terminal: Option<Terminal<CrosstermBackend<io::Stdout>>> = None;
+    let result = loop {
+        match receiver.recv_timeout(Duration::from_millis(80)) {
+            Ok(result) => break result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break Err(anyhow::anyhow!("Loading stopped unexpectedly")),
+        }
+        if started.elapsed() < Duration::from_millis(300) {
+            continue;
+        }
+        if terminal.is_none() {
+            enable_raw_mode()?;
+            let mut stdout = io::stdout();
+            execute!(stdout, EnterAlternateScreen)?;
+            terminal = Some(Terminal::new(CrosstermBackend::new(stdout))?);
+        }
+        // A format that isn't read as a stream may keep its worker busy a while longer;
+        // it is left to finish on its own
+        if event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.code == KeyCode::Esc || ctrl_c {
+                    progress.cancel();
+                    break Err(anyhow::anyhow!("Loading was cancelled"));
+                }
+            }
+        }
+        if let Some(terminal) = &mut terminal {
+            terminal.draw(|f| render_loading_screen(f, location, progress.bytes_read(), total, started.elapsed(), theme))?;
+        }
+    };
+
+    if let Some(mut terminal) = terminal {
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+    }
+    result
+}
+
+/// Put the opened file at the top of the start screen's list; files are stored by absolute path
+fn remember_recent_file(file: &Path, location: &str) {
+    let path = if is_remote(file) {
+        location.to_string()
+    } else {
+        file.canonicalize()
+            .unwrap_or_else(|_| file.to_path_buf())
+            .to_string_lossy()
+            .to_string()
+    };
+    if let Err(e) = RecentFiles::new().and_then(|recent| recent.record(&path)) {
+        tracing::warn!(error = %e, "failed to record recent file");
+    }
+}
+
+/// Diff the first table of the opened file against the matching table of a baseline file
+fn open_file_diff(
+    app: &mut AppState,
+    data_source: &DataSource,
+    baseline_path: &Path,
+    key_column: Option<&str>,
+    config: &config::Config,
+) -> Result<()> {
+    let baseline = DataSource::open_with_config(baseline_path.to_path_buf(), config)
+        .with_context(|| format!("Failed to open '{}'", baseline_path.display()))?;
+    let baseline_tables = baseline.get_tables()?;
+
+    let table_name = app.current_table().unwrap_or_default().to_string();
+    let baseline_table = baseline_tables
+        .iter()
+        .find(|t| **t == table_name)
+        .or_else(|| baseline_tables.first())
+        .ok_or_else(|| anyhow::anyhow!("No tables/sheets found in '{}'", baseline_path.display()))?;
+
+    let old = baseline.get_all_table_data(baseline_table)?;
+    let new = data_source.get_all_table_data(&table_name)?;
+    let description = format!(
+        "{} vs {}",
+        display_file_name(&app.db_path),
+        display_file_name(&baseline_path.to_string_lossy())
+    );
+    app.open_diff(&old, &new, key_column, description)
+}
+
+fn display_file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Leave the alternate screen while `f` runs (e.g. an external command), then redraw
+fn with_suspended_terminal<B: ratatui::backend::Backend, T>(
+    terminal: &mut Terminal<B>,
+    f: impl FnOnce() -> T,
+) -> Result<T> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+
+    let result = f();
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    Ok(result)
+}
+
+/// Run a query on a worker thread, redrawing with a spinner until it finishes; ESC or
+/// Ctrl+C interrupts it where the data source allows
+fn run_query_job<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut AppState,
     data_source: &mut DataSource,
+    job: &QueryJob,
+    theme: &Theme,
+    tabs: &[String],
+    active: usize,
+) -> Result<Result<QueryOutcome>> {
+    let interrupt = data_source.interrupt_handle();
+    app.running_query = Some(RunningQuery {
+        label: job.label(),
+        started: Instant::now(),
+        cancellable: interrupt.is_some(),
+        cancelled: false,
+    });
+    std::thread::scope(|scope| {
+        let worker = scope.spawn(|| job.run(data_source));
+        while !worker.is_finished() {
+            terminal.draw(|f| render_ui(f, app, theme, tabs, active))?;
+            if !event::poll(Duration::from_millis(80))? {
+                continue;
+            }
+            // Other keys are dropped rather than acted on once the query is done
+            if let Event::Key(key) = event::read()? {
+                let cancel = key.code == KeyCode::Esc
+                    || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+                if let (true, Some(interrupt), Some(running)) = (cancel, &interrupt, &mut app.running_query) {
+                    interrupt.interrupt();
+                    running.cancelled = true;
+                }
+            }
+        }
+        Ok(worker
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("The query failed unexpectedly"))))
+    })
+}
+
+fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    sessions: &mut [Session],
     theme: &Theme,
 ) -> Result<()> {
     let mut last_tick = Instant::now();
     let tick_rate = Duration::from_millis(100);
+    let mut last_change_check = Instant::now();
+    let change_check_rate = Duration::from_secs(1);
+    let tabs: Vec<String> = sessions
+        .iter()
+        .map(|session| display_file_name(&session.app.db_path))
+        .collect();
+    let mut active = 0;
 
     loop {
-        // Draw UI
-        terminal.draw(|f| render_ui(f, app, theme))?;
+        let Session { app, data_source } = &mut sessions[active];
+
+        app.fit_page_size(terminal.size()?.height, data_source)?;
+
+        // Draw the UI only when something changed, and not while more input is queued, so
+        // an idle screen costs nothing and a burst of keys is drawn once
+        if app.needs_redraw() && !event::poll(Duration::ZERO)? {
+            terminal.draw(|f| render_ui(f, app, theme, &tabs, active))?;
+            app.dirty = false;
+        }
 
         // Handle events
         let timeout = tick_rate
@@ -113,17 +599,51 @@ fn run_app<B: ratatui::backend::Backend>(
             .unwrap_or_else(|| Duration::from_secs(0));
 
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
+            let event = event::read()?;
+            if let Event::Resize(_, _) = event {
+                app.dirty = true;
+            }
+            if let Event::Key(key) = event {
+                app.dirty = true;
+
                 // Clear status message on any key press
                 if app.status_message.is_some() {
                     app.status_message = None;
                 }
 
+                if let Some(tab) = switched_tab(key, app, active, tabs.len()) {
+                    active = tab;
+                    sessions[active].app.dirty = true;
+                    continue;
+                }
+
                 // Handle key event
                 if !app.handle_key_event(key, data_source)? {
                     return Ok(());
                 }
 
+                // Run a piped command outside the TUI and show what it printed
+                if let Some(request) = app.pending_pipe.take() {
+                    let result = with_suspended_terminal(terminal, || {
+                        external::run_piped(&request.command, &request.input)
+                    })?;
+                    app.show_pipe_output(&request, result);
+                }
+
+                // Hand the terminal to an external tool, then pick up its changes
+                if let Some(launch) = app.pending_tool.take() {
+                    let result = with_suspended_terminal(terminal, || {
+                        external::run_interactive(&launch.command)
+                    })?;
+                    app.finish_external_tool(&launch, result, data_source)?;
+                }
+
+                // Run queries on a worker thread, keeping the UI drawn and ESC able to cancel them
+                if let Some(job) = app.pending_query.take() {
+                    let result = run_query_job(terminal, app, data_source, &job, theme, &tabs, active)?;
+                    app.finish_query(&job, result, data_source)?;
+                }
+
                 // Load data if we're in data mode and don't have current data
                 if app.navigation_mode == NavigationMode::Data && app.current_data.is_none() {
                     app.load_current_data(data_source)?;
@@ -134,5 +654,25 @@ fn run_app<B: ratatui::backend::Backend>(
         if last_tick.elapsed() >= tick_rate {
             last_tick = Instant::now();
         }
+
+        // Show a workbook sheet once it has been read in the background
+        let Session { app, data_source } = &mut sessions[active];
+        if let Err(e) = app.poll_sheets(data_source) {
+            app.show_error(format!("Failed to show the sheet: {}", e));
+        }
+        // Show table stats the background worker has counted, and ask for the selected table's
+        sessions[active].app.poll_table_stats();
+        // Fill in the total of a page shown before its table was counted
+        sessions[active].app.poll_row_count();
+
+        // Pick up commits made to any open database by other processes
+        if last_change_check.elapsed() >= change_check_rate {
+            last_change_check = Instant::now();
+            for Session { app, data_source } in sessions.iter_mut() {
+                if let Err(e) = app.check_external_changes(data_source) {
+                    app.show_error(format!("Failed to refresh changed database: {}", e));
+                }
+            }
+        }
     }
 }
\ No newline at end of file
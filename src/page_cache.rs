@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::database::{ChangeSignature, Database, QueryResult};
+use crate::filter::ColumnFilter;
+use crate::persistence::SortOrder;
+
+/// Pages kept, the least recently shown dropped first
+const CACHED_PAGES: usize = 16;
+
+/// A page of a table as read: where it starts and the sort and filters it was read with
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageKey {
+    pub table: String,
+    pub offset: usize,
+    pub limit: usize,
+    pub sort: Option<SortOrder>,
+    pub filters: Vec<ColumnFilter>,
+}
+
+type Prefetched = (PageKey, Option<ChangeSignature>, Result<QueryResult, String>);
+type Counted = (CountKey, Option<ChangeSignature>, Result<usize, String>);
+/// A table and the filters its rows are counted with
+type CountKey = (String, Vec<ColumnFilter>);
+
+/// Recently shown pages of SQLite tables, and the page after them read ahead on a thread with
+/// a connection of its own, so paging through a large table doesn't wait on a query each
+/// time. The pages are read without counting the table's rows: that is done on another
+/// thread, so a page of a huge table shows before its COUNT(*) is done. Pages and counts are
+/// dropped as soon as the database files change.
+pub struct PageCache {
+    pages: VecDeque<(PageKey, QueryResult)>, // least recently used first
+    signature: Option<ChangeSignature>,      // the database files the pages were read from
+    pending: Vec<PageKey>,
+    requests: Sender<(PageKey, Option<ChangeSignature>)>,
+    results: Receiver<Prefetched>,
+    counts: Vec<(CountKey, usize)>, // rows of a table passing the filters
+    counting: Vec<CountKey>,
+    count_requests: Sender<(CountKey, Option<ChangeSignature>)>,
+    count_results: Receiver<Counted>,
+}
+
+impl PageCache {
+    /// Start a cache on a database file. The threads end once the cache is dropped.
+    pub fn spawn(path: PathBuf) -> Self {
+        let (requests, queued) = channel::<(PageKey, Option<ChangeSignature>)>();
+        let (sender, results) = channel();
+        let page_path = path.clone();
+        std::thread::spawn(move || {
+            let db = Database::open_readonly(&page_path).map_err(|e| e.to_string());
+            for (key, signature) in queued {
+                let page = match &db {
+                    Ok(db) => db
+                        .get_table_page(&key.table, key.offset, key.limit, key.sort.as_ref(), &key.filters)
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.clone()),
+                };
+                if sender.send((key, signature, page)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (count_requests, queued) = channel::<(CountKey, Option<ChangeSignature>)>();
+        let (sender, count_results) = channel();
+        std::thread::spawn(move || {
+            let db = Database::open_readonly(&path).map_err(|e| e.to_string());
+            for ((table, filters), signature) in queued {
+                let count = match &db {
+                    Ok(db) => db.count_rows(&table, &filters).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.clone()),
+                };
+                if sender.send(((table, filters), signature, count)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            pages: VecDeque::new(),
+            signature: None,
+            pending: Vec::new(),
+            requests,
+            results,
+            counts: Vec::new(),
+            counting: Vec::new(),
+            count_requests,
+            count_results,
+        }
+    }
+
+    /// Forget every page and count once the database files are no longer as they were read
+    pub fn sync(&mut self, signature: Option<ChangeSignature>) {
+        if signature != self.signature {
+            self.pages.clear();
+            self.counts.clear();
+            self.signature = signature;
+        }
+    }
+
+    /// Rows of the page's table passing its filters, once counted; asks for the count when
+    /// it hasn't been yet
+    pub fn row_count(&mut self, key: &PageKey) -> Option<usize> {
+        let counted = (key.table.clone(), key.filters.clone());
+        if let Some((_, count)) = self.counts.iter().find(|(c, _)| *c == counted) {
+            return Some(*count);
+        }
+        if !self.counting.contains(&counted)
+            && self.count_requests.send((counted.clone(), self.signature.clone())).is_ok()
+        {
+            self.counting.push(counted);
+        }
+        None
+    }
+
+    /// Take in the counts done so far; a failed count is returned as an error
+    pub fn collect_counts(&mut self) -> Result<(), String> {
+        for (counted, signature, count) in self.count_results.try_iter().collect::<Vec<_>>() {
+            self.counting.retain(|c| *c != counted);
+            if signature == self.signature {
+                self.counts.push((counted, count?));
+            }
+        }
+        Ok(())
+    }
+
+    /// A page read before, waiting for it when it is being read ahead
+    pub fn get(&mut self, key: &PageKey) -> Option<QueryResult> {
+        self.collect(false);
+        if self.pending.contains(key) {
+            self.collect(true);
+        }
+        let idx = self.pages.iter().position(|(cached, _)| cached == key)?;
+        let entry = self.pages.remove(idx)?;
+        let page = entry.1.clone();
+        self.pages.push_back(entry);
+        Some(page)
+    }
+
+    pub fn insert(&mut self, key: PageKey, page: QueryResult) {
+        self.pages.retain(|(cached, _)| *cached != key);
+        if self.pages.len() >= CACHED_PAGES {
+            self.pages.pop_front();
+        }
+        self.pages.push_back((key, page));
+    }
+
+    /// Read a page in the background, unless it is already cached or on its way
+    pub fn prefetch(&mut self, key: PageKey) {
+        if self.pending.contains(&key) || self.pages.iter().any(|(cached, _)| *cached == key) {
+            return;
+        }
+        // A worker whose thread has died just never answers
+        if self.requests.send((key.clone(), self.signature.clone())).is_ok() {
+            self.pending.push(key);
+        }
+    }
+
+    /// Take in the pages read ahead so far, or, with `wait`, every page asked for. Pages read
+    /// before the database files changed are dropped.
+    fn collect(&mut self, wait: bool) {
+        while !self.pending.is_empty() {
+            let received = if wait { self.results.recv().ok() } else { self.results.try_recv().ok() };
+            let Some((key, signature, page)) = received else {
+                break;
+            };
+            self.pending.retain(|pending| *pending != key);
+            if let (Ok(page), true) = (page, signature == self.signature) {
+                self.insert(key, page);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Value;
+
+    fn key(offset: usize) -> PageKey {
+        PageKey { table: "t".to_string(), offset, limit: 10, sort: None, filters: Vec::new() }
+    }
+
+    #[test]
+    fn test_page_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pages.db");
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (n INTEGER); WITH RECURSIVE c(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM c WHERE n < 50) INSERT INTO t SELECT n FROM c;")
+            .unwrap();
+        let db = Database::open_readonly(&path).unwrap();
+
+        let mut cache = PageCache::spawn(path);
+        cache.sync(db.change_signature());
+        assert!(cache.get(&key(0)).is_none());
+        cache.insert(key(0), db.get_table_data("t", 0, 10, None, &[]).unwrap());
+        cache.prefetch(key(10));
+        cache.prefetch(key(10));
+        let page = cache.get(&key(10)).unwrap();
+        assert_eq!(page.rows[0].last(), Some(&Value::Int(11)));
+        assert_eq!(page.total_rows, 10);
+        assert!(cache.pending.is_empty());
+        assert!(cache.get(&key(0)).is_some());
+
+        // Reading the table in another order or after a change is a different page
+        assert!(cache.get(&PageKey { sort: Some(SortOrder { column: "n".to_string(), descending: true }), ..key(0) }).is_none());
+        cache.sync(None);
+        assert!(cache.get(&key(0)).is_none());
+    }
+
+    #[test]
+    fn test_background_count() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("count.db");
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2), (3);")
+            .unwrap();
+
+        let mut cache = PageCache::spawn(path);
+        let filtered = PageKey { filters: vec![ColumnFilter::parse("n", ">1").unwrap()], ..key(0) };
+        assert_eq!(cache.row_count(&key(0)), None);
+        assert_eq!(cache.row_count(&filtered), None);
+        let mut counts = None;
+        for _ in 0..100 {
+            cache.collect_counts().unwrap();
+            counts = cache.row_count(&key(0)).zip(cache.row_count(&filtered));
+            if counts.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(counts, Some((3, 2)));
+        // Counted only once, whatever the page
+        assert_eq!(cache.row_count(&key(20)), Some(3));
+
+        assert_eq!(cache.row_count(&PageKey { table: "missing".to_string(), ..key(0) }), None);
+        let mut failed = Ok(());
+        for _ in 0..100 {
+            failed = cache.collect_counts();
+            if failed.is_err() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert!(failed.is_err());
+    }
+}
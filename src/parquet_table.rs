@@ -0,0 +1,264 @@
+use anyhow::Result;
+use parquet::arrow::arrow_reader::{ArrowReaderOptions, ParquetRecordBatchReaderBuilder, RowSelection, RowSelector};
+use parquet::arrow::ProjectionMask;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config::{NumberLocale, ParquetSettings};
+use crate::database::QueryResult;
+use crate::file_reader::{detect_compression, paginate_data, read_parquet_file, record_batch_rows};
+use crate::filter::{filter_rows, ColumnFilter};
+use crate::value::Value;
+
+/// The rows of a Parquet file. Plain files are read a page at a time, decoding only the row
+/// groups and data pages the page falls in; compressed files are read whole, as they can't seek.
+pub struct ParquetTable {
+    path: PathBuf,
+    settings: ParquetSettings,
+    columns: Vec<String>,
+    row_groups: Vec<usize>,        // rows in each row group, from the file's footer
+    loaded: OnceLock<QueryResult>, // every row; once read, edits go here and pages come from it
+}
+
+impl ParquetTable {
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %path.display()), err)]
+    pub fn open(path: &Path, settings: &ParquetSettings) -> Result<Self> {
+        if detect_compression(path).is_some() {
+            return Ok(Self::with_rows(path, settings, read_parquet_file(path, settings)?));
+        }
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            settings: settings.clone(),
+            columns: builder.schema().fields().iter().map(|field| field.name().to_string()).collect(),
+            row_groups: builder.metadata().row_groups().iter().map(|group| group.num_rows() as usize).collect(),
+            loaded: OnceLock::new(),
+        })
+    }
+
+    /// A table of rows already read, such as those of the CSV an edited file was saved to
+    pub fn with_rows(path: &Path, settings: &ParquetSettings, data: QueryResult) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            settings: settings.clone(),
+            columns: data.columns.clone(),
+            row_groups: Vec::new(),
+            loaded: OnceLock::from(data),
+        }
+    }
+
+    pub fn columns(&self) -> &[String] {
+        match self.loaded.get() {
+            Some(data) => &data.columns,
+            None => &self.columns,
+        }
+    }
+
+    /// Whether every row has been read, so pages come from memory
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.get().is_some()
+    }
+
+    fn total_rows(&self) -> usize {
+        self.row_groups.iter().sum()
+    }
+
+    /// Every row, read from the file the first time it is needed
+    pub fn data(&self) -> Result<&QueryResult> {
+        if let Some(data) = self.loaded.get() {
+            return Ok(data);
+        }
+        let data = read_parquet_file(&self.path, &self.settings)?;
+        Ok(self.loaded.get_or_init(|| data))
+    }
+
+    pub fn data_mut(&mut self) -> Result<&mut QueryResult> {
+        self.data()?;
+        Ok(self.loaded.get_mut().expect("rows were just read"))
+    }
+
+    /// `limit` rows from `offset`, with the number of rows in the table. Only the row groups
+    /// covering the page are read, and the page index lets the reader skip the data pages
+    /// before it. With `projection`, only those columns (by index) are decoded and the others
+    /// are left NULL.
+    pub fn page(&self, offset: usize, limit: usize, projection: Option<&[usize]>) -> Result<QueryResult> {
+        if self.loaded.get().is_some() {
+            return Ok(paginate_data(self.data()?, offset, limit));
+        }
+        let end = offset.saturating_add(limit);
+        let mut groups = Vec::new();
+        let (mut group_start, mut skip, mut available) = (0, 0, 0);
+        for (idx, rows) in self.row_groups.iter().enumerate() {
+            let group_end = group_start + rows;
+            if group_end > offset && group_start < end {
+                if groups.is_empty() {
+                    skip = offset - group_start;
+                }
+                groups.push(idx);
+                available += rows;
+            }
+            group_start = group_end;
+        }
+
+        let mut rows = Vec::new();
+        if !groups.is_empty() {
+            let selection = vec![RowSelector::skip(skip), RowSelector::select(limit.min(available - skip))];
+            let options = ArrowReaderOptions::new().with_page_index(true);
+            let builder = ParquetRecordBatchReaderBuilder::try_new_with_options(File::open(&self.path)?, options)?;
+            let mask = match projection {
+                Some(columns) => ProjectionMask::roots(builder.parquet_schema(), columns.iter().copied()),
+                None => ProjectionMask::all(),
+            };
+            let reader = builder
+                .with_batch_size(self.settings.batch_size.max(1))
+                .with_row_groups(groups)
+                .with_row_selection(RowSelection::from(selection))
+                .with_projection(mask)
+                .build()?;
+            rows = record_batch_rows(reader)?;
+        }
+        if let Some(columns) = projection {
+            // The decoded columns come in file order; put them back in place
+            let mut decoded: Vec<usize> = columns.iter().copied().filter(|&idx| idx < self.columns.len()).collect();
+            decoded.sort_unstable();
+            decoded.dedup();
+            for row in &mut rows {
+                let mut full = vec![Value::Null; self.columns.len()];
+                for (&idx, value) in decoded.iter().zip(row.drain(..)) {
+                    full[idx] = value;
+                }
+                *row = full;
+            }
+        }
+        Ok(QueryResult { columns: self.columns.clone(), rows, total_rows: self.total_rows() })
+    }
+
+    /// A page of the rows passing `filters`, reading the file a batch at a time and keeping
+    /// only the page
+    pub fn filtered_page(&self, filters: &[ColumnFilter], offset: usize, limit: usize, locale: NumberLocale) -> Result<QueryResult> {
+        if self.loaded.get().is_some() {
+            return Ok(paginate_data(&filter_rows(self.data()?, filters, locale), offset, limit));
+        }
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&self.path)?)?
+            .with_batch_size(self.settings.batch_size.max(1))
+            .build()?;
+        let mut rows = Vec::new();
+        let mut matches = 0;
+        for batch in reader {
+            let chunk = QueryResult {
+                columns: self.columns.clone(),
+                rows: record_batch_rows(std::iter::once(batch))?,
+                total_rows: 0,
+            };
+            for row in filter_rows(&chunk, filters, locale).rows {
+                if matches >= offset && rows.len() < limit {
+                    rows.push(row);
+                }
+                matches += 1;
+            }
+        }
+        Ok(QueryResult { columns: self.columns.clone(), rows, total_rows: matches })
+    }
+
+    /// Every value of one column, decoding none of the others
+    pub fn column(&self, column: &str) -> Result<QueryResult> {
+        let Some(idx) = self.columns().iter().position(|c| c == column) else {
+            return Err(anyhow::anyhow!("Column '{}' not found", column));
+        };
+        if let Some(data) = self.loaded.get() {
+            let rows = data.rows.iter().map(|row| row.get(idx).cloned().into_iter().collect()).collect();
+            return Ok(QueryResult { columns: vec![column.to_string()], rows, total_rows: data.total_rows });
+        }
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&self.path)?)?;
+        let projection = ProjectionMask::roots(builder.parquet_schema(), [idx]);
+        let reader = builder
+            .with_batch_size(self.settings.batch_size.max(1))
+            .with_projection(projection)
+            .build()?;
+        let rows = record_batch_rows(reader)?;
+        Ok(QueryResult { columns: vec![column.to_string()], total_rows: rows.len(), rows })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterOp;
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    /// 1000 rows of `id` and `kind` in row groups of 300, with data pages of 50 rows
+    fn write_parquet(path: &Path) {
+        let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(0..1000));
+        let kinds: ArrayRef = Arc::new(StringArray::from_iter_values((0..1000).map(|i| format!("k{}", i % 4))));
+        let batch = RecordBatch::try_from_iter(vec![("id", ids), ("kind", kinds)]).unwrap();
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(300)
+            .set_data_page_row_count_limit(50)
+            .set_write_batch_size(50)
+            .build();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), Some(properties)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_parquet_pages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("items.parquet");
+        write_parquet(&path);
+
+        let table = ParquetTable::open(&path, &ParquetSettings::default()).unwrap();
+        assert_eq!(table.row_groups, vec![300, 300, 300, 100]);
+        let page = table.page(580, 40, None).unwrap();
+        assert_eq!((page.total_rows, page.rows.len()), (1000, 40));
+        // Across the end of the second row group
+        assert_eq!(page.rows[0], vec![Value::Int(580), Value::from("k0")]);
+        assert_eq!(page.rows[39], vec![Value::Int(619), Value::from("k3")]);
+        assert_eq!(table.page(990, 25, None).unwrap().rows.len(), 10);
+        assert!(table.page(1000, 25, None).unwrap().rows.is_empty());
+        assert_eq!(table.page(0, usize::MAX, None).unwrap().rows, read_parquet_file(&path, &ParquetSettings::default()).unwrap().rows);
+        assert!(table.loaded.get().is_none());
+
+        let ids = table.column("id").unwrap();
+        assert_eq!((ids.columns.len(), ids.rows.len()), (1, 1000));
+        assert_eq!(ids.rows[999], vec![Value::Int(999)]);
+    }
+
+    #[test]
+    fn test_parquet_projected_page() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("items.parquet");
+        write_parquet(&path);
+
+        let table = ParquetTable::open(&path, &ParquetSettings::default()).unwrap();
+        let page = table.page(580, 40, Some(&[1])).unwrap();
+        assert_eq!(page.columns, vec!["id", "kind"]);
+        assert_eq!((page.total_rows, page.rows.len()), (1000, 40));
+        // `id` is not decoded
+        assert_eq!(page.rows[0], vec![Value::Null, Value::from("k0")]);
+        assert_eq!(page.rows[39], vec![Value::Null, Value::from("k3")]);
+
+        let ids = table.page(580, 2, Some(&[0])).unwrap();
+        assert_eq!(ids.rows, vec![vec![Value::Int(580), Value::Null], vec![Value::Int(581), Value::Null]]);
+        assert_eq!(table.page(580, 2, Some(&[1, 0])).unwrap().rows, table.page(580, 2, None).unwrap().rows);
+        assert!(table.loaded.get().is_none());
+    }
+
+    #[test]
+    fn test_parquet_filter() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("items.parquet");
+        write_parquet(&path);
+
+        let table = ParquetTable::open(&path, &ParquetSettings { batch_size: 64 }).unwrap();
+        let filters = [ColumnFilter { column: "kind".to_string(), op: FilterOp::Eq, value: "k1".to_string() }];
+        let page = table.filtered_page(&filters, 200, 100, NumberLocale::En).unwrap();
+        assert_eq!((page.total_rows, page.rows.len()), (250, 50));
+        assert_eq!(page.rows[0][0], Value::Int(801));
+    }
+}
@@ -19,21 +19,187 @@ pub enum PersistedComputedColumnType {
     Aggregate(String),
     RowOperation(Vec<String>),
     MixedOperation(Vec<String>, Vec<String>),
+    Window(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub column: String,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnFilter {
+    pub column: String,
+    pub expression: String, // e.g. ">100", "contains:foo", "=NULL"
+}
+
+/// How a table was last viewed, restored the next time it is opened
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableViewState {
+    pub sort: Option<SortOrder>,
+    pub filters: Vec<ColumnFilter>,
+    pub hidden_columns: Vec<String>,
+    pub pinned_columns: Vec<String>, // kept on the left while scrolling sideways
+    pub column_widths: HashMap<String, u16>,
+    pub data_offset: usize,
+    pub selected_row_idx: usize,
+    pub selected_col_idx: usize,
+}
+
+/// The table a file was left on and the query run over it, shown again on the next open
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastView {
+    pub table: String,
+    pub query: Option<String>,
+    pub query_params: Vec<(String, String)>, // values bound to the query's placeholders
+}
+
+/// A named row or cell the user wants to find again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub table: String,
+    pub rowid: Option<i64>,       // SQLite rows are found again by rowid
+    pub position: usize,          // absolute row index in the view the bookmark was made in
+    pub sort: Option<SortOrder>,  // sort of that view, to find file rows again
+    pub column: Option<String>,   // set for cell bookmarks
+}
+
+/// A value bound to a statement, as SQLite stores it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// A statement undoing one row change of a save, with the values bound to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReverseStatement {
+    pub sql: String,
+    pub params: Vec<SqlValue>,
+}
+
+/// A save to a SQLite table, kept as the statements undoing it so it can be reverted (`Z`),
+/// also after a restart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteBatch {
+    pub table: String,
+    pub saved_at: u64,                     // Unix timestamp
+    pub statements: Vec<ReverseStatement>, // last change first
+}
+
+const WRITE_BATCH_LIMIT: usize = 20;
+
+/// A query kept under a name to run again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct FileComputedColumns {
+pub struct FileSession {
     pub file_path: String,
     pub file_hash: String, // Simple hash to detect file changes
     pub last_modified: u64, // Unix timestamp
     pub computed_columns: HashMap<String, Vec<PersistedComputedColumn>>, // table_name -> columns
+    #[serde(default)]
+    pub view_states: HashMap<String, TableViewState>, // table_name -> view state
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub write_batches: Vec<WriteBatch>, // saves to the database not reverted, oldest first
+    #[serde(default)]
+    pub favorite_tables: Vec<String>, // pinned to the top of the sidebar
+    #[serde(default)]
+    pub query_history: Vec<String>, // executed queries, oldest first
+    #[serde(default)]
+    pub saved_queries: Vec<SavedQuery>,
+    #[serde(default)]
+    pub last_view: Option<LastView>,
 }
 
-pub struct ComputedColumnPersistence {
+/// A file or connection opened before, listed on the start screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: String,   // absolute path, or a connection string without its password
+    pub opened_at: u64, // Unix timestamp
+}
+
+const RECENT_FILES_LIMIT: usize = 20;
+const QUERY_HISTORY_LIMIT: usize = 200;
+
+/// Add an executed query to the end of the history; running a query again moves it there
+pub fn push_query_history(history: &mut Vec<String>, query: &str) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+    history.retain(|q| q != query);
+    history.push(query.to_string());
+    if history.len() > QUERY_HISTORY_LIMIT {
+        history.drain(..history.len() - QUERY_HISTORY_LIMIT);
+    }
+}
+
+/// Recently opened files, newest first, kept in `recent.json` next to the per-file data
+pub struct RecentFiles {
+    storage_file: PathBuf,
+}
+
+impl RecentFiles {
+    pub fn new() -> Result<Self> {
+        Ok(Self::at(get_storage_path()?.join("recent.json")))
+    }
+
+    pub fn at(storage_file: PathBuf) -> Self {
+        Self { storage_file }
+    }
+
+    /// A missing or unreadable list is empty
+    pub fn load(&self) -> Vec<RecentFile> {
+        fs::read_to_string(&self.storage_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Move `path` to the top of the list, dropping the oldest entries past the limit
+    pub fn record(&self, path: &str) -> Result<()> {
+        let mut recent = self.load();
+        recent.retain(|file| file.path != path);
+        recent.insert(
+            0,
+            RecentFile {
+                path: path.to_string(),
+                opened_at: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs(),
+            },
+        );
+        recent.truncate(RECENT_FILES_LIMIT);
+
+        let json = serde_json::to_string_pretty(&recent)
+            .context("Failed to serialize recent files")?;
+        fs::write(&self.storage_file, json)
+            .context("Failed to write recent files")?;
+        Ok(())
+    }
+}
+
+/// What is kept of each file between runs, in a JSON file of its own: computed columns, how
+/// each table was viewed, the table it was left on, bookmarks, favorites and queries
+pub struct SessionStore {
     storage_path: PathBuf,
 }
 
-impl ComputedColumnPersistence {
+impl SessionStore {
     pub fn new() -> Result<Self> {
         let storage_path = get_storage_path()?;
         Ok(Self { storage_path })
@@ -46,18 +212,7 @@ impl ComputedColumnPersistence {
         computed_columns: &[ComputedColumn],
     ) -> Result<()> {
         let file_hash = self.calculate_file_hash(file_path)?;
-        let mut file_data = self.load_file_data(file_path).unwrap_or_else(|_| {
-            let last_modified = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            FileComputedColumns {
-                file_path: file_path.to_string(),
-                file_hash: file_hash.clone(),
-                last_modified,
-                computed_columns: HashMap::new(),
-            }
-        });
+        let mut file_data = self.load_or_new_file_data(file_path)?;
 
         // Update file data
         file_data.file_hash = file_hash;
@@ -75,15 +230,152 @@ impl ComputedColumnPersistence {
                     ComputedColumnType::Aggregate(func) => PersistedComputedColumnType::Aggregate(func.clone()),
                     ComputedColumnType::RowOperation(cols) => PersistedComputedColumnType::RowOperation(cols.clone()),
                     ComputedColumnType::MixedOperation(cols, aggs) => PersistedComputedColumnType::MixedOperation(cols.clone(), aggs.clone()),
+                    ComputedColumnType::Window(func) => PersistedComputedColumnType::Window(func.clone()),
                 },
             })
             .collect();
 
         file_data.computed_columns.insert(table_name.to_string(), persisted_columns);
 
-        // Save to file
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn save_view_state(
+        &self,
+        file_path: &str,
+        table_name: &str,
+        view_state: &TableViewState,
+    ) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data
+            .view_states
+            .insert(table_name.to_string(), view_state.clone());
+
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn load_view_state(&self, file_path: &str, table_name: &str) -> Option<TableViewState> {
+        self.load_file_data(file_path)
+            .ok()
+            .and_then(|data| data.view_states.get(table_name).cloned())
+    }
+
+    pub fn save_last_view(&self, file_path: &str, last_view: &LastView) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.last_view = Some(last_view.clone());
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn load_last_view(&self, file_path: &str) -> Option<LastView> {
+        self.load_file_data(file_path).ok().and_then(|data| data.last_view)
+    }
+
+    pub fn save_bookmarks(&self, file_path: &str, bookmarks: &[Bookmark]) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.bookmarks = bookmarks.to_vec();
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn load_bookmarks(&self, file_path: &str) -> Vec<Bookmark> {
+        self.load_file_data(file_path)
+            .map(|data| data.bookmarks)
+            .unwrap_or_default()
+    }
+
+    /// Keep the statements undoing a save, forgetting the oldest saves past the limit
+    pub fn push_write_batch(&self, file_path: &str, batch: WriteBatch) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.write_batches.push(batch);
+        let excess = file_data.write_batches.len().saturating_sub(WRITE_BATCH_LIMIT);
+        file_data.write_batches.drain(..excess);
+        self.write_file_data(file_path, &file_data)
+    }
+
+    /// The most recent save not reverted yet
+    pub fn last_write_batch(&self, file_path: &str) -> Option<WriteBatch> {
+        self.load_file_data(file_path)
+            .ok()
+            .and_then(|mut data| data.write_batches.pop())
+    }
+
+    /// Forget the most recent save, once it has been reverted or can't be
+    pub fn pop_write_batch(&self, file_path: &str) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.write_batches.pop();
+        self.write_file_data(file_path, &file_data)
+    }
+
+    /// Forget every save, once the database was changed in a way not kept as a save
+    pub fn clear_write_batches(&self, file_path: &str) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        if file_data.write_batches.is_empty() {
+            return Ok(());
+        }
+        file_data.write_batches.clear();
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn save_favorite_tables(&self, file_path: &str, favorites: &[String]) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.favorite_tables = favorites.to_vec();
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn load_favorite_tables(&self, file_path: &str) -> Vec<String> {
+        self.load_file_data(file_path)
+            .map(|data| data.favorite_tables)
+            .unwrap_or_default()
+    }
+
+    pub fn save_query_history(&self, file_path: &str, history: &[String]) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.query_history = history.to_vec();
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn load_query_history(&self, file_path: &str) -> Vec<String> {
+        self.load_file_data(file_path)
+            .map(|data| data.query_history)
+            .unwrap_or_default()
+    }
+
+    pub fn save_saved_queries(&self, file_path: &str, queries: &[SavedQuery]) -> Result<()> {
+        let mut file_data = self.load_or_new_file_data(file_path)?;
+        file_data.saved_queries = queries.to_vec();
+        self.write_file_data(file_path, &file_data)
+    }
+
+    pub fn load_saved_queries(&self, file_path: &str) -> Vec<SavedQuery> {
+        self.load_file_data(file_path)
+            .map(|data| data.saved_queries)
+            .unwrap_or_default()
+    }
+
+    // View state and bookmarks survive file changes, so they leave the stored hash alone
+    fn load_or_new_file_data(&self, file_path: &str) -> Result<FileSession> {
+        match self.load_file_data(file_path) {
+            Ok(data) => Ok(data),
+            Err(_) => Ok(FileSession {
+                file_path: file_path.to_string(),
+                file_hash: self.calculate_file_hash(file_path)?,
+                last_modified: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs(),
+                computed_columns: HashMap::new(),
+                view_states: HashMap::new(),
+                bookmarks: Vec::new(),
+                write_batches: Vec::new(),
+                favorite_tables: Vec::new(),
+                query_history: Vec::new(),
+                saved_queries: Vec::new(),
+                last_view: None,
+            }),
+        }
+    }
+
+    fn write_file_data(&self, file_path: &str, file_data: &FileSession) -> Result<()> {
         let storage_file = self.get_storage_file_path(file_path);
-        let json = serde_json::to_string_pretty(&file_data)
+        let json = serde_json::to_string_pretty(file_data)
             .context("Failed to serialize computed columns")?;
         fs::write(&storage_file, json)
             .context("Failed to write computed columns file")?;
@@ -121,6 +413,7 @@ impl ComputedColumnPersistence {
                     PersistedComputedColumnType::Aggregate(func) => ComputedColumnType::Aggregate(func),
                     PersistedComputedColumnType::RowOperation(cols) => ComputedColumnType::RowOperation(cols),
                     PersistedComputedColumnType::MixedOperation(cols, aggs) => ComputedColumnType::MixedOperation(cols, aggs),
+                    PersistedComputedColumnType::Window(func) => ComputedColumnType::Window(func),
                 },
             })
             .collect();
@@ -140,7 +433,7 @@ impl ComputedColumnPersistence {
         }
     }
 
-    fn load_file_data(&self, file_path: &str) -> Result<FileComputedColumns> {
+    fn load_file_data(&self, file_path: &str) -> Result<FileSession> {
         let storage_file = self.get_storage_file_path(file_path);
         
         if !storage_file.exists() {
@@ -149,19 +442,14 @@ impl ComputedColumnPersistence {
 
         let content = fs::read_to_string(&storage_file)
             .context("Failed to read computed columns file")?;
-        let file_data: FileComputedColumns = serde_json::from_str(&content)
+        let file_data: FileSession = serde_json::from_str(&content)
             .context("Failed to parse computed columns file")?;
 
         Ok(file_data)
     }
 
     fn get_storage_file_path(&self, file_path: &str) -> PathBuf {
-        // Create a safe filename from the file path
-        let safe_name = file_path
-            .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
-            .replace(' ', "_");
-        
-        self.storage_path.join(format!("{}.json", safe_name))
+        self.storage_path.join(format!("{}.json", safe_file_name(file_path)))
     }
 
     fn calculate_file_hash(&self, file_path: &str) -> Result<String> {
@@ -188,6 +476,21 @@ impl ComputedColumnPersistence {
     }
 }
 
+/// A file name standing for `file_path` among the per-file data
+fn safe_file_name(file_path: &str) -> String {
+    file_path
+        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        .replace(' ', "_")
+}
+
+/// Where the row index of a large CSV file is kept, in `csv_index/` next to the per-file data
+pub fn csv_index_path(file_path: &Path) -> Result<PathBuf> {
+    let dir = get_storage_path()?.join("csv_index");
+    fs::create_dir_all(&dir).context("Failed to create the index directory")?;
+    let file_path = fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_path_buf());
+    Ok(dir.join(format!("{}.json", safe_file_name(&file_path.to_string_lossy()))))
+}
+
 fn get_storage_path() -> Result<PathBuf> {
     let home_dir = std::env::var("HOME")
         .context("HOME environment variable not set")?;
@@ -216,7 +519,7 @@ mod tests {
         let test_file = temp_dir.path().join("test.csv");
         fs::write(&test_file, "name,age\nJohn,25\nJane,30").unwrap();
 
-        let persistence = ComputedColumnPersistence::new().unwrap();
+        let persistence = SessionStore::new().unwrap();
         
         let computed_cols = vec![
             ComputedColumn {
@@ -244,4 +547,198 @@ mod tests {
         assert_eq!(loaded_cols[0].name, "age_doubled");
         assert_eq!(loaded_cols[0].expression, "age * 2");
     }
+
+    #[test]
+    fn test_view_state_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("view_state.csv");
+        fs::write(&test_file, "name,age\nJohn,25\nJane,30").unwrap();
+        let file_path = test_file.to_str().unwrap();
+
+        let persistence = SessionStore::new().unwrap();
+        assert!(persistence.load_view_state(file_path, "CSV Data").is_none());
+
+        let view_state = TableViewState {
+            sort: Some(SortOrder {
+                column: "age".to_string(),
+                descending: true,
+            }),
+            hidden_columns: vec!["name".to_string()],
+            pinned_columns: vec!["id".to_string()],
+            column_widths: HashMap::from([("age".to_string(), 12)]),
+            selected_row_idx: 1,
+            ..TableViewState::default()
+        };
+        persistence
+            .save_view_state(file_path, "CSV Data", &view_state)
+            .unwrap();
+
+        let loaded = persistence.load_view_state(file_path, "CSV Data").unwrap();
+        assert_eq!(loaded, view_state);
+        assert!(persistence.load_view_state(file_path, "Other").is_none());
+    }
+
+    #[test]
+    fn test_write_batch_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("batches.db");
+        fs::write(&test_file, "").unwrap();
+        let file_path = test_file.to_str().unwrap();
+
+        let persistence = SessionStore::new().unwrap();
+        assert!(persistence.last_write_batch(file_path).is_none());
+
+        let batch = |n: i64| WriteBatch {
+            table: "users".to_string(),
+            saved_at: 1_700_000_000,
+            statements: vec![ReverseStatement {
+                sql: "UPDATE \"users\" SET \"name\" = ? WHERE rowid = ?".to_string(),
+                params: vec![SqlValue::Text("Bob".to_string()), SqlValue::Integer(n)],
+            }],
+        };
+        for n in 0..25 {
+            persistence.push_write_batch(file_path, batch(n)).unwrap();
+        }
+        assert_eq!(persistence.last_write_batch(file_path), Some(batch(24)));
+        persistence.pop_write_batch(file_path).unwrap();
+        assert_eq!(persistence.last_write_batch(file_path), Some(batch(23)));
+
+        // Only the most recent saves are kept
+        for _ in 0..WRITE_BATCH_LIMIT - 1 {
+            persistence.pop_write_batch(file_path).unwrap();
+        }
+        assert!(persistence.last_write_batch(file_path).is_none());
+
+        persistence.push_write_batch(file_path, batch(0)).unwrap();
+        persistence.push_write_batch(file_path, batch(1)).unwrap();
+        persistence.clear_write_batches(file_path).unwrap();
+        assert!(persistence.last_write_batch(file_path).is_none());
+    }
+
+    #[test]
+    fn test_last_view_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("last_view.csv");
+        fs::write(&test_file, "name,age\nJohn,25\nJane,30").unwrap();
+        let file_path = test_file.to_str().unwrap();
+
+        let persistence = SessionStore::new().unwrap();
+        assert!(persistence.load_last_view(file_path).is_none());
+
+        let last_view = LastView {
+            table: "CSV Data".to_string(),
+            query: Some("SELECT * FROM x WHERE age > :age".to_string()),
+            query_params: vec![("age".to_string(), "18".to_string())],
+        };
+        persistence.save_last_view(file_path, &last_view).unwrap();
+        let view_state = TableViewState {
+            filters: vec![ColumnFilter { column: "name".to_string(), expression: "contains:J".to_string() }],
+            data_offset: 100,
+            ..TableViewState::default()
+        };
+        persistence.save_view_state(file_path, "CSV Data", &view_state).unwrap();
+        assert_eq!(persistence.load_last_view(file_path), Some(last_view));
+        assert_eq!(persistence.load_view_state(file_path, "CSV Data"), Some(view_state));
+    }
+
+    #[test]
+    fn test_bookmark_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("bookmarks.csv");
+        fs::write(&test_file, "name,age\nJohn,25\nJane,30").unwrap();
+        let file_path = test_file.to_str().unwrap();
+
+        let persistence = SessionStore::new().unwrap();
+        assert!(persistence.load_bookmarks(file_path).is_empty());
+
+        let bookmarks = vec![Bookmark {
+            name: "bad age".to_string(),
+            table: "CSV Data".to_string(),
+            rowid: None,
+            position: 1,
+            sort: None,
+            column: Some("age".to_string()),
+        }];
+        persistence.save_bookmarks(file_path, &bookmarks).unwrap();
+        assert_eq!(persistence.load_bookmarks(file_path), bookmarks);
+
+        // Bookmarks, favorites and view state are stored side by side
+        persistence
+            .save_view_state(file_path, "CSV Data", &TableViewState::default())
+            .unwrap();
+        persistence
+            .save_favorite_tables(file_path, &["CSV Data".to_string()])
+            .unwrap();
+        assert_eq!(persistence.load_bookmarks(file_path), bookmarks);
+        assert_eq!(persistence.load_favorite_tables(file_path), vec!["CSV Data"]);
+    }
+
+    #[test]
+    fn test_query_history_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("history.csv");
+        fs::write(&test_file, "name,age\nJohn,25\nJane,30").unwrap();
+        let file_path = test_file.to_str().unwrap();
+
+        let persistence = SessionStore::new().unwrap();
+        assert!(persistence.load_query_history(file_path).is_empty());
+
+        let mut history = Vec::new();
+        push_query_history(&mut history, "SELECT * FROM x");
+        push_query_history(&mut history, "  ");
+        push_query_history(&mut history, "SELECT name FROM x ");
+        push_query_history(&mut history, "SELECT * FROM x");
+        assert_eq!(history, vec!["SELECT name FROM x", "SELECT * FROM x"]);
+
+        persistence.save_query_history(file_path, &history).unwrap();
+        persistence
+            .save_favorite_tables(file_path, &["CSV Data".to_string()])
+            .unwrap();
+        assert_eq!(persistence.load_query_history(file_path), history);
+
+        for i in 0..QUERY_HISTORY_LIMIT {
+            push_query_history(&mut history, &format!("SELECT {}", i));
+        }
+        assert_eq!(history.len(), QUERY_HISTORY_LIMIT);
+        assert_eq!(history[0], "SELECT 0");
+    }
+
+    #[test]
+    fn test_saved_query_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("saved_queries.csv");
+        fs::write(&test_file, "name,age\nJohn,25\nJane,30").unwrap();
+        let file_path = test_file.to_str().unwrap();
+
+        let persistence = SessionStore::new().unwrap();
+        assert!(persistence.load_saved_queries(file_path).is_empty());
+
+        let queries = vec![SavedQuery {
+            name: "adults".to_string(),
+            query: "SELECT * FROM x WHERE age >= 18".to_string(),
+        }];
+        persistence.save_saved_queries(file_path, &queries).unwrap();
+        persistence.save_query_history(file_path, &["SELECT 1".to_string()]).unwrap();
+        assert_eq!(persistence.load_saved_queries(file_path), queries);
+    }
+
+    #[test]
+    fn test_recent_files() {
+        let temp_dir = tempdir().unwrap();
+        let recent = RecentFiles::at(temp_dir.path().join("recent.json"));
+        assert!(recent.load().is_empty());
+
+        recent.record("/data/sales.db").unwrap();
+        recent.record("/data/people.csv").unwrap();
+        recent.record("/data/sales.db").unwrap();
+        let paths: Vec<String> = recent.load().into_iter().map(|file| file.path).collect();
+        assert_eq!(paths, vec!["/data/sales.db", "/data/people.csv"]);
+
+        for i in 0..RECENT_FILES_LIMIT {
+            recent.record(&format!("/data/{}.csv", i)).unwrap();
+        }
+        let loaded = recent.load();
+        assert_eq!(loaded.len(), RECENT_FILES_LIMIT);
+        assert_eq!(loaded[0].path, format!("/data/{}.csv", RECENT_FILES_LIMIT - 1));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+
+use crate::column_stats::{column_stats, is_missing, ColumnStats};
+use crate::config::NumberLocale;
+use crate::database::QueryResult;
+use crate::export::{html_escape, html_table, markdown_table};
+use crate::file_reader::{infer_column_type, ColumnType};
+use crate::value::Value;
+
+/// Distinct values shown as samples of each column
+const SAMPLE_VALUES: usize = 3;
+
+/// Longest sample shown before it is cut short
+const SAMPLE_WIDTH: usize = 24;
+
+/// Profile of one column of the report (`P`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    pub stats: ColumnStats,
+    pub column_type: ColumnType,           // the type most values have
+    pub mixed: Vec<(ColumnType, usize)>,   // values of other types, most frequent first
+    pub padded: usize,                     // text with leading or trailing whitespace
+    pub samples: Vec<String>,              // first distinct values, in table order
+}
+
+impl ColumnProfile {
+    /// Percentage of the rows that are missing
+    pub fn null_percent(&self) -> f64 {
+        percent(self.stats.nulls, self.stats.rows)
+    }
+
+    /// Percentage of the present values that are distinct; 100 for a unique column
+    pub fn distinct_percent(&self) -> f64 {
+        percent(self.stats.distinct, self.stats.rows - self.stats.nulls)
+    }
+
+    /// Values that look like data entry mistakes, e.g. `2 text values among int`
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues: Vec<String> = self
+            .mixed
+            .iter()
+            .map(|(ty, count)| format!("{} {} values among {}", count, ty.label(), self.column_type.label()))
+            .collect();
+        if self.padded > 0 {
+            issues.push(format!("{} values with leading or trailing whitespace", self.padded));
+        }
+        issues
+    }
+}
+
+/// Profile of every column of a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableProfile {
+    pub table: String,
+    pub rows: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+impl TableProfile {
+    /// The report as a GitHub-flavored Markdown document
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "# Profile of {}\n\n{} rows, {} columns\n\n{}",
+            self.table,
+            self.rows,
+            self.columns.len(),
+            markdown_table(&report_columns(), &self.report_rows())
+        )
+    }
+
+    /// The report as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Profile of {0}</title></head>\n<body>\n\
+             <h1>Profile of {0}</h1>\n<p>{1} rows, {2} columns</p>\n{3}</body>\n</html>\n",
+            html_escape(&self.table),
+            self.rows,
+            self.columns.len(),
+            html_table(&report_columns(), &self.report_rows())
+        )
+    }
+
+    fn report_rows(&self) -> Vec<Vec<String>> {
+        self.columns
+            .iter()
+            .map(|profile| {
+                vec![
+                    profile.stats.column.clone(),
+                    profile.column_type.label().to_string(),
+                    format!("{} ({:.1}%)", profile.stats.nulls, profile.null_percent()),
+                    format!("{} ({:.1}%)", profile.stats.distinct, profile.distinct_percent()),
+                    profile.stats.min.clone().unwrap_or_default(),
+                    profile.stats.max.clone().unwrap_or_default(),
+                    profile.samples.join(", "),
+                    profile.issues().join("; "),
+                ]
+            })
+            .collect()
+    }
+}
+
+fn report_columns() -> Vec<String> {
+    ["Column", "Type", "Nulls", "Distinct", "Min", "Max", "Samples", "Issues"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect()
+}
+
+/// Profile every column of `data` but SQLite's implicit rowid. Text cells are typed by what
+/// they parse as in `locale`.
+pub fn profile_table(table: &str, data: &QueryResult, locale: NumberLocale) -> TableProfile {
+    let columns = data
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| *column != "rowid")
+        .map(|(idx, column)| profile_column(column, data.rows.iter().filter_map(|row| row.get(idx)), locale))
+        .collect();
+    TableProfile { table: table.to_string(), rows: data.rows.len(), columns }
+}
+
+fn profile_column<'a>(column: &str, cells: impl Iterator<Item = &'a Value> + Clone, locale: NumberLocale) -> ColumnProfile {
+    const TYPES: [ColumnType; 5] = [ColumnType::Int, ColumnType::Float, ColumnType::Bool, ColumnType::Date, ColumnType::Text];
+    let mut counts = [0usize; TYPES.len()];
+    let mut padded = 0;
+    let mut seen = HashSet::new();
+    let mut samples = Vec::new();
+    for cell in cells.clone().filter(|cell| !is_missing(cell)) {
+        let ty = match cell {
+            Value::Int(_) => ColumnType::Int,
+            Value::Float(_) => ColumnType::Float,
+            Value::Bool(_) => ColumnType::Bool,
+            Value::Date(_) => ColumnType::Date,
+            Value::Blob(_) => ColumnType::Text,
+            Value::Text(text) => {
+                if text.trim() != text {
+                    padded += 1;
+                }
+                infer_column_type(std::iter::once(text.as_str()), locale)
+            }
+            Value::Null => continue,
+        };
+        counts[TYPES.iter().position(|t| *t == ty).unwrap_or(TYPES.len() - 1)] += 1;
+
+        let text = cell.to_string();
+        if samples.len() < SAMPLE_VALUES && seen.insert(text.clone()) {
+            samples.push(if text.chars().count() > SAMPLE_WIDTH {
+                format!("{}...", text.chars().take(SAMPLE_WIDTH - 3).collect::<String>())
+            } else {
+                text
+            });
+        }
+    }
+
+    // Integers among floats are floats too
+    if counts[1] > 0 {
+        counts[1] += std::mem::take(&mut counts[0]);
+    }
+    let dominant = (0..TYPES.len()).max_by_key(|&idx| (counts[idx], std::cmp::Reverse(idx))).unwrap_or(4);
+    let column_type = if counts[dominant] == 0 { ColumnType::Text } else { TYPES[dominant] };
+    let mut mixed: Vec<(ColumnType, usize)> = (0..TYPES.len())
+        .filter(|&idx| idx != dominant && counts[idx] > 0)
+        .map(|idx| (TYPES[idx], counts[idx]))
+        .collect();
+    mixed.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    ColumnProfile { stats: column_stats(column, cells, locale), column_type, mixed, padded, samples }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        total => count as f64 * 100.0 / total as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    fn sample() -> QueryResult {
+        let rows = text_rows(vec![
+            vec!["1", "1", "Ann"],
+            vec!["2", "2.5", " Bob"],
+            vec!["3", "n/a", ""],
+            vec!["4", "", "Ann"],
+        ]);
+        QueryResult {
+            columns: vec!["rowid".to_string(), "amount".to_string(), "name".to_string()],
+            total_rows: rows.len(),
+            rows,
+        }
+    }
+
+    #[test]
+    fn test_profile_table() {
+        let profile = profile_table("t", &sample(), NumberLocale::En);
+        assert_eq!(profile.rows, 4);
+        assert_eq!(profile.columns.len(), 2);
+
+        let amount = &profile.columns[0];
+        assert_eq!(amount.column_type, ColumnType::Float);
+        assert_eq!(amount.mixed, vec![(ColumnType::Text, 1)]);
+        assert_eq!(amount.null_percent(), 25.0);
+        assert_eq!(amount.samples, vec!["1", "2.5", "n/a"]);
+        assert_eq!(amount.issues(), vec!["1 text values among float"]);
+
+        let name = &profile.columns[1];
+        assert_eq!(name.column_type, ColumnType::Text);
+        assert_eq!((name.stats.distinct, name.padded), (2, 1));
+        assert!((name.distinct_percent() - 66.67).abs() < 0.01);
+        assert_eq!(name.issues(), vec!["1 values with leading or trailing whitespace"]);
+    }
+
+    #[test]
+    fn test_profile_exports() {
+        let profile = profile_table("t", &sample(), NumberLocale::En);
+        let markdown = profile.to_markdown();
+        assert!(markdown.starts_with("# Profile of t\n\n4 rows, 2 columns\n\n| Column | Type |"));
+        assert!(markdown.contains("| amount | float | 1 (25.0%) | 3 (100.0%) |"));
+        let html = profile.to_html();
+        assert!(html.contains("<h1>Profile of t</h1>"));
+        assert!(html.contains("<td>amount</td><td>float</td>"));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::database::TableSchema;
+use crate::value::Value;
+
+/// A copy of `row` to add as a new row (`A`): the rowid and primary key cells are left
+/// empty, so the database assigns them when the row is saved
+pub fn duplicate_row(row: &[Value], columns: &[String], schema: Option<&TableSchema>) -> Vec<Value> {
+    let is_key = |column: &str| {
+        column == "rowid"
+            || schema.is_some_and(|schema| schema.columns.iter().any(|c| c.primary_key && c.name == column))
+    };
+    row.iter()
+        .zip(columns)
+        .map(|(value, column)| if is_key(column) { Value::Text(String::new()) } else { value.clone() })
+        .collect()
+}
+
+/// The insert form (`+`), filling in a new row one column at a time
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertForm {
+    pub fields: Vec<FormField>,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormField {
+    pub column: usize,        // index in the page's columns
+    pub name: String,
+    pub input: String,
+    pub hint: Option<String>, // what an empty field stands for, e.g. the column's default
+}
+
+impl InsertForm {
+    /// A form over the `editable` columns of the page, with the column defaults of the schema
+    /// as hints; left empty, a field is left out of the INSERT so the default applies
+    pub fn new(columns: &[String], editable: impl Fn(usize) -> bool, schema: Option<&TableSchema>) -> Self {
+        let fields = columns
+            .iter()
+            .enumerate()
+            .filter(|(idx, name)| *name != "rowid" && editable(*idx))
+            .map(|(idx, name)| {
+                let column = schema.and_then(|schema| schema.columns.iter().find(|c| &c.name == name));
+                let hint = column.and_then(|c| match &c.default {
+                    Some(default) => Some(format!("default {}", default)),
+                    None if c.primary_key => Some("assigned by the database".to_string()),
+                    None => None,
+                });
+                FormField { column: idx, name: name.clone(), input: String::new(), hint }
+            })
+            .collect();
+        Self { fields, selected: 0 }
+    }
+
+    pub fn current(&mut self) -> Option<&mut FormField> {
+        self.fields.get_mut(self.selected)
+    }
+
+    /// Move to the next field, or the previous one; false when already at the end
+    pub fn step(&mut self, forward: bool) -> bool {
+        let next = if forward { self.selected + 1 } else { self.selected.wrapping_sub(1) };
+        if next < self.fields.len() {
+            self.selected = next;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The new row with `width` cells, as typed into the form
+    pub fn row(&self, width: usize) -> Vec<Value> {
+        let mut row = vec![Value::Text(String::new()); width];
+        for field in &self.fields {
+            if let Some(cell) = row.get_mut(field.column) {
+                *cell = Value::Text(field.input.clone());
+            }
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::ColumnSchema;
+
+    fn schema() -> TableSchema {
+        TableSchema {
+            columns: vec![
+                ColumnSchema { name: "id".to_string(), column_type: "INTEGER".to_string(), primary_key: true, ..Default::default() },
+                ColumnSchema { name: "name".to_string(), column_type: "TEXT".to_string(), ..Default::default() },
+                ColumnSchema { name: "qty".to_string(), default: Some("0".to_string()), ..Default::default() },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_duplicate_row() {
+        let columns: Vec<String> = ["rowid", "id", "name", "qty"].iter().map(|c| c.to_string()).collect();
+        let row = vec![Value::Int(7), Value::Int(7), Value::from("bolt"), Value::Int(3)];
+        let copy = duplicate_row(&row, &columns, Some(&schema()));
+        assert_eq!(copy, vec![Value::Text(String::new()), Value::Text(String::new()), Value::from("bolt"), Value::Int(3)]);
+        assert_eq!(duplicate_row(&row[1..], &columns[1..], None), row[1..].to_vec());
+    }
+
+    #[test]
+    fn test_insert_form() {
+        let columns: Vec<String> = ["rowid", "id", "name", "qty", "total"].iter().map(|c| c.to_string()).collect();
+        // The last column is computed
+        let mut form = InsertForm::new(&columns, |idx| idx < 4, Some(&schema()));
+        let names: Vec<&str> = form.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "name", "qty"]);
+        assert_eq!(form.fields[0].hint.as_deref(), Some("assigned by the database"));
+        assert_eq!(form.fields[2].hint.as_deref(), Some("default 0"));
+
+        assert!(form.step(true));
+        form.current().unwrap().input.push_str("nut");
+        assert!(form.step(true));
+        assert!(!form.step(true));
+        assert!(form.step(false));
+        assert_eq!(form.row(5)[2], Value::from("nut"));
+        assert_eq!(form.row(5)[3], Value::Text(String::new()));
+    }
+}
@@ -0,0 +1,1327 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use flate2::bufread::ZlibDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use crate::database::QueryResult;
+use crate::file_reader::{decode_text, read_input_bytes};
+use crate::value::text_rows;
+
+/// Value labels of one column, keyed by the value as it is displayed ("1" -> "Male")
+type ValueLabels = HashMap<String, String>;
+
+/// The statistics package a data file comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatFormat {
+    Stata,
+    Spss,
+    Sas,
+}
+
+impl StatFormat {
+    /// Name shown in the sidebar and status messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatFormat::Stata => "Stata",
+            StatFormat::Spss => "SPSS",
+            StatFormat::Sas => "SAS",
+        }
+    }
+
+    /// Name matched against the `file_types` of external tools
+    pub fn file_type(&self) -> &'static str {
+        match self {
+            StatFormat::Stata => "dta",
+            StatFormat::Spss => "sav",
+            StatFormat::Sas => "sas7bdat",
+        }
+    }
+}
+
+/// Read a Stata, SPSS or SAS data file. Coded columns show their value labels, date
+/// formats are shown as dates and missing values read as NULL.
+#[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()), err)]
+pub fn read_statistical_file<P: AsRef<Path>>(path: P, format: StatFormat) -> Result<QueryResult> {
+    let path = path.as_ref();
+    let bytes = read_input_bytes(path)?;
+    let result = match format {
+        StatFormat::Stata => read_stata(&bytes),
+        StatFormat::Spss => read_spss(&bytes),
+        StatFormat::Sas => read_sas(&bytes),
+    };
+    result.with_context(|| format!("Failed to read {} file {}", format.name(), path.display()))
+}
+
+/// A binary file read with its own byte order
+struct Binary<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Binary<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8]> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| anyhow::anyhow!("File is truncated at byte {}", offset))
+    }
+
+    /// An unsigned integer of 1 to 8 bytes
+    fn uint(&self, offset: usize, len: usize) -> Result<u64> {
+        let bytes = self.bytes(offset, len)?;
+        let fold = |value: u64, &byte: &u8| (value << 8) | byte as u64;
+        Ok(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16> {
+        Ok(self.uint(offset, 2)? as u16)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32> {
+        Ok(self.uint(offset, 4)? as u32)
+    }
+
+    fn i32(&self, offset: usize) -> Result<i32> {
+        Ok(self.u32(offset)? as i32)
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64> {
+        self.uint(offset, 8)
+    }
+
+    fn f32(&self, offset: usize) -> Result<f32> {
+        Ok(f32::from_bits(self.u32(offset)?))
+    }
+
+    fn f64(&self, offset: usize) -> Result<f64> {
+        Ok(f64::from_bits(self.u64(offset)?))
+    }
+
+    /// Fixed-width text padded with NULs (and sometimes garbage after the first NUL)
+    fn text(&self, offset: usize, len: usize) -> Result<String> {
+        Ok(c_string(self.bytes(offset, len)?))
+    }
+
+    /// Offset just past `tag`, which must start at `offset`
+    fn after_tag(&self, offset: usize, tag: &str) -> Result<usize> {
+        if self.bytes(offset, tag.len())? != tag.as_bytes() {
+            return Err(anyhow::anyhow!("Expected {} at byte {}", tag, offset));
+        }
+        Ok(offset + tag.len())
+    }
+}
+
+fn c_string(raw: &[u8]) -> String {
+    let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    decode_text(&raw[..end])
+}
+
+fn null() -> String {
+    "NULL".to_string()
+}
+
+/// Format a date stored as days since `epoch`, or None when out of range
+fn format_days(epoch: NaiveDate, days: f64) -> Option<String> {
+    let date = epoch.checked_add_signed(chrono::Duration::try_days(days.floor() as i64)?)?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+/// Format a timestamp stored as seconds since `epoch`
+fn format_seconds(epoch: NaiveDate, seconds: f64) -> Option<String> {
+    let millis = chrono::Duration::try_milliseconds((seconds * 1000.0).round() as i64)?;
+    let datetime: NaiveDateTime = epoch.and_hms_opt(0, 0, 0)?.checked_add_signed(millis)?;
+    Some(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Format a time of day stored as seconds since midnight
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.round() as i64;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+fn epoch_1960() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1960, 1, 1).unwrap_or_default()
+}
+
+/// Replace coded values by their labels, leaving unlabelled values as they are
+fn finish(columns: Vec<String>, mut rows: Vec<Vec<String>>, labels: &[Option<ValueLabels>]) -> QueryResult {
+    for row in &mut rows {
+        for (cell, labels) in row.iter_mut().zip(labels) {
+            if let Some(label) = labels.as_ref().and_then(|labels| labels.get(cell.as_str())) {
+                *cell = label.clone();
+            }
+        }
+    }
+    let total_rows = rows.len();
+    QueryResult { columns, rows: text_rows(rows), total_rows }
+}
+
+// Stata: releases 111 to 115 are plain binary, 117 to 119 wrap the same sections in tags.
+// Variable types are normalised to the 117+ codes.
+const STATA_STRL: u16 = 32768;
+const STATA_DOUBLE: u16 = 65526;
+const STATA_FLOAT: u16 = 65527;
+const STATA_LONG: u16 = 65528;
+const STATA_INT: u16 = 65529;
+const STATA_BYTE: u16 = 65530;
+
+struct StataLayout {
+    release: u32,
+    nobs: usize,
+    types: Vec<u16>,
+    names: Vec<String>,
+    formats: Vec<String>,
+    label_names: Vec<String>,
+    data_offset: usize,
+    strls: HashMap<(u64, u64), String>,
+    value_labels: HashMap<String, ValueLabels>,
+}
+
+fn read_stata(bytes: &[u8]) -> Result<QueryResult> {
+    let (binary, layout) = if bytes.starts_with(b"<stata_dta>") {
+        stata_tagged_layout(bytes)?
+    } else {
+        stata_binary_layout(bytes)?
+    };
+
+    let widths = layout
+        .types
+        .iter()
+        .map(|&kind| stata_width(kind))
+        .collect::<Result<Vec<_>>>()?;
+    let row_len: usize = widths.iter().sum();
+
+    let mut rows = Vec::with_capacity(layout.nobs);
+    for row_idx in 0..layout.nobs {
+        let mut offset = layout.data_offset + row_idx * row_len;
+        let mut row = Vec::with_capacity(widths.len());
+        for (col, &width) in widths.iter().enumerate() {
+            row.push(stata_cell(&binary, &layout, offset, col)?);
+            offset += width;
+        }
+        rows.push(row);
+    }
+
+    let labels: Vec<Option<ValueLabels>> = layout
+        .label_names
+        .iter()
+        .map(|name| layout.value_labels.get(name).cloned())
+        .collect();
+    Ok(finish(layout.names, rows, &labels))
+}
+
+fn stata_width(kind: u16) -> Result<usize> {
+    Ok(match kind {
+        1..=2045 => kind as usize,
+        STATA_STRL | STATA_DOUBLE => 8,
+        STATA_FLOAT | STATA_LONG => 4,
+        STATA_INT => 2,
+        STATA_BYTE => 1,
+        _ => return Err(anyhow::anyhow!("Unknown Stata variable type {}", kind)),
+    })
+}
+
+fn stata_cell(binary: &Binary, layout: &StataLayout, offset: usize, col: usize) -> Result<String> {
+    // Values above these are the missing values ., .a, ..., .z
+    let number = match layout.types[col] {
+        kind @ 1..=2045 => return binary.text(offset, kind as usize),
+        STATA_STRL => {
+            // (variable, observation) key into the strL section
+            let v_len = match layout.release {
+                117 => 4,
+                118 => 2,
+                _ => 3,
+            };
+            let key = (binary.uint(offset, v_len)?, binary.uint(offset + v_len, 8 - v_len)?);
+            return Ok(layout.strls.get(&key).cloned().unwrap_or_default());
+        }
+        STATA_BYTE => {
+            let value = binary.u8(offset)? as i8;
+            return Ok(if value > 100 { null() } else { value.to_string() });
+        }
+        STATA_INT => {
+            let value = binary.u16(offset)? as i16;
+            return Ok(if value > 32_740 { null() } else { value.to_string() });
+        }
+        STATA_LONG => {
+            let value = binary.i32(offset)?;
+            if value > 2_147_483_620 {
+                return Ok(null());
+            }
+            value as f64
+        }
+        STATA_FLOAT => {
+            let value = binary.f32(offset)?;
+            if value.is_nan() || value >= f32::from_bits(0x7f00_0000) {
+                return Ok(null());
+            }
+            if !is_stata_date(&layout.formats[col]) {
+                return Ok(value.to_string());
+            }
+            value as f64
+        }
+        _ => {
+            let value = binary.f64(offset)?;
+            if value.is_nan() || value >= f64::from_bits(0x7fe0_0000_0000_0000) {
+                return Ok(null());
+            }
+            value
+        }
+    };
+
+    let format = layout.formats[col].as_str();
+    let formatted = if format.starts_with("%td") || format.starts_with("%d") {
+        format_days(epoch_1960(), number)
+    } else if format.starts_with("%tc") || format.starts_with("%tC") {
+        format_seconds(epoch_1960(), number / 1000.0)
+    } else {
+        None
+    };
+    Ok(formatted.unwrap_or_else(|| number.to_string()))
+}
+
+fn is_stata_date(format: &str) -> bool {
+    ["%td", "%d", "%tc", "%tC"].iter().any(|prefix| format.starts_with(prefix))
+}
+
+/// Releases 111 to 115 (Stata 7 to 12)
+fn stata_binary_layout(bytes: &[u8]) -> Result<(Binary<'_>, StataLayout)> {
+    let release = *bytes.first().ok_or_else(|| anyhow::anyhow!("File is empty"))? as u32;
+    if !matches!(release, 111 | 113..=115) {
+        return Err(anyhow::anyhow!("Stata file format {} is not supported", release));
+    }
+    let binary = Binary { data: bytes, big_endian: bytes.get(1) == Some(&1) };
+    let nvar = binary.u16(4)? as usize;
+    let nobs = binary.u32(6)? as usize;
+
+    let mut offset = 109; // release, byte order, file type, nvar, nobs, data label, timestamp
+    let types = (0..nvar)
+        .map(|i| {
+            Ok(match binary.u8(offset + i)? {
+                251 => STATA_BYTE,
+                252 => STATA_INT,
+                253 => STATA_LONG,
+                254 => STATA_FLOAT,
+                255 => STATA_DOUBLE,
+                width => width as u16,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    offset += nvar;
+    let names = stata_strings(&binary, &mut offset, nvar, 33)?;
+    offset += 2 * (nvar + 1); // sort list
+    let formats = stata_strings(&binary, &mut offset, nvar, if release >= 114 { 49 } else { 12 })?;
+    let label_names = stata_strings(&binary, &mut offset, nvar, 33)?;
+    offset += 81 * nvar; // variable labels
+
+    // Expansion fields end with a zero type and length
+    loop {
+        let kind = binary.u8(offset)?;
+        let len = binary.u32(offset + 1)? as usize;
+        offset += 5 + len;
+        if kind == 0 && len == 0 {
+            break;
+        }
+    }
+
+    let data_offset = offset;
+    let row_len = types.iter().map(|&kind| stata_width(kind)).sum::<Result<usize>>()?;
+    let mut offset = data_offset + nobs * row_len;
+    let mut value_labels = HashMap::new();
+    while offset + 40 <= bytes.len() {
+        let len = binary.u32(offset)? as usize;
+        let name = binary.text(offset + 4, 33)?;
+        value_labels.insert(name, stata_label_table(&binary, offset + 40, len)?);
+        offset += 40 + len;
+    }
+
+    let layout = StataLayout {
+        release,
+        nobs,
+        types,
+        names,
+        formats,
+        label_names,
+        data_offset,
+        strls: HashMap::new(),
+        value_labels,
+    };
+    Ok((binary, layout))
+}
+
+/// Releases 117 to 119 (Stata 13 and newer), located through the section map
+fn stata_tagged_layout(bytes: &[u8]) -> Result<(Binary<'_>, StataLayout)> {
+    let mut binary = Binary { data: bytes, big_endian: false };
+    let offset = binary.after_tag(0, "<stata_dta><header><release>")?;
+    let release: u32 = std::str::from_utf8(binary.bytes(offset, 3)?)?.parse()?;
+    if !(117..=119).contains(&release) {
+        return Err(anyhow::anyhow!("Stata file format {} is not supported", release));
+    }
+    let offset = binary.after_tag(offset + 3, "</release><byteorder>")?;
+    binary.big_endian = binary.bytes(offset, 3)? == b"MSF";
+
+    let offset = binary.after_tag(offset + 3, "</byteorder><K>")?;
+    let k_len = if release == 119 { 4 } else { 2 };
+    let nvar = binary.uint(offset, k_len)? as usize;
+    let offset = binary.after_tag(offset + k_len, "</K><N>")?;
+    let n_len = if release == 117 { 4 } else { 8 };
+    let nobs = binary.uint(offset, n_len)? as usize;
+    let offset = binary.after_tag(offset + n_len, "</N><label>")?;
+    let label_len_len = if release == 117 { 1 } else { 2 };
+    let label_len = binary.uint(offset, label_len_len)? as usize;
+    let offset = binary.after_tag(offset + label_len_len + label_len, "</label><timestamp>")?;
+    let timestamp_len = binary.u8(offset)? as usize;
+    let offset = binary.after_tag(offset + 1 + timestamp_len, "</timestamp></header><map>")?;
+    let map = (0..14)
+        .map(|i| Ok(binary.u64(offset + 8 * i)? as usize))
+        .collect::<Result<Vec<_>>>()?;
+
+    let name_len = if release == 117 { 33 } else { 129 };
+    let format_len = if release == 117 { 49 } else { 57 };
+
+    let mut offset = binary.after_tag(map[2], "<variable_types>")?;
+    let types = (0..nvar)
+        .map(|i| binary.u16(offset + 2 * i))
+        .collect::<Result<Vec<_>>>()?;
+    offset = binary.after_tag(map[3], "<varnames>")?;
+    let names = stata_strings(&binary, &mut offset, nvar, name_len)?;
+    offset = binary.after_tag(map[5], "<formats>")?;
+    let formats = stata_strings(&binary, &mut offset, nvar, format_len)?;
+    offset = binary.after_tag(map[6], "<value_label_names>")?;
+    let label_names = stata_strings(&binary, &mut offset, nvar, name_len)?;
+    let data_offset = binary.after_tag(map[9], "<data>")?;
+
+    // Long strings: GSO, variable, observation, type, length, contents
+    let mut strls = HashMap::new();
+    let o_len = if release == 117 { 4 } else { 8 };
+    let mut offset = binary.after_tag(map[10], "<strls>")?;
+    while binary.bytes(offset, 3).is_ok_and(|tag| tag == b"GSO") {
+        let v = binary.u32(offset + 3)? as u64;
+        let o = binary.uint(offset + 7, o_len)?;
+        let binary_contents = binary.u8(offset + 7 + o_len)? == 129;
+        let len = binary.u32(offset + 8 + o_len)? as usize;
+        let contents = binary.bytes(offset + 12 + o_len, len)?;
+        let text = if binary_contents { decode_text(contents) } else { c_string(contents) };
+        strls.insert((v, o), text);
+        offset += 12 + o_len + len;
+    }
+
+    let mut value_labels = HashMap::new();
+    let mut offset = binary.after_tag(map[11], "<value_labels>")?;
+    while let Ok(table_offset) = binary.after_tag(offset, "<lbl>") {
+        let len = binary.u32(table_offset)? as usize;
+        let name = binary.text(table_offset + 4, name_len)?;
+        let table_start = table_offset + 4 + name_len + 3;
+        value_labels.insert(name, stata_label_table(&binary, table_start, len)?);
+        offset = binary.after_tag(table_start + len, "</lbl>")?;
+    }
+
+    let layout = StataLayout {
+        release,
+        nobs,
+        types,
+        names,
+        formats,
+        label_names,
+        data_offset,
+        strls,
+        value_labels,
+    };
+    Ok((binary, layout))
+}
+
+fn stata_strings(binary: &Binary, offset: &mut usize, count: usize, width: usize) -> Result<Vec<String>> {
+    let strings = (0..count)
+        .map(|i| binary.text(*offset + i * width, width))
+        .collect::<Result<Vec<_>>>()?;
+    *offset += count * width;
+    Ok(strings)
+}
+
+/// A value label table: count, text length, text offsets, values, then the texts
+fn stata_label_table(binary: &Binary, offset: usize, len: usize) -> Result<ValueLabels> {
+    let count = binary.u32(offset)? as usize;
+    let text_len = binary.u32(offset + 4)? as usize;
+    let values_offset = offset + 8 + 4 * count;
+    let text = binary.bytes(values_offset + 4 * count, text_len.min(len))?;
+
+    let mut labels = ValueLabels::new();
+    for i in 0..count {
+        let start = binary.u32(offset + 8 + 4 * i)? as usize;
+        let value = binary.i32(values_offset + 4 * i)?;
+        labels.insert(value.to_string(), c_string(text.get(start..).unwrap_or_default()));
+    }
+    Ok(labels)
+}
+
+// SPSS: a dictionary of records, then cases of 8-byte slots, optionally bytecode compressed
+// (`.sav`) or bytecode compressed inside zlib blocks (`.zsav`).
+const SPSS_SYSMIS: f64 = -f64::MAX;
+
+struct SpssVariable {
+    short_name: String,
+    width: usize, // 0 for numbers
+    format_type: u32,
+    slot: usize,
+}
+
+/// Variables (by slot) sharing a set of value labels, with the raw 8-byte values
+type SpssLabelSet = (Vec<usize>, Vec<([u8; 8], String)>);
+
+struct SpssColumn {
+    name: String,
+    numeric: bool,
+    format_type: u32,
+    segments: Vec<(usize, usize)>, // (slot, bytes used) of each piece of a very long string
+    labels: Option<ValueLabels>,
+}
+
+fn read_spss(bytes: &[u8]) -> Result<QueryResult> {
+    let magic = bytes.get(..4).unwrap_or_default();
+    if magic != b"$FL2" && magic != b"$FL3" {
+        return Err(anyhow::anyhow!("Not an SPSS system file"));
+    }
+    // The layout code is 2 or 3, which tells the byte order
+    let layout_code = Binary { data: bytes, big_endian: false }.u32(64)?;
+    let binary = Binary { data: bytes, big_endian: !matches!(layout_code, 2 | 3) };
+    let compression = binary.i32(72)?;
+    let case_count = binary.i32(80)?;
+    let bias = binary.f64(84)?;
+
+    let mut variables: Vec<SpssVariable> = Vec::new();
+    let mut slot_count = 0;
+    let mut label_sets: Vec<SpssLabelSet> = Vec::new();
+    let mut long_names = HashMap::new();
+    let mut very_long_widths = HashMap::new();
+
+    let mut offset = 176;
+    loop {
+        let record_type = binary.i32(offset)?;
+        offset += 4;
+        match record_type {
+            2 => {
+                let width = binary.i32(offset)?;
+                let has_label = binary.i32(offset + 4)? == 1;
+                let missing_count = binary.i32(offset + 8)?.unsigned_abs() as usize;
+                let print_format = binary.u32(offset + 12)?;
+                let short_name = binary.text(offset + 20, 8)?.trim_end().to_string();
+                offset += 28;
+                if has_label {
+                    let len = binary.u32(offset)? as usize;
+                    offset += 4 + len.div_ceil(4) * 4;
+                }
+                offset += 8 * missing_count;
+                // Strings wider than 8 bytes continue in records of width -1
+                if width >= 0 {
+                    variables.push(SpssVariable {
+                        short_name,
+                        width: width as usize,
+                        format_type: (print_format >> 16) & 0xFF,
+                        slot: slot_count,
+                    });
+                }
+                slot_count += 1;
+            }
+            3 => {
+                let count = binary.u32(offset)? as usize;
+                offset += 4;
+                let mut labels = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let value: [u8; 8] = binary.bytes(offset, 8)?.try_into()?;
+                    let len = binary.u8(offset + 8)? as usize;
+                    labels.push((value, decode_text(binary.bytes(offset + 9, len)?)));
+                    offset += 8 + (len + 1).div_ceil(8) * 8;
+                }
+                // Always followed by a type 4 record listing the variables (1-based slots)
+                if binary.i32(offset)? != 4 {
+                    return Err(anyhow::anyhow!("Value labels without variables at byte {}", offset));
+                }
+                let var_count = binary.u32(offset + 4)? as usize;
+                let slots = (0..var_count)
+                    .map(|i| Ok((binary.u32(offset + 8 + 4 * i)? as usize).saturating_sub(1)))
+                    .collect::<Result<Vec<_>>>()?;
+                offset += 8 + 4 * var_count;
+                label_sets.push((slots, labels));
+            }
+            6 => {
+                let lines = binary.u32(offset)? as usize;
+                offset += 4 + 80 * lines;
+            }
+            7 => {
+                let subtype = binary.i32(offset)?;
+                let size = binary.u32(offset + 4)? as usize;
+                let count = binary.u32(offset + 8)? as usize;
+                let data = decode_text(binary.bytes(offset + 12, size * count)?);
+                offset += 12 + size * count;
+                match subtype {
+                    // SHORT=Long name pairs separated by tabs
+                    13 => long_names.extend(spss_pairs(&data)),
+                    // SHORT=00300 widths of strings longer than 255 bytes
+                    14 => very_long_widths.extend(
+                        spss_pairs(&data).filter_map(|(name, width)| Some((name, width.parse::<usize>().ok()?))),
+                    ),
+                    _ => {}
+                }
+            }
+            999 => {
+                offset += 4;
+                break;
+            }
+            _ => return Err(anyhow::anyhow!("Unknown SPSS record type {} at byte {}", record_type, offset - 4)),
+        }
+    }
+
+    let data = match compression {
+        0 => bytes[offset.min(bytes.len())..].to_vec(),
+        1 => spss_decompress(&binary, bytes.get(offset..).unwrap_or_default(), bias),
+        2 => {
+            let blocks_end = binary.u64(offset + 8)? as usize;
+            let blocks = bytes.get(offset + 24..blocks_end).unwrap_or_default();
+            spss_decompress(&binary, &spss_inflate(blocks)?, bias)
+        }
+        _ => return Err(anyhow::anyhow!("Unknown SPSS compression {}", compression)),
+    };
+
+    // Very long strings are stored as several variables of 252 useful bytes each
+    let mut columns = Vec::new();
+    let mut idx = 0;
+    while idx < variables.len() {
+        let variable = &variables[idx];
+        let width = very_long_widths.get(&variable.short_name).copied().unwrap_or(variable.width);
+        let segment_count = if width > 255 { width.div_ceil(252) } else { 1 };
+        let segments = variables[idx..(idx + segment_count).min(variables.len())]
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let used = if segment_count == 1 { segment.width } else { 252.min(width - 252 * i) };
+                (segment.slot, used)
+            })
+            .collect();
+        let labels = label_sets
+            .iter()
+            .find(|(slots, _)| slots.contains(&variable.slot))
+            .map(|(_, labels)| {
+                labels
+                    .iter()
+                    .map(|(raw, label)| {
+                        let value = if variable.width == 0 {
+                            spss_number(f64_from(raw, binary.big_endian))
+                        } else {
+                            decode_text(raw).trim_end().to_string()
+                        };
+                        (value, label.clone())
+                    })
+                    .collect()
+            });
+        columns.push(SpssColumn {
+            name: long_names.get(&variable.short_name).cloned().unwrap_or_else(|| variable.short_name.clone()),
+            numeric: variable.width == 0,
+            format_type: variable.format_type,
+            segments,
+            labels,
+        });
+        idx += segment_count;
+    }
+
+    let case_len = slot_count * 8;
+    let mut case_total = data.len().checked_div(case_len).unwrap_or(0);
+    if case_count >= 0 {
+        case_total = case_total.min(case_count as usize);
+    }
+    let data = Binary { data: &data, big_endian: binary.big_endian };
+    let mut rows = Vec::with_capacity(case_total);
+    for case_idx in 0..case_total {
+        let case_offset = case_idx * case_len;
+        let row = columns
+            .iter()
+            .map(|column| {
+                if column.numeric {
+                    let (slot, _) = column.segments[0];
+                    return Ok(spss_cell(data.f64(case_offset + 8 * slot)?, column.format_type));
+                }
+                let mut raw = Vec::new();
+                for &(slot, used) in &column.segments {
+                    raw.extend_from_slice(data.bytes(case_offset + 8 * slot, used)?);
+                }
+                Ok(decode_text(&raw).trim_end().to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        rows.push(row);
+    }
+
+    let labels: Vec<Option<ValueLabels>> = columns.iter_mut().map(|column| column.labels.take()).collect();
+    let names = columns.into_iter().map(|column| column.name).collect();
+    Ok(finish(names, rows, &labels))
+}
+
+fn f64_from(raw: &[u8; 8], big_endian: bool) -> f64 {
+    if big_endian { f64::from_be_bytes(*raw) } else { f64::from_le_bytes(*raw) }
+}
+
+fn spss_pairs(data: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    data.split(['\t', '\0'])
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+}
+
+fn spss_number(value: f64) -> String {
+    if value == SPSS_SYSMIS || value.is_nan() { null() } else { value.to_string() }
+}
+
+/// Dates are seconds since the start of the Gregorian calendar, 1582-10-14
+fn spss_cell(value: f64, format_type: u32) -> String {
+    if value == SPSS_SYSMIS || value.is_nan() {
+        return null();
+    }
+    let epoch = NaiveDate::from_ymd_opt(1582, 10, 14).unwrap_or_default();
+    let formatted = match format_type {
+        20 | 23 | 24 | 28 | 29 | 30 | 38 | 39 => format_days(epoch, value / 86_400.0),
+        22 | 41 => format_seconds(epoch, value),
+        21 => Some(format_time(value)),
+        _ => None,
+    };
+    formatted.unwrap_or_else(|| value.to_string())
+}
+
+/// Expand bytecode: each block of eight codes is followed by the raw slots it refers to
+fn spss_decompress(binary: &Binary, compressed: &[u8], bias: f64) -> Vec<u8> {
+    let number = |value: f64| {
+        if binary.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    };
+    let mut data = Vec::new();
+    let mut offset = 0;
+    'blocks: while let Some(codes) = compressed.get(offset..offset + 8) {
+        offset += 8;
+        for &code in codes {
+            match code {
+                0 => {}
+                1..=251 => data.extend_from_slice(&number(code as f64 - bias)),
+                252 => break 'blocks,
+                253 => {
+                    let Some(raw) = compressed.get(offset..offset + 8) else {
+                        break 'blocks;
+                    };
+                    data.extend_from_slice(raw);
+                    offset += 8;
+                }
+                254 => data.extend_from_slice(b"        "),
+                255 => data.extend_from_slice(&number(SPSS_SYSMIS)),
+            }
+        }
+    }
+    data
+}
+
+/// Concatenate the zlib streams of a `.zsav` file
+fn spss_inflate(mut blocks: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    while !blocks.is_empty() {
+        let mut decoder = ZlibDecoder::new(blocks);
+        decoder.read_to_end(&mut data)?;
+        let consumed = decoder.total_in() as usize;
+        if consumed == 0 {
+            break;
+        }
+        blocks = &blocks[consumed.min(blocks.len())..];
+    }
+    Ok(data)
+}
+
+// SAS: a header, then pages of subheaders (metadata or compressed rows) and uncompressed rows
+const SAS_MAGIC: [u8; 32] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc2, 0xea, 0x81, 0x60,
+    0xb3, 0x14, 0x11, 0xcf, 0xbd, 0x92, 0x08, 0x00, 0x09, 0xc7, 0x31, 0x8c, 0x18, 0x1f, 0x10, 0x11,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum SasCompression {
+    Rle,
+    Rdc,
+}
+
+struct SasColumn {
+    offset: usize,
+    width: usize,
+    numeric: bool,
+}
+
+#[derive(Default)]
+struct SasMeta {
+    row_length: usize,
+    row_count: usize,
+    mix_page_rows: usize,
+    compression: Option<SasCompression>,
+    text_blocks: Vec<Vec<u8>>,
+    names: Vec<String>,
+    columns: Vec<SasColumn>,
+    formats: Vec<String>,
+}
+
+impl SasMeta {
+    fn text(&self, binary: &Binary, offset: usize) -> Result<String> {
+        let block = binary.u16(offset)? as usize;
+        let start = binary.u16(offset + 2)? as usize;
+        let len = binary.u16(offset + 4)? as usize;
+        let raw = self
+            .text_blocks
+            .get(block)
+            .and_then(|text| text.get(start..start + len))
+            .unwrap_or_default();
+        Ok(decode_text(raw).trim_end().to_string())
+    }
+}
+
+fn read_sas(bytes: &[u8]) -> Result<QueryResult> {
+    if !bytes.starts_with(&SAS_MAGIC) || bytes.len() < 288 {
+        return Err(anyhow::anyhow!("Not a SAS data file"));
+    }
+    let wide = bytes[32] == 0x33; // 64-bit layout
+    let align = if bytes[35] == 0x33 { 4 } else { 0 };
+    let binary = Binary { data: bytes, big_endian: bytes[37] == 0x00 };
+    let header_len = binary.u32(196 + align)? as usize;
+    let page_size = binary.u32(200 + align)? as usize;
+    if page_size == 0 {
+        return Err(anyhow::anyhow!("SAS page size is zero"));
+    }
+
+    let int_len = if wide { 8 } else { 4 };
+    let page_header_len = if wide { 32 } else { 16 };
+    let pointer_len = if wide { 24 } else { 12 };
+    let mut meta = SasMeta::default();
+    let mut raw_rows: Vec<Vec<u8>> = Vec::new();
+
+    let mut page = header_len;
+    while page + page_size <= bytes.len() {
+        let page_type = binary.u16(page + page_header_len)?;
+        let block_count = binary.u16(page + page_header_len + 2)? as usize;
+        let subheader_count = binary.u16(page + page_header_len + 4)? as usize;
+        let pointers = page + page_header_len + 8;
+        let kind = page_type & 0x0F00;
+        if page_type & 0x9000 == 0x9000 {
+            page += page_size;
+            continue; // page of a compressed file's free-space map
+        }
+
+        if matches!(kind, 0x0000 | 0x0200 | 0x0400) {
+            for i in 0..subheader_count {
+                let pointer = pointers + i * pointer_len;
+                let offset = page + binary.uint(pointer, int_len)? as usize;
+                let len = binary.uint(pointer + int_len, int_len)? as usize;
+                let compression = binary.u8(pointer + 2 * int_len)?;
+                let subheader_type = binary.u8(pointer + 2 * int_len + 1)?;
+                if len == 0 || compression == 1 {
+                    continue; // empty or truncated
+                }
+                if meta.compression.is_some() && subheader_type == 1 && matches!(compression, 0 | 4) {
+                    let raw = binary.bytes(offset, len)?;
+                    raw_rows.push(match (compression, meta.compression) {
+                        (4, Some(SasCompression::Rle)) => sas_rle_decompress(raw, meta.row_length),
+                        (4, Some(SasCompression::Rdc)) => sas_rdc_decompress(raw, meta.row_length),
+                        _ => raw.to_vec(),
+                    });
+                    continue;
+                }
+                sas_subheader(&binary, offset, len, int_len, &mut meta)?;
+            }
+        }
+
+        // Uncompressed rows follow the subheader pointers on data and mix pages
+        let rows_left = meta.row_count.saturating_sub(raw_rows.len());
+        let (rows_offset, row_count) = match kind {
+            0x0100 => (pointers, block_count.min(rows_left)),
+            0x0200 => {
+                let end = pointers + subheader_count * pointer_len;
+                (end + (end - page) % 8, meta.mix_page_rows.min(rows_left))
+            }
+            _ => (pointers, 0),
+        };
+        for row in 0..row_count {
+            raw_rows.push(binary.bytes(rows_offset + row * meta.row_length, meta.row_length)?.to_vec());
+        }
+        page += page_size;
+    }
+
+    let rows = raw_rows
+        .iter()
+        .take(meta.row_count)
+        .map(|raw| {
+            meta.columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    let format = meta.formats.get(i).map(String::as_str).unwrap_or_default();
+                    let cell = raw.get(column.offset..column.offset + column.width).unwrap_or_default();
+                    if column.numeric {
+                        sas_number(cell, binary.big_endian, format)
+                    } else {
+                        decode_text(cell).trim_end_matches([' ', '\0']).to_string()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let labels: Vec<Option<ValueLabels>> = meta.columns.iter().map(|_| None).collect();
+    Ok(finish(meta.names, rows, &labels))
+}
+
+fn sas_subheader(binary: &Binary, offset: usize, len: usize, int_len: usize, meta: &mut SasMeta) -> Result<()> {
+    // 64-bit big-endian files put the 4-byte signature after four zero bytes
+    let mut signature = binary.u32(offset)?;
+    if int_len == 8 && signature == 0 {
+        signature = binary.u32(offset + 4)?;
+    }
+    match signature {
+        // Row size
+        0xF7F7_F7F7 => {
+            meta.row_length = binary.uint(offset + 5 * int_len, int_len)? as usize;
+            meta.row_count = binary.uint(offset + 6 * int_len, int_len)? as usize;
+            meta.mix_page_rows = binary.uint(offset + 15 * int_len, int_len)? as usize;
+        }
+        // Column text: names, formats and the compression method
+        0xFFFF_FFFD => {
+            let size = binary.u16(offset + int_len)? as usize;
+            let block = binary.bytes(offset + int_len, size)?;
+            let contains = |needle: &[u8]| block.windows(needle.len()).any(|window| window == needle);
+            if contains(b"SASYZCRL") {
+                meta.compression = Some(SasCompression::Rle);
+            } else if contains(b"SASYZCR2") {
+                meta.compression = Some(SasCompression::Rdc);
+            }
+            meta.text_blocks.push(block.to_vec());
+        }
+        // Column names
+        0xFFFF_FFFF => {
+            let count = len.saturating_sub(2 * int_len + 12) / 8;
+            for i in 0..count {
+                let name = meta.text(binary, offset + int_len + 8 * (i + 1))?;
+                meta.names.push(name);
+            }
+        }
+        // Column offsets, widths and types
+        0xFFFF_FFFC => {
+            let count = len.saturating_sub(2 * int_len + 12) / (int_len + 8);
+            for i in 0..count {
+                let base = offset + int_len + 8 + i * (int_len + 8);
+                meta.columns.push(SasColumn {
+                    offset: binary.uint(base, int_len)? as usize,
+                    width: binary.u32(base + int_len)? as usize,
+                    numeric: binary.u8(base + int_len + 6)? == 1,
+                });
+            }
+        }
+        // Format and label of one column
+        0xFFFF_FBFE => {
+            let format = meta.text(binary, offset + 22 + 3 * int_len)?;
+            meta.formats.push(format.to_uppercase());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Numbers may be stored in fewer than 8 bytes, keeping the most significant ones.
+/// Missing values are NaNs; dates count days and datetimes seconds since 1960.
+fn sas_number(raw: &[u8], big_endian: bool, format: &str) -> String {
+    let mut bytes = [0u8; 8];
+    let width = raw.len().min(8);
+    if big_endian {
+        bytes[..width].copy_from_slice(&raw[..width]);
+    } else {
+        bytes[8 - width..].copy_from_slice(&raw[raw.len() - width..]);
+    }
+    let value = f64_from(&bytes, big_endian);
+    if value.is_nan() {
+        return null();
+    }
+
+    const DATE_FORMATS: [&str; 8] = ["DATE", "DDMMYY", "MMDDYY", "YYMMDD", "E8601DA", "B8601DA", "WEEKDATE", "WORDDATE"];
+    const DATETIME_FORMATS: [&str; 4] = ["DATETIME", "E8601DT", "B8601DT", "DATEAMPM"];
+    let formatted = if DATETIME_FORMATS.contains(&format) {
+        format_seconds(epoch_1960(), value)
+    } else if DATE_FORMATS.contains(&format) {
+        format_days(epoch_1960(), value)
+    } else if matches!(format, "TIME" | "HHMM" | "E8601TM") {
+        Some(format_time(value))
+    } else {
+        None
+    };
+    formatted.unwrap_or_else(|| value.to_string())
+}
+
+/// SASYZCRL: a control byte's high nibble is the command, its low nibble part of the count
+fn sas_rle_decompress(input: &[u8], row_length: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(row_length);
+    let mut offset = 0;
+    let byte_at = |i: usize| input.get(i).copied().unwrap_or(0) as usize;
+    while offset < input.len() && output.len() < row_length {
+        let command = input[offset] >> 4;
+        let low = (input[offset] & 0x0F) as usize;
+        let (copy, fill, len, used) = match command {
+            0x0 => (true, 0, byte_at(offset + 1) + 64 + low * 256, 2),
+            0x1 => (true, 0, byte_at(offset + 1) + 64 + low * 256 + 4096, 2),
+            0x2 => (true, 0, low + 96, 1),
+            0x4 => (false, byte_at(offset + 2) as u8, byte_at(offset + 1) + 18 + low * 256, 3),
+            0x5 => (false, b'@', byte_at(offset + 1) + 17 + low * 256, 2),
+            0x6 => (false, b' ', byte_at(offset + 1) + 17 + low * 256, 2),
+            0x7 => (false, 0, byte_at(offset + 1) + 17 + low * 256, 2),
+            0x8 => (true, 0, low + 1, 1),
+            0x9 => (true, 0, low + 17, 1),
+            0xA => (true, 0, low + 33, 1),
+            0xB => (true, 0, low + 49, 1),
+            0xC => (false, byte_at(offset + 1) as u8, low + 3, 2),
+            0xD => (false, b'@', low + 2, 1),
+            0xE => (false, b' ', low + 2, 1),
+            0xF => (false, 0, low + 2, 1),
+            _ => break,
+        };
+        offset += used;
+        if copy {
+            let end = (offset + len).min(input.len());
+            output.extend_from_slice(&input[offset..end]);
+            offset = end;
+        } else {
+            output.resize(output.len() + len, fill);
+        }
+    }
+    output.resize(row_length, 0);
+    output
+}
+
+/// SASYZCR2 (Ross data compression): 16 control bits say whether each item is a literal
+/// byte or a run / back-reference command
+fn sas_rdc_decompress(input: &[u8], row_length: usize) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(row_length);
+    let mut offset = 0;
+    let mut control_bits = 0u16;
+    let mut control_mask = 0u16;
+    let byte_at = |i: usize| input.get(i).copied().unwrap_or(0) as usize;
+    while offset < input.len() {
+        control_mask >>= 1;
+        if control_mask == 0 {
+            control_bits = ((byte_at(offset) << 8) | byte_at(offset + 1)) as u16;
+            control_mask = 0x8000;
+            offset += 2;
+        }
+        if control_bits & control_mask == 0 {
+            if let Some(&byte) = input.get(offset) {
+                output.push(byte);
+            }
+            offset += 1;
+            continue;
+        }
+
+        let command = byte_at(offset) >> 4;
+        let count = byte_at(offset) & 0x0F;
+        offset += 1;
+        match command {
+            0 => {
+                // Short run
+                output.resize(output.len() + count + 3, byte_at(offset) as u8);
+                offset += 1;
+            }
+            1 => {
+                // Long run
+                let count = count + (byte_at(offset) << 4) + 19;
+                output.resize(output.len() + count, byte_at(offset + 1) as u8);
+                offset += 2;
+            }
+            _ => {
+                // Back-reference: long pattern with an explicit length, short pattern otherwise
+                let distance = count + 3 + (byte_at(offset) << 4);
+                let len = if command == 2 {
+                    offset += 1;
+                    byte_at(offset) + 16
+                } else {
+                    command
+                };
+                offset += 1;
+                let Some(start) = output.len().checked_sub(distance) else {
+                    break;
+                };
+                for i in 0..len {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+    }
+    output.resize(row_length, 0);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days_since(epoch: NaiveDate, date: NaiveDate) -> i64 {
+        (date - epoch).num_days()
+    }
+
+    fn padded(text: &str, width: usize) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize(width, 0);
+        bytes
+    }
+
+    fn stata_file() -> Vec<u8> {
+        let joined = days_since(epoch_1960(), NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()) as i32;
+        let mut dta = Vec::new();
+        dta.extend_from_slice(b"<stata_dta><header><release>118</release><byteorder>LSF</byteorder><K>");
+        dta.extend_from_slice(&5u16.to_le_bytes());
+        dta.extend_from_slice(b"</K><N>");
+        dta.extend_from_slice(&2u64.to_le_bytes());
+        dta.extend_from_slice(b"</N><label>");
+        dta.extend_from_slice(&0u16.to_le_bytes());
+        dta.extend_from_slice(b"</label><timestamp>\0</timestamp></header>");
+        let map_offset = dta.len() + "<map>".len();
+        dta.extend_from_slice(b"<map>");
+        dta.resize(dta.len() + 14 * 8, 0);
+        dta.extend_from_slice(b"</map>");
+
+        let mut map = vec![0u64, (map_offset - 5) as u64];
+        let mut section = |dta: &mut Vec<u8>, open: &str, body: Vec<u8>, close: &str| {
+            map.push(dta.len() as u64);
+            dta.extend_from_slice(open.as_bytes());
+            dta.extend_from_slice(&body);
+            dta.extend_from_slice(close.as_bytes());
+        };
+        let types: Vec<u8> = [8u16, STATA_BYTE, STATA_DOUBLE, STATA_LONG, STATA_STRL]
+            .iter()
+            .flat_map(|t| t.to_le_bytes())
+            .collect();
+        section(&mut dta, "<variable_types>", types, "</variable_types>");
+        let names = ["name", "region", "income", "joined", "notes"].iter().flat_map(|n| padded(n, 129)).collect();
+        section(&mut dta, "<varnames>", names, "</varnames>");
+        section(&mut dta, "<sortlist>", vec![0; 12], "</sortlist>");
+        let formats = ["%8s", "%8.0g", "%9.2f", "%td", "%9s"].iter().flat_map(|f| padded(f, 57)).collect();
+        section(&mut dta, "<formats>", formats, "</formats>");
+        let label_names = ["", "regions", "", "", ""].iter().flat_map(|l| padded(l, 129)).collect();
+        section(&mut dta, "<value_label_names>", label_names, "</value_label_names>");
+        section(&mut dta, "<variable_labels>", vec![0; 5 * 321], "</variable_labels>");
+        section(&mut dta, "<characteristics>", Vec::new(), "</characteristics>");
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&padded("Alice", 8));
+        data.push(1);
+        data.extend_from_slice(&1234.5f64.to_le_bytes());
+        data.extend_from_slice(&joined.to_le_bytes());
+        data.extend_from_slice(&[5, 0, 1, 0, 0, 0, 0, 0]); // strL (v=5, o=1)
+        data.extend_from_slice(&padded("Bob", 8));
+        data.push(3);
+        data.extend_from_slice(&f64::from_bits(0x7fe0_0000_0000_0000).to_le_bytes());
+        data.extend_from_slice(&2_147_483_621i32.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        section(&mut dta, "<data>", data, "</data>");
+
+        let mut strls = b"GSO".to_vec();
+        strls.extend_from_slice(&5u32.to_le_bytes());
+        strls.extend_from_slice(&1u64.to_le_bytes());
+        strls.push(130);
+        strls.extend_from_slice(&6u32.to_le_bytes());
+        strls.extend_from_slice(b"first\0");
+        section(&mut dta, "<strls>", strls, "</strls>");
+
+        let texts = b"North\0South\0";
+        let mut table = Vec::new();
+        for value in [2u32, texts.len() as u32, 0, 6, 1, 2] {
+            table.extend_from_slice(&value.to_le_bytes());
+        }
+        table.extend_from_slice(texts);
+        let mut lbl = b"<lbl>".to_vec();
+        lbl.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        lbl.extend_from_slice(&padded("regions", 129));
+        lbl.extend_from_slice(&[0; 3]);
+        lbl.extend_from_slice(&table);
+        lbl.extend_from_slice(b"</lbl>");
+        section(&mut dta, "<value_labels>", lbl, "</value_labels>");
+        map.push(dta.len() as u64);
+        dta.extend_from_slice(b"</stata_dta>");
+        map.push(dta.len() as u64);
+
+        for (i, offset) in map.iter().enumerate() {
+            dta[map_offset + 8 * i..map_offset + 8 * (i + 1)].copy_from_slice(&offset.to_le_bytes());
+        }
+        dta
+    }
+
+    #[test]
+    fn test_read_stata_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("survey.dta");
+        std::fs::write(&path, stata_file()).unwrap();
+
+        let data = read_statistical_file(&path, StatFormat::Stata).unwrap();
+        assert_eq!(data.columns, vec!["name", "region", "income", "joined", "notes"]);
+        assert_eq!(data.rows, vec![
+            vec!["Alice", "North", "1234.5", "2024-01-15", "first"],
+            vec!["Bob", "3", "NULL", "NULL", ""],
+        ]);
+    }
+
+    fn spss_file() -> Vec<u8> {
+        let epoch = NaiveDate::from_ymd_opt(1582, 10, 14).unwrap();
+        let joined = (days_since(epoch, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()) * 86_400) as f64;
+        let int = |value: i32| value.to_le_bytes().to_vec();
+
+        let mut sav = b"$FL2".to_vec();
+        sav.extend_from_slice(&padded("@(#) SPSS DATA FILE", 60));
+        for value in [2, 4, 1, 0, 2] {
+            sav.extend_from_slice(&int(value));
+        }
+        sav.extend_from_slice(&100f64.to_le_bytes());
+        sav.resize(176, b' ');
+
+        let variable = |sav: &mut Vec<u8>, width: i32, format: i32, name: &str| {
+            sav.extend_from_slice(&int(2));
+            sav.extend_from_slice(&int(width));
+            sav.extend_from_slice(&[int(0), int(0), int(format), int(format)].concat());
+            sav.extend_from_slice(format!("{:<8}", name).as_bytes());
+        };
+        variable(&mut sav, 0, 5 << 16 | 8 << 8, "ID");
+        variable(&mut sav, 8, 1 << 16 | 8 << 8, "NAME");
+        variable(&mut sav, 0, 5 << 16 | 8 << 8, "SEX");
+        variable(&mut sav, 0, 20 << 16 | 11 << 8, "JOINED");
+
+        sav.extend_from_slice(&[int(3), int(2)].concat());
+        sav.extend_from_slice(&1f64.to_le_bytes());
+        sav.extend_from_slice(b"\x04Male   ");
+        sav.extend_from_slice(&2f64.to_le_bytes());
+        sav.extend_from_slice(b"\x06Female ");
+        sav.extend_from_slice(&[int(4), int(1), int(3)].concat());
+
+        let long_names = b"NAME=full_name";
+        sav.extend_from_slice(&[int(7), int(13), int(1), int(long_names.len() as i32)].concat());
+        sav.extend_from_slice(long_names);
+        sav.extend_from_slice(&[int(999), int(0)].concat());
+
+        sav.extend_from_slice(&[101, 253, 102, 253, 102, 253, 255, 255]);
+        sav.extend_from_slice(b"Alice   ");
+        sav.extend_from_slice(&joined.to_le_bytes());
+        sav.extend_from_slice(b"Bob     ");
+        sav.extend_from_slice(&[252, 0, 0, 0, 0, 0, 0, 0]);
+        sav
+    }
+
+    #[test]
+    fn test_read_spss_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("survey.sav");
+        std::fs::write(&path, spss_file()).unwrap();
+
+        let data = read_statistical_file(&path, StatFormat::Spss).unwrap();
+        assert_eq!(data.columns, vec!["ID", "full_name", "SEX", "JOINED"]);
+        assert_eq!(data.rows, vec![
+            vec!["1", "Alice", "Female", "2024-01-15"],
+            vec!["2", "Bob", "NULL", "NULL"],
+        ]);
+    }
+
+    /// A 64-bit little-endian file with one metadata page and one data page
+    fn sas_file() -> Vec<u8> {
+        const PAGE: usize = 1024;
+        let joined = days_since(epoch_1960(), NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()) as f64;
+        let mut sas = SAS_MAGIC.to_vec();
+        sas.resize(PAGE, 0);
+        sas[32] = 0x33;
+        sas[35] = 0x33;
+        sas[37] = 0x01;
+        sas[200..204].copy_from_slice(&(PAGE as u32).to_le_bytes());
+        sas[204..208].copy_from_slice(&(PAGE as u32).to_le_bytes());
+        sas[208..216].copy_from_slice(&2u64.to_le_bytes());
+
+        let signature = |value: u32| [value.to_le_bytes(), [0xFF * (value >> 31) as u8; 4]].concat();
+        let text_ref = |block: u16, offset: u16, len: u16| {
+            [block.to_le_bytes(), offset.to_le_bytes(), len.to_le_bytes(), [0; 2]].concat()
+        };
+
+        let mut row_size = signature(0xF7F7_F7F7);
+        row_size.resize(128, 0);
+        row_size[40..48].copy_from_slice(&20u64.to_le_bytes());
+        row_size[48..56].copy_from_slice(&2u64.to_le_bytes());
+
+        let mut text = vec![0; 4];
+        text.extend_from_slice(b"NAMEAGEJOINEDDATE");
+        let text_len = text.len() as u16;
+        text[..2].copy_from_slice(&text_len.to_le_bytes());
+        let column_text = [signature(0xFFFF_FFFD), text].concat();
+
+        let mut column_names = [signature(0xFFFF_FFFF), vec![0; 8]].concat();
+        for (offset, len) in [(4, 4), (8, 3), (11, 6)] {
+            column_names.extend_from_slice(&text_ref(0, offset, len));
+        }
+        column_names.resize(52, 0);
+
+        let mut attributes = [signature(0xFFFF_FFFC), vec![0; 8]].concat();
+        for (offset, width, kind) in [(0u64, 8u32, 2u8), (8, 4, 1), (12, 8, 1)] {
+            attributes.extend_from_slice(&offset.to_le_bytes());
+            attributes.extend_from_slice(&width.to_le_bytes());
+            attributes.extend_from_slice(&[0, 0, kind, 0]);
+        }
+        attributes.resize(76, 0);
+
+        let format = |offset: u16, len: u16| {
+            let mut subheader = signature(0xFFFF_FBFE);
+            subheader.resize(46, 0);
+            subheader.extend_from_slice(&text_ref(0, offset, len));
+            subheader.resize(64, 0);
+            subheader
+        };
+        let subheaders = [row_size, column_text, column_names, attributes, format(0, 0), format(0, 0), format(17, 4)];
+
+        let mut meta = vec![0; PAGE];
+        meta[36..38].copy_from_slice(&(subheaders.len() as u16).to_le_bytes());
+        let mut offset = 256;
+        for (i, subheader) in subheaders.iter().enumerate() {
+            let pointer = 40 + 24 * i;
+            meta[pointer..pointer + 8].copy_from_slice(&(offset as u64).to_le_bytes());
+            meta[pointer + 8..pointer + 16].copy_from_slice(&(subheader.len() as u64).to_le_bytes());
+            meta[offset..offset + subheader.len()].copy_from_slice(subheader);
+            offset += subheader.len();
+        }
+        sas.extend_from_slice(&meta);
+
+        let mut data = vec![0; PAGE];
+        data[32..34].copy_from_slice(&256u16.to_le_bytes());
+        data[34..36].copy_from_slice(&2u16.to_le_bytes());
+        let rows = [
+            [&b"Alice   "[..], &30f64.to_le_bytes()[4..], &joined.to_le_bytes()].concat(),
+            [&b"Bob     "[..], &f64::NAN.to_le_bytes()[4..], &f64::NAN.to_le_bytes()].concat(),
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            data[40 + 20 * i..60 + 20 * i].copy_from_slice(row);
+        }
+        sas.extend_from_slice(&data);
+        sas
+    }
+
+    #[test]
+    fn test_read_sas_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("survey.sas7bdat");
+        std::fs::write(&path, sas_file()).unwrap();
+
+        let data = read_statistical_file(&path, StatFormat::Sas).unwrap();
+        assert_eq!(data.columns, vec!["NAME", "AGE", "JOINED"]);
+        assert_eq!(data.rows, vec![
+            vec!["Alice", "30", "2024-01-15"],
+            vec!["Bob", "NULL", "NULL"],
+        ]);
+    }
+
+    #[test]
+    fn test_sas_decompression() {
+        // Copy "abc", three blanks, two @
+        assert_eq!(sas_rle_decompress(b"\x82abc\xE1\xD0", 8), b"abc   @@");
+        // Literals, a run of four "z", a literal, then a three byte back-reference
+        assert_eq!(sas_rdc_decompress(b"\x28\x00ab\x01zc\x30\x00", 10), b"abzzzzczzc");
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::database::{Database, TableStats};
+
+/// Counts rows of SQLite tables on a thread with a connection of its own, so COUNT(*) over
+/// large tables never holds up the UI. Tables are only counted when asked for.
+pub struct StatsWorker {
+    requests: Sender<String>,
+    results: Receiver<(String, Result<TableStats, String>)>,
+}
+
+impl StatsWorker {
+    /// Start a worker on a database file. The thread ends once the worker is dropped.
+    pub fn spawn(path: PathBuf) -> Self {
+        let (requests, pending) = channel::<String>();
+        let (sender, results) = channel();
+        std::thread::spawn(move || {
+            let db = Database::open_readonly(&path).map_err(|e| e.to_string());
+            for table in pending {
+                let stats = match &db {
+                    Ok(db) => db.table_stats(&table).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.clone()),
+                };
+                if sender.send((table, stats)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results }
+    }
+
+    pub fn request(&self, table: &str) {
+        // A worker whose thread has died just never answers
+        let _ = self.requests.send(table.to_string());
+    }
+
+    /// Stats computed since the last call
+    pub fn finished(&self) -> Vec<(String, Result<TableStats, String>)> {
+        self.results.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_stats_worker() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("stats.db");
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2);")
+            .unwrap();
+
+        let worker = StatsWorker::spawn(path);
+        worker.request("t");
+        worker.request("missing");
+        let mut results = Vec::new();
+        for _ in 0..100 {
+            results.extend(worker.finished());
+            if results.len() == 2 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(results[0].0, "t");
+        assert_eq!(results[0].1.as_ref().unwrap().rows, 2);
+        assert!(results[1].1.is_err());
+    }
+}
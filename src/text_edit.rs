@@ -0,0 +1,184 @@
+use std::ops::Range;
+
+/// Text of a cell edited in the multi-line editor, with the cursor as a byte offset into it.
+/// Lines are word-wrapped to the editor's width, and the cursor moves over the wrapped lines
+/// as they are shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl TextEditor {
+    /// Start editing `text` with the cursor at its end
+    pub fn new(text: String) -> Self {
+        let cursor = text.len();
+        Self { text, cursor }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    /// Move to the wrapped line above or below, keeping the column where it can
+    pub fn vertical(&mut self, down: bool, width: usize) {
+        let lines = self.wrap(width);
+        let (line, column) = self.position(&lines);
+        let target = if down { line + 1 } else { line.wrapping_sub(1) };
+        if target < lines.len() {
+            self.cursor = self.offset_in(&lines, target, column);
+        }
+    }
+
+    /// Move to the start of the wrapped line, or its end
+    pub fn line_edge(&mut self, end: bool, width: usize) {
+        let lines = self.wrap(width);
+        let (line, _) = self.position(&lines);
+        self.cursor = if end { self.offset_in(&lines, line, usize::MAX) } else { lines[line].start };
+    }
+
+    /// Move to the start of the text, or its end
+    pub fn text_edge(&mut self, end: bool) {
+        self.cursor = if end { self.text.len() } else { 0 };
+    }
+
+    /// Byte ranges of the lines as shown `width` characters wide: breaks in the text end a
+    /// line, and longer lines wrap after the last space that fits, or mid-word when none does
+    pub fn wrap(&self, width: usize) -> Vec<Range<usize>> {
+        let width = width.max(1);
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for paragraph in self.text.split('\n') {
+            let end = start + paragraph.len();
+            let mut line_start = start;
+            loop {
+                let rest = &self.text[line_start..end];
+                let Some((limit, _)) = rest.char_indices().nth(width) else {
+                    lines.push(line_start..end);
+                    break;
+                };
+                let wrap_at = rest[..limit].rfind(' ').map_or(limit, |space| space + 1);
+                lines.push(line_start..line_start + wrap_at);
+                line_start += wrap_at;
+            }
+            start = end + 1;
+        }
+        lines
+    }
+
+    /// The wrapped line and the column the cursor is at
+    pub fn position(&self, lines: &[Range<usize>]) -> (usize, usize) {
+        // Where a line wraps, its end is the start of the next one, shown on the next
+        let line = lines.iter().rposition(|line| line.start <= self.cursor).unwrap_or(0);
+        (line, self.text[lines[line].start..self.cursor].chars().count())
+    }
+
+    /// The offset of `column` in a wrapped line, or of its end when it is shorter
+    fn offset_in(&self, lines: &[Range<usize>], line: usize, column: usize) -> usize {
+        let range = lines[line].clone();
+        let wrapped = lines.get(line + 1).is_some_and(|next| next.start == range.end);
+        let text = &self.text[range.clone()];
+        let mut offsets: Vec<usize> = text.char_indices().map(|(i, _)| range.start + i).collect();
+        // The end of a line that wraps is the start of the next, so the cursor stops short of it
+        if !wrapped || offsets.is_empty() {
+            offsets.push(range.end);
+        }
+        offsets[column.min(offsets.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(editor: &TextEditor, width: usize) -> Vec<&str> {
+        editor.wrap(width).into_iter().map(|line| &editor.text()[line]).collect()
+    }
+
+    #[test]
+    fn test_word_wrap() {
+        let editor = TextEditor::new("the quick brown fox\njumps\n\nover".to_string());
+        assert_eq!(shown(&editor, 10), vec!["the quick ", "brown fox", "jumps", "", "over"]);
+        let editor = TextEditor::new("abcdefghij klm".to_string());
+        assert_eq!(shown(&editor, 4), vec!["abcd", "efgh", "ij ", "klm"]);
+        assert_eq!(shown(&TextEditor::new(String::new()), 4), vec![""]);
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut editor = TextEditor::new("héllo".to_string());
+        editor.left();
+        editor.left();
+        editor.insert('_');
+        editor.backspace();
+        editor.backspace();
+        assert_eq!(editor.text(), "hélo");
+        editor.text_edge(false);
+        editor.delete();
+        editor.insert_str("J");
+        assert_eq!(editor.text(), "Jélo");
+        assert_eq!(editor.cursor(), 1);
+    }
+
+    #[test]
+    fn test_cursor_movement() {
+        let mut editor = TextEditor::new("the quick brown fox\njumps".to_string());
+        let lines = editor.wrap(10);
+        assert_eq!(editor.position(&lines), (2, 5));
+
+        editor.vertical(false, 10);
+        assert_eq!(editor.position(&editor.wrap(10)), (1, 5));
+        editor.vertical(false, 10);
+        assert_eq!(editor.position(&editor.wrap(10)), (0, 5));
+        editor.line_edge(true, 10);
+        // Before the space the line wraps at
+        assert_eq!(editor.position(&editor.wrap(10)), (0, 9));
+        editor.right();
+        assert_eq!(editor.position(&editor.wrap(10)), (1, 0));
+        editor.line_edge(true, 10);
+        assert_eq!(editor.position(&editor.wrap(10)), (1, 9));
+        editor.vertical(true, 10);
+        assert_eq!(editor.position(&editor.wrap(10)), (2, 5));
+        editor.line_edge(false, 10);
+        assert_eq!(editor.cursor(), "the quick brown fox\n".len());
+    }
+}
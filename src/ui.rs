@@ -1,18 +1,49 @@
 use anyhow::{Context, Result};
 use arboard::Clipboard;
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState},
+    symbols,
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
     Frame,
 };
+use unicode_width::UnicodeWidthStr;
 
-use crate::config::Theme;
-use crate::data_source::DataSource;
-use crate::database::QueryResult;
-use crate::persistence::ComputedColumnPersistence;
+use crate::config::{expand_home, Config, ExternalTool, FormatConfig, NumberLocale, Theme};
+use crate::data_source::{summarize_rows, DataSource};
+use crate::database::{
+    highlight_sql, row_changes, ChangeSignature, QueryResult, RowChange, SchemaObject, SearchMatch, SqlTokenKind, StatementPlan,
+    SyntaxError, TableSchema, TableStats,
+};
+use crate::diff::{diff_tables, write_diff_csv, DiffKind, DiffResult};
+use crate::external::{self, CommandOutput, PipeRequest, ToolLaunch};
+use crate::export::{parse_tsv, tsv, write_results, ExportFormat, ExportScope};
+use crate::file_reader::{backup_path, infer_column_types, ColumnType};
+use crate::filter::{ColumnFilter, FilterOp, SearchPattern};
+use crate::format::{format_number, format_value, truncate_to_width};
+use crate::geo::{ascii_map, parse_geometry, GeoSummary};
+use crate::completion::{complete_path, Completion};
+use crate::download::format_bytes;
+use crate::table_stats::StatsWorker;
+use crate::page_cache::{PageCache, PageKey};
+use crate::column_stats::{aggregate, ColumnStats, ColumnSummary, Histogram, SummaryKind, ValueCounts};
+use crate::value::Value;
+use crate::window::WindowSpec;
+use crate::undo::{CellChange, EditLog, PageEdit};
+use crate::column_edit::ColumnEdit;
+use crate::fill::fill_values;
+use crate::bulk_edit::BulkUpdate;
+use crate::row_form::{duplicate_row, InsertForm};
+use crate::text_edit::TextEditor;
+use crate::join::{join_tables, JoinSpec};
+use crate::profile::{profile_table, TableProfile};
+use crate::keymap::{Action, Keymap};
+use crate::persistence::{push_query_history, Bookmark, LastView, RecentFile, SavedQuery, SessionStore, SortOrder, TableViewState, WriteBatch};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NavigationMode {
@@ -23,6 +54,292 @@ pub enum NavigationMode {
     DetailedView,
     ErrorDisplay,
     ComputedColumn,
+    ComputedColumns,
+    DiffInput,
+    Diff,
+    JoinInput,
+    Join,
+    Profile,
+    GlobalSearch,
+    SearchResults,
+    PipeInput,
+    PipeOutput,
+    ToolPicker,
+    BookmarkInput,
+    Bookmarks,
+    SchemaObjects,
+    ObjectDefinition,
+    TableSchema,
+    Ddl,
+    ConfirmSave,
+    ConfirmRevert,
+    ConfirmFileSave,
+    ConfirmPaste,
+    FillDown,
+    BulkEditInput,
+    BulkPreview,
+    InsertForm,
+    TextEdit,
+    Visual,
+    ConfirmStatement,
+    FilterInput,
+    GotoRow,
+    CellSearch,
+    ColumnManager,
+    ColumnEditInput,
+    ConfirmDropColumn,
+    AttachInput,
+    QueryHistory,
+    SavedQueryName,
+    SavedQueries,
+    QueryParameters,
+    DatabaseResults,
+    ExportResults,
+    ExportPath,
+    ColumnSummary,
+}
+
+/// Rows fetched at a time when the table search (`n`/`N`) runs past the loaded page
+const CELL_SEARCH_CHUNK: usize = 1000;
+
+/// Cells with more characters than this, or with line breaks, are edited in the multi-line editor
+const LONG_TEXT_EDIT: usize = 60;
+
+/// The widest a column can be made with `>`
+const MAX_COLUMN_WIDTH: u16 = 120;
+
+/// Sections listed in the sidebar below the tables of a SQLite database, by object kind
+const SIDEBAR_SECTIONS: [(&str, &str); 4] = [
+    ("view", "Views"),
+    ("virtual table", "Virtual tables"),
+    ("index", "Indexes"),
+    ("trigger", "Triggers"),
+];
+
+/// A line of the sidebar the cursor can rest on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SidebarEntry {
+    Table(usize),          // Index into `tables`; views and virtual tables are browsed too
+    Section(&'static str), // Header of a foldable section, by object kind
+    Object(usize),         // Index or trigger, by index into `sidebar_objects`
+}
+
+/// What part of the data is sent to an external command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PipeScope {
+    Row,
+    Column,
+    Table,
+}
+
+impl PipeScope {
+    fn next(self) -> Self {
+        match self {
+            PipeScope::Row => PipeScope::Column,
+            PipeScope::Column => PipeScope::Table,
+            PipeScope::Table => PipeScope::Row,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PipeScope::Row => "row",
+            PipeScope::Column => "column",
+            PipeScope::Table => "table",
+        }
+    }
+}
+
+/// Database work the main loop runs on a worker thread, so the UI keeps drawing and the
+/// query can be cancelled
+#[derive(Debug, Clone)]
+pub enum QueryJob {
+    Query { query: String, params: Vec<(String, String)>, table: Option<String>, page_size: usize }, // as typed at the query prompt; no table for the whole database
+    Statement(StatementPlan),                                  // confirmed by the user
+    ColumnSummary { table: String, column: String, query: Option<(String, Vec<(String, String)>)>, filters: Vec<ColumnFilter>, kind: SummaryKind, locale: NumberLocale }, // over the current query's rows if there is one
+    Join { spec: JoinSpec, table: String, filters: Vec<ColumnFilter>, config: Box<Config> }, // the table's filtered rows with the other file's
+    Profile { table: String, query: Option<(String, Vec<(String, String)>)>, filters: Vec<ColumnFilter>, locale: NumberLocale }, // of the current query's rows if there is one
+}
+
+#[derive(Debug)]
+pub enum QueryOutcome {
+    Rows(QueryResult),         // first page of the query's result
+    Confirm(StatementPlan),    // the query changes data and needs confirming first
+    Executed(u64),             // rows changed by a statement
+    Summary(ColumnSummary),    // stats or histogram of the selected column
+    Joined(JoinedTable),       // the current table joined with another file's
+    Profile(TableProfile),     // report on every column of the table
+}
+
+impl QueryJob {
+    pub fn run(&self, data_source: &mut DataSource) -> Result<QueryOutcome> {
+        match self {
+            QueryJob::Query { query, params, table, page_size } => {
+                let table = table.as_deref().unwrap_or("");
+                if let Some(plan) = data_source.plan_statement(query, params, table)? {
+                    return Ok(QueryOutcome::Confirm(plan));
+                }
+                data_source.discard_query_results();
+                Ok(QueryOutcome::Rows(data_source.execute_custom_query(query, params, table, 0, *page_size)?))
+            }
+            QueryJob::Statement(plan) => Ok(QueryOutcome::Executed(data_source.execute_statement(plan)?)),
+            QueryJob::ColumnSummary { table, column, query: Some((query, params)), kind, locale, .. } => {
+                let data = data_source.execute_custom_query(query, params, table, 0, i64::MAX as usize)?;
+                Ok(QueryOutcome::Summary(summarize_rows(&data, column, *kind, *locale)?))
+            }
+            QueryJob::ColumnSummary { table, column, query: None, filters, kind, locale } => {
+                Ok(QueryOutcome::Summary(data_source.column_summary(table, column, filters, *kind, *locale)?))
+            }
+            QueryJob::Profile { table, query, filters, locale } => {
+                let data = match query {
+                    Some((query, params)) => data_source.execute_custom_query(query, params, table, 0, i64::MAX as usize)?,
+                    None => data_source.get_table_data(table, 0, i64::MAX as usize, None, filters, *locale)?,
+                };
+                Ok(QueryOutcome::Profile(profile_table(table, &data, *locale)))
+            }
+            QueryJob::Join { spec, table, filters, config } => {
+                let other = DataSource::open_with_config(spec.path.clone(), config)
+                    .with_context(|| format!("Failed to open '{}'", spec.path.display()))?;
+                let tables = other.get_tables()?;
+                let other_table = match &spec.table {
+                    Some(name) => tables
+                        .iter()
+                        .find(|t| *t == name)
+                        .ok_or_else(|| anyhow::anyhow!("Table '{}' not found in '{}'", name, spec.path.display()))?,
+                    None => tables
+                        .iter()
+                        .find(|t| *t == table)
+                        .or_else(|| tables.first())
+                        .ok_or_else(|| anyhow::anyhow!("No tables/sheets found in '{}'", spec.path.display()))?,
+                };
+                let left = data_source.get_table_data(table, 0, i64::MAX as usize, None, filters, config.format.number_locale)?;
+                let right = other.get_all_table_data(other_table)?;
+                Ok(QueryOutcome::Joined(JoinedTable {
+                    description: format!(
+                        "{} {} join {} of {} on {} = {}",
+                        table,
+                        spec.kind.label(),
+                        other_table,
+                        spec.path.file_name().map_or_else(|| spec.path.to_string_lossy(), |name| name.to_string_lossy()),
+                        spec.key,
+                        spec.other_key
+                    ),
+                    data: join_tables(&left, &right, &spec.key, &spec.other_key, spec.kind, other_table)?,
+                    selected_row: 0,
+                    selected_col: 0,
+                }))
+            }
+        }
+    }
+
+    /// What the footer says is running
+    pub fn label(&self) -> &'static str {
+        match self {
+            QueryJob::Query { .. } | QueryJob::Statement(_) => "Running query",
+            QueryJob::ColumnSummary { kind: SummaryKind::Stats, .. } => "Computing column stats",
+            QueryJob::ColumnSummary { kind: SummaryKind::Histogram, .. } => "Counting values for the chart",
+            QueryJob::ColumnSummary { kind: SummaryKind::ValueCounts, .. } => "Counting values",
+            QueryJob::Join { .. } => "Joining tables",
+            QueryJob::Profile { .. } => "Profiling the table",
+        }
+    }
+}
+
+/// A query in progress on the worker thread, shown with a spinner in the footer
+#[derive(Debug, Clone)]
+pub struct RunningQuery {
+    pub label: &'static str,
+    pub started: Instant,
+    pub cancellable: bool, // SQLite statements can be interrupted
+    pub cancelled: bool,
+}
+
+/// Results of a query over the whole database (`I`), kept in their own tab with their own
+/// paging so the table view is left as it was
+#[derive(Debug, Clone)]
+pub struct DatabaseQuery {
+    pub query: String,
+    pub params: Vec<(String, String)>,
+    pub data: QueryResult, // page being shown
+    pub offset: usize,
+    pub selected_row: usize,
+    pub selected_col: usize,
+}
+
+/// A bulk edit (`U`) shown on the page, waiting to be applied
+#[derive(Debug, Clone)]
+pub struct BulkPreview {
+    pub update: BulkUpdate,
+    pub cells: Vec<(usize, usize, Value)>, // row and column on the page, with the new value
+}
+
+/// The current table joined with a table of another file (`J`), browsed in its own view
+#[derive(Debug, Clone)]
+pub struct JoinedTable {
+    pub description: String,
+    pub data: QueryResult, // every joined row
+    pub selected_row: usize,
+    pub selected_col: usize,
+}
+
+/// A view left by following a foreign key (`f`), returned to with Backspace
+#[derive(Debug, Clone)]
+pub struct FollowedKey {
+    pub table: String,
+    pub filters: Vec<ColumnFilter>,
+    pub offset: usize,
+    pub selected_row: usize,
+    pub selected_col: usize,
+}
+
+/// What the start screen asks the caller to do after a key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartAction {
+    Stay,
+    Open(String),
+    Quit,
+}
+
+/// The recently opened files shown when sqbrowser starts without a file argument
+pub struct StartScreen {
+    pub recent: Vec<RecentFile>,
+    pub selected: usize,
+}
+
+impl StartScreen {
+    pub fn new(recent: Vec<RecentFile>) -> Self {
+        Self { recent, selected: 0 }
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyEvent) -> StartAction {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => StartAction::Quit,
+            KeyCode::Char('q') | KeyCode::Esc => StartAction::Quit,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                StartAction::Stay
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected + 1 < self.recent.len() {
+                    self.selected += 1;
+                }
+                StartAction::Stay
+            }
+            KeyCode::Home => {
+                self.selected = 0;
+                StartAction::Stay
+            }
+            KeyCode::End => {
+                self.selected = self.recent.len().saturating_sub(1);
+                StartAction::Stay
+            }
+            KeyCode::Enter => match self.recent.get(self.selected) {
+                Some(file) => StartAction::Open(file.path.clone()),
+                None => StartAction::Stay,
+            },
+            _ => StartAction::Stay,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -45,8 +362,20 @@ pub enum ComputedColumnType {
     Aggregate(String),                        // sum, mean, count, etc.
     RowOperation(Vec<String>),                // operations on individual rows like Age + Height
     MixedOperation(Vec<String>, Vec<String>), // (columns, aggregate_expressions) like age*sum(height)
+    Window(String),                           // cumsum, rank, lag, lead or rolling_mean, over the rows in the order shown
+}
+
+/// What the computed column prompt does on Enter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComputedColumnEdit {
+    Add,
+    Expression(usize), // replaces the expression of the column at this index
+    Rename(usize),
 }
 
+/// A table, an aggregate such as `sum(Age)` and the filters it was worked out with
+type AggregateKey = (String, String, Vec<ColumnFilter>);
+
 pub struct AppState {
     pub tables: Vec<String>,
     pub selected_table_idx: usize,
@@ -59,11 +388,16 @@ pub struct AppState {
     pub page_size: usize,
     pub current_data: Option<QueryResult>,
     pub original_data: Option<QueryResult>, // Store original data for comparison
+    pub undecoded_columns: Vec<usize>,    // Hidden columns the page was read without, NULL until decoded
+    pub edit_log: EditLog,                  // Unsaved edits of the page, for undo and redo
     pub db_path: String,
     pub status_message: Option<String>,
     pub show_help: bool,
     pub edit_input: String,
     pub editing_cell: Option<(usize, usize)>, // (row, col) indices
+    pub text_editor: Option<TextEditor>,      // Long text of the edited cell, in the multi-line editor
+    pub text_editor_size: (u16, u16),         // Width and height of the multi-line editor, in percent of the screen
+    pub visual_anchor: Option<usize>,         // Row of the page where the visual selection (V) started
     pub data_modified: bool,
     pub detailed_view_row: Option<usize>, // Row index for detailed view
     pub detailed_view_selected_field: usize, // Selected field in detailed view
@@ -71,16 +405,182 @@ pub struct AppState {
     pub error_message: Option<String>,    // Error message to display
     pub previous_navigation_mode: NavigationMode, // Previous mode before error display
     pub computed_column_input: String,    // Input for computed column expression
+    pub computed_column_edit: Option<ComputedColumnEdit>, // Change made from the computed column manager; None adds from the data view
+    pub computed_manager_selected: usize, // Selected column in the computed column manager
     pub computed_columns: Vec<ComputedColumn>, // List of computed columns
-    pub persistence: ComputedColumnPersistence, // Persistence for computed columns
+    pub persistence: SessionStore,        // What is kept of the file between runs
+    pub keymap: Keymap,                   // Keys of the table list and data view, Vim's when configured
+    pub format: FormatConfig,             // Number/date display formatting
+    pub view_state: TableViewState,       // Sort, filters and layout of the current table
+    pub diff_input: String,               // Input for the diff prompt ("other_table [key]")
+    pub diff_result: Option<DiffResult>,  // Rows that differ between two tables
+    pub diff_description: String,         // What is being compared, shown in the diff title
+    pub diff_selected_row: usize,         // Selected row in the diff view
+    pub join_input: String,               // Input for the join prompt ("file key[=other_key] ...")
+    pub joined_table: Option<JoinedTable>, // Result of the last join, shown in the join view
+    pub reader_config: Config,            // Reader settings for other files opened from here, e.g. to join
+    pub table_profile: Option<TableProfile>, // Report shown in the profile popup
+    pub profile_scroll: u16,              // Scroll offset in the profile popup
+    pub search_input: String,             // Input for the global search prompt
+    pub search_results: Vec<SearchMatch>, // Matches of the last global search
+    pub search_selected: usize,           // Selected match in the search results
+    pub pipe_input: String,               // Shell command the selection is piped to
+    pub pipe_scope: PipeScope,            // Whether the row, column or table is piped
+    pub pending_pipe: Option<PipeRequest>, // Command for the main loop to run with the TUI suspended
+    pub pipe_output: Option<CommandOutput>, // Output of the last piped command
+    pub pipe_output_title: String,        // Command line shown above its output
+    pub pipe_output_scroll: u16,          // Scroll offset in the command output popup
+    pub external_tools: Vec<ExternalTool>, // Configured tools that handle the open file type
+    pub tool_selected: usize,             // Selected entry in the external tool picker
+    pub column_manager_selected: usize,   // Selected column in the show/hide columns popup
+    pub column_edit_input: String,        // `name [type] [= default]` of a new column, or a column's new name
+    pub column_edit_target: Option<String>, // Column renamed or dropped; None when adding one
+    pub columns_changed: bool,            // File columns added, renamed or dropped since the last save
+    pub fill_input: String,               // Rows to fill down (Ctrl+D); empty for the rest of the page
+    pub fill_series: bool,                // Continue a series rather than copy the cell
+    pub bulk_edit_input: String,          // `column = expression [WHERE condition]` typed at the bulk edit prompt (U)
+    pub bulk_preview: Option<BulkPreview>, // Cells the bulk edit changes, highlighted until applied
+    pub insert_form: Option<InsertForm>,  // New row being filled in a column at a time (+)
+    pub value_counts_selected: usize,     // Selected value in the value counts popup
+    pub column_summary: Option<ColumnSummary>, // Stats or histogram shown in a popup
+    pub pending_tool: Option<ToolLaunch>, // Tool for the main loop to launch with the TUI suspended
+    pub pending_query: Option<QueryJob>,  // Query for the main loop to run on a worker thread
+    pub current_query_params: Vec<(String, String)>, // Values bound to the current query's placeholders
+    pub parameter_query: String,          // Query waiting for its parameter values
+    pub query_params: Vec<(String, String)>, // Placeholder names and the values typed for them
+    pub query_param_selected: usize,      // Parameter being edited in the form
+    pub last_param_values: HashMap<String, String>, // Value last used for each placeholder name
+    pub running_query: Option<RunningQuery>, // Query being run, while the spinner shows
+    pub query_over_database: bool,        // The query prompt runs over the whole database (`I`), not the current table
+    pub database_query: Option<DatabaseQuery>, // Result tab of the last whole-database query
+    pub export_selected: usize,           // Format, or the pipe line after them, chosen in the export popup
+    pub results_origin: NavigationMode,   // View the export popup and its piped output return to
+    pub export_typed: bool,               // JSON exports write numbers, booleans and nulls typed rather than as text
+    pub export_scope: ExportScope,        // Rows the export popup writes: all, the page or the selection
+    pub export_visible_columns: bool,     // The export popup leaves out the columns hidden with `C`
+    pub export_computed: bool,            // The export popup writes the computed columns along with the table's own
+    pub export_path_input: String,        // File the chosen export format is written to
+    pub export_path_matches: Vec<String>, // Directory entries Tab found for the typed path
+    pub export_overwrite: bool,           // The typed file exists and Enter again overwrites it
+    pub bookmarks: Vec<Bookmark>,         // Named rows/cells of this file
+    pub bookmark_input: String,           // Name typed for a new bookmark
+    pub bookmark_cell: bool,              // Whether the new bookmark marks the cell or the whole row
+    pub bookmark_selected: usize,         // Selected entry in the bookmark list
+    pub table_order: Vec<String>,         // Tables in the file's own order, before favorites are pinned
+    pub favorite_tables: Vec<String>,     // Tables pinned to the top of the sidebar
+    pub change_signature: Option<ChangeSignature>, // Last seen state of the database files
+    pub external_change: bool,            // Data changed on disk but the view was not refreshed
+    pub dirty: bool,                      // Something shown changed since the screen was last drawn
+    pub column_types: HashMap<String, ColumnType>, // Inferred types of file columns, by name
+    pub schema_objects: Vec<SchemaObject>, // Indexes and triggers listed in the schema view
+    pub schema_selected: usize,           // Selected entry in the schema view
+    pub sidebar_objects: Vec<SchemaObject>, // Views, virtual tables, indexes and triggers in the sidebar sections
+    pub collapsed_sections: Vec<&'static str>, // Sidebar sections folded to their header, by kind
+    pub sidebar_cursor: Option<SidebarEntry>, // Section header or index/trigger under the cursor; None on the selected table
+    pub followed_keys: Vec<FollowedKey>,  // Views left by following foreign keys, most recent last
+    pub stats_worker: Option<StatsWorker>, // Counts SQLite tables in the background for the overview pane
+    pub table_stats: HashMap<String, Result<TableStats, String>>, // Stats counted so far, by table
+    pub stats_pending: Vec<String>,       // Tables the worker is counting
+    pub aggregates: Vec<(AggregateKey, f64)>, // Aggregates of computed columns over whole tables, until the table changes
+    pub window_values: HashMap<String, Vec<Value>>, // Values of window computed columns on the page, by expression
+    pub page_cache: Option<PageCache>,    // Pages of SQLite tables shown before or read ahead
+    pub counting_rows: bool,              // The page's table is being counted; its total_rows is a lower bound
+    pub loading_sheets: Vec<(String, Instant)>, // Workbook sheets being read in the background, since when
+    pub table_schema: Option<TableSchema>, // Columns, indexes and keys of the table in the schema panel
+    pub table_schema_scroll: u16,         // Scroll offset in the schema panel
+    pub ddl: Vec<String>,                 // CREATE statements shown in the DDL popup
+    pub ddl_scroll: u16,                  // Scroll offset in the DDL popup
+    pub ddl_origin: NavigationMode,       // View the DDL popup returns to
+    pub pending_changes: Vec<RowChange>,  // SQLite edits waiting for the user to confirm the save
+    pub pending_revert: Option<WriteBatch>, // Last save to the database, waiting for the user to confirm its revert
+    pub pending_file_save: Option<(PathBuf, QueryResult)>, // File saved in place and the edited table waiting to overwrite it
+    pub pending_paste: Vec<(usize, usize, String)>, // Cells (row, column, text) a paste changes once confirmed
+    pub paste_skipped: (usize, usize),    // Pasted cells left out: not fitting their column's type, outside the page
+    pub pending_statement: Option<StatementPlan>, // Data-modifying query waiting for confirmation
+    pub filters: Vec<ColumnFilter>,       // Active column filters, at most one per column
+    pub filter_input: String,             // Expression typed at the filter prompt
+    pub goto_input: String,               // Row number or percentage typed at the goto prompt
+    pub cell_search: Option<String>,      // Text highlighted in the table and stepped through with n/N
+    pub cell_search_input: String,        // Text typed at the search prompt
+    pub attach_input: String,             // Path typed at the attach prompt
+    pub attached_databases: Vec<String>,  // Schema names of attached SQLite files, grouped in the sidebar
+    pub query_history: Vec<String>,       // Queries executed on this file, oldest first
+    pub history_position: Option<usize>,  // History entry shown in the query prompt while browsing with Up/Down
+    pub history_draft: String,            // Query typed before browsing the history, restored past the newest entry
+    pub history_search: String,           // Text searched for in the query history popup
+    pub history_selected: usize,          // Selected match in the query history popup
+    pub saved_queries: Vec<SavedQuery>,   // Named queries of this file
+    pub saved_query_name: String,         // Name typed for the query being saved
+    pub saved_query_selected: usize,      // Selected entry in the saved query list
+    pub completion: Option<Completion>,   // Names offered by Tab in the query prompt
+    pub query_error: Option<SyntaxError>, // Syntax error of the query in the prompt, shown until it is edited
 }
 
 impl AppState {
     pub fn new(db_path: String, tables: Vec<String>) -> Result<Self> {
-        let persistence = ComputedColumnPersistence::new()
-            .context("Failed to initialize computed column persistence")?;
+        let persistence = SessionStore::new()
+            .context("Failed to initialize the session store")?;
 
         Ok(Self {
+            table_order: tables.clone(),
+            favorite_tables: Vec::new(),
+            change_signature: None,
+            external_change: false,
+            dirty: true,
+            column_types: HashMap::new(),
+            schema_objects: Vec::new(),
+            schema_selected: 0,
+            sidebar_objects: Vec::new(),
+            collapsed_sections: vec!["index", "trigger"],
+            sidebar_cursor: None,
+            followed_keys: Vec::new(),
+            stats_worker: None,
+            table_stats: HashMap::new(),
+            stats_pending: Vec::new(),
+            aggregates: Vec::new(),
+            window_values: HashMap::new(),
+            page_cache: None,
+            counting_rows: false,
+            loading_sheets: Vec::new(),
+            table_schema: None,
+            table_schema_scroll: 0,
+            ddl: Vec::new(),
+            ddl_scroll: 0,
+            ddl_origin: NavigationMode::Table,
+            pending_changes: Vec::new(),
+            pending_revert: None,
+            pending_file_save: None,
+            pending_paste: Vec::new(),
+            paste_skipped: (0, 0),
+            pending_statement: None,
+            query_over_database: false,
+            database_query: None,
+            export_selected: 0,
+            results_origin: NavigationMode::Data,
+            export_typed: true,
+            export_scope: ExportScope::All,
+            export_visible_columns: false,
+            export_computed: true,
+            export_path_input: String::new(),
+            export_path_matches: Vec::new(),
+            export_overwrite: false,
+            filters: Vec::new(),
+            filter_input: String::new(),
+            goto_input: String::new(),
+            cell_search: None,
+            cell_search_input: String::new(),
+            attach_input: String::new(),
+            attached_databases: Vec::new(),
+            query_history: Vec::new(),
+            history_position: None,
+            history_draft: String::new(),
+            history_search: String::new(),
+            history_selected: 0,
+            saved_queries: Vec::new(),
+            saved_query_name: String::new(),
+            saved_query_selected: 0,
+            completion: None,
+            query_error: None,
             tables,
             selected_table_idx: 0,
             selected_row_idx: 0,
@@ -92,10 +592,15 @@ impl AppState {
             page_size: 25,
             current_data: None,
             original_data: None,
+            undecoded_columns: Vec::new(),
+            edit_log: EditLog::default(),
             db_path,
             status_message: None,
             show_help: false,
             edit_input: String::new(),
+            text_editor: None,
+            text_editor_size: (70, 50),
+            visual_anchor: None,
             editing_cell: None,
             data_modified: false,
             detailed_view_row: None,
@@ -104,8 +609,56 @@ impl AppState {
             error_message: None,
             previous_navigation_mode: NavigationMode::Data,
             computed_column_input: String::new(),
+            computed_column_edit: None,
+            computed_manager_selected: 0,
             computed_columns: Vec::new(),
             persistence,
+            keymap: Keymap::default(),
+            format: FormatConfig::default(),
+            view_state: TableViewState::default(),
+            diff_input: String::new(),
+            diff_result: None,
+            diff_description: String::new(),
+            diff_selected_row: 0,
+            join_input: String::new(),
+            joined_table: None,
+            reader_config: Config::default(),
+            table_profile: None,
+            profile_scroll: 0,
+            search_input: String::new(),
+            search_results: Vec::new(),
+            search_selected: 0,
+            pipe_input: String::new(),
+            pipe_scope: PipeScope::Row,
+            pending_pipe: None,
+            pipe_output: None,
+            pipe_output_title: String::new(),
+            pipe_output_scroll: 0,
+            external_tools: Vec::new(),
+            tool_selected: 0,
+            column_manager_selected: 0,
+            column_edit_input: String::new(),
+            column_edit_target: None,
+            columns_changed: false,
+            fill_input: String::new(),
+            fill_series: false,
+            bulk_edit_input: String::new(),
+            bulk_preview: None,
+            insert_form: None,
+            value_counts_selected: 0,
+            column_summary: None,
+            pending_tool: None,
+            pending_query: None,
+            current_query_params: Vec::new(),
+            parameter_query: String::new(),
+            query_params: Vec::new(),
+            query_param_selected: 0,
+            last_param_values: HashMap::new(),
+            running_query: None,
+            bookmarks: Vec::new(),
+            bookmark_input: String::new(),
+            bookmark_cell: true,
+            bookmark_selected: 0,
         })
     }
 
@@ -131,9 +684,52 @@ impl AppState {
             NavigationMode::Edit => self.handle_edit_mode(key_event, data_source),
             NavigationMode::DetailedView => self.handle_detailed_view(key_event, data_source),
             NavigationMode::ErrorDisplay => self.handle_error_display(key_event, data_source),
+            NavigationMode::ComputedColumns => self.handle_computed_column_manager(key_event, data_source),
             NavigationMode::ComputedColumn => {
                 self.handle_computed_column_input(key_event, data_source)
             }
+            NavigationMode::DiffInput => self.handle_diff_input(key_event, data_source),
+            NavigationMode::Diff => self.handle_diff_view(key_event),
+            NavigationMode::JoinInput => self.handle_join_input(key_event),
+            NavigationMode::Join => self.handle_join_view(key_event),
+            NavigationMode::Profile => self.handle_profile(key_event),
+            NavigationMode::GlobalSearch => self.handle_global_search_input(key_event, data_source),
+            NavigationMode::SearchResults => self.handle_search_results(key_event, data_source),
+            NavigationMode::PipeInput => self.handle_pipe_input(key_event, data_source),
+            NavigationMode::PipeOutput => self.handle_pipe_output(key_event),
+            NavigationMode::ToolPicker => self.handle_tool_picker(key_event),
+            NavigationMode::BookmarkInput => self.handle_bookmark_input(key_event, data_source),
+            NavigationMode::Bookmarks => self.handle_bookmark_list(key_event, data_source),
+            NavigationMode::SchemaObjects => self.handle_schema_objects(key_event, data_source),
+            NavigationMode::ObjectDefinition => self.handle_object_definition(key_event),
+            NavigationMode::TableSchema => self.handle_table_schema(key_event, data_source),
+            NavigationMode::Ddl => self.handle_ddl(key_event),
+            NavigationMode::ConfirmSave => self.handle_confirm_save(key_event, data_source),
+            NavigationMode::ConfirmRevert => self.handle_confirm_revert(key_event, data_source),
+            NavigationMode::ConfirmFileSave => self.handle_confirm_file_save(key_event, data_source),
+            NavigationMode::ConfirmPaste => self.handle_confirm_paste(key_event),
+            NavigationMode::FillDown => self.handle_fill_down(key_event),
+            NavigationMode::BulkEditInput => self.handle_bulk_edit_input(key_event, data_source),
+            NavigationMode::BulkPreview => self.handle_bulk_preview(key_event),
+            NavigationMode::InsertForm => self.handle_insert_form(key_event),
+            NavigationMode::TextEdit => self.handle_text_edit(key_event, data_source),
+            NavigationMode::Visual => self.handle_visual_mode(key_event),
+            NavigationMode::ConfirmStatement => self.handle_confirm_statement(key_event),
+            NavigationMode::QueryParameters => self.handle_query_parameters(key_event),
+            NavigationMode::DatabaseResults => self.handle_database_results(key_event, data_source),
+            NavigationMode::ExportResults => self.handle_export_results(key_event, data_source),
+            NavigationMode::ExportPath => self.handle_export_path(key_event, data_source),
+            NavigationMode::ColumnSummary => self.handle_column_summary(key_event, data_source),
+            NavigationMode::FilterInput => self.handle_filter_input(key_event, data_source),
+            NavigationMode::GotoRow => self.handle_goto_row(key_event, data_source),
+            NavigationMode::CellSearch => self.handle_cell_search(key_event, data_source),
+            NavigationMode::ColumnManager => self.handle_column_manager(key_event, data_source),
+            NavigationMode::ColumnEditInput => self.handle_column_edit_input(key_event, data_source),
+            NavigationMode::ConfirmDropColumn => self.handle_confirm_drop_column(key_event, data_source),
+            NavigationMode::AttachInput => self.handle_attach_input(key_event, data_source),
+            NavigationMode::QueryHistory => self.handle_query_history(key_event),
+            NavigationMode::SavedQueryName => self.handle_saved_query_name(key_event, data_source),
+            NavigationMode::SavedQueries => self.handle_saved_queries(key_event, data_source),
         }
     }
 
@@ -142,41 +738,79 @@ impl AppState {
         key_event: KeyEvent,
         data_source: &mut DataSource,
     ) -> Result<bool> {
+        // Any other key accepts the inserted name and closes the suggestions
+        if key_event.code != KeyCode::Tab {
+            self.completion = None;
+        }
+        // A second Enter runs the query anyway, for syntax the parser doesn't know
+        let checked = if key_event.code == KeyCode::Enter { self.query_error.take() } else { None };
+        self.query_error = None;
         match key_event.code {
+            KeyCode::Tab => match &mut self.completion {
+                Some(completion) => completion.cycle(&mut self.query_input),
+                None => {
+                    let names = self.completion_names(data_source);
+                    self.completion = Completion::start(&mut self.query_input, &names);
+                }
+            },
             KeyCode::Esc => {
-                self.navigation_mode = NavigationMode::Data;
+                self.navigation_mode = self.query_origin();
                 self.query_input.clear();
+                self.history_position = None;
+            }
+            KeyCode::Up => {
+                let position = match self.history_position {
+                    Some(position) => position.saturating_sub(1),
+                    None if self.query_history.is_empty() => return Ok(true),
+                    None => {
+                        self.history_draft = self.query_input.clone();
+                        self.query_history.len() - 1
+                    }
+                };
+                self.history_position = Some(position);
+                self.query_input = self.query_history[position].clone();
+            }
+            KeyCode::Down => {
+                if let Some(position) = self.history_position {
+                    if position + 1 < self.query_history.len() {
+                        self.history_position = Some(position + 1);
+                        self.query_input = self.query_history[position + 1].clone();
+                    } else {
+                        self.history_position = None;
+                        self.query_input = std::mem::take(&mut self.history_draft);
+                    }
+                }
+            }
+            KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.query_history.is_empty() {
+                    self.status_message = Some("No queries run on this file yet".to_string());
+                } else {
+                    self.history_search = self.query_input.clone();
+                    self.history_selected = 0;
+                    self.navigation_mode = NavigationMode::QueryHistory;
+                }
+            }
+            KeyCode::Char('s') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.query_input.trim().is_empty() {
+                    self.status_message = Some("Type a query to save first".to_string());
+                } else {
+                    self.saved_query_name.clear();
+                    self.navigation_mode = NavigationMode::SavedQueryName;
+                }
             }
             KeyCode::Enter => {
-                if !self.query_input.trim().is_empty() {
-                    if let Some(table_name) = self.current_table() {
-                        if data_source.supports_custom_queries() {
-                            match data_source.execute_custom_query(
-                                &self.query_input,
-                                table_name,
-                                0,
-                                self.page_size,
-                            ) {
-                                Ok(result) => {
-                                    self.current_query = Some(self.query_input.clone());
-                                    self.current_data = Some(result);
-                                    self.selected_row_idx = 0;
-                                    self.data_offset = 0;
-                                    self.status_message =
-                                        Some("Query executed successfully".to_string());
-                                }
-                                Err(e) => {
-                                    self.show_error(format!("Query error: {}", e));
-                                }
-                            }
-                        } else {
-                            self.status_message =
-                                Some("Custom queries not supported for this file type".to_string());
-                        }
+                if checked.is_none() {
+                    if let Some(error) = data_source.syntax_error(&self.query_input) {
+                        self.query_error = Some(error);
+                        return Ok(true);
                     }
                 }
-                self.navigation_mode = NavigationMode::Data;
-                self.query_input.clear();
+                self.navigation_mode = self.query_origin();
+                let query = std::mem::take(&mut self.query_input);
+                if !query.trim().is_empty() {
+                    self.run_custom_query(&query, data_source);
+                }
+                self.history_position = None;
             }
             KeyCode::Backspace => {
                 self.query_input.pop();
@@ -189,215 +823,391 @@ impl AppState {
         Ok(true)
     }
 
-    fn handle_table_navigation(
-        &mut self,
-        key_event: KeyEvent,
-        data_source: &mut DataSource,
-    ) -> Result<bool> {
+    /// Table names, then the columns of the current table, for completion
+    fn completion_names(&self, data_source: &DataSource) -> Vec<String> {
+        let columns = self
+            .current_table()
+            .and_then(|table| data_source.column_names(table))
+            .or_else(|| self.current_data.as_ref().map(|data| data.columns.clone()))
+            .unwrap_or_default();
+        self.tables.iter().cloned().chain(columns).collect()
+    }
+
+    /// Load the queries previously run on the open file
+    pub fn load_query_history(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        self.query_history = self.persistence.load_query_history(&effective_path);
+    }
+
+    /// The view the query prompt returns to: the result tab for whole-database queries that
+    /// already have one
+    fn query_origin(&self) -> NavigationMode {
+        if self.query_over_database && self.database_query.is_some() {
+            NavigationMode::DatabaseResults
+        } else {
+            NavigationMode::Data
+        }
+    }
+
+    /// Open the query prompt for a query over the whole database rather than the current table
+    fn open_database_query(&mut self, data_source: &DataSource) {
+        if !data_source.supports_custom_queries() {
+            self.status_message = Some("Custom queries not supported for this file type".to_string());
+            return;
+        }
+        self.query_over_database = true;
+        self.query_input.clear();
+        self.history_position = None;
+        self.navigation_mode = NavigationMode::Query;
+    }
+
+    /// Run a query against the current table (or the whole database), showing its first page
+    /// of results
+    fn run_custom_query(&mut self, query: &str, data_source: &mut DataSource) {
+        self.record_query(query, data_source);
+        if self.current_table().is_none() && !self.query_over_database {
+            return;
+        }
+        if !data_source.supports_custom_queries() {
+            self.status_message = Some("Custom queries not supported for this file type".to_string());
+            return;
+        }
+        let names = data_source.query_parameters(query);
+        if names.is_empty() {
+            self.queue_query(query.to_string(), Vec::new());
+            return;
+        }
+        // Ask for the placeholder values, starting from those entered last time
+        self.parameter_query = query.to_string();
+        self.query_params = names
+            .into_iter()
+            .map(|name| {
+                let value = self.last_param_values.get(&name).cloned().unwrap_or_default();
+                (name, value)
+            })
+            .collect();
+        self.query_param_selected = 0;
+        self.navigation_mode = NavigationMode::QueryParameters;
+    }
+
+    fn queue_query(&mut self, query: String, params: Vec<(String, String)>) {
+        let table = match self.current_table() {
+            _ if self.query_over_database => None,
+            Some(table) => Some(table.to_string()),
+            None => return,
+        };
+        self.pending_query = Some(QueryJob::Query { query, params, table, page_size: self.page_size });
+    }
+
+    /// Fill in the values of the query's `:name` placeholders; they are bound, never pasted
+    /// into the SQL
+    fn handle_query_parameters(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let count = self.query_params.len();
         match key_event.code {
-            KeyCode::Up => {
-                if self.selected_table_idx > 0 {
-                    self.selected_table_idx -= 1;
-                    self.reset_data_view();
-                    self.load_current_data(data_source)?;
-                }
+            KeyCode::Esc => {
+                self.query_input = std::mem::take(&mut self.parameter_query);
+                self.navigation_mode = NavigationMode::Query;
             }
-            KeyCode::Down => {
-                if self.selected_table_idx < self.tables.len().saturating_sub(1) {
-                    self.selected_table_idx += 1;
-                    self.reset_data_view();
-                    self.load_current_data(data_source)?;
+            KeyCode::Enter => {
+                for (name, value) in &self.query_params {
+                    self.last_param_values.insert(name.clone(), value.clone());
                 }
+                self.navigation_mode = self.query_origin();
+                let query = std::mem::take(&mut self.parameter_query);
+                let params = std::mem::take(&mut self.query_params);
+                self.queue_query(query, params);
             }
-            KeyCode::Right | KeyCode::Enter => {
-                self.navigation_mode = NavigationMode::Data;
-                self.data_offset = 0;
-                self.selected_row_idx = 0;
+            KeyCode::Tab | KeyCode::Down => {
+                self.query_param_selected = (self.query_param_selected + 1) % count;
             }
-            KeyCode::Char('q') | KeyCode::Char('c')
-                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
-            {
-                return Ok(false);
+            KeyCode::BackTab | KeyCode::Up => {
+                self.query_param_selected = (self.query_param_selected + count - 1) % count;
             }
-            KeyCode::Char('h') => {
-                self.show_help = !self.show_help;
+            KeyCode::Backspace => {
+                self.query_params[self.query_param_selected].1.pop();
+            }
+            KeyCode::Char(c) => {
+                self.query_params[self.query_param_selected].1.push(c);
             }
             _ => {}
         }
         Ok(true)
     }
 
-    fn handle_data_navigation(
+    /// Show the outcome of a query the main loop ran on its worker thread
+    pub fn finish_query(
         &mut self,
-        key_event: KeyEvent,
+        job: &QueryJob,
+        result: Result<QueryOutcome>,
         data_source: &mut DataSource,
-    ) -> Result<bool> {
+    ) -> Result<()> {
+        let cancelled = self.running_query.take().is_some_and(|running| running.cancelled);
+        match (job, result) {
+            (_, Err(_)) if cancelled => {
+                self.status_message = Some("Query cancelled".to_string());
+            }
+            (QueryJob::Query { query, params, table: None, .. }, Ok(QueryOutcome::Rows(result))) => {
+                self.database_query = Some(DatabaseQuery {
+                    query: query.clone(),
+                    params: params.clone(),
+                    data: result,
+                    offset: 0,
+                    selected_row: 0,
+                    selected_col: 0,
+                });
+                self.navigation_mode = NavigationMode::DatabaseResults;
+                self.status_message = Some("Query executed successfully".to_string());
+            }
+            (_, Ok(QueryOutcome::Rows(result))) => {
+                if let QueryJob::Query { query, params, .. } = job {
+                    self.current_query = Some(query.clone());
+                    self.current_query_params = params.clone();
+                }
+                self.current_data = Some(result);
+                self.undecoded_columns.clear();
+                self.selected_row_idx = 0;
+                self.data_offset = 0;
+                self.status_message = Some("Query executed successfully".to_string());
+            }
+            (_, Ok(QueryOutcome::Confirm(plan))) => {
+                self.pending_statement = Some(plan);
+                self.navigation_mode = NavigationMode::ConfirmStatement;
+            }
+            (_, Ok(QueryOutcome::Executed(changed))) => self.statement_ran(changed, data_source)?,
+            (_, Ok(QueryOutcome::Summary(summary))) => self.show_column_summary(summary),
+            (_, Ok(QueryOutcome::Joined(joined))) => {
+                self.status_message = Some(format!("{} joined rows", joined.data.rows.len()));
+                self.joined_table = Some(joined);
+                self.navigation_mode = NavigationMode::Join;
+            }
+            (_, Ok(QueryOutcome::Profile(profile))) => {
+                self.table_profile = Some(profile);
+                self.profile_scroll = 0;
+                self.navigation_mode = NavigationMode::Profile;
+            }
+            (QueryJob::ColumnSummary { .. }, Err(e)) => self.show_error(format!("Failed to summarize the column: {}", e)),
+            (QueryJob::Profile { .. }, Err(e)) => self.show_error(format!("Failed to profile the table: {}", e)),
+            (QueryJob::Join { .. }, Err(e)) => self.show_error(format!("Join failed: {:#}", e)),
+            (QueryJob::Statement(_), Err(e)) => self.show_error(format!("Statement failed: {:#}", e)),
+            (_, Err(e)) => self.show_error(format!("Query error: {}", e)),
+        }
+        Ok(())
+    }
+
+    fn record_query(&mut self, query: &str, data_source: &DataSource) {
+        push_query_history(&mut self.query_history, query);
+        let effective_path = self.get_effective_persistence_path(data_source);
+        // Not worth interrupting the query for; connections have no file to store it next to
+        if let Err(e) = self.persistence.save_query_history(&effective_path, &self.query_history) {
+            tracing::warn!(error = %e, "failed to save query history");
+        }
+    }
+
+    /// Load the named queries stored for the open file
+    pub fn load_saved_queries(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        self.saved_queries = self.persistence.load_saved_queries(&effective_path);
+    }
+
+    fn save_saved_queries(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        if let Err(e) = self.persistence.save_saved_queries(&effective_path, &self.saved_queries) {
+            self.show_error(format!("Failed to save queries: {}", e));
+        }
+    }
+
+    /// Name the query in the prompt; saving under an existing name replaces that query
+    fn handle_saved_query_name(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
         match key_event.code {
-            KeyCode::Up => {
-                if self.selected_row_idx > 0 {
-                    self.selected_row_idx -= 1;
-                } else if self.data_offset > 0 {
-                    self.data_offset = self.data_offset.saturating_sub(self.page_size);
-                    self.selected_row_idx = self.page_size - 1;
-                    self.load_current_data(data_source)?;
-                    if let Some(data) = &self.current_data {
-                        if self.selected_row_idx >= data.rows.len() {
-                            self.selected_row_idx = data.rows.len().saturating_sub(1);
-                        }
-                    }
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Query;
+            }
+            KeyCode::Enter => {
+                let name = match self.saved_query_name.trim() {
+                    "" => self.query_input.trim().to_string(),
+                    name => name.to_string(),
+                };
+                let saved = SavedQuery {
+                    name: name.clone(),
+                    query: self.query_input.trim().to_string(),
+                };
+                match self.saved_queries.iter_mut().find(|q| q.name == name) {
+                    Some(existing) => *existing = saved,
+                    None => self.saved_queries.push(saved),
+                }
+                self.save_saved_queries(data_source);
+                if self.navigation_mode != NavigationMode::ErrorDisplay {
+                    self.navigation_mode = NavigationMode::Query;
+                    self.status_message = Some(format!("Saved query '{}'", name));
                 }
             }
+            KeyCode::Backspace => {
+                self.saved_query_name.pop();
+            }
+            KeyCode::Char(c) => {
+                self.saved_query_name.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn open_saved_queries(&mut self) {
+        if self.saved_queries.is_empty() {
+            self.status_message =
+                Some("No saved queries yet; press Ctrl+S in the query prompt to save one".to_string());
+            return;
+        }
+        self.navigation_mode = NavigationMode::SavedQueries;
+        self.saved_query_selected = self.saved_query_selected.min(self.saved_queries.len() - 1);
+    }
+
+    fn handle_saved_queries(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Up => {
+                self.saved_query_selected = self.saved_query_selected.saturating_sub(1);
+            }
             KeyCode::Down => {
-                if let Some(data) = &self.current_data {
-                    if self.selected_row_idx < data.rows.len().saturating_sub(1) {
-                        self.selected_row_idx += 1;
-                    } else if self.data_offset + data.rows.len() < data.total_rows {
-                        self.data_offset += self.page_size;
-                        self.selected_row_idx = 0;
-                        self.load_current_data(data_source)?;
-                    }
-                }
+                self.saved_query_selected =
+                    (self.saved_query_selected + 1).min(self.saved_queries.len().saturating_sub(1));
             }
-            KeyCode::Left => {
-                if let Some(data) = &self.current_data {
-                    let min_col = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-                        1
-                    } else {
-                        0
-                    };
-                    if self.selected_col_idx > min_col {
-                        self.selected_col_idx -= 1;
-                    } else {
-                        // Go back to table view when at first column
-                        self.navigation_mode = NavigationMode::Table;
-                        self.reset_data_view();
-                        self.load_current_data(data_source)?;
-                    }
+            KeyCode::Delete | KeyCode::Char('d') if self.saved_query_selected < self.saved_queries.len() => {
+                let removed = self.saved_queries.remove(self.saved_query_selected);
+                self.save_saved_queries(data_source);
+                self.status_message = Some(format!("Removed query '{}'", removed.name));
+                if self.saved_queries.is_empty() {
+                    self.navigation_mode = NavigationMode::Data;
                 } else {
-                    self.navigation_mode = NavigationMode::Table;
-                    self.reset_data_view();
-                    self.load_current_data(data_source)?;
-                }
-            }
-            KeyCode::Right => {
-                if let Some(data) = &self.current_data {
-                    if self.selected_col_idx < data.columns.len().saturating_sub(1) {
-                        self.selected_col_idx += 1;
-                    }
+                    self.saved_query_selected = self.saved_query_selected.min(self.saved_queries.len() - 1);
                 }
             }
-            KeyCode::PageUp => {
-                if self.data_offset > 0 {
-                    self.data_offset = self.data_offset.saturating_sub(self.page_size);
-                    self.selected_row_idx = 0;
-                    self.load_current_data(data_source)?;
+            // Opened in the query prompt to adjust before running
+            KeyCode::Char('e') => {
+                if let Some(saved) = self.saved_queries.get(self.saved_query_selected) {
+                    self.query_over_database = false;
+                    self.query_input = saved.query.clone();
+                    self.history_position = None;
+                    self.navigation_mode = NavigationMode::Query;
                 }
             }
-            KeyCode::PageDown => {
-                if let Some(data) = &self.current_data {
-                    if self.data_offset + data.rows.len() < data.total_rows {
-                        self.data_offset += self.page_size;
-                        self.selected_row_idx = 0;
-                        self.load_current_data(data_source)?;
-                    }
+            KeyCode::Enter => {
+                if let Some(saved) = self.saved_queries.get(self.saved_query_selected).cloned() {
+                    self.navigation_mode = NavigationMode::Data;
+                    self.query_over_database = false;
+                    self.run_custom_query(&saved.query, data_source);
                 }
             }
-            KeyCode::Home => {
-                self.data_offset = 0;
-                self.selected_row_idx = 0;
-                self.load_current_data(data_source)?;
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// History entries containing the search text, newest first
+    pub fn history_matches(&self) -> Vec<&String> {
+        let search = self.history_search.to_lowercase();
+        self.query_history
+            .iter()
+            .rev()
+            .filter(|query| query.to_lowercase().contains(&search))
+            .collect()
+    }
+
+    fn handle_query_history(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let match_count = self.history_matches().len();
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Query;
             }
-            KeyCode::End => {
-                if let Some(data) = &self.current_data {
-                    self.data_offset = data.total_rows.saturating_sub(self.page_size);
-                    self.selected_row_idx = 0;
-                    self.load_current_data(data_source)?;
-                }
+            KeyCode::Up => {
+                self.history_selected = self.history_selected.saturating_sub(1);
             }
-            KeyCode::Char(' ') => {
-                if let Some(data) = &self.current_data {
-                    if self.selected_row_idx < data.rows.len()
-                        && self.selected_col_idx < data.columns.len()
-                    {
-                        // Prevent editing rowid column (column 0)
-                        if !data.columns.is_empty()
-                            && data.columns[0] == "rowid"
-                            && self.selected_col_idx == 0
-                        {
-                            self.show_error("Cannot edit rowid column".to_string());
-                            return Ok(true);
-                        }
-
-                        self.navigation_mode = NavigationMode::Edit;
-                        self.editing_cell = Some((self.selected_row_idx, self.selected_col_idx));
-                        self.edit_input =
-                            data.rows[self.selected_row_idx][self.selected_col_idx].clone();
-                    }
+            KeyCode::Down => {
+                self.history_selected = (self.history_selected + 1).min(match_count.saturating_sub(1));
+            }
+            // Like a shell, Ctrl+R again steps to the next older match
+            KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.history_selected = (self.history_selected + 1).min(match_count.saturating_sub(1));
+            }
+            KeyCode::Enter => {
+                if let Some(query) = self.history_matches().get(self.history_selected) {
+                    self.query_input = query.to_string();
+                    self.history_position = None;
                 }
+                self.navigation_mode = NavigationMode::Query;
             }
-            KeyCode::Char('n') => {
-                // Add new row
-                if let Some(data) = &mut self.current_data {
-                    let mut new_row: Vec<String> =
-                        data.columns.iter().map(|_| String::new()).collect();
-                    // Set rowid to empty for new rows (will be handled by INSERT)
-                    if !data.columns.is_empty() && data.columns[0] == "rowid" {
-                        new_row[0] = String::new();
-                    }
+            KeyCode::Backspace => {
+                self.history_search.pop();
+                self.history_selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.history_search.push(c);
+                self.history_selected = 0;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
 
-                    data.rows.push(new_row);
-                    data.total_rows += 1;
-                    self.data_modified = true;
-                    self.selected_row_idx = data.rows.len() - 1;
-                    self.selected_col_idx = if data.columns.is_empty() || data.columns[0] != "rowid"
-                    {
-                        0
-                    } else {
-                        1
-                    };
-                    
-                    // Immediately enter edit mode for the first editable cell
-                    self.navigation_mode = NavigationMode::Edit;
-                    self.editing_cell = Some((self.selected_row_idx, self.selected_col_idx));
-                    self.edit_input = String::new(); // Start with empty input for new cell
-                    self.status_message = Some("New row added - editing".to_string());
-                }
+    fn handle_table_navigation(
+        &mut self,
+        key_event: KeyEvent,
+        data_source: &mut DataSource,
+    ) -> Result<bool> {
+        let key_event = match self.keymap.action(key_event) {
+            Some(Action::Key(code)) => KeyEvent::new(code, KeyModifiers::NONE),
+            _ => key_event,
+        };
+        match key_event.code {
+            KeyCode::Up => {
+                self.move_sidebar_cursor(false, data_source)?;
             }
-            KeyCode::Char('i') => {
-                self.navigation_mode = NavigationMode::Query;
-                self.query_input.clear();
+            KeyCode::Down => {
+                self.move_sidebar_cursor(true, data_source)?;
             }
-            KeyCode::Char('=') => {
-                self.navigation_mode = NavigationMode::ComputedColumn;
-                self.computed_column_input.clear();
+            KeyCode::Right | KeyCode::Enter => match self.sidebar_cursor {
+                Some(SidebarEntry::Section(kind)) => self.toggle_section(kind),
+                Some(SidebarEntry::Object(_)) => self.navigation_mode = NavigationMode::ObjectDefinition,
+                _ => self.navigation_mode = NavigationMode::Data,
+            },
+            KeyCode::Char('*') => {
+                self.toggle_favorite(data_source);
             }
-            KeyCode::Char('e') => {
-                self.export_to_csv(data_source)?;
+            KeyCode::Char('I') => {
+                self.open_database_query(data_source);
             }
-            KeyCode::Char('s') => {
-                // If we're in a custom query, warn user to go back to table view
-                if self.current_query.is_some() {
-                    self.show_error(
-                        "Cannot save custom query results. Press 'r' to reload table data first."
-                            .to_string(),
-                    );
-                } else {
-                    self.save_changes(data_source)?;
-                }
+            KeyCode::Char('R') => {
+                self.show_database_results();
             }
             KeyCode::Char('r') => {
-                // Clear custom query to reload original table data
-                self.current_query = None;
-                self.load_current_data(data_source)?;
+                self.refresh_from_disk(data_source)?;
             }
-            KeyCode::Enter => {
-                // Show detailed view for selected row
-                if let Some(data) = &self.current_data {
-                    if self.selected_row_idx < data.rows.len() {
-                        self.detailed_view_row = Some(self.selected_row_idx);
-                        self.detailed_view_selected_field = 0;
-                        self.navigation_mode = NavigationMode::DetailedView;
-                    }
+            KeyCode::Char('F') => {
+                self.open_global_search();
+            }
+            KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_global_search();
+            }
+            KeyCode::Char('x') => {
+                self.open_tool_picker();
+            }
+            KeyCode::Char('B') => {
+                self.open_bookmark_list();
+            }
+            KeyCode::Char('S') => {
+                self.open_table_schema(data_source);
+            }
+            KeyCode::Char('L') => {
+                self.open_ddl(data_source);
+            }
+            KeyCode::Char('A') => {
+                if matches!(data_source, DataSource::Sqlite(_)) {
+                    self.attach_input.clear();
+                    self.navigation_mode = NavigationMode::AttachInput;
+                } else {
+                    self.status_message = Some("Only SQLite databases can attach other databases".to_string());
                 }
             }
             KeyCode::Char('q') | KeyCode::Char('c')
@@ -413,1486 +1223,8386 @@ impl AppState {
         Ok(true)
     }
 
-    fn handle_edit_mode(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+    /// The sidebar as listed: plain tables first, then a section per kind of schema object.
+    /// A folded section still lists the selected table, so the selection stays visible.
+    fn sidebar_entries(&self) -> Vec<SidebarEntry> {
+        let kinds: HashMap<&str, &str> = self
+            .sidebar_objects
+            .iter()
+            .filter(|o| matches!(o.kind.as_str(), "view" | "virtual table"))
+            .map(|o| (o.name.as_str(), o.kind.as_str()))
+            .collect();
+        let tables_of = |kind: Option<&str>| -> Vec<SidebarEntry> {
+            (0..self.tables.len())
+                .filter(|&i| kinds.get(self.tables[i].as_str()).copied() == kind)
+                .map(SidebarEntry::Table)
+                .collect()
+        };
+
+        let mut entries = tables_of(None);
+        for (kind, _) in SIDEBAR_SECTIONS {
+            let members: Vec<SidebarEntry> = match kind {
+                "view" | "virtual table" => tables_of(Some(kind)),
+                _ => (0..self.sidebar_objects.len())
+                    .filter(|&j| self.sidebar_objects[j].kind == kind)
+                    .map(SidebarEntry::Object)
+                    .collect(),
+            };
+            if members.is_empty() {
+                continue;
+            }
+            entries.push(SidebarEntry::Section(kind));
+            let collapsed = self.collapsed_sections.contains(&kind);
+            entries.extend(
+                members
+                    .into_iter()
+                    .filter(|entry| !collapsed || *entry == SidebarEntry::Table(self.selected_table_idx)),
+            );
+        }
+        entries
+    }
+
+    /// Move the sidebar cursor one line. Tables open as the cursor reaches them; on a section
+    /// header or an index/trigger the current table stays open.
+    fn move_sidebar_cursor(&mut self, down: bool, data_source: &mut DataSource) -> Result<()> {
+        let entries = self.sidebar_entries();
+        let current = self.sidebar_cursor.unwrap_or(SidebarEntry::Table(self.selected_table_idx));
+        let Some(position) = entries.iter().position(|entry| *entry == current) else {
+            return Ok(());
+        };
+        let target = if down { entries.get(position + 1) } else { position.checked_sub(1).and_then(|p| entries.get(p)) };
+        match target {
+            Some(SidebarEntry::Table(idx)) if *idx == self.selected_table_idx => self.sidebar_cursor = None,
+            Some(SidebarEntry::Table(idx)) => {
+                self.followed_keys.clear();
+                self.switch_table(*idx, data_source)?;
+            }
+            Some(entry) => self.sidebar_cursor = Some(*entry),
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn toggle_section(&mut self, kind: &'static str) {
+        if let Some(idx) = self.collapsed_sections.iter().position(|k| *k == kind) {
+            self.collapsed_sections.remove(idx);
+        } else {
+            self.collapsed_sections.push(kind);
+        }
+    }
+
+    fn handle_object_definition(&mut self, key_event: KeyEvent) -> Result<bool> {
+        if matches!(key_event.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+            self.navigation_mode = NavigationMode::Table;
+        }
+        Ok(true)
+    }
+
+    fn handle_attach_input(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
         match key_event.code {
             KeyCode::Esc => {
-                self.navigation_mode = NavigationMode::Data;
-                self.editing_cell = None;
-                self.edit_input.clear();
+                self.navigation_mode = NavigationMode::Table;
             }
             KeyCode::Enter => {
-                if let Some((row_idx, col_idx)) = self.editing_cell {
-                    if let Some(data) = &mut self.current_data {
-                        if row_idx < data.rows.len() && col_idx < data.columns.len() {
-                            // Don't allow saving changes to rowid column
-                            if !data.columns.is_empty()
-                                && data.columns[0] == "rowid"
-                                && col_idx == 0
-                            {
-                                self.show_error("Cannot edit rowid column".to_string());
-                            } else {
-                                data.rows[row_idx][col_idx] = self.edit_input.clone();
-                                self.data_modified = true;
-                                self.status_message = Some("Cell updated (not saved)".to_string());
-                            }
-                        }
+                self.navigation_mode = NavigationMode::Table;
+                let path = self.attach_input.trim().to_string();
+                if path.is_empty() {
+                    return Ok(true);
+                }
+                match data_source.attach_database(std::path::Path::new(&path)) {
+                    Ok(schema) => {
+                        self.attached_databases = data_source.attached_databases();
+                        self.reload_tables(data_source)?;
+                        let prefix = format!("{}.", schema);
+                        let count = self.tables.iter().filter(|t| t.starts_with(&prefix)).count();
+                        self.status_message = Some(format!(
+                            "Attached {} as {} ({} tables) - use {}.<table> in queries",
+                            path, schema, count, schema
+                        ));
                     }
+                    Err(e) => self.show_error(format!("Attach failed: {}", e)),
                 }
-                self.navigation_mode = NavigationMode::Data;
-                self.editing_cell = None;
-                self.edit_input.clear();
+            }
+            KeyCode::Backspace => {
+                self.attach_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.attach_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
 
-                // Refresh computed columns after edit
-                if let Err(e) = self.refresh_computed_columns() {
-                    self.show_error(format!("Failed to update computed columns: {}", e));
+    /// Show the selected table's columns, indexes and foreign keys. Servers don't report
+    /// their schema, so their column types are inferred from the page loaded.
+    fn open_table_schema(&mut self, data_source: &DataSource) {
+        let Some(table) = self.current_table() else {
+            return;
+        };
+        let schema = match data_source.table_schema(table, self.format.number_locale) {
+            Ok(Some(schema)) => schema,
+            Ok(None) => match &self.current_data {
+                Some(data) => {
+                    let (columns, rows) = without_rowid(data.columns.clone(), data.rows.clone());
+                    let data = QueryResult { columns, rows, total_rows: data.total_rows };
+                    let types = infer_column_types(&data, self.format.number_locale);
+                    TableSchema::inferred(&data.columns, &types)
+                }
+                None => {
+                    self.status_message = Some("Open the table to see its column types".to_string());
+                    return;
                 }
+            },
+            Err(e) => {
+                self.show_error(format!("Failed to read the schema of {}: {}", table, e));
+                return;
+            }
+        };
+        self.table_schema = Some(schema);
+        self.table_schema_scroll = 0;
+        self.navigation_mode = NavigationMode::TableSchema;
+    }
+
+    fn handle_table_schema(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.navigation_mode = NavigationMode::Table;
             }
             KeyCode::Up => {
-                self.save_current_edit_and_move_to(MoveTo::Up, data_source)?;
+                self.table_schema_scroll = self.table_schema_scroll.saturating_sub(1);
             }
             KeyCode::Down => {
-                self.save_current_edit_and_move_to(MoveTo::Down, data_source)?;
-            }
-            KeyCode::Left => {
-                self.save_current_edit_and_move_to(MoveTo::Left, data_source)?;
-            }
-            KeyCode::Right => {
-                self.save_current_edit_and_move_to(MoveTo::Right, data_source)?;
-            }
-            KeyCode::Backspace => {
-                self.edit_input.pop();
+                self.table_schema_scroll = self.table_schema_scroll.saturating_add(1);
             }
-            KeyCode::Char('n') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                // Add new row
-                if let Some(data) = &mut self.current_data {
-                    let mut new_row: Vec<String> =
-                        data.columns.iter().map(|_| String::new()).collect();
-                    // Set rowid to empty for new rows (will be handled by INSERT)
-                    if !data.columns.is_empty() && data.columns[0] == "rowid" {
-                        new_row[0] = String::new();
-                    }
-
-                    data.rows.push(new_row);
-                    data.total_rows += 1;
-                    self.data_modified = true;
-                    self.selected_row_idx = data.rows.len() - 1;
-                    self.selected_col_idx = if data.columns.is_empty() || data.columns[0] != "rowid"
-                    {
-                        0
-                    } else {
-                        1
-                    };
-                    self.editing_cell = Some((self.selected_row_idx, self.selected_col_idx));
-                    self.edit_input.clear();
-                    self.status_message = Some("New row added".to_string());
-                }
+            KeyCode::PageUp => {
+                self.table_schema_scroll = self.table_schema_scroll.saturating_sub(self.page_size as u16);
             }
-            KeyCode::Char(c) => {
-                self.edit_input.push(c);
+            KeyCode::PageDown => {
+                self.table_schema_scroll = self.table_schema_scroll.saturating_add(self.page_size as u16);
             }
-            KeyCode::Tab => {
-                // Save current edit and move to next cell
-                if let Some((row_idx, col_idx)) = self.editing_cell {
-                    if let Some(data) = &mut self.current_data {
-                        if row_idx < data.rows.len() && col_idx < data.columns.len() {
-                            // Don't allow saving changes to rowid column
-                            if !data.columns.is_empty()
-                                && data.columns[0] == "rowid"
-                                && col_idx == 0
-                            {
-                                // Skip saving changes to rowid column
-                            } else {
-                                data.rows[row_idx][col_idx] = self.edit_input.clone();
-                                self.data_modified = true;
-                            }
-
-                            // Move to next cell
-                            if col_idx < data.columns.len() - 1 {
-                                self.selected_col_idx += 1;
-                                self.editing_cell = Some((row_idx, col_idx + 1));
-                                self.edit_input = data.rows[row_idx][col_idx + 1].clone();
-                            } else if row_idx < data.rows.len() - 1 {
-                                self.selected_row_idx += 1;
-                                let min_col =
-                                    if !data.columns.is_empty() && data.columns[0] == "rowid" {
-                                        1
-                                    } else {
-                                        0
-                                    };
-                                self.selected_col_idx = min_col;
-                                self.editing_cell = Some((row_idx + 1, min_col));
-                                self.edit_input = data.rows[row_idx + 1][min_col].clone();
-                            } else {
-                                // At the end, exit edit mode
-                                self.navigation_mode = NavigationMode::Data;
-                                self.editing_cell = None;
-                                self.edit_input.clear();
-                            }
-                        }
-                    }
-                }
+            // The indexes and triggers of every table
+            KeyCode::Char('S') => {
+                self.navigation_mode = NavigationMode::Table;
+                self.open_schema_objects(data_source)?;
             }
             _ => {}
         }
         Ok(true)
     }
 
-    fn save_current_edit_and_move_to(
-        &mut self,
-        direction: MoveTo,
-        data_source: &mut DataSource,
-    ) -> Result<()> {
-        // Save current edit
-        if let Some((row_idx, col_idx)) = self.editing_cell {
-            if let Some(data) = &mut self.current_data {
-                if row_idx < data.rows.len() && col_idx < data.columns.len() {
-                    // Don't allow saving changes to rowid column
-                    if !data.columns.is_empty() && data.columns[0] == "rowid" && col_idx == 0 {
-                        // Skip saving changes to rowid column
-                    } else {
-                        data.rows[row_idx][col_idx] = self.edit_input.clone();
-                        self.data_modified = true;
-                    }
-                }
+    /// Show the CREATE statements of the current table, returning to the current view
+    fn open_ddl(&mut self, data_source: &DataSource) {
+        let Some(table) = self.current_table() else {
+            return;
+        };
+        match data_source.table_ddl(table) {
+            Ok(ddl) => {
+                self.ddl = ddl;
+                self.ddl_scroll = 0;
+                self.ddl_origin = self.navigation_mode.clone();
+                self.navigation_mode = NavigationMode::Ddl;
             }
+            Err(e) => self.status_message = Some(e.to_string()),
         }
+    }
 
-        // Move to new position
-        if let Some(data) = &self.current_data {
-            let (mut new_row, mut new_col) = (self.selected_row_idx, self.selected_col_idx);
-
-            match direction {
-                MoveTo::Up => {
-                    if new_row > 0 {
-                        new_row -= 1;
-                    } else if self.data_offset > 0 {
-                        self.data_offset = self.data_offset.saturating_sub(self.page_size);
-                        new_row = self.page_size - 1;
-                        self.load_current_data(data_source)?;
-                        if let Some(data) = &self.current_data {
-                            if new_row >= data.rows.len() {
-                                new_row = data.rows.len().saturating_sub(1);
-                            }
-                        }
-                    }
-                }
-                MoveTo::Down => {
-                    if new_row < data.rows.len().saturating_sub(1) {
-                        new_row += 1;
-                    } else if self.data_offset + data.rows.len() < data.total_rows {
-                        self.data_offset += self.page_size;
-                        new_row = 0;
-                        self.load_current_data(data_source)?;
-                    }
-                }
-                MoveTo::Left => {
-                    let min_col = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-                        1
-                    } else {
-                        0
-                    };
-                    if new_col > min_col {
-                        new_col -= 1;
-                    }
-                }
-                MoveTo::Right => {
-                    if new_col < data.columns.len().saturating_sub(1) {
-                        new_col += 1;
-                    }
-                }
+    fn handle_ddl(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => {
+                self.navigation_mode = self.ddl_origin.clone();
             }
-
-            // Update position and edit input
-            self.selected_row_idx = new_row;
-            self.selected_col_idx = new_col;
-            self.editing_cell = Some((new_row, new_col));
-
-            // Load new cell content
-            if let Some(data) = &self.current_data {
-                if new_row < data.rows.len() && new_col < data.columns.len() {
-                    self.edit_input = data.rows[new_row][new_col].clone();
-                }
+            KeyCode::Up => {
+                self.ddl_scroll = self.ddl_scroll.saturating_sub(1);
             }
-        }
-
-        Ok(())
-    }
-
-    fn reset_data_view(&mut self) {
-        self.current_query = None;
-        self.current_data = None;
-        self.original_data = None;
-        self.selected_row_idx = 0;
-        self.selected_col_idx = 0;
-        self.data_offset = 0;
-        self.editing_cell = None;
-        self.edit_input.clear();
-        self.data_modified = false;
-    }
-
-    fn ensure_valid_col_selection(&mut self) {
-        if let Some(data) = &self.current_data {
-            let min_col = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-                1
-            } else {
-                0
-            };
-            if self.selected_col_idx < min_col {
-                self.selected_col_idx = min_col;
+            KeyCode::Down => {
+                self.ddl_scroll = self.ddl_scroll.saturating_add(1);
+            }
+            KeyCode::PageUp => {
+                self.ddl_scroll = self.ddl_scroll.saturating_sub(self.page_size as u16);
+            }
+            KeyCode::PageDown => {
+                self.ddl_scroll = self.ddl_scroll.saturating_add(self.page_size as u16);
             }
+            KeyCode::Home => {
+                self.ddl_scroll = 0;
+            }
+            _ => {}
         }
+        Ok(true)
     }
 
-    pub fn load_current_data(&mut self, data_source: &mut DataSource) -> Result<()> {
-        if let Some(table_name) = self.current_table().map(|s| s.to_string()) {
-            let result = if let Some(query) = &self.current_query {
-                data_source.execute_custom_query(
-                    query,
-                    &table_name,
-                    self.data_offset,
-                    self.page_size,
-                )?
-            } else {
-                data_source.get_table_data(&table_name, self.data_offset, self.page_size)?
-            };
-
-            // Store original data for comparison when saving
-            self.original_data = Some(result.clone());
-            self.current_data = Some(result);
-
-            // Load saved computed columns if available
-            self.load_computed_columns(&table_name, data_source)?;
-
-            // Apply computed columns to the loaded data
-            self.apply_computed_columns(data_source)?;
-
-            // Ensure column selection is valid (skip rowid)
-            self.ensure_valid_col_selection();
+    fn handle_profile(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('P') => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Up => {
+                self.profile_scroll = self.profile_scroll.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.profile_scroll = self.profile_scroll.saturating_add(1);
+            }
+            KeyCode::PageUp => {
+                self.profile_scroll = self.profile_scroll.saturating_sub(self.page_size as u16);
+            }
+            KeyCode::PageDown => {
+                self.profile_scroll = self.profile_scroll.saturating_add(self.page_size as u16);
+            }
+            KeyCode::Home => {
+                self.profile_scroll = 0;
+            }
+            KeyCode::Char('e') => self.export_profile(false),
+            KeyCode::Char('E') => self.export_profile(true),
+            _ => {}
         }
-        Ok(())
+        Ok(true)
     }
 
-    fn get_effective_persistence_path(&self, data_source: &DataSource) -> String {
-        // Use the effective save path if available, otherwise fall back to the original db_path
-        if let Some(effective_path) = data_source.get_effective_save_path() {
-            effective_path.to_string_lossy().to_string()
+    /// Write the profile report to a timestamped Markdown or HTML file in the export directory
+    fn export_profile(&mut self, html: bool) {
+        let Some(profile) = &self.table_profile else {
+            return;
+        };
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let (filename, report) = if html {
+            (format!("profile_{}_{}.html", profile.table, timestamp), profile.to_html())
         } else {
-            self.db_path.clone()
+            (format!("profile_{}_{}.md", profile.table, timestamp), profile.to_markdown())
+        };
+        let path = self.reader_config.export.path_for(&filename);
+        match std::fs::write(&path, report) {
+            Ok(()) => self.status_message = Some(format!("Exported the profile to {}", path.display())),
+            Err(e) => self.show_error(format!("Profile export failed: {}", e)),
         }
     }
 
-    fn load_computed_columns(&mut self, table_name: &str, data_source: &DataSource) -> Result<()> {
-        let effective_path = self.get_effective_persistence_path(data_source);
-        
-        // Check if file has changed and recalculation is needed
-        if self.persistence.should_recalculate(&effective_path) {
-            // File has changed, clear computed columns to force user to recreate them
-            // This is a safety measure to prevent incorrect calculations
-            self.computed_columns.clear();
+    fn open_schema_objects(&mut self, data_source: &DataSource) -> Result<()> {
+        self.schema_objects = data_source.schema_objects()?;
+        if self.schema_objects.is_empty() {
+            self.status_message = Some("No indexes or triggers in this file".to_string());
             return Ok(());
         }
-
-        match self
-            .persistence
-            .load_computed_columns(&effective_path, table_name)
-        {
-            Ok(columns) => {
-                self.computed_columns = columns;
-            }
-            Err(_) => {
-                // No saved columns or file doesn't exist, start with empty list
-                self.computed_columns.clear();
-            }
-        }
-        Ok(())
-    }
-
-    fn save_computed_columns(&self, table_name: &str, data_source: &DataSource) -> Result<()> {
-        let effective_path = self.get_effective_persistence_path(data_source);
-        self.persistence
-            .save_computed_columns(&effective_path, table_name, &self.computed_columns)
-            .context("Failed to save computed columns")?;
-        Ok(())
-    }
-
-    fn export_to_csv(&mut self, data_source: &DataSource) -> Result<()> {
-        if let Some(table_name) = self.current_table() {
-            let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-            let filename = if let Some(_query) = &self.current_query {
-                format!("query_export_{}.csv", timestamp)
-            } else {
-                format!("{}_{}.csv", table_name, timestamp)
-            };
-
-            let rows_exported = if let Some(query) = &self.current_query {
-                data_source.export_query_to_csv(query, &filename)?
-            } else {
-                data_source.export_table_to_csv(table_name, &filename)?
-            };
-
-            self.status_message = Some(format!("Exported {} rows to {}", rows_exported, filename));
-        }
+        // Start on the objects of the selected table
+        self.schema_selected = self
+            .current_table()
+            .and_then(|table| self.schema_objects.iter().position(|o| o.table == table))
+            .unwrap_or(0);
+        self.navigation_mode = NavigationMode::SchemaObjects;
         Ok(())
     }
 
-    pub fn save_changes(&mut self, data_source: &mut DataSource) -> Result<()> {
-        if !self.data_modified {
-            self.status_message = Some("No changes to save".to_string());
-            return Ok(());
-        }
-
-        let table_name = self.current_table().map(|s| s.to_string());
-        if let Some(table_name) = table_name {
-            if let Some(data) = self.current_data.clone() {
-                match data_source.save_table_data(&table_name, &data) {
-                    Ok(()) => {
-                        self.data_modified = false;
-                        
-                        // Reload the data source to reflect the saved changes
-                        if let Err(e) = data_source.reload_data() {
-                            self.status_message = Some(format!("Save successful but reload failed: {}", e));
-                        } else {
-                            match data_source {
-                                crate::data_source::DataSource::Csv(_, path) => {
-                                    self.status_message = Some(format!("Changes saved to {}", path.display()));
-                                }
-                                crate::data_source::DataSource::Xlsx(_, path) => {
-                                    let csv_path = path.with_extension("csv");
-                                    self.status_message = Some(format!(
-                                        "Changes saved to {} (converted from Excel)", 
-                                        csv_path.display()
-                                    ));
-                                }
-                                crate::data_source::DataSource::Parquet(_, path) => {
-                                    let csv_path = path.with_extension("csv");
-                                    self.status_message = Some(format!(
-                                        "Changes saved to {} (converted from Parquet)", 
-                                        csv_path.display()
-                                    ));
-                                }
-                                crate::data_source::DataSource::Sqlite(_) => {
-                                    self.status_message = Some("SQLite direct save not implemented yet".to_string());
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        // Fallback to export behavior for SQLite and unsupported operations
-                        if matches!(data_source, crate::data_source::DataSource::Sqlite(_)) {
-                            let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-                            let filename = format!("{}_exported_{}.csv", table_name, timestamp);
-                            self.write_csv_data(&data, &filename)?;
-                            self.data_modified = false;
-                            self.status_message = Some(format!(
-                                "Changes exported to {} (SQLite direct save not supported)", 
-                                filename
-                            ));
-                        } else {
-                            return Err(e);
-                        }
-                    }
+    fn handle_schema_objects(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Table;
+            }
+            KeyCode::Up => {
+                self.schema_selected = self.schema_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.schema_selected =
+                    (self.schema_selected + 1).min(self.schema_objects.len().saturating_sub(1));
+            }
+            KeyCode::Enter => {
+                // Select the table the object belongs to
+                let table = self.schema_objects.get(self.schema_selected).map(|o| o.table.clone());
+                if let Some(table_idx) = table.and_then(|t| self.tables.iter().position(|n| *n == t)) {
+                    self.switch_table(table_idx, data_source)?;
                 }
+                self.navigation_mode = NavigationMode::Table;
             }
+            _ => {}
         }
-        Ok(())
-    }
-
-    fn write_csv_data(&self, data: &crate::database::QueryResult, filename: &str) -> Result<()> {
-        let mut writer = csv::Writer::from_path(filename)?;
-
-        // Write header
-        writer.write_record(&data.columns)?;
-
-        // Write data rows
-        for row in &data.rows {
-            writer.write_record(row)?;
-        }
-
-        writer.flush()?;
-        Ok(())
+        Ok(true)
     }
 
-    fn handle_detailed_view(
+    fn handle_data_navigation(
         &mut self,
         key_event: KeyEvent,
-        _data_source: &DataSource,
+        data_source: &mut DataSource,
     ) -> Result<bool> {
-        match key_event.code {
-            KeyCode::Esc => {
-                self.navigation_mode = NavigationMode::Data;
-                self.detailed_view_row = None;
-                self.detailed_view_selected_field = 0;
+        let key_event = match self.keymap.action(key_event) {
+            None => key_event,
+            Some(Action::Key(code)) => KeyEvent::new(code, KeyModifiers::NONE),
+            Some(Action::Pending) => return Ok(true),
+            Some(Action::HalfPage(down)) => {
+                let row = self.data_offset + self.selected_row_idx;
+                let half = (self.page_size / 2).max(1);
+                self.jump_to_row(if down { row + half } else { row.saturating_sub(half) }, data_source)?;
+                return Ok(true);
             }
+            Some(Action::EdgeColumn(last)) => {
+                let columns = self.display_columns();
+                if let Some(&col) = if last { columns.last() } else { columns.first() } {
+                    self.selected_col_idx = col;
+                }
+                return Ok(true);
+            }
+        };
+        // Moving around keeps the page as read; anything else may need its hidden columns
+        let moving = matches!(
+            key_event.code,
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End
+        );
+        if !moving {
+            self.decode_hidden_columns(data_source)?;
+        }
+        match key_event.code {
             KeyCode::Up => {
-                if let Some(data) = &self.current_data {
-                    if self.detailed_view_selected_field > 0 {
-                        self.detailed_view_selected_field -= 1;
+                if self.selected_row_idx > 0 {
+                    self.selected_row_idx -= 1;
+                } else if self.data_offset > 0 {
+                    self.data_offset = self.data_offset.saturating_sub(self.page_size);
+                    self.selected_row_idx = self.page_size - 1;
+                    self.load_current_data(data_source)?;
+                    if let Some(data) = &self.current_data {
+                        if self.selected_row_idx >= data.rows.len() {
+                            self.selected_row_idx = data.rows.len().saturating_sub(1);
+                        }
                     }
                 }
             }
             KeyCode::Down => {
                 if let Some(data) = &self.current_data {
-                    if self.detailed_view_selected_field < data.columns.len().saturating_sub(1) {
-                        self.detailed_view_selected_field += 1;
+                    if self.selected_row_idx < data.rows.len().saturating_sub(1) {
+                        self.selected_row_idx += 1;
+                    } else if self.data_offset + data.rows.len() < data.total_rows {
+                        self.data_offset += self.page_size;
+                        self.selected_row_idx = 0;
+                        self.load_current_data(data_source)?;
                     }
                 }
             }
-            KeyCode::Char('c') if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                // Copy selected field value to clipboard
-                if let Some(row_idx) = self.detailed_view_row {
-                    if let Some(data) = &self.current_data {
-                        if row_idx < data.rows.len()
-                            && self.detailed_view_selected_field < data.columns.len()
-                        {
-                            let value =
-                                data.rows[row_idx][self.detailed_view_selected_field].clone();
-                            match self.copy_to_clipboard(&value) {
-                                Ok(_) => {
-                                    self.status_message = Some("Copied to clipboard".to_string());
-                                }
-                                Err(e) => {
-                                    self.show_error(format!("Failed to copy to clipboard: {}", e));
-                                }
-                            }
-                        }
+            KeyCode::Left => {
+                if self.current_data.is_some() {
+                    if let Some(col) = self.adjacent_visible_col(self.selected_col_idx, false) {
+                        self.selected_col_idx = col;
+                    } else {
+                        // Go back to table view when at first column
+                        self.navigation_mode = NavigationMode::Table;
+                        self.persist_view_state(data_source);
+                        self.reset_data_view();
+                        self.load_current_data(data_source)?;
                     }
+                } else {
+                    self.navigation_mode = NavigationMode::Table;
+                    self.reset_data_view();
+                    self.load_current_data(data_source)?;
                 }
             }
-            KeyCode::Char('q') | KeyCode::Char('c')
-                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
-            {
-                return Ok(false);
+            KeyCode::Right => {
+                if let Some(col) = self.adjacent_visible_col(self.selected_col_idx, true) {
+                    self.selected_col_idx = col;
+                }
             }
-            _ => {}
-        }
-        Ok(true)
-    }
-
-    fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
-        if self.clipboard.is_none() {
-            self.clipboard = Some(Clipboard::new()?);
-        }
-
-        if let Some(clipboard) = &mut self.clipboard {
-            clipboard.set_text(text)?;
-            // Small delay to ensure clipboard managers have time to see the content
-            std::thread::sleep(std::time::Duration::from_millis(150));
-        }
-        Ok(())
-    }
+            KeyCode::PageUp if self.data_offset > 0 => {
+                self.data_offset = self.data_offset.saturating_sub(self.page_size);
+                self.selected_row_idx = 0;
+                self.load_current_data(data_source)?;
+            }
+            KeyCode::PageDown => {
+                if let Some(data) = &self.current_data {
+                    if self.data_offset + data.rows.len() < data.total_rows {
+                        self.data_offset += self.page_size;
+                        self.selected_row_idx = 0;
+                        self.load_current_data(data_source)?;
+                    }
+                }
+            }
+            KeyCode::Home => {
+                self.data_offset = 0;
+                self.selected_row_idx = 0;
+                self.load_current_data(data_source)?;
+            }
+            KeyCode::End if self.counting_rows => {
+                self.status_message = Some("The rows are still being counted; try again in a moment".to_string());
+            }
+            KeyCode::End => {
+                if let Some(data) = &self.current_data {
+                    self.data_offset = data.total_rows.saturating_sub(self.page_size);
+                    self.selected_row_idx = 0;
+                    self.load_current_data(data_source)?;
+                }
+            }
+            KeyCode::Char('g') if self.current_data.is_some() => {
+                self.goto_input.clear();
+                self.navigation_mode = NavigationMode::GotoRow;
+            }
+            KeyCode::Char(' ') => {
+                if let Some(data) = &self.current_data {
+                    if self.selected_row_idx < data.rows.len()
+                        && self.selected_col_idx < data.columns.len()
+                    {
+                        // Prevent editing rowid column (column 0)
+                        if !data.columns.is_empty()
+                            && data.columns[0] == "rowid"
+                            && self.selected_col_idx == 0
+                        {
+                            self.show_error("Cannot edit rowid column".to_string());
+                            return Ok(true);
+                        }
 
-    fn show_error(&mut self, error: String) {
-        self.error_message = Some(error);
-        self.previous_navigation_mode = self.navigation_mode.clone();
-        self.navigation_mode = NavigationMode::ErrorDisplay;
-    }
+                        self.navigation_mode = NavigationMode::Edit;
+                        self.editing_cell = Some((self.selected_row_idx, self.selected_col_idx));
+                        self.edit_input =
+                            data.rows[self.selected_row_idx][self.selected_col_idx].to_string();
+                        if self.edit_input.chars().count() > LONG_TEXT_EDIT || self.edit_input.contains('\n') {
+                            self.open_text_editor();
+                        }
+                    }
+                }
+            }
+            KeyCode::Char('n') => {
+                self.jump_to_cell_match(data_source, true)?;
+            }
+            KeyCode::Char('N') => {
+                self.jump_to_cell_match(data_source, false)?;
+            }
+            KeyCode::Char('a') => {
+                // Add new row
+                if let Some(data) = &mut self.current_data {
+                    // The rowid stays empty too; INSERT assigns it
+                    let new_row: Vec<Value> =
+                        data.columns.iter().map(|_| Value::Text(String::new())).collect();
 
-    fn handle_error_display(
-        &mut self,
-        key_event: KeyEvent,
-        _data_source: &DataSource,
-    ) -> Result<bool> {
-        match key_event.code {
-            KeyCode::Esc => {
-                self.navigation_mode = self.previous_navigation_mode.clone();
-                self.error_message = None;
+                    self.edit_log.record(PageEdit::RowInserted { row: data.rows.len(), values: new_row.clone() });
+                    data.rows.push(new_row);
+                    data.total_rows += 1;
+                    self.data_modified = true;
+                    self.selected_row_idx = data.rows.len() - 1;
+                    self.selected_col_idx = if data.columns.is_empty() || data.columns[0] != "rowid"
+                    {
+                        0
+                    } else {
+                        1
+                    };
+                    
+                    // Immediately enter edit mode for the first editable cell
+                    self.navigation_mode = NavigationMode::Edit;
+                    self.editing_cell = Some((self.selected_row_idx, self.selected_col_idx));
+                    self.edit_input = String::new(); // Start with empty input for new cell
+                    self.status_message = Some("New row added - editing".to_string());
+                }
+            }
+            KeyCode::Char('A') => {
+                self.duplicate_selected_row(data_source);
+            }
+            KeyCode::Char('V') if self.current_data.as_ref().is_some_and(|data| !data.rows.is_empty()) => {
+                self.visual_anchor = Some(self.selected_row_idx);
+                self.navigation_mode = NavigationMode::Visual;
+            }
+            KeyCode::Char('+') => {
+                self.open_insert_form(data_source);
+            }
+            KeyCode::Char('o') => {
+                self.toggle_sort(data_source)?;
+            }
+            KeyCode::Char('i') => {
+                self.query_over_database = false;
+                self.navigation_mode = NavigationMode::Query;
+                self.query_input.clear();
+            }
+            KeyCode::Char('I') => {
+                self.open_database_query(data_source);
+            }
+            KeyCode::Char('R') => {
+                self.show_database_results();
+            }
+            KeyCode::Char('=') => {
+                self.navigation_mode = NavigationMode::ComputedColumn;
+                self.computed_column_edit = None;
+                self.computed_column_input.clear();
+            }
+            KeyCode::Char('M') => {
+                self.computed_manager_selected = 0;
+                self.navigation_mode = NavigationMode::ComputedColumns;
+            }
+            KeyCode::Char('D') => {
+                self.navigation_mode = NavigationMode::DiffInput;
+                self.diff_input.clear();
+            }
+            KeyCode::Char('J') => {
+                self.open_join_prompt();
+            }
+            KeyCode::Char('P') => {
+                if let Some(table) = self.current_table() {
+                    self.pending_query = Some(QueryJob::Profile {
+                        table: table.to_string(),
+                        query: self.current_query.clone().map(|query| (query, self.current_query_params.clone())),
+                        filters: self.filters.clone(),
+                        locale: self.format.number_locale,
+                    });
+                }
+            }
+            KeyCode::Char('F') => {
+                self.open_global_search();
+            }
+            KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_global_search();
+            }
+            KeyCode::Char('x') => {
+                self.open_tool_picker();
+            }
+            KeyCode::Char('B') => {
+                self.open_bookmark_list();
+            }
+            KeyCode::Char('Q') => {
+                self.open_saved_queries();
+            }
+            KeyCode::Char('t') => {
+                self.request_column_summary(SummaryKind::Stats);
+            }
+            KeyCode::Char('z') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.step_edit_history(false);
+            }
+            KeyCode::Char('d') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.start_fill_down();
+            }
+            KeyCode::Char('U') => {
+                if self.current_query.is_some() {
+                    self.show_error("Bulk edits change the table; press 'r' to go back to it first".to_string());
+                } else if self.current_data.is_some() {
+                    self.navigation_mode = NavigationMode::BulkEditInput;
+                }
+            }
+            KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.step_edit_history(true);
+            }
+            KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                match self.paste_from_clipboard() {
+                    Ok(text) => self.prepare_paste(&text),
+                    Err(e) => self.show_error(format!("Failed to paste from clipboard: {}", e)),
+                }
+            }
+            KeyCode::Char('v') => {
+                self.request_column_summary(SummaryKind::Histogram);
+            }
+            KeyCode::Char('u') => {
+                self.request_column_summary(SummaryKind::ValueCounts);
+            }
+            KeyCode::Char('H') => {
+                self.format.humanize = !self.format.humanize;
+                self.status_message = Some(if self.format.humanize {
+                    "Showing epoch timestamps and UUID blobs in readable form".to_string()
+                } else {
+                    "Showing raw values".to_string()
+                });
+            }
+            KeyCode::Char('b') => {
+                if self.current_query.is_some() {
+                    self.status_message =
+                        Some("Bookmarks apply to table data; press 'r' first".to_string());
+                } else if !self.filters.is_empty() && !matches!(data_source, DataSource::Sqlite(_)) {
+                    self.status_message =
+                        Some("Clear the filters (W) before bookmarking rows of a file".to_string());
+                } else if self.current_data.as_ref().is_some_and(|d| !d.rows.is_empty()) {
+                    self.bookmark_input.clear();
+                    self.navigation_mode = NavigationMode::BookmarkInput;
+                }
+            }
+            KeyCode::Char('/') => {
+                self.cell_search_input = self.cell_search.clone().unwrap_or_default();
+                self.navigation_mode = NavigationMode::CellSearch;
+            }
+            KeyCode::Char('w') => {
+                self.open_filter_prompt();
+            }
+            KeyCode::Char('f') => {
+                self.follow_foreign_key(data_source)?;
+            }
+            KeyCode::Char('L') => {
+                self.open_ddl(data_source);
+            }
+            KeyCode::Backspace if !self.followed_keys.is_empty() => {
+                self.follow_back(data_source)?;
+            }
+            KeyCode::Char('W') => {
+                self.clear_filters(data_source)?;
+            }
+            KeyCode::Char('p') => {
+                self.toggle_pinned_column(data_source);
+            }
+            KeyCode::Char('<') | KeyCode::Char('>') => {
+                self.resize_column(key_event.code == KeyCode::Char('>'), data_source);
+            }
+            KeyCode::Char('C') if self.current_data.as_ref().is_some_and(|data| !data.columns.is_empty()) => {
+                self.column_manager_selected = 0;
+                self.navigation_mode = NavigationMode::ColumnManager;
+            }
+            KeyCode::Char('|') => {
+                // The last command is kept so it can be rerun or tweaked
+                self.results_origin = NavigationMode::Data;
+                self.navigation_mode = NavigationMode::PipeInput;
+            }
+            KeyCode::Char('e') => {
+                self.open_export_results();
+            }
+            KeyCode::Char('E') => {
+                self.export_to_sqlite(data_source);
+            }
+            KeyCode::Char('y') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let page = self.current_data.as_ref().map_or(0, |data| data.rows.len());
+                self.copy_rows((0..page).collect(), true);
+            }
+            KeyCode::Char('y') => {
+                let cell = self.current_data.as_ref().and_then(|data| {
+                    data.rows.get(self.selected_row_idx)?.get(self.selected_col_idx).map(|cell| cell.to_string())
+                });
+                if let Some(cell) = cell {
+                    match self.copy_to_clipboard(&cell) {
+                        Ok(()) => self.status_message = Some("Copied the cell to the clipboard".to_string()),
+                        Err(e) => self.show_error(format!("Failed to copy to clipboard: {}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('Y') => {
+                self.copy_rows(self.selected_rows(), false);
+            }
+            KeyCode::Char('s') => {
+                // If we're in a custom query, warn user to go back to table view
+                if self.current_query.is_some() {
+                    self.show_error(
+                        "Cannot save custom query results. Press 'r' to reload table data first."
+                            .to_string(),
+                    );
+                } else if !self.filters.is_empty() && !matches!(data_source, DataSource::Sqlite(_)) {
+                    // Files are rewritten whole, which would drop the filtered-out rows
+                    self.show_error("Clear the filters (W) before saving the file".to_string());
+                } else if self.view_state.sort.is_some() && !matches!(data_source, DataSource::Sqlite(_)) {
+                    // The edited page is put back in file order, which a sort hides
+                    self.show_error("Clear the sort (o) before saving the file".to_string());
+                } else {
+                    self.save_changes(data_source)?;
+                }
+            }
+            KeyCode::Char('Z') if matches!(data_source, DataSource::Sqlite(_)) => {
+                self.confirm_revert(data_source);
+            }
+            KeyCode::Char('r') => {
+                // Clear custom query to reload original table data
+                self.current_query = None;
+                if self.external_change {
+                    self.refresh_from_disk(data_source)?;
+                } else {
+                    self.load_current_data(data_source)?;
+                }
+            }
+            KeyCode::Enter => {
+                // Show detailed view for selected row
+                if let Some(data) = &self.current_data {
+                    if self.selected_row_idx < data.rows.len() {
+                        self.detailed_view_row = Some(self.selected_row_idx);
+                        self.detailed_view_selected_field = 0;
+                        self.navigation_mode = NavigationMode::DetailedView;
+                    }
+                }
             }
             KeyCode::Char('q') | KeyCode::Char('c')
                 if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
             {
                 return Ok(false);
             }
+            KeyCode::Char('h') => {
+                self.show_help = !self.show_help;
+            }
             _ => {}
         }
         Ok(true)
     }
 
-    fn handle_computed_column_input(
-        &mut self,
-        key_event: KeyEvent,
-        data_source: &mut DataSource,
-    ) -> Result<bool> {
+    fn handle_edit_mode(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
         match key_event.code {
             KeyCode::Esc => {
                 self.navigation_mode = NavigationMode::Data;
-                self.computed_column_input.clear();
+                self.editing_cell = None;
+                self.edit_input.clear();
             }
             KeyCode::Enter => {
-                if !self.computed_column_input.trim().is_empty() {
-                    match self.parse_and_add_computed_column(&self.computed_column_input.clone()) {
-                        Ok(_) => {
-                            self.apply_computed_columns(data_source)?;
-                            // Save computed columns to persistence
-                            if let Some(table_name) = self.current_table() {
-                                if let Err(e) = self.save_computed_columns(table_name, data_source) {
-                                    self.status_message =
-                                        Some(format!("Column added but save failed: {}", e));
-                                } else {
-                                    self.status_message =
-                                        Some("Computed column added and saved".to_string());
-                                }
+                if let Some(message) = self.edit_type_error() {
+                    self.show_error(message);
+                    return Ok(true);
+                }
+                if let Some((row_idx, col_idx)) = self.editing_cell {
+                    if let Some(data) = &mut self.current_data {
+                        if row_idx < data.rows.len() && col_idx < data.columns.len() {
+                            // Don't allow saving changes to rowid column
+                            if !data.columns.is_empty()
+                                && data.columns[0] == "rowid"
+                                && col_idx == 0
+                            {
+                                self.show_error("Cannot edit rowid column".to_string());
                             } else {
-                                self.status_message = Some("Computed column added".to_string());
+                                let value = edited_cell(&data.rows[row_idx][col_idx], &self.edit_input);
+                                let change = CellChange::make(data, row_idx, col_idx, value);
+                                self.edit_log.record(PageEdit::Cells(vec![change]));
+                                self.data_modified = true;
+                                self.status_message = Some("Cell updated (not saved)".to_string());
                             }
                         }
-                        Err(e) => {
-                            self.show_error(format!("Expression error: {}", e));
-                        }
                     }
                 }
                 self.navigation_mode = NavigationMode::Data;
-                self.computed_column_input.clear();
+                self.editing_cell = None;
+                self.edit_input.clear();
+
+                // Refresh computed columns after edit
+                if let Err(e) = self.refresh_computed_columns() {
+                    self.show_error(format!("Failed to update computed columns: {}", e));
+                }
             }
-            KeyCode::Backspace => {
-                self.computed_column_input.pop();
+            KeyCode::Up => {
+                self.save_current_edit_and_move_to(MoveTo::Up, data_source)?;
             }
-            KeyCode::Char(c) => {
-                self.computed_column_input.push(c);
+            KeyCode::Down => {
+                self.save_current_edit_and_move_to(MoveTo::Down, data_source)?;
             }
-            _ => {}
-        }
-        Ok(true)
-    }
-
-    fn parse_and_add_computed_column(&mut self, expression: &str) -> Result<()> {
-        let expression = expression.trim();
-
-        // Check if expression has custom name (contains '=')
-        let (column_name, expr_part) = if let Some(eq_pos) = expression.find('=') {
-            let name = expression[..eq_pos].trim();
-            let expr = expression[eq_pos + 1..].trim();
-            if name.is_empty() || expr.is_empty() {
-                return Err(anyhow::anyhow!(
-                    "Invalid syntax. Use 'column_name=expression'"
-                ));
+            KeyCode::Left => {
+                self.save_current_edit_and_move_to(MoveTo::Left, data_source)?;
             }
-            // Validate column name (no special characters except underscore)
-            if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                return Err(anyhow::anyhow!(
-                    "Column name can only contain letters, numbers, and underscores"
-                ));
+            KeyCode::Right => {
+                self.save_current_edit_and_move_to(MoveTo::Right, data_source)?;
             }
-            (Some(name.to_string()), expr)
-        } else {
-            (None, expression)
-        };
-
-        // Parse different types of expressions
-        if let Some(captures) = regex::Regex::new(r"^(sum|mean|count|min|max)\(([^)]+)\)$")
-            .unwrap()
-            .captures(expr_part)
-        {
-            // Aggregate function
-            let func = captures.get(1).unwrap().as_str();
-            let column = captures.get(2).unwrap().as_str().trim();
+            KeyCode::Backspace => {
+                self.edit_input.pop();
+            }
+            KeyCode::Char('n') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Add new row
+                if let Some(data) = &mut self.current_data {
+                    // The rowid stays empty too; INSERT assigns it
+                    let new_row: Vec<Value> =
+                        data.columns.iter().map(|_| Value::Text(String::new())).collect();
 
-            // Verify column exists
-            if let Some(data) = &self.current_data {
-                if !data.columns.contains(&column.to_string()) {
-                    return Err(anyhow::anyhow!("Column '{}' does not exist", column));
+                    self.edit_log.record(PageEdit::RowInserted { row: data.rows.len(), values: new_row.clone() });
+                    data.rows.push(new_row);
+                    data.total_rows += 1;
+                    self.data_modified = true;
+                    self.selected_row_idx = data.rows.len() - 1;
+                    self.selected_col_idx = if data.columns.is_empty() || data.columns[0] != "rowid"
+                    {
+                        0
+                    } else {
+                        1
+                    };
+                    self.editing_cell = Some((self.selected_row_idx, self.selected_col_idx));
+                    self.edit_input.clear();
+                    self.status_message = Some("New row added".to_string());
                 }
             }
-
-            let computed_col = ComputedColumn {
-                name: column_name.unwrap_or_else(|| format!("{}({})", func, column)),
-                expression: expr_part.to_string(),
-                column_type: ComputedColumnType::Aggregate(func.to_string()),
-            };
-
-            self.computed_columns.push(computed_col);
-            Ok(())
-        } else if expr_part.contains('+')
-            || expr_part.contains('-')
-            || expr_part.contains('*')
-            || expr_part.contains('/')
-            || expr_part
-                .chars()
-                .all(|c| c.is_ascii_digit() || c == '.' || c == ' ')
-        {
-            // Row operation, mixed operation, or constant expression
-            let columns_used = self.extract_column_names(expr_part)?;
-            let aggregate_expressions = self.extract_aggregate_expressions(expr_part)?;
-
-            // Verify all columns exist if any are used
-            if let Some(data) = &self.current_data {
-                for col in &columns_used {
-                    if !data.columns.contains(col) {
-                        return Err(anyhow::anyhow!("Column '{}' does not exist", col));
-                    }
+            KeyCode::Char('d') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Keep the value typed, then fill it down
+                self.handle_edit_mode(KeyEvent::from(KeyCode::Enter), data_source)?;
+                if self.navigation_mode == NavigationMode::Data {
+                    self.start_fill_down();
                 }
-                // Verify columns in aggregate expressions exist
-                for agg_expr in &aggregate_expressions {
-                    let column_in_agg = self.extract_column_from_aggregate(agg_expr)?;
-                    if !data.columns.contains(&column_in_agg) {
-                        return Err(anyhow::anyhow!(
-                            "Column '{}' in aggregate '{}' does not exist",
-                            column_in_agg,
-                            agg_expr
-                        ));
-                    }
+            }
+            KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                match self.paste_from_clipboard() {
+                    Ok(text) => self.edit_input.push_str(text.trim_end_matches(['\r', '\n'])),
+                    Err(e) => self.show_error(format!("Failed to paste from clipboard: {}", e)),
                 }
             }
+            KeyCode::Char('e') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_text_editor();
+            }
+            KeyCode::Char(c) => {
+                self.edit_input.push(c);
+            }
+            KeyCode::Tab => {
+                // Save current edit and move to next cell
+                if let Some((row_idx, col_idx)) = self.editing_cell {
+                    let next_col = self.adjacent_visible_col(col_idx, true);
+                    let first_col = self.first_visible_col();
+                    if let Some(data) = &mut self.current_data {
+                        if row_idx < data.rows.len() && col_idx < data.columns.len() {
+                            // Don't allow saving changes to rowid column
+                            if !data.columns.is_empty()
+                                && data.columns[0] == "rowid"
+                                && col_idx == 0
+                            {
+                                // Skip saving changes to rowid column
+                            } else {
+                                let value = edited_cell(&data.rows[row_idx][col_idx], &self.edit_input);
+                                let change = CellChange::make(data, row_idx, col_idx, value);
+                                self.edit_log.record(PageEdit::Cells(vec![change]));
+                                self.data_modified = true;
+                            }
 
-            let column_type = if aggregate_expressions.is_empty() {
-                ComputedColumnType::RowOperation(columns_used)
-            } else {
-                ComputedColumnType::MixedOperation(columns_used, aggregate_expressions)
-            };
-
-            let computed_col = ComputedColumn {
-                name: column_name.unwrap_or_else(|| expr_part.to_string()),
-                expression: expr_part.to_string(),
-                column_type,
-            };
-
-            self.computed_columns.push(computed_col);
-            Ok(())
-        } else {
-            // Check if it's a simple numeric constant or column name
-            if expr_part.trim().parse::<f64>().is_ok() {
-                // It's a numeric constant
-                let computed_col = ComputedColumn {
-                    name: column_name.unwrap_or_else(|| expr_part.to_string()),
-                    expression: expr_part.to_string(),
-                    column_type: ComputedColumnType::RowOperation(vec![]),
-                };
-
-                self.computed_columns.push(computed_col);
-                Ok(())
-            } else if let Some(data) = &self.current_data {
-                // Check if it's a column name
-                if data.columns.contains(&expr_part.to_string()) {
-                    let computed_col = ComputedColumn {
-                        name: column_name.unwrap_or_else(|| expr_part.to_string()),
-                        expression: expr_part.to_string(),
-                        column_type: ComputedColumnType::RowOperation(vec![expr_part.to_string()]),
-                    };
-
-                    self.computed_columns.push(computed_col);
-                    Ok(())
-                } else {
-                    Err(anyhow::anyhow!("Invalid expression format. Use sum(Column), mean(Column), Column1 + Column2, or numeric constants"))
+                            // Move to next cell, skipping hidden columns
+                            if let Some(next_col) = next_col {
+                                self.selected_col_idx = next_col;
+                                self.editing_cell = Some((row_idx, next_col));
+                                self.edit_input = data.rows[row_idx][next_col].to_string();
+                            } else if row_idx < data.rows.len() - 1 {
+                                self.selected_row_idx += 1;
+                                let min_col = first_col.unwrap_or(col_idx);
+                                self.selected_col_idx = min_col;
+                                self.editing_cell = Some((row_idx + 1, min_col));
+                                self.edit_input = data.rows[row_idx + 1][min_col].to_string();
+                            } else {
+                                // At the end, exit edit mode
+                                self.navigation_mode = NavigationMode::Data;
+                                self.editing_cell = None;
+                                self.edit_input.clear();
+                            }
+                        }
+                    }
                 }
-            } else {
-                Err(anyhow::anyhow!("Invalid expression format. Use sum(Column), mean(Column), Column1 + Column2, or numeric constants"))
             }
+            _ => {}
         }
+        Ok(true)
     }
 
-    fn extract_column_names(&self, expression: &str) -> Result<Vec<String>> {
-        let mut columns = Vec::new();
-        let mut current_token = String::new();
-        let mut in_column = false;
+    /// Edit the value in the multi-line editor instead of the cell
+    fn open_text_editor(&mut self) {
+        self.text_editor = Some(TextEditor::new(self.edit_input.clone()));
+        self.navigation_mode = NavigationMode::TextEdit;
+    }
 
-        for ch in expression.chars() {
-            match ch {
-                '+' | '-' | '*' | '/' | '(' | ')' | ' ' | ',' => {
-                    if in_column && !current_token.trim().is_empty() {
-                        let token = current_token.trim().to_string();
-                        // Only add if it's not a number and not a function name
-                        if !token.parse::<f64>().is_ok()
-                            && !["sum", "mean", "count", "min", "max"].contains(&token.as_str())
-                        {
-                            columns.push(token);
-                        }
-                        current_token.clear();
-                        in_column = false;
-                    }
+    fn handle_text_edit(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        let Some(editor) = &mut self.text_editor else {
+            self.navigation_mode = NavigationMode::Data;
+            return Ok(true);
+        };
+        // Lines wrap as they are shown, at the width of the editor on screen
+        let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        let width = text_editor_area(Rect::new(0, 0, columns, rows), self.text_editor_size).width.saturating_sub(2) as usize;
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Esc => {
+                self.text_editor = None;
+                self.editing_cell = None;
+                self.edit_input.clear();
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Enter if key_event.modifiers.contains(KeyModifiers::ALT) => editor.insert('\n'),
+            KeyCode::Enter => {
+                // Saved like a cell edited in place; a value that doesn't fit the column keeps
+                // the editor open
+                self.edit_input = editor.text().to_string();
+                self.handle_edit_mode(KeyEvent::from(KeyCode::Enter), data_source)?;
+                if self.editing_cell.is_none() {
+                    self.text_editor = None;
                 }
-                _ => {
-                    if !in_column && !ch.is_whitespace() {
-                        in_column = true;
-                    }
-                    if in_column {
-                        current_token.push(ch);
+            }
+            KeyCode::Up if control => self.resize_text_editor(0, 10),
+            KeyCode::Down if control => self.resize_text_editor(0, -10),
+            KeyCode::Left if control => self.resize_text_editor(-10, 0),
+            KeyCode::Right if control => self.resize_text_editor(10, 0),
+            KeyCode::Up => editor.vertical(false, width),
+            KeyCode::Down => editor.vertical(true, width),
+            KeyCode::Left => editor.left(),
+            KeyCode::Right => editor.right(),
+            KeyCode::Home if control => editor.text_edge(false),
+            KeyCode::End if control => editor.text_edge(true),
+            KeyCode::Home => editor.line_edge(false, width),
+            KeyCode::End => editor.line_edge(true, width),
+            KeyCode::Backspace => editor.backspace(),
+            KeyCode::Delete => editor.delete(),
+            KeyCode::Char('v') if control => match self.paste_from_clipboard() {
+                Ok(text) => {
+                    if let Some(editor) = &mut self.text_editor {
+                        editor.insert_str(&text.replace("\r\n", "\n"));
                     }
                 }
-            }
-        }
-
-        if in_column && !current_token.trim().is_empty() {
-            let token = current_token.trim().to_string();
-            if !token.parse::<f64>().is_ok()
-                && !["sum", "mean", "count", "min", "max"].contains(&token.as_str())
-            {
-                columns.push(token);
-            }
+                Err(e) => self.show_error(format!("Failed to paste from clipboard: {}", e)),
+            },
+            KeyCode::Char(_) if control => {}
+            KeyCode::Char(c) => editor.insert(c),
+            _ => {}
         }
+        Ok(true)
+    }
 
-        // Remove duplicates
-        columns.sort();
-        columns.dedup();
-
-        Ok(columns)
+    /// Grow or shrink the multi-line editor, in steps of the screen size
+    fn resize_text_editor(&mut self, width: i16, height: i16) {
+        let (w, h) = self.text_editor_size;
+        self.text_editor_size = (
+            w.saturating_add_signed(width).clamp(30, 100),
+            h.saturating_add_signed(height).clamp(20, 100),
+        );
     }
 
-    fn extract_aggregate_expressions(&self, expression: &str) -> Result<Vec<String>> {
-        let mut aggregates = Vec::new();
-        let regex = regex::Regex::new(r"(sum|mean|count|min|max)\([^)]+\)").unwrap();
+    /// Why the value being edited doesn't fit the cell's type (or, for text cells, its
+    /// column's inferred type), if it doesn't
+    fn edit_type_error(&self) -> Option<String> {
+        let (row_idx, col_idx) = self.editing_cell?;
+        self.cell_type_error(row_idx, col_idx, &self.edit_input)
+    }
 
-        for capture in regex.captures_iter(expression) {
-            if let Some(full_match) = capture.get(0) {
-                aggregates.push(full_match.as_str().to_string());
-            }
+    /// Why `input` can't replace a cell of the page: it doesn't fit the cell's stored type or
+    /// the column's inferred one
+    fn cell_type_error(&self, row_idx: usize, col_idx: usize, input: &str) -> Option<String> {
+        let data = self.current_data.as_ref()?;
+        let column = data.columns.get(col_idx)?;
+        let cell = data.rows.get(row_idx)?.get(col_idx)?;
+        if !matches!(cell, Value::Text(_) | Value::Null) {
+            return parse_edited_cell(cell, input).is_none().then(|| {
+                format!("'{}' is not a valid {} value for column '{}'", input, cell.kind_label(), column)
+            });
         }
+        let column_type = self.column_types.get(column)?;
+        (!column_type.accepts(input, self.format.number_locale)).then(|| {
+            format!("'{}' is not a valid {} value for column '{}'", input, column_type.label(), column)
+        })
+    }
 
-        Ok(aggregates)
+    fn paste_from_clipboard(&mut self) -> Result<String> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(Clipboard::new()?);
+        }
+        match &mut self.clipboard {
+            Some(clipboard) => Ok(clipboard.get_text()?),
+            None => Ok(String::new()),
+        }
     }
 
-    fn extract_column_from_aggregate(&self, aggregate_expr: &str) -> Result<String> {
-        let regex = regex::Regex::new(r"^(sum|mean|count|min|max)\(([^)]+)\)$").unwrap();
+    /// Lay tab-separated text over the page from the selected cell on, across the shown
+    /// columns, and ask before changing the cells it differs from
+    fn prepare_paste(&mut self, text: &str) {
+        let columns = self.display_columns();
+        let first_col = columns.iter().position(|&idx| idx == self.selected_col_idx).unwrap_or(0);
+        let Some(data) = &self.current_data else {
+            return;
+        };
 
-        if let Some(captures) = regex.captures(aggregate_expr) {
-            if let Some(column_match) = captures.get(2) {
-                return Ok(column_match.as_str().trim().to_string());
+        let mut changes = Vec::new();
+        let (mut invalid, mut outside) = (0, 0);
+        for (row_offset, values) in parse_tsv(text).into_iter().enumerate() {
+            for (col_offset, value) in values.into_iter().enumerate() {
+                let row_idx = self.selected_row_idx + row_offset;
+                let Some(&col_idx) = columns.get(first_col + col_offset).filter(|_| row_idx < data.rows.len()) else {
+                    outside += 1;
+                    continue;
+                };
+                if data.rows[row_idx].get(col_idx).is_some_and(|cell| *cell == value) {
+                    continue;
+                }
+                if self.cell_type_error(row_idx, col_idx, &value).is_some() {
+                    invalid += 1;
+                } else {
+                    changes.push((row_idx, col_idx, value));
+                }
             }
         }
 
-        Err(anyhow::anyhow!(
-            "Invalid aggregate expression: {}",
-            aggregate_expr
-        ))
+        if changes.is_empty() {
+            self.status_message = Some(match invalid + outside {
+                0 => "The clipboard matches the cells already".to_string(),
+                n => format!("Nothing to paste: {} cells don't fit their column or fall outside the page", n),
+            });
+            return;
+        }
+        self.pending_paste = changes;
+        self.paste_skipped = (invalid, outside);
+        self.navigation_mode = NavigationMode::ConfirmPaste;
     }
 
-    fn apply_computed_columns(&mut self, _data_source: &DataSource) -> Result<()> {
-        if let Some(data) = &mut self.current_data {
-            for computed_col in &self.computed_columns {
-                // Check if column already exists, if so, remove it first
-                if let Some(pos) = data.columns.iter().position(|x| x == &computed_col.name) {
-                    data.columns.remove(pos);
-                    for row in &mut data.rows {
-                        if pos < row.len() {
-                            row.remove(pos);
-                        }
-                    }
+    /// Undo (Ctrl+Z) or redo (Ctrl+R) the last edit of the page, moving to where it was
+    fn step_edit_history(&mut self, redo: bool) {
+        let Some(data) = &mut self.current_data else {
+            return;
+        };
+        let edit = if redo { self.edit_log.redo(data) } else { self.edit_log.undo(data) };
+        let Some(edit) = edit.cloned() else {
+            self.status_message = Some(if redo { "Nothing to redo" } else { "Nothing to undo" }.to_string());
+            return;
+        };
+        match &edit {
+            PageEdit::Cells(changes) => {
+                if let Some(change) = changes.first() {
+                    self.selected_row_idx = change.row;
+                    self.selected_col_idx = change.col;
                 }
+            }
+            PageEdit::RowInserted { row, .. } | PageEdit::RowsDeleted { row, .. } => {
+                self.selected_row_idx = (*row).min(data.rows.len().saturating_sub(1));
+            }
+        }
+        self.data_modified = self.edit_log.has_edits() || self.columns_changed;
+        self.status_message = Some(format!("{} {}", if redo { "Redid" } else { "Undid" }, edit.describe()));
+        if let Err(e) = self.refresh_computed_columns() {
+            self.show_error(format!("Failed to update computed columns: {}", e));
+        }
+    }
 
-                // Add the new computed column
-                data.columns.push(computed_col.name.clone());
-
-                match &computed_col.column_type {
-                    ComputedColumnType::Aggregate(func) => {
-                        let value =
-                            Self::compute_aggregate_static(data, func, &computed_col.expression)?;
-                        for row in &mut data.rows {
-                            row.push(value.clone());
-                        }
-                    }
-                    ComputedColumnType::RowOperation(columns_used) => {
-                        let expression = computed_col.expression.clone();
-                        let cols = columns_used.clone();
-                        let mut computed_values = Vec::new();
-
-                        for row in &data.rows {
-                            let value =
-                                Self::compute_row_operation_static(data, row, &expression, &cols)?;
-                            computed_values.push(value);
-                        }
-
-                        for (row, value) in data.rows.iter_mut().zip(computed_values) {
-                            row.push(value);
-                        }
-                    }
-                    ComputedColumnType::MixedOperation(columns_used, aggregate_expressions) => {
-                        let expression = computed_col.expression.clone();
-                        let cols = columns_used.clone();
-                        let aggs = aggregate_expressions.clone();
-                        let mut computed_values = Vec::new();
-
-                        for row in &data.rows {
-                            let value = Self::compute_mixed_operation_static(
-                                data,
-                                row,
-                                &expression,
-                                &cols,
-                                &aggs,
-                            )?;
-                            computed_values.push(value);
-                        }
+    /// Ask how far to fill the selected cell down (`Ctrl+D`)
+    fn start_fill_down(&mut self) {
+        let Some(data) = &self.current_data else {
+            return;
+        };
+        if self.selected_col_idx == 0 && data.columns.first().is_some_and(|c| c == "rowid") {
+            self.show_error("Cannot edit rowid column".to_string());
+        } else if self.selected_row_idx + 1 >= data.rows.len() {
+            self.status_message = Some("No rows below to fill".to_string());
+        } else {
+            self.fill_input.clear();
+            self.fill_series = false;
+            self.navigation_mode = NavigationMode::FillDown;
+        }
+    }
 
-                        for (row, value) in data.rows.iter_mut().zip(computed_values) {
-                            row.push(value);
-                        }
-                    }
-                }
+    fn handle_fill_down(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Tab => {
+                self.fill_series = !self.fill_series;
+            }
+            KeyCode::Backspace => {
+                self.fill_input.pop();
             }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                self.fill_input.push(c);
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                self.fill_down();
+            }
+            _ => {}
         }
-        Ok(())
+        Ok(true)
     }
 
-    fn compute_aggregate_static(
-        data: &QueryResult,
-        func: &str,
-        expression: &str,
-    ) -> Result<String> {
-        // Extract column name from expression like "sum(Age)"
-        let column_name = expression
-            .trim_start_matches(func)
-            .trim_start_matches('(')
-            .trim_end_matches(')')
-            .trim();
-
-        let col_idx = data
-            .columns
-            .iter()
-            .position(|col| col == column_name)
-            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", column_name))?;
-
-        let mut values = Vec::new();
-        for row in &data.rows {
-            if col_idx < row.len() {
-                if let Ok(val) = row[col_idx].parse::<f64>() {
-                    values.push(val);
-                }
+    /// Fill the rows below the selected cell, up to the end of the page, with copies of it
+    /// or the series it starts; one undo step takes the whole fill back
+    fn fill_down(&mut self) {
+        let (row, col) = (self.selected_row_idx, self.selected_col_idx);
+        let Some(data) = &mut self.current_data else {
+            return;
+        };
+        let below = data.rows.len().saturating_sub(row + 1);
+        let count = self.fill_input.parse().map_or(below, |count: usize| count.min(below));
+        let Some(start) = data.rows.get(row).and_then(|r| r.get(col)).cloned() else {
+            return;
+        };
+        let previous = row.checked_sub(1).and_then(|above| data.rows[above].get(col));
+        let values = match fill_values(&start, previous, count, self.fill_series) {
+            Ok(values) => values,
+            Err(e) => {
+                self.show_error(e.to_string());
+                return;
             }
+        };
+        let cells = values
+            .into_iter()
+            .enumerate()
+            .map(|(offset, value)| CellChange::make(data, row + 1 + offset, col, value))
+            .collect();
+        self.edit_log.record(PageEdit::Cells(cells));
+        self.data_modified = true;
+        self.status_message = Some(format!(
+            "Filled {} cells with {} (not saved)",
+            count,
+            if self.fill_series { "a series" } else { "copies" }
+        ));
+        if let Err(e) = self.refresh_computed_columns() {
+            self.show_error(format!("Failed to update computed columns: {}", e));
         }
+    }
 
-        if values.is_empty() {
-            return Ok("0".to_string());
+    fn handle_bulk_edit_input(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                self.preview_bulk_edit(data_source);
+            }
+            KeyCode::Backspace => {
+                self.bulk_edit_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.bulk_edit_input.push(c);
+            }
+            _ => {}
         }
-
-        let result = match func {
-            "sum" => values.iter().sum::<f64>(),
-            "mean" => values.iter().sum::<f64>() / values.len() as f64,
-            "count" => values.len() as f64,
-            "min" => values.iter().fold(f64::INFINITY, |a, &b| a.min(b)),
-            "max" => values.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
-            _ => return Err(anyhow::anyhow!("Unknown function: {}", func)),
-        };
-
-        Ok(if result.fract() == 0.0 {
-            format!("{:.0}", result)
-        } else {
-            format!("{:.2}", result)
-        })
+        Ok(true)
     }
 
-    fn compute_row_operation_static(
-        data: &QueryResult,
-        row: &[String],
-        expression: &str,
-        columns_used: &[String],
-    ) -> Result<String> {
-        let mut expr = expression.to_string();
-
-        // Replace column names with their values
-        for col_name in columns_used {
-            if let Some(col_idx) = data.columns.iter().position(|col| col == col_name) {
-                if col_idx < row.len() {
-                    let value = row[col_idx].parse::<f64>().unwrap_or(0.0);
-                    expr = expr.replace(col_name, &value.to_string());
+    /// Work out the cells of the page the bulk edit changes and highlight them in the grid
+    fn preview_bulk_edit(&mut self, data_source: &DataSource) {
+        let (Some(table_name), Some(page)) = (self.current_table().map(|s| s.to_string()), &self.current_data) else {
+            return;
+        };
+        let update = match BulkUpdate::parse(&self.bulk_edit_input, &table_name) {
+            Ok(update) => update,
+            Err(e) => {
+                self.show_error(format!("Invalid update: {}", e));
+                return;
+            }
+        };
+        let mut columns = Vec::new();
+        for (column, _) in &update.assignments {
+            match page.columns.iter().position(|c| c == column) {
+                Some(idx) if column != "rowid" && !self.computed_columns.iter().any(|c| &c.name == column) => {
+                    columns.push(idx)
+                }
+                _ => {
+                    self.show_error(format!("'{}' is not a column that can be edited", column));
+                    return;
                 }
             }
         }
+        let rows = match data_source.preview_update(&table_name, &update, page, self.format.number_locale) {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.show_error(format!("Update failed: {:#}", e));
+                return;
+            }
+        };
 
-        // Simple expression evaluator for basic math operations
-        Self::evaluate_expression_static(&expr)
+        // Values are stored like typed ones, so a number stays a number
+        let cells: Vec<(usize, usize, Value)> = rows
+            .into_iter()
+            .flat_map(|(row, values)| columns.iter().zip(values).map(move |(&col, value)| (row, col, value)))
+            .filter_map(|(row, col, value)| {
+                let cell = &page.rows[row][col];
+                let value = edited_cell(cell, &value.to_string());
+                (value != *cell).then_some((row, col, value))
+            })
+            .collect();
+        if cells.is_empty() {
+            self.status_message = Some("The update changes no cells on this page".to_string());
+            return;
+        }
+        self.status_message = Some(format!("The update changes {} cells on this page", cells.len()));
+        self.bulk_preview = Some(BulkPreview { update, cells });
+        self.navigation_mode = NavigationMode::BulkPreview;
     }
 
-    fn compute_mixed_operation_static(
-        data: &QueryResult,
-        row: &[String],
-        expression: &str,
-        columns_used: &[String],
-        aggregate_expressions: &[String],
-    ) -> Result<String> {
-        let mut expr = expression.to_string();
-
-        // First, replace aggregate expressions with their computed values
-        for agg_expr in aggregate_expressions {
-            // Parse the aggregate function and column
-            let regex = regex::Regex::new(r"^(sum|mean|count|min|max)\(([^)]+)\)$").unwrap();
-            if let Some(captures) = regex.captures(agg_expr) {
-                let func = captures.get(1).unwrap().as_str();
-                let agg_value = Self::compute_aggregate_static(data, func, agg_expr)?;
-                expr = expr.replace(agg_expr, &agg_value);
+    fn handle_bulk_preview(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                let (Some(preview), Some(data)) = (self.bulk_preview.take(), &mut self.current_data) else {
+                    return Ok(true);
+                };
+                let count = preview.cells.len();
+                let cells = preview
+                    .cells
+                    .into_iter()
+                    .map(|(row, col, value)| CellChange::make(data, row, col, value))
+                    .collect();
+                self.edit_log.record(PageEdit::Cells(cells));
+                self.data_modified = true;
+                self.status_message = Some(format!("Updated {} cells (not saved)", count));
+                if let Err(e) = self.refresh_computed_columns() {
+                    self.show_error(format!("Failed to update computed columns: {}", e));
+                }
             }
-        }
-
-        // Then, replace column names with their values from the current row
-        for col_name in columns_used {
-            if let Some(col_idx) = data.columns.iter().position(|col| col == col_name) {
-                if col_idx < row.len() {
-                    let value = row[col_idx].parse::<f64>().unwrap_or(0.0);
-                    expr = expr.replace(col_name, &value.to_string());
+            // The whole table, through the query prompt's confirmation of statements
+            KeyCode::Char('a') => {
+                let Some(preview) = &self.bulk_preview else {
+                    return Ok(true);
+                };
+                if self.edit_log.has_edits() {
+                    self.status_message = Some("Save or undo the edits of the page first".to_string());
+                    return Ok(true);
                 }
+                self.pending_query = Some(QueryJob::Query {
+                    query: preview.update.to_sql("x"),
+                    params: Vec::new(),
+                    table: self.current_table().map(|t| t.to_string()),
+                    page_size: self.page_size,
+                });
+                self.bulk_preview = None;
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.bulk_preview = None;
+                self.navigation_mode = NavigationMode::Data;
             }
+            _ => {}
         }
-
-        // Finally, evaluate the expression
-        Self::evaluate_expression_static(&expr)
+        Ok(true)
     }
 
-    fn evaluate_expression_static(expr: &str) -> Result<String> {
-        // Simple evaluator for basic arithmetic with proper operator precedence
-        let expr = expr.replace(" ", "");
-
-        // Handle parentheses first
-        if let Some(start) = expr.rfind('(') {
-            if let Some(end) = expr[start..].find(')') {
-                let inner = &expr[start + 1..start + end];
-                let inner_result = Self::evaluate_expression_static(inner)?;
-                let new_expr = format!(
-                    "{}{}{}",
-                    &expr[..start],
-                    inner_result,
-                    &expr[start + end + 1..]
-                );
-                return Self::evaluate_expression_static(&new_expr);
-            }
+    /// Add a copy of the selected row below it, with its rowid and primary key left for the
+    /// database to assign
+    fn duplicate_selected_row(&mut self, data_source: &DataSource) {
+        let Some(table_name) = self.current_table().map(|s| s.to_string()) else {
+            return;
+        };
+        let schema = data_source.table_schema(&table_name, self.format.number_locale).ok().flatten();
+        let Some(data) = &mut self.current_data else {
+            return;
+        };
+        let Some(row) = data.rows.get(self.selected_row_idx) else {
+            return;
+        };
+        let copy = duplicate_row(row, &data.columns, schema.as_ref());
+        let at = self.selected_row_idx + 1;
+        self.edit_log.record(PageEdit::RowInserted { row: at, values: copy.clone() });
+        data.rows.insert(at, copy);
+        data.total_rows += 1;
+        self.selected_row_idx = at;
+        self.data_modified = true;
+        self.status_message = Some("Duplicated the row (not saved)".to_string());
+        if let Err(e) = self.refresh_computed_columns() {
+            self.show_error(format!("Failed to update computed columns: {}", e));
         }
+    }
 
-        // Handle multiplication/division (higher precedence)
-        if let Some(pos) = expr.rfind('*') {
-            let left = Self::evaluate_expression_static(&expr[..pos])?;
-            let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
-            let result = left.parse::<f64>()? * right.parse::<f64>()?;
-            return Ok(if result.fract() == 0.0 {
-                format!("{:.0}", result)
-            } else {
-                format!("{:.2}", result)
-            });
+    fn open_insert_form(&mut self, data_source: &DataSource) {
+        let (Some(table_name), Some(data)) = (self.current_table().map(|s| s.to_string()), &self.current_data) else {
+            return;
+        };
+        let schema = data_source.table_schema(&table_name, self.format.number_locale).ok().flatten();
+        let form = InsertForm::new(
+            &data.columns,
+            |idx| !self.computed_columns.iter().any(|c| c.name == data.columns[idx]),
+            schema.as_ref(),
+        );
+        if form.fields.is_empty() {
+            return;
         }
+        self.insert_form = Some(form);
+        self.navigation_mode = NavigationMode::InsertForm;
+    }
 
-        if let Some(pos) = expr.rfind('/') {
-            let left = Self::evaluate_expression_static(&expr[..pos])?;
-            let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
-            let right_val = right.parse::<f64>()?;
-            if right_val == 0.0 {
-                return Err(anyhow::anyhow!("Division by zero"));
+    fn handle_insert_form(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let Some(form) = &mut self.insert_form else {
+            self.navigation_mode = NavigationMode::Data;
+            return Ok(true);
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.insert_form = None;
+                self.navigation_mode = NavigationMode::Data;
             }
-            let result = left.parse::<f64>()? / right_val;
-            return Ok(if result.fract() == 0.0 {
-                format!("{:.0}", result)
-            } else {
-                format!("{:.2}", result)
-            });
-        }
-
-        // Handle addition/subtraction (lower precedence)
-        if let Some(pos) = expr.rfind('+') {
-            let left = Self::evaluate_expression_static(&expr[..pos])?;
-            let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
-            let result = left.parse::<f64>()? + right.parse::<f64>()?;
-            return Ok(if result.fract() == 0.0 {
-                format!("{:.0}", result)
-            } else {
-                format!("{:.2}", result)
-            });
-        }
-
-        if let Some(pos) = expr.rfind('-') {
-            // Make sure this isn't a negative number at the start
-            if pos > 0 {
-                let left = Self::evaluate_expression_static(&expr[..pos])?;
-                let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
-                let result = left.parse::<f64>()? - right.parse::<f64>()?;
-                return Ok(if result.fract() == 0.0 {
-                    format!("{:.0}", result)
-                } else {
-                    format!("{:.2}", result)
-                });
+            KeyCode::Up | KeyCode::BackTab => {
+                form.step(false);
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                form.step(true);
+            }
+            // Enter walks through the columns and adds the row after the last one
+            KeyCode::Enter if !form.step(true) => {
+                self.add_form_row();
+            }
+            KeyCode::Backspace => {
+                if let Some(field) = form.current() {
+                    field.input.pop();
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(field) = form.current() {
+                    field.input.push(c);
+                }
             }
+            _ => {}
         }
+        Ok(true)
+    }
 
-        // Base case - just a number
-        if let Ok(num) = expr.parse::<f64>() {
-            Ok(if num.fract() == 0.0 {
-                format!("{:.0}", num)
-            } else {
-                format!("{:.2}", num)
-            })
-        } else {
-            Ok(expr.to_string())
+    fn add_form_row(&mut self) {
+        self.navigation_mode = NavigationMode::Data;
+        let (Some(form), Some(data)) = (self.insert_form.take(), &mut self.current_data) else {
+            return;
+        };
+        let row = form.row(data.columns.len());
+        self.edit_log.record(PageEdit::RowInserted { row: data.rows.len(), values: row.clone() });
+        data.rows.push(row);
+        data.total_rows += 1;
+        self.selected_row_idx = data.rows.len() - 1;
+        self.data_modified = true;
+        self.status_message = Some("New row added (not saved)".to_string());
+        if let Err(e) = self.refresh_computed_columns() {
+            self.show_error(format!("Failed to update computed columns: {}", e));
         }
     }
 
-    fn refresh_computed_columns(&mut self) -> Result<()> {
-        if let Some(data) = &mut self.current_data {
-            // Remove all computed columns first
-            let mut cols_to_remove = Vec::new();
-            for computed_col in &self.computed_columns {
-                if let Some(pos) = data.columns.iter().position(|x| x == &computed_col.name) {
-                    cols_to_remove.push(pos);
+    fn handle_confirm_paste(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                let changes = std::mem::take(&mut self.pending_paste);
+                if let Some(data) = &mut self.current_data {
+                    let cells = changes
+                        .iter()
+                        .map(|(row_idx, col_idx, input)| {
+                            let value = edited_cell(&data.rows[*row_idx][*col_idx], input);
+                            CellChange::make(data, *row_idx, *col_idx, value)
+                        })
+                        .collect();
+                    self.edit_log.record(PageEdit::Cells(cells));
+                    self.data_modified = true;
+                    self.status_message = Some(format!("Pasted {} cells (not saved)", changes.len()));
                 }
+                if let Err(e) = self.refresh_computed_columns() {
+                    self.show_error(format!("Failed to update computed columns: {}", e));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.pending_paste.clear();
+                self.navigation_mode = NavigationMode::Data;
             }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn save_current_edit_and_move_to(
+        &mut self,
+        direction: MoveTo,
+        data_source: &mut DataSource,
+    ) -> Result<()> {
+        if let Some(message) = self.edit_type_error() {
+            self.show_error(message);
+            return Ok(());
+        }
 
-            // Remove in reverse order to maintain indices
-            cols_to_remove.sort_by(|a, b| b.cmp(a));
-            for pos in cols_to_remove {
-                data.columns.remove(pos);
-                for row in &mut data.rows {
-                    if pos < row.len() {
-                        row.remove(pos);
+        // Save current edit
+        if let Some((row_idx, col_idx)) = self.editing_cell {
+            if let Some(data) = &mut self.current_data {
+                if row_idx < data.rows.len() && col_idx < data.columns.len() {
+                    // Don't allow saving changes to rowid column
+                    if !data.columns.is_empty() && data.columns[0] == "rowid" && col_idx == 0 {
+                        // Skip saving changes to rowid column
+                    } else {
+                        let value = edited_cell(&data.rows[row_idx][col_idx], &self.edit_input);
+                        let change = CellChange::make(data, row_idx, col_idx, value);
+                        self.edit_log.record(PageEdit::Cells(vec![change]));
+                        self.data_modified = true;
                     }
                 }
             }
+        }
 
-            // Re-apply all computed columns
-            for computed_col in &self.computed_columns {
-                data.columns.push(computed_col.name.clone());
+        // Move to new position
+        if let Some(data) = &self.current_data {
+            let (mut new_row, mut new_col) = (self.selected_row_idx, self.selected_col_idx);
 
-                match &computed_col.column_type {
-                    ComputedColumnType::Aggregate(func) => {
-                        let value =
-                            Self::compute_aggregate_static(data, func, &computed_col.expression)?;
-                        for row in &mut data.rows {
-                            row.push(value.clone());
+            match direction {
+                MoveTo::Up => {
+                    if new_row > 0 {
+                        new_row -= 1;
+                    } else if self.data_offset > 0 {
+                        self.data_offset = self.data_offset.saturating_sub(self.page_size);
+                        new_row = self.page_size - 1;
+                        self.load_current_data(data_source)?;
+                        if let Some(data) = &self.current_data {
+                            if new_row >= data.rows.len() {
+                                new_row = data.rows.len().saturating_sub(1);
+                            }
                         }
                     }
-                    ComputedColumnType::RowOperation(columns_used) => {
-                        let expression = computed_col.expression.clone();
-                        let cols = columns_used.clone();
-                        let mut computed_values = Vec::new();
-
-                        for row in &data.rows {
-                            let value =
-                                Self::compute_row_operation_static(data, row, &expression, &cols)?;
-                            computed_values.push(value);
-                        }
+                }
+                MoveTo::Down => {
+                    if new_row < data.rows.len().saturating_sub(1) {
+                        new_row += 1;
+                    } else if self.data_offset + data.rows.len() < data.total_rows {
+                        self.data_offset += self.page_size;
+                        new_row = 0;
+                        self.load_current_data(data_source)?;
+                    }
+                }
+                MoveTo::Left => {
+                    if let Some(col) = self.adjacent_visible_col(new_col, false) {
+                        new_col = col;
+                    }
+                }
+                MoveTo::Right => {
+                    if let Some(col) = self.adjacent_visible_col(new_col, true) {
+                        new_col = col;
+                    }
+                }
+            }
+
+            // Update position and edit input
+            self.selected_row_idx = new_row;
+            self.selected_col_idx = new_col;
+            self.editing_cell = Some((new_row, new_col));
+
+            // Load new cell content
+            if let Some(data) = &self.current_data {
+                if new_row < data.rows.len() && new_col < data.columns.len() {
+                    self.edit_input = data.rows[new_row][new_col].to_string();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reset_data_view(&mut self) {
+        self.current_query = None;
+        self.current_data = None;
+        self.original_data = None;
+        self.undecoded_columns.clear();
+        self.edit_log.clear();
+        self.selected_row_idx = 0;
+        self.selected_col_idx = 0;
+        self.data_offset = 0;
+        self.editing_cell = None;
+        self.edit_input.clear();
+        self.data_modified = false;
+        self.columns_changed = false;
+        self.filters.clear();
+    }
+
+    fn switch_table(&mut self, table_idx: usize, data_source: &mut DataSource) -> Result<()> {
+        self.persist_view_state(data_source);
+        self.selected_table_idx = table_idx;
+        self.sidebar_cursor = None;
+        self.reset_data_view();
+        self.restore_view_state(data_source);
+        self.load_current_data(data_source)
+    }
+
+    /// Prompt for a filter on the selected column, starting from its current filter
+    fn open_filter_prompt(&mut self) {
+        if self.current_query.is_some() {
+            self.status_message = Some("Filters apply to table data; press 'r' first".to_string());
+            return;
+        }
+        let Some(column) = self.selected_column_name() else {
+            return;
+        };
+        if self.computed_columns.iter().any(|col| col.name == column) {
+            self.status_message = Some("Computed columns cannot be filtered".to_string());
+            return;
+        }
+        self.filter_input = self
+            .filters
+            .iter()
+            .find(|f| f.column == column)
+            .map(|f| f.expression())
+            .unwrap_or_default();
+        self.navigation_mode = NavigationMode::FilterInput;
+    }
+
+    fn selected_column_name(&self) -> Option<String> {
+        self.current_data
+            .as_ref()
+            .and_then(|data| data.columns.get(self.selected_col_idx).cloned())
+    }
+
+    /// Jump to the row number or percentage of the table typed at the goto prompt (`g`)
+    fn handle_goto_row(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                let Some(total) = self.current_data.as_ref().map(|data| data.total_rows) else {
+                    return Ok(true);
+                };
+                if self.counting_rows {
+                    self.status_message = Some("The rows are still being counted; try again in a moment".to_string());
+                    return Ok(true);
+                }
+                match goto_row(&self.goto_input, total) {
+                    Ok(row) => {
+                        self.jump_to_row(row, data_source)?;
+                        self.status_message = Some(format!("Row {} of {}", row + 1, total));
+                    }
+                    Err(e) => self.show_error(e.to_string()),
+                }
+            }
+            KeyCode::Backspace => {
+                self.goto_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.goto_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_filter_input(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                let Some(column) = self.selected_column_name() else {
+                    return Ok(true);
+                };
+                // An empty expression removes the column's filter
+                let filter = if self.filter_input.trim().is_empty() {
+                    None
+                } else {
+                    match ColumnFilter::parse(&column, &self.filter_input) {
+                        Ok(filter) => Some(filter),
+                        Err(e) => {
+                            self.show_error(format!("Invalid filter: {}", e));
+                            return Ok(true);
+                        }
+                    }
+                };
+                self.filters.retain(|f| f.column != column);
+                self.status_message = Some(match &filter {
+                    Some(filter) => format!("Filtered {} {}", column, filter.expression()),
+                    None => format!("Filter on {} removed", column),
+                });
+                self.filters.extend(filter);
+                self.reload_filtered(data_source)?;
+            }
+            KeyCode::Backspace => {
+                self.filter_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.filter_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_cell_search(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                if self.cell_search_input.is_empty() {
+                    self.cell_search = None;
+                    self.status_message = Some("Search cleared".to_string());
+                } else if let Err(e) = SearchPattern::parse(&self.cell_search_input) {
+                    self.show_error(e.to_string());
+                } else {
+                    self.cell_search = Some(self.cell_search_input.clone());
+                    self.jump_to_cell_match(data_source, true)?;
+                }
+            }
+            KeyCode::Backspace => {
+                self.cell_search_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.cell_search_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Move to the next (or previous) cell containing the search term, wrapping around the
+    /// table once. Rows past the loaded page are fetched in chunks, so tables larger than
+    /// memory are searched a chunk at a time.
+    fn jump_to_cell_match(&mut self, data_source: &mut DataSource, forward: bool) -> Result<()> {
+        let Some(term) = self.cell_search.clone() else {
+            self.status_message = Some("Press / to search the table first".to_string());
+            return Ok(());
+        };
+        let pattern = SearchPattern::parse(&term)?;
+        let Some(table_name) = self.current_table().map(|s| s.to_string()) else {
+            return Ok(());
+        };
+        let (Some(data), Some(original)) = (&self.current_data, &self.original_data) else {
+            return Ok(());
+        };
+        // Computed columns are left out, the fetched chunks don't have them
+        let columns = original.columns.len();
+        let rows = data.total_rows.max(self.data_offset + data.rows.len());
+        let cells = rows * columns;
+        if cells == 0 {
+            return Ok(());
+        }
+        let start = (self.data_offset + self.selected_row_idx) * columns + self.selected_col_idx;
+
+        let mut chunk: Option<(usize, QueryResult)> = None;
+        let mut found = None;
+        for step in 1..=cells {
+            let cell = if forward {
+                (start + step) % cells
+            } else {
+                (start + cells - step % cells) % cells
+            };
+            let (row, col) = (cell / columns, cell % columns);
+            if !self.column_visible(col) {
+                continue;
+            }
+            // The loaded page may hold edits not saved yet, so it is searched as shown
+            let value = if row >= self.data_offset && row - self.data_offset < data.rows.len() {
+                data.rows[row - self.data_offset].get(col)
+            } else {
+                let chunk_offset = row / CELL_SEARCH_CHUNK * CELL_SEARCH_CHUNK;
+                if chunk.as_ref().is_none_or(|(offset, _)| *offset != chunk_offset) {
+                    chunk = Some((chunk_offset, self.fetch_rows(data_source, &table_name, chunk_offset, CELL_SEARCH_CHUNK)?));
+                }
+                chunk
+                    .as_ref()
+                    .and_then(|(offset, result)| result.rows.get(row - offset))
+                    .and_then(|values| values.get(col))
+            };
+            if value.is_some_and(|value| pattern.matches(&value.as_text())) {
+                let wrapped = if forward { cell <= start } else { cell >= start };
+                found = Some((row, col, wrapped));
+                break;
+            }
+        }
+
+        let Some((row, col, wrapped)) = found else {
+            self.status_message = Some(format!("No cells contain '{}'", term));
+            return Ok(());
+        };
+        let page_offset = row / self.page_size * self.page_size;
+        if page_offset != self.data_offset {
+            self.data_offset = page_offset;
+            self.load_current_data(data_source)?;
+        }
+        self.selected_row_idx = row - page_offset;
+        self.selected_col_idx = col;
+        let position = format!("Match at row {}", row + 1);
+        self.status_message = Some(if wrapped {
+            format!("{} (search wrapped)", position)
+        } else {
+            position
+        });
+        Ok(())
+    }
+
+    fn clear_filters(&mut self, data_source: &mut DataSource) -> Result<()> {
+        if self.filters.is_empty() {
+            self.status_message = Some("No filters to clear".to_string());
+            return Ok(());
+        }
+        self.filters.clear();
+        self.status_message = Some("Filters cleared".to_string());
+        self.reload_filtered(data_source)
+    }
+
+    fn reload_filtered(&mut self, data_source: &mut DataSource) -> Result<()> {
+        let column = self.selected_col_idx;
+        self.data_offset = 0;
+        self.selected_row_idx = 0;
+        if let Err(e) = self.load_current_data(data_source) {
+            // A filter SQLite rejects (e.g. on a renamed column) must not get stuck
+            self.filters.pop();
+            self.show_error(format!("Filter failed: {}", e));
+            self.load_current_data(data_source)?;
+        }
+        self.selected_col_idx = column;
+        self.ensure_valid_col_selection();
+        Ok(())
+    }
+
+    /// Open the row(s) the selected cell refers to through a foreign key: the parent table,
+    /// filtered on the key's values. The view left is kept for Backspace.
+    fn follow_foreign_key(&mut self, data_source: &mut DataSource) -> Result<()> {
+        if self.current_query.is_some() {
+            self.status_message = Some("Foreign keys apply to table data; press 'r' first".to_string());
+            return Ok(());
+        }
+        if self.data_modified {
+            self.status_message = Some("Save (s) or reload (r) the changes before following a key".to_string());
+            return Ok(());
+        }
+        let (Some(table), Some(column)) = (self.current_table().map(|t| t.to_string()), self.selected_column_name())
+        else {
+            return Ok(());
+        };
+        let Some((data, row)) = self
+            .current_data
+            .as_ref()
+            .and_then(|data| data.rows.get(self.selected_row_idx).map(|row| (data, row)))
+        else {
+            return Ok(());
+        };
+
+        let keys = data_source.foreign_keys(&table)?;
+        let Some((key, references)) = keys.into_iter().find(|(key, _)| key.columns.contains(&column)) else {
+            self.status_message = Some(format!("{} is not a foreign key", column));
+            return Ok(());
+        };
+        // Every column of a composite key is matched, with its value in this row
+        let mut filters = Vec::new();
+        for (from, to) in key.columns.iter().zip(&references) {
+            let value = data
+                .columns
+                .iter()
+                .position(|c| c == from)
+                .and_then(|idx| row.get(idx))
+                .map(|cell| cell.to_string())
+                .unwrap_or_default();
+            if value == "NULL" {
+                self.status_message = Some(format!("{} is NULL and refers to no row", from));
+                return Ok(());
+            }
+            filters.push(ColumnFilter { column: to.clone(), op: FilterOp::Eq, value });
+        }
+        let Some(table_idx) = self.tables.iter().position(|t| *t == key.table) else {
+            self.status_message = Some(format!("Table '{}' not found", key.table));
+            return Ok(());
+        };
+
+        self.followed_keys.push(FollowedKey {
+            table,
+            filters: self.filters.clone(),
+            offset: self.data_offset,
+            selected_row: self.selected_row_idx,
+            selected_col: self.selected_col_idx,
+        });
+        self.switch_table(table_idx, data_source)?;
+        self.filters = filters;
+        self.data_offset = 0;
+        self.selected_row_idx = 0;
+        self.load_current_data(data_source)?;
+        if let Some(col_idx) = self.current_data.as_ref().and_then(|d| d.columns.iter().position(|c| *c == references[0])) {
+            self.selected_col_idx = col_idx;
+        }
+        self.navigation_mode = NavigationMode::Data;
+        self.status_message = Some(format!(
+            "{} of {} refers to {} - Backspace to go back",
+            key.columns.join(", "),
+            self.followed_keys.last().map(|k| k.table.as_str()).unwrap_or_default(),
+            key.table
+        ));
+        Ok(())
+    }
+
+    /// Return to the view a foreign key was followed from
+    fn follow_back(&mut self, data_source: &mut DataSource) -> Result<()> {
+        let Some(origin) = self.followed_keys.pop() else {
+            return Ok(());
+        };
+        let Some(table_idx) = self.tables.iter().position(|t| *t == origin.table) else {
+            self.status_message = Some(format!("Table '{}' not found", origin.table));
+            return Ok(());
+        };
+        self.switch_table(table_idx, data_source)?;
+        self.filters = origin.filters;
+        self.data_offset = origin.offset;
+        self.selected_row_idx = origin.selected_row;
+        self.selected_col_idx = origin.selected_col;
+        self.load_current_data(data_source)?;
+        self.ensure_valid_col_selection();
+        self.navigation_mode = NavigationMode::Data;
+        Ok(())
+    }
+
+    /// Cycle the sort on the selected column: ascending, descending, unsorted
+    fn toggle_sort(&mut self, data_source: &mut DataSource) -> Result<()> {
+        if self.current_query.is_some() {
+            self.status_message = Some("Sorting applies to table data; press 'r' first".to_string());
+            return Ok(());
+        }
+        let Some(column) = self
+            .current_data
+            .as_ref()
+            .and_then(|data| data.columns.get(self.selected_col_idx).cloned())
+        else {
+            return Ok(());
+        };
+        if self.computed_columns.iter().any(|col| col.name == column) {
+            self.status_message = Some("Computed columns cannot be sorted".to_string());
+            return Ok(());
+        }
+
+        self.view_state.sort = match &self.view_state.sort {
+            Some(sort) if sort.column == column && !sort.descending => Some(SortOrder {
+                column: column.clone(),
+                descending: true,
+            }),
+            Some(sort) if sort.column == column => None,
+            _ => Some(SortOrder {
+                column: column.clone(),
+                descending: false,
+            }),
+        };
+
+        self.status_message = Some(match &self.view_state.sort {
+            Some(sort) if sort.descending => format!("Sorted by {} (descending)", column),
+            Some(_) => format!("Sorted by {} (ascending)", column),
+            None => "Sort cleared".to_string(),
+        });
+
+        self.data_offset = 0;
+        self.selected_row_idx = 0;
+        self.load_current_data(data_source)
+    }
+
+    /// Remember the current table's view (sort, filters, cursor, ...) for the next session,
+    /// and the table and its query as the ones the file was left on
+    pub fn persist_view_state(&mut self, data_source: &DataSource) {
+        let Some(table_name) = self.current_table().map(|s| s.to_string()) else {
+            return;
+        };
+        let mut view_state = self.view_state.clone();
+        view_state.filters = self
+            .filters
+            .iter()
+            .map(|filter| crate::persistence::ColumnFilter { column: filter.column.clone(), expression: filter.expression() })
+            .collect();
+        view_state.data_offset = self.data_offset;
+        view_state.selected_row_idx = self.selected_row_idx;
+        view_state.selected_col_idx = self.selected_col_idx;
+        let last_view = LastView {
+            table: table_name.clone(),
+            query: self.current_query.clone(),
+            query_params: self.current_query_params.clone(),
+        };
+
+        let effective_path = self.get_effective_persistence_path(data_source);
+        if let Err(e) = self
+            .persistence
+            .save_view_state(&effective_path, &table_name, &view_state)
+            .and_then(|()| self.persistence.save_last_view(&effective_path, &last_view))
+        {
+            self.status_message = Some(format!("Failed to save view state: {}", e));
+        }
+    }
+
+    pub fn restore_view_state(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        let view_state = self
+            .current_table()
+            .and_then(|table_name| self.persistence.load_view_state(&effective_path, table_name))
+            .unwrap_or_default();
+
+        // A filter that no longer parses is dropped
+        self.filters = view_state
+            .filters
+            .iter()
+            .filter_map(|filter| ColumnFilter::parse(&filter.column, &filter.expression).ok())
+            .collect();
+        self.data_offset = view_state.data_offset;
+        self.selected_row_idx = view_state.selected_row_idx;
+        self.selected_col_idx = view_state.selected_col_idx;
+        self.view_state = view_state;
+    }
+
+    /// Show the file as it was left: the table last viewed, its query run again, and that
+    /// table's sort, filters and cursor. When they no longer fit the file, such as a query
+    /// over a column since dropped, the first page of the table is shown instead.
+    pub fn restore_session(&mut self, data_source: &mut DataSource) -> Result<()> {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        if let Some(last_view) = self.persistence.load_last_view(&effective_path) {
+            if let Some(idx) = self.tables.iter().position(|t| *t == last_view.table) {
+                self.selected_table_idx = idx;
+                self.current_query = last_view.query;
+                self.current_query_params = last_view.query_params;
+            }
+        }
+        self.restore_view_state(data_source);
+        if let Err(e) = self.load_current_data(data_source) {
+            self.reset_data_view();
+            self.view_state = TableViewState::default();
+            self.show_error(format!("Could not reopen the file where it was left: {}", e));
+            self.load_current_data(data_source)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a column of the current data is shown in the table (rowid and the columns
+    /// hidden with `C` are not)
+    fn column_visible(&self, idx: usize) -> bool {
+        self.current_data
+            .as_ref()
+            .and_then(|data| data.columns.get(idx))
+            .is_some_and(|name| {
+                let rowid = idx == 0 && name == "rowid";
+                !rowid && !self.view_state.hidden_columns.contains(name)
+            })
+    }
+
+    /// Move the cursor to a row of the table (0 for the first, past the end for the last),
+    /// loading the page it is on
+    fn jump_to_row(&mut self, row: usize, data_source: &mut DataSource) -> Result<()> {
+        let Some(total) = self.current_data.as_ref().map(|data| data.total_rows) else {
+            return Ok(());
+        };
+        let row = row.min(total.saturating_sub(1));
+        if row >= self.data_offset && row < self.data_offset + self.page_size {
+            self.selected_row_idx = row - self.data_offset;
+        } else {
+            self.data_offset = row / self.page_size * self.page_size;
+            self.selected_row_idx = row - self.data_offset;
+            self.load_current_data(data_source)?;
+        }
+        if let Some(data) = &self.current_data {
+            self.selected_row_idx = self.selected_row_idx.min(data.rows.len().saturating_sub(1));
+        }
+        Ok(())
+    }
+
+    /// Shown columns in the order they are drawn: pinned ones first
+    fn display_columns(&self) -> Vec<usize> {
+        let Some(data) = &self.current_data else {
+            return Vec::new();
+        };
+        let (mut pinned, rest): (Vec<usize>, Vec<usize>) = (0..data.columns.len())
+            .filter(|&idx| self.column_visible(idx))
+            .partition(|&idx| self.view_state.pinned_columns.contains(&data.columns[idx]));
+        pinned.extend(rest);
+        pinned
+    }
+
+    /// The nearest shown column right (or left) of `from`, as drawn
+    fn adjacent_visible_col(&self, from: usize, forward: bool) -> Option<usize> {
+        let columns = self.display_columns();
+        match columns.iter().position(|&idx| idx == from) {
+            Some(pos) if forward => columns.get(pos + 1).copied(),
+            Some(pos) => pos.checked_sub(1).map(|pos| columns[pos]),
+            // From rowid or a column just hidden, go by position in the table
+            None if forward => columns.iter().copied().filter(|&idx| idx > from).min(),
+            None => columns.iter().copied().filter(|&idx| idx < from).max(),
+        }
+    }
+
+    fn first_visible_col(&self) -> Option<usize> {
+        self.display_columns().first().copied()
+    }
+
+    /// Pin the selected column to the left of the table, or unpin it
+    fn toggle_pinned_column(&mut self, data_source: &DataSource) {
+        let Some(column) = self.selected_column_name() else {
+            return;
+        };
+        let pinned = &mut self.view_state.pinned_columns;
+        self.status_message = Some(if let Some(pos) = pinned.iter().position(|c| *c == column) {
+            pinned.remove(pos);
+            format!("Unpinned {}", column)
+        } else {
+            pinned.push(column.clone());
+            format!("Pinned {}", column)
+        });
+        self.persist_view_state(data_source);
+    }
+
+    /// The header of a column in the table, and whether it is computed: computed columns are
+    /// starred, typed columns show their type
+    fn column_header(&self, name: &str) -> (String, bool) {
+        if self.computed_columns.iter().any(|col| col.name == name) {
+            (format!("*{}", name), true)
+        } else {
+            match self.column_types.get(name) {
+                Some(column_type) => (format!("{} ({})", name, column_type.label()), false),
+                None => (name.to_string(), false),
+            }
+        }
+    }
+
+    /// Widen (or narrow) the selected column by two cells; the width is kept per table until
+    /// it is reset from the column manager
+    fn resize_column(&mut self, wider: bool, data_source: &DataSource) {
+        let (Some(column), Some(data)) = (self.selected_column_name(), &self.current_data) else {
+            return;
+        };
+        let width = match self.view_state.column_widths.get(&column) {
+            Some(&width) => width,
+            None => {
+                let cells = data.rows.iter().map(|row| {
+                    let cell = row.get(self.selected_col_idx).map(|c| format_value(c, &self.format)).unwrap_or_default();
+                    truncate_to_width(&cell, 40).width()
+                });
+                fitted_width(&self.column_header(&column).0, cells)
+            }
+        };
+        let width = if wider { width.saturating_add(2) } else { width.saturating_sub(2) }.clamp(3, MAX_COLUMN_WIDTH);
+        self.view_state.column_widths.insert(column.clone(), width);
+        self.status_message = Some(format!("{} is {} wide", column, width));
+        self.persist_view_state(data_source);
+    }
+
+    /// Summarize the selected column on the worker thread; the result opens in a popup
+    fn request_column_summary(&mut self, kind: SummaryKind) {
+        let (Some(table), Some(data)) = (self.current_table(), &self.current_data) else {
+            return;
+        };
+        let Some(column) = data.columns.get(self.selected_col_idx).cloned() else {
+            return;
+        };
+        // Computed columns only exist on the loaded page
+        if self.computed_columns.iter().any(|col| col.name == column) {
+            match summarize_rows(data, &column, kind, self.format.number_locale) {
+                Ok(summary) => {
+                    self.show_column_summary(summary);
+                    self.status_message = Some("A computed column is summarized over the rows shown".to_string());
+                }
+                Err(e) => self.show_error(format!("Failed to summarize the column: {}", e)),
+            }
+            return;
+        }
+        self.pending_query = Some(QueryJob::ColumnSummary {
+            table: table.to_string(),
+            column,
+            query: self.current_query.clone().map(|query| (query, self.current_query_params.clone())),
+            filters: self.filters.clone(),
+            kind,
+            locale: self.format.number_locale,
+        });
+    }
+
+    fn show_column_summary(&mut self, summary: ColumnSummary) {
+        self.column_summary = Some(summary);
+        self.value_counts_selected = 0;
+        self.navigation_mode = NavigationMode::ColumnSummary;
+    }
+
+    fn handle_column_summary(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        let values = match &self.column_summary {
+            Some(ColumnSummary::ValueCounts(counts)) => counts.values.len(),
+            _ => 0,
+        };
+        let last = values.saturating_sub(1);
+        match key_event.code {
+            KeyCode::Enter if values > 0 => {
+                self.navigation_mode = NavigationMode::Data;
+                self.filter_on_counted_value(data_source)?;
+            }
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('t' | 'v' | 'u' | 'q') => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Up => self.value_counts_selected = self.value_counts_selected.saturating_sub(1),
+            KeyCode::Down => self.value_counts_selected = (self.value_counts_selected + 1).min(last),
+            KeyCode::PageUp => self.value_counts_selected = self.value_counts_selected.saturating_sub(self.page_size),
+            KeyCode::PageDown => self.value_counts_selected = (self.value_counts_selected + self.page_size).min(last),
+            KeyCode::Home => self.value_counts_selected = 0,
+            KeyCode::End => self.value_counts_selected = last,
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Filter the table on the value selected in the value counts popup, replacing the
+    /// column's filter like the `w` prompt
+    fn filter_on_counted_value(&mut self, data_source: &mut DataSource) -> Result<()> {
+        let Some(ColumnSummary::ValueCounts(counts)) = &self.column_summary else {
+            return Ok(());
+        };
+        let Some((value, _)) = counts.values.get(self.value_counts_selected) else {
+            return Ok(());
+        };
+        if self.current_query.is_some() {
+            self.status_message = Some("Filters apply to table data; press 'r' first".to_string());
+            return Ok(());
+        }
+        if self.computed_columns.iter().any(|col| col.name == counts.column) {
+            self.status_message = Some("Computed columns cannot be filtered".to_string());
+            return Ok(());
+        }
+        let filter = match value {
+            Value::Null => ColumnFilter { column: counts.column.clone(), op: FilterOp::IsNull, value: String::new() },
+            Value::Blob(_) => {
+                self.status_message = Some("Blobs cannot be filtered on".to_string());
+                return Ok(());
+            }
+            value => ColumnFilter { column: counts.column.clone(), op: FilterOp::Eq, value: value.to_string() },
+        };
+        self.status_message = Some(format!("Filtered {} {}", filter.column, filter.expression()));
+        self.filters.retain(|f| f.column != filter.column);
+        self.filters.push(filter);
+        self.reload_filtered(data_source)
+    }
+
+    fn handle_column_manager(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        let columns = self.manageable_columns();
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('C') => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Up => {
+                self.column_manager_selected = self.column_manager_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.column_manager_selected =
+                    (self.column_manager_selected + 1).min(columns.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => {
+                let Some(column) = columns.get(self.column_manager_selected) else {
+                    return Ok(true);
+                };
+                let hidden = &mut self.view_state.hidden_columns;
+                if let Some(pos) = hidden.iter().position(|c| c == column) {
+                    hidden.remove(pos);
+                } else if columns.iter().filter(|c| !hidden.contains(c)).count() > 1 {
+                    hidden.push(column.clone());
+                } else {
+                    self.status_message = Some("At least one column stays visible".to_string());
+                    return Ok(true);
+                }
+                self.ensure_valid_col_selection();
+                self.persist_view_state(data_source);
+            }
+            KeyCode::Char('a') => {
+                self.view_state.hidden_columns.clear();
+                self.persist_view_state(data_source);
+            }
+            KeyCode::Char('w') => {
+                if let Some(column) = columns.get(self.column_manager_selected) {
+                    if self.view_state.column_widths.remove(column).is_some() {
+                        self.persist_view_state(data_source);
+                    }
+                }
+            }
+            KeyCode::Char('+') => {
+                self.column_edit_target = None;
+                self.column_edit_input.clear();
+                self.navigation_mode = NavigationMode::ColumnEditInput;
+            }
+            KeyCode::Char('r') => {
+                if let Some(column) = columns.get(self.column_manager_selected) {
+                    self.column_edit_target = Some(column.clone());
+                    self.column_edit_input = column.clone();
+                    self.navigation_mode = NavigationMode::ColumnEditInput;
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(column) = columns.get(self.column_manager_selected) {
+                    self.column_edit_target = Some(column.clone());
+                    self.navigation_mode = NavigationMode::ConfirmDropColumn;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_column_edit_input(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::ColumnManager;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::ColumnManager;
+                let edit = match self.column_edit_target.take() {
+                    Some(from) => Ok(ColumnEdit::Rename { from, to: self.column_edit_input.trim().to_string() }),
+                    None => ColumnEdit::parse_add(&self.column_edit_input),
+                };
+                match edit {
+                    Ok(edit) => self.alter_column(&edit, data_source)?,
+                    Err(e) => self.show_error(format!("Invalid column: {}", e)),
+                }
+            }
+            KeyCode::Backspace => {
+                self.column_edit_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.column_edit_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_confirm_drop_column(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::ColumnManager;
+                if let Some(column) = self.column_edit_target.take() {
+                    self.alter_column(&ColumnEdit::Drop(column), data_source)?;
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.column_edit_target = None;
+                self.navigation_mode = NavigationMode::ColumnManager;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Add, rename or drop a column of the current table. SQLite tables change at once; file
+    /// tables change in memory and are written by the next save. Hidden, pinned, sorted and
+    /// filtered columns follow a rename and are forgotten once dropped.
+    fn alter_column(&mut self, edit: &ColumnEdit, data_source: &mut DataSource) -> Result<()> {
+        let Some(table_name) = self.current_table().map(|s| s.to_string()) else {
+            return Ok(());
+        };
+        // Reloading the page would lose them
+        if self.edit_log.has_edits() {
+            self.show_error("Save or undo (Ctrl+Z) the edits of the page before changing its columns".to_string());
+            return Ok(());
+        }
+        if let Err(e) = data_source.alter_column(&table_name, edit) {
+            self.show_error(format!("Failed to change the columns: {:#}", e));
+            return Ok(());
+        }
+        self.aggregates.retain(|((table, _, _), _)| *table != table_name);
+
+        let view = &mut self.view_state;
+        for columns in [&mut view.hidden_columns, &mut view.pinned_columns] {
+            *columns = columns.iter().filter_map(|c| edit.column_after(c)).collect();
+        }
+        view.column_widths = view
+            .column_widths
+            .drain()
+            .filter_map(|(column, width)| edit.column_after(&column).map(|column| (column, width)))
+            .collect();
+        view.sort = view.sort.take().and_then(|sort| {
+            edit.column_after(&sort.column).map(|column| SortOrder { column, ..sort })
+        });
+        let follow = |column: &mut String| match edit.column_after(column) {
+            Some(after) => {
+                *column = after;
+                true
+            }
+            None => false,
+        };
+        view.filters.retain_mut(|filter| follow(&mut filter.column));
+        self.filters.retain_mut(|filter| follow(&mut filter.column));
+        self.persist_view_state(data_source);
+
+        let saved = if matches!(data_source, DataSource::Sqlite(_)) {
+            self.forget_write_batches(data_source);
+            self.reload_tables(data_source)?;
+            self.refresh_from_disk(data_source)?;
+            ""
+        } else {
+            self.columns_changed = true;
+            self.data_modified = true;
+            self.load_current_data(data_source)?;
+            " (not saved)"
+        };
+        self.column_manager_selected =
+            self.column_manager_selected.min(self.manageable_columns().len().saturating_sub(1));
+        self.status_message = Some(format!("{}{}", edit.describe(), saved));
+        Ok(())
+    }
+
+    /// Columns listed in the show/hide popup: all but rowid
+    fn manageable_columns(&self) -> Vec<String> {
+        self.current_data
+            .as_ref()
+            .map(|data| {
+                data.columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, name)| !(*idx == 0 && *name == "rowid"))
+                    .map(|(_, name)| name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn ensure_valid_col_selection(&mut self) {
+        if let Some(data) = &self.current_data {
+            let min_col = if !data.columns.is_empty() && data.columns[0] == "rowid" {
+                1
+            } else {
+                0
+            };
+            if self.selected_col_idx < min_col {
+                self.selected_col_idx = min_col;
+            }
+            if self.selected_col_idx >= data.columns.len() {
+                self.selected_col_idx = data.columns.len().saturating_sub(1).max(min_col);
+            }
+            if self.selected_row_idx >= data.rows.len() {
+                self.selected_row_idx = data.rows.len().saturating_sub(1);
+            }
+        }
+        // Hidden columns can't be selected
+        if self.current_data.is_some() && !self.column_visible(self.selected_col_idx) {
+            if let Some(col) = self
+                .adjacent_visible_col(self.selected_col_idx, true)
+                .or_else(|| self.adjacent_visible_col(self.selected_col_idx, false))
+            {
+                self.selected_col_idx = col;
+            }
+        }
+    }
+
+    pub fn load_current_data(&mut self, data_source: &mut DataSource) -> Result<()> {
+        if let Some(table_name) = self.current_table().map(|s| s.to_string()) {
+            // A workbook sheet not read yet is read in the background; the page is shown
+            // once it is in
+            if data_source.load_in_background(&table_name) {
+                if !self.loading_sheets.iter().any(|(sheet, _)| *sheet == table_name) {
+                    self.loading_sheets.push((table_name, Instant::now()));
+                }
+                self.current_data = None;
+                self.original_data = None;
+                self.undecoded_columns.clear();
+                return Ok(());
+            }
+            let result = self.fetch_page(data_source, &table_name)?;
+            self.undecoded_columns = match self.decoded_columns(data_source, &table_name) {
+                Some(decoded) => (0..result.columns.len()).filter(|idx| !decoded.contains(idx)).collect(),
+                None => Vec::new(),
+            };
+
+            self.column_types = data_source
+                .column_types(&table_name, self.format.number_locale)
+                .map(|types| result.columns.iter().cloned().zip(types).collect())
+                .unwrap_or_default();
+
+            // Store original data for comparison when saving
+            self.original_data = Some(result.clone());
+            self.edit_log.clear();
+            self.current_data = Some(result);
+
+            // Load saved computed columns if available
+            self.load_computed_columns(&table_name, data_source)?;
+
+            // Apply computed columns to the loaded data
+            self.apply_computed_columns(data_source)?;
+
+            // Ensure column selection is valid (skip rowid)
+            self.ensure_valid_col_selection();
+        }
+        Ok(())
+    }
+
+    /// The page at `data_offset`, from the page cache when it was shown before or read ahead;
+    /// the page after it is then read ahead. Query results are kept by the database itself.
+    /// Until the cache has counted the table, the total is the rows up to the end of the page,
+    /// one more when the page is full, so there is a next page to go to.
+    fn fetch_page(&mut self, data_source: &DataSource, table_name: &str) -> Result<QueryResult> {
+        self.counting_rows = false;
+        let (Some(cache), None) = (&mut self.page_cache, &self.current_query) else {
+            return self.fetch_rows(data_source, table_name, self.data_offset, self.page_size);
+        };
+        let key = PageKey {
+            table: table_name.to_string(),
+            offset: self.data_offset,
+            limit: self.page_size,
+            sort: self.view_state.sort.clone(),
+            filters: self.filters.clone(),
+        };
+        cache.sync(data_source.change_signature());
+        let mut result = match cache.get(&key) {
+            Some(page) => page,
+            None => {
+                let page = data_source.get_uncounted_table_data(table_name, key.offset, key.limit, key.sort.as_ref(), &key.filters, self.format.number_locale)?;
+                cache.insert(key.clone(), page.clone());
+                page
+            }
+        };
+        match cache.row_count(&key) {
+            Some(total) => result.total_rows = total,
+            None => {
+                result.total_rows = key.offset + result.rows.len() + usize::from(result.rows.len() == key.limit);
+                self.counting_rows = true;
+            }
+        }
+        if key.offset + key.limit < result.total_rows {
+            cache.prefetch(PageKey { offset: key.offset + key.limit, ..key });
+        }
+        Ok(result)
+    }
+
+    /// Rows of the current view (the custom query, or the sorted and filtered table)
+    fn fetch_rows(
+        &self,
+        data_source: &DataSource,
+        table_name: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<QueryResult> {
+        if let Some(query) = &self.current_query {
+            data_source.execute_custom_query(query, &self.current_query_params, table_name, offset, limit)
+        } else if let Some(columns) = self.decoded_columns(data_source, table_name) {
+            data_source.get_projected_page(table_name, offset, limit, &columns, self.format.number_locale)
+        } else {
+            data_source.get_table_data(
+                table_name,
+                offset,
+                limit,
+                self.view_state.sort.as_ref(),
+                &self.filters,
+                self.format.number_locale,
+            )
+        }
+    }
+
+    /// The columns a page of the table is read with when some are hidden and the source can
+    /// skip columns: all but the hidden ones no computed column refers to. None when every
+    /// column is read, as for queries and sorted or filtered views.
+    fn decoded_columns(&self, data_source: &DataSource, table_name: &str) -> Option<Vec<usize>> {
+        if self.current_query.is_some()
+            || self.view_state.sort.is_some()
+            || !self.filters.is_empty()
+            || self.view_state.hidden_columns.is_empty()
+            || !data_source.projects_columns()
+        {
+            return None;
+        }
+        let columns = data_source.column_names(table_name)?;
+        let needed = |name: &String| {
+            !self.view_state.hidden_columns.contains(name)
+                || self.computed_columns.iter().any(|column| column.expression.contains(name.as_str()))
+        };
+        Some((0..columns.len()).filter(|&idx| needed(&columns[idx])).collect())
+    }
+
+    /// Read the hidden columns the page was read without into it (and its original), before
+    /// anything that needs whole rows, such as the detailed view, edits or exports
+    fn decode_hidden_columns(&mut self, data_source: &DataSource) -> Result<()> {
+        let (Some(table_name), Some(original)) = (self.current_table(), &self.original_data) else {
+            return Ok(());
+        };
+        if self.undecoded_columns.is_empty() {
+            return Ok(());
+        }
+        let page = data_source.get_projected_page(
+            table_name,
+            self.data_offset,
+            original.rows.len(),
+            &self.undecoded_columns,
+            self.format.number_locale,
+        )?;
+        let undecoded = std::mem::take(&mut self.undecoded_columns);
+        // Rows added on the page come after the ones read, and have no values to fill in
+        for data in [&mut self.current_data, &mut self.original_data].into_iter().flatten() {
+            for (row, read) in data.rows.iter_mut().zip(&page.rows) {
+                for &idx in &undecoded {
+                    if let (Some(cell), Some(value)) = (row.get_mut(idx), read.get(idx)) {
+                        *cell = value.clone();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_effective_persistence_path(&self, data_source: &DataSource) -> String {
+        // Use the effective save path if available, otherwise fall back to the original db_path
+        if let Some(effective_path) = data_source.get_effective_save_path() {
+            effective_path.to_string_lossy().to_string()
+        } else {
+            self.db_path.clone()
+        }
+    }
+
+    fn load_computed_columns(&mut self, table_name: &str, data_source: &DataSource) -> Result<()> {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        
+        // Check if file has changed and recalculation is needed
+        if self.persistence.should_recalculate(&effective_path) {
+            // File has changed, clear computed columns to force user to recreate them
+            // This is a safety measure to prevent incorrect calculations
+            self.computed_columns.clear();
+            return Ok(());
+        }
+
+        match self
+            .persistence
+            .load_computed_columns(&effective_path, table_name)
+        {
+            Ok(columns) => {
+                self.computed_columns = columns;
+            }
+            Err(_) => {
+                // No saved columns or file doesn't exist, start with empty list
+                self.computed_columns.clear();
+            }
+        }
+        Ok(())
+    }
+
+    fn save_computed_columns(&self, table_name: &str, data_source: &DataSource) -> Result<()> {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        self.persistence
+            .save_computed_columns(&effective_path, table_name, &self.computed_columns)
+            .context("Failed to save computed columns")?;
+        Ok(())
+    }
+
+    fn open_export_results(&mut self) {
+        self.results_origin = self.navigation_mode.clone();
+        self.export_selected = 0;
+        self.export_scope = ExportScope::All;
+        self.export_visible_columns = !self.hidden_export_columns().is_empty();
+        self.navigation_mode = NavigationMode::ExportResults;
+    }
+
+    /// The table the export popup writes (filtered and sorted as shown), None for a query's results
+    fn exported_table(&self) -> Option<&str> {
+        match (&self.results_origin, &self.current_query) {
+            (NavigationMode::Data | NavigationMode::Visual, None) => self.current_table(),
+            _ => None,
+        }
+    }
+
+    /// Write every row of the table or query in front to a file, or pipe them to a command
+    /// typed on the popup's last line
+    fn handle_export_results(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        let choices = ExportFormat::ALL.len() + 1;
+        let piping = self.export_selected == ExportFormat::ALL.len();
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = self.results_origin.clone();
+            }
+            KeyCode::Up => {
+                self.export_selected = (self.export_selected + choices - 1) % choices;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.export_selected = (self.export_selected + 1) % choices;
+            }
+            KeyCode::Backspace if piping => {
+                self.pipe_input.pop();
+            }
+            KeyCode::Char(c) if piping => {
+                self.pipe_input.push(c);
+            }
+            KeyCode::Char('t') => {
+                self.export_typed = !self.export_typed;
+            }
+            KeyCode::Char('r') => {
+                self.export_scope = self.export_scope.next();
+            }
+            KeyCode::Char('c') if !self.hidden_export_columns().is_empty() => {
+                self.export_visible_columns = !self.export_visible_columns;
+            }
+            KeyCode::Char('v') if !self.exported_computed_columns().is_empty() => {
+                self.export_computed = !self.export_computed;
+            }
+            KeyCode::Enter if !piping => {
+                let format = ExportFormat::ALL[self.export_selected];
+                let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+                let filename =
+                    format!("{}_{}.{}", self.exported_table().unwrap_or("query_export"), timestamp, format.extension());
+                self.export_path_input = self.reader_config.export.path_for(&filename).to_string_lossy().into_owned();
+                self.export_path_matches.clear();
+                self.export_overwrite = false;
+                self.navigation_mode = NavigationMode::ExportPath;
+            }
+            KeyCode::Enter => {
+                let command = self.pipe_input.trim().to_string();
+                if command.is_empty() {
+                    return Ok(true);
+                }
+                self.navigation_mode = self.results_origin.clone();
+                let data = match self.exported_rows(data_source) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        self.show_error(format!("Failed to collect query results: {}", e));
+                        return Ok(true);
+                    }
+                };
+                match external::to_csv(&data.columns, &data.rows) {
+                    Ok(input) => {
+                        let label = match self.exported_table() {
+                            Some(table) => format!("table {}", table),
+                            None => "query results".to_string(),
+                        };
+                        self.pending_pipe = Some(PipeRequest { command, input, label })
+                    }
+                    Err(e) => self.show_error(format!("Failed to collect query results: {}", e)),
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Choose where the export popup's format is written; Tab completes the path, and an
+    /// existing file is only overwritten after a second Enter
+    fn handle_export_path(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        if key_event.code != KeyCode::Tab {
+            self.export_path_matches.clear();
+        }
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::ExportResults;
+            }
+            KeyCode::Tab => {
+                let matches = complete_path(&mut self.export_path_input);
+                self.export_path_matches = if matches.len() > 1 { matches } else { Vec::new() };
+                self.export_overwrite = false;
+            }
+            KeyCode::Backspace => {
+                self.export_path_input.pop();
+                self.export_overwrite = false;
+            }
+            KeyCode::Char(c) => {
+                self.export_path_input.push(c);
+                self.export_overwrite = false;
+            }
+            KeyCode::Enter => {
+                let path = expand_home(self.export_path_input.trim());
+                if path.as_os_str().is_empty() {
+                    return Ok(true);
+                }
+                if path.is_dir() {
+                    self.show_error(format!("{} is a directory; add a file name", path.display()));
+                    return Ok(true);
+                }
+                if path.exists() && !self.export_overwrite {
+                    self.export_overwrite = true;
+                    return Ok(true);
+                }
+
+                self.navigation_mode = self.results_origin.clone();
+                let format = ExportFormat::ALL[self.export_selected];
+                let data = match self.exported_rows(data_source) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        self.show_error(format!("Failed to collect query results: {}", e));
+                        return Ok(true);
+                    }
+                };
+                match write_results(&data, &path, format, self.export_typed, self.format.number_locale) {
+                    Ok(()) => {
+                        self.status_message = Some(format!("Exported {} rows to {}", data.rows.len(), path.display()));
+                    }
+                    Err(e) => self.show_error(format!("{} export failed: {}", format.label(), e)),
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Rows of the page the selection covers: the visual selection (V), or the one under the cursor
+    fn selected_rows(&self) -> Vec<usize> {
+        match self.visual_anchor {
+            Some(anchor) => (anchor.min(self.selected_row_idx)..=anchor.max(self.selected_row_idx)).collect(),
+            None => vec![self.selected_row_idx],
+        }
+    }
+
+    /// Select rows of the page from the row `V` was pressed on to the cursor, and act on them
+    fn handle_visual_mode(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let rows = self.current_data.as_ref().map_or(0, |data| data.rows.len());
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('V') => self.leave_visual_mode(),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected_row_idx = self.selected_row_idx.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected_row_idx = (self.selected_row_idx + 1).min(rows.saturating_sub(1));
+            }
+            KeyCode::Home => self.selected_row_idx = 0,
+            KeyCode::End => self.selected_row_idx = rows.saturating_sub(1),
+            KeyCode::Left => {
+                if let Some(col) = self.adjacent_visible_col(self.selected_col_idx, false) {
+                    self.selected_col_idx = col;
+                }
+            }
+            KeyCode::Right => {
+                if let Some(col) = self.adjacent_visible_col(self.selected_col_idx, true) {
+                    self.selected_col_idx = col;
+                }
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let selected = self.selected_rows();
+                self.leave_visual_mode();
+                self.copy_rows(selected, key_event.code == KeyCode::Char('Y'));
+            }
+            KeyCode::Char('d') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Fill the selected cells of the column from the first one
+                let selected = self.selected_rows();
+                self.leave_visual_mode();
+                self.selected_row_idx = selected[0];
+                self.start_fill_down();
+                if selected.len() > 1 && self.navigation_mode == NavigationMode::FillDown {
+                    self.fill_input = (selected.len() - 1).to_string();
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                let selected = self.selected_rows();
+                self.leave_visual_mode();
+                self.delete_rows(selected[0], selected.len());
+            }
+            KeyCode::Char('e') => {
+                self.open_export_results();
+                self.export_scope = ExportScope::Selected;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn leave_visual_mode(&mut self) {
+        self.visual_anchor = None;
+        self.navigation_mode = NavigationMode::Data;
+    }
+
+    /// Delete `count` rows of the page from `row` on, as one undoable edit saved with the page
+    fn delete_rows(&mut self, row: usize, count: usize) {
+        let Some(data) = &mut self.current_data else {
+            return;
+        };
+        let edit = PageEdit::delete_rows(data, row, count);
+        self.selected_row_idx = row.min(data.rows.len().saturating_sub(1));
+        self.status_message = Some(match count {
+            1 => "Deleted the row (not saved)".to_string(),
+            n => format!("Deleted {} rows (not saved)", n),
+        });
+        self.edit_log.record(edit);
+        self.data_modified = true;
+        if let Err(e) = self.refresh_computed_columns() {
+            self.show_error(format!("Failed to update computed columns: {}", e));
+        }
+    }
+
+    /// The page shown behind the export popup and the rows selected on it
+    fn export_page(&self) -> Option<(&QueryResult, Vec<usize>)> {
+        match (&self.results_origin, &self.database_query) {
+            (NavigationMode::DatabaseResults, Some(results)) => Some((&results.data, vec![results.selected_row])),
+            _ => self.current_data.as_ref().map(|data| (data, self.selected_rows())),
+        }
+    }
+
+    /// Columns of the table behind the export popup that are hidden with `C`
+    fn hidden_export_columns(&self) -> Vec<&String> {
+        match (&self.results_origin, &self.current_data) {
+            (NavigationMode::Data | NavigationMode::Visual, Some(data)) => {
+                data.columns.iter().filter(|name| self.view_state.hidden_columns.contains(name)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Computed columns of the table behind the export popup
+    fn exported_computed_columns(&self) -> &[ComputedColumn] {
+        match self.results_origin {
+            NavigationMode::Data | NavigationMode::Visual => &self.computed_columns,
+            _ => &[],
+        }
+    }
+
+    /// Add the computed columns to the rows read for an export: aggregates are the ones shown,
+    /// and window columns are worked out over the rows in the order they are written
+    fn push_exported_computed_columns(&mut self, data: &mut QueryResult, data_source: &DataSource) -> Result<()> {
+        let locale = self.format.number_locale;
+        let aggregates = self.aggregate_values(Some(data_source))?;
+        let mut window_values = HashMap::new();
+        for column in &self.computed_columns {
+            if !matches!(column.column_type, ComputedColumnType::Window(_)) {
+                continue;
+            }
+            let Some(window) = WindowSpec::parse(&column.expression).transpose()? else {
+                continue;
+            };
+            let idx = data
+                .columns
+                .iter()
+                .position(|col| *col == window.column)
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", window.column))?;
+            let values = window.compute(data.rows.iter().map(|row| row.get(idx).unwrap_or(&Value::Null)), locale);
+            window_values.insert(column.expression.clone(), values);
+        }
+        Self::push_computed_columns(data, &self.computed_columns, &aggregates, &window_values, locale)
+    }
+
+    /// The rows of the view the export popup was opened on that its scope covers, with the
+    /// computed columns unless left out, and without the rowid kept for editing and, when
+    /// asked, the hidden columns; the others are kept in the order shown
+    fn exported_rows(&mut self, data_source: &DataSource) -> Result<QueryResult> {
+        let mut data = match (self.export_scope, &self.results_origin, &self.database_query) {
+            (ExportScope::All, NavigationMode::DatabaseResults, Some(results)) => {
+                data_source.execute_custom_query(&results.query, &results.params, "", 0, i64::MAX as usize)?
+            }
+            (ExportScope::All, _, _) => {
+                let mut data = self.fetch_all_rows(data_source)?;
+                if self.export_computed && !self.exported_computed_columns().is_empty() {
+                    self.push_exported_computed_columns(&mut data, data_source)?;
+                }
+                data
+            }
+            (scope, _, _) => {
+                let (page, selected) = self.export_page().ok_or_else(|| anyhow::anyhow!("No rows shown"))?;
+                let rows = match scope {
+                    ExportScope::Selected => selected.iter().filter_map(|&idx| page.rows.get(idx).cloned()).collect(),
+                    _ => page.rows.clone(),
+                };
+                QueryResult { columns: page.columns.clone(), total_rows: rows.len(), rows }
+            }
+        };
+        if !self.export_computed {
+            // The page shown has them already
+            for column in self.exported_computed_columns() {
+                if let Some(pos) = data.columns.iter().position(|name| *name == column.name) {
+                    data.columns.remove(pos);
+                    for row in &mut data.rows {
+                        if pos < row.len() {
+                            row.remove(pos);
+                        }
+                    }
+                }
+            }
+        }
+        let (columns, rows) = without_rowid(data.columns, data.rows);
+        if !self.export_visible_columns || self.hidden_export_columns().is_empty() {
+            return Ok(QueryResult { total_rows: rows.len(), columns, rows });
+        }
+
+        let shown: Vec<usize> = match &self.current_data {
+            Some(page) => self
+                .display_columns()
+                .iter()
+                .filter_map(|&idx| columns.iter().position(|c| *c == page.columns[idx]))
+                .collect(),
+            None => (0..columns.len()).collect(),
+        };
+        let rows: Vec<Vec<Value>> = rows
+            .into_iter()
+            .map(|row| shown.iter().map(|&idx| row.get(idx).cloned().unwrap_or(Value::Null)).collect())
+            .collect();
+        Ok(QueryResult {
+            columns: shown.iter().map(|&idx| columns[idx].clone()).collect(),
+            total_rows: rows.len(),
+            rows,
+        })
+    }
+
+    fn export_to_sqlite(&mut self, data_source: &DataSource) {
+        let Some(table_name) = self.current_table() else {
+            return;
+        };
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let path = self.reader_config.export.path_for(&format!("{}_{}.db", table_name, timestamp));
+        match data_source.export_table_to_sqlite(table_name, &path.to_string_lossy(), self.format.number_locale) {
+            Ok(rows) => {
+                self.status_message = Some(format!("Exported {} typed rows to {}", rows, path.display()));
+            }
+            Err(e) => self.show_error(format!("Typed export failed: {}", e)),
+        }
+    }
+
+    pub fn save_changes(&mut self, data_source: &mut DataSource) -> Result<()> {
+        if !self.data_modified {
+            self.status_message = Some("No changes to save".to_string());
+            return Ok(());
+        }
+
+        if matches!(data_source, DataSource::Sqlite(_)) {
+            self.confirm_sqlite_save();
+            return Ok(());
+        }
+
+        let Some(table_name) = self.current_table().map(|s| s.to_string()) else {
+            return Ok(());
+        };
+        let data = self.edited_table(data_source, &table_name)?;
+        if let Some(path) = data_source.saved_in_place() {
+            self.pending_file_save = Some((path, data));
+            self.navigation_mode = NavigationMode::ConfirmFileSave;
+            return Ok(());
+        }
+        self.write_table_file(data_source, &table_name, &data)
+    }
+
+    /// The whole table with the edits of the page: its rows replace the ones loaded from the
+    /// file, rows added to it follow them, and computed columns are left out
+    fn edited_table(&self, data_source: &DataSource, table_name: &str) -> Result<QueryResult> {
+        let (Some(page), Some(original)) = (&self.current_data, &self.original_data) else {
+            return Err(anyhow::anyhow!("No data loaded"));
+        };
+        let mut table = data_source.get_table_data(table_name, 0, i64::MAX as usize, None, &[], self.format.number_locale)?;
+        let positions: Vec<Option<usize>> =
+            table.columns.iter().map(|column| page.columns.iter().position(|c| c == column)).collect();
+        let rows = page.rows.iter().map(|row| {
+            positions
+                .iter()
+                .map(|pos| pos.and_then(|pos| row.get(pos)).cloned().unwrap_or(Value::Null))
+                .collect::<Vec<_>>()
+        });
+        let start = self.data_offset.min(table.rows.len());
+        let end = (start + original.rows.len()).min(table.rows.len());
+        table.rows.splice(start..end, rows);
+        table.total_rows = table.rows.len();
+        Ok(table)
+    }
+
+    fn handle_confirm_file_save(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                let (Some((_, data)), Some(table_name)) =
+                    (self.pending_file_save.take(), self.current_table().map(|s| s.to_string()))
+                else {
+                    return Ok(true);
+                };
+                if let Err(e) = self.write_table_file(data_source, &table_name, &data) {
+                    self.show_error(format!("Save failed: {}", e));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.pending_file_save = None;
+                self.navigation_mode = NavigationMode::Data;
+                self.status_message = Some("Save cancelled; edits are kept".to_string());
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Write the whole table back to its file, or to a CSV next to it for other formats
+    fn write_table_file(&mut self, data_source: &mut DataSource, table_name: &str, data: &QueryResult) -> Result<()> {
+        let in_place = data_source.saved_in_place();
+        match data_source.save_table_data(table_name, data) {
+            Ok(()) => {
+                self.data_modified = false;
+                self.columns_changed = false;
+                self.edit_log.clear();
+                self.aggregates.retain(|((table, _, _), _)| table != table_name);
+                
+                // Reload the data source to reflect the saved changes
+                if let Err(e) = data_source.reload_data() {
+                    self.status_message = Some(format!("Save successful but reload failed: {}", e));
+                } else if let Some(saved) = in_place {
+                    self.status_message = Some(match backup_path(&saved) {
+                        backup if backup.exists() => format!(
+                            "Changes saved to {} (the previous version is in {})",
+                            saved.display(),
+                            backup.display()
+                        ),
+                        _ => format!("Changes saved to {}", saved.display()),
+                    });
+                } else {
+                    match data_source {
+                        crate::data_source::DataSource::Xlsx(_, path, _, _) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            let format = if crate::file_reader::decompressed_path(&*path).extension().is_some_and(|ext| ext == "ods") {
+                                "ODS"
+                            } else {
+                                "Excel"
+                            };
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from {})",
+                                csv_path.display(),
+                                format
+                            ));
+                        }
+                        crate::data_source::DataSource::Parquet(_, path, _) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from Parquet)", 
+                                csv_path.display()
+                            ));
+                        }
+                        crate::data_source::DataSource::Json(_, path) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from JSON)",
+                                csv_path.display()
+                            ));
+                        }
+                        crate::data_source::DataSource::Arrow(_, path) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from Arrow)",
+                                csv_path.display()
+                            ));
+                        }
+                        crate::data_source::DataSource::Dbf(_, path) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from DBF)",
+                                csv_path.display()
+                            ));
+                        }
+                        crate::data_source::DataSource::Html(_, path) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from HTML)",
+                                csv_path.display()
+                            ));
+                        }
+                        crate::data_source::DataSource::Statistical(_, path, format) => {
+                            let csv_path = crate::file_reader::converted_csv_path(&*path);
+                            self.status_message = Some(format!(
+                                "Changes saved to {} (converted from {})",
+                                csv_path.display(),
+                                format.name()
+                            ));
+                        }
+                        // SQLite is saved row by row after confirmation, see confirm_sqlite_save;
+                        // the other databases are read-only and never get here
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// Work out what the edits change in the SQLite table and ask before writing them
+    fn confirm_sqlite_save(&mut self) {
+        let (Some(original), Some(edited)) = (&self.original_data, &self.current_data) else {
+            return;
+        };
+        match row_changes(original, edited) {
+            Ok(changes) if changes.is_empty() => {
+                self.data_modified = false;
+                self.status_message = Some("No changes to save".to_string());
+            }
+            Ok(changes) => {
+                self.pending_changes = changes;
+                self.navigation_mode = NavigationMode::ConfirmSave;
+            }
+            Err(e) => self.show_error(format!("Cannot save: {}", e)),
+        }
+    }
+
+    fn handle_confirm_save(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                self.write_pending_changes(data_source)?;
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.pending_changes.clear();
+                self.navigation_mode = NavigationMode::Data;
+                self.status_message = Some("Save cancelled; edits are kept".to_string());
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_confirm_statement(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                if let Some(plan) = self.pending_statement.take() {
+                    self.pending_query = Some(QueryJob::Statement(plan));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.pending_statement = None;
+                self.navigation_mode = NavigationMode::Data;
+                self.status_message = Some("Statement cancelled; nothing was changed".to_string());
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// After a confirmed statement ran, reload the table list and show the current table
+    fn statement_ran(&mut self, changed: u64, data_source: &mut DataSource) -> Result<()> {
+        self.forget_write_batches(data_source);
+        let table = self.current_table().map(|t| t.to_string());
+        self.current_query = None;
+        self.reload_tables(data_source)?;
+        // The statement may have dropped or renamed the table being viewed
+        if self.current_table().map(|t| t.to_string()) != table {
+            self.selected_table_idx = self.selected_table_idx.min(self.tables.len().saturating_sub(1));
+            self.reset_data_view();
+        }
+        self.refresh_from_disk(data_source)?;
+        self.status_message = Some(match changed {
+            0 => "Statement ran".to_string(),
+            1 => "Statement ran; 1 row changed".to_string(),
+            n => format!("Statement ran; {} rows changed", n),
+        });
+        Ok(())
+    }
+
+    fn write_pending_changes(&mut self, data_source: &mut DataSource) -> Result<()> {
+        let changes = std::mem::take(&mut self.pending_changes);
+        let Some(table_name) = self.current_table().map(|s| s.to_string()) else {
+            return Ok(());
+        };
+        match data_source.apply_row_changes(&table_name, &changes) {
+            Ok(statements) => {
+                let (updated, inserted, deleted) = count_row_changes(&changes);
+                let batch = WriteBatch {
+                    table: table_name.clone(),
+                    saved_at: chrono::Utc::now().timestamp().max(0) as u64,
+                    statements,
+                };
+                let effective_path = self.get_effective_persistence_path(data_source);
+                let kept = self.persistence.push_write_batch(&effective_path, batch);
+                self.data_modified = false;
+                self.edit_log.clear();
+                // Our own commit is not an external change
+                self.change_signature = data_source.change_signature();
+                self.table_stats.remove(&table_name);
+                self.aggregates.retain(|((table, _, _), _)| *table != table_name);
+                self.load_current_data(data_source)?;
+                self.status_message = Some(format!(
+                    "Saved {} changed and {} new rows to {}{}{}",
+                    updated,
+                    inserted,
+                    table_name,
+                    deleted_rows_note(deleted),
+                    match kept {
+                        Ok(()) => String::new(),
+                        Err(e) => format!(" (it can't be reverted: {})", e),
+                    }
+                ));
+            }
+            Err(e) => self.show_error(format!("Save failed, nothing was written: {:#}", e)),
+        }
+        Ok(())
+    }
+
+    /// Saves to the database can't be reverted past a change not kept as one: a statement from
+    /// the query prompt or a column added, renamed or dropped
+    fn forget_write_batches(&self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        if let Err(e) = self.persistence.clear_write_batches(&effective_path) {
+            tracing::warn!(error = %e, "failed to forget the saves to revert");
+        }
+    }
+
+    /// Ask before reverting the last save to the database (`Z`), kept from this run or an
+    /// earlier one
+    fn confirm_revert(&mut self, data_source: &DataSource) {
+        if self.data_modified {
+            self.show_error("Save or undo (Ctrl+Z) the edits of the page before reverting a save".to_string());
+            return;
+        }
+        let effective_path = self.get_effective_persistence_path(data_source);
+        match self.persistence.last_write_batch(&effective_path) {
+            Some(batch) => {
+                self.pending_revert = Some(batch);
+                self.navigation_mode = NavigationMode::ConfirmRevert;
+            }
+            None => self.status_message = Some("No saves to revert".to_string()),
+        }
+    }
+
+    fn handle_confirm_revert(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                if let Some(batch) = self.pending_revert.take() {
+                    self.revert_write_batch(batch, data_source)?;
+                }
+            }
+            // A save that can't be reverted any more would keep the older ones from it
+            KeyCode::Char('d') => {
+                self.navigation_mode = NavigationMode::Data;
+                if let Some(batch) = self.pending_revert.take() {
+                    let effective_path = self.get_effective_persistence_path(data_source);
+                    match self.persistence.pop_write_batch(&effective_path) {
+                        Ok(()) => {
+                            self.status_message =
+                                Some(format!("Forgot the last save to {}; the database was not changed", batch.table))
+                        }
+                        Err(e) => self.show_error(format!("Failed to forget the save: {}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.pending_revert = None;
+                self.navigation_mode = NavigationMode::Data;
+                self.status_message = Some("Revert cancelled; nothing was changed".to_string());
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn revert_write_batch(&mut self, batch: WriteBatch, data_source: &mut DataSource) -> Result<()> {
+        if let Err(e) = data_source.revert_changes(&batch.statements) {
+            self.show_error(format!(
+                "Revert failed, nothing was changed: {:#}. Z and then d forgets the save",
+                e
+            ));
+            return Ok(());
+        }
+        let effective_path = self.get_effective_persistence_path(data_source);
+        let forgotten = self.persistence.pop_write_batch(&effective_path);
+        // Our own commit is not an external change
+        self.change_signature = data_source.change_signature();
+        self.table_stats.remove(&batch.table);
+        self.aggregates.retain(|((table, _, _), _)| *table != batch.table);
+        self.load_current_data(data_source)?;
+        self.status_message = Some(match forgotten {
+            Ok(()) => format!("Reverted the last save to {}", batch.table),
+            Err(e) => format!("Reverted the last save to {}, but it is still listed: {}", batch.table, e),
+        });
+        Ok(())
+    }
+
+    fn handle_detailed_view(
+        &mut self,
+        key_event: KeyEvent,
+        _data_source: &DataSource,
+    ) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+                self.detailed_view_row = None;
+                self.detailed_view_selected_field = 0;
+            }
+            KeyCode::Up if self.detailed_view_selected_field > 0 => {
+                self.detailed_view_selected_field -= 1;
+            }
+            KeyCode::Down => {
+                if let Some(data) = &self.current_data {
+                    if self.detailed_view_selected_field < data.columns.len().saturating_sub(1) {
+                        self.detailed_view_selected_field += 1;
+                    }
+                }
+            }
+            KeyCode::Char('c') if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Copy selected field value to clipboard
+                if let Some(row_idx) = self.detailed_view_row {
+                    if let Some(data) = &self.current_data {
+                        if row_idx < data.rows.len()
+                            && self.detailed_view_selected_field < data.columns.len()
+                        {
+                            let value =
+                                data.rows[row_idx][self.detailed_view_selected_field].to_string();
+                            match self.copy_to_clipboard(&value) {
+                                Ok(_) => {
+                                    self.status_message = Some("Copied to clipboard".to_string());
+                                }
+                                Err(e) => {
+                                    self.show_error(format!("Failed to copy to clipboard: {}", e));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Char('c')
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                return Ok(false);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Copy rows of the page, with their shown columns, to the clipboard as tab-separated
+    /// text that pastes into spreadsheets
+    fn copy_rows(&mut self, rows: Vec<usize>, header: bool) {
+        let columns = self.display_columns();
+        let Some(data) = &self.current_data else {
+            return;
+        };
+        let mut lines: Vec<Vec<String>> = Vec::new();
+        if header {
+            lines.push(columns.iter().map(|&idx| data.columns[idx].clone()).collect());
+        }
+        for row in rows.iter().filter_map(|&idx| data.rows.get(idx)) {
+            lines.push(columns.iter().map(|&idx| row.get(idx).map(|cell| cell.to_string()).unwrap_or_default()).collect());
+        }
+        let copied = lines.len() - usize::from(header);
+        match self.copy_to_clipboard(&tsv(&lines)) {
+            Ok(()) => {
+                self.status_message = Some(match copied {
+                    1 => "Copied the row to the clipboard".to_string(),
+                    n => format!("Copied {} rows to the clipboard", n),
+                })
+            }
+            Err(e) => self.show_error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(Clipboard::new()?);
+        }
+
+        if let Some(clipboard) = &mut self.clipboard {
+            clipboard.set_text(text)?;
+            // Small delay to ensure clipboard managers have time to see the content
+            std::thread::sleep(std::time::Duration::from_millis(150));
+        }
+        Ok(())
+    }
+
+    fn show_database_results(&mut self) {
+        if self.database_query.is_some() {
+            self.navigation_mode = NavigationMode::DatabaseResults;
+        } else {
+            self.status_message = Some("No database query yet - press I to run one".to_string());
+        }
+    }
+
+    /// Browse the result tab of a whole-database query; the table view keeps its own page
+    fn handle_database_results(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        let page_size = self.page_size;
+        let Some(results) = &mut self.database_query else {
+            self.navigation_mode = NavigationMode::Data;
+            return Ok(true);
+        };
+        let mut page = None;
+        let mut reopen = None;
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = if self.current_data.is_some() { NavigationMode::Data } else { NavigationMode::Table };
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                results.selected_row = results.selected_row.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if results.selected_row + 1 < results.data.rows.len() => {
+                results.selected_row += 1;
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                results.selected_col = results.selected_col.saturating_sub(1);
+            }
+            KeyCode::Right | KeyCode::Char('l') if results.selected_col + 1 < results.data.columns.len() => {
+                results.selected_col += 1;
+            }
+            KeyCode::PageDown if results.offset + page_size < results.data.total_rows => {
+                page = Some(results.offset + page_size);
+            }
+            KeyCode::PageUp if results.offset > 0 => {
+                page = Some(results.offset.saturating_sub(page_size));
+            }
+            KeyCode::Home if results.offset > 0 => {
+                page = Some(0);
+            }
+            KeyCode::End => {
+                let last = results.data.total_rows.saturating_sub(1) / page_size.max(1) * page_size;
+                if last != results.offset {
+                    page = Some(last);
+                }
+            }
+            // A new query starts from the one shown
+            KeyCode::Char('I') => {
+                reopen = Some(results.query.clone());
+            }
+            KeyCode::Char('e') => {
+                self.open_export_results();
+                return Ok(true);
+            }
+            _ => {}
+        }
+
+        if let Some(offset) = page {
+            let (query, params) = (results.query.clone(), results.params.clone());
+            match data_source.execute_custom_query(&query, &params, "", offset, page_size) {
+                Ok(data) => {
+                    results.data = data;
+                    results.offset = offset;
+                    results.selected_row = 0;
+                }
+                Err(e) => self.show_error(format!("Query error: {}", e)),
+            }
+        }
+        if let Some(query) = reopen {
+            self.open_database_query(data_source);
+            self.query_input = query;
+        }
+        Ok(true)
+    }
+
+    pub fn show_error(&mut self, error: String) {
+        tracing::warn!(error = %error, "error shown to user");
+        self.error_message = Some(error);
+        self.previous_navigation_mode = self.navigation_mode.clone();
+        self.navigation_mode = NavigationMode::ErrorDisplay;
+    }
+
+    fn handle_error_display(
+        &mut self,
+        key_event: KeyEvent,
+        _data_source: &DataSource,
+    ) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = self.previous_navigation_mode.clone();
+                self.error_message = None;
+            }
+            KeyCode::Char('q') | KeyCode::Char('c')
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                return Ok(false);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_computed_column_input(
+        &mut self,
+        key_event: KeyEvent,
+        data_source: &mut DataSource,
+    ) -> Result<bool> {
+        // Changes made from the manager go back to it
+        let origin = match self.computed_column_edit {
+            Some(_) => NavigationMode::ComputedColumns,
+            None => NavigationMode::Data,
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = origin;
+                self.computed_column_input.clear();
+            }
+            KeyCode::Enter => {
+                let input = self.computed_column_input.trim().to_string();
+                let edit = self.computed_column_edit.take().unwrap_or(ComputedColumnEdit::Add);
+                self.navigation_mode = origin;
+                self.computed_column_input.clear();
+                if !input.is_empty() {
+                    if let Err(e) = self.edit_computed_column(edit, &input, data_source) {
+                        self.show_error(format!("Expression error: {}", e));
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                self.computed_column_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.computed_column_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_computed_column_manager(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        let selected = self.computed_manager_selected;
+        let count = self.computed_columns.len();
+        let shift = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('M') => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Up if shift => self.move_computed_column(-1, data_source)?,
+            KeyCode::Down if shift => self.move_computed_column(1, data_source)?,
+            KeyCode::Char('K') => self.move_computed_column(-1, data_source)?,
+            KeyCode::Char('J') => self.move_computed_column(1, data_source)?,
+            KeyCode::Up => {
+                self.computed_manager_selected = selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.computed_manager_selected = (selected + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Char('=') | KeyCode::Char('+') => {
+                self.computed_column_edit = Some(ComputedColumnEdit::Add);
+                self.computed_column_input.clear();
+                self.navigation_mode = NavigationMode::ComputedColumn;
+            }
+            KeyCode::Char('e') if selected < count => {
+                let column = &self.computed_columns[selected];
+                self.computed_column_input = format!("{}={}", column.name, column.expression);
+                self.computed_column_edit = Some(ComputedColumnEdit::Expression(selected));
+                self.navigation_mode = NavigationMode::ComputedColumn;
+            }
+            KeyCode::Char('r') if selected < count => {
+                self.computed_column_input = self.computed_columns[selected].name.clone();
+                self.computed_column_edit = Some(ComputedColumnEdit::Rename(selected));
+                self.navigation_mode = NavigationMode::ComputedColumn;
+            }
+            KeyCode::Delete | KeyCode::Char('d') if selected < count => {
+                let mut columns = self.computed_columns.clone();
+                let removed = columns.remove(selected);
+                self.set_computed_columns(columns, data_source)?;
+                self.computed_manager_selected = selected.min(self.computed_columns.len().saturating_sub(1));
+                self.status_message = Some(format!("Removed computed column '{}'", removed.name));
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Move the selected computed column one place left (`-1`) or right (`1`) among the others
+    fn move_computed_column(&mut self, step: isize, data_source: &DataSource) -> Result<()> {
+        let selected = self.computed_manager_selected;
+        let Some(target) = selected.checked_add_signed(step).filter(|t| *t < self.computed_columns.len()) else {
+            return Ok(());
+        };
+        let mut columns = self.computed_columns.clone();
+        columns.swap(selected, target);
+        self.set_computed_columns(columns, data_source)?;
+        self.computed_manager_selected = target;
+        Ok(())
+    }
+
+    /// Add a computed column from the prompt's `name=expression`, or change the expression or
+    /// name of the one picked in the manager
+    fn edit_computed_column(&mut self, edit: ComputedColumnEdit, input: &str, data_source: &DataSource) -> Result<()> {
+        let mut columns = self.computed_columns.clone();
+        let changed = match edit {
+            ComputedColumnEdit::Add => {
+                columns.push(self.parse_computed_column(input)?);
+                columns.len() - 1
+            }
+            ComputedColumnEdit::Expression(idx) => {
+                columns[idx] = self.parse_computed_column(input)?;
+                idx
+            }
+            ComputedColumnEdit::Rename(idx) => {
+                if !input.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(anyhow::anyhow!("Column name can only contain letters, numbers, and underscores"));
+                }
+                columns[idx].name = input.to_string();
+                idx
+            }
+        };
+        let name = columns[changed].name.clone();
+        let is_computed = |column: &String| self.computed_columns.iter().any(|c| c.name == *column);
+        let table_column = self
+            .current_data
+            .as_ref()
+            .is_some_and(|data| data.columns.iter().any(|c| *c == name && !is_computed(c)));
+        if table_column || columns.iter().filter(|c| c.name == name).count() > 1 {
+            return Err(anyhow::anyhow!("There is already a column named '{}'", name));
+        }
+
+        self.set_computed_columns(columns, data_source)?;
+        self.status_message = Some(match edit {
+            ComputedColumnEdit::Add => "Computed column added and saved".to_string(),
+            ComputedColumnEdit::Expression(_) => format!("Changed computed column '{}'", name),
+            ComputedColumnEdit::Rename(_) => format!("Renamed the computed column to '{}'", name),
+        });
+        Ok(())
+    }
+
+    /// Replace the computed columns of the table, working them out again on the page and
+    /// saving them for the table. Columns that can't be worked out leave the old ones in place.
+    fn set_computed_columns(&mut self, columns: Vec<ComputedColumn>, data_source: &DataSource) -> Result<()> {
+        self.strip_computed_columns();
+        let previous = std::mem::replace(&mut self.computed_columns, columns);
+        if let Err(e) = self.apply_computed_columns(data_source) {
+            self.strip_computed_columns();
+            self.computed_columns = previous;
+            self.apply_computed_columns(data_source)?;
+            return Err(e);
+        }
+        self.ensure_valid_col_selection();
+        if let Some(table_name) = self.current_table() {
+            if let Err(e) = self.save_computed_columns(table_name, data_source) {
+                self.show_error(format!("Failed to save computed columns: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_diff_input(
+        &mut self,
+        key_event: KeyEvent,
+        data_source: &mut DataSource,
+    ) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+                self.diff_input.clear();
+            }
+            KeyCode::Enter => {
+                let input = self.diff_input.trim().to_string();
+                self.diff_input.clear();
+                self.navigation_mode = NavigationMode::Data;
+                if !input.is_empty() {
+                    if let Err(e) = self.diff_against_table(&input, data_source) {
+                        self.show_error(format!("Diff error: {}", e));
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                self.diff_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.diff_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn open_join_prompt(&mut self) {
+        if self.current_query.is_some() {
+            self.status_message = Some("Joins apply to table data; press 'r' first".to_string());
+            return;
+        }
+        self.join_input.clear();
+        self.navigation_mode = NavigationMode::JoinInput;
+    }
+
+    fn handle_join_input(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                let Some(table) = self.current_table().map(|t| t.to_string()) else {
+                    return Ok(true);
+                };
+                match JoinSpec::parse(&self.join_input) {
+                    Ok(spec) => {
+                        self.pending_query = Some(QueryJob::Join {
+                            spec,
+                            table,
+                            filters: self.filters.clone(),
+                            config: Box::new(self.reader_config.clone()),
+                        })
+                    }
+                    Err(e) => self.show_error(format!("Invalid join: {}", e)),
+                }
+            }
+            KeyCode::Backspace => {
+                self.join_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.join_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_join_view(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let page_size = self.page_size;
+        let Some(joined) = &mut self.joined_table else {
+            self.navigation_mode = NavigationMode::Data;
+            return Ok(true);
+        };
+        let last_row = joined.data.rows.len().saturating_sub(1);
+        let last_col = joined.data.columns.len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Up => joined.selected_row = joined.selected_row.saturating_sub(1),
+            KeyCode::Down => joined.selected_row = (joined.selected_row + 1).min(last_row),
+            KeyCode::Left => joined.selected_col = joined.selected_col.saturating_sub(1),
+            KeyCode::Right => joined.selected_col = (joined.selected_col + 1).min(last_col),
+            KeyCode::PageUp => joined.selected_row = joined.selected_row.saturating_sub(page_size),
+            KeyCode::PageDown => joined.selected_row = (joined.selected_row + page_size).min(last_row),
+            KeyCode::Home => joined.selected_row = 0,
+            KeyCode::End => joined.selected_row = last_row,
+            KeyCode::Char('e') => {
+                let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+                let path = self.reader_config.export.path_for(&format!("join_export_{}.csv", timestamp));
+                match write_results(&joined.data, &path, ExportFormat::Csv, true, self.format.number_locale) {
+                    Ok(()) => {
+                        self.status_message =
+                            Some(format!("Exported {} rows to {}", joined.data.rows.len(), path.display()))
+                    }
+                    Err(e) => self.show_error(format!("Join export failed: {}", e)),
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(false);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Diff the current table against another table of the same source.
+    /// `input` is "other_table" or "other_table key_column".
+    fn diff_against_table(&mut self, input: &str, data_source: &DataSource) -> Result<()> {
+        let current_table = self
+            .current_table()
+            .ok_or_else(|| anyhow::anyhow!("No table selected"))?
+            .to_string();
+        let (other_table, key_column) = split_table_and_key(input, &self.tables)
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", input.trim()))?;
+
+        let old = data_source.get_all_table_data(&other_table)?;
+        let new = data_source.get_all_table_data(&current_table)?;
+        self.open_diff(
+            &old,
+            &new,
+            key_column.as_deref(),
+            format!("{} vs {}", current_table, other_table),
+        )
+    }
+
+    /// Compute a diff of `new` against the baseline `old` and switch to the diff view.
+    /// Without an explicit key the first non-rowid column is used.
+    pub fn open_diff(
+        &mut self,
+        old: &QueryResult,
+        new: &QueryResult,
+        key_column: Option<&str>,
+        description: String,
+    ) -> Result<()> {
+        let key_column = match key_column {
+            Some(key) => key.to_string(),
+            None => new
+                .columns
+                .iter()
+                .find(|c| *c != "rowid")
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Table has no columns to use as key"))?,
+        };
+
+        let result = diff_tables(old, new, &key_column)?;
+        self.status_message = Some(format!(
+            "{} added, {} removed, {} changed, {} unchanged",
+            result.count(DiffKind::Added),
+            result.count(DiffKind::Removed),
+            result.count(DiffKind::Changed),
+            result.unchanged
+        ));
+        self.diff_result = Some(result);
+        self.diff_description = description;
+        self.diff_selected_row = 0;
+        self.navigation_mode = NavigationMode::Diff;
+        Ok(())
+    }
+
+    /// Write the diff shown to a timestamped CSV file in the export directory
+    fn export_diff(&mut self) {
+        let Some(diff) = &self.diff_result else {
+            return;
+        };
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let path = self.reader_config.export.path_for(&format!("diff_export_{}.csv", timestamp));
+        match write_diff_csv(diff, &path) {
+            Ok(()) => {
+                self.status_message = Some(format!("Exported {} differing rows to {}", diff.rows.len(), path.display()))
+            }
+            Err(e) => self.show_error(format!("Diff export failed: {}", e)),
+        }
+    }
+
+    fn handle_diff_view(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let row_count = self.diff_result.as_ref().map_or(0, |d| d.rows.len());
+        let last_row = row_count.saturating_sub(1);
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+                self.diff_result = None;
+            }
+            KeyCode::Up => {
+                self.diff_selected_row = self.diff_selected_row.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.diff_selected_row = (self.diff_selected_row + 1).min(last_row);
+            }
+            KeyCode::PageUp => {
+                self.diff_selected_row = self.diff_selected_row.saturating_sub(self.page_size);
+            }
+            KeyCode::PageDown => {
+                self.diff_selected_row = (self.diff_selected_row + self.page_size).min(last_row);
+            }
+            KeyCode::Home => {
+                self.diff_selected_row = 0;
+            }
+            KeyCode::End => {
+                self.diff_selected_row = last_row;
+            }
+            KeyCode::Char('e') => {
+                self.export_diff();
+            }
+            KeyCode::Char('q') | KeyCode::Char('c')
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                return Ok(false);
+            }
+            KeyCode::Char('h') => {
+                self.show_help = !self.show_help;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn open_global_search(&mut self) {
+        self.previous_navigation_mode = self.navigation_mode.clone();
+        self.navigation_mode = NavigationMode::GlobalSearch;
+        self.search_input.clear();
+    }
+
+    fn handle_global_search_input(
+        &mut self,
+        key_event: KeyEvent,
+        data_source: &mut DataSource,
+    ) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = self.previous_navigation_mode.clone();
+                self.search_input.clear();
+            }
+            KeyCode::Enter => {
+                let term = self.search_input.trim().to_string();
+                self.navigation_mode = self.previous_navigation_mode.clone();
+                if term.is_empty() {
+                    return Ok(true);
+                }
+                match data_source.search_all_tables(&term, 500) {
+                    Ok(results) if results.is_empty() => {
+                        self.status_message = Some(format!("No matches for '{}'", term));
+                    }
+                    Ok(results) => {
+                        self.status_message = Some(format!(
+                            "{} matches for '{}'{}",
+                            results.len(),
+                            term,
+                            if results.len() >= 500 { " (limit reached)" } else { "" }
+                        ));
+                        self.search_results = results;
+                        self.search_selected = 0;
+                        self.navigation_mode = NavigationMode::SearchResults;
+                    }
+                    Err(e) => {
+                        self.show_error(format!("Search error: {}", e));
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                self.search_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.search_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_search_results(
+        &mut self,
+        key_event: KeyEvent,
+        data_source: &mut DataSource,
+    ) -> Result<bool> {
+        let last = self.search_results.len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = self.previous_navigation_mode.clone();
+            }
+            KeyCode::Up => {
+                self.search_selected = self.search_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.search_selected = (self.search_selected + 1).min(last);
+            }
+            KeyCode::PageUp => {
+                self.search_selected = self.search_selected.saturating_sub(self.page_size);
+            }
+            KeyCode::PageDown => {
+                self.search_selected = (self.search_selected + self.page_size).min(last);
+            }
+            KeyCode::Enter => {
+                if let Some(found) = self.search_results.get(self.search_selected).cloned() {
+                    if let Err(e) = self.jump_to_match(&found, data_source) {
+                        self.show_error(format!("Failed to open match: {}", e));
+                    }
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Char('c')
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                return Ok(false);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Open the table of a search match with the cursor on the matching cell
+    fn jump_to_match(&mut self, found: &SearchMatch, data_source: &mut DataSource) -> Result<()> {
+        let table_idx = self
+            .tables
+            .iter()
+            .position(|t| *t == found.table)
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", found.table))?;
+        let position = data_source.row_position(&found.table, found.rowid)?;
+
+        // The position is in rowid order, so any saved sort is dropped
+        self.go_to_row(table_idx, position, None, Some(&found.column), data_source)?;
+        let row = if matches!(data_source, DataSource::Sqlite(_)) { "rowid" } else { "row" };
+        self.status_message = Some(format!("{} / {} {} / {}", found.table, row, found.rowid, found.column));
+        Ok(())
+    }
+
+    /// Open a table with the cursor on an absolute row position of the given sort order
+    fn go_to_row(
+        &mut self,
+        table_idx: usize,
+        position: usize,
+        sort: Option<SortOrder>,
+        column: Option<&str>,
+        data_source: &mut DataSource,
+    ) -> Result<()> {
+        self.switch_table(table_idx, data_source)?;
+        // The position is in the whole table, so filters kept for it would move the row
+        self.filters.clear();
+        self.view_state.sort = sort;
+        self.data_offset = (position / self.page_size) * self.page_size;
+        self.selected_row_idx = position % self.page_size;
+        self.load_current_data(data_source)?;
+        if let (Some(data), Some(column)) = (&self.current_data, column) {
+            if let Some(col_idx) = data.columns.iter().position(|c| c == column) {
+                self.selected_col_idx = col_idx;
+            }
+        }
+        self.navigation_mode = NavigationMode::Data;
+        Ok(())
+    }
+
+    /// Pick up the stats the worker has counted, and ask it for the selected table's while
+    /// the table list is in use. Tables are counted once, as they are first selected.
+    pub fn poll_table_stats(&mut self) {
+        let Some(worker) = &self.stats_worker else {
+            return;
+        };
+        for (table, stats) in worker.finished() {
+            self.stats_pending.retain(|t| *t != table);
+            self.table_stats.insert(table, stats);
+            self.dirty = true;
+        }
+        if self.navigation_mode != NavigationMode::Table {
+            return;
+        }
+        if let Some(table) = self.current_table() {
+            if !self.table_stats.contains_key(table) && !self.stats_pending.iter().any(|t| t == table) {
+                worker.request(table);
+                self.stats_pending.push(table.to_string());
+            }
+        }
+    }
+
+    /// Fit the page to a terminal `height` rows high, unless the page size is set in the
+    /// config or with `--page-size`. The selected row stays selected; the page is read again
+    /// only while no edits are pending and no popup is open, like an external change.
+    pub fn fit_page_size(&mut self, height: u16, data_source: &mut DataSource) -> Result<()> {
+        let rows = page_rows(self, height);
+        let idle = matches!(self.navigation_mode, NavigationMode::Table | NavigationMode::Data);
+        if self.reader_config.page_size != 0 || rows == self.page_size || self.data_modified || !idle {
+            return Ok(());
+        }
+        let row = self.data_offset + self.selected_row_idx;
+        self.page_size = rows;
+        self.data_offset = row / rows * rows;
+        self.selected_row_idx = row % rows;
+        self.dirty = true;
+        if self.current_data.is_some() {
+            self.load_current_data(data_source)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the screen is to be drawn again: after a change, and on every tick while a
+    /// spinner turns
+    pub fn needs_redraw(&self) -> bool {
+        self.dirty || !self.loading_sheets.is_empty()
+    }
+
+    /// Take in the workbook sheets read in the background, showing the selected one if it
+    /// was waited for
+    pub fn poll_sheets(&mut self, data_source: &mut DataSource) -> Result<()> {
+        for (sheet, result) in data_source.finished_sheets() {
+            self.loading_sheets.retain(|(loading, _)| *loading != sheet);
+            self.dirty = true;
+            match result {
+                Err(e) => self.show_error(format!("Failed to read sheet '{}': {}", sheet, e)),
+                Ok(()) if self.current_table() == Some(sheet.as_str()) && self.current_data.is_none() => {
+                    self.load_current_data(data_source)?;
+                }
+                Ok(()) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill in the total of the page shown once the page cache has counted its table's rows
+    pub fn poll_row_count(&mut self) {
+        let (true, Some(cache)) = (self.counting_rows, &mut self.page_cache) else {
+            return;
+        };
+        if let Err(e) = cache.collect_counts() {
+            self.counting_rows = false;
+            self.dirty = true;
+            self.status_message = Some(format!("Failed to count the rows: {}", e));
+            return;
+        }
+        let Some(table) = self.tables.get(self.selected_table_idx) else {
+            return;
+        };
+        let key = PageKey {
+            table: table.clone(),
+            offset: self.data_offset,
+            limit: self.page_size,
+            sort: self.view_state.sort.clone(),
+            filters: self.filters.clone(),
+        };
+        let Some(total) = cache.row_count(&key) else {
+            return;
+        };
+        self.counting_rows = false;
+        self.dirty = true;
+        // Rows added to or deleted from the page since it was read count on top of the table's
+        let change = match (&self.original_data, &self.current_data) {
+            (Some(original), Some(data)) => data.total_rows as i64 - original.total_rows as i64,
+            _ => 0,
+        };
+        if let Some(original) = &mut self.original_data {
+            original.total_rows = total;
+        }
+        if let Some(data) = &mut self.current_data {
+            data.total_rows = (total as i64 + change).max(0) as usize;
+        }
+    }
+
+    /// Read the table list and the sidebar sections of the data source again
+    pub fn reload_tables(&mut self, data_source: &DataSource) -> Result<()> {
+        self.sidebar_objects = data_source.sidebar_objects()?;
+        self.sidebar_cursor = None;
+        self.followed_keys.clear();
+        self.table_stats.clear();
+        self.aggregates.clear();
+        self.set_tables(data_source.get_tables()?);
+        Ok(())
+    }
+
+    /// Replace the table list, keeping favorites pinned and the selected table selected
+    fn set_tables(&mut self, tables: Vec<String>) {
+        let selected = self.current_table().map(|t| t.to_string());
+        self.table_order = tables;
+
+        // Stable sort, so both groups keep the file's order
+        let mut tables = self.table_order.clone();
+        tables.sort_by_key(|table| !self.favorite_tables.contains(table));
+        self.tables = tables;
+
+        if let Some(idx) = selected.and_then(|name| self.tables.iter().position(|t| *t == name)) {
+            self.selected_table_idx = idx;
+        }
+    }
+
+    /// Poll the database files and pick up commits made by other processes. The current
+    /// page is refreshed in place unless there are pending edits or a popup is open, in
+    /// which case a banner asks the user to refresh with 'r'.
+    pub fn check_external_changes(&mut self, data_source: &mut DataSource) -> Result<()> {
+        let Some(signature) = data_source.change_signature() else {
+            return Ok(());
+        };
+        let previous = self.change_signature.replace(signature.clone());
+        if previous.is_none() || previous.as_ref() == Some(&signature) {
+            return Ok(());
+        }
+        self.dirty = true;
+
+        let idle = matches!(self.navigation_mode, NavigationMode::Table | NavigationMode::Data);
+        if self.data_modified || !idle {
+            self.external_change = true;
+            return Ok(());
+        }
+
+        self.refresh_from_disk(data_source)?;
+        self.status_message = Some("Database changed on disk; view refreshed".to_string());
+        Ok(())
+    }
+
+    /// Reload the table list and the current page, keeping the cursor where it was
+    fn refresh_from_disk(&mut self, data_source: &mut DataSource) -> Result<()> {
+        data_source.discard_query_results();
+        self.reload_tables(data_source)?;
+        if self.selected_table_idx >= self.tables.len() {
+            self.selected_table_idx = self.tables.len().saturating_sub(1);
+            self.reset_data_view();
+        }
+        self.data_modified = false;
+        self.load_current_data(data_source)?;
+        self.external_change = false;
+        self.change_signature = data_source.change_signature();
+        Ok(())
+    }
+
+    /// Load the favorite tables stored for the open file and start on the first of them
+    pub fn load_favorite_tables(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        self.favorite_tables = self.persistence.load_favorite_tables(&effective_path);
+        self.set_tables(self.table_order.clone());
+        self.selected_table_idx = 0;
+    }
+
+    fn toggle_favorite(&mut self, data_source: &DataSource) {
+        let Some(table) = self.current_table().map(|t| t.to_string()) else {
+            return;
+        };
+        if let Some(idx) = self.favorite_tables.iter().position(|t| *t == table) {
+            self.favorite_tables.remove(idx);
+            self.status_message = Some(format!("Unpinned '{}'", table));
+        } else {
+            self.favorite_tables.push(table.clone());
+            self.status_message = Some(format!("Pinned '{}' to the top", table));
+        }
+        self.set_tables(self.table_order.clone());
+
+        let effective_path = self.get_effective_persistence_path(data_source);
+        if let Err(e) = self
+            .persistence
+            .save_favorite_tables(&effective_path, &self.favorite_tables)
+        {
+            self.show_error(format!("Failed to save favorite tables: {}", e));
+        }
+    }
+
+    /// Load the bookmarks stored for the open file
+    pub fn load_bookmarks(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        self.bookmarks = self.persistence.load_bookmarks(&effective_path);
+    }
+
+    fn save_bookmarks(&mut self, data_source: &DataSource) {
+        let effective_path = self.get_effective_persistence_path(data_source);
+        if let Err(e) = self.persistence.save_bookmarks(&effective_path, &self.bookmarks) {
+            self.show_error(format!("Failed to save bookmarks: {}", e));
+        }
+    }
+
+    fn handle_bookmark_input(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Tab => {
+                self.bookmark_cell = !self.bookmark_cell;
+            }
+            KeyCode::Enter => {
+                self.navigation_mode = NavigationMode::Data;
+                if let Some(bookmark) = self.bookmark_at_cursor() {
+                    self.status_message = Some(format!("Bookmarked '{}'", bookmark.name));
+                    self.bookmarks.push(bookmark);
+                    self.save_bookmarks(data_source);
+                }
+            }
+            KeyCode::Backspace => {
+                self.bookmark_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.bookmark_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn bookmark_at_cursor(&self) -> Option<Bookmark> {
+        let data = self.current_data.as_ref()?;
+        let row = data.rows.get(self.selected_row_idx)?;
+        let table = self.current_table()?.to_string();
+        let position = self.data_offset + self.selected_row_idx;
+        let rowid = match data.columns.first() {
+            Some(first) if first == "rowid" => match row.first() {
+                Some(Value::Int(rowid)) => Some(*rowid),
+                _ => None,
+            },
+            _ => None,
+        };
+        let column = self
+            .bookmark_cell
+            .then(|| data.columns.get(self.selected_col_idx).cloned())
+            .flatten();
+
+        let name = match self.bookmark_input.trim() {
+            "" => match &column {
+                Some(column) => format!("{} row {} / {}", table, position + 1, column),
+                None => format!("{} row {}", table, position + 1),
+            },
+            name => name.to_string(),
+        };
+
+        Some(Bookmark {
+            name,
+            table,
+            rowid,
+            position,
+            sort: self.view_state.sort.clone(),
+            column,
+        })
+    }
+
+    fn open_bookmark_list(&mut self) {
+        if self.bookmarks.is_empty() {
+            self.status_message = Some("No bookmarks yet; press 'b' on a row to add one".to_string());
+            return;
+        }
+        self.previous_navigation_mode = self.navigation_mode.clone();
+        self.navigation_mode = NavigationMode::Bookmarks;
+        self.bookmark_selected = self.bookmark_selected.min(self.bookmarks.len() - 1);
+    }
+
+    fn handle_bookmark_list(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = self.previous_navigation_mode.clone();
+            }
+            KeyCode::Up => {
+                self.bookmark_selected = self.bookmark_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.bookmark_selected =
+                    (self.bookmark_selected + 1).min(self.bookmarks.len().saturating_sub(1));
+            }
+            KeyCode::Delete | KeyCode::Char('d') if self.bookmark_selected < self.bookmarks.len() => {
+                let removed = self.bookmarks.remove(self.bookmark_selected);
+                self.save_bookmarks(data_source);
+                self.status_message = Some(format!("Removed bookmark '{}'", removed.name));
+                if self.bookmarks.is_empty() {
+                    self.navigation_mode = self.previous_navigation_mode.clone();
+                } else {
+                    self.bookmark_selected = self.bookmark_selected.min(self.bookmarks.len() - 1);
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(bookmark) = self.bookmarks.get(self.bookmark_selected).cloned() {
+                    if let Err(e) = self.jump_to_bookmark(&bookmark, data_source) {
+                        self.show_error(format!("Failed to open bookmark: {}", e));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn jump_to_bookmark(&mut self, bookmark: &Bookmark, data_source: &mut DataSource) -> Result<()> {
+        let table_idx = self
+            .tables
+            .iter()
+            .position(|t| *t == bookmark.table)
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", bookmark.table))?;
+
+        // SQLite rows are looked up by rowid so bookmarks survive inserts and deletes
+        let (position, sort) = match bookmark.rowid {
+            Some(rowid) => (data_source.row_position(&bookmark.table, rowid)?, None),
+            None => (bookmark.position, bookmark.sort.clone()),
+        };
+        self.go_to_row(table_idx, position, sort, bookmark.column.as_deref(), data_source)?;
+        self.status_message = Some(format!("Bookmark '{}'", bookmark.name));
+        Ok(())
+    }
+
+    fn handle_pipe_input(&mut self, key_event: KeyEvent, data_source: &mut DataSource) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = NavigationMode::Data;
+            }
+            KeyCode::Tab => {
+                self.pipe_scope = self.pipe_scope.next();
+            }
+            KeyCode::Enter => {
+                let command = self.pipe_input.trim().to_string();
+                self.navigation_mode = NavigationMode::Data;
+                if command.is_empty() {
+                    return Ok(true);
+                }
+                match self.selection_csv(data_source) {
+                    Ok(input) => {
+                        let label = self.pipe_scope.label().to_string();
+                        self.pending_pipe = Some(PipeRequest { command, input, label })
+                    }
+                    Err(e) => self.show_error(format!("Failed to collect {}: {}", self.pipe_scope.label(), e)),
+                }
+            }
+            KeyCode::Backspace => {
+                self.pipe_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.pipe_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_pipe_output(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.navigation_mode = self.results_origin.clone();
+                self.pipe_output = None;
+            }
+            KeyCode::Up => {
+                self.pipe_output_scroll = self.pipe_output_scroll.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.pipe_output_scroll = self.pipe_output_scroll.saturating_add(1);
+            }
+            KeyCode::PageUp => {
+                self.pipe_output_scroll = self.pipe_output_scroll.saturating_sub(self.page_size as u16);
+            }
+            KeyCode::PageDown => {
+                self.pipe_output_scroll = self.pipe_output_scroll.saturating_add(self.page_size as u16);
+            }
+            KeyCode::Home => {
+                self.pipe_output_scroll = 0;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn open_tool_picker(&mut self) {
+        if self.external_tools.is_empty() {
+            self.status_message = Some("No external tools configured for this file type".to_string());
+            return;
+        }
+        if self.data_modified {
+            self.show_error(
+                "Save your changes before opening an external tool; the view is reloaded afterwards"
+                    .to_string(),
+            );
+            return;
+        }
+        self.previous_navigation_mode = self.navigation_mode.clone();
+        self.navigation_mode = NavigationMode::ToolPicker;
+        self.tool_selected = self.tool_selected.min(self.external_tools.len() - 1);
+    }
+
+    fn handle_tool_picker(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.navigation_mode = self.previous_navigation_mode.clone();
+            }
+            KeyCode::Up => {
+                self.tool_selected = self.tool_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.tool_selected =
+                    (self.tool_selected + 1).min(self.external_tools.len().saturating_sub(1));
+            }
+            KeyCode::Enter => {
+                if let Some(tool) = self.external_tools.get(self.tool_selected) {
+                    let table = self.current_table().unwrap_or_default();
+                    self.pending_tool = Some(ToolLaunch {
+                        name: tool.name.clone(),
+                        command: external::expand_tool_command(&tool.command, &self.db_path, table),
+                    });
+                }
+                self.navigation_mode = self.previous_navigation_mode.clone();
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Reload the file after an external tool exited, since it may have changed it
+    pub fn finish_external_tool(
+        &mut self,
+        launch: &ToolLaunch,
+        result: Result<std::process::ExitStatus>,
+        data_source: &mut DataSource,
+    ) -> Result<()> {
+        let status = match result {
+            Ok(status) => status,
+            Err(e) => {
+                self.show_error(format!("{:#}", e));
+                return Ok(());
+            }
+        };
+
+        data_source.reload_data()?;
+        self.reload_tables(data_source)?;
+        if self.selected_table_idx >= self.tables.len() {
+            self.selected_table_idx = self.tables.len().saturating_sub(1);
+            self.reset_data_view();
+        }
+        self.load_current_data(data_source)?;
+
+        self.status_message = Some(if status.success() {
+            format!("{} closed, view reloaded", launch.name)
+        } else {
+            format!("{} exited with {}, view reloaded", launch.name, status)
+        });
+        Ok(())
+    }
+
+    /// Show the result of a piped command once the main loop has run it
+    pub fn show_pipe_output(&mut self, request: &PipeRequest, result: Result<CommandOutput>) {
+        match result {
+            Ok(output) => {
+                self.pipe_output_title = format!("{} | {}", request.label, request.command);
+                self.pipe_output = Some(output);
+                self.pipe_output_scroll = 0;
+                self.navigation_mode = NavigationMode::PipeOutput;
+            }
+            Err(e) => self.show_error(format!("{:#}", e)),
+        }
+    }
+
+    /// The selected row, the current column or the whole table as CSV (rowid excluded)
+    fn selection_csv(&self, data_source: &DataSource) -> Result<String> {
+        let data = self
+            .current_data
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No data loaded"))?;
+
+        let (columns, rows) = match self.pipe_scope {
+            PipeScope::Row => {
+                let row = data
+                    .rows
+                    .get(self.selected_row_idx)
+                    .ok_or_else(|| anyhow::anyhow!("No row selected"))?;
+                (data.columns.clone(), vec![row.clone()])
+            }
+            PipeScope::Column => {
+                let column = data
+                    .columns
+                    .get(self.selected_col_idx)
+                    .ok_or_else(|| anyhow::anyhow!("No column selected"))?;
+                let all = self.fetch_all_rows(data_source)?;
+                let idx = all.columns.iter().position(|c| c == column).ok_or_else(|| {
+                    anyhow::anyhow!("Computed column '{}' can only be piped row by row", column)
+                })?;
+                let rows = all
+                    .rows
+                    .iter()
+                    .map(|row| vec![row.get(idx).cloned().unwrap_or(Value::Null)])
+                    .collect();
+                (vec![column.clone()], rows)
+            }
+            PipeScope::Table => {
+                let all = self.fetch_all_rows(data_source)?;
+                (all.columns, all.rows)
+            }
+        };
+
+        let (columns, rows) = without_rowid(columns, rows);
+        external::to_csv(&columns, &rows)
+    }
+
+    /// Every row of the current table or query, in the current sort order
+    fn fetch_all_rows(&self, data_source: &DataSource) -> Result<QueryResult> {
+        let table_name = self
+            .current_table()
+            .ok_or_else(|| anyhow::anyhow!("No table selected"))?;
+        match &self.current_query {
+            Some(query) => {
+                data_source.execute_custom_query(query, &self.current_query_params, table_name, 0, i64::MAX as usize)
+            }
+            None => data_source.get_table_data(
+                table_name,
+                0,
+                i64::MAX as usize,
+                self.view_state.sort.as_ref(),
+                &self.filters,
+                self.format.number_locale,
+            ),
+        }
+    }
+
+    fn parse_computed_column(&self, expression: &str) -> Result<ComputedColumn> {
+        let expression = expression.trim();
+
+        // Check if expression has custom name (contains '=')
+        let (column_name, expr_part) = if let Some(eq_pos) = expression.find('=') {
+            let name = expression[..eq_pos].trim();
+            let expr = expression[eq_pos + 1..].trim();
+            if name.is_empty() || expr.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Invalid syntax. Use 'column_name=expression'"
+                ));
+            }
+            // Validate column name (no special characters except underscore)
+            if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(anyhow::anyhow!(
+                    "Column name can only contain letters, numbers, and underscores"
+                ));
+            }
+            (Some(name.to_string()), expr)
+        } else {
+            (None, expression)
+        };
+
+        // Parse different types of expressions
+        if let Some(window) = WindowSpec::parse(expr_part).transpose()? {
+            if let Some(data) = &self.current_data {
+                if !data.columns.contains(&window.column) {
+                    return Err(anyhow::anyhow!("Column '{}' does not exist", window.column));
+                }
+            }
+            Ok(ComputedColumn {
+                name: column_name.unwrap_or_else(|| expr_part.to_string()),
+                expression: expr_part.to_string(),
+                column_type: ComputedColumnType::Window(window.func.name().to_string()),
+            })
+        } else if let Some(captures) = regex::Regex::new(r"^(sum|mean|count|min|max)\(([^)]+)\)$")
+            .unwrap()
+            .captures(expr_part)
+        {
+            // Aggregate function
+            let func = captures.get(1).unwrap().as_str();
+            let column = captures.get(2).unwrap().as_str().trim();
+
+            // Verify column exists
+            if let Some(data) = &self.current_data {
+                if !data.columns.contains(&column.to_string()) {
+                    return Err(anyhow::anyhow!("Column '{}' does not exist", column));
+                }
+            }
+
+            let computed_col = ComputedColumn {
+                name: column_name.unwrap_or_else(|| format!("{}({})", func, column)),
+                expression: expr_part.to_string(),
+                column_type: ComputedColumnType::Aggregate(func.to_string()),
+            };
+
+            Ok(computed_col)
+        } else if expr_part.contains('+')
+            || expr_part.contains('-')
+            || expr_part.contains('*')
+            || expr_part.contains('/')
+            || expr_part
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.' || c == ' ')
+        {
+            // Row operation, mixed operation, or constant expression
+            let columns_used = self.extract_column_names(expr_part)?;
+            let aggregate_expressions = self.extract_aggregate_expressions(expr_part)?;
+
+            // Verify all columns exist if any are used
+            if let Some(data) = &self.current_data {
+                for col in &columns_used {
+                    if !data.columns.contains(col) {
+                        return Err(anyhow::anyhow!("Column '{}' does not exist", col));
+                    }
+                }
+                // Verify columns in aggregate expressions exist
+                for agg_expr in &aggregate_expressions {
+                    let column_in_agg = self.extract_column_from_aggregate(agg_expr)?;
+                    if !data.columns.contains(&column_in_agg) {
+                        return Err(anyhow::anyhow!(
+                            "Column '{}' in aggregate '{}' does not exist",
+                            column_in_agg,
+                            agg_expr
+                        ));
+                    }
+                }
+            }
+
+            let column_type = if aggregate_expressions.is_empty() {
+                ComputedColumnType::RowOperation(columns_used)
+            } else {
+                ComputedColumnType::MixedOperation(columns_used, aggregate_expressions)
+            };
+
+            let computed_col = ComputedColumn {
+                name: column_name.unwrap_or_else(|| expr_part.to_string()),
+                expression: expr_part.to_string(),
+                column_type,
+            };
+
+            Ok(computed_col)
+        } else {
+            // Check if it's a simple numeric constant or column name
+            if expr_part.trim().parse::<f64>().is_ok() {
+                // It's a numeric constant
+                let computed_col = ComputedColumn {
+                    name: column_name.unwrap_or_else(|| expr_part.to_string()),
+                    expression: expr_part.to_string(),
+                    column_type: ComputedColumnType::RowOperation(vec![]),
+                };
+
+                Ok(computed_col)
+            } else if let Some(data) = &self.current_data {
+                // Check if it's a column name
+                if data.columns.contains(&expr_part.to_string()) {
+                    let computed_col = ComputedColumn {
+                        name: column_name.unwrap_or_else(|| expr_part.to_string()),
+                        expression: expr_part.to_string(),
+                        column_type: ComputedColumnType::RowOperation(vec![expr_part.to_string()]),
+                    };
+
+                    Ok(computed_col)
+                } else {
+                    Err(anyhow::anyhow!("Invalid expression format. Use sum(Column), mean(Column), Column1 + Column2, or numeric constants"))
+                }
+            } else {
+                Err(anyhow::anyhow!("Invalid expression format. Use sum(Column), mean(Column), Column1 + Column2, or numeric constants"))
+            }
+        }
+    }
+
+    fn extract_column_names(&self, expression: &str) -> Result<Vec<String>> {
+        let mut columns = Vec::new();
+        let mut current_token = String::new();
+        let mut in_column = false;
+
+        for ch in expression.chars() {
+            match ch {
+                '+' | '-' | '*' | '/' | '(' | ')' | ' ' | ',' => {
+                    if in_column && !current_token.trim().is_empty() {
+                        let token = current_token.trim().to_string();
+                        // Only add if it's not a number and not a function name
+                        if token.parse::<f64>().is_err()
+                            && !["sum", "mean", "count", "min", "max"].contains(&token.as_str())
+                        {
+                            columns.push(token);
+                        }
+                        current_token.clear();
+                        in_column = false;
+                    }
+                }
+                _ => {
+                    if !in_column && !ch.is_whitespace() {
+                        in_column = true;
+                    }
+                    if in_column {
+                        current_token.push(ch);
+                    }
+                }
+            }
+        }
+
+        if in_column && !current_token.trim().is_empty() {
+            let token = current_token.trim().to_string();
+            if token.parse::<f64>().is_err()
+                && !["sum", "mean", "count", "min", "max"].contains(&token.as_str())
+            {
+                columns.push(token);
+            }
+        }
+
+        // Remove duplicates
+        columns.sort();
+        columns.dedup();
+
+        Ok(columns)
+    }
+
+    fn extract_aggregate_expressions(&self, expression: &str) -> Result<Vec<String>> {
+        let mut aggregates = Vec::new();
+        let regex = regex::Regex::new(r"(sum|mean|count|min|max)\([^)]+\)").unwrap();
+
+        for capture in regex.captures_iter(expression) {
+            if let Some(full_match) = capture.get(0) {
+                aggregates.push(full_match.as_str().to_string());
+            }
+        }
+
+        Ok(aggregates)
+    }
+
+    fn extract_column_from_aggregate(&self, aggregate_expr: &str) -> Result<String> {
+        let regex = regex::Regex::new(r"^(sum|mean|count|min|max)\(([^)]+)\)$").unwrap();
+
+        if let Some(captures) = regex.captures(aggregate_expr) {
+            if let Some(column_match) = captures.get(2) {
+                return Ok(column_match.as_str().trim().to_string());
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Invalid aggregate expression: {}",
+            aggregate_expr
+        ))
+    }
+
+    /// The value of every aggregate the computed columns use, such as `sum(Age)`. Those of a
+    /// table are worked out over all of its rows passing the filters, by the data source, and
+    /// kept until the table changes; without the data source at hand, or for a query's
+    /// results, they are worked out over the rows shown.
+    fn aggregate_values(&mut self, data_source: Option<&DataSource>) -> Result<HashMap<String, String>> {
+        let regex = regex::Regex::new(r"^(sum|mean|count|min|max)\(([^)]+)\)$").unwrap();
+        let table = self.current_table().filter(|_| self.current_query.is_none()).map(|t| t.to_string());
+        let locale = self.format.number_locale;
+        let expressions: Vec<String> = self
+            .computed_columns
+            .iter()
+            .flat_map(|col| match &col.column_type {
+                ComputedColumnType::Aggregate(_) => vec![col.expression.clone()],
+                ComputedColumnType::MixedOperation(_, aggregates) => aggregates.clone(),
+                ComputedColumnType::RowOperation(_) | ComputedColumnType::Window(_) => Vec::new(),
+            })
+            .collect();
+
+        let mut values = HashMap::new();
+        for expression in expressions {
+            let Some(captures) = regex.captures(&expression) else {
+                continue;
+            };
+            let (func, column) = (&captures[1], captures[2].trim());
+            let key = table.clone().map(|table| (table, expression.clone(), self.filters.clone()));
+            let cached = key.as_ref().and_then(|key| self.aggregates.iter().find(|(cached, _)| cached == key));
+            let value = match (cached, key, data_source) {
+                (Some((_, value)), _, _) => *value,
+                (None, Some(key), Some(data_source)) => {
+                    let value = data_source.column_aggregate(&key.0, column, func, &self.filters, locale)?;
+                    self.aggregates.push((key, value));
+                    value
+                }
+                _ => {
+                    let Some(data) = &self.current_data else {
+                        continue;
+                    };
+                    let idx = data
+                        .columns
+                        .iter()
+                        .position(|col| col == column)
+                        .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", column))?;
+                    aggregate(func, data.rows.iter().filter_map(|row| row.get(idx)), locale)?
+                }
+            };
+            values.insert(expression, format_number(value));
+        }
+        Ok(values)
+    }
+
+    /// Work out the window computed columns, such as `cumsum(Amount)`, for the page. Those of
+    /// a table run over all of its rows passing the filters in the order shown, so a running
+    /// total goes on across pages; those of a query's results run over the rows shown.
+    /// Without the data source at hand, the values worked out last are kept.
+    fn compute_window_values(&mut self, data_source: Option<&DataSource>) -> Result<()> {
+        let locale = self.format.number_locale;
+        let page = self.current_table().filter(|_| self.current_query.is_none()).map(|table| PageKey {
+            table: table.to_string(),
+            offset: self.data_offset,
+            limit: self.page_size,
+            sort: self.view_state.sort.clone(),
+            filters: self.filters.clone(),
+        });
+        let mut values = HashMap::new();
+        for column in &self.computed_columns {
+            if !matches!(column.column_type, ComputedColumnType::Window(_)) {
+                continue;
+            }
+            let Some(window) = WindowSpec::parse(&column.expression).transpose()? else {
+                continue;
+            };
+            let computed = match (&page, data_source, self.window_values.remove(&column.expression)) {
+                (Some(page), Some(data_source), _) => data_source.window_column(page, &window, locale)?,
+                (_, None, Some(kept)) => kept,
+                _ => {
+                    let Some(data) = &self.current_data else {
+                        continue;
+                    };
+                    let idx = data
+                        .columns
+                        .iter()
+                        .position(|col| *col == window.column)
+                        .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", window.column))?;
+                    window.compute(data.rows.iter().map(|row| row.get(idx).unwrap_or(&Value::Null)), locale)
+                }
+            };
+            values.insert(column.expression.clone(), computed);
+        }
+        self.window_values = values;
+        Ok(())
+    }
+
+    fn apply_computed_columns(&mut self, data_source: &DataSource) -> Result<()> {
+        let aggregates = self.aggregate_values(Some(data_source))?;
+        self.compute_window_values(Some(data_source))?;
+        self.strip_computed_columns();
+        if let Some(data) = &mut self.current_data {
+            Self::push_computed_columns(data, &self.computed_columns, &aggregates, &self.window_values, self.format.number_locale)?;
+        }
+        Ok(())
+    }
+
+    /// Add the computed columns after the columns of `data`, with the aggregates and the
+    /// window values (one per row, in order) they use
+    fn push_computed_columns(
+        data: &mut QueryResult,
+        computed_columns: &[ComputedColumn],
+        aggregates: &HashMap<String, String>,
+        window_values: &HashMap<String, Vec<Value>>,
+        locale: NumberLocale,
+    ) -> Result<()> {
+        for computed_col in computed_columns {
+            data.columns.push(computed_col.name.clone());
+
+            match &computed_col.column_type {
+                ComputedColumnType::Window(_) => {
+                    let values = window_values.get(&computed_col.expression);
+                    for (idx, row) in data.rows.iter_mut().enumerate() {
+                        row.push(values.and_then(|values| values.get(idx)).cloned().unwrap_or(Value::Null));
+                    }
+                }
+                ComputedColumnType::Aggregate(_) => {
+                    let value = Self::aggregate_value(aggregates, &computed_col.expression)?;
+                    for row in &mut data.rows {
+                        row.push(Value::from(value.as_str()));
+                    }
+                }
+                ComputedColumnType::RowOperation(columns_used) => {
+                    let mut computed_values = Vec::new();
+                    for row in &data.rows {
+                        let value = Self::compute_row_operation_static(
+                            data,
+                            row,
+                            &computed_col.expression,
+                            columns_used,
+                            locale,
+                        )?;
+                        computed_values.push(value);
+                    }
+
+                    for (row, value) in data.rows.iter_mut().zip(computed_values) {
+                        row.push(Value::from(value));
+                    }
+                }
+                ComputedColumnType::MixedOperation(columns_used, aggregate_expressions) => {
+                    let mut computed_values = Vec::new();
+                    for row in &data.rows {
+                        let value = Self::compute_mixed_operation_static(
+                            data,
+                            row,
+                            &computed_col.expression,
+                            columns_used,
+                            aggregate_expressions,
+                            aggregates,
+                            locale,
+                        )?;
+                        computed_values.push(value);
+                    }
+
+                    for (row, value) in data.rows.iter_mut().zip(computed_values) {
+                        row.push(Value::from(value));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn aggregate_value(aggregates: &HashMap<String, String>, expression: &str) -> Result<String> {
+        aggregates
+            .get(expression)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Invalid aggregate expression: {}", expression))
+    }
+
+    fn compute_row_operation_static(
+        data: &QueryResult,
+        row: &[Value],
+        expression: &str,
+        columns_used: &[String],
+        locale: NumberLocale,
+    ) -> Result<String> {
+        let mut expr = expression.to_string();
+
+        // Replace column names with their values
+        for col_name in columns_used {
+            if let Some(col_idx) = data.columns.iter().position(|col| col == col_name) {
+                if col_idx < row.len() {
+                    let value = row[col_idx].as_number(locale).unwrap_or(0.0);
+                    expr = expr.replace(col_name, &value.to_string());
+                }
+            }
+        }
+
+        // Simple expression evaluator for basic math operations
+        Self::evaluate_expression_static(&expr)
+    }
+
+    fn compute_mixed_operation_static(
+        data: &QueryResult,
+        row: &[Value],
+        expression: &str,
+        columns_used: &[String],
+        aggregate_expressions: &[String],
+        aggregates: &HashMap<String, String>,
+        locale: NumberLocale,
+    ) -> Result<String> {
+        let mut expr = expression.to_string();
+
+        // First, replace aggregate expressions with their computed values
+        for agg_expr in aggregate_expressions {
+            if let Some(agg_value) = aggregates.get(agg_expr) {
+                expr = expr.replace(agg_expr, agg_value);
+            }
+        }
+
+        // Then, replace column names with their values from the current row
+        for col_name in columns_used {
+            if let Some(col_idx) = data.columns.iter().position(|col| col == col_name) {
+                if col_idx < row.len() {
+                    let value = row[col_idx].as_number(locale).unwrap_or(0.0);
+                    expr = expr.replace(col_name, &value.to_string());
+                }
+            }
+        }
+
+        // Finally, evaluate the expression
+        Self::evaluate_expression_static(&expr)
+    }
+
+    fn evaluate_expression_static(expr: &str) -> Result<String> {
+        // Simple evaluator for basic arithmetic with proper operator precedence
+        let expr = expr.replace(" ", "");
+
+        // Handle parentheses first
+        if let Some(start) = expr.rfind('(') {
+            if let Some(end) = expr[start..].find(')') {
+                let inner = &expr[start + 1..start + end];
+                let inner_result = Self::evaluate_expression_static(inner)?;
+                let new_expr = format!(
+                    "{}{}{}",
+                    &expr[..start],
+                    inner_result,
+                    &expr[start + end + 1..]
+                );
+                return Self::evaluate_expression_static(&new_expr);
+            }
+        }
+
+        // Handle multiplication/division (higher precedence)
+        if let Some(pos) = expr.rfind('*') {
+            let left = Self::evaluate_expression_static(&expr[..pos])?;
+            let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
+            let result = left.parse::<f64>()? * right.parse::<f64>()?;
+            return Ok(format_number(result));
+        }
+
+        if let Some(pos) = expr.rfind('/') {
+            let left = Self::evaluate_expression_static(&expr[..pos])?;
+            let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
+            let right_val = right.parse::<f64>()?;
+            if right_val == 0.0 {
+                return Err(anyhow::anyhow!("Division by zero"));
+            }
+            let result = left.parse::<f64>()? / right_val;
+            return Ok(format_number(result));
+        }
+
+        // Handle addition/subtraction (lower precedence)
+        if let Some(pos) = expr.rfind('+') {
+            let left = Self::evaluate_expression_static(&expr[..pos])?;
+            let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
+            let result = left.parse::<f64>()? + right.parse::<f64>()?;
+            return Ok(format_number(result));
+        }
+
+        if let Some(pos) = expr.rfind('-') {
+            // Make sure this isn't a negative number at the start
+            if pos > 0 {
+                let left = Self::evaluate_expression_static(&expr[..pos])?;
+                let right = Self::evaluate_expression_static(&expr[pos + 1..])?;
+                let result = left.parse::<f64>()? - right.parse::<f64>()?;
+                return Ok(format_number(result));
+            }
+        }
+
+        // Base case - just a number
+        if let Ok(num) = expr.parse::<f64>() {
+            Ok(format_number(num))
+        } else {
+            Ok(expr.to_string())
+        }
+    }
+
+    /// Take the computed columns off the page
+    fn strip_computed_columns(&mut self) {
+        let Some(data) = &mut self.current_data else {
+            return;
+        };
+        let mut cols_to_remove = Vec::new();
+        for computed_col in &self.computed_columns {
+            if let Some(pos) = data.columns.iter().position(|x| x == &computed_col.name) {
+                cols_to_remove.push(pos);
+            }
+        }
+
+        // Remove in reverse order to maintain indices
+        cols_to_remove.sort_by(|a, b| b.cmp(a));
+        for pos in cols_to_remove {
+            data.columns.remove(pos);
+            for row in &mut data.rows {
+                if pos < row.len() {
+                    row.remove(pos);
+                }
+            }
+        }
+    }
+
+    fn refresh_computed_columns(&mut self) -> Result<()> {
+        let aggregates = self.aggregate_values(None)?;
+        self.compute_window_values(None)?;
+        self.strip_computed_columns();
+        if let Some(data) = &mut self.current_data {
+            Self::push_computed_columns(data, &self.computed_columns, &aggregates, &self.window_values, self.format.number_locale)?;
+        }
+        Ok(())
+    }
+}
+
+/// The rows filling the table area of a terminal `height` rows high, as `render_ui` lays it
+/// out: the header and footer, the query result tabs when shown, and the table's borders
+/// and column header take the rest
+pub fn page_rows(app: &AppState, height: u16) -> usize {
+    let result_tabs = u16::from(app.database_query.is_some());
+    usize::from(height.saturating_sub(3 + 3 + result_tabs + 3)).max(1)
+}
+
+/// Draw the app of the active file; `tabs` names every open file, shown as numbered tabs
+/// in the header when there is more than one
+pub fn render_ui(frame: &mut Frame, app: &AppState, theme: &Theme, tabs: &[String], active_tab: usize) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Body
+            Constraint::Length(3), // Footer
+        ])
+        .split(frame.area());
+
+    // Header
+    let title = if tabs.len() > 1 {
+        let mut spans = vec![Span::raw("SQLite Browser - ")];
+        for (idx, tab) in tabs.iter().enumerate() {
+            if idx > 0 {
+                spans.push(Span::styled(theme.text(" │ ").into_owned(), Style::default().fg(theme.border)));
+            }
+            let style = if idx == active_tab {
+                Style::default().fg(theme.selected_text).bg(theme.selected_bg)
+            } else {
+                Style::default().fg(theme.border)
+            };
+            spans.push(Span::styled(format!(" {} {} ", idx + 1, tab), style));
+        }
+        Line::from(spans)
+    } else {
+        Line::from(format!(
+            "SQLite Browser - {}",
+            std::path::Path::new(&app.db_path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("Unknown")
+        ))
+    };
+    let header = Paragraph::new(title)
+    .style(
+        Style::default()
+            .fg(theme.header)
+            .add_modifier(Modifier::BOLD),
+    )
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .border_style(Style::default().fg(theme.header)),
+    );
+    frame.render_widget(header, chunks[0]);
+
+    // Body
+    let body_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(25), // Sidebar
+            Constraint::Min(0),     // Main area
+        ])
+        .split(chunks[1]);
+
+    // Render sidebar (tables list)
+    render_sidebar(frame, app, body_chunks[0], theme);
+
+    // Render main area, behind tabs once a whole-database query has results
+    let main_area = if app.database_query.is_some() {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(body_chunks[1]);
+        render_result_tabs(frame, app, areas[0], theme);
+        areas[1]
+    } else {
+        body_chunks[1]
+    };
+    if results_tab_shown(app) {
+        render_database_results(frame, app, main_area, theme);
+    } else if app.navigation_mode == NavigationMode::Table && app.stats_worker.is_some() {
+        let areas = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(30)])
+            .split(main_area);
+        render_main_area(frame, app, areas[0], theme);
+        render_table_overview(frame, app, areas[1], theme);
+    } else {
+        render_main_area(frame, app, main_area, theme);
+    }
+
+    // Query input overlay
+    if app.navigation_mode == NavigationMode::Query {
+        render_query_input(frame, app, theme);
+    }
+
+    // Edit input overlay
+    if app.navigation_mode == NavigationMode::Edit {
+        render_edit_input(frame, app, theme);
+    }
+
+    // Computed column input overlay
+    if app.navigation_mode == NavigationMode::ComputedColumn {
+        render_computed_column_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ComputedColumns {
+        render_computed_column_manager(frame, app, theme);
+    }
+
+    // Diff prompt and view overlays
+    if app.navigation_mode == NavigationMode::DiffInput {
+        render_diff_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::Diff {
+        render_diff_view(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::Profile {
+        render_profile(frame, app, theme);
+    }
+
+    // Join prompt and view overlays
+    if app.navigation_mode == NavigationMode::JoinInput {
+        render_join_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::Join {
+        render_join_view(frame, app, theme);
+    }
+
+    // Global search prompt and results overlays
+    if app.navigation_mode == NavigationMode::GlobalSearch {
+        render_global_search_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::SearchResults {
+        render_search_results(frame, app, theme);
+    }
+
+    // Pipe prompt and command output overlays
+    if app.navigation_mode == NavigationMode::PipeInput {
+        render_pipe_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::PipeOutput {
+        render_pipe_output(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ExportResults {
+        render_export_results(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ExportPath {
+        render_export_path(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ToolPicker {
+        render_tool_picker(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::BookmarkInput {
+        render_bookmark_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::Bookmarks {
+        render_bookmark_list(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::SchemaObjects {
+        render_schema_objects(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::TableSchema {
+        render_table_schema(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ObjectDefinition {
+        render_object_definition(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::Ddl {
+        render_ddl(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ConfirmSave {
+        render_confirm_save(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ConfirmRevert {
+        render_confirm_revert(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ConfirmFileSave {
+        render_confirm_file_save(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ConfirmPaste {
+        render_confirm_paste(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::FillDown {
+        render_fill_down(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::BulkEditInput {
+        render_bulk_edit_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::InsertForm {
+        render_insert_form(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::TextEdit {
+        render_text_editor(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ConfirmStatement {
+        render_confirm_statement(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::QueryParameters {
+        render_query_parameters(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::FilterInput {
+        render_filter_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::GotoRow {
+        render_goto_row(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::CellSearch {
+        render_cell_search(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ColumnManager {
+        render_column_manager(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ColumnEditInput {
+        render_column_edit_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ConfirmDropColumn {
+        render_confirm_drop_column(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::ColumnSummary {
+        match &app.column_summary {
+            Some(ColumnSummary::Stats(stats)) => render_column_stats(frame, app, stats, theme),
+            Some(ColumnSummary::Histogram(chart)) => render_histogram(frame, chart, theme),
+            Some(ColumnSummary::ValueCounts(counts)) => render_value_counts(frame, app, counts, theme),
+            None => {}
+        }
+    }
+
+    if app.navigation_mode == NavigationMode::AttachInput {
+        render_attach_input(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::QueryHistory {
+        render_query_input(frame, app, theme);
+        render_query_history(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::SavedQueryName {
+        render_saved_query_name(frame, app, theme);
+    }
+
+    if app.navigation_mode == NavigationMode::SavedQueries {
+        render_saved_queries(frame, app, theme);
+    }
+
+    // Help overlay
+    if app.show_help {
+        render_help(frame, theme);
+    }
+
+    // Detailed view overlay
+    if app.navigation_mode == NavigationMode::DetailedView {
+        render_detailed_view(frame, app, theme);
+    }
+
+    // Error display overlay
+    if app.navigation_mode == NavigationMode::ErrorDisplay {
+        render_error_display(frame, app, theme);
+    }
+
+    // Footer
+    render_footer(frame, app, chunks[2], theme);
+}
+
+pub fn render_start_screen(frame: &mut Frame, screen: &StartScreen, theme: &Theme) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Recent files
+            Constraint::Length(3), // Footer
+        ])
+        .split(frame.area());
+
+    let header = Paragraph::new("SQLite Browser - Recent files")
+        .style(
+            Style::default()
+                .fg(theme.header)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .border_style(Style::default().fg(theme.header)),
+        );
+    frame.render_widget(header, chunks[0]);
+
+    let rows: Vec<Row> = screen
+        .recent
+        .iter()
+        .map(|file| {
+            let path = std::path::Path::new(&file.path);
+            let (name, location) = if crate::postgres_database::is_connection_string(path) {
+                (file.path.clone(), "PostgreSQL".to_string())
+            } else if crate::libsql_database::is_libsql_url(path) {
+                (file.path.clone(), "libSQL".to_string())
+            } else if crate::html_table::is_web_url(path) {
+                (file.path.clone(), "Web".to_string())
+            } else {
+                (
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| file.path.clone()),
+                    path.parent()
+                        .map(|dir| dir.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                )
+            };
+            let opened = chrono::DateTime::from_timestamp(file.opened_at as i64, 0)
+                .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            Row::new(vec![
+                Cell::from(name).style(Style::default().fg(theme.detailed_view_value)),
+                Cell::from(location).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(opened).style(Style::default().fg(theme.number)),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Percentage(30),
+        Constraint::Min(20),
+        Constraint::Length(16),
+    ];
+
+    let header = Row::new(["File", "Location", "Opened"].map(|title| {
+        Cell::from(title).style(
+            Style::default()
+                .fg(theme.column_header)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Recent files ({})", screen.recent.len()))
+                .border_style(Style::default().fg(theme.border)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(screen.selected));
+    frame.render_stateful_widget(table, chunks[1], &mut table_state);
+
+    let footer = Paragraph::new(Line::from(Span::styled(
+        theme.text("↑↓ Navigate | Enter Open | q Quit"),
+        Style::default().fg(Color::DarkGray),
+    )))
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .border_style(Style::default().fg(theme.border)),
+    );
+    frame.render_widget(footer, chunks[2]);
+}
+
+/// The screen shown while a large file is read on the worker thread, with a bar of how
+/// much of it has been read; formats that aren't read as a stream only show the time spent
+pub fn render_loading_screen(
+    frame: &mut Frame,
+    location: &str,
+    read: u64,
+    total: Option<u64>,
+    elapsed: Duration,
+    theme: &Theme,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Progress bar
+            Constraint::Length(4), // Footer
+        ])
+        .split(frame.area());
+
+    let header = Paragraph::new(format!("SQLite Browser - {}", location))
+        .style(Style::default().fg(theme.header).add_modifier(Modifier::BOLD))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .border_style(Style::default().fg(theme.header)),
+        );
+    frame.render_widget(header, chunks[0]);
+
+    let body = Block::default()
+        .borders(Borders::ALL)
+        .border_set(theme.border_set())
+        .border_style(Style::default().fg(theme.border));
+    let inner = body.inner(chunks[1]);
+    frame.render_widget(body, chunks[1]);
+
+    let (status, ratio) = match total {
+        Some(total) if total > 0 && read > 0 => (
+            format!(
+                "Loading: {} of {} ({}%, {}s)",
+                crate::download::format_bytes(read),
+                crate::download::format_bytes(total),
+                read.min(total) * 100 / total,
+                elapsed.as_secs()
+            ),
+            Some(read.min(total) as f64 / total as f64),
+        ),
+        _ => (format!("Loading ({}s)", elapsed.as_secs()), None),
+    };
+    if let Some(ratio) = ratio.filter(|_| inner.height > 0 && inner.width > 4) {
+        let width = inner.width - 4;
+        let filled = (ratio * width as f64).round() as usize;
+        let (full, empty) = if theme.ascii { ("#", "-") } else { ("█", "░") };
+        let bar = Paragraph::new(format!("{}{}", full.repeat(filled), empty.repeat(width as usize - filled)))
+            .style(Style::default().fg(theme.status))
+            .alignment(Alignment::Center);
+        let row = Rect { y: inner.y + inner.height / 2, height: 1, ..inner };
+        frame.render_widget(bar, row);
+    }
+
+    let footer = Paragraph::new(vec![
+        Line::from(Span::styled(status, Style::default().fg(theme.status))),
+        Line::from(Span::styled(theme.text("ESC/Ctrl+C Cancel"), Style::default().fg(Color::DarkGray))),
+    ])
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .border_style(Style::default().fg(theme.border)),
+    );
+    frame.render_widget(footer, chunks[2]);
+}
+
+/// Progress of a file being downloaded from a URL before it opens
+pub fn render_download_screen(
+    frame: &mut Frame,
+    url: &str,
+    received: u64,
+    total: Option<u64>,
+    theme: &Theme,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Empty until the file opens
+            Constraint::Length(4), // Footer
+        ])
+        .split(frame.area());
+
+    let header = Paragraph::new(format!("SQLite Browser - {}", url))
+        .style(
+            Style::default()
+                .fg(theme.header)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .border_style(Style::default().fg(theme.header)),
+        );
+    frame.render_widget(header, chunks[0]);
+
+    let body = Block::default()
+        .borders(Borders::ALL)
+        .border_set(theme.border_set())
+        .border_style(Style::default().fg(theme.border));
+    frame.render_widget(body, chunks[1]);
+
+    let progress = match total {
+        Some(total) if total > 0 => format!(
+            "Downloading: {} of {} ({}%)",
+            crate::download::format_bytes(received),
+            crate::download::format_bytes(total),
+            received * 100 / total
+        ),
+        _ => format!("Downloading: {}", crate::download::format_bytes(received)),
+    };
+    let footer = Paragraph::new(vec![
+        Line::from(Span::styled(progress, Style::default().fg(theme.status))),
+        Line::from(Span::styled(
+            theme.text("Ctrl+C Cancel"),
+            Style::default().fg(Color::DarkGray),
+        )),
+    ])
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .border_style(Style::default().fg(theme.border)),
+    );
+    frame.render_widget(footer, chunks[2]);
+}
+
+fn render_sidebar(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
+    let border_style = if app.navigation_mode == NavigationMode::Table {
+        Style::default().fg(theme.selected_border)
+    } else {
+        Style::default().fg(theme.border)
+    };
+
+    let title_style = if app.navigation_mode == NavigationMode::Table {
+        Style::default()
+            .fg(theme.selected_border)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+            .fg(theme.border)
+            .add_modifier(Modifier::BOLD)
+    };
+
+    let data_path = crate::file_reader::decompressed_path(&app.db_path).to_string_lossy().to_string();
+    let sidebar_title = if [".xlsx", ".xlsm", ".xlsb", ".xls", ".ods"].iter().any(|ext| data_path.ends_with(ext)) {
+        "Sheets"
+    } else if [".csv", ".parquet", ".json", ".jsonl", ".ndjson", ".arrow", ".arrows", ".feather", ".ipc", ".dbf", ".dta", ".sav", ".zsav", ".sas7bdat"]
+        .iter()
+        .any(|ext| data_path.ends_with(ext))
+    {
+        "Data"
+    } else {
+        "Tables"
+    };
+
+    // With attached databases, tables are grouped under a header per database and listed
+    // without their schema prefix
+    let schema_of = |table: &str| {
+        app.attached_databases
+            .iter()
+            .find(|schema| {
+                table
+                    .strip_prefix(schema.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .cloned()
+    };
+    let mut items: Vec<Line> = Vec::new();
+    let mut current_group = None;
+    let mut in_section = false;
+    for (n, entry) in app.sidebar_entries().into_iter().enumerate() {
+        let under_cursor = app.navigation_mode == NavigationMode::Table && app.sidebar_cursor == Some(entry);
+        match entry {
+            SidebarEntry::Table(i) => {
+                let table = &app.tables[i];
+                if !app.attached_databases.is_empty() && !in_section {
+                    let group = schema_of(table);
+                    if n == 0 || group != current_group {
+                        let label = group.as_deref().unwrap_or("main");
+                        items.push(Line::from(Span::styled(
+                            theme.text(&format!("── {} ──", label)).into_owned(),
+                            Style::default().fg(theme.border).add_modifier(Modifier::BOLD),
+                        )));
+                        current_group = group;
+                    }
+                }
+                let name = match &current_group {
+                    Some(schema) if !in_section => &table[schema.len() + 1..],
+                    _ => table.as_str(),
+                };
+                items.push(sidebar_table_line(app, i, table, name, theme));
+            }
+            SidebarEntry::Section(kind) => {
+                in_section = true;
+                let (_, label) = SIDEBAR_SECTIONS.iter().find(|(k, _)| *k == kind).copied().unwrap_or((kind, kind));
+                let count = app.sidebar_objects.iter().filter(|o| o.kind == kind).count();
+                let arrow = if app.collapsed_sections.contains(&kind) { "▸" } else { "▾" };
+                let style = if under_cursor {
+                    Style::default().fg(theme.selected_border).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+                } else {
+                    Style::default().fg(theme.border).add_modifier(Modifier::BOLD)
+                };
+                items.push(Line::from(Span::styled(
+                    theme.text(&format!("{} {} ({})", arrow, label, count)).into_owned(),
+                    style,
+                )));
+            }
+            SidebarEntry::Object(j) => {
+                let object = &app.sidebar_objects[j];
+                let line = if under_cursor {
+                    Span::styled(
+                        theme.text(&format!("▶ {}", object.name)).into_owned(),
+                        Style::default().fg(theme.selected_border).add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Span::styled(format!("  {}", object.name), Style::default().fg(Color::DarkGray))
+                };
+                items.push(Line::from(line));
+            }
+        }
+    }
+
+    let list = Paragraph::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .border_style(border_style)
+            .title(Span::styled(sidebar_title, title_style)),
+    );
+
+    frame.render_widget(list, area);
+}
+
+/// Row count, size and ANALYZE state of the selected table, next to the table list
+fn render_table_overview(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
+    let field = Style::default().fg(theme.detailed_view_field).add_modifier(Modifier::BOLD);
+    let value = Style::default().fg(theme.text);
+    let row = |label: &str, text: String| {
+        Line::from(vec![Span::styled(format!("{:<10}", label), field), Span::styled(text, value)])
+    };
+
+    let table = app.current_table().unwrap_or_default();
+    let lines = match app.table_stats.get(table) {
+        Some(Ok(stats)) => vec![
+            row("Rows", stats.rows.to_string()),
+            row("Columns", stats.columns.to_string()),
+            row(
+                "Size",
+                stats.size_bytes.map(format_bytes).unwrap_or_else(|| "unknown (no dbstat)".to_string()),
+            ),
+            row(
+                "Analyzed",
+                match stats.analyzed_rows {
+                    Some(rows) => format!("~{} rows", rows),
+                    None => "never".to_string(),
+                },
+            ),
+        ],
+        Some(Err(e)) => vec![Line::from(Span::styled(e.clone(), Style::default().fg(theme.error)))],
+        None => vec![Line::from(Span::styled(
+            theme.text("Counting…").into_owned(),
+            Style::default().fg(Color::DarkGray),
+        ))],
+    };
+
+    let overview = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .border_style(Style::default().fg(theme.border))
+                .title("Overview"),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: true });
+    frame.render_widget(overview, area);
+}
+
+/// One table in the sidebar; `name` is the table as shown, without its schema in a group
+fn sidebar_table_line<'a>(app: &AppState, idx: usize, table: &str, name: &str, theme: &Theme) -> Line<'a> {
+    let star = if app.favorite_tables.iter().any(|t| t == table) { "★ " } else { "" };
+    // Sheets being read show a spinner after their name
+    let name = match app.loading_sheets.iter().find(|(sheet, _)| sheet == table) {
+        Some((_, started)) => format!("{} {}", name, spinner_frame(*started, theme)),
+        None => name.to_string(),
+    };
+    if idx == app.selected_table_idx {
+        if app.navigation_mode == NavigationMode::Table && app.sidebar_cursor.is_none() {
+            Line::from(Span::styled(
+                theme.text(&format!("▶ {}{}", star, name)).into_owned(),
+                Style::default()
+                    .fg(theme.selected_border)
+                    .add_modifier(Modifier::BOLD),
+            ))
+        } else {
+            Line::from(Span::styled(
+                theme.text(&format!("▶ {}{}", star, name)).into_owned(),
+                Style::default().fg(Color::DarkGray),
+            ))
+        }
+    } else {
+        Line::from(Span::styled(
+            theme.text(&format!("  {}{}", star, name)).into_owned(),
+            Style::default().fg(theme.text),
+        ))
+    }
+}
+
+/// The frame of a spinner started at `started`, turning every 100ms
+fn spinner_frame(started: Instant, theme: &Theme) -> &'static str {
+    const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    const ASCII_SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+    let frames: &[&str] = if theme.ascii { &ASCII_SPINNER } else { &SPINNER };
+    frames[(started.elapsed().as_millis() / 100) as usize % frames.len()]
+}
+
+fn render_main_area(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
+    if app.tables.is_empty() || app.selected_table_idx >= app.tables.len() {
+        let placeholder = Paragraph::new("Select a table to view its contents")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(theme.border_set())
+                    .title("Table Contents")
+                    .border_style(Style::default().fg(theme.border)),
+            );
+        frame.render_widget(placeholder, area);
+        return;
+    }
+
+    let border_style = match app.navigation_mode {
+        NavigationMode::Data => Style::default().fg(theme.selected_border),
+        NavigationMode::Edit => Style::default().fg(theme.edit_border),
+        _ => Style::default().fg(theme.border),
+    };
+
+    let title_style = match app.navigation_mode {
+        NavigationMode::Data => Style::default()
+            .fg(theme.selected_border)
+            .add_modifier(Modifier::BOLD),
+        NavigationMode::Edit => Style::default()
+            .fg(theme.edit_border)
+            .add_modifier(Modifier::BOLD),
+        _ => Style::default()
+            .fg(theme.border)
+            .add_modifier(Modifier::BOLD),
+    };
+
+    if let Some(data) = &app.current_data {
+        let table_name = &app.tables[app.selected_table_idx];
+
+        // Calculate pagination info
+        let current_page = (app.data_offset / app.page_size) + 1;
+        let total_pages = (data.total_rows + app.page_size - 1) / app.page_size.max(1);
+        let start_row = app.data_offset + 1;
+        let end_row = (app.data_offset + data.rows.len()).min(data.total_rows);
+
+        // Tables left by following foreign keys lead up to this one
+        let breadcrumb: Vec<&str> = app
+            .followed_keys
+            .iter()
+            .map(|key| key.table.as_str())
+            .chain([table_name.as_str()])
+            .collect();
+        let total = if app.counting_rows { theme.text("counting…").into_owned() } else { data.total_rows.to_string() };
+        let mut title = format!(
+            "Table: {} | Total: {} rows | Columns: {}",
+            theme.text(&breadcrumb.join(" → ")),
+            total,
+            data.columns.len()
+        );
+
+        if app.counting_rows {
+            title.push_str(&format!(" | Page {} | Rows {}-{}", current_page, start_row, end_row));
+        } else if total_pages > 1 {
+            title.push_str(&format!(
+                " | Page {}/{} | Rows {}-{}",
+                current_page, total_pages, start_row, end_row
+            ));
+        }
+
+        if !app.filters.is_empty() {
+            let filters: Vec<String> = app
+                .filters
+                .iter()
+                .map(|f| format!("{} {}", f.column, f.expression()))
+                .collect();
+            title.push_str(&format!(" | Filter: {}", filters.join(", ")));
+        }
+
+        if app.current_query.is_some() {
+            title.push_str(" | Custom Query");
+        } else if let Some(sort) = &app.view_state.sort {
+            title.push_str(&format!(
+                " | Sorted by {} {}",
+                sort.column,
+                theme.text(if sort.descending { "↓" } else { "↑" })
+            ));
+        }
+
+        if app.data_modified {
+            title.push_str(" | *MODIFIED*");
+        }
+
+        if let Some(term) = &app.cell_search {
+            title.push_str(&format!(" | Search: {}", term));
+        }
+        let search = app.cell_search.as_deref().and_then(|term| SearchPattern::parse(term).ok());
+
+        // Create table rows (skip rowid and hidden columns for display, pinned ones first)
+        let visible_cols = app.display_columns();
+        let pinned: Vec<&String> = visible_cols
+            .iter()
+            .map(|&idx| &data.columns[idx])
+            .filter(|name| app.view_state.pinned_columns.contains(name))
+            .collect();
+        let hidden_count = app
+            .view_state
+            .hidden_columns
+            .iter()
+            .filter(|name| data.columns.contains(name))
+            .count();
+        if hidden_count > 0 {
+            title.push_str(&format!(" | {} hidden", hidden_count));
+        }
+
+        // Computed columns are starred, typed columns show their type
+        let headers: Vec<(String, bool)> = visible_cols.iter().map(|&idx| app.column_header(&data.columns[idx])).collect();
+        // A bulk edit being previewed shows the cells it changes as old → new
+        let preview: HashMap<(usize, usize), &Value> = app
+            .bulk_preview
+            .iter()
+            .filter(|_| app.navigation_mode == NavigationMode::BulkPreview)
+            .flat_map(|preview| preview.cells.iter().map(|(row, col, value)| ((*row, *col), value)))
+            .collect();
+        if !preview.is_empty() {
+            title.push_str(&format!(" | Preview: {} cells change", preview.len()));
+        }
+        // Rows of the visual selection (V), also while exporting them
+        let visual = match app.visual_anchor {
+            Some(anchor) => anchor.min(app.selected_row_idx)..anchor.max(app.selected_row_idx) + 1,
+            None => 0..0,
+        };
+        if !visual.is_empty() {
+            title.push_str(&format!(" | {} rows selected", visual.len()));
+        }
+        // Columns are as wide as their header and the values on the page, measured in
+        // terminal columns, unless resized with `<`/`>`; the ones that don't fit are scrolled
+        // to, keeping the selected column in view. Pinned columns stay put on the left. A
+        // column's cells are only formatted once it is found to be on screen, so a table of
+        // hundreds of columns draws as fast as a narrow one.
+        let format_column = |j: usize| -> (u16, Vec<String>) {
+            let idx = visible_cols[j];
+            let set_width = app.view_state.column_widths.get(&data.columns[idx]).copied();
+            let cells: Vec<String> = data
+                .rows
+                .iter()
+                .enumerate()
+                .map(|(i, row_data)| {
+                    let mut cell = row_data.get(idx).map(|c| format_value(c, &app.format)).unwrap_or_default();
+                    if let Some(value) = preview.get(&(i, idx)) {
+                        cell = theme.text(&format!("{} → {}", cell, format_value(value, &app.format))).into_owned();
+                    }
+                    truncate_to_width(&cell, set_width.map_or(40, usize::from))
+                })
+                .collect();
+            let width = set_width.unwrap_or_else(|| fitted_width(&headers[j].0, cells.iter().map(|cell| cell.width())));
+            (width, cells)
+        };
+        let mut columns: Vec<Option<(u16, Vec<String>)>> = vec![None; visible_cols.len()];
+        let mut column_width = |j: usize| columns[j].get_or_insert_with(|| format_column(j)).0;
+        let pinned_width: u16 = (0..pinned.len()).map(|j| column_width(j) + 1).sum();
+        let selected_display_col = visible_cols
+            .iter()
+            .position(|&idx| idx == app.selected_col_idx)
+            .and_then(|pos| pos.checked_sub(pinned.len()))
+            .unwrap_or(0);
+        let scrolled = column_window(
+            visible_cols.len() - pinned.len(),
+            |j| column_width(pinned.len() + j),
+            selected_display_col,
+            area.width.saturating_sub(2).saturating_sub(pinned_width),
+        );
+        if scrolled.len() + pinned.len() < visible_cols.len() {
+            title.push_str(&format!(
+                " | Showing columns {}-{} of {}",
+                pinned.len() + scrolled.start + 1,
+                pinned.len() + scrolled.end,
+                visible_cols.len()
+            ));
+        }
+        if !pinned.is_empty() {
+            let names: Vec<&str> = pinned.iter().map(|name| name.as_str()).collect();
+            title.push_str(&format!(" | Pinned: {}", names.join(", ")));
+        }
+        let window: Vec<usize> = (0..pinned.len())
+            .chain(scrolled.start + pinned.len()..scrolled.end + pinned.len())
+            .collect();
+        let window_columns: Vec<(u16, Vec<String>)> =
+            window.iter().map(|&j| columns[j].take().unwrap_or_else(|| format_column(j))).collect();
+
+        let rows: Vec<Row> = data
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row_data)| {
+                let cells: Vec<Cell> = window
+                    .iter()
+                    .zip(&window_columns)
+                    .map(|(&j, (_, cells))| (visible_cols[j], cells[i].clone()))
+                    .map(|(actual_col_idx, content)| {
+                        // Numbers line up on the right, whether stored as numbers or in
+                        // typed numeric columns
+                        let numeric = row_data.get(actual_col_idx).is_some_and(|cell| cell.is_numeric())
+                            || data
+                                .columns
+                                .get(actual_col_idx)
+                                .and_then(|column| app.column_types.get(column))
+                                .is_some_and(|column_type| column_type.is_numeric());
+                        let content = if numeric {
+                            Line::from(content).alignment(Alignment::Right)
+                        } else {
+                            Line::from(content)
+                        };
+
+                        // Highlight selected cell in Edit mode or Data mode
+                        if (app.navigation_mode == NavigationMode::Edit
+                            || app.navigation_mode == NavigationMode::Data
+                            || app.navigation_mode == NavigationMode::Visual)
+                            && i == app.selected_row_idx
+                            && actual_col_idx == app.selected_col_idx
+                        {
+                            if app.navigation_mode == NavigationMode::Edit {
+                                Cell::from(content).style(
+                                    Style::default()
+                                        .fg(theme.edit_text)
+                                        .bg(theme.edit_bg)
+                                        .add_modifier(Modifier::BOLD),
+                                )
+                            } else {
+                                Cell::from(content).style(
+                                    Style::default()
+                                        .fg(theme.selected_text)
+                                        .bg(theme.selected_bg)
+                                        .add_modifier(Modifier::BOLD),
+                                )
+                            }
+                        } else if visual.contains(&i) {
+                            Cell::from(content).style(Style::default().fg(theme.selected_text).bg(theme.selected_bg))
+                        } else if preview.contains_key(&(i, actual_col_idx)) {
+                            Cell::from(content).style(Style::default().fg(theme.edit_text).bg(theme.edit_bg))
+                        } else if search.as_ref().is_some_and(|pattern| pattern.matches(&row_data[actual_col_idx].as_text())) {
+                            Cell::from(content)
+                                .style(Style::default().fg(theme.text).add_modifier(Modifier::REVERSED))
+                        } else {
+                            Cell::from(content).style(Style::default().fg(theme.text))
+                        }
+                    })
+                    .collect();
+
+                Row::new(cells)
+            })
+            .collect();
+
+        let widths: Vec<Constraint> = window_columns.iter().map(|(width, _)| Constraint::Length(*width)).collect();
+
+        let table = Table::new(rows, widths)
+            .header(Row::new(
+                window
+                    .iter()
+                    .map(|&j| &headers[j])
+                    .map(|(header_text, is_computed)| {
+                        let color = if *is_computed { theme.number } else { theme.column_header };
+                        Cell::from(header_text.clone())
+                            .style(Style::default().fg(color).add_modifier(Modifier::BOLD))
+                    })
+                    .collect::<Vec<_>>(),
+            ))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(theme.border_set())
+                    .title(Span::styled(title, title_style))
+                    .border_style(border_style),
+            )
+            .style(Style::default().fg(theme.text));
+
+        frame.render_widget(table, area);
+    } else {
+        let table = app.current_table().unwrap_or_default();
+        let text = match app.loading_sheets.iter().find(|(sheet, _)| sheet == table) {
+            Some((_, started)) => format!(
+                "{} Reading sheet '{}' ({}s)",
+                spinner_frame(*started, theme),
+                table,
+                started.elapsed().as_secs()
+            ),
+            None => "Loading...".to_string(),
+        };
+        let placeholder = Paragraph::new(text)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(theme.border_set())
+                    .title("Table Contents")
+                    .border_style(border_style),
+            );
+        frame.render_widget(placeholder, area);
+    }
+}
+
+/// Text typed over a cell, read as the cell's own type; unchanged text keeps the cell as is
+fn parse_edited_cell(cell: &Value, input: &str) -> Option<Value> {
+    if *cell == *input {
+        return Some(cell.clone());
+    }
+    cell.parse_like(input)
+}
+
+/// The cell stored for an edit, kept as text when it doesn't fit the cell's type
+fn edited_cell(cell: &Value, input: &str) -> Value {
+    parse_edited_cell(cell, input).unwrap_or_else(|| Value::Text(input.to_string()))
+}
+
+/// The row (0 for the first) typed at the goto prompt: a row number counted from 1, or a
+/// percentage of the `total` rows such as `50%`; numbers past the end go to the last row
+fn goto_row(input: &str, total: usize) -> Result<usize> {
+    let input = input.trim().replace([',', '_', ' '], "");
+    let last = total.saturating_sub(1);
+    if let Some(percent) = input.strip_suffix('%') {
+        return match percent.parse::<f64>() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(((total as f64 * percent / 100.0) as usize).min(last)),
+            _ => Err(anyhow::anyhow!("'{}' is not a percentage from 0% to 100%", input)),
+        };
+    }
+    input
+        .parse::<usize>()
+        .map(|row| row.saturating_sub(1).min(last))
+        .map_err(|_| anyhow::anyhow!("'{}' is not a row number or a percentage such as 50%", input))
+}
+
+/// Drop the rowid column that editable data carries
+fn without_rowid(mut columns: Vec<String>, mut rows: Vec<Vec<Value>>) -> (Vec<String>, Vec<Vec<Value>>) {
+    if let Some(rowid_idx) = columns.iter().position(|c| c == "rowid") {
+        columns.remove(rowid_idx);
+        for row in &mut rows {
+            if rowid_idx < row.len() {
+                row.remove(rowid_idx);
+            }
+        }
+    }
+    (columns, rows)
+}
+
+/// Whether the result tab is in front, including the prompts and errors opened from it
+fn results_tab_shown(app: &AppState) -> bool {
+    if app.database_query.is_none() {
+        return false;
+    }
+    match app.navigation_mode {
+        NavigationMode::DatabaseResults => true,
+        NavigationMode::Query | NavigationMode::QueryParameters | NavigationMode::QueryHistory => {
+            app.query_over_database
+        }
+        NavigationMode::ErrorDisplay => app.previous_navigation_mode == NavigationMode::DatabaseResults,
+        NavigationMode::ExportResults | NavigationMode::ExportPath | NavigationMode::PipeOutput => {
+            app.results_origin == NavigationMode::DatabaseResults
+        }
+        _ => false,
+    }
+}
+
+fn render_result_tabs(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
+    let results_shown = results_tab_shown(app);
+    let style = |active: bool| {
+        if active {
+            Style::default().fg(theme.selected_text).bg(theme.selected_bg).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.border)
+        }
+    };
+    let table = match app.current_table() {
+        Some(table) => format!(" Table: {} ", table),
+        None => " Table ".to_string(),
+    };
+    let tabs = Line::from(vec![
+        Span::styled(table, style(!results_shown)),
+        Span::styled(theme.text(" │ ").into_owned(), Style::default().fg(theme.border)),
+        Span::styled(" Database query (R) ", style(results_shown)),
+    ]);
+    frame.render_widget(Paragraph::new(tabs), area);
+}
+
+fn render_database_results(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
+    let Some(results) = &app.database_query else {
+        return;
+    };
+    let data = &results.data;
+    let active = app.navigation_mode == NavigationMode::DatabaseResults;
+
+    let mut title = format!("Database query | Total: {} rows | Columns: {}", data.total_rows, data.columns.len());
+    let total_pages = data.total_rows.div_ceil(app.page_size.max(1));
+    if total_pages > 1 {
+        title.push_str(&format!(
+            " | Page {}/{} | Rows {}-{}",
+            results.offset / app.page_size.max(1) + 1,
+            total_pages,
+            results.offset + 1,
+            results.offset + data.rows.len()
+        ));
+    }
+
+    let rows: Vec<Row> = data
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let cells: Vec<Cell> = row
+                .iter()
+                .enumerate()
+                .map(|(j, cell)| {
+                    let cell = format_value(cell, &app.format);
+                    let content = truncate_to_width(&cell, 40);
+                    let style = if active && i == results.selected_row && j == results.selected_col {
+                        Style::default()
+                            .fg(theme.selected_text)
+                            .bg(theme.selected_bg)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(theme.text)
+                    };
+                    Cell::from(content).style(style)
+                })
+                .collect();
+            Row::new(cells)
+        })
+        .collect();
+
+    let widths: Vec<Constraint> = data
+        .columns
+        .iter()
+        .map(|_| Constraint::Percentage(100 / data.columns.len().max(1) as u16))
+        .collect();
+    let header = Row::new(
+        data.columns
+            .iter()
+            .map(|column| {
+                Cell::from(column.clone())
+                    .style(Style::default().fg(theme.column_header).add_modifier(Modifier::BOLD))
+            })
+            .collect::<Vec<_>>(),
+    );
+    let border_color = if active { theme.selected_border } else { theme.border };
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(Span::styled(title, Style::default().fg(border_color).add_modifier(Modifier::BOLD)))
+                .border_style(Style::default().fg(border_color)),
+        )
+        .style(Style::default().fg(theme.text));
+    frame.render_widget(table, area);
+}
+
+fn render_query_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    // Room for a line or two of suggestions or the syntax error below the query
+    let height = if app.completion.is_some() || app.query_error.is_some() { 7 } else { 5 };
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: height.min(area.height.saturating_sub(area.height / 2 - 2)),
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let mut lines = match app.query_error.as_ref().and_then(|error| error.position) {
+        // The character the parser stumbled on is highlighted, or the cursor when the query ended too soon
+        Some(position) => {
+            let before: String = app.query_input.chars().take(position).collect();
+            let at: String = app.query_input.chars().skip(position).take(1).collect();
+            let after: String = app.query_input.chars().skip(position + 1).collect();
+            let error_style = Style::default().fg(theme.query_bg).bg(theme.error).add_modifier(Modifier::BOLD);
+            let line = if at.is_empty() {
+                Line::from(vec![Span::raw(before), Span::styled("_", error_style)])
+            } else {
+                Line::from(vec![Span::raw(before), Span::styled(at, error_style), Span::raw(format!("{}_", after))])
+            };
+            vec![line]
+        }
+        None => vec![Line::from(format!("{}_", app.query_input))],
+    };
+    if let Some(error) = &app.query_error {
+        lines.push(Line::from(Span::styled(
+            format!("{} - edit the query, or Enter to run it anyway", error.message),
+            Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+        )));
+    }
+    if let Some(completion) = &app.completion {
+        let mut suggestions = Vec::new();
+        for (i, name) in completion.candidates.iter().enumerate() {
+            let style = if completion.selected == Some(i) {
+                Style::default()
+                    .fg(theme.selected_text)
+                    .bg(theme.selected_bg)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.column_header)
+            };
+            suggestions.push(Span::styled(name.clone(), style));
+            suggestions.push(Span::raw("  "));
+        }
+        lines.push(Line::from(suggestions));
+    }
+    let query_input = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(if app.query_over_database {
+                    "Query the Whole Database (ESC to cancel)"
+                } else {
+                    "Enter SQL Query (ESC to cancel)"
+                })
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(query_input, popup_area);
+}
+
+fn render_query_history(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let matches = app.history_matches();
+    let rows: Vec<Row> = matches
+        .iter()
+        .map(|query| {
+            Row::new(vec![Cell::from(query.as_str()).style(Style::default().fg(theme.detailed_view_value))])
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Min(10)])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!(
+                    "Query history: {}_ ({} of {})",
+                    app.history_search,
+                    matches.len(),
+                    app.query_history.len()
+                ))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.history_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_saved_query_name(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let name_input = Paragraph::new(format!("{}_", app.saved_query_name))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Save query as (ESC to go back)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(name_input, popup_area);
+}
+
+fn render_query_parameters(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let height = (app.query_params.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 2 / 3,
+        height,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let name_width = app.query_params.iter().map(|(name, _)| name.len() + 1).max().unwrap_or(0);
+    let lines: Vec<Line> = app
+        .query_params
+        .iter()
+        .enumerate()
+        .map(|(i, (name, value))| {
+            let selected = i == app.query_param_selected;
+            let name_style = if selected {
+                Style::default().fg(theme.detailed_view_field).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.detailed_view_field)
+            };
+            Line::from(vec![
+                Span::styled(format!("{:<width$} ", format!(":{}", name), width = name_width), name_style),
+                Span::styled(
+                    if selected { format!("{}_", value) } else { value.clone() },
+                    Style::default().fg(theme.query_text),
+                ),
+            ])
+        })
+        .collect();
+    let form = Paragraph::new(lines)
+        .style(Style::default().bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Query parameters (Tab: next, Enter: run, ESC: back)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(form, popup_area);
+}
+
+fn render_saved_queries(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = app
+        .saved_queries
+        .iter()
+        .map(|saved| {
+            Row::new(vec![
+                Cell::from(saved.name.as_str()).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(saved.query.as_str()).style(Style::default().fg(theme.detailed_view_value)),
+            ])
+        })
+        .collect();
+
+    let widths = [Constraint::Percentage(25), Constraint::Min(20)];
+
+    let header = Row::new(["Name", "Query"].map(|title| {
+        Cell::from(title).style(
+            Style::default()
+                .fg(theme.column_header)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Saved queries ({})", app.saved_queries.len()))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.saved_query_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_edit_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(7),
+        width: area.width * 2 / 3,
+        height: 3,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let edit_input = Paragraph::new(format!("{}_", app.edit_input))
+        .style(Style::default().fg(theme.edit_text).bg(theme.edit_area_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .border_style(Style::default().fg(theme.edit_border))
+                .style(Style::default().bg(theme.edit_area_bg)),
+        );
+
+    frame.render_widget(edit_input, popup_area);
+}
+
+fn render_computed_column_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let title = match app.computed_column_edit {
+        Some(ComputedColumnEdit::Rename(idx)) => {
+            format!("Rename computed column '{}'", app.computed_columns.get(idx).map_or("", |c| c.name.as_str()))
+        }
+        Some(ComputedColumnEdit::Expression(_)) => "Computed Column (name=expression)".to_string(),
+        _ => "Computed Column (e.g., sum(Age), column1=Age*2)".to_string(),
+    };
+    let computed_col_input = Paragraph::new(format!("{}_", app.computed_column_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(computed_col_input, popup_area);
+}
+
+fn render_computed_column_manager(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let height = (app.computed_columns.len().max(1) as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 2 / 3,
+        height,
+    };
+
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = if app.computed_columns.is_empty() {
+        vec![Row::new(vec![Cell::from("No computed columns; = adds one").style(Style::default().fg(Color::DarkGray))])]
+    } else {
+        app.computed_columns
+            .iter()
+            .map(|column| {
+                Row::new(vec![
+                    Cell::from(column.name.as_str()).style(Style::default().fg(theme.detailed_view_field)),
+                    Cell::from(column.expression.as_str()).style(Style::default().fg(theme.detailed_view_value)),
+                ])
+            })
+            .collect()
+    };
+    let widths = match app.computed_columns.iter().map(|c| c.name.width()).max() {
+        Some(name_width) => vec![Constraint::Length(name_width.clamp(4, 30) as u16 + 1), Constraint::Min(10)],
+        None => vec![Constraint::Min(10)],
+    };
+
+    let table = Table::new(rows, widths)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Computed columns (= adds, e edits, r renames, d deletes, Shift+↑↓ moves)")
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let selected = (!app.computed_columns.is_empty()).then_some(app.computed_manager_selected);
+    let mut table_state = TableState::default().with_selected(selected);
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_diff_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let diff_input = Paragraph::new(format!("{}_", app.diff_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Diff against table (other_table [key_column])")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(diff_input, popup_area);
+}
+
+fn render_diff_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: 1,
+        y: 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let Some(diff) = &app.diff_result else {
+        return;
+    };
+
+    let title = format!(
+        "Diff: {} | key: {} | +{} -{} ~{} | {} unchanged",
+        app.diff_description,
+        diff.key_column,
+        diff.count(DiffKind::Added),
+        diff.count(DiffKind::Removed),
+        diff.count(DiffKind::Changed),
+        diff.unchanged
+    );
+
+    let rows: Vec<Row> = diff
+        .rows
+        .iter()
+        .map(|diff_row| {
+            let (marker, row_style) = match diff_row.kind {
+                DiffKind::Added => ("+", Style::default().fg(theme.status)),
+                DiffKind::Removed => ("-", Style::default().fg(theme.error)),
+                DiffKind::Changed => ("~", Style::default().fg(theme.text)),
+            };
+
+            let mut cells = vec![Cell::from(marker).style(row_style.add_modifier(Modifier::BOLD))];
+            for (col_idx, value) in diff_row.values.iter().enumerate() {
+                if diff_row.changed_columns.contains(&col_idx) {
+                    let old_value = diff_row
+                        .old_values
+                        .as_ref()
+                        .and_then(|old| old.get(col_idx))
+                        .map(|s| s.as_str())
+                        .unwrap_or("");
+                    let content = theme.text(&format!("{} → {}", old_value, value)).into_owned();
+                    cells.push(Cell::from(content).style(
+                        Style::default()
+                            .fg(theme.edit_text)
+                            .bg(theme.edit_bg)
+                            .add_modifier(Modifier::BOLD),
+                    ));
+                } else {
+                    cells.push(Cell::from(value.as_str()).style(row_style));
+                }
+            }
+            Row::new(cells)
+        })
+        .collect();
+
+    let mut widths = vec![Constraint::Length(2)];
+    widths.extend(
+        (0..diff.columns.len())
+            .map(|_| Constraint::Percentage(100 / diff.columns.len().max(1) as u16)),
+    );
+
+    let mut header_cells = vec![Cell::from("")];
+    header_cells.extend(diff.columns.iter().map(|column| {
+        Cell::from(column.as_str()).style(
+            Style::default()
+                .fg(theme.column_header)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header_cells))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(Span::styled(
+                    title,
+                    Style::default()
+                        .fg(theme.selected_border)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .border_style(Style::default().fg(theme.selected_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.diff_selected_row));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_join_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let join_input = Paragraph::new(format!("{}_", app.join_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Join with a file (file key[=other_key] [left|inner] [table])")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(join_input, popup_area);
+}
+
+fn render_join_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: 1,
+        y: 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let Some(joined) = &app.joined_table else {
+        return;
+    };
+    let data = &joined.data;
+
+    // Only the page of rows holding the selected one is laid out
+    let visible_rows = (popup_area.height as usize).saturating_sub(3).max(1);
+    let first_row = joined.selected_row / visible_rows * visible_rows;
+    let page = &data.rows[first_row.min(data.rows.len())..(first_row + visible_rows).min(data.rows.len())];
+
+    // Columns are sized to their content, scrolling right once the selected one is off screen
+    let widths: Vec<u16> = (0..data.columns.len())
+        .map(|idx| {
+            let cells = page.iter().map(|row| row.get(idx).map_or(0, |cell| format_value(cell, &app.format).width()));
+            cells.chain([data.columns[idx].width()]).max().unwrap_or(0).clamp(3, 30) as u16
+        })
+        .collect();
+    let available = popup_area.width.saturating_sub(2);
+    let mut first_col = 0;
+    while first_col < joined.selected_col
+        && widths[first_col..=joined.selected_col].iter().map(|w| w + 1).sum::<u16>() > available
+    {
+        first_col += 1;
+    }
+
+    let rows: Vec<Row> = page
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let cells: Vec<Cell> = row
+                .iter()
+                .enumerate()
+                .skip(first_col)
+                .map(|(j, cell)| {
+                    let style = if first_row + i == joined.selected_row && j == joined.selected_col {
+                        Style::default()
+                            .fg(theme.selected_text)
+                            .bg(theme.selected_bg)
+                            .add_modifier(Modifier::BOLD)
+                    } else if *cell == Value::Null {
+                        Style::default().fg(Color::DarkGray)
+                    } else {
+                        Style::default().fg(theme.text)
+                    };
+                    Cell::from(format_value(cell, &app.format)).style(style)
+                })
+                .collect();
+            Row::new(cells)
+        })
+        .collect();
+
+    let header = Row::new(
+        data.columns
+            .iter()
+            .skip(first_col)
+            .map(|column| {
+                Cell::from(column.as_str()).style(Style::default().fg(theme.column_header).add_modifier(Modifier::BOLD))
+            })
+            .collect::<Vec<_>>(),
+    );
+    let title = format!(
+        "Join: {} | {} rows | Row {}/{}",
+        joined.description,
+        data.rows.len(),
+        (joined.selected_row + 1).min(data.rows.len()),
+        data.rows.len()
+    );
+    let table = Table::new(rows, widths[first_col..].iter().map(|w| Constraint::Length(*w)))
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(Span::styled(
+                    title,
+                    Style::default()
+                        .fg(theme.selected_border)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .border_style(Style::default().fg(theme.selected_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        );
+    frame.render_widget(table, popup_area);
+}
+
+fn render_global_search_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let search_input = Paragraph::new(format!("{}_", app.search_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Search all tables (ESC to cancel)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(search_input, popup_area);
+}
+
+fn render_search_results(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = app
+        .search_results
+        .iter()
+        .map(|found| {
+            Row::new(vec![
+                Cell::from(found.table.as_str()).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(found.rowid.to_string()).style(Style::default().fg(theme.number)),
+                Cell::from(found.column.as_str()).style(Style::default().fg(theme.column_header)),
+                Cell::from(found.snippet.as_str()).style(Style::default().fg(theme.detailed_view_value)),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Percentage(20),
+        Constraint::Length(10),
+        Constraint::Percentage(20),
+        Constraint::Min(10),
+    ];
+
+    let header = Row::new(["Table", "Row", "Column", "Snippet"].map(|title| {
+        Cell::from(title).style(
+            Style::default()
+                .fg(theme.column_header)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Search Results ({})", app.search_results.len()))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.search_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_pipe_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let pipe_input = Paragraph::new(format!("{}_", app.pipe_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!(
+                    "Pipe {} as CSV to command (Tab to change, ESC to cancel)",
+                    app.pipe_scope.label()
+                ))
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(pipe_input, popup_area);
+}
+
+fn render_pipe_output(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let Some(output) = &app.pipe_output else {
+        return;
+    };
+
+    let mut lines: Vec<Line> = output
+        .stdout
+        .lines()
+        .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.text))))
+        .collect();
+    if !output.stderr.is_empty() {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.extend(
+            output
+                .stderr
+                .lines()
+                .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.error)))),
+        );
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "(no output)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    let status = match output.status {
+        Some(code) => format!("exit {}", code),
+        None => "terminated".to_string(),
+    };
+    let border_color = if output.success() {
+        theme.detailed_view_border
+    } else {
+        theme.error
+    };
+
+    let output_view = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("{} ({})", app.pipe_output_title, status))
+                .border_style(Style::default().fg(border_color))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .scroll((app.pipe_output_scroll, 0));
+
+    frame.render_widget(output_view, popup_area);
+}
+
+fn render_export_results(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let height = (ExportFormat::ALL.len() as u16 + 3).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 2 / 3,
+        height,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let name = app.exported_table().unwrap_or("query_export");
+    let mut rows: Vec<Row> = ExportFormat::ALL
+        .iter()
+        .map(|format| {
+            let values = match (format.is_json(), app.export_typed) {
+                (false, _) => "",
+                (true, true) => " (typed values, t for text)",
+                (true, false) => " (values as text, t for typed)",
+            };
+            Row::new(vec![
+                Cell::from(format.label()).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(format!("{}_<time>.{}{}", name, format.extension(), values))
+                    .style(Style::default().fg(theme.detailed_view_value)),
+            ])
+        })
+        .collect();
+    rows.push(Row::new(vec![
+        Cell::from("Pipe to").style(Style::default().fg(theme.detailed_view_field)),
+        Cell::from(format!("| {}_", app.pipe_input)).style(Style::default().fg(theme.detailed_view_value)),
+    ]));
+
+    let page = app.export_page().map(|(page, _)| page);
+    let scope = match app.export_scope {
+        ExportScope::All => format!("all {} rows", page.map_or(0, |page| page.total_rows)),
+        ExportScope::Page => format!("the {} rows of this page", page.map_or(0, |page| page.rows.len())),
+        ExportScope::Selected => match app.export_page().map_or(0, |(_, selected)| selected.len()) {
+            1 => "the selected row".to_string(),
+            n => format!("the {} selected rows", n),
+        },
+    };
+    let hidden = app.hidden_export_columns().len();
+    let columns = match (hidden, app.export_visible_columns) {
+        (0, _) => "",
+        (_, true) => " | hidden columns left out, c includes them",
+        (_, false) => " | hidden columns included, c leaves them out",
+    };
+    let computed = match (app.exported_computed_columns().len(), app.export_computed) {
+        (0, _) => "",
+        (_, true) => " | computed columns included, v leaves them out",
+        (_, false) => " | computed columns left out, v includes them",
+    };
+    let table = Table::new(rows, [Constraint::Percentage(30), Constraint::Percentage(70)])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(match app.exported_table() {
+                    Some(table) => format!("Export {} of {} (r for other rows){}{}", scope, table, columns, computed),
+                    None => format!("Export {} of the query (r for other rows){}", scope, computed),
+                })
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.export_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_export_path(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(4) / 2,
+        width: area.width * 2 / 3,
+        height: 4.min(area.height),
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let hint = if app.export_overwrite {
+        Line::from(Span::styled(
+            "The file exists - Enter again to overwrite it",
+            Style::default().fg(theme.error),
+        ))
+    } else {
+        Line::from(Span::styled(app.export_path_matches.join("  "), Style::default().fg(theme.help)))
+    };
+    let format = ExportFormat::ALL[app.export_selected.min(ExportFormat::ALL.len() - 1)];
+    let input = Paragraph::new(vec![Line::from(format!("{}_", app.export_path_input)), hint])
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Export {} to", format.label()))
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(input, popup_area);
+}
+
+fn render_tool_picker(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let height = (app.external_tools.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 2 / 3,
+        height,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = app
+        .external_tools
+        .iter()
+        .map(|tool| {
+            Row::new(vec![
+                Cell::from(tool.name.as_str()).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(tool.command.as_str()).style(Style::default().fg(theme.detailed_view_value)),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Percentage(30), Constraint::Percentage(70)])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Open in external tool")
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.tool_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_bookmark_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let target = if app.bookmark_cell { "cell" } else { "row" };
+    let bookmark_input = Paragraph::new(format!("{}_", app.bookmark_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Bookmark {} - name (Tab: row/cell, ESC to cancel)", target))
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(bookmark_input, popup_area);
+}
+
+/// How wide a column is drawn when not resized: as wide as its header and widest cell
+/// (in terminal columns), within 3 and 40 cells
+fn fitted_width(header: &str, cells: impl Iterator<Item = usize>) -> u16 {
+    cells.chain([header.width()]).max().unwrap_or(0).clamp(3, 40) as u16
+}
+
+/// The columns that fit in `available` cells, scrolled just far enough right for the
+/// `selected` one to be shown. Column `j` is `width(j)` wide, with a space between columns;
+/// only the columns next to the selected one are measured.
+fn column_window(count: usize, mut width: impl FnMut(usize) -> u16, selected: usize, available: u16) -> std::ops::Range<usize> {
+    if count == 0 {
+        return 0..0;
+    }
+    let selected = selected.min(count - 1);
+    let available = u32::from(available) + 1;
+    let mut needed = u32::from(width(selected)) + 1;
+    let mut first = selected;
+    while first > 0 {
+        let wider = needed + u32::from(width(first - 1)) + 1;
+        if wider > available {
+            break;
+        }
+        needed = wider;
+        first -= 1;
+    }
+    let mut end = selected + 1;
+    while end < count {
+        let wider = needed + u32::from(width(end)) + 1;
+        if wider > available {
+            break;
+        }
+        needed = wider;
+        end += 1;
+    }
+    first..end
+}
+
+fn render_column_manager(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let columns = app.manageable_columns();
+    let height = (columns.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 4,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width / 2,
+        height,
+    };
+
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = columns
+        .iter()
+        .map(|column| {
+            let shown = !app.view_state.hidden_columns.contains(column);
+            Row::new(vec![
+                Cell::from(if shown { "[x]" } else { "[ ]" }).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(column.as_str()).style(Style::default().fg(if shown {
+                    theme.detailed_view_value
+                } else {
+                    Color::DarkGray
+                })),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Length(4), Constraint::Min(10)])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Columns (Space shows/hides, + adds, r renames, d drops)")
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.column_manager_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+fn render_column_edit_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let title = match &app.column_edit_target {
+        Some(column) => format!("Rename column '{}'", column),
+        None => "New column (name [type] [= default])".to_string(),
+    };
+    let input = Paragraph::new(format!("{}_", app.column_edit_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(input, popup_area);
+}
+
+fn render_confirm_drop_column(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let Some(column) = &app.column_edit_target else {
+        return;
+    };
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let question = format!(
+        "Drop column '{}' and its values? A database table changes at once, a file when saved (s).",
+        column
+    );
+    let question = Paragraph::new(question)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Drop column (y/Enter: drop, n/ESC: cancel)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(question, popup_area);
+}
+
+fn render_column_stats(frame: &mut Frame, app: &AppState, stats: &ColumnStats, theme: &Theme) {
+    let row = |label: &str, value: String| {
+        Row::new(vec![
+            Cell::from(label.to_string()).style(Style::default().fg(theme.detailed_view_field)),
+            Cell::from(value).style(Style::default().fg(theme.detailed_view_value)),
+        ])
+    };
+    let number = |value: f64| format_value(&Value::Float(value), &app.format);
+    let percent = |count: usize| match stats.rows {
+        0 => String::new(),
+        rows => format!(" ({:.1}%)", count as f64 * 100.0 / rows as f64),
+    };
+
+    let mut rows = vec![
+        row("Rows", format_value(&Value::Int(stats.rows as i64), &app.format)),
+        row("Nulls", format!("{}{}", stats.nulls, percent(stats.nulls))),
+        row("Distinct", stats.distinct.to_string()),
+        row("Min", stats.min.clone().unwrap_or_else(|| "-".to_string())),
+        row("Max", stats.max.clone().unwrap_or_else(|| "-".to_string())),
+    ];
+    if let Some(numeric) = &stats.numeric {
+        rows.push(row("Mean", number(numeric.mean)));
+        rows.push(row("Median", number(numeric.median)));
+        rows.push(row("Std dev", number(numeric.stddev)));
+    }
+    if !stats.top_values.is_empty() {
+        rows.push(row("", String::new()));
+        rows.push(row("Most frequent", String::new()));
+        for (value, count) in &stats.top_values {
+            rows.push(row(&format!("  {}", value), format!("{}{}", count, percent(*count))));
+        }
+    }
+
+    let area = frame.area();
+    let height = (rows.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 4,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width / 2,
+        height,
+    };
+    frame.render_widget(Clear, popup_area);
+
+    let table = Table::new(rows, [Constraint::Percentage(50), Constraint::Percentage(50)]).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .title(format!("Stats of {} - ESC to close", stats.column))
+            .border_style(Style::default().fg(theme.detailed_view_border))
+            .style(Style::default().bg(theme.detailed_view_bg)),
+    );
+    frame.render_widget(table, popup_area);
+}
+
+/// Horizontal bars, so range and value labels get room to be read
+fn render_histogram(frame: &mut Frame, chart: &Histogram, theme: &Theme) {
+    let area = frame.area();
+    let height = (chart.bars.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 3 / 4,
+        height,
+    };
+    frame.render_widget(Clear, popup_area);
+
+    let label_width = chart.bars.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0).min(30);
+    let bars: Vec<Bar> = chart
+        .bars
+        .iter()
+        .map(|(label, count)| {
+            let label: String = label.chars().take(label_width).collect();
+            Bar::default()
+                .label(Line::from(format!("{:>width$}", label, width = label_width)))
+                .value(*count)
+                .text_value(count.to_string())
+                .style(Style::default().fg(theme.number))
+                .value_style(Style::default().fg(theme.detailed_view_bg).bg(theme.number))
+        })
+        .collect();
+
+    let title = if chart.numeric {
+        format!("Distribution of {} - ESC to close", chart.column)
+    } else {
+        format!("Most frequent values of {} - ESC to close", chart.column)
+    };
+    let bar_chart = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .bar_set(if theme.ascii { ASCII_BARS } else { symbols::bar::NINE_LEVELS })
+        .label_style(Style::default().fg(theme.detailed_view_field))
+        .data(BarGroup::default().bars(&bars));
+    frame.render_widget(bar_chart, popup_area);
+}
+
+fn render_value_counts(frame: &mut Frame, app: &AppState, counts: &ValueCounts, theme: &Theme) {
+    let area = frame.area();
+    let height = (counts.values.len() as u16 + 3).min(area.height * 3 / 4);
+    let popup_area = Rect {
+        x: area.width / 4,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width / 2,
+        height,
+    };
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = counts
+        .values
+        .iter()
+        .map(|(value, count)| {
+            let percent = match counts.rows {
+                0 => 0.0,
+                rows => *count as f64 * 100.0 / rows as f64,
+            };
+            Row::new(vec![
+                Cell::from(format_value(value, &app.format)).style(Style::default().fg(if *value == Value::Null {
+                    Color::DarkGray
+                } else {
+                    theme.detailed_view_value
+                })),
+                Cell::from(Line::from(count.to_string()).alignment(Alignment::Right))
+                    .style(Style::default().fg(theme.number)),
+                Cell::from(Line::from(format!("{:.1}%", percent)).alignment(Alignment::Right))
+                    .style(Style::default().fg(theme.detailed_view_field)),
+            ])
+        })
+        .collect();
+
+    let title = if counts.distinct > counts.values.len() {
+        format!("Values of {} ({} most frequent of {}) - Enter filters", counts.column, counts.values.len(), counts.distinct)
+    } else {
+        format!("Values of {} ({} distinct) - Enter filters", counts.column, counts.distinct)
+    };
+    let table = Table::new(rows, [Constraint::Min(10), Constraint::Length(10), Constraint::Length(7)])
+        .header(
+            Row::new(vec![
+                Cell::from("Value"),
+                Cell::from(Line::from("Count").alignment(Alignment::Right)),
+                Cell::from(Line::from("%").alignment(Alignment::Right)),
+            ])
+            .style(Style::default().fg(theme.detailed_view_field).add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut table_state = TableState::default().with_selected(Some(app.value_counts_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
+
+/// Bars drawn with plain characters for terminals set to ASCII
+const ASCII_BARS: symbols::bar::Set = symbols::bar::Set {
+    full: "#",
+    seven_eighths: "#",
+    three_quarters: "#",
+    five_eighths: "#",
+    half: "#",
+    three_eighths: "#",
+    one_quarter: "#",
+    one_eighth: " ",
+    empty: " ",
+};
+
+fn render_cell_search(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    frame.render_widget(Clear, popup_area);
+
+    let search_input = Paragraph::new(format!("{}_", app.cell_search_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Search the table, ignoring case, or re:regex (empty clears, ESC to cancel)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(search_input, popup_area);
+}
+
+fn render_filter_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let column = app.selected_column_name().unwrap_or_default();
+    let filter_input = Paragraph::new(format!("{}_", app.filter_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!(
+                    "Filter {} - >100, <=x, !=x, contains:foo, re:regex, =NULL (empty removes, ESC to cancel)",
+                    column
+                ))
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(filter_input, popup_area);
+}
+
+fn render_goto_row(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let total = app.current_data.as_ref().map_or(0, |data| data.total_rows);
+    let goto_input = Paragraph::new(format!("{}_", app.goto_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Go to row - a number from 1 to {} or a percentage such as 50% (ESC to cancel)", total))
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(goto_input, popup_area);
+}
+
+fn render_attach_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let attach_input = Paragraph::new(format!("{}_", app.attach_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Attach SQLite database - path (ESC to cancel)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(attach_input, popup_area);
+}
+
+fn render_confirm_save(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let (updated, inserted, deleted) = count_row_changes(&app.pending_changes);
+    let table = app.current_table().unwrap_or_default();
+    let question = Paragraph::new(format!(
+        "Write {} changed and {} new rows to '{}'{}?",
+        updated,
+        inserted,
+        table,
+        deleted_rows_note(deleted)
+    ))
+    .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .title("Save to database (y/Enter: write, n/ESC: cancel)")
+            .border_style(Style::default().fg(theme.query_border))
+            .style(Style::default().bg(theme.query_bg)),
+    );
+
+    frame.render_widget(question, popup_area);
+}
+
+fn render_confirm_revert(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let Some(batch) = &app.pending_revert else {
+        return;
+    };
+    let saved_at = chrono::DateTime::from_timestamp(batch.saved_at as i64, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    let changes = match batch.statements.len() {
+        1 => "the row change".to_string(),
+        n => format!("the {} row changes", n),
+    };
+    let question = Paragraph::new(format!("Undo {} saved to '{}' at {}?", changes, batch.table, saved_at))
+    .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .title("Revert last save (y/Enter: revert, d: forget it, n/ESC: cancel)")
+            .border_style(Style::default().fg(theme.query_border))
+            .style(Style::default().bg(theme.query_bg)),
+    );
+
+    frame.render_widget(question, popup_area);
+}
+
+fn render_confirm_file_save(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let Some((path, data)) = &app.pending_file_save else {
+        return;
+    };
+    let question = Paragraph::new(format!(
+        "Overwrite {} with its {} rows? The current file is kept as {}.",
+        path.display(),
+        data.rows.len(),
+        backup_path(path).display()
+    ))
+    .wrap(Wrap { trim: true })
+    .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.border_set())
+            .title("Save to file (y/Enter: overwrite, n/ESC: cancel)")
+            .border_style(Style::default().fg(theme.query_border))
+            .style(Style::default().bg(theme.query_bg)),
+    );
+
+    frame.render_widget(question, popup_area);
+}
+
+fn render_confirm_paste(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let rows: HashSet<usize> = app.pending_paste.iter().map(|(row, _, _)| *row).collect();
+    let mut question = format!("Change {} cells in {} rows?", app.pending_paste.len(), rows.len());
+    let (invalid, outside) = app.paste_skipped;
+    if invalid > 0 {
+        question.push_str(&format!(" {} values that don't fit their column are skipped.", invalid));
+    }
+    if outside > 0 {
+        question.push_str(&format!(" {} values past the page or the last column are left out.", outside));
+    }
+    let question = Paragraph::new(question)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Paste (y/Enter: change the cells, n/ESC: cancel)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(question, popup_area);
+}
+
+fn render_fill_down(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let start = app
+        .current_data
+        .as_ref()
+        .and_then(|data| data.rows.get(app.selected_row_idx))
+        .and_then(|row| row.get(app.selected_col_idx))
+        .map(|cell| cell.to_string())
+        .unwrap_or_default();
+    let title = format!(
+        "Fill down {} '{}' (rows, empty for the rest of the page; Tab: {})",
+        if app.fill_series { "the series from" } else { "copies of" },
+        start,
+        if app.fill_series { "copy instead" } else { "continue a series" }
+    );
+    let input = Paragraph::new(format!("{}_", app.fill_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
+
+    frame.render_widget(input, popup_area);
+}
+
+fn render_bulk_edit_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 6,
+        y: area.height / 2 - 2,
+        width: area.width * 2 / 3,
+        height: 5,
+    };
+
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let input = Paragraph::new(format!("{}_", app.bulk_edit_input))
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Bulk edit (column = expression [WHERE condition], or UPDATE x SET ...)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
 
-                        for (row, value) in data.rows.iter_mut().zip(computed_values) {
-                            row.push(value);
-                        }
-                    }
-                    ComputedColumnType::MixedOperation(columns_used, aggregate_expressions) => {
-                        let expression = computed_col.expression.clone();
-                        let cols = columns_used.clone();
-                        let aggs = aggregate_expressions.clone();
-                        let mut computed_values = Vec::new();
-
-                        for row in &data.rows {
-                            let value = Self::compute_mixed_operation_static(
-                                data,
-                                row,
-                                &expression,
-                                &cols,
-                                &aggs,
-                            )?;
-                            computed_values.push(value);
-                        }
+    frame.render_widget(input, popup_area);
+}
 
-                        for (row, value) in data.rows.iter_mut().zip(computed_values) {
-                            row.push(value);
-                        }
-                    }
-                }
-            }
-        }
-        Ok(())
+/// Where the multi-line editor goes, centered and sized in percent of the screen
+fn text_editor_area(area: Rect, (width, height): (u16, u16)) -> Rect {
+    let width = (area.width as u32 * width as u32 / 100) as u16;
+    let height = ((area.height as u32 * height as u32 / 100) as u16).clamp(3.min(area.height), area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
     }
 }
 
-pub fn render_ui(frame: &mut Frame, app: &AppState, theme: &Theme) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3), // Header
-            Constraint::Min(0),    // Body
-            Constraint::Length(3), // Footer
-        ])
-        .split(frame.area());
+fn render_text_editor(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let (Some(editor), Some((_, col)), Some(data)) = (&app.text_editor, app.editing_cell, &app.current_data) else {
+        return;
+    };
+    let popup_area = text_editor_area(frame.area(), app.text_editor_size);
 
-    // Header
-    let header = Paragraph::new(format!(
-        "SQLite Browser - {}",
-        std::path::Path::new(&app.db_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Unknown")
-    ))
-    .style(
-        Style::default()
-            .fg(theme.header)
-            .add_modifier(Modifier::BOLD),
-    )
-    .alignment(Alignment::Center)
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(theme.header)),
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let width = popup_area.width.saturating_sub(2) as usize;
+    let height = popup_area.height.saturating_sub(2) as usize;
+    let text = editor.text();
+    let wrapped = editor.wrap(width);
+    let (cursor_line, _) = editor.position(&wrapped);
+    let style = Style::default().fg(theme.query_text).bg(theme.query_bg);
+    let lines: Vec<Line> = wrapped
+        .iter()
+        .enumerate()
+        .map(|(i, range)| {
+            if i != cursor_line {
+                return Line::from(Span::styled(&text[range.clone()], style));
+            }
+            // The character under the cursor is shown reversed, or a space past the end
+            let (before, rest) = text[range.clone()].split_at(editor.cursor() - range.start);
+            let under = rest.chars().next().map_or(0, |c| c.len_utf8());
+            let (at, after) = if rest.is_empty() { (" ", "") } else { rest.split_at(under) };
+            Line::from(vec![
+                Span::styled(before, style),
+                Span::styled(at, style.add_modifier(Modifier::REVERSED)),
+                Span::styled(after, style),
+            ])
+        })
+        .collect();
+
+    // Keep the cursor's line in view
+    let scroll = (cursor_line + 1).saturating_sub(height);
+    let title = format!(
+        "Edit {} ({} chars; Enter: save, Alt+Enter: new line, Ctrl+arrows: resize, ESC: cancel)",
+        data.columns.get(col).map(String::as_str).unwrap_or_default(),
+        text.chars().count()
     );
-    frame.render_widget(header, chunks[0]);
+    let paragraph = Paragraph::new(lines)
+        .style(style)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.edit_border))
+                .style(Style::default().bg(theme.query_bg)),
+        )
+        .scroll((scroll as u16, 0));
 
-    // Body
-    let body_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length(25), // Sidebar
-            Constraint::Min(0),     // Main area
-        ])
-        .split(chunks[1]);
+    frame.render_widget(paragraph, popup_area);
+}
 
-    // Render sidebar (tables list)
-    render_sidebar(frame, app, body_chunks[0], theme);
+fn render_insert_form(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let Some(form) = &app.insert_form else {
+        return;
+    };
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
 
-    // Render main area
-    render_main_area(frame, app, body_chunks[1], theme);
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
 
-    // Query input overlay
-    if app.navigation_mode == NavigationMode::Query {
-        render_query_input(frame, app, theme);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("New row - {}", app.current_table().unwrap_or_default()),
+            Style::default().fg(theme.detailed_view_title).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for (i, field) in form.fields.iter().enumerate() {
+        let selected = i == form.selected;
+        let (field_style, value_style) = if selected {
+            let style = Style::default().fg(theme.selected_text).bg(theme.selected_bg);
+            (style.add_modifier(Modifier::BOLD), style)
+        } else {
+            (
+                Style::default().fg(theme.detailed_view_field).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.detailed_view_value),
+            )
+        };
+        let mut spans = vec![
+            Span::styled(format!("{}: ", field.name), field_style),
+            Span::styled(if selected { format!("{}_", field.input) } else { field.input.clone() }, value_style),
+        ];
+        if let Some(hint) = field.hint.as_ref().filter(|_| field.input.is_empty()) {
+            spans.push(Span::styled(format!("  ({})", hint), Style::default().fg(Color::DarkGray)));
+        }
+        lines.push(Line::from(spans));
     }
 
-    // Edit input overlay
-    if app.navigation_mode == NavigationMode::Edit {
-        render_edit_input(frame, app, theme);
-    }
+    // Keep the field being typed in view on tall forms
+    let inner_height = popup_area.height.saturating_sub(2) as usize;
+    let scroll = (form.selected + 2).saturating_sub(inner_height.saturating_sub(1));
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Insert Row (empty fields are left to the database)")
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .scroll((scroll as u16, 0));
 
-    // Computed column input overlay
-    if app.navigation_mode == NavigationMode::ComputedColumn {
-        render_computed_column_input(frame, app, theme);
-    }
+    frame.render_widget(paragraph, popup_area);
+}
 
-    // Help overlay
-    if app.show_help {
-        render_help(frame, theme);
-    }
+fn render_confirm_statement(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let Some(plan) = &app.pending_statement else {
+        return;
+    };
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 4,
+        width: area.width * 3 / 4,
+        height: area.height / 2,
+    };
 
-    // Detailed view overlay
-    if app.navigation_mode == NavigationMode::DetailedView {
-        render_detailed_view(frame, app, theme);
-    }
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
 
-    // Error display overlay
-    if app.navigation_mode == NavigationMode::ErrorDisplay {
-        render_error_display(frame, app, theme);
-    }
+    let effect = match plan.affected_rows {
+        Some(1) => "This changes 1 row.".to_string(),
+        Some(n) => format!("This changes {} rows.", n),
+        None => "This changes the database; the rows affected can't be counted beforehand.".to_string(),
+    };
+    let text = vec![
+        Line::from(Span::styled(effect, Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ]
+    .into_iter()
+    .chain(plan.sql.lines().map(|line| Line::from(line.to_string())))
+    .collect::<Vec<_>>();
+    let question = Paragraph::new(text)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Run statement (y/Enter: run, n/ESC: cancel)")
+                .border_style(Style::default().fg(theme.query_border))
+                .style(Style::default().bg(theme.query_bg)),
+        );
 
-    // Footer
-    render_footer(frame, app, chunks[2], theme);
+    frame.render_widget(question, popup_area);
 }
 
-fn render_sidebar(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
-    let border_style = if app.navigation_mode == NavigationMode::Table {
-        Style::default().fg(theme.selected_border)
-    } else {
-        Style::default().fg(theme.border)
-    };
+/// Number of updated, inserted and deleted rows
+fn count_row_changes(changes: &[RowChange]) -> (usize, usize, usize) {
+    let count = |kind: fn(&RowChange) -> bool| changes.iter().filter(|c| kind(c)).count();
+    (
+        count(|c| matches!(c, RowChange::Update { .. })),
+        count(|c| matches!(c, RowChange::Insert { .. })),
+        count(|c| matches!(c, RowChange::Delete { .. })),
+    )
+}
 
-    let title_style = if app.navigation_mode == NavigationMode::Table {
-        Style::default()
-            .fg(theme.selected_border)
-            .add_modifier(Modifier::BOLD)
-    } else {
-        Style::default()
-            .fg(theme.border)
-            .add_modifier(Modifier::BOLD)
-    };
+/// ", deleting 3 rows" after the count of changed and new rows, when rows are deleted
+fn deleted_rows_note(deleted: usize) -> String {
+    match deleted {
+        0 => String::new(),
+        1 => ", deleting 1 row".to_string(),
+        n => format!(", deleting {} rows", n),
+    }
+}
 
-    let sidebar_title = if app.db_path.ends_with(".xlsx") || app.db_path.ends_with(".xls") {
-        "Sheets"
-    } else if app.db_path.ends_with(".csv") {
-        "Data"
-    } else if app.db_path.ends_with(".parquet") {
-        "Data"
-    } else {
-        "Tables"
+fn render_bookmark_list(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
     };
 
-    let items: Vec<Line> = app
-        .tables
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
+
+    let rows: Vec<Row> = app
+        .bookmarks
         .iter()
-        .enumerate()
-        .map(|(i, table)| {
-            if i == app.selected_table_idx {
-                if app.navigation_mode == NavigationMode::Table {
-                    Line::from(Span::styled(
-                        format!("▶ {}", table),
-                        Style::default()
-                            .fg(theme.selected_border)
-                            .add_modifier(Modifier::BOLD),
-                    ))
-                } else {
-                    Line::from(Span::styled(
-                        format!("▶ {}", table),
-                        Style::default().fg(Color::DarkGray),
-                    ))
-                }
-            } else {
-                Line::from(Span::styled(
-                    format!("  {}", table),
-                    Style::default().fg(theme.text),
-                ))
-            }
+        .map(|bookmark| {
+            let location = match bookmark.rowid {
+                Some(rowid) => format!("rowid {}", rowid),
+                None => format!("row {}", bookmark.position + 1),
+            };
+            Row::new(vec![
+                Cell::from(bookmark.name.as_str()).style(Style::default().fg(theme.detailed_view_value)),
+                Cell::from(bookmark.table.as_str()).style(Style::default().fg(theme.detailed_view_field)),
+                Cell::from(location).style(Style::default().fg(theme.number)),
+                Cell::from(bookmark.column.clone().unwrap_or_default())
+                    .style(Style::default().fg(theme.column_header)),
+            ])
         })
         .collect();
 
-    let list = Paragraph::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(border_style)
-            .title(Span::styled(sidebar_title, title_style)),
-    );
+    let widths = [
+        Constraint::Min(20),
+        Constraint::Percentage(20),
+        Constraint::Length(14),
+        Constraint::Percentage(20),
+    ];
 
-    frame.render_widget(list, area);
-}
+    let header = Row::new(["Name", "Table", "Row", "Column"].map(|title| {
+        Cell::from(title).style(
+            Style::default()
+                .fg(theme.column_header)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
 
-fn render_main_area(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
-    if app.tables.is_empty() || app.selected_table_idx >= app.tables.len() {
-        let placeholder = Paragraph::new("Select a table to view its contents")
-            .style(Style::default().fg(Color::DarkGray))
-            .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Table Contents")
-                    .border_style(Style::default().fg(theme.border)),
-            );
-        frame.render_widget(placeholder, area);
-        return;
-    }
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("Bookmarks ({})", app.bookmarks.len()))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
+        );
 
-    let border_style = match app.navigation_mode {
-        NavigationMode::Data => Style::default().fg(theme.selected_border),
-        NavigationMode::Edit => Style::default().fg(theme.edit_border),
-        _ => Style::default().fg(theme.border),
-    };
+    let mut table_state = TableState::default().with_selected(Some(app.bookmark_selected));
+    frame.render_stateful_widget(table, popup_area, &mut table_state);
+}
 
-    let title_style = match app.navigation_mode {
-        NavigationMode::Data => Style::default()
-            .fg(theme.selected_border)
-            .add_modifier(Modifier::BOLD),
-        NavigationMode::Edit => Style::default()
-            .fg(theme.edit_border)
-            .add_modifier(Modifier::BOLD),
-        _ => Style::default()
-            .fg(theme.border)
-            .add_modifier(Modifier::BOLD),
+fn render_schema_objects(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
     };
 
-    if let Some(data) = &app.current_data {
-        let table_name = &app.tables[app.selected_table_idx];
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
 
-        // Calculate pagination info
-        let current_page = (app.data_offset / app.page_size) + 1;
-        let total_pages = (data.total_rows + app.page_size - 1) / app.page_size.max(1);
-        let start_row = app.data_offset + 1;
-        let end_row = (app.data_offset + data.rows.len()).min(data.total_rows);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(popup_area);
 
-        let mut title = format!(
-            "Table: {} | Total: {} rows | Columns: {}",
-            table_name,
-            data.total_rows,
-            data.columns.len()
+    let rows: Vec<Row> = app
+        .schema_objects
+        .iter()
+        .map(|object| {
+            Row::new(vec![
+                Cell::from(object.kind.as_str()).style(Style::default().fg(theme.column_header)),
+                Cell::from(object.name.as_str()).style(Style::default().fg(theme.detailed_view_value)),
+                Cell::from(object.table.as_str()).style(Style::default().fg(theme.detailed_view_field)),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Percentage(55),
+        Constraint::Percentage(35),
+    ];
+
+    let header = Row::new(["Type", "Name", "Table"].map(|title| {
+        Cell::from(title).style(
+            Style::default()
+                .fg(theme.column_header)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!(
+                    "Indexes and triggers ({}) - Enter: go to table, ESC to close",
+                    app.schema_objects.len()
+                ))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(theme.selected_text)
+                .bg(theme.selected_bg)
+                .add_modifier(Modifier::BOLD),
         );
 
-        if total_pages > 1 {
-            title.push_str(&format!(
-                " | Page {}/{} | Rows {}-{}",
-                current_page, total_pages, start_row, end_row
-            ));
-        }
-
-        if app.current_query.is_some() {
-            title.push_str(" | Custom Query");
-        }
+    let mut table_state = TableState::default().with_selected(Some(app.schema_selected));
+    frame.render_stateful_widget(table, chunks[0], &mut table_state);
+
+    let lines: Vec<Line> = match app.schema_objects.get(app.schema_selected) {
+        Some(SchemaObject { sql: Some(sql), .. }) => sql
+            .lines()
+            .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.text))))
+            .collect(),
+        Some(_) => vec![Line::from(Span::styled(
+            "(created automatically by SQLite for a UNIQUE or PRIMARY KEY constraint)",
+            Style::default().fg(Color::DarkGray),
+        ))],
+        None => Vec::new(),
+    };
 
-        if app.data_modified {
-            title.push_str(" | *MODIFIED*");
-        }
+    let definition = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title("Definition")
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false });
 
-        // Create table rows (skip rowid column for display)
-        let col_offset = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-            1
-        } else {
-            0
-        };
-        let rows: Vec<Row> = data
-            .rows
-            .iter()
-            .enumerate()
-            .map(|(i, row_data)| {
-                let display_row = if col_offset > 0 && row_data.len() > col_offset {
-                    &row_data[col_offset..]
-                } else {
-                    row_data
-                };
+    frame.render_widget(definition, chunks[1]);
+}
 
-                let cells: Vec<Cell> = display_row
-                    .iter()
-                    .enumerate()
-                    .map(|(j, cell)| {
-                        let actual_col_idx = j + col_offset;
-                        let content = if cell.len() > 40 {
-                            format!("{}...", &cell[..37])
-                        } else {
-                            cell.clone()
-                        };
+/// The SQL of the index or trigger under the sidebar cursor
+fn render_object_definition(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let Some(SidebarEntry::Object(idx)) = app.sidebar_cursor else {
+        return;
+    };
+    let Some(object) = app.sidebar_objects.get(idx) else {
+        return;
+    };
+    let area = frame.area();
+    let popup_area = Rect {
+        x: area.width / 8,
+        y: area.height / 4,
+        width: area.width * 3 / 4,
+        height: area.height / 2,
+    };
 
-                        // Highlight selected cell in Edit mode or Data mode
-                        if (app.navigation_mode == NavigationMode::Edit
-                            || app.navigation_mode == NavigationMode::Data)
-                            && i == app.selected_row_idx
-                            && actual_col_idx == app.selected_col_idx
-                        {
-                            if app.navigation_mode == NavigationMode::Edit {
-                                Cell::from(content).style(
-                                    Style::default()
-                                        .fg(theme.edit_text)
-                                        .bg(theme.edit_bg)
-                                        .add_modifier(Modifier::BOLD),
-                                )
-                            } else {
-                                Cell::from(content).style(
-                                    Style::default()
-                                        .fg(theme.selected_text)
-                                        .bg(theme.selected_bg)
-                                        .add_modifier(Modifier::BOLD),
-                                )
-                            }
-                        } else {
-                            Cell::from(content).style(Style::default().fg(theme.text))
-                        }
-                    })
-                    .collect();
+    // Clear the background area first
+    frame.render_widget(Clear, popup_area);
 
-                Row::new(cells)
-            })
-            .collect();
+    let lines: Vec<Line> = match &object.sql {
+        Some(sql) => sql
+            .lines()
+            .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.text))))
+            .collect(),
+        None => vec![Line::from(Span::styled(
+            "(created automatically by SQLite for a UNIQUE or PRIMARY KEY constraint)",
+            Style::default().fg(Color::DarkGray),
+        ))],
+    };
 
-        // Create column widths (for display columns only)
-        let display_col_count = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-            data.columns.len() - 1
-        } else {
-            data.columns.len()
-        };
-        let widths: Vec<Constraint> = (0..display_col_count)
-            .map(|_| Constraint::Percentage(100 / display_col_count.max(1) as u16))
-            .collect();
+    let definition = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(theme.border_set())
+                .title(format!("{} {} on {} - ESC to close", object.kind, object.name, object.table))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false });
 
-        // Skip rowid column for display
-        let display_columns = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-            &data.columns[1..]
-        } else {
-            &data.columns[..]
-        };
+    frame.render_widget(definition, popup_area);
+}
 
-        let col_offset = if !data.columns.is_empty() && data.columns[0] == "rowid" {
-            1
-        } else {
-            0
+/// SQL split into lines of highlighted spans
+fn sql_lines<'a>(sql: &str, theme: &Theme) -> Vec<Line<'a>> {
+    let mut lines = vec![Line::default()];
+    for (kind, text) in highlight_sql(sql) {
+        let style = match kind {
+            SqlTokenKind::Keyword => Style::default().fg(theme.column_header).add_modifier(Modifier::BOLD),
+            SqlTokenKind::String => Style::default().fg(theme.detailed_view_field),
+            SqlTokenKind::Number => Style::default().fg(theme.number),
+            SqlTokenKind::Comment => Style::default().fg(Color::DarkGray),
+            SqlTokenKind::Other => Style::default().fg(theme.text),
         };
-
-        let table = Table::new(rows, widths)
-            .header(Row::new(
-                display_columns
-                    .iter()
-                    .map(|h| {
-                        // Check if this is a computed column
-                        let is_computed = app.computed_columns.iter().any(|col| &col.name == h);
-                        if is_computed {
-                            let header_text = format!("*{}", h);
-                            Cell::from(header_text).style(
-                                Style::default()
-                                    .fg(theme.number)
-                                    .add_modifier(Modifier::BOLD),
-                            )
-                        } else {
-                            Cell::from(h.as_str()).style(
-                                Style::default()
-                                    .fg(theme.column_header)
-                                    .add_modifier(Modifier::BOLD),
-                            )
-                        }
-                    })
-                    .collect::<Vec<_>>(),
-            ))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(Span::styled(title, title_style))
-                    .border_style(border_style),
-            )
-            .style(Style::default().fg(theme.text));
-
-        frame.render_widget(table, area);
-    } else {
-        let placeholder = Paragraph::new("Loading...")
-            .style(Style::default().fg(Color::DarkGray))
-            .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Table Contents")
-                    .border_style(border_style),
-            );
-        frame.render_widget(placeholder, area);
+        // Strings and comments may span lines
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+            }
+            if !part.is_empty() {
+                if let Some(line) = lines.last_mut() {
+                    line.push_span(Span::styled(part.to_string(), style));
+                }
+            }
+        }
     }
+    lines
 }
 
-fn render_query_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+fn render_ddl(frame: &mut Frame, app: &AppState, theme: &Theme) {
     let area = frame.area();
     let popup_area = Rect {
-        x: area.width / 6,
-        y: area.height / 2 - 2,
-        width: area.width * 2 / 3,
-        height: 5,
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
     };
 
     // Clear the background area first
     frame.render_widget(Clear, popup_area);
 
-    let query_input = Paragraph::new(format!("{}_", app.query_input))
-        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+    let mut lines = Vec::new();
+    for (i, statement) in app.ddl.iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::from(""));
+        }
+        lines.extend(sql_lines(&format!("{};", statement), theme));
+    }
+
+    let table = app.current_table().unwrap_or_default();
+    let ddl = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Enter SQL Query (ESC to cancel)")
-                .border_style(Style::default().fg(theme.query_border))
-                .style(Style::default().bg(theme.query_bg)),
-        );
-
-    frame.render_widget(query_input, popup_area);
+                .border_set(theme.border_set())
+                .title(format!("CREATE statements of {} - ESC to close", table))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .scroll((app.ddl_scroll, 0));
+    frame.render_widget(ddl, popup_area);
 }
 
-fn render_edit_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+fn render_profile(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let Some(profile) = &app.table_profile else {
+        return;
+    };
     let area = frame.area();
     let popup_area = Rect {
-        x: area.width / 6,
-        y: area.height.saturating_sub(7),
-        width: area.width * 2 / 3,
-        height: 3,
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
     };
 
     // Clear the background area first
     frame.render_widget(Clear, popup_area);
 
-    let edit_input = Paragraph::new(format!("{}_", app.edit_input))
-        .style(Style::default().fg(theme.edit_text).bg(theme.edit_area_bg))
+    let field = Style::default().fg(theme.detailed_view_field);
+    let value = Style::default().fg(theme.detailed_view_value);
+    let mut lines = Vec::new();
+    for column in &profile.columns {
+        let stats = &column.stats;
+        lines.push(Line::from(vec![
+            Span::styled(stats.column.clone(), Style::default().fg(theme.column_header).add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" ({})", column.column_type.label()), field),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("  Nulls ", field),
+            Span::styled(format!("{} ({:.1}%)", stats.nulls, column.null_percent()), value),
+            Span::styled("  Distinct ", field),
+            Span::styled(format!("{} ({:.1}%)", stats.distinct, column.distinct_percent()), value),
+            Span::styled("  Min ", field),
+            Span::styled(stats.min.clone().unwrap_or_else(|| "-".to_string()), value),
+            Span::styled("  Max ", field),
+            Span::styled(stats.max.clone().unwrap_or_else(|| "-".to_string()), value),
+        ]));
+        if !column.samples.is_empty() {
+            lines.push(Line::from(vec![Span::styled("  Samples ", field), Span::styled(column.samples.join(", "), value)]));
+        }
+        for issue in column.issues() {
+            lines.push(Line::from(Span::styled(format!("  ! {}", issue), Style::default().fg(theme.error))));
+        }
+        lines.push(Line::from(""));
+    }
+
+    let report = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(theme.edit_border))
-                .style(Style::default().bg(theme.edit_area_bg)),
-        );
-
-    frame.render_widget(edit_input, popup_area);
+                .border_set(theme.border_set())
+                .title(format!(
+                    "Profile of {} | {} rows, {} columns - e/E Export Markdown/HTML",
+                    profile.table,
+                    profile.rows,
+                    profile.columns.len()
+                ))
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((app.profile_scroll, 0));
+    frame.render_widget(report, popup_area);
 }
 
-fn render_computed_column_input(frame: &mut Frame, app: &AppState, theme: &Theme) {
+fn render_table_schema(frame: &mut Frame, app: &AppState, theme: &Theme) {
+    let Some(schema) = &app.table_schema else {
+        return;
+    };
     let area = frame.area();
     let popup_area = Rect {
-        x: area.width / 6,
-        y: area.height / 2 - 2,
-        width: area.width * 2 / 3,
-        height: 5,
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
     };
 
     // Clear the background area first
     frame.render_widget(Clear, popup_area);
 
-    let computed_col_input = Paragraph::new(format!("{}_", app.computed_column_input))
-        .style(Style::default().fg(theme.query_text).bg(theme.query_bg))
+    let section = |title: &str| {
+        Line::from(Span::styled(
+            title.to_string(),
+            Style::default().fg(theme.help_section_header).add_modifier(Modifier::BOLD),
+        ))
+    };
+    let field = Style::default().fg(theme.detailed_view_field).add_modifier(Modifier::BOLD);
+    let value = Style::default().fg(theme.detailed_view_value);
+    let name_width = schema.columns.iter().map(|c| c.name.chars().count()).max().unwrap_or(0);
+    let type_width = schema.columns.iter().map(|c| c.column_type.chars().count()).max().unwrap_or(0);
+
+    let mut lines = vec![section("Columns")];
+    for column in &schema.columns {
+        let mut constraints = Vec::new();
+        if column.primary_key {
+            constraints.push("PRIMARY KEY".to_string());
+        }
+        if column.not_null {
+            constraints.push("NOT NULL".to_string());
+        }
+        if let Some(default) = &column.default {
+            constraints.push(format!("DEFAULT {}", default));
+        }
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<width$}  ", column.name, width = name_width), field),
+            Span::styled(
+                format!("{:<width$}  ", column.column_type, width = type_width),
+                Style::default().fg(theme.number),
+            ),
+            Span::styled(constraints.join(" "), value),
+        ]));
+    }
+
+    if !schema.indexes.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(section("Indexes"));
+        for index in &schema.indexes {
+            let kind = match (index.origin.as_str(), index.unique) {
+                ("pk", _) => " PRIMARY KEY",
+                ("u", _) => " UNIQUE constraint",
+                (_, true) => " UNIQUE",
+                _ => "",
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {}", index.name), field),
+                Span::styled(format!(" ({}){}", index.columns.join(", "), kind), value),
+            ]));
+        }
+    }
+
+    if !schema.foreign_keys.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(section("Foreign keys"));
+        for key in &schema.foreign_keys {
+            let mut target = key.table.clone();
+            if !key.references.is_empty() {
+                target.push_str(&format!("({})", key.references.join(", ")));
+            }
+            let mut actions = String::new();
+            if key.on_delete != "NO ACTION" {
+                actions.push_str(&format!(" ON DELETE {}", key.on_delete));
+            }
+            if key.on_update != "NO ACTION" {
+                actions.push_str(&format!(" ON UPDATE {}", key.on_update));
+            }
+            lines.push(Line::from(vec![
+                Span::styled(format!("  ({})", key.columns.join(", ")), field),
+                Span::styled(format!(" {} {}{}", theme.text("→"), target, actions), value),
+            ]));
+        }
+    }
+
+    let table = app.current_table().unwrap_or_default();
+    let title = if schema.inferred {
+        format!("Schema of {} - types inferred from the values, ESC to close", table)
+    } else {
+        format!("Schema of {} - S: all indexes and triggers, ESC to close", table)
+    };
+    let panel = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Computed Column (e.g., sum(Age), column1=Age*2)")
-                .border_style(Style::default().fg(theme.query_border))
-                .style(Style::default().bg(theme.query_bg)),
-        );
-
-    frame.render_widget(computed_col_input, popup_area);
+                .border_set(theme.border_set())
+                .title(title)
+                .border_style(Style::default().fg(theme.detailed_view_border))
+                .style(Style::default().bg(theme.detailed_view_bg)),
+        )
+        .scroll((app.table_schema_scroll, 0));
+    frame.render_widget(panel, popup_area);
 }
 
 fn render_detailed_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
@@ -1927,8 +9637,9 @@ fn render_detailed_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
                 ];
 
                 // Add each field with its value
-                for (i, (column, value)) in data.columns.iter().zip(row_data.iter()).enumerate() {
+                for (i, (column, cell)) in data.columns.iter().zip(row_data.iter()).enumerate() {
                     let is_selected = i == app.detailed_view_selected_field;
+                    let value = &cell.to_string();
 
                     let field_style = if is_selected {
                         Style::default()
@@ -1951,9 +9662,17 @@ fn render_detailed_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
 
                     lines.push(Line::from(vec![
                         Span::styled(format!("{}: ", column), field_style),
-                        Span::styled(value, value_style),
+                        Span::styled(value.clone(), value_style),
                     ]));
 
+                    // Preview WKT/GeoJSON values of the selected field
+                    if let Some(geo) = parse_geometry(value).filter(|_| is_selected) {
+                        lines.extend(geo_preview_lines(&geo, popup_area.width, theme));
+                    } else if is_selected {
+                        // Nested JSON (e.g. from JSON files) is easier to read indented
+                        lines.extend(json_preview_lines(value, theme));
+                    }
+
                     if i < data.columns.len() - 1 {
                         lines.push(Line::from(""));
                     }
@@ -1962,7 +9681,7 @@ fn render_detailed_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
                 lines.push(Line::from(""));
                 lines.push(Line::from(""));
                 lines.push(Line::from(Span::styled(
-                    "↑↓ Navigate fields | c Copy value | ESC Close",
+                    theme.text("↑↓ Navigate fields | c Copy value | ESC Close"),
                     Style::default().fg(Color::DarkGray),
                 )));
 
@@ -1970,6 +9689,7 @@ fn render_detailed_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
                     .block(
                         Block::default()
                             .borders(Borders::ALL)
+                            .border_set(theme.border_set())
                             .title("Detailed View")
                             .border_style(Style::default().fg(theme.detailed_view_border))
                             .style(Style::default().bg(theme.detailed_view_bg)),
@@ -1987,6 +9707,66 @@ fn render_detailed_view(frame: &mut Frame, app: &AppState, theme: &Theme) {
     }
 }
 
+fn json_preview_lines(value: &str, theme: &Theme) -> Vec<Line<'static>> {
+    let trimmed = value.trim();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return Vec::new();
+    }
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(trimmed) else {
+        return Vec::new();
+    };
+    let Ok(pretty) = serde_json::to_string_pretty(&json) else {
+        return Vec::new();
+    };
+    pretty
+        .lines()
+        .map(|line| {
+            Line::from(Span::styled(
+                format!("  {}", line),
+                Style::default().fg(theme.detailed_view_value),
+            ))
+        })
+        .collect()
+}
+
+fn geo_preview_lines(geo: &GeoSummary, popup_width: u16, theme: &Theme) -> Vec<Line<'static>> {
+    let label_style = Style::default().fg(theme.detailed_view_field);
+    let value_style = Style::default().fg(theme.detailed_view_value);
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled("  Geometry: ", label_style),
+        Span::styled(
+            format!("{}, {} points", geo.geometry_type, geo.point_count()),
+            value_style,
+        ),
+    ])];
+
+    if let Some((min_x, min_y, max_x, max_y)) = geo.bounds() {
+        lines.push(Line::from(vec![
+            Span::styled("  Bounds:   ", label_style),
+            Span::styled(
+                format!(
+                    "({}, {}) - ({}, {})",
+                    format_number(min_x),
+                    format_number(min_y),
+                    format_number(max_x),
+                    format_number(max_y)
+                ),
+                value_style,
+            ),
+        ]));
+
+        let width = (popup_width as usize).saturating_sub(8).min(48);
+        for row in ascii_map(geo, width, 12) {
+            lines.push(Line::from(Span::styled(
+                format!("  {}", row),
+                Style::default().fg(theme.number),
+            )));
+        }
+    }
+    lines
+}
+
 fn render_error_display(frame: &mut Frame, app: &AppState, theme: &Theme) {
     let area = frame.area();
     let popup_area = Rect {
@@ -2000,7 +9780,7 @@ fn render_error_display(frame: &mut Frame, app: &AppState, theme: &Theme) {
     frame.render_widget(Clear, popup_area);
 
     if let Some(error_msg) = &app.error_message {
-        let lines = vec![
+        let mut lines = vec![
             Line::from(Span::styled(
                 "Error",
                 Style::default()
@@ -2008,18 +9788,24 @@ fn render_error_display(frame: &mut Frame, app: &AppState, theme: &Theme) {
                     .add_modifier(Modifier::BOLD),
             )),
             Line::from(""),
-            Line::from(Span::styled(error_msg, Style::default().fg(theme.text))),
-            Line::from(""),
-            Line::from(Span::styled(
-                "Press ESC to close",
-                Style::default().fg(Color::DarkGray),
-            )),
         ];
+        for message_line in error_msg.lines() {
+            lines.push(Line::from(Span::styled(
+                message_line,
+                Style::default().fg(theme.text),
+            )));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Press ESC to close",
+            Style::default().fg(Color::DarkGray),
+        )));
 
         let error_display = Paragraph::new(lines)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_set(theme.border_set())
                     .title("Error")
                     .border_style(Style::default().fg(theme.error))
                     .style(Style::default().bg(Color::Black)),
@@ -2044,7 +9830,7 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
     let help_line = |key: &str, desc: &str, theme: &Theme| -> Line {
         Line::from(vec![
             Span::styled(
-                key.to_string(),
+                theme.text(key).into_owned(),
                 Style::default()
                     .fg(theme.help_key)
                     .add_modifier(Modifier::BOLD),
@@ -2072,7 +9858,25 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
                 .add_modifier(Modifier::BOLD),
         )),
         help_line("  ↑↓", "Navigate tables", theme),
-        help_line("  →/Enter", "Enter table data view", theme),
+        help_line("  →/Enter", "Enter table data view; fold a section, show an index/trigger's SQL", theme),
+        help_line("  *", "Pin/unpin the table at the top of the list", theme),
+        help_line("  r", "Refresh tables and data from disk", theme),
+        help_line("  Ctrl+D", "Fill the cell down the page: copies, or Tab for a series of numbers or dates", theme),
+        help_line("  V", "Select rows from the cursor to copy (y), delete (d), export (e) or fill (Ctrl+D)", theme),
+        help_line("  A", "Duplicate the selected row as a new row, leaving its key to the database", theme),
+        help_line("  +", "Add a row through a form, a column at a time", theme),
+        help_line("  U", "Bulk edit with column = expression [WHERE condition], previewed on the page first", theme),
+        help_line("  Ctrl+Z/Ctrl+R", "Undo / redo the last edit, new row or paste before saving", theme),
+        help_line("  F/Ctrl+F", "Search all tables", theme),
+        help_line("  x", "Open the file in an external tool", theme),
+        help_line("  B", "List bookmarks", theme),
+        help_line("  S", "Show the table's columns, keys and indexes (S again: all indexes and triggers)", theme),
+        help_line("  L", "Show the CREATE statements of the table (SQLite)", theme),
+        help_line("  A", "Attach another SQLite file for cross-database queries", theme),
+        help_line("  I", "Query the whole database; results open in their own tab", theme),
+        help_line("  R", "Show the database query results again", theme),
+        help_line("  Tab/S-Tab", "Next/previous open file", theme),
+        help_line("  Alt+1-9", "Go to the open file with that number", theme),
         help_line("  h", "Toggle this help", theme),
         help_line("  Ctrl+C", "Exit application", theme),
         Line::from(""),
@@ -2086,18 +9890,67 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
         help_line("  ←", "Back to table list (when at first column)", theme),
         help_line("  Space", "Enter edit mode for selected cell", theme),
         help_line("  Enter", "Show detailed view for selected row", theme),
-        help_line("  n", "Add new row", theme),
+        help_line("  a", "Add new row", theme),
         help_line("  PgUp/Dn", "Page navigation", theme),
         help_line("  Home", "Go to first page", theme),
         help_line("  End", "Go to last page", theme),
+        help_line("  g", "Go to a row number, or a percentage of the table such as 50%", theme),
+        help_line("  o", "Sort by column (asc/desc/off)", theme),
+        help_line("  /", "Search the table (re: for a regex); matching cells are highlighted", theme),
+        help_line("  n/N", "Jump to the next/previous match, across pages", theme),
+        help_line("  w", "Filter the column: >100, <=x, !=x, contains:foo, re:regex, =NULL or a value", theme),
+        help_line("  L", "Show the CREATE statements of the table (SQLite)", theme),
+        help_line("  f", "Follow the foreign key of the cell to the row(s) it refers to (SQLite)", theme),
+        help_line("  Backspace", "Go back to where the foreign key was followed from", theme),
+        help_line("  W", "Clear all filters", theme),
+        help_line("  C", "Show, hide, add (+), rename (r) or drop (d) columns, or fit a resized one again (w)", theme),
+        help_line("  p", "Pin the column to the left while scrolling sideways, or unpin it", theme),
+        help_line("  </>", "Narrow/widen the column, remembered per table", theme),
         help_line("  i", "Enter query mode (SQLite only)", theme),
-        help_line("  =", "Add computed column (name=expression)", theme),
-        help_line("  e", "Export to CSV", theme),
-        help_line("  s", "Save changes", theme),
+        help_line("  I", "Query the whole database; results open in their own tab", theme),
+        help_line("  R", "Show the database query results again", theme),
+        help_line("  =", "Add computed column (name=expression): math, sum(x), cumsum(x), rank(x), lag(x[, n]), lead(x[, n]), rolling_mean(x, n)", theme),
+        help_line("  M", "Manage computed columns: edit (e), rename (r), delete (d) or reorder (Shift+↑↓)", theme),
+        help_line("  D", "Diff against another table (other_table [key])", theme),
+        help_line("  P", "Profile the table: types, nulls, distinct values, min/max, samples and suspicious values", theme),
+        help_line("  J", "Join with a table of another file (file key[=other_key] [left|inner] [table])", theme),
+        help_line("  |", "Pipe row/column/table as CSV to a shell command", theme),
+        help_line("  b", "Bookmark the selected cell or row", theme),
+        help_line("  t", "Show stats of the column: nulls, distinct, min/max, mean/median/std dev or top values", theme),
+        help_line("  v", "Chart the column: a histogram of numbers, or its most frequent values", theme),
+        help_line("  u", "Count the column's values; Enter on one filters the table on it", theme),
+        help_line("  H", "Toggle readable epoch timestamps and UUID blobs", theme),
+        help_line("  E", "Export the table to a SQLite database with inferred types", theme),
+        help_line("  y/Y", "Copy the cell / the row to the clipboard (tab-separated)", theme),
+        help_line("  Ctrl+Y", "Copy the page with its header to the clipboard as TSV", theme),
+        help_line("  Ctrl+V", "Paste tab-separated text over the cells from the selected one (asks first)", theme),
+        help_line("  F/Ctrl+F", "Search all tables", theme),
+        help_line("  x", "Open the file in an external tool", theme),
+        help_line("  B", "List bookmarks", theme),
+        help_line("  Q", "List saved queries to run again", theme),
+        help_line("  e", "Export the table or query (all rows, the page or the selected row) to CSV, Parquet, JSON, Markdown, HTML or a command", theme),
+        help_line("  s", "Save changes (SQLite: asks before writing to the database)", theme),
+        help_line("  Z", "Revert the last save to the database, also one made before a restart, or forget it (SQLite)", theme),
         help_line("  r", "Refresh data", theme),
+        help_line("  Tab/S-Tab", "Next/previous open file", theme),
+        help_line("  Alt+1-9", "Go to the open file with that number", theme),
         help_line("  h", "Toggle this help", theme),
         help_line("  Ctrl+C", "Exit application", theme),
         Line::from(""),
+        Line::from(Span::styled(
+            "Vim Keys (\"keymap\": \"vim\" in the config):",
+            Style::default()
+                .fg(theme.help_section_header)
+                .add_modifier(Modifier::BOLD),
+        )),
+        help_line("  hjkl", "Move the cursor, as the arrows", theme),
+        help_line("  gg/G", "Go to first/last page", theme),
+        help_line("  Ctrl+D/U", "Scroll half a page down/up", theme),
+        help_line("  0/$", "Go to first/last column", theme),
+        help_line("  Ctrl+G", "Go to a row number or percentage (g otherwise)", theme),
+        help_line("  :", "Query the table", theme),
+        help_line("  ?", "Toggle this help", theme),
+        Line::from(""),
         Line::from(Span::styled(
             "Edit Mode:",
             Style::default()
@@ -2109,6 +9962,7 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
         help_line("  Enter", "Save changes and exit edit mode", theme),
         help_line("  Tab", "Save and move to next cell", theme),
         help_line("  Ctrl+N", "Add new row", theme),
+        help_line("  Ctrl+E", "Edit in the multi-line editor, used by itself for long text (Alt+Enter: new line, Ctrl+arrows: resize)", theme),
         help_line("  ESC", "Cancel edit", theme),
         Line::from(""),
         Line::from(Span::styled(
@@ -2118,7 +9972,12 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
                 .add_modifier(Modifier::BOLD),
         )),
         help_line("  Type", "Type your SQL query", theme),
-        help_line("  Enter", "Execute query", theme),
+        help_line("  Enter", "Execute query; statements that change a SQLite database ask first", theme),
+        help_line("  Tab", "Complete a table or column name; again for the next match", theme),
+        help_line("  ↑↓", "Recall earlier queries on this file", theme),
+        help_line("  Ctrl+R", "Search the query history", theme),
+        help_line("  Ctrl+S", "Save the query under a name", theme),
+        help_line("  :name", "Placeholder whose value is asked for when the query runs", theme),
         help_line("  ESC", "Cancel query", theme),
         Line::from(""),
         Line::from(Span::styled(
@@ -2165,6 +10024,7 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
+                .border_set(theme.border_set())
                 .title("Help")
                 .border_style(Style::default().fg(theme.help))
                 .style(Style::default().bg(theme.help_bg)),
@@ -2180,25 +10040,98 @@ fn render_help(frame: &mut Frame, theme: &Theme) {
 
 fn render_footer(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
     let footer_text = match app.navigation_mode {
-        NavigationMode::Table => "↑↓ Navigate | → Enter | h Help | Ctrl+C Exit",
-        NavigationMode::Data => "↑↓←→ Navigate | ← Back | Space Edit | Enter Details | n New Row | PgUp/Dn Page | i Query | = Computed | e Export | s Save | h Help | Ctrl+C Exit",
-        NavigationMode::Query => "Type query | Enter Execute | ESC Cancel",
-        NavigationMode::Edit => "Type to edit | ↑↓←→ Navigate | Enter Save | Tab Next | Ctrl+N New Row | ESC Cancel",
+        NavigationMode::Table => "↑↓ Navigate | → Enter | * Pin | I Query database | F Search all | x Tools | B Bookmarks | S Schema | L DDL | A Attach | h Help | Ctrl+C Exit",
+        NavigationMode::Data => "↑↓←→ Navigate | ← Back | Space Edit | Enter Details | a New Row | PgUp/Dn Page | g Go to row | o Sort | / Search | n/N Next/Prev | w Filter | W Clear filters | C Columns | p Pin | </> Width | t Stats | v Chart | u Values | f Follow key | i/I Query table/database | = Computed | M Manage computed | D Diff | J Join | P Profile | F Search all | | Pipe | x Tools | b/B Bookmark | Q Saved queries | y/Y/Ctrl+Y Copy | Ctrl+D Fill down | U Bulk edit | Ctrl+Z/Ctrl+R Undo/Redo | e/E Export | s Save | Z Revert save | h Help | Ctrl+C Exit",
+        NavigationMode::Query => "Type query | Tab Complete name | ↑↓ History | Ctrl+R Search history | Ctrl+S Save | Enter Execute | ESC Cancel",
+        NavigationMode::SavedQueryName => "Type name (optional) | Enter Save | ESC Back",
+        NavigationMode::SavedQueries => "↑↓ Navigate | Enter Run | e Edit | d Delete | ESC Close",
+        NavigationMode::QueryHistory => "Type to search | ↑↓ Navigate | Ctrl+R Older match | Enter Recall | ESC Back",
+        NavigationMode::Edit => "Type to edit | ↑↓←→ Navigate | Enter Save | Tab Next | Ctrl+N New Row | Ctrl+V Paste | Ctrl+D Fill down | Ctrl+E Multi-line | ESC Cancel",
         NavigationMode::DetailedView => "↑↓ Navigate fields | c Copy value | ESC Close",
         NavigationMode::ErrorDisplay => "ESC Close error",
-        NavigationMode::ComputedColumn => "Type expression | Enter Add | ESC Cancel",
+        NavigationMode::ComputedColumn => match app.computed_column_edit {
+            Some(ComputedColumnEdit::Rename(_)) => "Type the new name | Enter Rename | ESC Back",
+            Some(ComputedColumnEdit::Expression(_)) => "Type name=expression | Enter Apply | ESC Back",
+            Some(ComputedColumnEdit::Add) => "Type expression | Enter Add | ESC Back",
+            None => "Type expression | Enter Add | ESC Cancel",
+        },
+        NavigationMode::ComputedColumns => "↑↓ Select | = Add | e Edit | r Rename | d Delete | Shift+↑↓ or K/J Move | ESC/Enter Close",
+        NavigationMode::DiffInput => "Type other_table [key_column] | Enter Compare | ESC Cancel",
+        NavigationMode::JoinInput => "Type file key[=other_key] [left|inner] [table] | Enter Join | ESC Cancel",
+        NavigationMode::Profile => "↑↓ Scroll | PgUp/Dn Page | e Export Markdown | E Export HTML | ESC Close",
+        NavigationMode::Join => "↑↓←→ Navigate | PgUp/Dn Page | Home/End First/last row | e Export CSV | ESC Close",
+        NavigationMode::GlobalSearch => "Type search term | Enter Search | ESC Cancel",
+        NavigationMode::SearchResults => "↑↓ Navigate | Enter Jump to row | ESC Close",
+        NavigationMode::BookmarkInput => "Type name (optional) | Tab Row/Cell | Enter Save | ESC Cancel",
+        NavigationMode::Bookmarks => "↑↓ Navigate | Enter Jump | d Delete | ESC Close",
+        NavigationMode::SchemaObjects => "↑↓ Navigate | Enter Go to table | ESC Close",
+        NavigationMode::ObjectDefinition => "ESC/Enter Close",
+        NavigationMode::TableSchema => "↑↓ Scroll | PgUp/PgDn Page | S All indexes and triggers | ESC Close",
+        NavigationMode::Ddl => "↑↓ Scroll | PgUp/PgDn Page | Home Top | ESC Close",
+        NavigationMode::ConfirmSave => "y/Enter Write to database | n/ESC Cancel",
+        NavigationMode::ConfirmRevert => "y/Enter Undo the save in the database | d Forget the save | n/ESC Cancel",
+        NavigationMode::ConfirmFileSave => "y/Enter Overwrite the file | n/ESC Cancel",
+        NavigationMode::ConfirmPaste => "y/Enter Change the cells | n/ESC Cancel",
+        NavigationMode::FillDown => "Type rows to fill | Tab Copy/Series | Enter Fill | ESC Cancel",
+        NavigationMode::BulkEditInput => "Type column = expression [WHERE condition] | Enter Preview | ESC Cancel",
+        NavigationMode::Visual => "↑↓ Extend selection | y/Y Copy (Y with header) | d Delete | e Export | Ctrl+D Fill | V/ESC Leave",
+        NavigationMode::TextEdit => "Type to edit | ↑↓←→ Home/End Move | Alt+Enter New line | Ctrl+arrows Resize | Enter Save | ESC Cancel",
+        NavigationMode::InsertForm => "Type the value | ↑↓/Tab Move between columns | Enter Next column, adds the row after the last | ESC Cancel",
+        NavigationMode::BulkPreview => "y/Enter Change the page's cells | a Run on the whole table (SQLite) | n/ESC Cancel",
+        NavigationMode::ConfirmStatement => "y/Enter Run statement | n/ESC Cancel",
+        NavigationMode::QueryParameters => "Type value ('quoted' for text, NULL) | Tab/↑↓ Next parameter | Enter Run | ESC Edit query",
+        NavigationMode::FilterInput => "Enter Apply | empty Enter Remove | ESC Cancel",
+        NavigationMode::GotoRow => "Enter Go | ESC Cancel",
+        NavigationMode::CellSearch => "Enter Search | empty Enter Clear | ESC Cancel",
+        NavigationMode::ColumnManager => "↑↓ Select | Space Show/Hide | a Show all | + Add | r Rename | d Drop | ESC/Enter Close",
+        NavigationMode::ColumnEditInput => "Type name [type] [= default] or the new name | Enter Apply | ESC Back",
+        NavigationMode::ConfirmDropColumn => "y/Enter Drop the column | n/ESC Cancel",
+        NavigationMode::AttachInput => "Type path to a SQLite file | Enter Attach | ESC Cancel",
+        NavigationMode::ToolPicker => "↑↓ Navigate | Enter Launch | ESC Cancel",
+        NavigationMode::PipeInput => "Type command | Tab Row/Column/Table | Enter Run | ESC Cancel",
+        NavigationMode::PipeOutput => "↑↓ Scroll | PgUp/PgDn Page | ESC Close",
+        NavigationMode::Diff => "↑↓ Navigate | PgUp/Dn Page | + added - removed ~ changed | e Export CSV | ESC Close",
+        NavigationMode::DatabaseResults => "↑↓←→ Navigate | PgUp/Dn Page | Home/End First/last page | I New query | e Export/pipe | ESC Back to table",
+        NavigationMode::ExportPath => "Tab Complete path | Enter Export | ESC Back to formats",
+        NavigationMode::ExportResults => "↑↓ Choose | r All/page/selected rows | c Hidden columns | v Computed columns | t Typed/text JSON | type a command on the Pipe line | Enter Export/Run | ESC Cancel",
+        NavigationMode::ColumnSummary => match &app.column_summary {
+            Some(ColumnSummary::ValueCounts(_)) => "↑↓ Navigate | PgUp/Dn Page | Enter Filter on value | ESC Close",
+            _ => "ESC/Enter Close",
+        },
     };
 
     let mut footer_content = vec![Line::from(Span::styled(
-        footer_text,
+        theme.text(footer_text),
         Style::default().fg(Color::DarkGray),
     ))];
 
-    if let Some(status) = &app.status_message {
+    if let Some(running) = &app.running_query {
+        let spinner = spinner_frame(running.started, theme);
+        let elapsed = running.started.elapsed();
+        let text = if running.cancelled {
+            format!("{} Cancelling...", spinner)
+        } else if running.cancellable {
+            format!("{} {} ({}s) - ESC to cancel", spinner, running.label, elapsed.as_secs())
+        } else {
+            format!("{} {} ({}s)", spinner, running.label, elapsed.as_secs())
+        };
+        footer_content.insert(
+            0,
+            Line::from(Span::styled(text, Style::default().fg(theme.status).add_modifier(Modifier::BOLD))),
+        );
+    } else if let Some(status) = &app.status_message {
         footer_content.insert(
             0,
             Line::from(Span::styled(status, Style::default().fg(theme.status))),
         );
+    } else if app.external_change {
+        footer_content.insert(
+            0,
+            Line::from(Span::styled(
+                "Data changed on disk - press r to refresh",
+                Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+            )),
+        );
     }
 
     let footer = Paragraph::new(footer_content)
@@ -2206,8 +10139,109 @@ fn render_footer(frame: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
+                .border_set(theme.border_set())
                 .border_style(Style::default().fg(theme.border)),
         );
 
     frame.render_widget(footer, area);
 }
+
+/// Split `other_table [key_column]` typed at the diff prompt. Table and sheet names may hold
+/// spaces, so the longest known name the input starts with is taken; a name in quotes is taken
+/// as written.
+fn split_table_and_key(input: &str, tables: &[String]) -> Option<(String, Option<String>)> {
+    let input = input.trim();
+    let (table, rest) = match input.strip_prefix('"').and_then(|quoted| quoted.split_once('"')) {
+        Some((table, rest)) => (table, rest),
+        None => {
+            let table = tables
+                .iter()
+                .filter(|table| {
+                    input
+                        .strip_prefix(table.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+                })
+                .max_by_key(|table| table.len())?;
+            (table.as_str(), &input[table.len()..])
+        }
+    };
+    if !tables.iter().any(|t| t == table) {
+        return None;
+    }
+    let key = rest.trim();
+    Some((table.to_string(), (!key.is_empty()).then(|| key.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_table_and_key() {
+        let tables: Vec<String> = ["Sheet", "Sheet 1", "orders"].iter().map(|t| t.to_string()).collect();
+        let split = |input: &str| split_table_and_key(input, &tables);
+        assert_eq!(split("Sheet 1"), Some(("Sheet 1".to_string(), None)));
+        assert_eq!(split("Sheet 1 id"), Some(("Sheet 1".to_string(), Some("id".to_string()))));
+        assert_eq!(split("Sheet id"), Some(("Sheet".to_string(), Some("id".to_string()))));
+        assert_eq!(split(" orders  order id "), Some(("orders".to_string(), Some("order id".to_string()))));
+        assert_eq!(split("\"Sheet\" 1"), Some(("Sheet".to_string(), Some("1".to_string()))));
+        assert_eq!(split("Sheet 12"), Some(("Sheet".to_string(), Some("12".to_string()))));
+        assert_eq!(split("Sheet1"), None);
+        assert_eq!(split("\"Sheet 2\""), None);
+        assert_eq!(split(""), None);
+    }
+
+    #[test]
+    fn test_goto_row() {
+        assert_eq!(goto_row("1500", 3000).unwrap(), 1499);
+        assert_eq!(goto_row("50%", 3000).unwrap(), 1500);
+        assert_eq!(goto_row("0%", 3000).unwrap(), 0);
+        assert_eq!(goto_row("100%", 3000).unwrap(), 2999);
+        assert_eq!(goto_row("1,000", 3000).unwrap(), 999);
+        assert_eq!(goto_row("5000", 3000).unwrap(), 2999);
+        assert!(goto_row("150%", 3000).is_err());
+        assert!(goto_row("abc", 3000).is_err());
+        assert!(goto_row("", 3000).is_err());
+    }
+
+    #[test]
+    fn test_jump_to_row_drops_saved_filters() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("jump.db");
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE other (x TEXT);
+             CREATE TABLE t (id INTEGER PRIMARY KEY, n INTEGER);
+             WITH RECURSIVE s(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM s WHERE i < 30) INSERT INTO t SELECT i, i FROM s;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let mut data_source = DataSource::open(path.clone()).unwrap();
+        let tables = data_source.get_tables().unwrap();
+        let mut app = AppState::new(path.to_string_lossy().to_string(), tables).unwrap();
+        app.selected_table_idx = app.tables.iter().position(|t| t == "other").unwrap();
+        app.page_size = 10;
+        let view_state = TableViewState {
+            filters: vec![crate::persistence::ColumnFilter { column: "n".to_string(), expression: ">20".to_string() }],
+            ..Default::default()
+        };
+        let effective_path = app.get_effective_persistence_path(&data_source);
+        app.persistence.save_view_state(&effective_path, "t", &view_state).unwrap();
+
+        let found = SearchMatch { table: "t".to_string(), rowid: 15, column: "n".to_string(), snippet: String::new() };
+        app.jump_to_match(&found, &mut data_source).unwrap();
+        assert!(app.filters.is_empty());
+        let data = app.current_data.as_ref().unwrap();
+        assert_eq!(data.rows[app.selected_row_idx][1], "15");
+        assert_eq!(data.columns[app.selected_col_idx], "n");
+    }
+
+    #[test]
+    fn test_fitted_width() {
+        assert_eq!(fitted_width("name", [2, 7].into_iter()), 7);
+        assert_eq!(fitted_width("id", [1].into_iter()), 3);
+        assert_eq!(fitted_width("notes", [80].into_iter()), 40);
+        assert_eq!(fitted_width("日本", std::iter::empty()), 4);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::database::QueryResult;
+use crate::value::Value;
+
+/// A cell changed by an edit, with its value before and after
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellChange {
+    pub row: usize,
+    pub col: usize,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl CellChange {
+    /// Put `value` in a cell of `data`, returning the change for the log
+    pub fn make(data: &mut QueryResult, row: usize, col: usize, value: Value) -> Self {
+        let before = std::mem::replace(&mut data.rows[row][col], value.clone());
+        Self { row, col, before, after: value }
+    }
+}
+
+/// One step of the edit history of the loaded page
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageEdit {
+    Cells(Vec<CellChange>),                       // a typed value or a whole paste
+    RowInserted { row: usize, values: Vec<Value> }, // a new row, with the values it was added with
+    RowsDeleted { row: usize, rows: Vec<Vec<Value>> }, // rows from `row` on, as they were
+}
+
+impl PageEdit {
+    /// Take `count` rows from `row` on out of `data`, returning the step that did it
+    pub fn delete_rows(data: &mut QueryResult, row: usize, count: usize) -> Self {
+        let end = (row + count).min(data.rows.len());
+        let rows: Vec<Vec<Value>> = data.rows.drain(row.min(end)..end).collect();
+        data.total_rows = data.total_rows.saturating_sub(rows.len());
+        PageEdit::RowsDeleted { row, rows }
+    }
+}
+
+impl PageEdit {
+    /// What the step did, for the status line
+    pub fn describe(&self) -> String {
+        match self {
+            PageEdit::Cells(changes) if changes.len() == 1 => "cell edit".to_string(),
+            PageEdit::Cells(changes) => format!("edit of {} cells", changes.len()),
+            PageEdit::RowInserted { .. } => "new row".to_string(),
+            PageEdit::RowsDeleted { rows, .. } if rows.len() == 1 => "row deletion".to_string(),
+            PageEdit::RowsDeleted { rows, .. } => format!("deletion of {} rows", rows.len()),
+        }
+    }
+
+    fn revert(&self, data: &mut QueryResult) {
+        match self {
+            PageEdit::Cells(changes) => {
+                for change in changes.iter().rev() {
+                    set_cell(data, change.row, change.col, &change.before);
+                }
+            }
+            PageEdit::RowInserted { row, .. } => {
+                if *row < data.rows.len() {
+                    data.rows.remove(*row);
+                    data.total_rows = data.total_rows.saturating_sub(1);
+                }
+            }
+            PageEdit::RowsDeleted { row, rows } => {
+                let at = (*row).min(data.rows.len());
+                data.rows.splice(at..at, rows.iter().cloned());
+                data.total_rows += rows.len();
+            }
+        }
+    }
+
+    fn apply(&self, data: &mut QueryResult) {
+        match self {
+            PageEdit::Cells(changes) => {
+                for change in changes {
+                    set_cell(data, change.row, change.col, &change.after);
+                }
+            }
+            PageEdit::RowInserted { row, values } => {
+                data.rows.insert((*row).min(data.rows.len()), values.clone());
+                data.total_rows += 1;
+            }
+            PageEdit::RowsDeleted { row, rows } => {
+                PageEdit::delete_rows(data, *row, rows.len());
+            }
+        }
+    }
+}
+
+fn set_cell(data: &mut QueryResult, row: usize, col: usize, value: &Value) {
+    if let Some(cell) = data.rows.get_mut(row).and_then(|row| row.get_mut(col)) {
+        *cell = value.clone();
+    }
+}
+
+/// Unsaved edits of the loaded page, undone with Ctrl+Z and redone with Ctrl+R. The page
+/// as loaded stays in `AppState::original_data`, which saving compares against.
+#[derive(Debug, Clone, Default)]
+pub struct EditLog {
+    undo: Vec<PageEdit>,
+    redo: Vec<PageEdit>,
+}
+
+impl EditLog {
+    /// Remember an edit already made to the page; a new edit drops what could be redone
+    pub fn record(&mut self, edit: PageEdit) {
+        let edit = match edit {
+            PageEdit::Cells(changes) => {
+                let changes: Vec<CellChange> = changes.into_iter().filter(|c| c.before != c.after).collect();
+                if changes.is_empty() {
+                    return;
+                }
+                PageEdit::Cells(changes)
+            }
+            edit => edit,
+        };
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Revert the last edit on `data`, returning it
+    pub fn undo(&mut self, data: &mut QueryResult) -> Option<&PageEdit> {
+        let edit = self.undo.pop()?;
+        edit.revert(data);
+        self.redo.push(edit);
+        self.redo.last()
+    }
+
+    /// Make the last undone edit again on `data`, returning it
+    pub fn redo(&mut self, data: &mut QueryResult) -> Option<&PageEdit> {
+        let edit = self.redo.pop()?;
+        edit.apply(data);
+        self.undo.push(edit);
+        self.undo.last()
+    }
+
+    /// Whether the page has edits that were not undone
+    pub fn has_edits(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::text_rows;
+
+    fn page() -> QueryResult {
+        QueryResult {
+            columns: vec!["a".to_string(), "b".to_string()],
+            rows: text_rows(vec![vec!["1", "x"], vec!["2", "y"]]),
+            total_rows: 2,
+        }
+    }
+
+    fn edit(data: &mut QueryResult, log: &mut EditLog, row: usize, col: usize, value: &str) {
+        let change = CellChange::make(data, row, col, Value::Text(value.to_string()));
+        log.record(PageEdit::Cells(vec![change]));
+    }
+
+    #[test]
+    fn test_undo_and_redo_cell_edits() {
+        let mut data = page();
+        let mut log = EditLog::default();
+        edit(&mut data, &mut log, 0, 1, "changed");
+        edit(&mut data, &mut log, 1, 0, "3");
+        assert!(log.has_edits());
+
+        assert_eq!(log.undo(&mut data).map(PageEdit::describe).as_deref(), Some("cell edit"));
+        assert_eq!(data.rows[1][0], "2");
+        log.undo(&mut data);
+        assert_eq!(data.rows, page().rows);
+        assert!(!log.has_edits());
+        assert!(log.undo(&mut data).is_none());
+
+        log.redo(&mut data);
+        assert_eq!(data.rows[0][1], "changed");
+        assert_eq!(data.rows[1][0], "2");
+
+        // A new edit drops what could be redone
+        edit(&mut data, &mut log, 1, 1, "z");
+        assert!(log.redo(&mut data).is_none());
+    }
+
+    #[test]
+    fn test_undo_row_insert_and_paste() {
+        let mut data = page();
+        let mut log = EditLog::default();
+        let values = vec![Value::Text(String::new()), Value::Text(String::new())];
+        data.rows.push(values.clone());
+        data.total_rows += 1;
+        log.record(PageEdit::RowInserted { row: 2, values });
+        let changes = vec![
+            CellChange { row: 2, col: 0, before: Value::Text(String::new()), after: Value::Int(5) },
+            CellChange { row: 2, col: 1, before: Value::Text(String::new()), after: Value::Text("w".to_string()) },
+        ];
+        for change in &changes {
+            data.rows[change.row][change.col] = change.after.clone();
+        }
+        log.record(PageEdit::Cells(changes));
+
+        assert_eq!(log.undo(&mut data).map(PageEdit::describe).as_deref(), Some("edit of 2 cells"));
+        assert_eq!(data.rows[2], vec!["", ""]);
+        assert_eq!(log.undo(&mut data).map(PageEdit::describe).as_deref(), Some("new row"));
+        assert_eq!((data.rows.len(), data.total_rows), (2, 2));
+
+        log.redo(&mut data);
+        log.redo(&mut data);
+        assert_eq!(data.rows[2], vec![Value::Int(5), Value::Text("w".to_string())]);
+        assert_eq!(data.total_rows, 3);
+    }
+
+    #[test]
+    fn test_undo_row_deletion() {
+        let mut data = page();
+        let mut log = EditLog::default();
+        let edit = PageEdit::delete_rows(&mut data, 0, 5);
+        assert_eq!(edit.describe(), "deletion of 2 rows");
+        log.record(edit);
+        assert_eq!((data.rows.len(), data.total_rows), (0, 0));
+
+        log.undo(&mut data);
+        assert_eq!(data.rows, page().rows);
+        assert_eq!(data.total_rows, 2);
+        log.redo(&mut data);
+        assert!(data.rows.is_empty());
+    }
+
+    #[test]
+    fn test_unchanged_cells_are_not_recorded() {
+        let mut data = page();
+        let mut log = EditLog::default();
+        edit(&mut data, &mut log, 0, 0, "1");
+        assert!(!log.has_edits());
+    }
+}
@@ -0,0 +1,213 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+
+use chrono::{NaiveDateTime, Timelike};
+
+use crate::config::NumberLocale;
+use crate::file_reader::{parse_bool, parse_date};
+use crate::format::parse_number;
+
+/// One cell of a table, typed as its source stores it. Sources without types (CSV, HTML
+/// tables, text columns) give `Text`, whose type is inferred per column for display.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bool(bool),
+    Date(NaiveDateTime), // dates without a time are at midnight
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// An empty text cell, such as those of a row just added
+    pub fn is_blank(&self) -> bool {
+        matches!(self, Value::Text(text) if text.is_empty())
+    }
+
+    /// A date without a time of day (stored at midnight)
+    pub fn is_date_only(&self) -> bool {
+        matches!(self, Value::Date(dt) if dt.num_seconds_from_midnight() == 0 && dt.nanosecond() == 0)
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Value::Int(_) | Value::Float(_))
+    }
+
+    /// The cell as a number: typed numbers as they are, text parsed in the given locale
+    pub fn as_number(&self, locale: NumberLocale) -> Option<f64> {
+        match self {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(x) => Some(*x),
+            Value::Text(text) => parse_number(text, locale),
+            _ => None,
+        }
+    }
+
+    /// The cell as text, borrowed when it already is text
+    pub fn as_text(&self) -> Cow<'_, str> {
+        match self {
+            Value::Text(text) => Cow::Borrowed(text),
+            other => Cow::Owned(other.to_string()),
+        }
+    }
+
+    /// Name of the value's type, as shown in edit errors
+    pub fn kind_label(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "number",
+            Value::Text(_) => "text",
+            Value::Bool(_) => "boolean",
+            Value::Date(_) => "date",
+            Value::Blob(_) => "blob",
+        }
+    }
+
+    /// Text typed over this cell, read as a value of the same type. `NULL` (or nothing, for
+    /// typed cells) clears the cell; None when the text doesn't fit the type.
+    pub fn parse_like(&self, input: &str) -> Option<Value> {
+        let trimmed = input.trim();
+        if trimmed == "NULL" || (trimmed.is_empty() && !matches!(self, Value::Text(_) | Value::Null)) {
+            return Some(Value::Null);
+        }
+        match self {
+            Value::Int(_) => trimmed.parse().ok().map(Value::Int),
+            Value::Float(_) => trimmed.parse().ok().map(Value::Float),
+            Value::Bool(_) => parse_bool(trimmed).map(Value::Bool),
+            Value::Date(_) => parse_date(trimmed).map(Value::Date),
+            // Blobs are shown by size, so their bytes can't be typed back
+            Value::Blob(_) => None,
+            Value::Null | Value::Text(_) => Some(Value::Text(input.to_string())),
+        }
+    }
+
+    /// Sort order: NULLs first, numbers by value (integers and floats together), then
+    /// booleans, dates, text and blobs
+    pub fn compare(&self, other: &Value) -> Ordering {
+        fn rank(value: &Value) -> u8 {
+            match value {
+                Value::Null => 0,
+                Value::Int(_) | Value::Float(_) => 1,
+                Value::Bool(_) => 2,
+                Value::Date(_) => 3,
+                Value::Text(_) => 4,
+                Value::Blob(_) => 5,
+            }
+        }
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Date(dt) if self.is_date_only() => write!(f, "{}", dt.format("%Y-%m-%d")),
+            Value::Date(dt) => write!(f, "{}", dt.format("%Y-%m-%d %H:%M:%S%.f")),
+            // 16-byte blobs are usually UUIDs; keep their bytes as a SQL blob literal
+            Value::Blob(bytes) if bytes.len() == 16 => {
+                write!(f, "x'")?;
+                for byte in bytes {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, "'")
+            }
+            Value::Blob(bytes) => write!(f, "[BLOB {} bytes]", bytes.len()),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_string())
+    }
+}
+
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        self.as_text() == other
+    }
+}
+
+impl PartialEq<String> for Value {
+    fn eq(&self, other: &String) -> bool {
+        self.as_text() == other.as_str()
+    }
+}
+
+impl PartialEq<&str> for Value {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_text() == *other
+    }
+}
+
+/// Rows of text cells, as read from untyped sources (and written in tests)
+pub fn text_rows<S: Into<String>>(rows: Vec<Vec<S>>) -> Vec<Vec<Value>> {
+    rows.into_iter()
+        .map(|row| row.into_iter().map(|cell| Value::Text(cell.into())).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_display_values() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(Value::Date(date.and_hms_opt(0, 0, 0).unwrap()).to_string(), "2024-03-01");
+        assert_eq!(
+            Value::Date(date.and_hms_milli_opt(9, 30, 0, 500).unwrap()).to_string(),
+            "2024-03-01 09:30:00.500"
+        );
+        assert_eq!(Value::Float(2.5).to_string(), "2.5");
+        assert_eq!(Value::Null.to_string(), "NULL");
+        assert_eq!(Value::Blob(vec![0xab; 16]).to_string(), format!("x'{}'", "ab".repeat(16)));
+        assert_eq!(Value::Blob(vec![1, 2]).to_string(), "[BLOB 2 bytes]");
+        assert_eq!(Value::Int(7), "7");
+    }
+
+    #[test]
+    fn test_parse_like() {
+        assert_eq!(Value::Int(1).parse_like("42"), Some(Value::Int(42)));
+        assert_eq!(Value::Int(1).parse_like("4.2"), None);
+        assert_eq!(Value::Float(1.0).parse_like(""), Some(Value::Null));
+        assert_eq!(Value::Bool(true).parse_like("no"), Some(Value::Bool(false)));
+        assert_eq!(Value::Text("a".into()).parse_like(""), Some(Value::Text(String::new())));
+        assert_eq!(Value::Null.parse_like("12"), Some(Value::Text("12".into())));
+        assert!(Value::Date(NaiveDateTime::default()).parse_like("soon").is_none());
+    }
+
+    #[test]
+    fn test_compare_values() {
+        let mut values = vec![Value::Float(2.5), Value::Null, Value::Int(10), Value::Int(-3)];
+        values.sort_by(|a, b| a.compare(b));
+        assert_eq!(values, vec![Value::Null, Value::Int(-3), Value::Float(2.5), Value::Int(10)]);
+        assert_eq!(Value::Text("b".into()).compare(&Value::Int(5)), Ordering::Greater);
+    }
+}
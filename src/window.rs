@@ -0,0 +1,165 @@
+use anyhow::Result;
+use regex::Regex;
+
+use crate::config::NumberLocale;
+use crate::database::quote_identifier;
+use crate::value::Value;
+
+/// A computed column worked out over the rows in the order shown, such as `cumsum(Amount)`
+/// or `rolling_mean(Value, 7)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSpec {
+    pub func: WindowFunc,
+    pub column: String,
+    pub size: usize, // rows looked back or ahead by lag/lead, or averaged by rolling_mean
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowFunc {
+    CumSum,      // total of the numbers up to the row
+    Rank,        // 1 for the largest number of the column, ties sharing a rank
+    Lag,         // the value `size` rows before
+    Lead,        // the value `size` rows after
+    RollingMean, // mean of the numbers in the row and the `size - 1` before it
+}
+
+impl WindowFunc {
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowFunc::CumSum => "cumsum",
+            WindowFunc::Rank => "rank",
+            WindowFunc::Lag => "lag",
+            WindowFunc::Lead => "lead",
+            WindowFunc::RollingMean => "rolling_mean",
+        }
+    }
+}
+
+impl WindowSpec {
+    /// Parse `cumsum(col)`, `rank(col)`, `lag(col[, n])`, `lead(col[, n])` or
+    /// `rolling_mean(col, n)`; None for anything else
+    pub fn parse(expression: &str) -> Option<Result<Self>> {
+        let regex = Regex::new(r"^(cumsum|rank|lag|lead|rolling_mean)\(\s*([^,()]+?)\s*(?:,\s*([^,()]*?)\s*)?\)$").unwrap();
+        let captures = regex.captures(expression.trim())?;
+        let func = match &captures[1] {
+            "cumsum" => WindowFunc::CumSum,
+            "rank" => WindowFunc::Rank,
+            "lag" => WindowFunc::Lag,
+            "lead" => WindowFunc::Lead,
+            _ => WindowFunc::RollingMean,
+        };
+        let size = match (captures.get(3), func) {
+            (Some(size), WindowFunc::Lag | WindowFunc::Lead | WindowFunc::RollingMean) => match size.as_str().parse::<usize>() {
+                Ok(size) if size > 0 => size,
+                _ => return Some(Err(anyhow::anyhow!("The window size of {} must be a whole number above 0", func.name()))),
+            },
+            (Some(_), _) => return Some(Err(anyhow::anyhow!("{} takes only a column", func.name()))),
+            (None, WindowFunc::RollingMean) => {
+                return Some(Err(anyhow::anyhow!("rolling_mean needs a window size, e.g. rolling_mean(Value, 7)")))
+            }
+            (None, _) => 1,
+        };
+        Some(Ok(WindowSpec { func, column: captures[2].to_string(), size }))
+    }
+
+    /// The value of every row, in order
+    pub fn compute<'a>(&self, cells: impl Iterator<Item = &'a Value>, locale: NumberLocale) -> Vec<Value> {
+        let cells: Vec<&Value> = cells.collect();
+        let numbers: Vec<Option<f64>> = cells.iter().map(|cell| cell.as_number(locale)).collect();
+        match self.func {
+            WindowFunc::CumSum => {
+                let mut total = 0.0;
+                numbers
+                    .iter()
+                    .map(|n| {
+                        total += n.unwrap_or(0.0);
+                        Value::Float(total)
+                    })
+                    .collect()
+            }
+            WindowFunc::Rank => {
+                let mut sorted: Vec<f64> = numbers.iter().flatten().copied().collect();
+                sorted.sort_by(|a, b| b.total_cmp(a));
+                numbers
+                    .iter()
+                    .map(|n| match n {
+                        Some(n) => Value::Int(sorted.partition_point(|x| x > n) as i64 + 1),
+                        None => Value::Null,
+                    })
+                    .collect()
+            }
+            WindowFunc::Lag => (0..cells.len())
+                .map(|idx| idx.checked_sub(self.size).map_or(Value::Null, |from| cells[from].clone()))
+                .collect(),
+            WindowFunc::Lead => (0..cells.len())
+                .map(|idx| cells.get(idx + self.size).map_or(Value::Null, |cell| (*cell).clone()))
+                .collect(),
+            WindowFunc::RollingMean => (0..numbers.len())
+                .map(|idx| {
+                    let window: Vec<f64> = numbers[(idx + 1).saturating_sub(self.size)..=idx].iter().flatten().copied().collect();
+                    match window.len() {
+                        0 => Value::Null,
+                        n => Value::Float(window.iter().sum::<f64>() / n as f64),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The SQLite window expression giving the value of each row, the rows in `order` (an
+    /// ORDER BY list)
+    pub fn sql(&self, order: &str) -> String {
+        let col = quote_identifier(&self.column);
+        let number = format!("CASE WHEN typeof({0}) IN ('integer', 'real') THEN {0} END", col);
+        let order = format!("ORDER BY {}", order);
+        match self.func {
+            WindowFunc::CumSum => format!("TOTAL({}) OVER ({} ROWS UNBOUNDED PRECEDING)", number, order),
+            WindowFunc::Rank => format!(
+                "CASE WHEN typeof({0}) IN ('integer', 'real') THEN \
+                 RANK() OVER (PARTITION BY typeof({0}) IN ('integer', 'real') ORDER BY {0} DESC) END",
+                col
+            ),
+            WindowFunc::Lag => format!("LAG({}, {}) OVER ({})", col, self.size, order),
+            WindowFunc::Lead => format!("LEAD({}, {}) OVER ({})", col, self.size, order),
+            WindowFunc::RollingMean => format!(
+                "AVG({}) OVER ({} ROWS BETWEEN {} PRECEDING AND CURRENT ROW)",
+                number,
+                order,
+                self.size - 1
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_window() {
+        let spec = WindowSpec::parse("rolling_mean( Value , 7)").unwrap().unwrap();
+        assert_eq!(spec, WindowSpec { func: WindowFunc::RollingMean, column: "Value".to_string(), size: 7 });
+        assert_eq!(WindowSpec::parse("lag(x)").unwrap().unwrap().size, 1);
+        assert_eq!(WindowSpec::parse("lead(x, 2)").unwrap().unwrap().size, 2);
+        assert!(WindowSpec::parse("rolling_mean(x)").unwrap().is_err());
+        assert!(WindowSpec::parse("lag(x, 0)").unwrap().is_err());
+        assert!(WindowSpec::parse("cumsum(x, 3)").unwrap().is_err());
+        assert!(WindowSpec::parse("sum(x)").is_none());
+        assert!(WindowSpec::parse("cumsum(x) + 1").is_none());
+    }
+
+    #[test]
+    fn test_compute_window() {
+        let cells = [Value::Int(3), Value::Null, Value::from("5"), Value::Int(3), Value::Float(1.0)];
+        let compute = |expression: &str| WindowSpec::parse(expression).unwrap().unwrap().compute(cells.iter(), NumberLocale::En);
+        assert_eq!(compute("cumsum(x)"), [3.0, 3.0, 8.0, 11.0, 12.0].map(Value::Float));
+        assert_eq!(compute("rank(x)"), vec![Value::Int(2), Value::Null, Value::Int(1), Value::Int(2), Value::Int(4)]);
+        assert_eq!(compute("lag(x)")[..2], [Value::Null, Value::Int(3)]);
+        assert_eq!(compute("lead(x, 2)")[3..], [Value::Null, Value::Null]);
+        assert_eq!(compute("lead(x, 2)")[0], Value::from("5"));
+        assert_eq!(
+            compute("rolling_mean(x, 2)"),
+            vec![Value::Float(3.0), Value::Float(3.0), Value::Float(5.0), Value::Float(4.0), Value::Float(2.0)]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use calamine::{open_workbook_auto, Reader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::OnceLock;
+
+use crate::config::XlsxSettings;
+use crate::database::QueryResult;
+use crate::file_reader::{detect_compression, read_sheet, read_xlsx_file};
+
+type LoadedSheet = (String, Result<QueryResult, String>);
+
+/// The sheets of a workbook. Only the sheet names are read on open; a sheet is read the
+/// first time it is shown, on a thread of its own so the screen stays responsive while a
+/// large sheet loads. Compressed workbooks are read whole, as they can't seek.
+pub struct Workbook {
+    path: PathBuf,
+    settings: XlsxSettings,
+    sheets: Vec<(String, OnceLock<QueryResult>)>,
+    loader: Option<SheetLoader>, // started on the first sheet read in the background
+    loading: Vec<String>,
+}
+
+/// A thread reading the sheets it is asked for, with the workbook open once for all of them
+struct SheetLoader {
+    requests: Sender<String>,
+    results: Receiver<LoadedSheet>,
+}
+
+impl SheetLoader {
+    /// The thread ends once the loader is dropped
+    fn spawn(path: PathBuf, settings: XlsxSettings) -> Self {
+        let (requests, pending) = channel::<String>();
+        let (sender, results) = channel();
+        std::thread::spawn(move || {
+            let mut workbook = open_workbook_auto(&path).map_err(|e| e.to_string());
+            for sheet in pending {
+                let data = match &mut workbook {
+                    Ok(workbook) => read_sheet(workbook, &sheet, &settings).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.clone()),
+                };
+                if sender.send((sheet, data)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results }
+    }
+}
+
+impl Workbook {
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %path.display()), err)]
+    pub fn open(path: &Path, settings: &XlsxSettings) -> Result<Self> {
+        if detect_compression(path).is_some() {
+            return Ok(Self::with_sheets(path, settings, read_xlsx_file(path, settings)?));
+        }
+        let names = open_workbook_auto(path)?.sheet_names();
+        Ok(Self {
+            path: path.to_path_buf(),
+            settings: settings.clone(),
+            sheets: names.into_iter().map(|name| (name, OnceLock::new())).collect(),
+            loader: None,
+            loading: Vec::new(),
+        })
+    }
+
+    /// A workbook of sheets already read, such as the CSV an edited workbook was saved to
+    pub fn with_sheets(path: &Path, settings: &XlsxSettings, sheets: Vec<(String, QueryResult)>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            settings: settings.clone(),
+            sheets: sheets.into_iter().map(|(name, data)| (name, OnceLock::from(data))).collect(),
+            loader: None,
+            loading: Vec::new(),
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.sheets.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn find(&self, name: &str) -> Result<&OnceLock<QueryResult>> {
+        self.sheets
+            .iter()
+            .find(|(sheet, _)| sheet == name)
+            .map(|(_, data)| data)
+            .ok_or_else(|| anyhow::anyhow!("Sheet '{}' not found", name))
+    }
+
+    /// The rows of a sheet, read from the file the first time they are needed
+    pub fn sheet(&self, name: &str) -> Result<&QueryResult> {
+        let loaded = self.find(name)?;
+        if let Some(data) = loaded.get() {
+            return Ok(data);
+        }
+        let mut workbook = open_workbook_auto(&self.path)?;
+        let data = read_sheet(&mut workbook, name, &self.settings)?;
+        Ok(loaded.get_or_init(|| data))
+    }
+
+    pub fn sheet_mut(&mut self, name: &str) -> Result<&mut QueryResult> {
+        self.sheet(name)?;
+        let (_, loaded) = self.sheets.iter_mut().find(|(sheet, _)| sheet == name).expect("sheet was just read");
+        Ok(loaded.get_mut().expect("sheet was just read"))
+    }
+
+    /// Every sheet, reading those not read yet
+    pub fn sheets(&self) -> Result<Vec<(String, &QueryResult)>> {
+        self.sheets.iter().map(|(name, _)| Ok((name.clone(), self.sheet(name)?))).collect()
+    }
+
+    /// Start reading a sheet in the background; false when it has already been read
+    pub fn load_in_background(&mut self, name: &str) -> bool {
+        if self.find(name).map_or(true, |loaded| loaded.get().is_some()) {
+            return false;
+        }
+        if !self.loading.iter().any(|sheet| sheet == name) {
+            let loader = self.loader.get_or_insert_with(|| SheetLoader::spawn(self.path.clone(), self.settings.clone()));
+            // A loader whose thread has died just never answers
+            let _ = loader.requests.send(name.to_string());
+            self.loading.push(name.to_string());
+        }
+        true
+    }
+
+    /// The sheets read in the background since the last call, each with the error it failed
+    /// with, if any
+    pub fn finished(&mut self) -> Vec<(String, Result<(), String>)> {
+        let Some(loader) = &self.loader else {
+            return Vec::new();
+        };
+        let mut finished = Vec::new();
+        for (name, data) in loader.results.try_iter().collect::<Vec<_>>() {
+            self.loading.retain(|sheet| *sheet != name);
+            let result = data.map(|data| {
+                if let Some((_, loaded)) = self.sheets.iter().find(|(sheet, _)| *sheet == name) {
+                    // A sheet read in the meantime, and maybe edited since, is kept
+                    let _ = loaded.set(data);
+                }
+            });
+            finished.push((name, result));
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Value;
+
+    #[test]
+    fn test_sheets_load_on_demand() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("book.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.sheet_by_name_mut("Sheet1").unwrap();
+        sheet.cell_mut("A1").set_value_string("name");
+        sheet.cell_mut("A2").set_value_string("Ann");
+        let other = book.new_sheet("Other").unwrap();
+        other.cell_mut("A1").set_value_string("n");
+        other.cell_mut("A2").set_value_number(7);
+        umya_spreadsheet::writer::xlsx::write(&book, &path).unwrap();
+
+        let mut workbook = Workbook::open(&path, &XlsxSettings::default()).unwrap();
+        assert_eq!(workbook.names(), vec!["Sheet1", "Other"]);
+        assert!(workbook.sheets.iter().all(|(_, loaded)| loaded.get().is_none()));
+
+        assert!(workbook.load_in_background("Other"));
+        assert!(workbook.load_in_background("Other"));
+        assert!(!workbook.load_in_background("missing"));
+        let mut finished = Vec::new();
+        for _ in 0..100 {
+            finished.extend(workbook.finished());
+            if !finished.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(finished, vec![("Other".to_string(), Ok(()))]);
+        assert!(!workbook.load_in_background("Other"));
+        assert_eq!(workbook.sheet("Other").unwrap().rows, vec![vec![Value::Int(7)]]);
+        assert!(workbook.sheets[0].1.get().is_none());
+
+        // Read on the spot when needed at once
+        assert_eq!(workbook.sheet_mut("Sheet1").unwrap().rows, vec![vec![Value::from("Ann")]]);
+        assert!(workbook.sheet("missing").is_err());
+    }
+}